@@ -0,0 +1,80 @@
+// Runs a rom without a window for 600 frames, then writes a screenshot and a save state.
+// A template for embedding the core: build a `Bus` with the sinks you need, hand it to
+// a `Cpu` and call `step_frame`.
+//
+//     cargo run --example headless_runner -- game.gb [out dir]
+//
+// Without a rom it runs a few instructions which turn on the lcd and loop.
+
+use std::{
+    env,
+    error::Error,
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use gba::{
+    cartridge::ROM_BANK_SIZE, cgb, state, vram::LCDC, Bus, Cartridge, Cpu, FrameCallback, Screen,
+};
+
+const FRAMES: usize = 600;
+const WIDTH: usize = 160;
+const HEIGHT: usize = 144;
+/// The shades of the DMG screen, lightest first
+const GRAYS: [[u8; 3]; 4] = [[0xFF; 3], [0xAA; 3], [0x55; 3], [0x00; 3]];
+/// Where execution starts after the boot rom
+const ENTRY: usize = 0x0100;
+
+/// ld a, $91; ldh (LCDC), a; jr -2
+fn builtin_rom() -> Vec<u8> {
+    let program = [0x3E, 0x91, 0xE0, LCDC as u8, 0x18, 0xFE];
+    let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+    rom[ENTRY..ENTRY + program.len()].copy_from_slice(&program);
+    rom
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let cartridge = match args.next() {
+        Some(path) => Cartridge::load(path.as_ref())?,
+        None => Cartridge::from_bytes(builtin_rom())?,
+    };
+    let out = PathBuf::from(args.next().unwrap_or_else(|| ".".to_string()));
+    let crc = cartridge.crc();
+
+    // keeps the last frame as rgb
+    let frame = Arc::new(Mutex::new(vec![[0u8; 3]; WIDTH * HEIGHT]));
+    let last = frame.clone();
+    let video = FrameCallback(move |screen: Screen| {
+        let rgb: Vec<[u8; 3]> = match screen {
+            Screen::Shades(shades) => shades.iter().map(|s| GRAYS[*s as usize & 3]).collect(),
+            Screen::Colors(colors) => colors.iter().map(|c| cgb::rgb(*c)).collect(),
+        };
+        *last.lock().unwrap() = rgb;
+    });
+    let bus = Bus::default().with_cartridge(cartridge).with_video(video);
+    let mut cpu = Cpu::new(bus);
+    cpu.skip_boot_rom();
+    for _ in 0..FRAMES {
+        cpu.step_frame();
+    }
+    if let Some(error) = cpu.fault() {
+        return Err(format!("the cpu stopped: {error}").into());
+    }
+
+    let screenshot = out.join("headless_runner.png");
+    let file = BufWriter::new(File::create(&screenshot)?);
+    let mut encoder = png::Encoder::new(file, WIDTH as u32, HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let rgb: Vec<u8> = frame.lock().unwrap().concat();
+    encoder.write_header()?.write_image_data(&rgb)?;
+    println!("wrote {}", screenshot.display());
+
+    let state = out.join("headless_runner.state");
+    state::write_file(&state, crc, &cpu.snapshot())?;
+    println!("wrote {}", state.display());
+    Ok(())
+}
@@ -0,0 +1,401 @@
+language-name = Deutsch
+
+app-main-heading = Das ist das Hauptfenster
+window-emulator = Emulator
+window-second-game = Weiteres Spiel
+window-colors = Farben
+window-capture = Aufnahme
+window-accessibility = Barrierefreiheit
+window-shortcuts = Tastenkürzel
+window-macros = Makros
+window-gamepads = Gamepads
+window-settings = Einstellungen
+window-speed = Geschwindigkeit
+window-speedrun = Speedrun
+window-crashed = Emulation abgestürzt
+window-memory = Speicher
+window-io = IO-Register
+window-vram = VRAM-Kacheln
+window-oam = Sprites
+window-memory-view = Speicheransicht
+window-memory-diff = Speicher-Diff
+window-logging = Protokoll
+window-updates = Aktualisierungen
+window-update = Aktualisierung verfügbar
+window-hud = HUD
+window-camera = Game Boy Camera
+window-rtc = Modul-Uhr
+window-link-port = Link-Anschluss
+window-serial = Serielle Ausgabe
+window-playtime = Spielzeit
+window-practice = Training
+window-save-states = Spielstände
+window-timeline = Zeitleiste
+window-resume = Fortsetzen
+window-movie = Film
+window-cheats = Cheats
+window-mixer = Mischpult
+window-console = Konsole
+window-debugger = Debugger
+
+speed-fps = { $fps } Bilder pro Sekunde
+speed-hotkeys = { $faster }: schneller, { $slower }: langsamer
+speed-slow-motion = Zeitlupe
+turbo = Vorspulen
+turbo-x2 = 2×
+turbo-x4 = 4×
+turbo-x8 = 8×
+turbo-uncapped = Unbegrenzt
+turbo-hold = { $key } halten zum Vorspulen
+rewind = Zurückspulen
+rewind-history = { $seconds } s zurück, { $kib } KiB
+rewind-hold = { $key } halten zum Zurückspulen
+focus-loss = Wenn das Fenster den Fokus verliert
+focus-keep-running = Weiterlaufen
+focus-pause = Pausieren
+focus-throttle = Verlangsamen
+desync = Wenn die Emulation zurückfällt
+desync-resync = Von dort weitermachen
+desync-drop-frames = Bilder überspringen, um aufzuholen
+desync-stretch-audio = Ton langsamer abspielen
+refresh = Bildwiederholrate
+refresh-native = 59,73 Hz wie die Hardware
+refresh-host-60 = 60 Hz passend zum Bildschirm, Ton etwas höher
+refresh-audio = Der Tonausgabe folgen, kein Knacksen
+run-ahead = Vorausberechnete Frames
+run-ahead-hint = Zeigt vorausberechnete und zurückgenommene Frames, das Spiel reagiert schneller auf die Tasten, braucht aber mehr Rechenzeit
+power-low-power = Energiesparmodus (max. 60 fps)
+power-audio-only = Im Hintergrund nur Ton
+
+speedrun-show = Timer anzeigen
+speedrun-auto-start = Bei der ersten Eingabe starten
+speedrun-hotkeys = { $split }: Start/Split, { $reset }: Zurücksetzen
+speedrun-finish = Beenden
+speedrun-export = LiveSplit exportieren
+
+status-running = Läuft
+status-paused = Pausiert
+status-fast-forward = Schneller Vorlauf { $percent }%
+status-slow-motion = Zeitlupe { $percent }%
+status-no-title = Kein Modul
+status-playtime = Gespielt: { $time }
+
+cpu-halted = CPU angehalten bei { $pc }
+cpu-stopped = CPU gestoppt bei { $pc }
+cpu-locked = CPU blockiert durch ungültigen Opcode { $op } bei { $pc }
+
+memory-unavailable = Speicher ist nicht verfügbar
+memory-start = Erste Adresse
+memory-end = Letzte Adresse
+memory-file = Datei
+memory-dump = Speichern
+memory-load = Laden
+memory-pause-first = Pausiere die Emulation, um in den Speicher zu laden
+memory-invalid-range = Adressen hexadezimal eingeben, die erste nicht nach der letzten
+memory-dumped = { $bytes } Bytes nach { $file } geschrieben
+memory-loaded = { $bytes } Bytes aus { $file } geladen
+memory-export-bg-maps = Auch die Hintergrundkarten exportieren
+memory-export-tiles = VRAM-Kacheln als PNG exportieren
+memory-exported = { $file } geschrieben
+memory-error = { $error }
+memory-view-go = Los
+memory-view-address = Adresse
+memory-view-value = Wert
+memory-view-write = Schreiben
+memory-view-pause-first = Pausiere die Emulation, um den Speicher zu ändern
+
+diff-frame = Bild { $frame }
+diff-snapshot = WRAM-Schnappschuss
+diff-run = Bilder ausführen
+diff-compare = Jetzt vergleichen
+diff-keep-changed = Nur hier weitersuchen
+diff-reset = Suche zurücksetzen
+diff-candidates = { $count } Adressen übrig
+diff-changed = { $count } Bytes geändert
+
+bookmarks = Lesezeichen
+bookmarks-inside = Erste Adresse liegt in { $name }
+bookmarks-name = Name
+bookmarks-comment = Kommentar
+bookmarks-add = Adressbereich merken
+bookmarks-other-bank = Das Lesezeichen liegt in ROM-Bank { $bank }, die gerade nicht eingeblendet ist
+
+hud-show = Über dem Spielbild anzeigen
+hud-hotkey = { $key }: ein-/ausblenden
+hud-label = Beschriftung
+hud-address = Adresse
+hud-format = Format
+hud-add = Wert hinzufügen
+
+camera-save-file = Speicherdatei
+camera-png-file = PNG zum Importieren
+camera-slot = Foto
+camera-export = Alle Fotos exportieren
+camera-import = In Foto importieren
+camera-exported = { $count } Fotos nach { $dir } geschrieben
+camera-imported = Foto { $slot } ersetzt, starte das Spiel neu, um es zu sehen
+camera-webcam-unsupported = Eine Live-Webcam für den Sensor wird noch nicht unterstützt
+
+rtc-day = Tag
+rtc-time = Uhrzeit
+rtc-halted = Angehalten
+rtc-carry = Tageszähler übergelaufen
+rtc-clear-carry = Zurücksetzen
+rtc-advance = +{ $days } Tag(e)
+
+playtime-export = Als CSV exportieren
+
+practice-enabled = Trainingsmodus
+practice-interval = Snapshot alle (0: nur bei Überwachung)
+practice-watches = Snapshot bei Speicheränderung, eine Adresse pro Zeile (C0A4 oder C0A4=05):
+practice-invalid-watch = Keine gültige Überwachung: { $watch }
+practice-taken = { $count } Snapshots erstellt
+practice-retry = Nochmal ({ $key })
+
+slots-slot = Platz { $number }
+slots-empty = Leer
+slots-saved-ago = Vor { $time } gespeichert
+slots-save = Speichern
+slots-load = Laden
+slots-failed = Fehlgeschlagen: { $error }
+slots-older-than-save = Der Stand ist älter als der Batteriespeicher, der Speicher wurde vor dem Laden gesichert
+slots-backups = Sicherungen
+slots-backups-of = Frühere Stände von Platz { $number }
+slots-no-backups = Beim Überschreiben des Platzes wird sein Stand hier aufbewahrt
+slots-restore = Wiederherstellen
+slots-suspend-on-exit = Beim Beenden einen Stand zum Weiterspielen speichern
+resume-question = Dort weiterspielen, wo das Spiel beendet wurde?
+resume-continue = Fortsetzen
+resume-start-over = Neu beginnen
+
+timeline-empty = Rückspul-Schnappschüsse und Spielstände erscheinen hier
+timeline-ago = vor { $time }
+
+movie-off = Keine Aufnahme
+movie-recording = Aufnahme, { $frames } Frames
+movie-playing = Spiele Frame { $frame } von { $frames }
+movie-record = Aufnehmen
+movie-stop = Stopp
+movie-play = Abspielen
+movie-none = Noch keine Filme für dieses Spiel
+movie-failed = Fehlgeschlagen: { $error }
+
+console-hint = Befehl, z. B. b $0150 oder help
+
+debugger-running = Läuft
+debugger-paused-at = Angehalten bei { $pc }
+debugger-continue = Fortsetzen
+debugger-pause = Anhalten
+debugger-step = Schritt
+debugger-step-frame = Bild weiter
+debugger-step-out = Rücksprung
+debugger-step-line = Zeile weiter
+debugger-step-dot = Punkt weiter
+debugger-beam = Zeile { $ly }, Punkt { $dot }
+debugger-run-to = Laufen bis
+debugger-breakpoints = Haltepunkte
+debugger-no-breakpoints = Keine Haltepunkte
+debugger-add = Hinzufügen
+debugger-trace = Jeden Befehl protokollieren
+debugger-trace-file = Schreibt die Register vor jedem Befehl in { $path }, im Format von Gameboy Doctor
+debugger-layers = Ebenen
+layer-background = Hintergrund
+layer-window = Fenster
+layer-sprites = Sprites
+layer-tint = Einfärben
+debugger-watchpoints = Watchpoints, pausieren beim Lesen (r) oder Schreiben (w)
+debugger-no-watchpoints = Keine Watchpoints
+debugger-call-stack = Aufrufstapel
+debugger-not-in-call = In keinem Aufruf
+debugger-call-frame = { $target } { $kind } aus { $site }, kehrt zurück nach { $return }
+debugger-disassembly = Disassemblierung, ein Klick auf eine Zeile setzt einen Haltepunkt
+registers-heading = Register
+registers-flags = Flags
+registers-ime = Interrupts aktiv (IME)
+registers-ppu = PPU: { $mode }, LY { $ly }
+
+logging-file-hint = Meldungen werden in gba.log im Protokollordner geschrieben
+
+crash-report-written = Ein Bericht wurde nach { $path } geschrieben
+crash-report-failed = Der Absturzbericht konnte nicht geschrieben werden
+crash-failing-op = Opcode { $op } bei { $pc }: { $text }
+crash-save-dump = Zustand speichern
+crash-dump-saved = Der Zustand wurde nach { $path } gespeichert
+crash-dump-failed = Der Zustand konnte nicht gespeichert werden: { $error }
+
+action-speedrun-split = Speedrun Start/Split
+action-speedrun-reset = Speedrun zurücksetzen
+action-speed-up = Schneller
+action-speed-down = Langsamer
+action-copy-screen = Bild kopieren
+action-copy-registers = Register kopieren
+action-toggle-hud = HUD ein-/ausblenden
+action-perf-overlay = Leistung ein-/ausblenden
+action-input-display = Gedrückte Tasten ein-/ausblenden
+action-mute-square-1 = Rechteck 1 stummschalten
+action-mute-square-2 = Rechteck 2 stummschalten
+action-mute-wave = Wave stummschalten
+action-mute-noise = Rauschen stummschalten
+action-solo-square-1 = Rechteck 1 solo
+action-solo-square-2 = Rechteck 2 solo
+action-solo-wave = Wave solo
+action-solo-noise = Rauschen solo
+action-practice-retry = Training: nochmal
+action-save-state = Spielstand speichern
+action-load-state = Spielstand laden
+action-previous-slot = Vorheriger Speicherplatz
+action-next-slot = Nächster Speicherplatz
+action-fast-forward = Vorspulen (halten)
+action-rewind = Zurückspulen (halten)
+action-fullscreen = Vollbild
+action-play-mode = Spielmodus
+play-mode-leave = { $key } zeigt die Fenster wieder
+sgb-border = SGB-Rahmen
+integer-scale = Ganzzahlig skalieren
+action-screenshot = Bildschirmfoto
+action-record = Aufnahme starten/stoppen
+action-pause = Pause/Fortsetzen
+action-reset = Zurücksetzen
+scaling-nearest = Nächster Nachbar
+scaling-bilinear = Bilinear
+scaling-sharp-bilinear = Scharf bilinear
+shortcuts-press-key = Taste drücken...
+shortcuts-conflict = Konflikt
+shortcuts-reset = Standard wiederherstellen
+macro-record = Aufnehmen
+macro-stop = Stopp
+macro-frames = { $count } Bilder
+macro-loop = Wiederholen
+macro-playing = läuft
+gamepad-none = Kein Gamepad verbunden
+gamepad-needs-feature = Gamepads brauchen das Feature gamepad
+gamepad-any = Jedes Gamepad
+gamepad-play-with = Spielen mit
+gamepad-missing = { $name } ist nicht verbunden, es spielt die Tastatur
+button-right = Rechts
+button-left = Links
+button-up = Hoch
+button-down = Runter
+button-a = A
+button-b = B
+button-select = Select
+button-start = Start
+settings-volume = Lautstärke
+settings-scale = Skalierung beim nächsten Start
+settings-file = Gespeichert in { $path }
+
+accessibility-ui-scale = UI-Skalierung
+accessibility-high-contrast = Kontrastreiches Design
+accessibility-game-palette = Spielpalette
+accessibility-language = Sprache
+palette-grey = Grau
+palette-blue-yellow = Blau/Gelb
+palette-high-contrast = Hoher Kontrast
+palette-dmg-green = DMG-Grün
+palette-pocket = Pocket-Grau
+palette-light = Light
+palette-delete = Löschen
+palette-save = Palette speichern
+palette-per-game = Änderungen werden für { $title } gespeichert
+update-current = Dies ist Version { $version }
+update-enabled = Einmal am Tag nach neuen Versionen suchen
+update-privacy = Fragt GitHub nur nach der neuesten Version, nichts über dich oder deine Spiele wird gesendet
+update-check-now = Jetzt suchen
+update-up-to-date = Dies ist die neueste Version
+update-failed = Suche nach Aktualisierungen fehlgeschlagen: { $error }
+update-all-releases = Alle Versionen
+update-available = Version { $version } ist verfügbar
+update-download = Herunterladen
+link-port-device = Angeschlossen
+link-port-disconnected = Nichts
+link-port-loopback = Rückkopplungskabel
+link-port-printer = Game Boy Printer
+link-port-trade = Tauschpartner
+link-port-script = Skriptpartner
+link-port-printer-hint = Gedruckte Seiten werden in { $dir } gespeichert
+link-port-script-hint = Datei mit den Antwortbytes
+link-port-plug = Anschließen
+link-port-network = Andere Instanz über das Netzwerk
+link-port-host = Bereitstellen
+link-port-join = Verbinden
+link-port-join-hint = Adresse, z.B. 192.168.0.2:5738
+link-port-bad-port = { $port } ist kein Port
+link-status-waiting = Warte auf die Gegenseite an Port { $port }
+link-status-connecting = Verbinde mit { $addr }
+link-status-connected = Verbunden mit { $addr }
+link-status-failed = Die Verbindung ist fehlgeschlagen: { $error }
+link-status-closed = Die Gegenseite hat die Verbindung beendet
+link-status-local = Mit dem zweiten Spiel verbunden
+
+serial-enabled = Ausgaben des Spiels zeigen
+serial-clear = Leeren
+serial-empty = Noch nichts ausgegeben
+
+second-game-controls = Mit der Tastatur spielen
+
+perf-fps = { $host } fps gezeichnet, { $emulated } fps emuliert
+perf-frame-time = Pro Frame { $emulating } ms emuliert, { $sleeping } ms geschlafen
+perf-audio = Audiopuffer { $percent } %
+
+input-display-normal = Normale Geschwindigkeit
+input-display-turbo = Vorspulen { $rate }
+
+vram-all = Alle Kacheln
+vram-8000 = Nummeriert ab $8000
+vram-8800 = Nummeriert ab $8800
+vram-map = Hintergrundkarte { $addr }
+vram-tile-tooltip = Kachel { $number } bei { $addr }
+vram-map-tooltip = { $x }, { $y } bei { $entry }: Kachel { $number } bei { $addr }
+
+oam-on-line = { $count } Sprites in Zeile { $line }
+oam-index = #
+oam-y = Y
+oam-x = X
+oam-tile = Kachel
+oam-flags = Flags
+oam-behind-background = Hinter dem Hintergrund
+oam-flip-x = Horizontal gespiegelt
+oam-flip-y = Vertikal gespiegelt
+oam-palette = Palette OBP{ $obp }
+oam-cgb = CGB: Bank { $bank }, Palette { $palette }
+oam-legend-on-line = In der Zeile
+oam-legend-dropped = In der Zeile, aber über der Grenze von 10
+
+io-joypad = Joypad
+io-serial = Seriell
+io-timer = Timer
+io-interrupts = Interrupts
+io-sound = Ton
+io-lcd = LCD
+io-cgb = Game Boy Color
+io-system = System
+capture-screenshot = Bildschirmfoto
+capture-record = Aufnehmen
+capture-stop = Stopp
+capture-recording = Nimmt auf, noch { $seconds } s
+capture-written = Gespeichert in { $path }
+capture-failed = Aufnahme konnte nicht gespeichert werden: { $error }
+toolbar-pause = Pause
+toolbar-resume = Fortsetzen
+toolbar-reset = Zurücksetzen
+toolbar-power-off = Ausschalten
+toolbar-power-on = Einschalten
+menu-file = Datei
+menu-recent = Zuletzt geladen
+menu-recent-none = Noch kein ROM geladen
+menu-recent-clear = Liste leeren
+menu-folder = ROMs im letzten Ordner
+menu-folder-empty = Keine ROMs im Ordner
+menu-new-window = Neben dem Spiel öffnen
+menu-drop-hint = Eine .gb- oder .gbc-Datei auf das Fenster ziehen, um sie zu laden
+cheats-description = Beschreibung
+cheats-code = Code
+cheats-code-hint = GameShark (01FF16D0) oder Game Genie (00A-17B-C49), mehrere mit + verbunden
+cheats-add = Hinzufügen
+cheats-delete = Löschen
+mixer-square-1 = Rechteck 1
+mixer-square-2 = Rechteck 2
+mixer-wave = Welle
+mixer-noise = Rauschen
+mixer-mute = Stumm
+mixer-solo = Solo
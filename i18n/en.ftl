@@ -0,0 +1,403 @@
+# English catalog, every other catalog falls back to it.
+# Copy this file to <config dir>/i18n/<language code>.ftl to add a translation.
+language-name = English
+
+app-main-heading = This is the main window
+window-emulator = Emulator
+window-second-game = Another game
+window-colors = Colors
+window-capture = Capture
+window-accessibility = Accessibility
+window-shortcuts = Shortcuts
+window-macros = Macros
+window-gamepads = Gamepads
+window-settings = Settings
+window-speed = Speed
+window-speedrun = Speedrun
+window-crashed = Emulation crashed
+window-memory = Memory
+window-memory-diff = Memory diff
+window-memory-view = Memory viewer
+window-io = IO registers
+window-vram = VRAM tiles
+window-oam = Sprites
+window-logging = Logging
+window-updates = Updates
+window-update = Update available
+window-hud = HUD
+window-camera = Game Boy Camera
+window-rtc = Cartridge clock
+window-link-port = Link port
+window-serial = Serial output
+window-playtime = Playtime
+window-practice = Practice
+window-save-states = Save states
+window-timeline = Timeline
+window-resume = Continue
+window-movie = Movie
+window-cheats = Cheats
+window-mixer = Mixer
+window-console = Console
+window-debugger = Debugger
+
+speed-fps = { $fps } frames per second
+speed-hotkeys = { $faster }: faster, { $slower }: slower
+speed-slow-motion = Slow motion
+turbo = Fast-forward
+turbo-x2 = 2×
+turbo-x4 = 4×
+turbo-x8 = 8×
+turbo-uncapped = Uncapped
+turbo-hold = Hold { $key } to fast-forward
+rewind = Rewind
+rewind-history = { $seconds } s to go back, { $kib } KiB
+rewind-hold = Hold { $key } to rewind
+focus-loss = When the window loses focus
+focus-keep-running = Keep running
+focus-pause = Pause
+focus-throttle = Slow down
+desync = When the emulation falls behind
+desync-resync = Continue from there
+desync-drop-frames = Skip frames to catch up
+desync-stretch-audio = Play the audio slower
+refresh = Refresh rate
+refresh-native = 59.73 Hz like the hardware
+refresh-host-60 = 60 Hz to match the display, slightly higher audio
+refresh-audio = Follow the audio output, no crackle
+run-ahead = Run-ahead frames
+run-ahead-hint = Shows frames emulated ahead and rolled back, the game reacts sooner to the buttons but takes more time to emulate
+power-low-power = Battery saver (limit to 60 fps)
+power-audio-only = Only audio in the background
+
+speedrun-show = Show timer
+speedrun-auto-start = Start on the first input
+speedrun-hotkeys = { $split }: start/split, { $reset }: reset
+speedrun-finish = Finish
+speedrun-export = Export LiveSplit
+
+status-running = Running
+status-paused = Paused
+status-fast-forward = Fast forward { $percent }%
+status-slow-motion = Slow motion { $percent }%
+status-no-title = No cartridge
+status-playtime = Played { $time }
+
+cpu-halted = CPU halted at { $pc }
+cpu-stopped = CPU stopped at { $pc }
+cpu-locked = CPU locked up on illegal opcode { $op } at { $pc }
+
+memory-unavailable = Memory is not available
+memory-start = First address
+memory-end = Last address
+memory-file = File
+memory-dump = Dump
+memory-load = Load
+memory-pause-first = Pause the emulation to load into memory
+memory-invalid-range = Enter the addresses in hex, the first one not after the last one
+memory-dumped = Wrote { $bytes } bytes to { $file }
+memory-loaded = Loaded { $bytes } bytes from { $file }
+memory-export-bg-maps = Also export the background maps
+memory-export-tiles = Export VRAM tiles as png
+memory-exported = Wrote { $file }
+memory-error = { $error }
+memory-view-go = Go
+memory-view-address = Address
+memory-view-value = Value
+memory-view-write = Write
+memory-view-pause-first = Pause the emulation to change memory
+
+diff-frame = Frame { $frame }
+diff-snapshot = Snapshot WRAM
+diff-run = Run frames
+diff-compare = Compare now
+diff-keep-changed = Search only in these
+diff-reset = Reset search
+diff-candidates = { $count } addresses left in the search
+diff-changed = { $count } bytes changed
+
+bookmarks = Bookmarks
+bookmarks-inside = First address is inside { $name }
+bookmarks-name = Name
+bookmarks-comment = Comment
+bookmarks-add = Bookmark the address range
+bookmarks-other-bank = The bookmark is in rom bank { $bank }, which is not mapped right now
+
+hud-show = Show over the game screen
+hud-hotkey = { $key }: show/hide
+hud-label = Label
+hud-address = Address
+hud-format = Format
+hud-add = Add value
+
+camera-save-file = Save file
+camera-png-file = PNG to import
+camera-slot = Photo
+camera-export = Export all photos
+camera-import = Import into photo
+camera-exported = Wrote { $count } photos to { $dir }
+camera-imported = Replaced photo { $slot }, restart the game to see it
+camera-webcam-unsupported = A live webcam feed for the sensor is not supported yet
+
+rtc-day = Day
+rtc-time = Time
+rtc-halted = Halted
+rtc-carry = Day counter overflowed
+rtc-clear-carry = Clear
+rtc-advance = +{ $days } day(s)
+
+playtime-export = Export as CSV
+
+practice-enabled = Practice mode
+practice-interval = Snapshot every (0: only on watches)
+practice-watches = Snapshot when memory changes, one address per line (C0A4 or C0A4=05):
+practice-invalid-watch = Not a watch: { $watch }
+practice-taken = { $count } snapshots taken
+practice-retry = Retry ({ $key })
+
+slots-slot = Slot { $number }
+slots-empty = Empty
+slots-saved-ago = Saved { $time } ago
+slots-save = Save
+slots-load = Load
+slots-failed = Failed: { $error }
+slots-older-than-save = The state is older than the battery save, the save was backed up before loading
+slots-backups = Backups
+slots-backups-of = Earlier states of slot { $number }
+slots-no-backups = Saving over the slot keeps its state here
+slots-restore = Restore
+slots-suspend-on-exit = Save a state on exit to continue from
+resume-question = Continue where you left off?
+resume-continue = Continue
+resume-start-over = Start over
+
+timeline-empty = Rewind snapshots and save states appear here
+timeline-ago = { $time } ago
+
+movie-off = Not recording
+movie-recording = Recording, { $frames } frames
+movie-playing = Playing frame { $frame } of { $frames }
+movie-record = Record
+movie-stop = Stop
+movie-play = Play
+movie-none = No movies recorded for this game
+movie-failed = Failed: { $error }
+
+console-hint = Command, e.g. b $0150 or help
+
+debugger-running = Running
+debugger-paused-at = Paused at { $pc }
+debugger-continue = Continue
+debugger-pause = Pause
+debugger-step = Step
+debugger-step-frame = Step frame
+debugger-step-out = Step out
+debugger-step-line = Step line
+debugger-step-dot = Step dot
+debugger-beam = Line { $ly }, dot { $dot }
+debugger-run-to = Run to
+debugger-breakpoints = Breakpoints
+debugger-no-breakpoints = No breakpoints
+debugger-add = Add
+debugger-trace = Trace every instruction
+debugger-trace-file = Writes the registers before each instruction into { $path }, in the format of Gameboy Doctor
+debugger-layers = Layers
+layer-background = Background
+layer-window = Window
+layer-sprites = Sprites
+layer-tint = Tint
+debugger-watchpoints = Watchpoints, pause on a read (r) or write (w)
+debugger-no-watchpoints = No watchpoints
+debugger-call-stack = Call stack
+debugger-not-in-call = Not in a call
+debugger-call-frame = { $target } { $kind } from { $site }, returns to { $return }
+debugger-disassembly = Disassembly, click a line for a breakpoint
+registers-heading = Registers
+registers-flags = Flags
+registers-ime = Interrupts enabled (IME)
+registers-ppu = PPU: { $mode }, LY { $ly }
+
+logging-file-hint = Messages are written to gba.log in the logs directory
+
+crash-report-written = A report was written to { $path }
+crash-report-failed = The crash report could not be written
+crash-failing-op = Opcode { $op } at { $pc }: { $text }
+crash-save-dump = Save state dump
+crash-dump-saved = The state was saved to { $path }
+crash-dump-failed = The state could not be saved: { $error }
+
+action-speedrun-split = Speedrun start/split
+action-speedrun-reset = Speedrun reset
+action-speed-up = Faster
+action-speed-down = Slower
+action-copy-screen = Copy screen
+action-copy-registers = Copy registers
+action-toggle-hud = Show/hide HUD
+action-perf-overlay = Show/hide performance
+action-input-display = Show/hide the held buttons
+action-mute-square-1 = Mute square 1
+action-mute-square-2 = Mute square 2
+action-mute-wave = Mute wave
+action-mute-noise = Mute noise
+action-solo-square-1 = Solo square 1
+action-solo-square-2 = Solo square 2
+action-solo-wave = Solo wave
+action-solo-noise = Solo noise
+action-practice-retry = Practice retry
+action-save-state = Save state
+action-load-state = Load state
+action-previous-slot = Previous state slot
+action-next-slot = Next state slot
+action-fast-forward = Fast-forward (hold)
+action-rewind = Rewind (hold)
+action-fullscreen = Fullscreen
+action-play-mode = Play mode
+play-mode-leave = { $key } shows the windows again
+sgb-border = SGB border
+integer-scale = Integer scale
+action-screenshot = Screenshot
+action-record = Start/stop recording
+action-pause = Pause/resume
+action-reset = Reset
+scaling-nearest = Nearest
+scaling-bilinear = Bilinear
+scaling-sharp-bilinear = Sharp bilinear
+shortcuts-press-key = press a key...
+shortcuts-conflict = conflict
+shortcuts-reset = Reset to defaults
+macro-record = Record
+macro-stop = Stop
+macro-frames = { $count } frames
+macro-loop = Loop
+macro-playing = playing
+gamepad-none = No gamepad connected
+gamepad-needs-feature = Gamepads need the gamepad feature
+gamepad-any = Any gamepad
+gamepad-play-with = Play with
+gamepad-missing = { $name } is not connected, the keyboard plays
+button-right = Right
+button-left = Left
+button-up = Up
+button-down = Down
+button-a = A
+button-b = B
+button-select = Select
+button-start = Start
+settings-volume = Volume
+settings-scale = Scale on the next start
+settings-file = Saved to { $path }
+
+accessibility-ui-scale = UI scale
+accessibility-high-contrast = High contrast theme
+accessibility-game-palette = Game palette
+accessibility-language = Language
+palette-grey = Grey
+palette-blue-yellow = Blue/Yellow
+palette-high-contrast = High contrast
+palette-dmg-green = DMG green
+palette-pocket = Pocket grey
+palette-light = Light
+palette-delete = Delete
+palette-save = Save palette
+palette-per-game = Changes are remembered for { $title }
+update-current = This is version { $version }
+update-enabled = Check for new releases once a day
+update-privacy = Only asks GitHub for the latest release, nothing about you or your games is sent
+update-check-now = Check now
+update-up-to-date = This is the latest release
+update-failed = Could not check for updates: { $error }
+update-all-releases = All releases
+update-available = Version { $version } is available
+update-download = Download
+link-port-device = Plugged in
+link-port-disconnected = Nothing
+link-port-loopback = Loopback cable
+link-port-printer = Game Boy Printer
+link-port-trade = Trade partner
+link-port-script = Scripted partner
+link-port-printer-hint = Printed pages are saved in { $dir }
+link-port-script-hint = File with the response bytes
+link-port-plug = Plug in
+link-port-network = Another instance over the network
+link-port-host = Host
+link-port-join = Join
+link-port-join-hint = Address, e.g. 192.168.0.2:5738
+link-port-bad-port = { $port } is not a port
+link-status-waiting = Waiting for the other side on port { $port }
+link-status-connecting = Connecting to { $addr }
+link-status-connected = Connected to { $addr }
+link-status-failed = The connection failed: { $error }
+link-status-closed = The other side left
+link-status-local = Linked to the second game
+
+serial-enabled = Show what the game prints
+serial-clear = Clear
+serial-empty = Nothing printed yet
+
+second-game-controls = Play with the keyboard
+
+perf-fps = { $host } fps drawn, { $emulated } fps emulated
+perf-frame-time = Per frame { $emulating } ms emulating, { $sleeping } ms sleeping
+perf-audio = Audio buffer { $percent }%
+
+input-display-normal = Normal speed
+input-display-turbo = Fast-forward { $rate }
+
+vram-all = All tiles
+vram-8000 = Numbered from $8000
+vram-8800 = Numbered from $8800
+vram-map = Background map { $addr }
+vram-tile-tooltip = Tile { $number } at { $addr }
+vram-map-tooltip = { $x }, { $y } at { $entry }: tile { $number } at { $addr }
+
+oam-on-line = { $count } sprites on line { $line }
+oam-index = #
+oam-y = Y
+oam-x = X
+oam-tile = Tile
+oam-flags = Flags
+oam-behind-background = Behind the background
+oam-flip-x = Flipped horizontally
+oam-flip-y = Flipped vertically
+oam-palette = Palette OBP{ $obp }
+oam-cgb = CGB: bank { $bank }, palette { $palette }
+oam-legend-on-line = On the line
+oam-legend-dropped = On the line, but over the limit of 10
+
+io-joypad = Joypad
+io-serial = Serial
+io-timer = Timer
+io-interrupts = Interrupts
+io-sound = Sound
+io-lcd = LCD
+io-cgb = Game Boy Color
+io-system = System
+capture-screenshot = Screenshot
+capture-record = Record
+capture-stop = Stop
+capture-recording = Recording, { $seconds } s left
+capture-written = Written to { $path }
+capture-failed = Could not write the capture: { $error }
+toolbar-pause = Pause
+toolbar-resume = Resume
+toolbar-reset = Reset
+toolbar-power-off = Power off
+toolbar-power-on = Power on
+menu-file = File
+menu-recent = Recent roms
+menu-recent-none = No rom loaded yet
+menu-recent-clear = Clear the list
+menu-folder = Roms in the last folder
+menu-folder-empty = No roms in the folder
+menu-new-window = Open next to the game
+menu-drop-hint = Drop a .gb or .gbc file onto the window to load it
+cheats-description = Description
+cheats-code = Code
+cheats-code-hint = GameShark (01FF16D0) or Game Genie (00A-17B-C49), several joined with +
+cheats-add = Add
+cheats-delete = Delete
+mixer-square-1 = Square 1
+mixer-square-2 = Square 2
+mixer-wave = Wave
+mixer-noise = Noise
+mixer-mute = Mute
+mixer-solo = Solo
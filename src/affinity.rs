@@ -0,0 +1,47 @@
+/// Scheduling of the emulation thread, to reduce frame pacing jitter on busy systems.
+/// Both need the `affinity` feature, the emulation runs normally if the os denies them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThreadTuning {
+    /// Index of the core to run on, pick a performance core on hybrid cpus
+    pub core: Option<usize>,
+    pub high_priority: bool,
+}
+impl ThreadTuning {
+    /// Applies the tuning to the calling thread
+    pub fn apply(self) {
+        if let Some(core) = self.core {
+            pin(core);
+        }
+        if self.high_priority {
+            raise_priority();
+        }
+    }
+}
+
+#[cfg(feature = "affinity")]
+fn pin(index: usize) {
+    let Some(core) = core_affinity::get_core_ids().and_then(|cores| cores.get(index).copied())
+    else {
+        log::warn!("core {index} does not exist, the emulation thread is not pinned");
+        return;
+    };
+    if !core_affinity::set_for_current(core) {
+        log::warn!("could not pin the emulation thread to core {index}");
+    }
+}
+#[cfg(feature = "affinity")]
+fn raise_priority() {
+    if let Err(e) =
+        thread_priority::set_current_thread_priority(thread_priority::ThreadPriority::Max)
+    {
+        log::warn!("could not raise the priority of the emulation thread: {e:?}");
+    }
+}
+#[cfg(not(feature = "affinity"))]
+fn pin(_index: usize) {
+    log::warn!("pinning the emulation thread needs the affinity feature");
+}
+#[cfg(not(feature = "affinity"))]
+fn raise_priority() {
+    log::warn!("raising the thread priority needs the affinity feature");
+}
@@ -0,0 +1,63 @@
+use std::{fs, io, ops::RangeInclusive, path::Path};
+
+use crate::banked::BankedAddr;
+
+/// A named address range with a free text comment, for reverse engineering
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    pub range: RangeInclusive<BankedAddr>,
+    pub name: String,
+    pub comment: String,
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Annotation>> {
+    Ok(parse(&fs::read_to_string(path)?))
+}
+pub fn save(path: &Path, annotations: &[Annotation]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, to_string(annotations))
+}
+/// Parses one annotation per line: `01:4000-01:40FF<tab>name<tab>comment`.
+/// Addresses without a bank are in bank 0, a single address can be written without the `-`,
+/// lines starting with `#` are skipped.
+pub fn parse(content: &str) -> Vec<Annotation> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let range = fields.next()?;
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            Some(Annotation {
+                range: BankedAddr::parse(start)?..=BankedAddr::parse(end)?,
+                name: fields.next()?.to_string(),
+                comment: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+pub fn to_string(annotations: &[Annotation]) -> String {
+    let mut content = String::from("# bank:start-bank:end\tname\tcomment\n");
+    for annotation in annotations {
+        content.push_str(&format!(
+            "{}-{}\t{}\t{}\n",
+            annotation.range.start(),
+            annotation.range.end(),
+            annotation.name,
+            annotation.comment.replace(['\t', '\n'], " ")
+        ));
+    }
+    content
+}
+/// The innermost annotation containing `addr`
+pub fn find(annotations: &[Annotation], addr: BankedAddr) -> Option<&Annotation> {
+    annotations
+        .iter()
+        .filter(|annotation| annotation.range.contains(&addr))
+        .min_by_key(|annotation| {
+            let (start, end) = (annotation.range.start(), annotation.range.end());
+            (end.bank - start.bank, end.addr.wrapping_sub(start.addr))
+        })
+}
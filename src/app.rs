@@ -0,0 +1,540 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    affinity::ThreadTuning,
+    audio_out,
+    gpu::{Gpu, Launcher, SecondGame},
+};
+use gba::{
+    boot_rom::{self, BootRom},
+    bus::Bus,
+    cartridge::Cartridge,
+    channels::Channels,
+    cheats::Cheats,
+    cpu::Cpu,
+    crash::CrashReport,
+    debugger::Debugger,
+    determinism::Host,
+    frontend::{DrawSignal, Frontend, SharedButtons},
+    layers::Layers,
+    link::NetworkLink,
+    logging,
+    movie::Movies,
+    paths::DataDirs,
+    perf::PerfStats,
+    power::Power,
+    practice::Practice,
+    ram::{Ram, RamPattern},
+    rewind::Rewind,
+    rtc::Rtc,
+    script::Script,
+    serial::LinkPort,
+    sgb::SgbBorder,
+    slots::{SlotRequest, Slots},
+    speed::{Refresh, Speed},
+    status::Status,
+    symbols::Symbols,
+    trace::Trace,
+    watch::RomWatch,
+};
+
+pub struct Gba {
+    dirs: DataDirs,
+    ram_pattern: RamPattern,
+    link_port: LinkPort,
+    deterministic: bool,
+    seed: Option<u64>,
+    frontend: Frontend,
+    rom: Option<PathBuf>,
+    /// Applied to the rom instead of the patch next to it
+    patch: Option<PathBuf>,
+    watch: bool,
+    thread_tuning: ThreadTuning,
+    /// Traces every instruction into this file from the start
+    trace: Option<PathBuf>,
+    /// Rhai script the cpu runs
+    script: Option<PathBuf>,
+    /// The `boot_rom::FILE_NAME` in the config directory if not set
+    boot_rom: Option<PathBuf>,
+    skip_boot_rom: bool,
+    /// Loads this save state slot after the first frame
+    state_slot: Option<usize>,
+    /// Integer scale of the game screen, the gui picks one otherwise
+    scale: Option<usize>,
+    refresh: Refresh,
+    /// Runs next to this one in the same window
+    second: Option<Box<Gba>>,
+}
+impl Gba {
+    /// Another game, which runs on its own thread
+    pub fn new(rom: Option<PathBuf>) -> Self {
+        Gba {
+            rom,
+            ..Default::default()
+        }
+    }
+    pub fn with_dirs(mut self, dirs: DataDirs) -> Self {
+        self.dirs = dirs;
+        self
+    }
+    pub fn with_ram_pattern(mut self, ram_pattern: RamPattern) -> Self {
+        self.ram_pattern = ram_pattern;
+        self
+    }
+    pub fn with_link_port(mut self, link_port: LinkPort) -> Self {
+        self.link_port = link_port;
+        self
+    }
+    /// Runs without host randomness and wall clock, see `Host`
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+    /// Runs deterministic from another seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.deterministic = true;
+        self.seed = Some(seed);
+        self
+    }
+    pub fn with_frontend(mut self, frontend: Frontend) -> Self {
+        self.frontend = frontend;
+        self
+    }
+    /// The game to run, without one only the boot rom runs
+    pub fn with_rom(mut self, path: PathBuf) -> Self {
+        self.rom = Some(path);
+        self
+    }
+    /// An ips or bps patch for the rom, instead of the one with the same name
+    pub fn with_patch(mut self, path: PathBuf) -> Self {
+        self.patch = Some(path);
+        self
+    }
+    /// Reloads the rom and resets when the file changes, for a quick build and test loop
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+    pub fn with_trace(mut self, path: PathBuf) -> Self {
+        self.trace = Some(path);
+        self
+    }
+    pub fn with_script(mut self, path: PathBuf) -> Self {
+        self.script = Some(path);
+        self
+    }
+    pub fn with_boot_rom(mut self, path: PathBuf) -> Self {
+        self.boot_rom = Some(path);
+        self
+    }
+    /// Starts the game right away, the boot rom does not run
+    pub fn with_skip_boot_rom(mut self, skip: bool) -> Self {
+        self.skip_boot_rom = skip;
+        self
+    }
+    pub fn with_state_slot(mut self, slot: usize) -> Self {
+        self.state_slot = Some(slot);
+        self
+    }
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+    /// How the frames are paced, can be changed in the speed window later
+    pub fn with_refresh(mut self, refresh: Refresh) -> Self {
+        self.refresh = refresh;
+        self
+    }
+    pub fn with_thread_tuning(mut self, tuning: ThreadTuning) -> Self {
+        self.thread_tuning = tuning;
+        self
+    }
+    /// A second game next to this one, linked to it by cable
+    pub fn with_second(mut self, second: Gba) -> Self {
+        let (here, there) = NetworkLink::pair();
+        self.link_port.plug(Box::new(here));
+        second.link_port.plug(Box::new(there));
+        self.second = Some(Box::new(second));
+        self
+    }
+    pub async fn run(mut self) {
+        if let Err(e) = self.dirs.create_all() {
+            eprintln!("could not create data directories: {e}");
+        }
+        logging::init(&self.dirs.logs);
+        let second = match self.second.take() {
+            Some(_) if self.frontend != Frontend::Egui => {
+                log::warn!("the second game needs the egui frontend");
+                None
+            }
+            Some(second) => Some(second.start().into_second_game()),
+            None => None,
+        };
+        let Instance {
+            receiver,
+            speed,
+            status,
+            input,
+            channels,
+            practice,
+            rewind,
+            slots,
+            movies,
+            perf,
+            layers,
+            sgb_border,
+            power,
+            cheats,
+            debugger,
+            trace,
+            rtc,
+            symbols,
+            link_port,
+            ram,
+            crash_receiver,
+            cpu,
+        } = self.start();
+        match self.frontend {
+            Frontend::Egui => {
+                let launcher = self.launcher();
+                let mut gpu = Gpu::new(receiver, self.dirs)
+                    .with_speed(speed)
+                    .with_ram(ram)
+                    .with_status(status)
+                    .with_input(input)
+                    .with_channels(channels)
+                    .with_practice(practice)
+                    .with_rewind(rewind)
+                    .with_slots(slots)
+                    .with_movies(movies)
+                    .with_perf(perf)
+                    .with_layers(layers)
+                    .with_sgb_border(sgb_border)
+                    .with_debugger(debugger)
+                    .with_trace(trace)
+                    .with_rtc(rtc)
+                    .with_power(power)
+                    .with_cheats(cheats)
+                    .with_symbols(symbols)
+                    .with_link_port(link_port)
+                    .with_crash_receiver(crash_receiver)
+                    .with_launcher(launcher);
+                if let Some(scale) = self.scale {
+                    gpu = gpu.with_scale(scale);
+                }
+                if let Some(rom) = &self.rom {
+                    gpu = gpu.with_rom_path(rom);
+                }
+                if let Some(second) = second {
+                    gpu = gpu.with_second(second);
+                }
+                gpu.run();
+            }
+            #[cfg(feature = "sdl")]
+            Frontend::Sdl => {
+                if let Err(e) = crate::sdl::run(receiver, input, speed) {
+                    log::error!("sdl frontend failed: {e}");
+                }
+            }
+            #[cfg(feature = "tui")]
+            Frontend::Tui => {
+                if let Err(e) = crate::tui::run(receiver, input, speed) {
+                    log::error!("terminal frontend failed: {e}");
+                }
+            }
+        }
+        cpu.stop();
+    }
+    /// Starts other games with the directories and the core options of this one,
+    /// unlinked and without its rom options
+    fn launcher(&self) -> Launcher {
+        let dirs = self.dirs.clone();
+        let ram_pattern = self.ram_pattern;
+        let thread_tuning = self.thread_tuning;
+        let boot_rom = self.boot_rom.clone();
+        let skip_boot_rom = self.skip_boot_rom;
+        let refresh = self.refresh;
+        Box::new(move |rom| {
+            let mut gba = Gba::new(Some(rom))
+                .with_dirs(dirs.clone())
+                .with_ram_pattern(ram_pattern)
+                .with_thread_tuning(thread_tuning)
+                .with_skip_boot_rom(skip_boot_rom)
+                .with_refresh(refresh);
+            if let Some(path) = &boot_rom {
+                gba = gba.with_boot_rom(path.clone());
+            }
+            gba.start().into_second_game()
+        })
+    }
+    /// Builds the core and runs it on its own thread
+    fn start(&self) -> Instance {
+        let (sender, receiver) = mpsc::channel();
+        let ram_pattern = self.ram_pattern;
+        let speed = Speed::default();
+        speed.set_refresh(self.refresh);
+        let cpu_speed = speed.clone();
+        let status = Status::default();
+        let input = SharedButtons::default();
+        let channels = Channels::default();
+        let practice = Practice::default();
+        let cpu_practice = practice.clone();
+        let rewind = Rewind::default();
+        rewind.set_enabled(true);
+        let cpu_rewind = rewind.clone();
+        let slots = Slots::new(self.dirs.states.clone());
+        let cpu_slots = slots.clone();
+        let movies = Movies::default();
+        let cpu_movies = movies.clone();
+        let perf = PerfStats::default();
+        let cpu_perf = perf.clone();
+        let power = Power::default();
+        let cpu_power = power.clone();
+        let cheats = Cheats::default();
+        let cpu_cheats = cheats.clone();
+        if let Some(slot) = self.state_slot {
+            slots.select(slot);
+            slots.request(SlotRequest::Load(slot));
+        }
+        let debugger = Debugger::default();
+        let trace = Trace::default();
+        if let Some(path) = &self.trace {
+            if let Err(e) = trace.start(path) {
+                eprintln!("could not trace into {}: {e}", path.display());
+            }
+        }
+        let (crash_sender, crash_rx) = mpsc::channel();
+        let crash_dir = self.dirs.states.clone();
+        let config = format!(
+            "ram pattern: {ram_pattern:?}\ndeterministic: {}\nseed: {:?}",
+            self.deterministic, self.seed
+        );
+        let host = if let Some(seed) = self.seed {
+            Host::seeded(seed)
+        } else if self.deterministic {
+            Host::deterministic()
+        } else {
+            Host::default()
+        };
+        // the cartridge and the clock window set the same clock
+        let rtc = Arc::new(Mutex::new(Rtc::default()));
+        let layers = Layers::default();
+        let sgb_border = SgbBorder::default();
+        let mut bus = Bus::default()
+            .with_host(host)
+            .with_ram_pattern(ram_pattern)
+            .with_link_port(self.link_port.clone())
+            .with_status(status.clone())
+            .with_video(sender)
+            .with_channels(channels.clone())
+            .with_input(input.clone())
+            .with_rtc(rtc.clone())
+            .with_debugger(debugger.clone())
+            .with_layers(layers.clone())
+            .with_sgb_border(sgb_border.clone());
+        let stream = match audio_out::open(status.clone()) {
+            Ok((stream, speaker)) => {
+                bus = bus.with_audio_out(speaker);
+                Some(stream)
+            }
+            Err(e) => {
+                log::warn!("playing without sound: {e}");
+                None
+            }
+        };
+        if !self.skip_boot_rom {
+            let path = self
+                .boot_rom
+                .clone()
+                .unwrap_or_else(|| self.dirs.config.join(boot_rom::FILE_NAME));
+            // without a boot rom the cpu starts where it would end
+            match BootRom::load(&path) {
+                Ok(boot_rom) => bus = bus.with_boot_rom(boot_rom),
+                Err(e) if self.boot_rom.is_some() => {
+                    log::error!("could not load boot rom {}: {e}", path.display())
+                }
+                Err(_) => {}
+            }
+        }
+        if let Some(path) = &self.rom {
+            match Cartridge::load_patched(path, self.patch.as_deref()) {
+                Ok(cartridge) => bus = bus.with_cartridge(cartridge),
+                Err(e) => log::error!("could not load rom {}: {e}", path.display()),
+            }
+        }
+        let symbols = Symbols::default();
+        if let Some(path) = &self.rom {
+            symbols.load_for(path);
+        }
+        let cpu_symbols = symbols.clone();
+        let rom_watch = self
+            .rom
+            .clone()
+            .filter(|_| self.watch)
+            .map(|rom| RomWatch::new(rom).with_patch(self.patch.clone()));
+        let ram = bus.ram();
+        let cpu_debugger = debugger.clone();
+        let cpu_trace = trace.clone();
+        let thread_tuning = self.thread_tuning;
+        let skip_boot_rom = !bus.has_boot_rom();
+        let script = self.script.clone();
+        let cpu = thread::spawn(move || {
+            thread_tuning.apply();
+            // kept outside, so what it did can still be read after a panic
+            let mut running: Option<Cpu> = None;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut cpu = Cpu::new(bus)
+                    .with_speed(cpu_speed)
+                    .with_practice(cpu_practice)
+                    .with_rewind(cpu_rewind)
+                    .with_slots(cpu_slots)
+                    .with_movies(cpu_movies)
+                    .with_perf(cpu_perf)
+                    .with_debugger(cpu_debugger)
+                    .with_trace(cpu_trace)
+                    .with_power(cpu_power)
+                    .with_cheats(cpu_cheats)
+                    .with_symbols(cpu_symbols);
+                if skip_boot_rom {
+                    cpu.skip_boot_rom();
+                }
+                if let Some(rom_watch) = rom_watch {
+                    cpu = cpu.with_rom_watch(rom_watch);
+                }
+                // the script stays on the cpu thread
+                if let Some(path) = script {
+                    match Script::load(&path, &cpu) {
+                        Ok(script) => cpu = cpu.with_script(script),
+                        Err(e) => log::error!("could not run the script {}: {e}", path.display()),
+                    }
+                }
+                running.insert(cpu).run()
+            }));
+            if let Err(payload) = result {
+                // the state can be broken enough to panic again
+                let post_mortem = running.as_ref().and_then(|cpu| {
+                    panic::catch_unwind(AssertUnwindSafe(|| cpu.post_mortem())).ok()
+                });
+                let report = CrashReport::from_panic(payload, post_mortem, &crash_dir, &config);
+                let _ = crash_sender.send(report);
+            }
+        });
+        Instance {
+            receiver,
+            speed: speed.clone(),
+            status,
+            input,
+            channels,
+            practice,
+            rewind,
+            slots,
+            movies,
+            perf,
+            layers,
+            sgb_border,
+            power,
+            cheats,
+            debugger,
+            trace,
+            rtc,
+            symbols,
+            link_port: self.link_port.clone(),
+            ram,
+            crash_receiver: crash_rx,
+            cpu: CpuThread {
+                speed,
+                handle: cpu,
+                _stream: stream,
+            },
+        }
+    }
+}
+
+/// A game running on its own cpu thread, with the handles the frontend shows
+/// and controls it with
+pub struct Instance {
+    pub receiver: Receiver<DrawSignal>,
+    pub speed: Speed,
+    pub status: Status,
+    pub input: SharedButtons,
+    pub channels: Channels,
+    pub practice: Practice,
+    pub rewind: Rewind,
+    pub slots: Slots,
+    pub movies: Movies,
+    pub perf: PerfStats,
+    pub layers: Layers,
+    pub sgb_border: SgbBorder,
+    pub power: Power,
+    pub cheats: Cheats,
+    pub debugger: Debugger,
+    pub trace: Trace,
+    pub rtc: Arc<Mutex<Rtc>>,
+    pub symbols: Symbols,
+    pub link_port: LinkPort,
+    pub ram: Arc<RwLock<Ram>>,
+    pub crash_receiver: Receiver<CrashReport>,
+    pub cpu: CpuThread,
+}
+
+impl Instance {
+    /// Shown next to the first game, the other handles are dropped
+    fn into_second_game(self) -> SecondGame {
+        SecondGame::new(
+            self.receiver,
+            self.input,
+            self.speed,
+            self.status,
+            self.ram,
+            self.crash_receiver,
+            self.cpu,
+        )
+    }
+}
+
+/// The thread the cpu of an instance runs on
+pub struct CpuThread {
+    speed: Speed,
+    handle: JoinHandle<()>,
+    /// Dropping the stream stops the sound, so it is kept until the cpu stops
+    _stream: Option<cpal::Stream>,
+}
+impl CpuThread {
+    /// Waits until the cpu wrote the save and ended
+    pub fn stop(self) {
+        self.speed.stop();
+        let _ = self.handle.join();
+    }
+}
+impl Default for Gba {
+    fn default() -> Gba {
+        Self {
+            dirs: DataDirs::default(),
+            ram_pattern: RamPattern::default(),
+            link_port: LinkPort::default(),
+            deterministic: false,
+            seed: None,
+            frontend: Frontend::default(),
+            rom: None,
+            patch: None,
+            watch: false,
+            thread_tuning: ThreadTuning::default(),
+            trace: None,
+            script: None,
+            boot_rom: None,
+            skip_boot_rom: false,
+            state_slot: None,
+            scale: None,
+            refresh: Refresh::default(),
+            second: None,
+        }
+    }
+}
@@ -1 +1,704 @@
-pub struct Audio;
+use crate::{
+    channels::Channels,
+    frontend::AudioSink,
+    ram::Ram,
+    state::{Reader, StateError, Writer},
+};
+
+/// Channel 1 sweep: bits 4-6 period, bit 3 negate, bits 0-2 shift
+pub const NR10: u16 = 0xFF10;
+/// Channel 1 duty in bits 6-7 and length in bits 0-5
+pub const NR11: u16 = 0xFF11;
+/// Channel 1 envelope: bits 4-7 initial volume, bit 3 increase, bits 0-2 period
+pub const NR12: u16 = 0xFF12;
+/// Channel 1 frequency, low byte
+pub const NR13: u16 = 0xFF13;
+/// Channel 1 trigger in bit 7, length enable in bit 6, frequency high bits 0-2
+pub const NR14: u16 = 0xFF14;
+pub const NR21: u16 = 0xFF16;
+pub const NR22: u16 = 0xFF17;
+pub const NR23: u16 = 0xFF18;
+pub const NR24: u16 = 0xFF19;
+/// Channel 3 dac in bit 7
+pub const NR30: u16 = 0xFF1A;
+/// Channel 3 length, 256 minus the value
+pub const NR31: u16 = 0xFF1B;
+/// Channel 3 volume in bits 5-6: mute, 100%, 50% and 25%
+pub const NR32: u16 = 0xFF1C;
+pub const NR33: u16 = 0xFF1D;
+pub const NR34: u16 = 0xFF1E;
+/// Channel 4 length in bits 0-5
+pub const NR41: u16 = 0xFF20;
+/// Channel 4 envelope, like NR12
+pub const NR42: u16 = 0xFF21;
+/// Channel 4 clock shift in bits 4-7, 7 bit lfsr in bit 3 and divisor code in bits 0-2
+pub const NR43: u16 = 0xFF22;
+/// Channel 4 trigger in bit 7 and length enable in bit 6
+pub const NR44: u16 = 0xFF23;
+/// Master volume, left in bits 4-6 and right in bits 0-2
+pub const NR50: u16 = 0xFF24;
+/// Panning, a bit per channel for the left in 4-7 and the right in 0-3
+pub const NR51: u16 = 0xFF25;
+/// Power in bit 7, bits 0-3 read whether channels 1-4 play
+pub const NR52: u16 = 0xFF26;
+/// 32 samples of 4 bits for channel 3, the high nibble plays first
+pub const WAVE_RAM: std::ops::RangeInclusive<u16> = 0xFF30..=0xFF3F;
+
+/// T-cycles per second
+const CPU_FREQUENCY: u32 = 4_194_304;
+/// The frame sequencer clocks length, sweep and envelope at 512Hz
+const SEQUENCER_PERIOD: u32 = CPU_FREQUENCY / 512;
+/// The 8 steps of a wave for the duties 12.5%, 25%, 50% and 75%
+const DUTY: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+/// Divisors of the noise clock, selected by the lower bits of NR43
+const NOISE_DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+/// Frames collected before they are queued to the sink
+const BATCH: usize = 256;
+
+/// Volume which changes over time
+#[derive(Clone, Debug, Default)]
+struct Envelope {
+    volume: u8,
+    timer: u8,
+}
+impl Envelope {
+    fn trigger(&mut self, nrx2: u8) {
+        self.volume = nrx2 >> 4;
+        self.timer = nrx2 & 0b111;
+    }
+    fn save_state(&self, w: &mut Writer) {
+        w.u8(self.volume);
+        w.u8(self.timer);
+    }
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.volume = r.u8()?;
+        self.timer = r.u8()?;
+        Ok(())
+    }
+    fn step(&mut self, nrx2: u8) {
+        let period = nrx2 & 0b111;
+        if period == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = period;
+            if nrx2 & 0b1000 != 0 && self.volume < 15 {
+                self.volume += 1;
+            } else if nrx2 & 0b1000 == 0 && self.volume > 0 {
+                self.volume -= 1;
+            }
+        }
+    }
+}
+
+/// Counts down while enabled in bit 6 of NRx4, the channel stops at 0
+fn step_length(length: &mut u16, enabled: &mut bool, nrx4: u8) {
+    if nrx4 & 0x40 != 0 && *length > 0 {
+        *length -= 1;
+        if *length == 0 {
+            *enabled = false;
+        }
+    }
+}
+
+/// Frequency sweep of channel 1
+#[derive(Clone, Debug, Default)]
+struct Sweep {
+    enabled: bool,
+    shadow: u16,
+    timer: u8,
+}
+impl Sweep {
+    /// The next frequency, above 2047 turns the channel off
+    fn next(&self, nr10: u8) -> u16 {
+        let delta = self.shadow >> (nr10 & 0b111);
+        if nr10 & 0b1000 != 0 {
+            self.shadow - delta
+        } else {
+            self.shadow + delta
+        }
+    }
+}
+
+/// A square wave channel, channel 1 has the sweep on top
+#[derive(Clone, Debug)]
+struct Square {
+    /// NRx0, the other registers follow it
+    base: u16,
+    enabled: bool,
+    length: u16,
+    envelope: Envelope,
+    sweep: Option<Sweep>,
+    /// Cycles until the next duty step
+    timer: u32,
+    duty_step: u8,
+}
+impl Square {
+    fn new(base: u16, sweep: bool) -> Self {
+        Square {
+            base,
+            enabled: false,
+            length: 0,
+            envelope: Envelope::default(),
+            sweep: sweep.then(Sweep::default),
+            timer: 0,
+            duty_step: 0,
+        }
+    }
+    fn save_state(&self, w: &mut Writer) {
+        w.bool(self.enabled);
+        w.u16(self.length);
+        self.envelope.save_state(w);
+        if let Some(sweep) = &self.sweep {
+            w.bool(sweep.enabled);
+            w.u16(sweep.shadow);
+            w.u8(sweep.timer);
+        }
+        w.u32(self.timer);
+        w.u8(self.duty_step);
+    }
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.enabled = r.bool()?;
+        self.length = r.u16()?;
+        self.envelope.load_state(r)?;
+        if let Some(sweep) = &mut self.sweep {
+            sweep.enabled = r.bool()?;
+            sweep.shadow = r.u16()?;
+            sweep.timer = r.u8()?;
+        }
+        self.timer = r.u32()?;
+        self.duty_step = r.u8()?;
+        Ok(())
+    }
+    fn frequency(&self, ram: &Ram) -> u16 {
+        (ram[self.base + 4] as u16 & 0b111) << 8 | ram[self.base + 3] as u16
+    }
+    fn period(&self, ram: &Ram) -> u32 {
+        (2048 - self.frequency(ram) as u32) * 4
+    }
+    /// The dac is off without an initial volume and increase, which silences the channel
+    fn dac_enabled(&self, ram: &Ram) -> bool {
+        ram[self.base + 2] & 0xF8 != 0
+    }
+    fn tick(&mut self, ram: &Ram) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = self.period(ram);
+            self.duty_step = (self.duty_step + 1) % 8;
+        }
+    }
+    /// 0..=15
+    fn output(&self, ram: &Ram) -> u8 {
+        let duty = DUTY[ram[self.base + 1] as usize >> 6];
+        if self.enabled && duty & (1 << self.duty_step) != 0 {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+    fn trigger(&mut self, ram: &mut Ram) {
+        self.enabled = self.dac_enabled(ram);
+        if self.length == 0 {
+            self.length = 64;
+        }
+        self.timer = self.period(ram);
+        self.envelope.trigger(ram[self.base + 2]);
+        let frequency = self.frequency(ram);
+        let nr10 = ram[self.base];
+        if let Some(sweep) = &mut self.sweep {
+            let period = (nr10 >> 4) & 0b111;
+            sweep.shadow = frequency;
+            sweep.timer = if period == 0 { 8 } else { period };
+            sweep.enabled = period != 0 || nr10 & 0b111 != 0;
+            if nr10 & 0b111 != 0 && sweep.next(nr10) > 2047 {
+                self.enabled = false;
+            }
+        }
+    }
+    fn step_sweep(&mut self, ram: &mut Ram) {
+        let nr10 = ram[self.base];
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+        sweep.timer = sweep.timer.saturating_sub(1);
+        if sweep.timer > 0 {
+            return;
+        }
+        let period = (nr10 >> 4) & 0b111;
+        sweep.timer = if period == 0 { 8 } else { period };
+        if !sweep.enabled || period == 0 {
+            return;
+        }
+        let frequency = sweep.next(nr10);
+        if frequency > 2047 {
+            self.enabled = false;
+        } else if nr10 & 0b111 != 0 {
+            sweep.shadow = frequency;
+            ram[self.base + 3] = frequency as u8;
+            ram[self.base + 4] = ram[self.base + 4] & !0b111 | (frequency >> 8) as u8;
+            if sweep.next(nr10) > 2047 {
+                self.enabled = false;
+            }
+        }
+    }
+}
+
+/// Channel 3, plays the samples in the wave ram
+#[derive(Clone, Debug, Default)]
+struct Wave {
+    enabled: bool,
+    length: u16,
+    timer: u32,
+    /// The sample which plays, 0..32
+    position: u8,
+}
+impl Wave {
+    fn save_state(&self, w: &mut Writer) {
+        w.bool(self.enabled);
+        w.u16(self.length);
+        w.u32(self.timer);
+        w.u8(self.position);
+    }
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.enabled = r.bool()?;
+        self.length = r.u16()?;
+        self.timer = r.u32()?;
+        self.position = r.u8()?;
+        Ok(())
+    }
+    fn period(ram: &Ram) -> u32 {
+        let frequency = (ram[NR34] as u32 & 0b111) << 8 | ram[NR33] as u32;
+        (2048 - frequency) * 2
+    }
+    fn dac_enabled(ram: &Ram) -> bool {
+        ram[NR30] & 0x80 != 0
+    }
+    fn tick(&mut self, ram: &Ram) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = Self::period(ram);
+            self.position = (self.position + 1) % 32;
+        }
+    }
+    fn output(&self, ram: &Ram) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        let byte = ram[WAVE_RAM.start() + self.position as u16 / 2];
+        let sample = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+        match (ram[NR32] >> 5) & 0b11 {
+            0 => 0,
+            shift => sample >> (shift - 1),
+        }
+    }
+    fn trigger(&mut self, ram: &Ram) {
+        self.enabled = Self::dac_enabled(ram);
+        if self.length == 0 {
+            self.length = 256;
+        }
+        self.timer = Self::period(ram);
+        self.position = 0;
+    }
+}
+
+/// Channel 4, pseudo random noise from a linear feedback shift register
+#[derive(Clone, Debug, Default)]
+struct Noise {
+    enabled: bool,
+    length: u16,
+    envelope: Envelope,
+    timer: u32,
+    lfsr: u16,
+}
+impl Noise {
+    fn save_state(&self, w: &mut Writer) {
+        w.bool(self.enabled);
+        w.u16(self.length);
+        self.envelope.save_state(w);
+        w.u32(self.timer);
+        w.u16(self.lfsr);
+    }
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.enabled = r.bool()?;
+        self.length = r.u16()?;
+        self.envelope.load_state(r)?;
+        self.timer = r.u32()?;
+        self.lfsr = r.u16()?;
+        Ok(())
+    }
+    fn period(ram: &Ram) -> u32 {
+        let nr43 = ram[NR43];
+        NOISE_DIVISORS[nr43 as usize & 0b111] << (nr43 >> 4)
+    }
+    fn dac_enabled(ram: &Ram) -> bool {
+        ram[NR42] & 0xF8 != 0
+    }
+    fn tick(&mut self, ram: &Ram) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = Self::period(ram);
+            let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+            self.lfsr = (self.lfsr >> 1) | feedback << 14;
+            if ram[NR43] & 0b1000 != 0 {
+                // the short mode also feeds back into bit 6
+                self.lfsr = self.lfsr & !(1 << 6) | feedback << 6;
+            }
+        }
+    }
+    fn output(&self) -> u8 {
+        if self.enabled && self.lfsr & 1 == 0 {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+    fn trigger(&mut self, ram: &Ram) {
+        self.enabled = Self::dac_enabled(ram);
+        if self.length == 0 {
+            self.length = 64;
+        }
+        self.timer = Self::period(ram);
+        self.envelope.trigger(ram[NR42]);
+        self.lfsr = 0x7FFF;
+    }
+}
+
+/// The sound hardware, ticked with the cycles the cpu ran
+pub struct Audio {
+    squares: [Square; 2],
+    wave: Wave,
+    noise: Noise,
+    channels: Channels,
+    /// Cycles until the frame sequencer steps
+    sequencer_timer: u32,
+    sequencer_step: u8,
+    /// Counts up by the sample rate every cycle, a sample is taken at the cpu frequency
+    sample_timer: u32,
+    /// Charge of the high pass capacitor per side, removes the dc offset
+    capacitor: [f32; 2],
+    samples: Vec<[f32; 2]>,
+    /// Samples are taken this much more often, which plays them slower
+    stretch: f32,
+}
+impl Audio {
+    pub fn new(channels: Channels) -> Self {
+        Audio {
+            squares: [Square::new(NR10, true), Square::new(NR21 - 1, false)],
+            wave: Wave::default(),
+            noise: Noise::default(),
+            channels,
+            sequencer_timer: SEQUENCER_PERIOD,
+            sequencer_step: 0,
+            sample_timer: 0,
+            capacitor: [0.; 2],
+            samples: Vec::with_capacity(BATCH),
+            stretch: 1.,
+        }
+    }
+    /// Powers back on, the mute and solo state is kept
+    pub fn reset(&mut self) {
+        *self = Audio::new(self.channels.clone());
+    }
+    /// The channels and the sequencer, the registers are in the ram
+    pub fn save_state(&self, w: &mut Writer) {
+        for square in &self.squares {
+            square.save_state(w);
+        }
+        self.wave.save_state(w);
+        self.noise.save_state(w);
+        w.u32(self.sequencer_timer);
+        w.u8(self.sequencer_step);
+        w.u32(self.sample_timer);
+        w.f32(self.capacitor[0]);
+        w.f32(self.capacitor[1]);
+    }
+    pub fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        for square in &mut self.squares {
+            square.load_state(r)?;
+        }
+        self.wave.load_state(r)?;
+        self.noise.load_state(r)?;
+        self.sequencer_timer = r.u32()?;
+        self.sequencer_step = r.u8()?;
+        self.sample_timer = r.u32()?;
+        self.capacitor = [r.f32()?, r.f32()?];
+        Ok(())
+    }
+    pub fn set_stretch(&mut self, stretch: f32) {
+        self.stretch = stretch;
+    }
+    fn powered(ram: &Ram) -> bool {
+        ram[NR52] & 0x80 != 0
+    }
+    /// Called after a write to a sound register, `before` is restored for writes which are ignored
+    pub fn write(&mut self, ram: &mut Ram, addr: u16, content: u8, before: u8) {
+        if addr == NR52 {
+            if content & 0x80 == 0 {
+                // turning the power off clears all registers
+                for addr in NR10..NR52 {
+                    ram[addr] = 0;
+                }
+                self.squares
+                    .iter_mut()
+                    .for_each(|square| square.enabled = false);
+                self.wave.enabled = false;
+                self.noise.enabled = false;
+            }
+            self.update_status(ram);
+            return;
+        }
+        if !Self::powered(ram) {
+            ram[addr] = before;
+            return;
+        }
+        for square in &mut self.squares {
+            match addr.wrapping_sub(square.base) {
+                1 => square.length = 64 - (content & 0x3F) as u16,
+                2 if !square.dac_enabled(ram) => square.enabled = false,
+                4 if content & 0x80 != 0 => square.trigger(ram),
+                _ => {}
+            }
+        }
+        match addr {
+            NR30 if !Wave::dac_enabled(ram) => self.wave.enabled = false,
+            NR31 => self.wave.length = 256 - content as u16,
+            NR34 if content & 0x80 != 0 => self.wave.trigger(ram),
+            NR41 => self.noise.length = 64 - (content & 0x3F) as u16,
+            NR42 if !Noise::dac_enabled(ram) => self.noise.enabled = false,
+            NR44 if content & 0x80 != 0 => self.noise.trigger(ram),
+            _ => {}
+        }
+        self.update_status(ram);
+    }
+    /// Bits 0-3 of NR52 show which channels play, the rest reads as set
+    fn update_status(&self, ram: &mut Ram) {
+        let enabled = [
+            self.squares[0].enabled,
+            self.squares[1].enabled,
+            self.wave.enabled,
+            self.noise.enabled,
+        ];
+        let playing = enabled
+            .iter()
+            .enumerate()
+            .filter(|(_, enabled)| **enabled)
+            .fold(0, |playing, (i, _)| playing | 1 << i);
+        ram[NR52] = ram[NR52] & 0x80 | 0x70 | playing;
+    }
+    pub fn tick(&mut self, ram: &mut Ram, cycles: usize, out: &mut dyn AudioSink) {
+        if !Self::powered(ram) {
+            return;
+        }
+        let sample_rate = (out.sample_rate() as f32 * self.stretch) as u32;
+        for _ in 0..cycles {
+            self.sequencer_timer -= 1;
+            if self.sequencer_timer == 0 {
+                self.sequencer_timer = SEQUENCER_PERIOD;
+                self.step_sequencer(ram);
+            }
+            for square in &mut self.squares {
+                square.tick(ram);
+            }
+            self.wave.tick(ram);
+            self.noise.tick(ram);
+            self.sample_timer += sample_rate;
+            if self.sample_timer >= CPU_FREQUENCY {
+                self.sample_timer -= CPU_FREQUENCY;
+                let sample = self.mix(ram, sample_rate);
+                self.samples.push(sample);
+                if self.samples.len() >= BATCH {
+                    out.queue(&self.samples);
+                    self.samples.clear();
+                }
+            }
+        }
+        self.update_status(ram);
+    }
+    /// Length on every second step, sweep on steps 2 and 6, envelope on step 7
+    fn step_sequencer(&mut self, ram: &mut Ram) {
+        let step = self.sequencer_step;
+        self.sequencer_step = (step + 1) % 8;
+        if step.is_multiple_of(2) {
+            step_length(&mut self.wave.length, &mut self.wave.enabled, ram[NR34]);
+            step_length(&mut self.noise.length, &mut self.noise.enabled, ram[NR44]);
+        }
+        if step == 7 {
+            self.noise.envelope.step(ram[NR42]);
+        }
+        for square in &mut self.squares {
+            if step.is_multiple_of(2) {
+                step_length(
+                    &mut square.length,
+                    &mut square.enabled,
+                    ram[square.base + 4],
+                );
+            }
+            if step == 2 || step == 6 {
+                square.step_sweep(ram);
+            }
+            if step == 7 {
+                square.envelope.step(ram[square.base + 2]);
+            }
+        }
+    }
+    /// One stereo sample of all audible channels, panned and scaled by the master volume
+    fn mix(&mut self, ram: &Ram, sample_rate: u32) -> [f32; 2] {
+        let panning = ram[NR51];
+        let mut sides = [0.; 2];
+        let outputs = [
+            (
+                self.squares[0].output(ram),
+                self.squares[0].dac_enabled(ram),
+            ),
+            (
+                self.squares[1].output(ram),
+                self.squares[1].dac_enabled(ram),
+            ),
+            (self.wave.output(ram), Wave::dac_enabled(ram)),
+            (self.noise.output(), Noise::dac_enabled(ram)),
+        ];
+        self.channels
+            .record_outputs(outputs.map(|(output, _)| output));
+        for (channel, (output, dac_enabled)) in outputs.into_iter().enumerate() {
+            self.channels.record_level(channel, output * 17);
+            if !self.channels.is_audible(channel) || !dac_enabled {
+                continue;
+            }
+            // the dac maps 0..=15 to 1.0..=-1.0
+            let volume = self.channels.channel_volume(channel) as f32 / 100.;
+            let analog = (1. - output as f32 / 7.5) * volume;
+            for (side, bit) in [(0, 4), (1, 0)] {
+                if panning & (1 << (channel + bit)) != 0 {
+                    sides[side] += analog;
+                }
+            }
+        }
+        let volume = [(ram[NR50] >> 4) & 0b111, ram[NR50] & 0b111];
+        // the capacitor charges by this factor per cycle on hardware
+        let charge = 0.999958f32.powf(CPU_FREQUENCY as f32 / sample_rate as f32);
+        let mut sample = [0.; 2];
+        for side in 0..2 {
+            let input = sides[side] / 4. * (volume[side] + 1) as f32 / 8.;
+            sample[side] = input - self.capacitor[side];
+            self.capacitor[side] = input - sample[side] * charge;
+        }
+        // the volume of the settings, after the filter so it does not change its charge
+        let master = self.channels.volume() as f32 / 100.;
+        sample.map(|side| side * master)
+    }
+}
+impl Default for Audio {
+    fn default() -> Self {
+        Audio::new(Channels::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{frontend::AudioSink, ram::Ram};
+
+    use super::*;
+
+    /// Plays a square wave on channel 1 until its length counter runs out
+    #[test]
+    fn square_channel() -> Result<(), String> {
+        let mut ram = Ram::default();
+        let mut audio = Audio::default();
+        let mut recorder = Recorder::default();
+        let write = |audio: &mut Audio, ram: &mut Ram, addr: u16, content: u8| {
+            let before = ram[addr];
+            ram[addr] = content;
+            audio.write(ram, addr, content, before);
+        };
+        write(&mut audio, &mut ram, NR52, 0x80);
+        write(&mut audio, &mut ram, NR50, 0x77);
+        write(&mut audio, &mut ram, NR51, 0xFF);
+        // 50% duty, full volume, 512Hz
+        write(&mut audio, &mut ram, NR11, 0x80);
+        write(&mut audio, &mut ram, NR12, 0xF0);
+        write(&mut audio, &mut ram, NR13, 0x00);
+        write(&mut audio, &mut ram, NR14, 0x87);
+        if ram[NR52] & 1 == 0 {
+            return Err("triggering did not turn the channel on".to_string());
+        }
+        audio.tick(&mut ram, 32768, &mut recorder);
+        let peak = recorder
+            .0
+            .iter()
+            .fold(0f32, |peak, [left, _]| peak.max(left.abs()));
+        if peak < 0.1 {
+            return Err(format!(
+                "the output peaks at {peak} after {} samples",
+                recorder.0.len()
+            ));
+        }
+        // length 2 runs out after two length steps
+        write(&mut audio, &mut ram, NR11, 0x80 | 62);
+        write(&mut audio, &mut ram, NR14, 0xC7);
+        audio.tick(&mut ram, 32768, &mut recorder);
+        if ram[NR52] & 1 != 0 {
+            return Err("the length counter did not turn the channel off".to_string());
+        }
+        Ok(())
+    }
+    /// Plays a saw tooth from the wave ram, then noise, each on its own
+    #[test]
+    fn wave_noise() -> Result<(), String> {
+        let mut ram = Ram::default();
+        let mut audio = Audio::default();
+        let write = |audio: &mut Audio, ram: &mut Ram, addr: u16, content: u8| {
+            let before = ram[addr];
+            ram[addr] = content;
+            audio.write(ram, addr, content, before);
+        };
+        write(&mut audio, &mut ram, NR52, 0x80);
+        write(&mut audio, &mut ram, NR50, 0x77);
+        write(&mut audio, &mut ram, NR51, 0xFF);
+        for (i, addr) in WAVE_RAM.enumerate() {
+            ram[addr] = (i as u8 * 2) << 4 | (i as u8 * 2 + 1);
+        }
+        write(&mut audio, &mut ram, NR30, 0x80);
+        write(&mut audio, &mut ram, NR32, 0x20);
+        write(&mut audio, &mut ram, NR33, 0x00);
+        write(&mut audio, &mut ram, NR34, 0x87);
+        let mut wave = Recorder::default();
+        audio.tick(&mut ram, 32768, &mut wave);
+        if ram[NR52] & 0b100 == 0 {
+            return Err("triggering did not turn the wave channel on".to_string());
+        }
+        write(&mut audio, &mut ram, NR30, 0x00);
+        write(&mut audio, &mut ram, NR42, 0xF0);
+        write(&mut audio, &mut ram, NR43, 0x11);
+        write(&mut audio, &mut ram, NR44, 0x80);
+        let mut noise = Recorder::default();
+        audio.tick(&mut ram, 32768, &mut noise);
+        if ram[NR52] & 0b1100 != 0b1000 {
+            return Err(format!(
+                "NR52 is {:#04x} with only the noise playing",
+                ram[NR52]
+            ));
+        }
+        for (name, recorder) in [("wave", wave), ("noise", noise)] {
+            let peak = recorder
+                .0
+                .iter()
+                .fold(0f32, |peak, [left, _]| peak.max(left.abs()));
+            if peak < 0.1 {
+                return Err(format!("the {name} channel peaks at {peak}"));
+            }
+        }
+        Ok(())
+    }
+    #[derive(Default)]
+    struct Recorder(Vec<[f32; 2]>);
+    impl AudioSink for Recorder {
+        fn queue(&mut self, samples: &[[f32; 2]]) {
+            self.0.extend_from_slice(samples);
+        }
+    }
+}
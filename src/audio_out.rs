@@ -0,0 +1,76 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use gba::{frontend::AudioSink, status::Status};
+
+/// Buffered audio in seconds, more is dropped so the delay does not grow
+const MAX_LATENCY: f32 = 0.1;
+
+/// Samples queued by the core and played by the output stream
+#[derive(Clone)]
+pub struct Speaker {
+    buffer: Arc<Mutex<VecDeque<[f32; 2]>>>,
+    capacity: usize,
+    sample_rate: u32,
+    status: Status,
+}
+impl AudioSink for Speaker {
+    fn queue(&mut self, samples: &[[f32; 2]]) {
+        let mut buffer = self.buffer.lock().unwrap();
+        let free = self.capacity.saturating_sub(buffer.len());
+        buffer.extend(&samples[..samples.len().min(free)]);
+        self.status
+            .set_audio_fill((buffer.len() * 100 / self.capacity) as u8);
+    }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+/// Opens the default output device. The stream plays as long as it is kept,
+/// it has to stay on the thread which opened it.
+pub fn open(status: Status) -> Result<(cpal::Stream, Speaker), String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("no audio output device")?;
+    let config: cpal::StreamConfig = device
+        .default_output_config()
+        .map_err(|e| e.to_string())?
+        .into();
+    let sample_rate = config.sample_rate;
+    let capacity = (sample_rate as f32 * MAX_LATENCY) as usize;
+    let speaker = Speaker {
+        buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        capacity,
+        sample_rate,
+        status,
+    };
+    let buffer = speaker.buffer.clone();
+    let status = speaker.status.clone();
+    let channels = config.channels as usize;
+    let mut last = [0.; 2];
+    let stream = device
+        .build_output_stream(
+            &config,
+            move |data: &mut [f32], _| {
+                let mut buffer = buffer.lock().unwrap();
+                for frame in data.chunks_mut(channels) {
+                    // repeats the last sample on an underrun instead of clicking
+                    last = buffer.pop_front().unwrap_or(last);
+                    for (i, sample) in frame.iter_mut().enumerate() {
+                        *sample = last[i % 2];
+                    }
+                }
+                // `Refresh::Audio` waits for the buffer to drain
+                status.set_audio_fill((buffer.len() * 100 / capacity) as u8);
+            },
+            |e| log::warn!("audio output failed: {e}"),
+            None,
+        )
+        .map_err(|e| e.to_string())?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok((stream, speaker))
+}
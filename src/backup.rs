@@ -0,0 +1,80 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const BACKUP_EXTENSION: &str = "bak";
+
+/// A previous version of a file, named `<file name>.<millis since epoch>.bak`
+#[derive(Clone, Debug)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub timestamp: u128,
+}
+impl Backup {
+    /// When the backup was made
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.timestamp as u64)
+    }
+}
+
+/// Copies `path` into a timestamped backup next to it
+/// and deletes all but the newest `keep` backups.
+/// Does nothing if `path` doesn't exist yet.
+pub fn backup(path: &Path, keep: usize) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    fs::copy(path, backup_path(path, timestamp))?;
+    for old in list(path)?.into_iter().skip(keep) {
+        fs::remove_file(old.path)?;
+    }
+    Ok(())
+}
+/// Returns all backups of `path`, newest first
+pub fn list(path: &Path) -> io::Result<Vec<Backup>> {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(Vec::new());
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry_path = entry?.path();
+        let timestamp = entry_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(name))
+            .and_then(|n| n.strip_prefix('.'))
+            .and_then(|n| n.strip_suffix(BACKUP_EXTENSION))
+            .and_then(|n| n.strip_suffix('.'))
+            .and_then(|n| n.parse().ok());
+        if let Some(timestamp) = timestamp {
+            backups.push(Backup {
+                path: entry_path,
+                timestamp,
+            });
+        }
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.timestamp));
+    Ok(backups)
+}
+/// Overwrites `path` with the content of the backup.
+/// The current version is backed up first so a restore can be undone.
+pub fn restore(path: &Path, backup: &Backup, keep: usize) -> io::Result<()> {
+    let content = fs::read(&backup.path)?;
+    self::backup(path, keep)?;
+    fs::write(path, content)
+}
+fn backup_path(path: &Path, timestamp: u128) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{timestamp}.{BACKUP_EXTENSION}"));
+    path.with_file_name(name)
+}
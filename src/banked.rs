@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// The switchable rom bank is mapped here
+const SWITCHABLE_ROM: std::ops::Range<u16> = 0x4000..0x8000;
+
+/// An address together with the bank mapped at it, written `bank:offset` like in symbol files.
+/// Outside switchable regions the bank is always 0, so `01:4000` and `02:4000` are different
+/// code while `00:C000` is the same byte whichever rom bank is selected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BankedAddr {
+    pub bank: u16,
+    pub addr: u16,
+}
+impl BankedAddr {
+    pub fn new(bank: u16, addr: u16) -> Self {
+        BankedAddr { bank, addr }
+    }
+    /// The byte the cpu sees at `addr` while `rom_bank` is selected
+    pub fn resolve(addr: u16, rom_bank: u16) -> Self {
+        let bank = if SWITCHABLE_ROM.contains(&addr) {
+            rom_bank
+        } else {
+            0
+        };
+        BankedAddr { bank, addr }
+    }
+    /// Parses `bank:offset` or a plain offset in bank 0, both in hex
+    pub fn parse(s: &str) -> Option<Self> {
+        let hex = |s: &str| u16::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok();
+        match s.split_once(':') {
+            Some((bank, addr)) => Some(BankedAddr::new(hex(bank)?, hex(addr)?)),
+            None => Some(BankedAddr::new(0, hex(s)?)),
+        }
+    }
+}
+impl fmt::Display for BankedAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}:{:04X}", self.bank, self.addr)
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    fmt,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bus::Bus,
+    cartridge::Cartridge,
+    cpu::{Cpu, CLOCK_SPEED, CYCLES_PER_FRAME},
+    frontend::Null,
+};
+
+/// One emulated minute
+pub const DEFAULT_FRAMES: u64 = 3600;
+
+/// The parts of `Bus::tick` a profile times
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Part {
+    Timer,
+    LinkPort,
+    Ppu,
+    Apu,
+}
+impl Part {
+    pub const ALL: [Part; 4] = [Part::Timer, Part::LinkPort, Part::Ppu, Part::Apu];
+    pub fn name(self) -> &'static str {
+        match self {
+            Part::Timer => "timer",
+            Part::LinkPort => "link port",
+            Part::Ppu => "ppu",
+            Part::Apu => "apu",
+        }
+    }
+}
+
+/// Where the time of the emulation goes, filled by a bus which has one attached.
+/// Taking the time costs time itself, so the parts are a bit slower than without.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub instructions: u64,
+    times: [Duration; Part::ALL.len()],
+    since: Option<Instant>,
+}
+impl Profile {
+    /// Starts timing the next part
+    pub fn start(&mut self) {
+        self.since = Some(Instant::now());
+    }
+    /// Adds the time since the last call or `start` to `part`
+    pub fn lap(&mut self, part: Part) {
+        let now = Instant::now();
+        if let Some(since) = self.since {
+            self.times[part as usize] += now - since;
+        }
+        self.since = Some(now);
+    }
+    pub fn time(&self, part: Part) -> Duration {
+        self.times[part as usize]
+    }
+}
+
+/// The result of a benchmark run
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub frames: u64,
+    pub wall: Duration,
+    pub profile: Profile,
+    /// Spent taking the frames out of the channel, like the gui does
+    pub video: Option<Duration>,
+}
+impl Report {
+    pub fn emulated(&self) -> Duration {
+        let cycles = self.frames * CYCLES_PER_FRAME as u64;
+        Duration::from_secs_f64(cycles as f64 / CLOCK_SPEED as f64)
+    }
+    /// Emulated seconds per wall second
+    pub fn speed(&self) -> f64 {
+        self.emulated().as_secs_f64() / self.wall.as_secs_f64()
+    }
+    pub fn instructions_per_second(&self) -> f64 {
+        self.profile.instructions as f64 / self.wall.as_secs_f64()
+    }
+}
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} frames, {:.2} emulated s in {:.3} s",
+            self.frames,
+            self.emulated().as_secs_f64(),
+            self.wall.as_secs_f64()
+        )?;
+        writeln!(f, "speed: {:.2} emulated s per s", self.speed())?;
+        writeln!(
+            f,
+            "instructions: {:.2} M per s",
+            self.instructions_per_second() / 1e6
+        )?;
+        let parts: Vec<_> = Part::ALL
+            .iter()
+            .map(|part| (part.name(), self.profile.time(*part)))
+            .chain(self.video.map(|video| ("video channel", video)))
+            .collect();
+        let rest = parts
+            .iter()
+            .fold(self.wall, |rest, (_, time)| rest.saturating_sub(*time));
+        for (name, time) in parts.into_iter().chain([("cpu and the rest", rest)]) {
+            writeln!(
+                f,
+                "  {name:<16} {:>6.3} s {:>5.1}%",
+                time.as_secs_f64(),
+                time.as_secs_f64() / self.wall.as_secs_f64() * 100.
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `cartridge` as fast as it goes for `frames` without a boot rom and without sound.
+/// With `video` the frames are sent through a channel and taken out after each one,
+/// like for the gui, else they are dropped in the ppu.
+pub fn run(cartridge: Cartridge, frames: u64, video: bool) -> Report {
+    let (sender, receiver) = mpsc::channel();
+    let mut bus = Bus::default().with_cartridge(cartridge).with_profile();
+    bus = if video {
+        bus.with_video(sender)
+    } else {
+        bus.with_video(Null)
+    };
+    let mut cpu = Cpu::new(bus);
+    cpu.skip_boot_rom();
+    let mut received = Duration::ZERO;
+    let start = Instant::now();
+    for _ in 0..frames {
+        cpu.step_frame();
+        if video {
+            let before = Instant::now();
+            for signal in receiver.try_iter() {
+                drop(signal);
+            }
+            received += before.elapsed();
+        }
+    }
+    Report {
+        frames,
+        wall: start.elapsed(),
+        profile: cpu.bus().profile().cloned().unwrap_or_default(),
+        video: video.then_some(received),
+    }
+}
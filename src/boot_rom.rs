@@ -0,0 +1,131 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    audio::{
+        NR10, NR11, NR12, NR13, NR21, NR22, NR23, NR30, NR31, NR32, NR33, NR41, NR42, NR43, NR50,
+        NR51, NR52,
+    },
+    cartridge::BOOT_ROM,
+    interrupt::IF,
+    ppu::{OBP0, OBP1},
+    vram::{BGP, LCDC},
+};
+
+/// Looked up in the config directory when no other boot rom is given
+pub const FILE_NAME: &str = "dmg_boot.bin";
+/// The io registers as the DMG boot rom leaves them. The sound is powered on first,
+/// the other sound registers ignore writes before. Registers which read the same
+/// after a reset are left out.
+pub const POST_BOOT_IO: [(u16, u8); 22] = [
+    (NR52, 0xF1),
+    (NR10, 0x80),
+    (NR11, 0xBF),
+    (NR12, 0xF3),
+    (NR13, 0xFF),
+    (NR21, 0x3F),
+    (NR22, 0x00),
+    (NR23, 0xFF),
+    (NR30, 0x7F),
+    (NR31, 0xFF),
+    (NR32, 0x9F),
+    (NR33, 0xFF),
+    (NR41, 0xFF),
+    (NR42, 0x00),
+    (NR43, 0x00),
+    (NR50, 0x77),
+    (NR51, 0xF3),
+    (LCDC, 0x91),
+    (BGP, 0xFC),
+    (OBP0, 0xFF),
+    (OBP1, 0xFF),
+    // the vblank interrupt of the last frame is still pending
+    (IF, 0xE1),
+];
+
+/// The program the cpu runs at power on, it scrolls in the logo and checks the
+/// cartridge header. Mapped over the cartridge until it writes `BOOT_ROM_DISABLE`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BootRom(Vec<u8>);
+impl BootRom {
+    pub fn load(path: &Path) -> io::Result<BootRom> {
+        BootRom::from_bytes(fs::read(path)?)
+    }
+    pub fn from_bytes(bytes: Vec<u8>) -> io::Result<BootRom> {
+        if bytes.len() != BOOT_ROM.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "a boot rom has {} bytes, this one {}",
+                    BOOT_ROM.len(),
+                    bytes.len()
+                ),
+            ));
+        }
+        Ok(BootRom(bytes))
+    }
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::{self, Bus, BOOT_ROM_DISABLE},
+        cartridge::Cartridge,
+        cpu::{Cpu, V16, V8},
+        testing::{micro_rom, ENTRY},
+        vram::{BGP, LCDC},
+    };
+
+    use super::*;
+
+    /// The boot rom covers the cartridge until it unmaps itself, without one the cpu
+    /// starts with the registers it leaves behind
+    #[test]
+    fn boot_rom() -> Result<(), String> {
+        if BootRom::from_bytes(vec![0; 255]).is_ok() {
+            return Err("a boot rom of 255 bytes loaded".to_string());
+        }
+        let boot_rom = BootRom::from_bytes(vec![0xB0; 0x100]).map_err(|e| e.to_string())?;
+        let mut rom = micro_rom(&[]);
+        rom[0] = 0xCA;
+        let cartridge = Cartridge::from_bytes(rom.clone()).map_err(|e| e.to_string())?;
+        let mut bus = Bus::default()
+            .with_boot_rom(boot_rom)
+            .with_cartridge(cartridge);
+        let mapped = bus.fetch(0);
+        bus.write_mem(BOOT_ROM_DISABLE, 1);
+        if (mapped, bus.fetch(0)) != (0xB0, 0xCA) {
+            return Err(format!(
+                "read ${mapped:02X} before and ${:02X} after unmapping",
+                bus.fetch(0)
+            ));
+        }
+        let cartridge = Cartridge::from_bytes(rom).map_err(|e| e.to_string())?;
+        let bus = Bus::default().with_cartridge(cartridge);
+        let ram = bus.ram();
+        let mut cpu = Cpu::new(bus);
+        cpu.skip_boot_rom();
+        let registers = (
+            cpu.r(V8::A),
+            cpu.r(V16::BC),
+            cpu.r(V16::HL),
+            cpu.r(V16::SP),
+            cpu.r(V16::PC),
+        );
+        if registers != (0x01, 0x0013, 0x014D, 0xFFFE, ENTRY) {
+            return Err(format!("started with A, BC, HL, SP, PC {registers:04X?}"));
+        }
+        let ram = ram.read().unwrap();
+        let io = (
+            bus::read(&ram, LCDC),
+            bus::read(&ram, BGP),
+            bus::read(&ram, 0),
+        );
+        if io != (0x91, 0xFC, 0xCA) {
+            return Err(format!("LCDC, BGP and $0000 read {io:02X?}"));
+        }
+        Ok(())
+    }
+}
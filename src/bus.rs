@@ -1,35 +1,708 @@
 use crate::{
-    audio::Audio,
-    gpu::{DrawSignal, Gpu},
-    ram::Ram,
+    audio::{Audio, NR10, NR52},
+    bench::{Part, Profile},
+    boot_rom::{BootRom, POST_BOOT_IO},
+    cartridge::{Cartridge, BOOT_ROM, ROM_BANK_SIZE},
+    cgb::{self, Cgb, BCPS, KEY0, KEY1, OCPD, SVBK, VBK},
+    channels::Channels,
+    cheats::CheatCode,
+    debugger::Debugger,
+    determinism::Host,
+    frontend::{AudioSink, Buttons, DrawSignal, InputSource, Null, VideoSink},
+    interrupt::{self, Interrupt},
+    joypad::{Joypad, P1},
+    layers::Layers,
+    mbc::{Mapping, RAM_BANK_SIZE},
+    ppu::{Ppu, LY, STAT},
+    ram::{self, Ram, RamPattern, ECHO, IO, OAM, SRAM, UNUSABLE, WRAM},
+    rtc::Rtc,
+    script::MemoryHooks,
+    serial::{LinkPort, SC},
+    sgb::{Sgb, SgbBorder, SgbVideo},
+    state::{Reader, StateError, Writer},
+    status::Status,
+    timer::{Timer, DIV},
 };
-use std::sync::{mpsc::Sender, RwLock};
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::SystemTime,
+};
+
+/// Writing a non zero value unmaps the boot rom
+pub const BOOT_ROM_DISABLE: u16 = 0xFF50;
+/// Everything below is rom, writes go to the cartridge
+const ROM_END: u16 = 0x8000;
+/// Writing the high byte of a source address copies 160 bytes from there into OAM
+pub const DMA: u16 = 0xFF46;
+/// Bits of the io registers which always read as 1, unmapped registers read 0xFF
+const IO_UNUSED_BITS: [u8; IO.end - IO.start] = [
+    // P1, SB, SC, -, DIV, TIMA, TMA, TAC, -, -, -, -, -, -, -, IF
+    0xC0, 0x00, 0x7E, 0xFF, 0x00, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xE0,
+    // NR10 - NR14, -, NR21 - NR24, NR30 - NR34, -
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF, 0xFF,
+    // NR41 - NR44, NR50 - NR52, -
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0x70, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    // wave ram
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // LCDC, STAT, SCY, SCX, LY, LYC, DMA, BGP, OBP0, OBP1, WY, WX, -, -, -, -
+    0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    // the boot rom register and the color registers of the CGB
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
 
 pub struct Bus {
-    ram: RwLock<Ram>,
+    ram: Arc<RwLock<Ram>>,
     // gpu: RwLock<Gpu>,
-    _audio: RwLock<Audio>,
-    gpu_sender: Option<Sender<DrawSignal>>,
+    audio: Audio,
+    video: Box<dyn VideoSink>,
+    /// Frames are not sent while the emulation catches up
+    skip_frames: bool,
+    /// The frames are rolled back after they are shown: no sound and nothing over the link port
+    ahead: bool,
+    audio_out: Box<dyn AudioSink>,
+    input: Box<dyn InputSource>,
+    link_port: LinkPort,
+    joypad: Joypad,
+    timer: Timer,
+    ppu: Ppu,
+    layers: Layers,
+    status: Status,
+    host: Host,
+    /// Kept to fill the memory the same way on a reset
+    ram_pattern: RamPattern,
+    cartridge: Option<Cartridge>,
+    /// Mapped again on a reset, without one the cpu starts after it
+    boot_rom: Option<BootRom>,
+    /// The banks and palettes of CGB mode, which CGB games turn on
+    cgb: Option<Cgb>,
+    /// The palettes and the border of SGB games on the DMG
+    sgb: Option<Sgb>,
+    sgb_border: SgbBorder,
+    /// Clock of MBC3 cartridges, shared with the gui
+    rtc: Arc<Mutex<Rtc>>,
+    debugger: Debugger,
+    /// Set by `flat`, then the memory is a plain 64 KiB without io and banking
+    /// and every access is recorded
+    accesses: Option<Mutex<Vec<BusAccess>>>,
+    /// The Game Genie codes, which patch what the cpu reads from the rom
+    rom_patches: Vec<CheatCode>,
+    /// Times the parts of `tick` for `--bench`
+    profile: Option<Profile>,
+    /// The addresses a script watches
+    memory_hooks: MemoryHooks,
+    /// Buttons a script holds instead of the ones of `input`
+    input_override: Option<Buttons>,
+}
+/// A read or write of the cpu on a flat bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusAccess {
+    pub addr: u16,
+    pub value: u8,
+    pub write: bool,
 }
 impl Bus {
-    pub fn with_gpu(mut self, gpu_sender: Sender<DrawSignal>) -> Self {
-        self.gpu_sender = Some(gpu_sender);
+    /// Plain memory without io, banking or interrupts, to test instructions on their own
+    pub fn flat() -> Self {
+        Bus {
+            accesses: Some(Mutex::default()),
+            ..Bus::default()
+        }
+    }
+    /// The accesses since the last call, on a flat bus
+    pub fn take_accesses(&self) -> Vec<BusAccess> {
+        self.accesses
+            .as_ref()
+            .map(|accesses| std::mem::take(&mut *accesses.lock().unwrap()))
+            .unwrap_or_default()
+    }
+    fn record(&self, addr: u16, value: u8, write: bool) {
+        if let Some(accesses) = &self.accesses {
+            accesses
+                .lock()
+                .unwrap()
+                .push(BusAccess { addr, value, write });
+        }
+    }
+    /// Times the parts of every tick and counts the instructions
+    pub fn with_profile(mut self) -> Self {
+        self.profile = Some(Profile::default());
         self
     }
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+    /// Called by the cpu for every instruction it executed
+    pub fn count_instruction(&mut self) {
+        if let Some(profile) = &mut self.profile {
+            profile.instructions += 1;
+        }
+    }
+    pub fn with_video(mut self, video: impl VideoSink + 'static) -> Self {
+        self.video = Box::new(video);
+        self
+    }
+    pub fn with_audio_out(mut self, audio: impl AudioSink + 'static) -> Self {
+        self.audio_out = Box::new(audio);
+        self
+    }
+    /// The apu leaves out muted channels and reports their levels to `channels`
+    pub fn with_channels(mut self, channels: Channels) -> Self {
+        self.audio = Audio::new(channels);
+        self
+    }
+    pub fn with_input(mut self, input: impl InputSource + 'static) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+    /// Has to come before `with_ram_pattern`, which draws random content from it
+    pub fn with_host(mut self, host: Host) -> Self {
+        self.host = host;
+        self
+    }
+    pub fn with_ram_pattern(mut self, pattern: RamPattern) -> Self {
+        self.ram = Arc::new(RwLock::new(Ram::with_pattern(pattern, &mut self.host)));
+        self.ram_pattern = pattern;
+        self
+    }
+    pub fn with_link_port(mut self, link_port: LinkPort) -> Self {
+        self.link_port = link_port;
+        self
+    }
+    pub fn with_status(mut self, status: Status) -> Self {
+        self.status = status;
+        self
+    }
+    /// The layers the debugger hides or tints
+    pub fn with_layers(mut self, layers: Layers) -> Self {
+        self.ppu = self.ppu.with_layers(layers.clone());
+        self.layers = layers;
+        self
+    }
+    /// Where SGB games send their border to. Has to come before `with_cartridge`
+    pub fn with_sgb_border(mut self, border: SgbBorder) -> Self {
+        self.sgb_border = border;
+        self
+    }
+    /// Memory accesses are checked against its watchpoints
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = debugger;
+        self
+    }
+    /// Has to come before `with_cartridge`
+    pub fn with_rtc(mut self, rtc: Arc<Mutex<Rtc>>) -> Self {
+        self.rtc = rtc;
+        self
+    }
+    /// Maps `boot_rom` at the start until it disables itself.
+    /// Has to come after `with_ram_pattern`
+    pub fn with_boot_rom(mut self, boot_rom: BootRom) -> Self {
+        ram::lock_mut(&self.ram).map(BOOT_ROM.start, boot_rom.bytes());
+        self.boot_rom = Some(boot_rom);
+        self
+    }
+    pub fn has_boot_rom(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+    /// Unmaps the boot rom and sets the io registers to what it leaves behind
+    pub fn skip_boot_rom(&mut self) {
+        self.write_mem(BOOT_ROM_DISABLE, 1);
+        for (addr, value) in POST_BOOT_IO {
+            self.write_mem(addr, value);
+        }
+    }
+    /// Maps the first two rom banks, the boot rom stays on top until it disables itself.
+    /// Has to come after `with_ram_pattern` and `with_status`
+    pub fn with_cartridge(mut self, cartridge: Cartridge) -> Self {
+        self.insert(cartridge);
+        self
+    }
+    fn insert(&mut self, mut cartridge: Cartridge) {
+        cartridge.set_rtc(self.rtc.clone());
+        let mut ram = ram::lock_mut(&self.ram);
+        map_banks(&mut ram, &mut cartridge, None);
+        self.cgb = cartridge.header().cgb.then(|| Cgb::new(&mut ram));
+        // CGB games use the colors of the CGB
+        self.sgb = (cartridge.header().sgb && self.cgb.is_none())
+            .then(|| Sgb::new(self.sgb_border.clone()));
+        drop(ram);
+        self.status.set_rom_bank(cartridge.mapping().romx as u16);
+        self.status.set_rom_loaded(true);
+        self.status.set_rom_crc(cartridge.crc());
+        self.cartridge = Some(cartridge);
+    }
+    /// Powers back on with `cartridge` inserted. The memory is refilled in place,
+    /// so the handles from `ram` stay valid.
+    pub fn reset(&mut self, cartridge: Cartridge) {
+        // the new cartridge reads the save file
+        self.save();
+        let mut ram = Ram::with_pattern(self.ram_pattern, &mut self.host);
+        if let Some(boot_rom) = &self.boot_rom {
+            ram.map(BOOT_ROM.start, boot_rom.bytes());
+        }
+        *ram::lock_mut(&self.ram) = ram;
+        self.joypad = Joypad::default();
+        self.timer = Timer::default();
+        self.ppu = Ppu::default().with_layers(self.layers.clone());
+        self.audio.reset();
+        self.insert(cartridge);
+    }
+    /// Powers off and back on with the same cartridge, does nothing without one
+    pub fn power_cycle(&mut self) {
+        self.save();
+        if let Some(cartridge) = self.cartridge.take() {
+            self.reset(cartridge);
+        }
+    }
+    /// Writes the battery backed ram and the clock to the save file
+    pub fn save(&mut self) {
+        let Some(cartridge) = &mut self.cartridge else {
+            return;
+        };
+        if let Some(bank) = cartridge.mapping().ram {
+            cartridge
+                .ram_bank_mut(bank)
+                .copy_from_slice(&ram::lock(&self.ram).bytes()[SRAM]);
+        }
+        self.status.take_sram_dirty();
+        if let Err(e) = cartridge.save() {
+            log::error!("could not write the save: {e}");
+        }
+    }
+    /// Writes the save and backs it up, before a save state replaces the ram
+    pub fn backup_save(&mut self) {
+        self.save();
+        if let Some(Err(e)) = self.cartridge.as_ref().map(Cartridge::backup_save) {
+            log::warn!("could not back up the save: {e}");
+        }
+    }
+    /// When the battery save was last written
+    pub fn save_modified(&self) -> Option<SystemTime> {
+        self.cartridge.as_ref()?.save_modified()
+    }
+    /// Writes the save if the game changed the ram since the last one
+    pub fn save_if_dirty(&mut self) {
+        if self.status.sram_dirty() {
+            self.save();
+        }
+    }
+    /// Restarts the random numbers for the frame the cpu starts
+    pub fn begin_frame(&mut self) {
+        let frame = self.status.frame() as u64;
+        self.host.begin_frame(frame);
+    }
+    pub fn set_skip_frames(&mut self, skip: bool) {
+        self.skip_frames = skip;
+    }
+    pub fn skip_frames(&self) -> bool {
+        self.skip_frames
+    }
+    pub fn set_ahead(&mut self, ahead: bool) {
+        self.ahead = ahead;
+    }
+    pub fn is_ahead(&self) -> bool {
+        self.ahead
+    }
+    /// Plays the sound slower by `stretch`, while the emulation runs behind
+    pub fn set_audio_stretch(&mut self, stretch: f32) {
+        self.audio.set_stretch(stretch);
+    }
+    /// The cartridge, the components and the whole address space
+    pub fn save_state(&self, w: &mut Writer) {
+        let cartridge = self
+            .cartridge
+            .as_ref()
+            .map_or(Vec::new(), Cartridge::save_state);
+        w.block(&cartridge);
+        self.timer.save_state(w);
+        self.ppu.save_state(w);
+        self.audio.save_state(w);
+        let mut cgb = Writer::default();
+        if let Some(state) = &self.cgb {
+            state.save_state(&mut cgb);
+        }
+        w.block(&cgb.finish());
+        let mut sgb = Writer::default();
+        if let Some(state) = &self.sgb {
+            state.save_state(&mut sgb);
+        }
+        w.block(&sgb.finish());
+        w.bytes(ram::lock(&self.ram).bytes());
+    }
+    pub fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        let cartridge_state = r.block()?;
+        self.timer.load_state(r)?;
+        self.ppu.load_state(r)?;
+        self.audio.load_state(r)?;
+        let cgb_state = r.block()?;
+        if let Some(cgb) = &mut self.cgb {
+            cgb.load_state(&mut Reader::new(cgb_state))?;
+        }
+        let sgb_state = r.block()?;
+        if let Some(sgb) = &mut self.sgb {
+            sgb.load_state(&mut Reader::new(sgb_state))?;
+            self.joypad.set_player(sgb.player());
+        }
+        let ram = r.bytes(ram::lock(&self.ram).bytes().len())?;
+        ram::lock_mut(&self.ram).map(0, ram);
+        if let Some(cartridge) = &mut self.cartridge {
+            cartridge.load_state(cartridge_state)?;
+            self.status.set_rom_bank(cartridge.mapping().romx as u16);
+        }
+        Ok(())
+    }
+    /// The banks the cartridge maps, `None` without a cartridge
+    pub fn mapping(&self) -> Option<Mapping> {
+        self.cartridge.as_ref().map(Cartridge::mapping)
+    }
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+    /// Shared handle to the memory, for the debugger
+    pub fn ram(&self) -> Arc<RwLock<Ram>> {
+        self.ram.clone()
+    }
     pub fn fetch(&self, index: u16) -> u8 {
-        self.ram.read().unwrap()[index]
+        if self.accesses.is_some() {
+            let value = ram::lock(&self.ram)[index];
+            self.record(index, value, false);
+            return value;
+        }
+        let value = self.patched(index, read(&ram::lock(&self.ram), index));
+        if self.debugger.is_active() {
+            self.debugger
+                .memory_access(index, false, value, self.status.pc());
+        }
+        if self.memory_hooks.is_active() {
+            self.memory_hooks.access(index, value, false);
+        }
+        value
     }
     pub fn write_mem(&mut self, addr: u16, content: u8) {
-        self.ram.write().unwrap()[addr] = content;
+        self.record(addr, content, true);
+        if self.debugger.is_active() {
+            self.debugger
+                .memory_access(addr, true, content, self.status.pc());
+        }
+        if self.memory_hooks.is_active() {
+            self.memory_hooks.access(addr, content, true);
+        }
+        self.poke(addr, content);
+    }
+    /// Writes like the cpu does, without checking the watchpoints
+    pub fn poke(&mut self, addr: u16, content: u8) {
+        if self.accesses.is_some() {
+            ram::lock_mut(&self.ram)[addr] = content;
+            return;
+        }
+        let addr = mirror(addr);
+        if UNUSABLE.contains(&(addr as usize)) {
+            return;
+        }
+        let mut ram = ram::lock_mut(&self.ram);
+        if addr < ROM_END {
+            if let Some(cartridge) = &mut self.cartridge {
+                let before = cartridge.mapping();
+                cartridge.write(addr, content);
+                let after = cartridge.mapping();
+                if after != before {
+                    map_banks(&mut ram, cartridge, Some(before));
+                    self.status.set_rom_bank(after.romx as u16);
+                }
+                // games disable the ram when they are done saving
+                if before.ram.is_some() && after.ram.is_none() && self.status.take_sram_dirty() {
+                    if let Err(e) = cartridge.save() {
+                        log::error!("could not write the save: {e}");
+                    }
+                }
+            }
+            return;
+        }
+        let before = ram[addr];
+        ram[addr] = content;
+        match addr {
+            P1 => {
+                if let Some(sgb) = &mut self.sgb {
+                    sgb.write(&ram, content);
+                    self.joypad.set_player(sgb.player());
+                }
+                self.joypad.write(&mut ram, before);
+            }
+            SC => self.link_port.write_control(&mut ram),
+            DIV => self.timer.reset_divider(&mut ram),
+            DMA => {
+                let source = (content as u16) << 8;
+                for (i, oam) in OAM.enumerate() {
+                    ram[oam as u16] = read(&ram, source.wrapping_add(i as u16));
+                }
+            }
+            BOOT_ROM_DISABLE if content != 0 => {
+                if let Some(cartridge) = &self.cartridge {
+                    let bank = cartridge.mapping().rom0;
+                    ram.map(BOOT_ROM.start, &cartridge.bank(bank)[BOOT_ROM]);
+                }
+            }
+            NR10..=NR52 => self.audio.write(&mut ram, addr, content, before),
+            // set once at power on
+            KEY0 => ram[KEY0] = before,
+            // only the switch can be prepared, the speed changes with STOP
+            KEY1 if self.cgb.is_some() => ram[KEY1] = before & 0x80 | content & 0x01,
+            VBK | BCPS..=OCPD | SVBK => {
+                if let Some(cgb) = &mut self.cgb {
+                    cgb.write(&mut ram, addr, content);
+                }
+            }
+            STAT => self.ppu.write_stat(&mut ram, content, before),
+            // read only, the ppu counts the lines
+            LY => ram[LY] = before,
+            _ if SRAM.contains(&(addr as usize)) => {
+                let Some(cartridge) = &mut self.cartridge else {
+                    return;
+                };
+                let before_mapping = cartridge.mapping();
+                if before_mapping.rtc.is_some() {
+                    cartridge.write_rtc(content);
+                    map_banks(&mut ram, cartridge, None);
+                    self.status.mark_sram_dirty();
+                } else if before_mapping.ram.is_none() {
+                    ram[addr] = before;
+                } else {
+                    // smaller ram repeats, only the low address lines are connected
+                    let size = cartridge.ram_size();
+                    if size < RAM_BANK_SIZE {
+                        let offset = (addr as usize - SRAM.start) % size;
+                        for mirror in (SRAM.start + offset..SRAM.end).step_by(size) {
+                            ram[mirror as u16] = content;
+                        }
+                    }
+                    self.status.mark_sram_dirty();
+                }
+            }
+            _ => {}
+        }
+    }
+    /// Advances the components which run alongside the cpu
+    pub fn tick(&mut self, cycles: usize) {
+        let held = self.buttons();
+        let mut ram = ram::lock_mut(&self.ram);
+        if let Some(profile) = &mut self.profile {
+            profile.start();
+        }
+        self.joypad.update(&mut ram, held);
+        self.timer.tick(&mut ram, cycles);
+        if let Some(profile) = &mut self.profile {
+            profile.lap(Part::Timer);
+        }
+        // a device would see the bytes of the rolled back frames twice
+        if !self.ahead {
+            self.link_port.poll(&mut ram, cycles);
+        }
+        if let Some(profile) = &mut self.profile {
+            profile.lap(Part::LinkPort);
+        }
+        let video: &mut dyn VideoSink = if self.skip_frames {
+            &mut Null
+        } else {
+            &mut *self.video
+        };
+        let mut sgb_video;
+        let video: &mut dyn VideoSink = match &self.sgb {
+            Some(sgb) => {
+                sgb_video = SgbVideo { sgb, video };
+                &mut sgb_video
+            }
+            None => video,
+        };
+        // in double speed the cpu and the timer run twice as fast as the rest
+        let cycles = if cgb::double_speed(&ram) {
+            cycles / 2
+        } else {
+            cycles
+        };
+        self.ppu.tick(&mut ram, cycles, video, self.cgb.as_ref());
+        if let Some(profile) = &mut self.profile {
+            profile.lap(Part::Ppu);
+        }
+        let audio: &mut dyn AudioSink = if self.ahead {
+            &mut Null
+        } else {
+            &mut *self.audio_out
+        };
+        self.audio.tick(&mut ram, cycles, audio);
+        if let Some(profile) = &mut self.profile {
+            profile.lap(Part::Apu);
+        }
+        self.status.set_ly(ram[LY]);
     }
-    pub fn send_gpu_signal(&self, signal: DrawSignal) {
-        if let Some(sender) = &self.gpu_sender {
-            // println!("send {signal:?} to gpu");
-            let _ = sender.send(signal);
+    /// LY and the cycles into the line
+    pub fn ppu_position(&self) -> (u8, usize) {
+        (ram::lock(&self.ram)[LY], self.ppu.dot())
+    }
+    /// Called when the debugger paused in the middle of a frame. Sends the frame as far
+    /// as it is drawn and publishes where the ppu is.
+    pub fn debug_pause(&mut self) {
+        let (ly, dot) = self.ppu_position();
+        self.status.set_ly(ly);
+        self.status.set_dot(dot as u16);
+        match &self.sgb {
+            Some(sgb) => self.ppu.send_frame(
+                &mut SgbVideo {
+                    sgb,
+                    video: &mut *self.video,
+                },
+                false,
+            ),
+            None => self.ppu.send_frame(&mut *self.video, self.cgb.is_some()),
         }
     }
+    /// Switches between normal and double speed if the game prepared it, called by STOP
+    pub fn switch_speed(&mut self) -> bool {
+        self.cgb.is_some() && cgb::switch_speed(&mut ram::lock_mut(&self.ram))
+    }
+    /// Whether an SGB game runs, which colors its frames itself
+    pub fn is_sgb(&self) -> bool {
+        self.sgb.is_some()
+    }
+    /// Whether a CGB game runs
+    pub fn is_cgb(&self) -> bool {
+        self.cgb.is_some()
+    }
+    pub fn double_speed(&self) -> bool {
+        self.cgb.is_some() && cgb::double_speed(&ram::lock(&self.ram))
+    }
+    /// Resets DIV like a write to it does
+    pub fn reset_divider(&mut self) {
+        self.timer.reset_divider(&mut ram::lock_mut(&self.ram));
+    }
+    /// Reads the buttons without advancing the clock, returns whether one on a selected
+    /// line is held. Ends STOP.
+    pub fn poll_buttons(&mut self) -> bool {
+        let held = self.buttons();
+        let mut ram = ram::lock_mut(&self.ram);
+        self.joypad.update(&mut ram, held);
+        ram[P1] & 0x0F != 0x0F
+    }
+    /// Sets the bit in IF, the cpu handles it between instructions
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        interrupt::request(&mut ram::lock_mut(&self.ram), interrupt);
+    }
+    pub fn pending_interrupt(&self) -> Option<Interrupt> {
+        interrupt::pending(&ram::lock(&self.ram))
+    }
+    pub fn acknowledge_interrupt(&mut self, interrupt: Interrupt) {
+        interrupt::acknowledge(&mut ram::lock_mut(&self.ram), interrupt);
+    }
+    pub fn send_gpu_signal(&mut self, signal: DrawSignal) {
+        self.video.send(signal);
+    }
+    /// Instruction fetches do not trigger read watchpoints
     pub fn fetch_op(&self, index: u16) -> OpCode {
-        OpCode(self.fetch(index))
+        if self.accesses.is_some() {
+            let value = ram::lock(&self.ram)[index];
+            self.record(index, value, false);
+            return OpCode(value);
+        }
+        OpCode(self.patched(index, read(&ram::lock(&self.ram), index)))
+    }
+    /// Replaces the randomness and the clock, movies run on a seeded one
+    pub fn set_host(&mut self, host: Host) {
+        self.host = host;
+    }
+    pub fn host_mut(&mut self) -> &mut Host {
+        &mut self.host
+    }
+    /// The buttons of the input source, also while they are overridden
+    pub fn input_buttons(&mut self) -> Buttons {
+        self.input.buttons()
+    }
+    pub fn set_memory_hooks(&mut self, hooks: MemoryHooks) {
+        self.memory_hooks = hooks;
+    }
+    /// Holds `buttons` instead of the ones of the input source, `None` gives them back
+    pub fn set_input_override(&mut self, buttons: Option<Buttons>) {
+        self.input_override = buttons;
+    }
+    /// The buttons the game sees, the override or the ones of the input source
+    pub fn buttons(&mut self) -> Buttons {
+        match self.input_override {
+            Some(buttons) => buttons,
+            None => self.input.buttons(),
+        }
+    }
+    /// Only the Game Genie codes are kept, the others do not patch the rom
+    pub fn set_rom_patches(&mut self, codes: &[CheatCode]) {
+        self.rom_patches = codes
+            .iter()
+            .filter(|code| matches!(code, CheatCode::GameGenie { .. }))
+            .copied()
+            .collect();
+    }
+    /// The rom byte with the Game Genie codes applied
+    fn patched(&self, addr: u16, value: u8) -> u8 {
+        if addr >= ROM_END {
+            return value;
+        }
+        self.rom_patches
+            .iter()
+            .find_map(|code| match *code {
+                CheatCode::GameGenie {
+                    addr: patched,
+                    value: new,
+                    compare,
+                } if patched == addr && compare.unwrap_or(value) == value => Some(new),
+                _ => None,
+            })
+            .unwrap_or(value)
+    }
+}
+/// The address echo ram mirrors, any other address stays
+fn mirror(addr: u16) -> u16 {
+    if ECHO.contains(&(addr as usize)) {
+        addr - (ECHO.start - WRAM.start) as u16
+    } else {
+        addr
+    }
+}
+/// What the cpu reads at `addr`. The rom banks and the cartridge ram are mapped
+/// into `ram` by `map_banks`.
+pub fn read(ram: &Ram, addr: u16) -> u8 {
+    let addr = mirror(addr);
+    match addr as usize {
+        a if UNUSABLE.contains(&a) => 0xFF,
+        a if IO.contains(&a) => {
+            let unused = match cgb::UNUSED_BITS.iter().find(|(reg, _)| *reg == addr) {
+                Some((_, bits)) if cgb::is_cgb(ram) => *bits,
+                _ => IO_UNUSED_BITS[a - IO.start],
+            };
+            ram[addr] | unused
+        }
+        _ => ram[addr],
+    }
+}
+/// Copies the banks the cartridge selects into the address space.
+/// The ram bank of the `previous` mapping is written back first, it may have changed.
+fn map_banks(ram: &mut Ram, cartridge: &mut Cartridge, previous: Option<Mapping>) {
+    if let Some(bank) = previous.and_then(|previous| previous.ram) {
+        cartridge
+            .ram_bank_mut(bank)
+            .copy_from_slice(&ram.bytes()[SRAM]);
+    }
+    let mapping = cartridge.mapping();
+    // the boot rom stays on top until it disables itself
+    let start = if ram[BOOT_ROM_DISABLE] == 0 {
+        BOOT_ROM.end
+    } else {
+        0
+    };
+    ram.map(start, &cartridge.bank(mapping.rom0)[start..]);
+    ram.map(ROM_BANK_SIZE, cartridge.bank(mapping.romx));
+    match (mapping.ram, mapping.rtc) {
+        (Some(bank), _) => ram.map(SRAM.start, cartridge.ram_bank(bank)),
+        // a clock register shows up in the whole area
+        (None, Some((_, value))) => ram.map(SRAM.start, &[value; RAM_BANK_SIZE]),
+        // disabled ram reads as open bus
+        (None, None) => ram.map(SRAM.start, &[0xFF; RAM_BANK_SIZE]),
     }
 }
 #[derive(Clone, Debug, Copy)]
@@ -38,9 +711,70 @@ pub struct OpCode(pub u8);
 impl Default for Bus {
     fn default() -> Bus {
         Bus {
-            ram: RwLock::new(Ram::default()),
-            gpu_sender: None,
-            _audio: RwLock::new(Audio),
+            ram: Arc::new(RwLock::new(Ram::default())),
+            video: Box::new(Null),
+            skip_frames: false,
+            ahead: false,
+            audio_out: Box::new(Null),
+            input: Box::new(Null),
+            link_port: LinkPort::default(),
+            joypad: Joypad::default(),
+            timer: Timer::default(),
+            ppu: Ppu::default(),
+            layers: Layers::default(),
+            status: Status::default(),
+            host: Host::default(),
+            ram_pattern: RamPattern::default(),
+            cartridge: None,
+            boot_rom: None,
+            cgb: None,
+            sgb: None,
+            sgb_border: SgbBorder::default(),
+            rtc: Arc::default(),
+            debugger: Debugger::default(),
+            audio: Audio::default(),
+            accesses: None,
+            rom_patches: Vec::new(),
+            profile: None,
+            memory_hooks: MemoryHooks::default(),
+            input_override: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interrupt::IF, ram::OAM};
+
+    use super::*;
+
+    /// Echo ram, the unusable area, unused io bits and OAM DMA
+    #[test]
+    fn memory_map() -> Result<(), String> {
+        let mut bus = Bus::default();
+        bus.write_mem(0xE123, 0x42);
+        if bus.fetch(0xC123) != 0x42 || bus.fetch(0xE123) != 0x42 {
+            return Err("echo ram does not mirror wram".to_string());
+        }
+        bus.write_mem(0xFEA0, 0x00);
+        if bus.fetch(0xFEA0) != 0xFF {
+            return Err("the unusable area does not read 0xFF".to_string());
+        }
+        bus.write_mem(IF, 0x01);
+        let (flags, unmapped) = (bus.fetch(IF), bus.fetch(0xFF03));
+        if flags != 0xE1 || unmapped != 0xFF {
+            return Err(format!(
+                "IF reads {flags:#04x} and 0xFF03 {unmapped:#04x}, expected 0xe1 and 0xff"
+            ));
+        }
+        for i in 0..OAM.len() as u16 {
+            bus.write_mem(0xC100 + i, i as u8);
+        }
+        bus.write_mem(DMA, 0xC1);
+        let oam = OAM.start as u16;
+        if bus.fetch(oam) != 0 || bus.fetch(oam + 0x9F) != 0x9F {
+            return Err("OAM DMA did not copy 0xC100 - 0xC19F".to_string());
         }
+        Ok(())
     }
 }
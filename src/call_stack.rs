@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::{instruction::Instruction, interrupt::Interrupt};
+
+/// Frames kept at most, games which never return push calls forever
+pub const MAX_DEPTH: usize = 256;
+
+/// How the cpu got into a call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    Rst,
+    Interrupt(Interrupt),
+}
+impl fmt::Display for CallKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallKind::Call => write!(f, "call"),
+            CallKind::Rst => write!(f, "rst"),
+            CallKind::Interrupt(interrupt) => write!(f, "{interrupt:?}"),
+        }
+    }
+}
+
+/// What an instruction does to the call stack when it jumps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackEffect {
+    Enter(CallKind),
+    Return,
+}
+impl StackEffect {
+    pub fn of(instruction: &Instruction) -> Option<StackEffect> {
+        match instruction {
+            Instruction::Call | Instruction::CallIfFlag => Some(StackEffect::Enter(CallKind::Call)),
+            Instruction::CallN => Some(StackEffect::Enter(CallKind::Rst)),
+            Instruction::Return | Instruction::ReturnIfFlag | Instruction::ReturnInterrupt => {
+                Some(StackEffect::Return)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// One call which has not returned yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    pub kind: CallKind,
+    /// The call instruction, or the pc the interrupt came in front of
+    pub site: u16,
+    pub target: u16,
+    /// Where the return address was pushed to
+    pub sp: u16,
+}
+impl CallFrame {
+    pub fn return_address(&self) -> u16 {
+        match self.kind {
+            CallKind::Call => self.site.wrapping_add(3),
+            CallKind::Rst => self.site.wrapping_add(1),
+            CallKind::Interrupt(_) => self.site,
+        }
+    }
+}
+impl fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "${:04X} {} from ${:04X}, returns to ${:04X}",
+            self.target,
+            self.kind,
+            self.site,
+            self.return_address()
+        )
+    }
+}
+
+/// A shadow of the calls on the stack of the game, innermost last.
+/// Games which pop return addresses themselves or move SP leave frames behind,
+/// they are dropped as soon as a return goes past them.
+#[derive(Clone, Debug, Default)]
+pub struct CallStack {
+    frames: Vec<CallFrame>,
+}
+impl CallStack {
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+    pub fn enter(&mut self, frame: CallFrame) {
+        if self.frames.len() == MAX_DEPTH {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+    /// Drops the frames a return left, `sp` is after the return
+    pub fn returned(&mut self, sp: u16) {
+        while self.frames.last().is_some_and(|frame| frame.sp < sp) {
+            self.frames.pop();
+        }
+    }
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use crate::vram::IndexedImage;
+
+/// Cartridge type of the Game Boy Camera in the header
+pub const POCKET_CAMERA: u8 = 0xFC;
+/// Photos in the battery backed ram
+pub const PHOTO_SLOTS: usize = 30;
+pub const PHOTO_WIDTH: usize = 128;
+pub const PHOTO_HEIGHT: usize = 112;
+/// The first photo starts in ram bank 1, every photo takes 0x1000 bytes
+const PHOTOS_START: usize = 0x2000;
+const PHOTO_SIZE: usize = 0x1000;
+const TILES_PER_ROW: usize = PHOTO_WIDTH / 8;
+/// The camera shows index 0 as white and 3 as black
+pub const GRAYSCALE: [[u8; 3]; 4] = [[0xFF; 3], [0xAA; 3], [0x55; 3], [0x00; 3]];
+/// 4x4 Bayer matrix for ordered dithering
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+fn slot_range(sav: &[u8], slot: usize) -> io::Result<std::ops::Range<usize>> {
+    let start = PHOTOS_START + slot * PHOTO_SIZE;
+    let end = start + PHOTO_WIDTH * PHOTO_HEIGHT / 4;
+    if slot >= PHOTO_SLOTS || sav.len() < end {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no photo slot {} in a {} byte save", slot + 1, sav.len()),
+        ));
+    }
+    Ok(start..end)
+}
+/// Decodes the photo in `slot`, stored as 16x14 tiles row by row
+pub fn photo(sav: &[u8], slot: usize) -> io::Result<IndexedImage> {
+    let data = &sav[slot_range(sav, slot)?];
+    let mut image = IndexedImage::new(PHOTO_WIDTH, PHOTO_HEIGHT);
+    for (tile, bytes) in data.chunks(16).enumerate() {
+        let (tx, ty) = (tile % TILES_PER_ROW * 8, tile / TILES_PER_ROW * 8);
+        for row in 0..8 {
+            let (low, high) = (bytes[row * 2], bytes[row * 2 + 1]);
+            for col in 0..8 {
+                let bit = 7 - col;
+                let index = (((high >> bit) & 1) << 1) | ((low >> bit) & 1);
+                image.pixels[(ty + row) * PHOTO_WIDTH + tx + col] = index;
+            }
+        }
+    }
+    Ok(image)
+}
+/// Writes every photo of the save as `photo-<slot>.png`, returns how many were written
+pub fn export_photos(sav: &[u8], dir: &Path) -> io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let mut written = 0;
+    for slot in 0..PHOTO_SLOTS {
+        let Ok(image) = photo(sav, slot) else {
+            break;
+        };
+        image.save_png(&dir.join(format!("photo-{:02}.png", slot + 1)), &GRAYSCALE)?;
+        written += 1;
+    }
+    Ok(written)
+}
+/// Replaces the photo in `slot` with the png, scaled to 128x112 and dithered to 4 shades.
+/// The game only lists the slot if it is not marked as deleted in its own index.
+pub fn import_photo(sav: &mut [u8], slot: usize, png: &Path) -> io::Result<()> {
+    let range = slot_range(sav, slot)?;
+    let (luma, width, height) = read_luma(png)?;
+    let data = &mut sav[range];
+    data.fill(0);
+    for y in 0..PHOTO_HEIGHT {
+        for x in 0..PHOTO_WIDTH {
+            let source = luma[y * height / PHOTO_HEIGHT * width + x * width / PHOTO_WIDTH];
+            // darkness 0..=3 * 16, the threshold decides between the two nearest shades
+            let level = (255 - source as usize) * 3 * 16 / 255;
+            let threshold = BAYER[y % 4][x % 4] as usize;
+            let index = (level / 16 + usize::from(level % 16 > threshold)).min(3) as u8;
+            let tile = y / 8 * TILES_PER_ROW + x / 8;
+            let addr = tile * 16 + y % 8 * 2;
+            let bit = 7 - x % 8;
+            data[addr] |= (index & 1) << bit;
+            data[addr + 1] |= (index >> 1) << bit;
+        }
+    }
+    Ok(())
+}
+/// The brightness of every pixel of a png, with its width and height
+fn read_luma(path: &Path) -> io::Result<(Vec<u8>, usize, usize)> {
+    let invalid = |e: png::DecodingError| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    // 8 bit samples after the transformations
+    let (color_type, _) = reader.output_color_type();
+    let (width, height) = (reader.info().width, reader.info().height);
+    let mut buf = vec![0; width as usize * height as usize * color_type.samples()];
+    let info = reader.next_frame(&mut buf).map_err(invalid)?;
+    let channels = info.color_type.samples();
+    let luma = buf[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| match pixel {
+            [r, g, b, ..] => ((*r as u32 * 299 + *g as u32 * 587 + *b as u32 * 114) / 1000) as u8,
+            [gray, ..] => *gray,
+            [] => 0,
+        })
+        .collect();
+    Ok((luma, info.width as usize, info.height as usize))
+}
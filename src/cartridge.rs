@@ -0,0 +1,476 @@
+use std::{
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{
+    backup,
+    mbc::{self, Mapping, Mbc, RAM_BANK_SIZE},
+    patch,
+    rtc::Rtc,
+    sram::SaveLayout,
+    state::StateError,
+};
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+/// Where the boot rom is mapped over the cartridge until it disables itself
+pub const BOOT_ROM: Range<usize> = 0x0000..0x0100;
+const HEADER_END: usize = 0x0150;
+const TITLE: Range<usize> = 0x0134..0x0144;
+/// Bit 7 marks games which use the CGB functions, the title ends before it
+pub const CGB_FLAG: usize = 0x0143;
+/// 0x03 marks games which use the SGB functions
+pub const SGB_FLAG: usize = 0x0146;
+/// Games for the SGB have to set it to 0x33 as well
+pub const OLD_LICENSEE: usize = 0x014B;
+pub const CARTRIDGE_TYPE: usize = 0x0147;
+const ROM_SIZE: usize = 0x0148;
+const RAM_SIZE: usize = 0x0149;
+const HEADER_CHECKSUM: usize = 0x014D;
+const GLOBAL_CHECKSUM: usize = 0x014E;
+/// Cartridge types with a battery which keeps the ram
+const BATTERY: [u8; 11] = [
+    0x03, 0x06, 0x09, 0x0D, 0x0F, 0x10, 0x13, 0x1B, 0x1E, 0x22, 0xFF,
+];
+/// Backups of the save file kept from previous sessions
+const SAVE_BACKUPS: usize = 5;
+
+/// The cartridge header at 0x0100..0x0150
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub title: String,
+    pub cartridge_type: u8,
+    /// In bytes
+    pub rom_size: usize,
+    /// In bytes
+    pub ram_size: usize,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+    /// Runs in CGB mode
+    pub cgb: bool,
+    /// Sends palettes and a border to the SGB
+    pub sgb: bool,
+}
+impl Header {
+    pub fn has_battery(&self) -> bool {
+        BATTERY.contains(&self.cartridge_type)
+    }
+    pub fn parse(rom: &[u8]) -> io::Result<Header> {
+        if rom.len() < HEADER_END {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file too small for a cartridge header",
+            ));
+        }
+        let cgb = rom[CGB_FLAG] & 0x80 != 0;
+        let title_end = if cgb { CGB_FLAG } else { TITLE.end };
+        let title = rom[TITLE.start..title_end]
+            .iter()
+            .take_while(|byte| **byte != 0)
+            .map(|byte| char::from(*byte))
+            .collect();
+        let ram_size = match rom[RAM_SIZE] {
+            0x01 => 0x800,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            _ => 0,
+        };
+        Ok(Header {
+            title,
+            cartridge_type: rom[CARTRIDGE_TYPE],
+            rom_size: 0x8000 << rom[ROM_SIZE].min(8),
+            ram_size,
+            header_checksum: rom[HEADER_CHECKSUM],
+            global_checksum: u16::from_be_bytes([rom[GLOBAL_CHECKSUM], rom[GLOBAL_CHECKSUM + 1]]),
+            cgb,
+            sgb: rom[SGB_FLAG] == 0x03 && rom[OLD_LICENSEE] == 0x33,
+        })
+    }
+}
+/// The checksum over the header which the boot rom verifies
+fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE.start..HEADER_CHECKSUM]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1))
+}
+/// The sum of all bytes except the checksum itself, no hardware checks it
+fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(i, _)| !(GLOBAL_CHECKSUM..GLOBAL_CHECKSUM + 2).contains(i))
+        .fold(0u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16))
+}
+
+/// A rom image with its header, the external ram and the bank controller
+/// which decides the banks at 0x0000, 0x4000 and 0xA000
+pub struct Cartridge {
+    rom: Vec<u8>,
+    header: Header,
+    /// Of the file, before it is padded to whole banks
+    crc: u32,
+    mbc: Box<dyn Mbc>,
+    /// All ram banks, the mapped one is only written back when it is switched
+    ram: Vec<u8>,
+    /// The `.sav` file next to the rom, if the ram has a battery
+    save_path: Option<PathBuf>,
+}
+impl Cartridge {
+    /// Reads the rom and applies a `.ips` or `.bps` patch next to it
+    pub fn load(path: &Path) -> io::Result<Cartridge> {
+        Cartridge::load_patched(path, None)
+    }
+    /// Reads the rom and applies `patch`, without one the patch next to it
+    pub fn load_patched(path: &Path, patch: Option<&Path>) -> io::Result<Cartridge> {
+        let mut rom = fs::read(path)?;
+        let patch = patch
+            .map(Path::to_path_buf)
+            .or_else(|| patch::find_patch(path));
+        if let Some(patch) = patch {
+            match patch::apply_file(&rom, &patch) {
+                Ok(patched) => {
+                    log::info!("applied {}", patch.display());
+                    rom = patched;
+                }
+                Err(e) => log::warn!("could not apply {}: {e}", patch.display()),
+            }
+        }
+        let mut cartridge = Cartridge::from_bytes(rom)?;
+        if cartridge.header.has_battery() {
+            let save_path = path.with_extension("sav");
+            cartridge.load_save(&save_path);
+            cartridge.save_path = Some(save_path);
+        }
+        Ok(cartridge)
+    }
+    pub fn from_bytes(mut rom: Vec<u8>) -> io::Result<Cartridge> {
+        let header = Header::parse(&rom)?;
+        if header_checksum(&rom) != header.header_checksum {
+            log::warn!("header checksum mismatch, real hardware would not boot");
+        }
+        if global_checksum(&rom) != header.global_checksum {
+            log::info!("global checksum mismatch");
+        }
+        if rom.len() != header.rom_size {
+            log::warn!(
+                "rom has {} bytes, the header says {}",
+                rom.len(),
+                header.rom_size
+            );
+        }
+        let crc = patch::crc32(&rom);
+        // at least two whole banks, so every bank can be mapped
+        let banks = rom.len().div_ceil(ROM_BANK_SIZE).max(2);
+        rom.resize(banks * ROM_BANK_SIZE, 0xFF);
+        log::info!(
+            "loaded {:?}, type {:#04x}, {} KiB rom, {} KiB ram",
+            header.title,
+            header.cartridge_type,
+            header.rom_size / 1024,
+            header.ram_size / 1024
+        );
+        // 2 KiB of ram still take a whole bank, mirrored
+        let ram_banks = header.ram_size.div_ceil(RAM_BANK_SIZE);
+        Ok(Cartridge {
+            rom,
+            mbc: mbc::for_type(header.cartridge_type, ram_banks),
+            ram: vec![0xFF; ram_banks * RAM_BANK_SIZE],
+            header,
+            crc,
+            save_path: None,
+        })
+    }
+    /// Reads the ram and the clock, the file is backed up before it is overwritten
+    fn load_save(&mut self, path: &Path) {
+        let save = match fs::read(path) {
+            Ok(save) => save,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                log::error!("could not read {}: {e}", path.display());
+                return;
+            }
+        };
+        let size = self.ram_size();
+        let layout = SaveLayout::detect(save.len(), size);
+        match layout {
+            SaveLayout::Ram { .. } => {}
+            SaveLayout::Padded { .. } => log::info!("converting the padded {}", path.display()),
+            SaveLayout::Short | SaveLayout::Unknown => log::warn!(
+                "{} has {} bytes, the cartridge {}",
+                path.display(),
+                save.len(),
+                size
+            ),
+        }
+        let (ram, footer) = layout.split(&save, size);
+        self.ram[..ram.len()].copy_from_slice(ram);
+        self.mirror_ram();
+        if let (Some(rtc), Some(loaded)) = (self.mbc.rtc(), Rtc::from_footer(footer)) {
+            *rtc.lock().unwrap() = loaded;
+        }
+        if let Err(e) = backup::backup(path, SAVE_BACKUPS) {
+            log::warn!("could not back up {}: {e}", path.display());
+        }
+        log::info!("loaded {}", path.display());
+    }
+    /// Writes the ram to the `.sav` file, with the clock appended like other emulators do.
+    /// The bus has to write back the mapped ram bank first.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.save_path else {
+            return Ok(());
+        };
+        let mut save = self.ram[..self.ram_size()].to_vec();
+        if let Some(rtc) = self.mbc.rtc() {
+            save.extend(rtc.lock().unwrap().to_footer());
+        }
+        fs::write(path, save)
+    }
+    /// When the `.sav` file was last written, `None` without a battery or before the first save
+    pub fn save_modified(&self) -> Option<SystemTime> {
+        self.save_path.as_ref()?.metadata().ok()?.modified().ok()
+    }
+    /// Keeps a copy of the `.sav` file with the backups made when it is loaded
+    pub fn backup_save(&self) -> io::Result<()> {
+        match &self.save_path {
+            Some(path) => backup::backup(path, SAVE_BACKUPS),
+            None => Ok(()),
+        }
+    }
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    /// The ram on the cartridge in bytes, it repeats in the bank if it is smaller
+    pub fn ram_size(&self) -> usize {
+        self.header.ram_size.min(self.ram.len())
+    }
+    /// Copies the start of a bank over its mirrors, if the ram is smaller than a bank
+    fn mirror_ram(&mut self) {
+        let size = self.ram_size();
+        if size > 0 && size < RAM_BANK_SIZE {
+            for mirror in (size..RAM_BANK_SIZE).step_by(size) {
+                self.ram.copy_within(..size, mirror);
+            }
+        }
+    }
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+    pub fn mapping(&self) -> Mapping {
+        self.mbc.mapping()
+    }
+    /// The content of a rom bank, numbers past the end wrap around like the address lines
+    pub fn bank(&self, bank: usize) -> &[u8] {
+        let bank = bank % (self.rom.len() / ROM_BANK_SIZE);
+        &self.rom[bank * ROM_BANK_SIZE..(bank + 1) * ROM_BANK_SIZE]
+    }
+    /// The content of a ram bank
+    pub fn ram_bank(&self, bank: usize) -> &[u8] {
+        &self.ram[bank * RAM_BANK_SIZE..(bank + 1) * RAM_BANK_SIZE]
+    }
+    pub fn ram_bank_mut(&mut self, bank: usize) -> &mut [u8] {
+        &mut self.ram[bank * RAM_BANK_SIZE..(bank + 1) * RAM_BANK_SIZE]
+    }
+    /// A write to 0xA000..0xC000 while a clock register is mapped there
+    pub fn write_rtc(&mut self, value: u8) {
+        self.mbc.write_rtc(value);
+    }
+    /// Shares the clock with the gui, it is only used if the cartridge has one
+    pub fn set_rtc(&mut self, rtc: Arc<Mutex<Rtc>>) {
+        self.mbc.set_rtc(rtc);
+    }
+    /// Writes into the rom area go to the bank controller
+    pub fn write(&mut self, addr: u16, value: u8) {
+        self.mbc.write(addr, value);
+    }
+    /// The controller registers and the ram, for snapshots
+    pub fn save_state(&self) -> Vec<u8> {
+        let mbc = self.mbc.save_state();
+        let mut state = vec![mbc.len() as u8];
+        state.extend(mbc);
+        state.extend_from_slice(&self.ram);
+        state
+    }
+    /// The state of a machine without a cartridge is empty and does not load
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let (&len, rest) = state.split_first().ok_or(StateError::Truncated)?;
+        if rest.len() < len as usize {
+            return Err(StateError::Truncated);
+        }
+        let (mbc, ram) = rest.split_at(len as usize);
+        self.mbc.load_state(mbc);
+        if ram.len() == self.ram.len() {
+            self.ram.copy_from_slice(ram);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{bus::Bus, ram::SRAM};
+
+    use super::*;
+
+    #[test]
+    fn bank_switching() -> Result<(), String> {
+        let mut rom = vec![0; 4 * ROM_BANK_SIZE];
+        for bank in 0..4 {
+            rom[bank * ROM_BANK_SIZE + 0x1000] = bank as u8;
+        }
+        // MBC1 with 4 ram banks
+        rom[CARTRIDGE_TYPE] = 0x01;
+        rom[CARTRIDGE_TYPE + 2] = 0x03;
+        let mut bus =
+            Bus::default().with_cartridge(Cartridge::from_bytes(rom).map_err(|e| e.to_string())?);
+        for bank in [1, 3, 2] {
+            bus.write_mem(0x2000, bank);
+            let mapped = bus.fetch(ROM_BANK_SIZE as u16 + 0x1000);
+            if mapped != bank {
+                return Err(format!("selected bank {bank}, bank {mapped} is mapped"));
+            }
+        }
+        if bus.fetch(0x1000) != 0 {
+            return Err("bank 0 was replaced".to_string());
+        }
+        let sram = SRAM.start as u16;
+        bus.write_mem(sram, 0x42);
+        if bus.fetch(sram) != 0xFF {
+            return Err("the ram was written while disabled".to_string());
+        }
+        // enable the ram, banking mode 1, fill banks 1 and 2
+        bus.write_mem(0x0000, 0x0A);
+        bus.write_mem(0x6000, 1);
+        for bank in [1, 2] {
+            bus.write_mem(0x4000, bank);
+            bus.write_mem(sram, bank);
+        }
+        bus.write_mem(0x4000, 1);
+        let value = bus.fetch(sram);
+        if value != 1 {
+            return Err(format!("ram bank 1 holds {value} after switching back"));
+        }
+        Ok(())
+    }
+    /// 2 KiB of ram take one bank and repeat in it
+    #[test]
+    fn small_ram() -> Result<(), String> {
+        let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+        rom[CARTRIDGE_TYPE] = 0x02;
+        rom[CARTRIDGE_TYPE + 2] = 0x01;
+        let cartridge = Cartridge::from_bytes(rom).map_err(|e| e.to_string())?;
+        if cartridge.header().ram_size != 0x800 || cartridge.ram.len() != RAM_BANK_SIZE {
+            return Err(format!(
+                "{:?} with {} bytes of ram",
+                cartridge.header(),
+                cartridge.ram.len()
+            ));
+        }
+        let mut bus = Bus::default().with_cartridge(cartridge);
+        bus.write_mem(0x0000, 0x0A);
+        bus.write_mem(0xA812, 0x42);
+        for addr in [0xA012, 0xA812, 0xB012, 0xB812] {
+            let value = bus.fetch(addr);
+            if value != 0x42 {
+                return Err(format!(
+                    "{addr:#06x} holds {value:#04x}, expected the mirror of 0xA812"
+                ));
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn mbc5_banking() -> Result<(), String> {
+        // 512 banks, 8 MiB
+        let mut rom = vec![0; 512 * ROM_BANK_SIZE];
+        for bank in 0..512 {
+            rom[bank * ROM_BANK_SIZE + 0x1000] = bank as u8;
+            rom[bank * ROM_BANK_SIZE + 0x1001] = (bank >> 8) as u8;
+        }
+        rom[CARTRIDGE_TYPE] = 0x19;
+        let mut bus =
+            Bus::default().with_cartridge(Cartridge::from_bytes(rom).map_err(|e| e.to_string())?);
+        for bank in [0x1FF_u16, 0x100, 0, 0x42] {
+            bus.write_mem(0x2000, bank as u8);
+            bus.write_mem(0x3000, (bank >> 8) as u8);
+            let addr = ROM_BANK_SIZE as u16 + 0x1000;
+            let mapped = u16::from_le_bytes([bus.fetch(addr), bus.fetch(addr + 1)]);
+            if mapped != bank {
+                return Err(format!(
+                    "selected bank {bank:#x}, bank {mapped:#x} is mapped"
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// The ram is written to the `.sav` next to the rom and read back by the next load
+    #[test]
+    fn battery_save() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("gba-selftest-{}", std::process::id()));
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let rom_path = dir.join("save.gb");
+        let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+        // MBC1 with ram and battery, one bank
+        rom[CARTRIDGE_TYPE] = 0x03;
+        rom[CARTRIDGE_TYPE + 2] = 0x02;
+        let result = (|| {
+            fs::write(&rom_path, &rom).map_err(|e| e.to_string())?;
+            let load = || Cartridge::load(&rom_path).map_err(|e| e.to_string());
+            let mut bus = Bus::default().with_cartridge(load()?);
+            let sram = SRAM.start as u16;
+            bus.write_mem(0x0000, 0x0A);
+            bus.write_mem(sram + 7, 0x42);
+            bus.save();
+            let mut bus = Bus::default().with_cartridge(load()?);
+            bus.write_mem(0x0000, 0x0A);
+            match bus.fetch(sram + 7) {
+                0x42 => Ok(()),
+                value => Err(format!("the save holds {value:#04x} instead of 0x42")),
+            }
+        })();
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+    /// The game halts the clock, sets the day and reads it back through the latch
+    #[test]
+    fn rtc_latch() -> Result<(), String> {
+        let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+        // MBC3 with timer and battery
+        rom[CARTRIDGE_TYPE] = 0x10;
+        rom[CARTRIDGE_TYPE + 2] = 0x03;
+        let mut bus =
+            Bus::default().with_cartridge(Cartridge::from_bytes(rom).map_err(|e| e.to_string())?);
+        let sram = SRAM.start as u16;
+        bus.write_mem(0x0000, 0x0A);
+        // DH: halt, day bit 8
+        bus.write_mem(0x4000, 0x0C);
+        bus.write_mem(sram, 0x41);
+        bus.write_mem(0x4000, 0x0B);
+        bus.write_mem(sram, 0x23);
+        bus.write_mem(0x4000, 0x08);
+        bus.write_mem(sram, 30);
+        bus.write_mem(0x6000, 0);
+        bus.write_mem(0x6000, 1);
+        for (register, expected) in [(0x08, 30), (0x0B, 0x23), (0x0C, 0x41)] {
+            bus.write_mem(0x4000, register);
+            let value = bus.fetch(sram);
+            if value != expected {
+                return Err(format!(
+                    "register {register:#04x} reads {value:#04x} instead of {expected:#04x}"
+                ));
+            }
+        }
+        // back to the ram
+        bus.write_mem(0x4000, 0);
+        bus.write_mem(sram, 0x42);
+        if bus.fetch(sram) != 0x42 {
+            return Err("the ram is not mapped after the clock".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use std::ops::Range;
+
+use crate::{
+    ram::{Ram, VRAM},
+    state::{Reader, StateError, Writer},
+};
+
+/// Bit 7 puts the hardware into CGB mode, the boot rom copies the CGB flag of the
+/// cartridge header here
+pub const KEY0: u16 = 0xFF4C;
+/// Bit 0 prepares a speed switch, which the next STOP makes. Bit 7 is set in double speed.
+pub const KEY1: u16 = 0xFF4D;
+/// Selects the VRAM bank at 0x8000
+pub const VBK: u16 = 0xFF4F;
+/// Index into the background palettes, bit 7 increments it after every write of BCPD
+pub const BCPS: u16 = 0xFF68;
+pub const BCPD: u16 = 0xFF69;
+/// Index into the sprite palettes, like BCPS
+pub const OCPS: u16 = 0xFF6A;
+pub const OCPD: u16 = 0xFF6B;
+/// Selects the WRAM bank at 0xD000, 0 selects 1 as well
+pub const SVBK: u16 = 0xFF70;
+/// Bits of the CGB registers which always read as 1 in CGB mode
+pub const UNUSED_BITS: [(u16, u8); 7] = [
+    (KEY1, 0x7E),
+    (VBK, 0xFE),
+    (BCPS, 0x40),
+    (BCPD, 0x00),
+    (OCPS, 0x40),
+    (OCPD, 0x00),
+    (SVBK, 0xF8),
+];
+/// The switchable half of WRAM
+const WRAMX: Range<usize> = 0xD000..0xE000;
+const WRAM_BANKS: usize = 8;
+/// 8 palettes of 4 colors, 2 bytes each
+const PALETTE_RAM: usize = 64;
+
+pub fn is_cgb(ram: &Ram) -> bool {
+    ram[KEY0] & 0x80 != 0
+}
+pub fn double_speed(ram: &Ram) -> bool {
+    ram[KEY1] & 0x80 != 0
+}
+/// Switches the speed if KEY1 prepared it, returns whether it did
+pub fn switch_speed(ram: &mut Ram) -> bool {
+    if ram[KEY1] & 0x01 == 0 {
+        return false;
+    }
+    ram[KEY1] = !ram[KEY1] & 0x80;
+    true
+}
+/// 5 bits per channel, red in the low bits, to 8 bits per channel
+pub fn rgb(color: u16) -> [u8; 3] {
+    let channel = |shift: u16| {
+        let c = (color >> shift) as u8 & 0x1F;
+        c << 3 | c >> 2
+    };
+    [channel(0), channel(5), channel(10)]
+}
+
+/// The banks and palettes the CGB adds. The selected banks live in the address space
+/// like the rom banks, the others are kept here until they are selected.
+#[derive(Clone, Debug)]
+pub struct Cgb {
+    vram: [Vec<u8>; 2],
+    vram_bank: usize,
+    wram: Vec<Vec<u8>>,
+    wram_bank: usize,
+    bg_palettes: [u8; PALETTE_RAM],
+    obj_palettes: [u8; PALETTE_RAM],
+}
+impl Cgb {
+    /// Switches `ram` into CGB mode with the banks of a fresh start
+    pub fn new(ram: &mut Ram) -> Self {
+        ram[KEY0] = 0x80;
+        ram[KEY1] = 0;
+        ram[VBK] = 0;
+        ram[SVBK] = 1;
+        let wram = ram.bytes()[WRAMX].to_vec();
+        Cgb {
+            vram: [ram.bytes()[VRAM].to_vec(), vec![0; VRAM.len()]],
+            vram_bank: 0,
+            wram: vec![wram; WRAM_BANKS],
+            wram_bank: 1,
+            // white until the game sets them
+            bg_palettes: [0xFF; PALETTE_RAM],
+            obj_palettes: [0xFF; PALETTE_RAM],
+        }
+    }
+    /// Called after a write to one of the CGB registers
+    pub fn write(&mut self, ram: &mut Ram, addr: u16, value: u8) {
+        match addr {
+            VBK => {
+                let bank = value as usize & 1;
+                self.vram[self.vram_bank].copy_from_slice(&ram.bytes()[VRAM]);
+                ram.map(VRAM.start, &self.vram[bank]);
+                self.vram_bank = bank;
+            }
+            SVBK => {
+                let bank = (value as usize & 7).max(1);
+                self.wram[self.wram_bank].copy_from_slice(&ram.bytes()[WRAMX]);
+                ram.map(WRAMX.start, &self.wram[bank]);
+                self.wram_bank = bank;
+            }
+            BCPD => write_palette(&mut self.bg_palettes, ram, BCPS, value),
+            OCPD => write_palette(&mut self.obj_palettes, ram, OCPS, value),
+            _ => {}
+        }
+        // the data registers read the byte at the current index
+        ram[BCPD] = self.bg_palettes[ram[BCPS] as usize & 0x3F];
+        ram[OCPD] = self.obj_palettes[ram[OCPS] as usize & 0x3F];
+    }
+    /// VRAM bank `bank`, the selected one from the address space
+    pub fn vram<'a>(&'a self, ram: &'a Ram, bank: usize) -> &'a [u8] {
+        if bank == self.vram_bank {
+            &ram.bytes()[VRAM]
+        } else {
+            &self.vram[bank]
+        }
+    }
+    /// Color `index` of background palette `palette` as 15 bit RGB
+    pub fn bg_color(&self, palette: u8, index: u8) -> u16 {
+        color(&self.bg_palettes, palette, index)
+    }
+    pub fn obj_color(&self, palette: u8, index: u8) -> u16 {
+        color(&self.obj_palettes, palette, index)
+    }
+    /// The banks which are not selected and the palettes, the rest is in the address space
+    pub fn save_state(&self, w: &mut Writer) {
+        w.u8(self.vram_bank as u8);
+        w.u8(self.wram_bank as u8);
+        for bank in self.vram.iter().chain(&self.wram) {
+            w.bytes(bank);
+        }
+        w.bytes(&self.bg_palettes);
+        w.bytes(&self.obj_palettes);
+    }
+    pub fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.vram_bank = r.u8()? as usize & 1;
+        self.wram_bank = (r.u8()? as usize & 7).max(1);
+        for bank in self.vram.iter_mut().chain(&mut self.wram) {
+            let len = bank.len();
+            bank.copy_from_slice(r.bytes(len)?);
+        }
+        self.bg_palettes.copy_from_slice(r.bytes(PALETTE_RAM)?);
+        self.obj_palettes.copy_from_slice(r.bytes(PALETTE_RAM)?);
+        Ok(())
+    }
+}
+/// Writes the palette byte at the index in `spec` and increments the index if asked to
+fn write_palette(palettes: &mut [u8; PALETTE_RAM], ram: &mut Ram, spec: u16, value: u8) {
+    let index = ram[spec] & 0x3F;
+    palettes[index as usize] = value;
+    if ram[spec] & 0x80 != 0 {
+        ram[spec] = 0x80 | (index + 1) & 0x3F;
+    }
+}
+fn color(palettes: &[u8; PALETTE_RAM], palette: u8, index: u8) -> u16 {
+    let at = (palette as usize & 7) * 8 + index as usize * 2;
+    u16::from_le_bytes([palettes[at], palettes[at + 1]]) & 0x7FFF
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        bus::{self, Bus, BOOT_ROM_DISABLE},
+        cartridge::{Cartridge, CGB_FLAG},
+        cpu::{Cpu, CpuMode, V16},
+        frontend::{FrameCallback, Screen},
+        testing::{micro_rom, run_rom, ENTRY},
+        vram::LCDC,
+    };
+
+    use super::*;
+
+    /// A CGB game switches the VRAM and WRAM banks, colors the screen and doubles the speed
+    #[test]
+    fn cgb_mode() -> Result<(), String> {
+        // STOP with a prepared switch, then HALT
+        let mut rom = micro_rom(&[0x10, 0x00, 0x76]);
+        let dmg = Bus::default().with_cartridge(Cartridge::from_bytes(rom.clone()).unwrap());
+        if dmg.fetch(VBK) != 0xFF {
+            return Err("VBK is readable on the DMG".to_string());
+        }
+        rom[CGB_FLAG] = 0x80;
+        let pixel = Arc::new(AtomicUsize::new(0));
+        let first = pixel.clone();
+        let mut bus = Bus::default()
+            .with_cartridge(Cartridge::from_bytes(rom.clone()).unwrap())
+            .with_video(FrameCallback(move |screen: Screen| {
+                if let Screen::Colors(frame) = screen {
+                    first.store(frame[0] as usize, Ordering::Relaxed);
+                }
+            }));
+        bus.write_mem(BOOT_ROM_DISABLE, 1);
+        bus.write_mem(0x8000, 0x11);
+        bus.write_mem(VBK, 1);
+        bus.write_mem(0x8000, 0x22);
+        let vram = (bus.fetch(0x8000), bus.fetch(VBK));
+        bus.write_mem(VBK, 0);
+        if vram != (0x22, 0xFF) || bus.fetch(0x8000) != 0x11 {
+            return Err(format!(
+                "VRAM bank 1 read {vram:02X?}, bank 0 {:02X}",
+                bus.fetch(0x8000)
+            ));
+        }
+        bus.write_mem(SVBK, 2);
+        bus.write_mem(0xD000, 0x33);
+        bus.write_mem(SVBK, 3);
+        let other = bus.fetch(0xD000);
+        bus.write_mem(SVBK, 2);
+        if other == 0x33 || bus.fetch(0xD000) != 0x33 {
+            return Err("WRAM banks 2 and 3 are the same".to_string());
+        }
+        // color 0 of background palette 0 is pure red
+        bus.write_mem(BCPS, 0x80);
+        bus.write_mem(BCPD, 0x1F);
+        bus.write_mem(BCPD, 0x00);
+        bus.write_mem(BCPS, 0x00);
+        if bus.fetch(BCPD) != 0x1F || bus.fetch(BCPS) != 0x40 {
+            return Err("BCPD does not read the palette at BCPS".to_string());
+        }
+        bus.write_mem(LCDC, 0x91);
+        let mut cpu = Cpu::new(bus);
+        // at the HALT, STOP would stop the ppu as well
+        cpu.w(V16::PC, ENTRY + 2);
+        cpu.step_frame();
+        let red = pixel.load(Ordering::Relaxed) as u16;
+        if red != 0x001F || rgb(red) != [0xFF, 0, 0] {
+            return Err(format!("the screen shows {red:04X} instead of red"));
+        }
+
+        let (cpu, ram) = run_rom(rom, |bus| bus.write_mem(KEY1, 1));
+        let key1 = bus::read(&ram.read().unwrap(), KEY1);
+        if *cpu.mode() != CpuMode::Halt || key1 != 0xFE {
+            return Err(format!("STOP left {:?} with KEY1 {key1:02X}", cpu.mode()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{
+    atomic::{AtomicU8, AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Square 1, square 2, wave and noise
+pub const CHANNELS: usize = 4;
+/// No channel is soloed
+const NO_SOLO: u8 = u8::MAX;
+/// Samples kept of each channel for the waveforms
+pub const WAVE_LENGTH: usize = 256;
+
+/// Which sound channels are heard and how loud each one currently plays,
+/// shared between the gui and the cpu thread
+#[derive(Clone, Debug)]
+pub struct Channels(Arc<ChannelsInner>);
+#[derive(Debug)]
+struct ChannelsInner {
+    /// A bit per channel
+    muted: AtomicU8,
+    /// The only channel heard, `NO_SOLO` if all unmuted channels are
+    solo: AtomicU8,
+    /// Of the whole output in percent
+    volume: AtomicU8,
+    /// Of each channel in percent
+    volumes: [AtomicU8; CHANNELS],
+    /// Peak amplitude since the gui last read it, 0..=255
+    levels: [AtomicU8; CHANNELS],
+    /// The latest outputs of each channel, 0..=15, as a ring
+    waves: [[AtomicU8; WAVE_LENGTH]; CHANNELS],
+    /// Where the next outputs go in `waves`
+    wave_position: AtomicUsize,
+}
+impl Channels {
+    /// Whether the channel is mixed into the output
+    pub fn is_audible(&self, channel: usize) -> bool {
+        match self.solo() {
+            Some(solo) => solo == channel,
+            None => !self.is_muted(channel),
+        }
+    }
+    pub fn is_muted(&self, channel: usize) -> bool {
+        self.0.muted.load(Ordering::Relaxed) & (1 << channel) != 0
+    }
+    pub fn toggle_mute(&self, channel: usize) {
+        self.0.muted.fetch_xor(1 << channel, Ordering::Relaxed);
+    }
+    pub fn solo(&self) -> Option<usize> {
+        let solo = self.0.solo.load(Ordering::Relaxed);
+        (solo != NO_SOLO).then_some(solo as usize)
+    }
+    /// Solos the channel, or hears all channels again if it already was
+    pub fn toggle_solo(&self, channel: usize) {
+        let solo = if self.solo() == Some(channel) {
+            NO_SOLO
+        } else {
+            channel as u8
+        };
+        self.0.solo.store(solo, Ordering::Relaxed);
+    }
+    pub fn volume(&self) -> u8 {
+        self.0.volume.load(Ordering::Relaxed)
+    }
+    pub fn set_volume(&self, volume: u8) {
+        self.0.volume.store(volume.min(100), Ordering::Relaxed);
+    }
+    pub fn channel_volume(&self, channel: usize) -> u8 {
+        self.0.volumes[channel].load(Ordering::Relaxed)
+    }
+    pub fn set_channel_volume(&self, channel: usize, volume: u8) {
+        self.0.volumes[channel].store(volume.min(100), Ordering::Relaxed);
+    }
+    /// Called by the apu with the amplitude of every sample it mixes
+    pub fn record_level(&self, channel: usize, level: u8) {
+        self.0.levels[channel].fetch_max(level, Ordering::Relaxed);
+    }
+    /// The peak since the last call, for the meters
+    pub fn take_level(&self, channel: usize) -> u8 {
+        self.0.levels[channel].swap(0, Ordering::Relaxed)
+    }
+    /// Called by the apu with the output of every channel for each sample it mixes
+    pub fn record_outputs(&self, outputs: [u8; CHANNELS]) {
+        let position = self.0.wave_position.load(Ordering::Relaxed);
+        for (wave, output) in self.0.waves.iter().zip(outputs) {
+            wave[position].store(output, Ordering::Relaxed);
+        }
+        self.0
+            .wave_position
+            .store((position + 1) % WAVE_LENGTH, Ordering::Relaxed);
+    }
+    /// The latest outputs of the channel, oldest first
+    pub fn wave(&self, channel: usize) -> [u8; WAVE_LENGTH] {
+        let position = self.0.wave_position.load(Ordering::Relaxed);
+        let wave = &self.0.waves[channel];
+        std::array::from_fn(|i| wave[(position + i) % WAVE_LENGTH].load(Ordering::Relaxed))
+    }
+}
+impl Default for Channels {
+    fn default() -> Self {
+        Channels(Arc::new(ChannelsInner {
+            muted: AtomicU8::new(0),
+            solo: AtomicU8::new(NO_SOLO),
+            volume: AtomicU8::new(100),
+            volumes: std::array::from_fn(|_| AtomicU8::new(100)),
+            levels: Default::default(),
+            waves: std::array::from_fn(|_| std::array::from_fn(|_| AtomicU8::new(0))),
+            wave_position: AtomicUsize::new(0),
+        }))
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub description: String,
+    /// The raw code, multiple codes are joined with `+`
+    pub code: String,
+    pub enabled: bool,
+}
+
+/// Reads a RetroArch `.cht` file
+pub fn load_cht(path: &Path) -> io::Result<Vec<Cheat>> {
+    Ok(parse_cht(&fs::read_to_string(path)?))
+}
+/// Writes the cheats as a RetroArch `.cht` file
+pub fn save_cht(path: &Path, cheats: &[Cheat]) -> io::Result<()> {
+    fs::write(path, to_cht(cheats))
+}
+/// Parses the `key = value` format used by RetroArch.
+/// Unknown keys are ignored, as RetroArch writes many core specific ones.
+pub fn parse_cht(content: &str) -> Vec<Cheat> {
+    let mut count = None;
+    let mut cheats: BTreeMap<usize, Cheat> = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim().trim_matches('"');
+        if key == "cheats" {
+            count = value.parse().ok();
+            continue;
+        }
+        let Some((index, field)) = key
+            .strip_prefix("cheat")
+            .and_then(|rest| rest.split_once('_'))
+        else {
+            continue;
+        };
+        let Ok(index) = index.parse() else {
+            continue;
+        };
+        let cheat = cheats.entry(index).or_insert_with(|| Cheat {
+            description: String::new(),
+            code: String::new(),
+            enabled: false,
+        });
+        match field {
+            "desc" => cheat.description = value.to_string(),
+            "code" => cheat.code = value.to_string(),
+            "enable" => cheat.enabled = value == "true",
+            _ => {}
+        }
+    }
+    cheats
+        .into_iter()
+        .filter(|(index, cheat)| count.is_none_or(|count| *index < count) && !cheat.code.is_empty())
+        .map(|(_, cheat)| cheat)
+        .collect()
+}
+pub fn to_cht(cheats: &[Cheat]) -> String {
+    let mut cht = format!("cheats = {}\n", cheats.len());
+    for (i, cheat) in cheats.iter().enumerate() {
+        cht.push_str(&format!(
+            "\ncheat{i}_desc = \"{}\"\ncheat{i}_code = \"{}\"\ncheat{i}_enable = {}\n",
+            cheat.description, cheat.code, cheat.enabled
+        ));
+    }
+    cht
+}
+
+/// One decoded code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheatCode {
+    /// Writes the value into the ram after every frame. The bank of the cartridge ram
+    /// is not switched, the code writes to whichever is mapped.
+    GameShark { bank: u8, value: u8, addr: u16 },
+    /// Replaces the byte the cpu reads from the rom, only if it holds `compare`
+    GameGenie {
+        addr: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+}
+/// Decodes a code, GameShark as `ttvvaaaa` and Game Genie as `ABC-DEF` or `ABC-DEF-GHI`
+pub fn parse_code(code: &str) -> Result<CheatCode, String> {
+    let digits: String = code.chars().filter(|c| *c != '-').collect();
+    let hex = |range: std::ops::Range<usize>| {
+        u16::from_str_radix(&digits[range], 16).map_err(|_| format!("{code} is not hex"))
+    };
+    if !digits.is_ascii() {
+        return Err(format!("{code} is not hex"));
+    }
+    match (digits.len(), code.contains('-')) {
+        (8, false) => {
+            // the address is little endian
+            let addr = hex(4..8)?.swap_bytes();
+            if addr < 0x8000 {
+                return Err(format!("{code} does not write the ram"));
+            }
+            Ok(CheatCode::GameShark {
+                bank: hex(0..2)? as u8,
+                value: hex(2..4)? as u8,
+                addr,
+            })
+        }
+        (6 | 9, _) => {
+            let nibble = |i: usize| hex(i..i + 1);
+            let addr = (nibble(5)? << 12 | nibble(2)? << 8 | nibble(3)? << 4 | nibble(4)?) ^ 0xF000;
+            if addr >= 0x8000 {
+                return Err(format!("{code} does not patch the rom"));
+            }
+            // the compare byte is rotated and scrambled, the digit in the middle is not used
+            let compare = if digits.len() == 9 {
+                let scrambled = (nibble(6)? << 4 | nibble(8)?) as u8;
+                Some(scrambled.rotate_right(2) ^ 0xBA)
+            } else {
+                None
+            };
+            Ok(CheatCode::GameGenie {
+                addr,
+                value: hex(0..2)? as u8,
+                compare,
+            })
+        }
+        _ => Err(format!(
+            "{code} is neither a GameShark nor a Game Genie code"
+        )),
+    }
+}
+/// Decodes the codes of a cheat, which are joined with `+`
+pub fn parse_codes(code: &str) -> Result<Vec<CheatCode>, String> {
+    code.split('+')
+        .map(|code| parse_code(code.trim()))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct CheatsInner {
+    codes: Vec<CheatCode>,
+    changed: bool,
+}
+/// The codes of the enabled cheats, the gui sets them and the cpu applies them.
+/// Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Cheats(Arc<Mutex<CheatsInner>>);
+impl Cheats {
+    /// Decodes the enabled cheats, the ones which do not decode are left out
+    pub fn set(&self, cheats: &[Cheat]) {
+        let codes = cheats
+            .iter()
+            .filter(|cheat| cheat.enabled)
+            .filter_map(|cheat| parse_codes(&cheat.code).ok())
+            .flatten()
+            .collect();
+        let mut inner = self.0.lock().unwrap();
+        inner.codes = codes;
+        inner.changed = true;
+    }
+    /// The codes if they changed since the last call
+    pub fn take_changed(&self) -> Option<Vec<CheatCode>> {
+        let mut inner = self.0.lock().unwrap();
+        if !inner.changed {
+            return None;
+        }
+        inner.changed = false;
+        Some(inner.codes.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::{Bus, BOOT_ROM_DISABLE},
+        cartridge::Cartridge,
+        testing::micro_rom,
+    };
+
+    use super::*;
+
+    #[test]
+    fn cheat_codes() -> Result<(), String> {
+        let expected = CheatCode::GameShark {
+            bank: 0x01,
+            value: 0xFF,
+            addr: 0xD016,
+        };
+        match parse_code("01FF16D0")? {
+            code if code == expected => {}
+            code => return Err(format!("01FF16D0 decoded to {code:?}")),
+        }
+        for (code, compare) in [("3C1-50F", None), ("3C1-50F-EBA", Some(0x00))] {
+            let expected = CheatCode::GameGenie {
+                addr: 0x0150,
+                value: 0x3C,
+                compare,
+            };
+            match parse_code(code)? {
+                decoded if decoded == expected => {}
+                decoded => return Err(format!("{code} decoded to {decoded:?}")),
+            }
+        }
+        if parse_code("0100FF3F").is_ok() || parse_code("3C1-507").is_ok() {
+            return Err("a code outside of its memory was accepted".to_string());
+        }
+        let mut bus = Bus::default().with_cartridge(Cartridge::from_bytes(micro_rom(&[])).unwrap());
+        bus.write_mem(BOOT_ROM_DISABLE, 1);
+        // the rom holds 0x00 at 0x0150, so only the first compare byte matches
+        bus.set_rom_patches(&parse_codes("3C1-50F-EBE")?);
+        if bus.fetch(0x0150) != 0x00 {
+            return Err("a Game Genie code patched a byte it did not compare to".to_string());
+        }
+        bus.set_rom_patches(&parse_codes("3C1-50F-EBA+01FF16D0")?);
+        if bus.fetch(0x0150) != 0x3C {
+            return Err("a Game Genie code did not patch the rom".to_string());
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,54 @@
-use std::time::{Duration, Instant};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    backup,
+    banked::BankedAddr,
     bus::{Bus, OpCode},
-    gpu::DrawSignal,
-    instruction::{AddressMove, Instruction},
+    call_stack::{CallFrame, CallKind, CallStack, StackEffect},
+    cartridge::Cartridge,
+    cheats::{CheatCode, Cheats},
+    crash::{Executed, History, PostMortem},
+    debugger::{Debugger, Edit},
+    determinism::Host,
+    disasm,
+    frontend::Buttons,
+    frontend::DrawSignal,
+    instruction::{AddressMove, DecodeError, Instruction},
+    interrupt::Interrupt,
+    movie::{ActiveMovie, Movie, MovieMode, MovieRequest, Movies},
+    opcodes,
+    pacing::Pacing,
+    perf::{FrameTime, PerfStats},
+    power::{Power, PowerRequest},
+    ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    practice::Practice,
+    ram,
+    rewind::Rewind,
+    script::Script,
+    slots::{SlotRequest, Slots, SLOT_BACKUPS},
+    speed::Speed,
+    state::{self, Reader, StateError, Writer},
+    symbols::Symbols,
+    trace::{Trace, TraceState},
+    watch::RomWatch,
 };
-const CLOCK_SPEED: usize = 4194304;
+pub const CLOCK_SPEED: usize = 4194304;
 const _FPS: f32 = 60.;
+pub const CYCLES_PER_FRAME: usize = 70224;
+/// The clock advances by a machine cycle while the cpu halts
+const IDLE_CYCLES: usize = 4;
+/// Frames between writing a changed save ram to its file
+const SAVE_INTERVAL: u32 = 60;
+/// Where the boot rom jumps into the cartridge
+pub const ENTRY_POINT: u16 = 0x0100;
+/// The flags in F, the low nibble is always 0
+const ZERO: u8 = 0x80;
+const SUBTRACT: u8 = 0x40;
+const HALF_CARRY: u8 = 0x20;
+const CARRY: u8 = 0x10;
 pub struct Cpu {
     bus: Bus,
     // memory model for the registers:
@@ -19,13 +61,74 @@ pub struct Cpu {
     //  [SP],  5
     // ]
     registers: [u16; 6],
-    cycles: usize,
+    /// Machine cycles by opcode, not taken and taken for branches
+    timings: [(u8, u8); 256],
+    /// Bytes of every unprefixed instruction, the pc moves by that unless it jumps
+    lengths: [u8; 256],
+    /// Operand bytes the current instruction has read so far
+    operands: u16,
+    /// Cycles since the start of the current frame
+    frame_cycles: usize,
     mode: CpuMode,
+    speed: Speed,
+    /// Interrupt master enable
+    ime: bool,
+    /// Set by EI, which enables interrupts only after the next instruction
+    ime_pending: bool,
+    /// HALT with interrupts disabled and one pending does not halt,
+    /// the next instruction then starts one byte early
+    halt_bug: bool,
+    /// The last step could not execute its instruction, the pc stays in front of it
+    fault: Option<DecodeError>,
+    /// Resets with the new rom when the file changes
+    rom_watch: Option<RomWatch>,
+    practice: Practice,
+    rewind: Rewind,
+    slots: Slots,
+    debugger: Debugger,
+    trace: Trace,
+    power: Power,
+    cheats: Cheats,
+    /// The GameShark codes of `cheats`, written after every frame
+    ram_writes: Vec<CheatCode>,
+    /// Cycles of the last instruction the bus has not ticked yet,
+    /// because the debugger paused on a dot in the middle of it
+    tick_debt: usize,
+    call_stack: CallStack,
+    /// The last instructions, for the crash report
+    history: History,
+    symbols: Symbols,
+    script: Option<Box<Script>>,
+    movies: Movies,
+    movie: Option<ActiveMovie>,
+    perf: PerfStats,
+}
+/// The registers an instruction works on, to set it up and check it on its own
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub ime: bool,
 }
 #[derive(PartialEq, Debug, Clone)]
 pub enum CpuMode {
     Run,
-    _Halt,
+    /// Waits for an interrupt
+    Halt,
+    /// Waits for a button press
+    Stop,
+    /// An illegal opcode was executed, only a reset recovers
+    Locked {
+        op: u8,
+    },
     _DebugGpu,
     Shutdown,
 }
@@ -34,61 +137,732 @@ impl Cpu {
         Self {
             bus,
             registers: [0; 6],
-            cycles: 0,
+            timings: opcodes::timings(),
+            lengths: opcodes::lengths(),
+            operands: 0,
+            frame_cycles: 0,
             mode: CpuMode::Run,
+            speed: Speed::default(),
+            ime: false,
+            ime_pending: false,
+            halt_bug: false,
+            fault: None,
+            rom_watch: None,
+            practice: Practice::default(),
+            rewind: Rewind::default(),
+            slots: Slots::default(),
+            debugger: Debugger::default(),
+            trace: Trace::default(),
+            power: Power::default(),
+            cheats: Cheats::default(),
+            ram_writes: Vec::new(),
+            tick_debt: 0,
+            call_stack: CallStack::default(),
+            history: History::default(),
+            symbols: Symbols::default(),
+            script: None,
+            movies: Movies::default(),
+            movie: None,
+            perf: PerfStats::default(),
         }
     }
-    pub fn run(mut self) {
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+    pub fn with_rom_watch(mut self, rom_watch: RomWatch) -> Self {
+        self.rom_watch = Some(rom_watch);
+        self
+    }
+    pub fn with_practice(mut self, practice: Practice) -> Self {
+        self.practice = practice;
+        self
+    }
+    pub fn with_rewind(mut self, rewind: Rewind) -> Self {
+        self.rewind = rewind;
+        self
+    }
+    pub fn with_slots(mut self, slots: Slots) -> Self {
+        self.slots = slots;
+        self
+    }
+    /// Has to be the one the bus got as well, which checks the watchpoints
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.debugger = debugger;
+        self
+    }
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.trace = trace;
+        self
+    }
+    pub fn with_power(mut self, power: Power) -> Self {
+        self.power = power;
+        self
+    }
+    pub fn with_cheats(mut self, cheats: Cheats) -> Self {
+        self.cheats = cheats;
+        self
+    }
+    pub fn with_movies(mut self, movies: Movies) -> Self {
+        self.movies = movies;
+        self
+    }
+    /// Measures the frame times for the performance overlay while it is enabled
+    pub fn with_perf(mut self, perf: PerfStats) -> Self {
+        self.perf = perf;
+        self
+    }
+    /// Runs the callbacks of `script` on frames and on the accesses it watches
+    pub fn with_script(mut self, script: Script) -> Self {
+        self.bus.set_memory_hooks(script.hooks().clone());
+        self.script = Some(Box::new(script));
+        self
+    }
+    /// The labels of the game, reloaded with the rom when it changes
+    pub fn with_symbols(mut self, symbols: Symbols) -> Self {
+        self.symbols = symbols;
+        self
+    }
+    pub fn run(&mut self) {
+        let mut pacing = Pacing::default();
         while self.mode != CpuMode::Shutdown {
-            self.cycles = 0;
-            let now = Instant::now();
-            while self.cycles < CLOCK_SPEED {
-                self.cycles += 1;
-                for _i in 0..10 {
-                    let y = rand::random::<usize>();
-                    let x = rand::random::<usize>();
-                    let signal = DrawSignal::DrawPixel(x % 100, y % 100, self.cycles % 4);
-                    self.bus.send_gpu_signal(signal);
-                    let signal = DrawSignal::DrawPixel((x % 100), (y % 100) + 1, self.cycles % 4);
-                    self.bus.send_gpu_signal(signal);
-                    let signal = DrawSignal::DrawPixel((x % 100) + 1, (y % 100), self.cycles % 4);
-                    self.bus.send_gpu_signal(signal);
-                    let signal =
-                        DrawSignal::DrawPixel((x % 100) + 1, (y % 100) + 1, self.cycles % 4);
-                    self.bus.send_gpu_signal(signal);
+            if self.speed.is_stopped() {
+                self.set_mode(CpuMode::Shutdown);
+                break;
+            }
+            if let Some(rom_watch) = &mut self.rom_watch {
+                if let Some(cartridge) = rom_watch.poll() {
+                    // the labels are built along with the rom
+                    self.symbols.load_for(rom_watch.path());
+                    self.reset(cartridge);
+                }
+            }
+            self.apply_edits();
+            if let Some(request) = self.power.take_request() {
+                self.switch_power(request);
+            }
+            if let Some(snapshot) = self.rewind.take_seek() {
+                match self.restore(&snapshot) {
+                    // a frame is run to show the point it went back to
+                    Ok(()) if self.speed.is_paused() => self.speed.run_frames(1),
+                    Ok(()) => {}
+                    Err(e) => log::error!("could not go back on the timeline: {e}"),
+                }
+            }
+            if self.speed.is_paused() || self.power.is_off() {
+                std::thread::sleep(Duration::from_millis(10));
+                // the time spent paused is not made up
+                pacing.resync();
+                continue;
+            }
+            if self.rewind.is_rewinding() && !self.rewind_step() {
+                // the history is used up, the game waits at its oldest point
+                std::thread::sleep(Duration::from_millis(10));
+                pacing.resync();
+                continue;
+            }
+            if !self.step_frame_ahead(self.speed.run_ahead()) {
+                // the debugger paused in the middle of the frame
+                continue;
+            }
+            let pc = self.registers[4];
+            if self.debugger.is_active() && self.debugger.frame_done(pc) {
+                self.speed.set_paused(true);
+                self.bus.status().set_pc(pc);
+            }
+            let emulating = pacing.elapsed();
+            let waiting = Instant::now();
+            pacing.frame_done(&self.speed, &mut self.bus);
+            if self.perf.is_enabled() {
+                self.perf.push(FrameTime {
+                    emulating,
+                    sleeping: waiting.elapsed(),
+                });
+            }
+            self.apply_cheats();
+            self.update_practice();
+            if self.rewind.frame_done() {
+                self.rewind.push(self.snapshot());
+            }
+            self.update_slots();
+            self.update_movie();
+            if self.bus.status().frame().is_multiple_of(SAVE_INTERVAL) {
+                self.bus.save_if_dirty();
+            }
+        }
+        self.bus.save();
+        if self.slots.suspend_on_exit() {
+            self.suspend();
+        }
+        self.trace.stop();
+    }
+    /// Saves the state to continue from on the next start, after the battery save
+    /// so that one is not newer
+    fn suspend(&self) {
+        let status = self.bus.status();
+        if !status.rom_loaded() {
+            return;
+        }
+        let path = self.slots.suspend_path(status.rom_crc());
+        match state::write_file(&path, status.rom_crc(), &self.snapshot()) {
+            Ok(()) => log::info!("suspended to {}", path.display()),
+            Err(e) => log::error!("could not suspend to {}: {e}", path.display()),
+        }
+    }
+    /// Makes the changes of the memory viewer and the register panel
+    fn apply_edits(&mut self) {
+        let edits = self.debugger.take_edits();
+        if edits.is_empty() {
+            return;
+        }
+        for edit in edits {
+            match edit {
+                Edit::Memory(addr, value) => self.bus.poke(addr, value),
+                Edit::Register(index, value) => self.registers[index] = value,
+                Edit::Ime(ime) => self.ime = ime,
+            }
+        }
+        // the gui shows the new values while paused
+        let status = self.bus.status();
+        status.set_pc(self.registers[4]);
+        status.set_registers(self.registers);
+        status.set_ime(self.ime);
+    }
+    /// Takes over the codes when they changed and writes the GameShark values
+    pub fn apply_cheats(&mut self) {
+        if let Some(codes) = self.cheats.take_changed() {
+            self.bus.set_rom_patches(&codes);
+            self.ram_writes = codes
+                .into_iter()
+                .filter(|code| matches!(code, CheatCode::GameShark { .. }))
+                .collect();
+        }
+        for code in &self.ram_writes {
+            if let CheatCode::GameShark { value, addr, .. } = *code {
+                self.bus.poke(addr, value);
+            }
+        }
+    }
+    /// Takes the practice snapshots when they are due and goes back to the latest on a retry
+    fn update_practice(&mut self) {
+        if let Some(snapshot) = self.practice.take_retry() {
+            if let Err(e) = self.restore(&snapshot) {
+                log::error!("could not go back to the practice snapshot: {e}");
+            }
+            return;
+        }
+        let due = self.practice.frame_done(&ram::lock(&self.bus.ram()));
+        if due {
+            self.practice.store(self.snapshot());
+        }
+    }
+    /// Goes back to the newest rewind snapshot, the frame after it is run to show it.
+    /// Returns false if there is none left.
+    fn rewind_step(&mut self) -> bool {
+        let Some(snapshot) = self.rewind.pop() else {
+            return false;
+        };
+        if let Err(e) = self.restore(&snapshot) {
+            log::error!("could not rewind: {e}");
+            self.rewind.clear();
+            return false;
+        }
+        true
+    }
+    /// Saves or loads the save state the gui asked for
+    fn update_slots(&mut self) {
+        let Some(request) = self.slots.take_request() else {
+            return;
+        };
+        let crc = self.bus.status().rom_crc();
+        let result = match request {
+            SlotRequest::Save(slot) => {
+                let path = self.slots.path(crc, slot);
+                // saving over a good state by accident is not the end of it
+                if let Err(e) = backup::backup(&path, SLOT_BACKUPS) {
+                    log::warn!("could not back up {}: {e}", path.display());
                 }
-                self.cycles += self.step();
+                state::write_file(&path, crc, &self.snapshot())
+            }
+            SlotRequest::Load(slot) => {
+                let path = self.slots.path(crc, slot);
+                let snapshot = state::read_file(&path, crc);
+                if snapshot.is_ok() {
+                    self.interlock_save(&path);
+                }
+                snapshot.and_then(|snapshot| self.restore(&snapshot))
+            }
+            SlotRequest::Resume => state::read_file(&self.slots.suspend_path(crc), crc)
+                .and_then(|snapshot| self.restore(&snapshot))
+                .and_then(|()| self.slots.discard_suspend(crc).map_err(StateError::from)),
+        };
+        if let Err(e) = &result {
+            log::error!("{request:?} failed: {e}");
+        }
+        self.slots
+            .report(request, result.map_err(|e| e.to_string()));
+    }
+    /// Before the state at `path` replaces the cartridge ram, warns if it is older than the
+    /// battery save and backs the save up, so the game progress since can be restored
+    fn interlock_save(&mut self, path: &Path) {
+        let state = path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let older = match (state, self.bus.save_modified()) {
+            (Some(state), Some(save)) => state < save,
+            _ => false,
+        };
+        if older {
+            log::warn!(
+                "{} is older than the battery save, the save is backed up",
+                path.display()
+            );
+        }
+        self.slots.set_older_than_save(older);
+        self.bus.backup_save();
+    }
+    /// Starts and stops the movies the gui asks for, then holds the buttons of the next frame
+    pub fn update_movie(&mut self) {
+        if let Some(request) = self.movies.take_request() {
+            if let Err(e) = self.movie_request(request) {
+                log::error!("movie: {e}");
+                self.movies.report_error(e);
+            }
+        }
+        let buttons = match &mut self.movie {
+            None => return,
+            Some(ActiveMovie::Recording { movie, .. }) => {
+                // the buttons are read once, so they stay the same through the frame
+                let buttons = self.bus.input_buttons();
+                movie.inputs.push(buttons.0);
+                Some(buttons)
+            }
+            Some(ActiveMovie::Playing { movie, frame }) => {
+                let buttons = movie.inputs.get(*frame).map(|buttons| Buttons(*buttons));
+                *frame += 1;
+                buttons
+            }
+        };
+        match buttons {
+            Some(buttons) => {
+                self.bus.set_input_override(Some(buttons));
+                if let Some(movie) = &self.movie {
+                    self.movies.set_mode(movie.mode());
+                }
+            }
+            None => {
+                log::info!("the movie is over");
+                self.stop_movie();
+            }
+        }
+    }
+    fn movie_request(&mut self, request: MovieRequest) -> Result<(), String> {
+        match request {
+            MovieRequest::Record(path) => {
+                self.stop_movie();
+                // the run has to repeat exactly from here on
+                let seed = self.bus.host_mut().random_u64();
+                self.bus.set_host(Host::seeded(seed));
+                self.bus.begin_frame();
+                let movie = Movie {
+                    crc: self.bus.status().rom_crc(),
+                    seed,
+                    start_frame: self.bus.status().frame(),
+                    start: self.snapshot(),
+                    inputs: Vec::new(),
+                };
+                log::info!("recording a movie into {}", path.display());
+                self.movie = Some(ActiveMovie::Recording { movie, path });
+            }
+            MovieRequest::Play(path) => {
+                let movie = Movie::load(&path)?;
+                if movie.crc != self.bus.status().rom_crc() {
+                    return Err(format!("recorded with another rom ({:08x})", movie.crc));
+                }
+                self.stop_movie();
+                self.restore(&movie.start).map_err(|e| e.to_string())?;
+                self.bus.set_host(Host::seeded(movie.seed));
+                self.bus.status().set_frame(movie.start_frame);
+                self.bus.begin_frame();
+                log::info!("playing the movie {}", path.display());
+                self.movie = Some(ActiveMovie::Playing { movie, frame: 0 });
+            }
+            MovieRequest::Stop => self.stop_movie(),
+        }
+        Ok(())
+    }
+    /// Writes the recording, the player gets the buttons back
+    fn stop_movie(&mut self) {
+        if let Some(ActiveMovie::Recording { movie, path }) = self.movie.take() {
+            match movie.save(&path) {
+                Ok(()) => log::info!("saved {} frames to {}", movie.inputs.len(), path.display()),
+                Err(e) => {
+                    log::error!("could not save the movie to {}: {e}", path.display());
+                    self.movies.report_error(e);
+                }
+            }
+        }
+        self.bus.set_input_override(None);
+        self.movies.set_mode(MovieMode::Off);
+    }
+    /// The whole machine state, for going back to this point
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        for register in self.registers {
+            w.u16(register);
+        }
+        w.bool(self.ime);
+        w.bool(self.ime_pending);
+        w.u32(self.frame_cycles as u32);
+        match self.mode {
+            CpuMode::Halt => w.u8(1),
+            CpuMode::Stop => w.u8(2),
+            CpuMode::Locked { op } => {
+                w.u8(3);
+                w.u8(op);
+            }
+            _ => w.u8(0),
+        }
+        w.bool(self.halt_bug);
+        self.bus.save_state(&mut w);
+        w.finish()
+    }
+    /// Loads a snapshot, the machine is left as it was if it is broken
+    pub fn restore(&mut self, snapshot: &[u8]) -> Result<(), StateError> {
+        let before = self.snapshot();
+        self.tick_debt = 0;
+        // the calls of the snapshot are not known
+        self.call_stack.clear();
+        self.history.clear();
+        let result = self.load_snapshot(snapshot);
+        if result.is_err() {
+            // the state from the same build always loads
+            let _ = self.load_snapshot(&before);
+        }
+        result
+    }
+    fn load_snapshot(&mut self, snapshot: &[u8]) -> Result<(), StateError> {
+        let mut r = Reader::new(snapshot);
+        for register in &mut self.registers {
+            *register = r.u16()?;
+        }
+        self.ime = r.bool()?;
+        self.ime_pending = r.bool()?;
+        self.frame_cycles = r.u32()? as usize;
+        let mode = match r.u8()? {
+            1 => CpuMode::Halt,
+            2 => CpuMode::Stop,
+            3 => CpuMode::Locked { op: r.u8()? },
+            _ => CpuMode::Run,
+        };
+        self.set_mode(mode);
+        self.halt_bug = r.bool()?;
+        self.bus.load_state(&mut r)
+    }
+    /// Steps until the ppu finished a frame and sent it to the video sink of the bus
+    /// Returns false if the debugger paused before the frame was done
+    pub fn step_frame(&mut self) -> bool {
+        loop {
+            // the rest of the instruction the debugger paused in comes first
+            let debt = std::mem::take(&mut self.tick_debt);
+            // a halted cpu does not step, but the clock keeps running
+            let stepped = if debt > 0 {
+                debt
+            } else {
+                self.step().max(IDLE_CYCLES)
+            };
+            if let Some(error) = self.fault {
+                if self.bus.is_ahead() {
+                    // the frame is rolled back, the real one stops at the fault
+                    return false;
+                }
+                let pc = self.registers[4];
+                log::error!("{error} at {pc:#06x}");
+                self.debugger.fault(pc, error);
+                self.debugger.set_call_stack(self.call_stack.frames());
+                self.speed.set_paused(true);
+                self.bus.status().set_pc(pc);
+                self.bus.status().set_registers(self.registers);
+                self.bus.status().set_ime(self.ime);
+                return false;
+            }
+            if self.script.is_some() {
+                self.run_script(|script, cpu| {
+                    if script.hooks().is_active() {
+                        script.memory_accesses(cpu);
+                    }
+                });
+            }
+            // STOP stops the clock as well
+            let on_dot = self.mode != CpuMode::Stop && self.tick(stepped);
+            // a frame takes twice the cpu cycles in double speed, the debt was counted before
+            if debt == 0 {
+                self.frame_cycles += if self.bus.double_speed() {
+                    stepped / 2
+                } else {
+                    stepped
+                };
+            }
+            if self.frame_cycles >= CYCLES_PER_FRAME {
+                self.frame_cycles -= CYCLES_PER_FRAME;
+                if self.bus.is_ahead() {
+                    // only the picture of the frame leaves the machine
+                    return true;
+                }
+                self.bus.status().next_frame();
+                self.bus.status().set_registers(self.registers);
+                self.bus.status().set_ime(self.ime);
+                let buttons = self.bus.buttons();
+                self.bus.status().set_buttons(buttons);
+                self.bus.begin_frame();
+                self.speed.frame_done();
+                self.debugger.set_call_stack(self.call_stack.frames());
+                self.run_script(Script::frame_done);
+                if on_dot {
+                    self.debug_pause();
+                }
+                return true;
+            }
+            let pc = self.registers[4];
+            if on_dot || self.debugger.is_active() && self.debugger.after_instruction(pc) {
+                self.debug_pause();
+                return false;
+            }
+        }
+    }
+    /// Runs a frame unseen and shows the one `frames` later instead. Those are emulated with
+    /// the same buttons and rolled back, so the picture answers the buttons `frames` earlier.
+    /// Runs a plain frame while the debugger, a script or the trace watch the instructions.
+    /// Returns false if the debugger paused before the frame was done
+    pub fn step_frame_ahead(&mut self, frames: u8) -> bool {
+        if frames == 0
+            || self.debugger.is_active()
+            || self.script.is_some()
+            || self.trace.is_active()
+        {
+            return self.step_frame();
+        }
+        let skip = self.bus.skip_frames();
+        self.bus.set_skip_frames(true);
+        let done = self.step_frame();
+        if !done {
+            self.bus.set_skip_frames(skip);
+            return false;
+        }
+        let snapshot = self.snapshot();
+        let call_stack = self.call_stack.clone();
+        let history = self.history.clone();
+        self.bus.set_ahead(true);
+        for frame in 1..=frames {
+            self.bus.set_skip_frames(skip || frame < frames);
+            if !self.step_frame() {
+                break;
             }
-            let elapsed = now.elapsed();
-            println!("elapsed {}", elapsed.as_millis());
-            if elapsed.as_secs() < 1 {
-                std::thread::sleep(Duration::from_secs(1) - elapsed);
+        }
+        self.bus.set_ahead(false);
+        self.bus.set_skip_frames(skip);
+        if let Err(e) = self.load_snapshot(&snapshot) {
+            log::error!("could not roll back the frames run ahead: {e}");
+        }
+        self.call_stack = call_stack;
+        self.history = history;
+        // the random numbers of the next frame start over
+        self.bus.begin_frame();
+        true
+    }
+    /// Lends the script the cpu, which it reads and changes
+    fn run_script(&mut self, f: impl FnOnce(&mut Script, &mut Cpu)) {
+        if let Some(mut script) = self.script.take() {
+            f(&mut script, self);
+            self.script = Some(script);
+        }
+    }
+    /// Ticks the bus, dot by dot while the debugger steps dots or lines.
+    /// Returns whether it paused on a dot, the cycles left are ticked when the cpu continues.
+    fn tick(&mut self, cycles: usize) -> bool {
+        if !self.debugger.is_active() || !self.debugger.is_stepping_dots() {
+            self.bus.tick(cycles);
+            return false;
+        }
+        // a dot takes two cycles in double speed
+        let dot = if self.bus.double_speed() { 2 } else { 1 };
+        let mut left = cycles;
+        while left > 0 {
+            let ticked = dot.min(left);
+            self.bus.tick(ticked);
+            left -= ticked;
+            let (ly, dot) = self.bus.ppu_position();
+            if self.debugger.after_dot(self.registers[4], ly, dot) {
+                self.tick_debt = left;
+                return true;
             }
         }
+        false
+    }
+    /// Pauses in the middle of a frame and shows what is drawn of it so far
+    fn debug_pause(&mut self) {
+        self.speed.set_paused(true);
+        self.bus.status().set_pc(self.registers[4]);
+        self.bus.status().set_registers(self.registers);
+        self.bus.status().set_ime(self.ime);
+        self.debugger.set_call_stack(self.call_stack.frames());
+        self.bus.debug_pause();
+    }
+    /// Starts at the entry point of the cartridge with the registers and the io
+    /// the boot rom leaves behind. A is 0x11 on the CGB, which games check.
+    pub fn skip_boot_rom(&mut self) {
+        self.bus.skip_boot_rom();
+        let cgb = self.bus.is_cgb();
+        V8::A.write(self, if cgb { 0x11 } else { 0x01 });
+        self.set_zero(true);
+        self.set_subtract(false);
+        self.set_half_carry(!cgb);
+        self.set_carry(!cgb);
+        let (bc, de, hl) = if cgb {
+            (0x0000, 0xFF56, 0x000D)
+        } else {
+            (0x0013, 0x00D8, 0x014D)
+        };
+        V16::BC.write(self, bc);
+        V16::DE.write(self, de);
+        V16::HL.write(self, hl);
+        V16::SP.write(self, 0xFFFE);
+        V16::PC.write(self, ENTRY_POINT);
+    }
+    /// Starts over from the boot rom with `cartridge` inserted
+    fn reset(&mut self, cartridge: Cartridge) {
+        self.bus.reset(cartridge);
+        self.reset_registers();
+    }
+    fn switch_power(&mut self, request: PowerRequest) {
+        match request {
+            PowerRequest::Reset | PowerRequest::On => {
+                self.bus.power_cycle();
+                self.reset_registers();
+                self.power.set_off(false);
+            }
+            PowerRequest::Insert(path) => match Cartridge::load(&path) {
+                Ok(cartridge) => {
+                    self.symbols.load_for(&path);
+                    self.reset(cartridge);
+                    self.power.set_off(false);
+                }
+                Err(e) => log::error!("could not load rom {}: {e}", path.display()),
+            },
+            PowerRequest::Off => {
+                self.bus.save();
+                self.power.set_off(true);
+                // the lcd shows its lightest color without power
+                self.bus.send_gpu_signal(DrawSignal::Frame(Box::new(
+                    [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+                )));
+            }
+        }
+    }
+    /// The cpu as it powers on, which runs the boot rom if there is one
+    fn reset_registers(&mut self) {
+        self.rewind.clear();
+        self.registers = [0; 6];
+        self.frame_cycles = 0;
+        self.tick_debt = 0;
+        self.call_stack.clear();
+        self.history.clear();
+        self.ime = false;
+        self.ime_pending = false;
+        self.halt_bug = false;
+        self.set_mode(CpuMode::Run);
+        if !self.bus.has_boot_rom() {
+            self.skip_boot_rom();
+        }
+        self.bus
+            .send_gpu_signal(DrawSignal::CpuMode(CpuMode::Run, self.registers[4]));
+    }
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: V8::A.read(self),
+            f: V8::F.read(self),
+            b: V8::B.read(self),
+            c: V8::C.read(self),
+            d: V8::D.read(self),
+            e: V8::E.read(self),
+            h: V8::H.read(self),
+            l: V8::L.read(self),
+            sp: V16::SP.read(self),
+            pc: V16::PC.read(self),
+            ime: self.ime,
+        }
+    }
+    pub fn set_state(&mut self, state: CpuState) {
+        for (register, value) in [
+            (V8::A, state.a),
+            (V8::F, state.f),
+            (V8::B, state.b),
+            (V8::C, state.c),
+            (V8::D, state.d),
+            (V8::E, state.e),
+            (V8::H, state.h),
+            (V8::L, state.l),
+        ] {
+            register.write(self, value);
+        }
+        V16::SP.write(self, state.sp);
+        V16::PC.write(self, state.pc);
+        self.ime = state.ime;
+        self.ime_pending = false;
+    }
+    /// The registers and the last instructions, taken after the thread panicked
+    pub fn post_mortem(&self) -> PostMortem {
+        let history = self
+            .history
+            .pcs()
+            .map(|pc| Executed {
+                pc,
+                op: self.bus.fetch_op(pc).0,
+                text: disasm::disassemble(&self.bus, pc).0,
+            })
+            .collect();
+        PostMortem {
+            state: self.state(),
+            rom_bank: self.bus.status().rom_bank(),
+            history,
+            crc: self.bus.status().rom_crc(),
+            snapshot: self.snapshot(),
+        }
+    }
+    /// Why the last step did not execute its instruction
+    pub fn fault(&self) -> Option<DecodeError> {
+        self.fault
+    }
+    /// The next instruction starts one byte early, after a HALT which did not halt
+    pub fn halt_bug(&self) -> bool {
+        self.halt_bug
+    }
+    pub fn mode(&self) -> &CpuMode {
+        &self.mode
     }
     pub fn set_mode(&mut self, mode: CpuMode) {
         self.mode = mode;
     }
     pub fn r<R: Read>(&mut self, reg: R) -> R::Value {
-        self.incr_cycles();
         reg.read(self)
     }
     pub fn w<W: Write>(&mut self, reg: W, value: W::Value) {
-        self.incr_cycles();
         reg.write(self, value);
     }
-    pub fn incr_cycles(&mut self) {
-        self.cycles += 1;
-    }
     /// Writes the number in the content register to memory at the address saved in addr.
-    /// Costs 2 cycles (two reads)
     pub fn write_mem8(&mut self, addr: V16, content: V8) {
         let addr = self.r(addr);
         let content = self.r(content);
         self.bus.write_mem(addr, content);
     }
     /// Writes the raw content to the addr
-    /// Costs doesnt cost any cycles
     pub fn write_mem16_raw(&mut self, addr: u16, content: u8) {
         self.bus.write_mem(addr, content);
     }
@@ -96,48 +870,183 @@ impl Cpu {
     fn pc(&mut self) -> u16 {
         self.r(V16::PC)
     }
-    /// fetches the next byte from memory at the progam counter.
-    /// PC will be incremented by 1
+    /// fetches the next operand byte, the operands follow the opcode at the program counter.
+    /// PC stays until the instruction is done
     fn next_byte(&mut self) -> u8 {
-        let pc = self.pc();
-        self.bus.fetch(pc)
+        self.operands += 1;
+        let pc = self.pc().wrapping_add(self.operands);
+        self.bus.fetch_op(pc).0
     }
-    /// fetches the next word(2 bytes) from memory at the progam counter.
-    /// PC will be incremented by 2
+    /// fetches the next two operand bytes as a little endian word
     fn next_word(&mut self) -> u16 {
         u16::from_ne_bytes([self.next_byte(), self.next_byte()])
     }
-    /// returns the cycles needed for this step
+    /// returns the clock cycles needed for this step
     pub fn step(&mut self) -> usize {
+        if self.mode == CpuMode::Stop {
+            if !self.bus.poll_buttons() {
+                return 0;
+            }
+            self.set_mode(CpuMode::Run);
+            let pc = self.pc();
+            self.bus
+                .send_gpu_signal(DrawSignal::CpuMode(CpuMode::Run, pc));
+        }
+        if let Some(interrupt) = self.bus.pending_interrupt() {
+            // a requested interrupt ends halt even with interrupts disabled
+            if self.mode == CpuMode::Halt {
+                self.set_mode(CpuMode::Run);
+                let pc = self.pc();
+                self.bus
+                    .send_gpu_signal(DrawSignal::CpuMode(CpuMode::Run, pc));
+            }
+            if self.ime && self.mode == CpuMode::Run {
+                return self.dispatch(interrupt);
+            }
+        }
         if self.mode != CpuMode::Run {
             return 0;
         }
-        self.cycles = 0;
+        match self.execute_next() {
+            Ok(cycles) => {
+                self.fault = None;
+                self.bus.count_instruction();
+                cycles
+            }
+            Err(error) => {
+                self.fault = Some(error);
+                0
+            }
+        }
+    }
+    /// Fetches and executes the instruction at the pc, without interrupts and halting.
+    /// Returns the clock cycles it took, on an error the pc stays in front of the instruction.
+    pub fn execute_next(&mut self) -> Result<usize, DecodeError> {
+        let enable_interrupts = self.ime_pending;
+        let halt_bug = std::mem::take(&mut self.halt_bug);
         let pc = self.pc();
+        self.bus.status().set_pc(pc);
+        self.history.push(pc);
+        if self.trace.is_active() {
+            self.log_trace(pc);
+        }
         let op = self.bus.fetch_op(pc);
         let instruction = Instruction::from(op);
-        let address_move = self.execute(instruction, op);
-        self.w(V16::PC, address_move.apply(pc));
-        self.cycles
+        let effect = StackEffect::of(&instruction);
+        let (cycles, taken_cycles) = self.timings[op.0 as usize];
+        self.operands = 0;
+        let mut taken = false;
+        let next = match self.execute(instruction, op) {
+            // the byte after HALT is read twice
+            Ok(AddressMove::Add(len)) if halt_bug => pc.wrapping_add(len - 1),
+            Ok(address_move) => {
+                taken = matches!(address_move, AddressMove::To(_));
+                address_move.apply(pc)
+            }
+            Err(error) => {
+                // stays in front of the instruction, like before it was fetched
+                self.halt_bug = halt_bug;
+                self.w(V16::PC, pc);
+                return Err(error);
+            }
+        };
+        self.w(V16::PC, next);
+        if let (true, Some(effect)) = (taken, effect) {
+            self.track_call(effect, pc, next);
+        }
+        // a DI right after EI cancels it
+        if enable_interrupts && self.ime_pending {
+            self.ime = true;
+            self.ime_pending = false;
+        }
+        if self.mode != CpuMode::Run {
+            log::info!("{:?} at {pc:#06x}", self.mode);
+            self.bus
+                .send_gpu_signal(DrawSignal::CpuMode(self.mode.clone(), pc));
+        }
+        let cycles = if taken { taken_cycles } else { cycles };
+        Ok(cycles as usize * 4)
+    }
+    /// Keeps the call stack up to date after a call or a return from `pc` to `target`
+    fn track_call(&mut self, effect: StackEffect, pc: u16, target: u16) {
+        let sp = self.r(V16::SP);
+        match effect {
+            StackEffect::Enter(kind) => self.call_stack.enter(CallFrame {
+                kind,
+                site: pc,
+                target,
+                sp,
+            }),
+            StackEffect::Return => {
+                self.call_stack.returned(sp);
+                if self.debugger.is_active() {
+                    self.debugger.returned(sp, target);
+                }
+            }
+        }
+    }
+    fn log_trace(&self, pc: u16) {
+        let registers = [V8::A, V8::F, V8::B, V8::C, V8::D, V8::E, V8::H, V8::L];
+        self.trace.log(&TraceState {
+            registers: registers.map(|register| register.read(self)),
+            sp: V16::SP.read(self),
+            pc,
+            pcmem: [0, 1, 2, 3].map(|i| self.bus.fetch_op(pc.wrapping_add(i)).0),
+            label: self
+                .symbols
+                .label(BankedAddr::resolve(pc, self.bus.status().rom_bank())),
+        });
     }
-    fn execute(&mut self, instruction: Instruction, op: OpCode) -> AddressMove {
+    /// Pushes the program counter and jumps to the interrupt vector,
+    /// returns the 5 machine cycles it takes in clock cycles
+    fn dispatch(&mut self, interrupt: Interrupt) -> usize {
+        log::debug!("{interrupt:?} interrupt");
+        self.ime = false;
+        self.bus.acknowledge_interrupt(interrupt);
+        let content = self.r(V16::PC).to_ne_bytes();
+        let sp = self.r(V16::SP);
+        self.write_mem16_raw(sp.wrapping_sub(1), content[1]);
+        self.write_mem16_raw(sp.wrapping_sub(2), content[0]);
+        self.w(V16::SP, sp.wrapping_sub(2));
+        self.w(V16::PC, interrupt.vector());
+        self.call_stack.enter(CallFrame {
+            kind: CallKind::Interrupt(interrupt),
+            site: u16::from_ne_bytes(content),
+            target: interrupt.vector(),
+            sp: sp.wrapping_sub(2),
+        });
+        20
+    }
+    fn execute(
+        &mut self,
+        instruction: Instruction,
+        op: OpCode,
+    ) -> Result<AddressMove, DecodeError> {
         let op = op.0;
         let n0 = (op & 0xF0) >> 4; // first nibble of op
         let n1 = op & 0x0F; // second nibble of op
+        let length = self.lengths[op as usize] as u16;
         use Instruction::*;
-        match instruction {
-            Nop => AddressMove::Add(1),
+        Ok(match instruction {
+            Nop => AddressMove::Add(length),
             Load16Mem => {
+                let reg = match n0 {
+                    0x0 => V16::BC,
+                    0x1 => V16::DE,
+                    0x2 => V16::HL,
+                    0x3 => V16::SP,
+                    _ => return Err(DecodeError::Operands(op)),
+                };
                 let new = self.next_word();
-                self.w(V16::from(n0), new);
-                AddressMove::Add(3)
+                self.w(reg, new);
+                AddressMove::Add(length)
             }
             Store8Mem => {
                 let address = match (n0, n1) {
                     (0x0, 0x2) => V16::BC,
                     (0x1, 0x2) => V16::DE,
                     (0x7, _) => V16::HL,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let content = match (n0, n1) {
                     (0x0..=0x2, 0x2) => V8::A,
@@ -148,10 +1057,10 @@ impl Cpu {
                     (0x7, 0x4) => V8::H,
                     (0x7, 0x5) => V8::L,
                     (0x7, 0x7) => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 self.write_mem8(address, content);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Decrement16 => {
                 let old_reg = match n0 {
@@ -159,23 +1068,25 @@ impl Cpu {
                     0x1 => V16::DE,
                     0x2 => V16::HL,
                     0x3 => V16::SP,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
 
                 let old = self.r(old_reg);
-                self.set_zero(old == 1);
-                self.set_subtract(true);
-                self.set_half_carry(old == 0x100);
                 self.w(old_reg, old.wrapping_sub(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Increment16 => {
-                let old = self.r(V16::from(n0));
-                self.set_zero(old == u16::MAX);
-                self.set_subtract(false);
-                self.set_half_carry(old == 0xff);
-                self.w(V16::from(n0), old.wrapping_add(1));
-                AddressMove::Add(1)
+                let reg = match n0 {
+                    0x0 => V16::BC,
+                    0x1 => V16::DE,
+                    0x2 => V16::HL,
+                    0x3 => V16::SP,
+                    _ => return Err(DecodeError::Operands(op)),
+                };
+                // the 16 bit increments leave the flags alone
+                let old = self.r(reg);
+                self.w(reg, old.wrapping_add(1));
+                AddressMove::Add(length)
             }
             Increment8 => {
                 let reg = match (n0, n1) {
@@ -186,14 +1097,14 @@ impl Cpu {
                     (0x1, 0xC) => V8::E,
                     (0x2, 0xC) => V8::L,
                     (0x3, 0xC) => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let old = self.r(reg);
                 self.set_zero(old == u8::MAX);
                 self.set_subtract(false);
-                self.set_half_carry(old == 0xf);
+                self.set_half_carry(old & 0xf == 0xf);
                 self.w(reg, old.wrapping_add(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Decrement8 => {
                 let reg = match (n0, n1) {
@@ -204,14 +1115,14 @@ impl Cpu {
                     (0x1, 0xD) => V8::E,
                     (0x2, 0xD) => V8::L,
                     (0x3, 0xD) => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let old = self.r(reg);
                 self.set_zero(old == 1);
                 self.set_subtract(true);
-                self.set_half_carry(old == 0x10);
+                self.set_half_carry(old & 0xf == 0);
                 self.w(reg, old.wrapping_sub(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Load8Mem => {
                 let n = self.next_byte();
@@ -223,23 +1134,23 @@ impl Cpu {
                     (0x1, 0xE) => V8::E,
                     (0x2, 0xE) => V8::L,
                     (0x3, 0xE) => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 self.w(reg, n);
-                AddressMove::Add(2)
+                AddressMove::Add(length)
             }
             RotateLeftCircle => {
                 let mut a = self.r(V8::A);
                 a = self.rotate_left_circle(a);
                 self.w(V8::A, a);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             StoreSP => {
                 let pos: u16 = self.next_word();
                 let content = self.r(V16::SP).to_ne_bytes();
                 self.write_mem16_raw(pos, content[0]);
-                self.write_mem16_raw(pos + 1, content[1]);
-                AddressMove::Add(1)
+                self.write_mem16_raw(pos.wrapping_add(1), content[1]);
+                AddressMove::Add(length)
             }
             Add16toHL => {
                 let current = self.r(V16::HL);
@@ -248,7 +1159,7 @@ impl Cpu {
                     1 => V16::DE,
                     2 => V16::HL,
                     3 => V16::SP,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 });
                 let (new, overflow) = current.overflowing_add(add);
                 self.set_carry(overflow);
@@ -256,63 +1167,50 @@ impl Cpu {
                 self.set_half_carry((current ^ add ^ new) & 0x1000 != 0);
                 self.w(V16::HL, new);
 
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             RotateRightCircle => {
                 let mut a = self.r(V8::A);
                 a = self.rotate_right_circle(a);
                 self.w(V8::A, a);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             RotateLeft => {
                 let mut a = self.r(V8::A);
                 a = self.rotate_left(a);
                 self.w(V8::A, a);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             RotateRight => {
                 let mut a = self.r(V8::A);
                 a = self.rotate_right(a);
                 self.w(V8::A, a);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Stop => {
-                todo!()
+                self.bus.reset_divider();
+                // a prepared speed switch happens instead of stopping
+                if !self.bus.switch_speed() {
+                    self.set_mode(CpuMode::Stop);
+                }
+                AddressMove::Add(length)
             }
             JumpRelative => {
-                let distance = self.next_byte();
-                let old = self.pc();
-                match (n0, n1) {
-                    (0x1, 0x8) => return AddressMove::To(old + distance as u16),
-                    (0x2, 0x8) => {
-                        if self.zero_flag() {
-                            return AddressMove::To(old + distance as u16);
-                        } else {
-                            return AddressMove::Add(2);
-                        }
-                    }
-                    (0x3, 0x8) => {
-                        if self.carry_flag() {
-                            return AddressMove::To(old + distance as u16);
-                        } else {
-                            return AddressMove::Add(2);
-                        }
-                    }
-                    (0x2, 0x0) => {
-                        if !self.zero_flag() {
-                            return AddressMove::To(old + distance as u16);
-                        } else {
-                            return AddressMove::Add(2);
-                        }
-                    }
-                    (0x3, 0x0) => {
-                        if !self.carry_flag() {
-                            return AddressMove::To(old + distance as u16);
-                        } else {
-                            return AddressMove::Add(2);
-                        }
-                    }
-                    _ => panic!(),
+                // relative to the end of the instruction
+                let distance = self.next_byte() as i8;
+                let target = self.pc().wrapping_add(length).wrapping_add(distance as u16);
+                let should_jump = match (n0, n1) {
+                    (0x1, 0x8) => true,
+                    (0x2, 0x8) => self.zero_flag(),
+                    (0x3, 0x8) => self.carry_flag(),
+                    (0x2, 0x0) => !self.zero_flag(),
+                    (0x3, 0x0) => !self.carry_flag(),
+                    _ => return Err(DecodeError::Operands(op)),
+                };
+                if should_jump {
+                    AddressMove::To(target)
+                } else {
+                    AddressMove::Add(length)
                 }
             }
             StoreHlIncr => {
@@ -321,7 +1219,7 @@ impl Cpu {
                 self.write_mem8(hl, a);
                 let hl = self.r(hl);
                 self.w(V16::HL, hl.wrapping_add(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             StoreHlDecr => {
                 let hl = V16::HL;
@@ -329,45 +1227,49 @@ impl Cpu {
                 self.write_mem8(hl, a);
                 let hl = self.r(hl);
                 self.w(V16::HL, hl.wrapping_sub(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Daa => {
                 let a = self.r(V8::A);
                 let mut adjust = 0;
-                if self.half_carry_flag() {
-                    adjust |= 0x06;
-                }
-                if self.carry_flag() {
-                    adjust |= 0x60;
-                }
-                if self.subtract_flag() {
-                    if a & 0xF > 0x9 {
+                let mut carry = self.carry_flag();
+                let res = if self.subtract_flag() {
+                    if self.half_carry_flag() {
                         adjust |= 0x06;
                     }
-                    if a >= 0xa0 {
+                    if carry {
                         adjust |= 0x60;
                     }
-                }
-                let (res, overflow) = a.overflowing_add(adjust);
+                    a.wrapping_sub(adjust)
+                } else {
+                    if self.half_carry_flag() || a & 0xF > 0x9 {
+                        adjust |= 0x06;
+                    }
+                    if carry || a > 0x99 {
+                        adjust |= 0x60;
+                        carry = true;
+                    }
+                    a.wrapping_add(adjust)
+                };
                 self.w(V8::A, res);
-                self.set_carry(overflow);
+                self.set_carry(carry);
                 self.set_zero(res == 0);
                 self.set_half_carry(false);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Load8MemHlIncr => {
                 let position = self.r(V16::HL);
                 let content = self.bus.fetch(position);
                 self.w(V8::A, content);
                 self.w(V16::HL, position.wrapping_add(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Load8MemHlDecr => {
                 let position = self.r(V16::HL);
                 let content = self.bus.fetch(position);
                 self.w(V8::A, content);
                 self.w(V16::HL, position.wrapping_sub(1));
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             ComplementA => {
                 let old = self.r(V8::A);
@@ -375,66 +1277,66 @@ impl Cpu {
                 self.w(V8::A, new);
                 self.set_half_carry(true);
                 self.set_subtract(true);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             IncMemHl => {
                 let hl = self.r(V16::HL);
                 let n = self.bus.fetch(hl);
-                let (res, overflow) = n.overflowing_add(1);
-                self.write_mem16_raw(hl, res);
-                self.set_carry(overflow);
+                let res = n.wrapping_add(1);
+                self.set_zero(res == 0);
                 self.set_subtract(false);
-                self.set_half_carry(n == 0xf);
+                self.set_half_carry(n & 0xf == 0xf);
                 self.write_mem16_raw(hl, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             DecMemHl => {
                 let hl = self.r(V16::HL);
                 let n = self.bus.fetch(hl);
-                let (res, overflow) = n.overflowing_sub(1);
-                self.write_mem16_raw(hl, res);
-                self.set_carry(overflow);
+                let res = n.wrapping_sub(1);
+                self.set_zero(res == 0);
                 self.set_subtract(true);
-                self.set_half_carry(n == 0xf);
+                self.set_half_carry(n & 0xf == 0);
                 self.write_mem16_raw(hl, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             StoreXMemHl => {
                 let x = self.next_byte();
                 let hl = self.r(V16::HL);
                 self.write_mem16_raw(hl, x);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             SetCarryFlag => {
                 self.set_carry(true);
                 self.set_half_carry(false);
-                self.set_zero(false);
-                AddressMove::Add(1)
+                self.set_subtract(false);
+                AddressMove::Add(length)
             }
             FlipCarryFlag => {
                 let current = self.carry_flag();
                 self.set_carry(!current);
-                AddressMove::Add(1)
+                self.set_half_carry(false);
+                self.set_subtract(false);
+                AddressMove::Add(length)
             }
             Load8into8 => {
                 let into = match n0 {
                     4 => match n1 {
                         0 | 1 | 2 | 3 | 4 | 5 | 7 => V8::B,
                         8 | 9 | 0xA | 0xB | 0xC | 0xD | 0xF => V8::C,
-                        _ => panic!(),
+                        _ => return Err(DecodeError::Operands(op)),
                     },
                     5 => match n1 {
                         0 | 1 | 2 | 3 | 4 | 5 | 7 => V8::D,
                         8 | 9 | 0xA | 0xB | 0xC | 0xD | 0xF => V8::E,
-                        _ => panic!(),
+                        _ => return Err(DecodeError::Operands(op)),
                     },
                     6 => match n1 {
                         0 | 1 | 2 | 3 | 4 | 5 | 7 => V8::H,
                         8 | 9 | 0xA | 0xB | 0xC | 0xD | 0xF => V8::L,
-                        _ => panic!(),
+                        _ => return Err(DecodeError::Operands(op)),
                     },
                     7 => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let from = match n1 {
                     0 => V8::B,
@@ -451,11 +1353,11 @@ impl Cpu {
                     0xC => V8::H,
                     0xD => V8::L,
                     0xF => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let from_value = self.r(from);
                 self.w(into, from_value);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Load16Meminto8 => {
                 let addr_reg = match (n0, n1) {
@@ -468,7 +1370,7 @@ impl Cpu {
                     (0x5, 0xE) => V16::HL,
                     (0x6, 0xE) => V16::HL,
                     (0x7, 0xE) => V16::HL,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let into = match (n0, n1) {
                     (0x0, 0xA) => V8::A,
@@ -480,16 +1382,21 @@ impl Cpu {
                     (0x5, 0xE) => V8::E,
                     (0x6, 0xE) => V8::L,
                     (0x7, 0xE) => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let addr = self.r(addr_reg);
                 let content = self.bus.fetch(addr);
                 self.w(into, content);
 
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Halt => {
-                todo!()
+                if !self.ime && self.bus.pending_interrupt().is_some() {
+                    self.halt_bug = true;
+                } else {
+                    self.set_mode(CpuMode::Halt);
+                }
+                AddressMove::Add(length)
             }
             Add8toA => {
                 let reg = match n1 {
@@ -500,7 +1407,7 @@ impl Cpu {
                     4 => V8::H,
                     5 => V8::L,
                     7 => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let a = self.r(V8::A);
                 let add = self.r(reg);
@@ -510,7 +1417,7 @@ impl Cpu {
                 self.set_subtract(false);
                 self.set_half_carry_add(a, add);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             AddMemToA => {
                 let hl = self.r(V16::HL);
@@ -522,7 +1429,7 @@ impl Cpu {
                 self.set_subtract(false);
                 self.set_half_carry_add(a, content);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Add8AndFlagToA => {
                 let add_reg = match n1 {
@@ -533,34 +1440,17 @@ impl Cpu {
                     0xC => V8::H,
                     0xD => V8::L,
                     0xF => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let adder = self.r(add_reg);
-
-                let carry = if self.carry_flag() { 1 } else { 0 };
-                let a = self.r(V8::A);
-                let (temp, overflow1) = a.overflowing_add(adder);
-                let (res, overflow2) = temp.overflowing_add(carry);
-                self.set_carry(overflow1 || overflow2);
-                self.set_zero(res == 0);
-                self.set_subtract(false);
-                self.set_half_carry_add(a, adder + carry);
-                self.w(V8::A, res);
-                AddressMove::Add(1)
+                self.add_with_carry(adder);
+                AddressMove::Add(length)
             }
             AddMemHlAndFlagToA => {
                 let position = self.r(V16::HL);
                 let adder = self.bus.fetch(position);
-
-                let carry = if self.carry_flag() { 1 } else { 0 };
-                let a = self.r(V8::A);
-                let (temp, overflow1) = a.overflowing_add(adder);
-                let (res, overflow2) = temp.overflowing_add(carry);
-                self.set_carry(overflow1 || overflow2);
-                self.set_zero(res == 0);
-                self.set_subtract(false);
-                self.set_half_carry_add(a, adder + carry);
-                AddressMove::Add(1)
+                self.add_with_carry(adder);
+                AddressMove::Add(length)
             }
             Sub8fromA => {
                 let sub_reg = match n1 {
@@ -571,7 +1461,7 @@ impl Cpu {
                     4 => V8::H,
                     5 => V8::L,
                     7 => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let sub = self.r(sub_reg);
                 let a = self.r(V8::A);
@@ -580,7 +1470,8 @@ impl Cpu {
                 self.set_zero(res == 0);
                 self.set_subtract(true);
                 self.set_half_carry_sub(a, sub);
-                AddressMove::Add(1)
+                self.w(V8::A, res);
+                AddressMove::Add(length)
             }
             SubMemToA => {
                 let hl = self.r(V16::HL);
@@ -592,7 +1483,7 @@ impl Cpu {
                 self.set_subtract(true);
                 self.set_half_carry_sub(a, content);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Sub8AndFlagToA => {
                 let sub_reg = match n1 {
@@ -603,30 +1494,17 @@ impl Cpu {
                     0xC => V8::H,
                     0xD => V8::L,
                     0xF => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let content = self.r(sub_reg);
-                let a = self.r(V8::A);
-                let (res, overflow) = a.overflowing_sub(content);
-                self.set_carry(overflow);
-                self.set_zero(res == 0);
-                self.set_subtract(true);
-                self.set_half_carry_sub(a, content);
-                self.w(V8::A, res);
-
-                AddressMove::Add(1)
+                self.sub_with_carry(content);
+                AddressMove::Add(length)
             }
             SubMemHlAndFlagToA => {
                 let hl = self.r(V16::HL);
                 let content = self.bus.fetch(hl);
-                let a = self.r(V8::A);
-                let (res, overflow) = a.overflowing_sub(content);
-                self.set_carry(overflow);
-                self.set_zero(res == 0);
-                self.set_subtract(true);
-                self.set_half_carry_sub(a, content);
-                self.w(V8::A, res);
-                AddressMove::Add(1)
+                self.sub_with_carry(content);
+                AddressMove::Add(length)
             }
             And8A => {
                 let sec = match n1 {
@@ -637,7 +1515,7 @@ impl Cpu {
                     4 => V8::H,
                     5 => V8::L,
                     7 => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let sec = self.r(sec);
                 let a = self.r(V8::A);
@@ -647,7 +1525,7 @@ impl Cpu {
                 self.set_half_carry(true);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             AndMemHlA => {
                 let position = self.r(V16::HL);
@@ -659,7 +1537,7 @@ impl Cpu {
                 self.set_half_carry(true);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Xor8A => {
                 let xor_reg = match n1 {
@@ -670,7 +1548,7 @@ impl Cpu {
                     0xC => V8::H,
                     0xD => V8::L,
                     0xF => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let xor = self.r(xor_reg);
                 let a = self.r(V8::A);
@@ -680,7 +1558,7 @@ impl Cpu {
                 self.set_half_carry(false);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             XorMemHlA => {
                 let hl = self.r(V16::HL);
@@ -692,7 +1570,7 @@ impl Cpu {
                 self.set_half_carry(false);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Or8A => {
                 let or = match n1 {
@@ -703,7 +1581,7 @@ impl Cpu {
                     4 => V8::H,
                     5 => V8::L,
                     7 => V8::A,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let or = self.r(or);
                 let a = self.r(V8::A);
@@ -713,7 +1591,7 @@ impl Cpu {
                 self.set_half_carry(false);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             OrMemHlA => {
                 let position = self.r(V16::HL);
@@ -725,18 +1603,18 @@ impl Cpu {
                 self.set_half_carry(false);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Compare8A => {
                 let cmp = match n1 {
-                    0 => V8::B,
-                    1 => V8::C,
-                    2 => V8::D,
-                    3 => V8::E,
-                    4 => V8::H,
-                    5 => V8::L,
-                    7 => V8::A,
-                    _ => panic!(),
+                    0x8 => V8::B,
+                    0x9 => V8::C,
+                    0xA => V8::D,
+                    0xB => V8::E,
+                    0xC => V8::H,
+                    0xD => V8::L,
+                    0xF => V8::A,
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let a = self.r(V8::A);
                 let cmp = self.r(cmp);
@@ -744,7 +1622,7 @@ impl Cpu {
                 self.set_half_carry_sub(a, cmp);
                 self.set_subtract(true);
                 self.set_carry(a < cmp);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             CompareMemHlA => {
                 let position = self.r(V16::HL);
@@ -754,7 +1632,7 @@ impl Cpu {
                 self.set_half_carry_sub(a, cmp);
                 self.set_subtract(true);
                 self.set_carry(a < cmp);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             ReturnIfFlag => {
                 let should_return = match (n0, n1) {
@@ -762,16 +1640,16 @@ impl Cpu {
                     (0xD, 0x0) => !self.carry_flag(),
                     (0xC, 0x8) => self.zero_flag(),
                     (0xD, 0x8) => self.carry_flag(),
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 if should_return {
                     let sp = self.r(V16::SP);
                     let lower = self.bus.fetch(sp);
-                    let upper = self.bus.fetch(sp + 1);
-                    self.w(V16::SP, sp + 2);
+                    let upper = self.bus.fetch(sp.wrapping_add(1));
+                    self.w(V16::SP, sp.wrapping_add(2));
                     AddressMove::To(u16::from_ne_bytes([lower, upper]))
                 } else {
-                    AddressMove::Add(1)
+                    AddressMove::Add(length)
                 }
             }
             Pop16 => {
@@ -780,14 +1658,17 @@ impl Cpu {
                     0xD => V16::DE,
                     0xE => V16::HL,
                     0xF => V16::AF,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let sp = self.r(V16::SP);
                 let lower = self.bus.fetch(sp);
-                let upper = self.bus.fetch(sp + 1);
-                self.w(to, u16::from_ne_bytes([lower, upper]));
-                self.w(V16::SP, sp + 2);
-                AddressMove::Add(1)
+                let upper = self.bus.fetch(sp.wrapping_add(1));
+                let value = u16::from_ne_bytes([lower, upper]);
+                // the low nibble of F does not exist
+                let value = if to == V16::AF { value & 0xFFF0 } else { value };
+                self.w(to, value);
+                self.w(V16::SP, sp.wrapping_add(2));
+                AddressMove::Add(length)
             }
             JumpIfFlag => {
                 // increments pc by 2
@@ -797,19 +1678,19 @@ impl Cpu {
                     (0xC, 0xA) => self.zero_flag(),
                     (0xD, 0x2) => !self.carry_flag(),
                     (0xD, 0xA) => self.carry_flag(),
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 if should_jump {
                     AddressMove::To(addr)
                 } else {
-                    AddressMove::Add(1)
+                    AddressMove::Add(length)
                 }
             }
             Jump16 => {
                 let addr = match n1 {
                     3 => self.next_word(),
                     9 => self.r(V16::HL),
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 AddressMove::To(addr)
             }
@@ -820,18 +1701,19 @@ impl Cpu {
                     (0xC, 0xC) => self.zero_flag(),
                     (0xD, 0x4) => !self.carry_flag(),
                     (0xD, 0xC) => self.carry_flag(),
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 if should_call {
-                    let pc = self.r(V16::PC);
+                    // returns behind the operands
+                    let pc = self.r(V16::PC).wrapping_add(3);
                     let pc_bytes = pc.to_ne_bytes();
                     let sp = self.r(V16::SP);
-                    self.w(V16::SP, sp - 2);
-                    self.write_mem16_raw(sp - 2, pc_bytes[0]);
-                    self.write_mem16_raw(sp - 1, pc_bytes[1]);
+                    self.w(V16::SP, sp.wrapping_sub(2));
+                    self.write_mem16_raw(sp.wrapping_sub(1), pc_bytes[1]);
+                    self.write_mem16_raw(sp.wrapping_sub(2), pc_bytes[0]);
                     AddressMove::To(addr)
                 } else {
-                    AddressMove::Add(1)
+                    AddressMove::Add(length)
                 }
             }
             Push16 => {
@@ -840,15 +1722,15 @@ impl Cpu {
                     0xD => V16::DE,
                     0xE => V16::HL,
                     0xF => V16::AF,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
                 let content = self.r(reg).to_ne_bytes();
                 let sp = self.r(V16::SP);
-                self.write_mem16_raw(sp - 2, content[0]);
-                self.write_mem16_raw(sp - 1, content[1]);
-                self.w(V16::SP, sp - 2);
+                self.write_mem16_raw(sp.wrapping_sub(1), content[1]);
+                self.write_mem16_raw(sp.wrapping_sub(2), content[0]);
+                self.w(V16::SP, sp.wrapping_sub(2));
 
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Add8ImmToA => {
                 let add = self.next_byte();
@@ -859,7 +1741,7 @@ impl Cpu {
                 self.set_subtract(false);
                 self.set_half_carry_add(a, add);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Sub8ImmToA => {
                 let sub = self.next_byte();
@@ -869,7 +1751,8 @@ impl Cpu {
                 self.set_zero(res == 0);
                 self.set_subtract(true);
                 self.set_half_carry_sub(a, sub);
-                AddressMove::Add(1)
+                self.w(V8::A, res);
+                AddressMove::Add(length)
             }
             And8ImmToA => {
                 let sec = self.next_byte();
@@ -880,7 +1763,7 @@ impl Cpu {
                 self.set_half_carry(true);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             Or8ImmToA => {
                 let or = self.next_byte();
@@ -891,14 +1774,9 @@ impl Cpu {
                 self.set_half_carry(false);
                 self.set_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             CallN => {
-                let content = self.r(V16::PC).to_ne_bytes();
-                let sp = self.r(V16::SP);
-                self.write_mem16_raw(sp - 2, content[0]);
-                self.write_mem16_raw(sp - 1, content[1]);
-                self.w(V16::SP, sp - 2);
                 let dest = match (n0, n1) {
                     (0xC, 0x7) => 0x00,
                     (0xD, 0x7) => 0x10,
@@ -908,55 +1786,50 @@ impl Cpu {
                     (0xD, 0xF) => 0x18,
                     (0xE, 0xF) => 0x28,
                     (0xF, 0xF) => 0x38,
-                    _ => panic!(),
+                    _ => return Err(DecodeError::Operands(op)),
                 };
+                let content = self.r(V16::PC).wrapping_add(1).to_ne_bytes();
+                let sp = self.r(V16::SP);
+                self.write_mem16_raw(sp.wrapping_sub(1), content[1]);
+                self.write_mem16_raw(sp.wrapping_sub(2), content[0]);
+                self.w(V16::SP, sp.wrapping_sub(2));
                 AddressMove::To(dest)
             }
             Return => {
                 let sp = self.r(V16::SP);
                 let lower = self.bus.fetch(sp);
-                let upper = self.bus.fetch(sp + 1);
+                let upper = self.bus.fetch(sp.wrapping_add(1));
 
-                self.w(V16::SP, sp + 2);
+                self.w(V16::SP, sp.wrapping_add(2));
                 AddressMove::To(u16::from_ne_bytes([lower, upper]))
             }
             ReturnInterrupt => {
-                todo!()
+                let sp = self.r(V16::SP);
+                let lower = self.bus.fetch(sp);
+                let upper = self.bus.fetch(sp.wrapping_add(1));
+                self.w(V16::SP, sp.wrapping_add(2));
+                // unlike EI without delay
+                self.ime = true;
+                AddressMove::To(u16::from_ne_bytes([lower, upper]))
             }
             Call => {
                 let new_pc = self.next_word();
-                let position = self.pc().to_ne_bytes();
+                let position = self.pc().wrapping_add(3).to_ne_bytes();
                 let sp = self.r(V16::SP);
-                self.write_mem16_raw(sp - 2, position[0]);
-                self.write_mem16_raw(sp - 1, position[1]);
-                self.w(V16::SP, sp - 2);
+                self.write_mem16_raw(sp.wrapping_sub(1), position[1]);
+                self.write_mem16_raw(sp.wrapping_sub(2), position[0]);
+                self.w(V16::SP, sp.wrapping_sub(2));
                 AddressMove::To(new_pc)
             }
             AddImmAndFlagToA => {
                 let add = self.next_byte();
-                let carry = if self.carry_flag() { 1 } else { 0 };
-                let a = self.r(V8::A);
-                let (temp, overflow1) = a.overflowing_add(add);
-                let (res, overflow2) = temp.overflowing_add(carry);
-                self.set_carry(overflow1 || overflow2);
-                self.set_zero(res == 0);
-                self.set_subtract(false);
-                self.set_half_carry_add(a, add + carry);
-                self.w(V8::A, res);
-                AddressMove::Add(1)
+                self.add_with_carry(add);
+                AddressMove::Add(length)
             }
             SubImmAndFlagToA => {
                 let sub = self.next_byte();
-                let carry = if self.carry_flag() { 1 } else { 0 };
-                let a = self.r(V8::A);
-                let (temp, overflow1) = a.overflowing_sub(sub);
-                let (res, overflow2) = temp.overflowing_sub(carry);
-                self.set_carry(overflow1 || overflow2);
-                self.set_zero(res == 0);
-                self.set_subtract(true);
-                self.set_half_carry_sub(a, sub + carry);
-                self.w(V8::A, res);
-                AddressMove::Add(1)
+                self.sub_with_carry(sub);
+                AddressMove::Add(length)
             }
             XorImmToA => {
                 let xor = self.next_byte();
@@ -967,7 +1840,7 @@ impl Cpu {
                 self.set_subtract(false);
                 self.set_half_carry(false);
                 self.w(V8::A, res);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             CompareImmToA => {
                 let cmp = self.next_byte();
@@ -976,166 +1849,181 @@ impl Cpu {
                 self.set_half_carry_sub(a, cmp);
                 self.set_carry(a < cmp);
                 self.set_subtract(true);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             StoreAToIoImm => {
-                todo!()
+                let addr = 0xFF00 | self.next_byte() as u16;
+                let a = self.r(V8::A);
+                self.write_mem16_raw(addr, a);
+                AddressMove::Add(length)
             }
             ReadAFromIoImm => {
-                let lower = 0xff;
-                let upper = self.next_byte();
-                let content = self.bus.fetch(u16::from_ne_bytes([lower, upper]));
+                let addr = 0xFF00 | self.next_byte() as u16;
+                let content = self.bus.fetch(addr);
                 self.w(V8::A, content);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             StoreAToIoC => {
-                todo!()
+                let addr = 0xFF00 | self.r(V8::C) as u16;
+                let a = self.r(V8::A);
+                self.write_mem16_raw(addr, a);
+                AddressMove::Add(length)
             }
             ReadAFromIoC => {
-                let lower = 0xff;
-                let upper = self.r(V8::C);
-                let content = self.bus.fetch(u16::from_ne_bytes([lower, upper]));
+                let addr = 0xFF00 | self.r(V8::C) as u16;
+                let content = self.bus.fetch(addr);
                 self.w(V8::A, content);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             DisableInterrupts => {
-                todo!()
+                self.ime = false;
+                self.ime_pending = false;
+                AddressMove::Add(length)
             }
             AddImmAsSignedToSp => {
-                todo!() // scary command..
+                let sp = self.sp_plus_signed_imm();
+                self.w(V16::SP, sp);
+                AddressMove::Add(length)
             }
             StoreAinMemHl => {
-                self.write_mem8(V16::HL, V8::A);
-                AddressMove::Add(1)
+                let addr = self.next_word();
+                let a = self.r(V8::A);
+                self.write_mem16_raw(addr, a);
+                AddressMove::Add(length)
             }
             LoadAfromMemHl => {
-                let hl = self.r(V16::HL);
-                let content = self.bus.fetch(hl);
+                let addr = self.next_word();
+                let content = self.bus.fetch(addr);
                 self.w(V8::A, content);
-                AddressMove::Add(1)
+                AddressMove::Add(length)
             }
             LoadSignedImmPlusSpInHl => {
-                todo!()
+                let hl = self.sp_plus_signed_imm();
+                self.w(V16::HL, hl);
+                AddressMove::Add(length)
             }
             LoadHlinSp => {
-                todo!()
+                let hl = self.r(V16::HL);
+                self.w(V16::SP, hl);
+                AddressMove::Add(length)
             }
             EnableInterrupts => {
-                todo!()
+                self.ime_pending = true;
+                AddressMove::Add(length)
             }
             TwoByteInstruction => {
-                todo!()
+                return Err(DecodeError::Unimplemented(op));
             }
-        }
+            Illegal => {
+                self.set_mode(CpuMode::Locked { op });
+                AddressMove::Add(0)
+            }
+        })
+    }
+    /// `ADC A,x`, adds `v` and the carry to A
+    fn add_with_carry(&mut self, v: u8) {
+        let carry = self.carry_flag() as u8;
+        let a = self.r(V8::A);
+        let res = a.wrapping_add(v).wrapping_add(carry);
+        self.set_carry(a as u16 + v as u16 + carry as u16 > 0xFF);
+        self.set_zero(res == 0);
+        self.set_subtract(false);
+        self.set_half_carry((a & 0xf) + (v & 0xf) + carry > 0xf);
+        self.w(V8::A, res);
+    }
+    /// `SBC A,x`, subtracts `v` and the carry from A
+    fn sub_with_carry(&mut self, v: u8) {
+        let carry = self.carry_flag() as u8;
+        let a = self.r(V8::A);
+        let res = a.wrapping_sub(v).wrapping_sub(carry);
+        self.set_carry((a as u16) < v as u16 + carry as u16);
+        self.set_zero(res == 0);
+        self.set_subtract(true);
+        self.set_half_carry(a & 0xf < (v & 0xf) + carry);
+        self.w(V8::A, res);
+    }
+    /// SP plus the signed operand, for `ADD SP,e8` and `LD HL,SP+e8`.
+    /// Z and N are reset, H and C come from adding the operand to the low byte of SP unsigned.
+    fn sp_plus_signed_imm(&mut self) -> u16 {
+        let offset = self.next_byte();
+        let sp = self.r(V16::SP);
+        let half_carry = (sp & 0x0F) + (offset as u16 & 0x0F) > 0x0F;
+        let carry = (sp & 0xFF) + offset as u16 > 0xFF;
+        self.w(V8::F, (half_carry as u8) << 5 | (carry as u8) << 4);
+        sp.wrapping_add(offset as i8 as u16)
     }
     /// returns true if the subtraction flag is set
-    /// increments the cycles by one
     fn subtract_flag(&mut self) -> bool {
         let current = self.r(V8::F);
-        current & 0x80 != 0
+        current & SUBTRACT != 0
     }
     /// returns true if the zero flag is set
-    /// increments the cycles by one
     fn zero_flag(&mut self) -> bool {
         let current = self.r(V8::F);
-        current & 0x40 != 0
+        current & ZERO != 0
     }
     /// returns true if the half carry flag is set
-    /// increments the cycles by one
     fn half_carry_flag(&mut self) -> bool {
         let current = self.r(V8::F);
-        current & 0x20 != 0
+        current & HALF_CARRY != 0
     }
     /// returns true if the carry flag is set
-    /// increments the cycles by one
     fn carry_flag(&mut self) -> bool {
         let current = self.r(V8::F);
-        current & 0x10 != 0
+        current & CARRY != 0
     }
-
-    fn set_subtract(&mut self, v: bool) {
-        let flag_reg = V8::F;
-        let current = flag_reg.read(self);
+    /// Sets or clears the bits of `mask` in F
+    fn set_flag(&mut self, mask: u8, v: bool) {
+        let current = self.r(V8::F);
         if v {
-            flag_reg.write(self, current | 0x80);
+            self.w(V8::F, current | mask);
         } else {
-            flag_reg.write(self, current ^ 0x80);
+            self.w(V8::F, current & !mask);
         }
     }
+    fn set_subtract(&mut self, v: bool) {
+        self.set_flag(SUBTRACT, v);
+    }
     fn set_carry(&mut self, v: bool) {
-        let flag_reg = V8::F;
-        let current = flag_reg.read(self);
-        if v {
-            flag_reg.write(self, current | 0x10);
-        } else {
-            flag_reg.write(self, current ^ 0x10);
-        }
+        self.set_flag(CARRY, v);
     }
     fn set_half_carry(&mut self, v: bool) {
-        let flag_reg = V8::F;
-        let current = flag_reg.read(self);
-        if v {
-            flag_reg.write(self, current | 0x20);
-        } else {
-            flag_reg.write(self, current ^ 0x20);
-        }
+        self.set_flag(HALF_CARRY, v);
     }
     fn set_half_carry_add(&mut self, v1: u8, v2: u8) {
-        let v = (v1 & 0xf) + (v2 & 0xf) > 0xf;
-        let flag_reg = V8::F;
-        let current = flag_reg.read(self);
-        if v {
-            flag_reg.write(self, current | 0x20);
-        } else {
-            flag_reg.write(self, current ^ 0x20);
-        }
+        self.set_half_carry((v1 & 0xf) + (v2 & 0xf) > 0xf);
     }
     fn set_half_carry_sub(&mut self, v1: u8, v2: u8) {
-        let v = (v2 & 0xf) > (v1 & 0xf);
-        let flag_reg = V8::F;
-        let current = flag_reg.read(self);
-        if v {
-            flag_reg.write(self, current | 0x20);
-        } else {
-            flag_reg.write(self, current ^ 0x20);
-        }
+        self.set_half_carry((v2 & 0xf) > (v1 & 0xf));
     }
     fn set_zero(&mut self, v: bool) {
-        let flag_reg = V8::F;
-        let current = flag_reg.read(self);
-        if v {
-            flag_reg.write(self, current | 0x40);
-        } else {
-            flag_reg.write(self, current ^ 0x40);
-        }
+        self.set_flag(ZERO, v);
     }
+    /// `RLCA`, bit 7 goes to the carry and to bit 0
     fn rotate_left_circle(&mut self, v: u8) -> u8 {
-        self.set_carry(v & 0xf0 != 0);
-        self.set_zero(v == 0);
-        self.set_subtract(false);
-        self.set_half_carry(false);
-        (v << 1) | (v >> 7)
+        self.set_rotate_flags(v & 0x80 != 0);
+        v.rotate_left(1)
     }
+    /// `RLA`, bit 7 goes to the carry and the carry to bit 0
     fn rotate_left(&mut self, v: u8) -> u8 {
-        self.set_carry(v & 0xf0 != 0);
-        self.set_zero(v == 0);
-        self.set_subtract(false);
-        self.set_half_carry(false);
-        v << 1
+        let carry = self.carry_flag() as u8;
+        self.set_rotate_flags(v & 0x80 != 0);
+        v << 1 | carry
     }
+    /// `RRCA`, bit 0 goes to the carry and to bit 7
     fn rotate_right_circle(&mut self, v: u8) -> u8 {
-        self.set_carry(v & 0x0 != 0);
-        self.set_zero(v == 0);
-        self.set_subtract(false);
-        self.set_half_carry(false);
-        (v >> 1) | ((v % 2) << 7)
+        self.set_rotate_flags(v & 0x01 != 0);
+        v.rotate_right(1)
     }
+    /// `RRA`, bit 0 goes to the carry and the carry to bit 7
     fn rotate_right(&mut self, v: u8) -> u8 {
-        self.set_carry(v & 0x0 != 0);
-        self.set_zero(v == 0);
-        self.set_subtract(false);
-        self.set_half_carry(false);
-        v >> 1
+        let carry = self.carry_flag() as u8;
+        self.set_rotate_flags(v & 0x01 != 0);
+        v >> 1 | carry << 7
+    }
+    /// The rotations of A reset Z, N and H and set the carry to the bit shifted out
+    fn set_rotate_flags(&mut self, carry: bool) {
+        self.w(V8::F, if carry { CARRY } else { 0 });
     }
 }
 
@@ -1147,6 +2035,7 @@ pub trait Write {
     type Value;
     fn write(&self, cpu: &mut Cpu, v: Self::Value);
 }
+/// The halves of the register pairs, the first register of a pair is its high byte
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum V8 {
     A,
@@ -1162,8 +2051,8 @@ impl Read for V8 {
     type Value = u8;
 
     fn read(&self, cpu: &Cpu) -> Self::Value {
-        let left = |i: usize| cpu.registers[i].to_ne_bytes()[0];
-        let right = |i: usize| cpu.registers[i].to_ne_bytes()[1];
+        let left = |i: usize| cpu.registers[i].to_be_bytes()[0];
+        let right = |i: usize| cpu.registers[i].to_be_bytes()[1];
         match self {
             V8::B => left(0),
             V8::C => right(0),
@@ -1180,24 +2069,24 @@ impl Write for V8 {
     type Value = u8;
     fn write(&self, cpu: &mut Cpu, v: Self::Value) {
         let set_left = |i: usize, v: u8| {
-            let right = cpu.registers[i].to_ne_bytes()[1];
+            let right = cpu.registers[i].to_be_bytes()[1];
 
-            return u16::from_ne_bytes([v, right]);
+            return u16::from_be_bytes([v, right]);
         };
         let set_right = |i: usize, v: u8| {
-            let left = cpu.registers[i].to_ne_bytes()[0];
-            return u16::from_ne_bytes([left, v]);
+            let left = cpu.registers[i].to_be_bytes()[0];
+            return u16::from_be_bytes([left, v]);
         };
         use V8::*;
         match self {
             B => cpu.registers[0] = set_left(0, v),
             C => cpu.registers[0] = set_right(0, v),
-            D => cpu.registers[0] = set_left(1, v),
-            E => cpu.registers[0] = set_right(1, v),
-            H => cpu.registers[0] = set_left(2, v),
-            L => cpu.registers[0] = set_right(2, v),
-            A => cpu.registers[0] = set_left(3, v),
-            F => cpu.registers[0] = set_right(3, v),
+            D => cpu.registers[1] = set_left(1, v),
+            E => cpu.registers[1] = set_right(1, v),
+            H => cpu.registers[2] = set_left(2, v),
+            L => cpu.registers[2] = set_right(2, v),
+            A => cpu.registers[3] = set_left(3, v),
+            F => cpu.registers[3] = set_right(3, v),
         }
     }
 }
@@ -1250,3 +2139,517 @@ impl Write for V16 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::Bus,
+        frontend::{Buttons, SharedButtons},
+        interrupt::{Interrupt, IE, IF},
+        testing::{micro_rom, run_rom, ENTRY},
+    };
+
+    use super::*;
+
+    #[test]
+    fn registers8() -> Result<(), String> {
+        let registers = [V8::A, V8::B, V8::C, V8::D, V8::E, V8::F, V8::H, V8::L];
+        let mut cpu = Cpu::new(Bus::default());
+        for (i, register) in registers.into_iter().enumerate() {
+            cpu.w(register, 0x10 + i as u8);
+        }
+        for (i, register) in registers.into_iter().enumerate() {
+            let read = cpu.r(register);
+            if read != 0x10 + i as u8 {
+                return Err(format!(
+                    "{register:?} wrote {:#04x}, read {read:#04x}",
+                    0x10 + i
+                ));
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn registers16() -> Result<(), String> {
+        let registers = [V16::AF, V16::BC, V16::DE, V16::HL, V16::PC, V16::SP];
+        let mut cpu = Cpu::new(Bus::default());
+        for (i, register) in registers.into_iter().enumerate() {
+            cpu.w(register, 0x1111 * (i as u16 + 1));
+        }
+        for (i, register) in registers.into_iter().enumerate() {
+            let read = cpu.r(register);
+            if read != 0x1111 * (i as u16 + 1) {
+                return Err(format!(
+                    "{register:?} wrote {:#06x}, read {read:#06x}",
+                    0x1111 * (i + 1)
+                ));
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn rom_halt() -> Result<(), String> {
+        let (mut cpu, _) = run_rom(micro_rom(&[0x00, 0x00, 0x76]), |_| {});
+        let pc = cpu.r(V16::PC);
+        if *cpu.mode() != CpuMode::Halt || pc != ENTRY + 3 {
+            return Err(format!(
+                "{:?} at {pc:#06x}, expected Halt at {:#06x}",
+                cpu.mode(),
+                ENTRY + 3
+            ));
+        }
+        Ok(())
+    }
+    #[test]
+    fn rom_store() -> Result<(), String> {
+        // ld hl, 0xC000; ld a, 0x42; ld (hl), a; halt
+        let (_, ram) = run_rom(
+            micro_rom(&[0x21, 0x00, 0xC0, 0x3E, 0x42, 0x77, 0x76]),
+            |_| {},
+        );
+        let stored = ram.read().unwrap()[0xC000];
+        if stored != 0x42 {
+            return Err(format!("0xC000 holds {stored:#04x} instead of 0x42"));
+        }
+        Ok(())
+    }
+    /// Without a boot rom the registers start as the DMG and the CGB boot roms leave them
+    #[test]
+    fn post_boot_registers() -> Result<(), String> {
+        for (cgb_flag, expected) in [
+            (0x00, [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]),
+            (0x80, [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D]),
+        ] {
+            let mut rom = micro_rom(&[]);
+            rom[0x143] = cgb_flag;
+            let cartridge = Cartridge::from_bytes(rom).map_err(|e| e.to_string())?;
+            let mut cpu = Cpu::new(Bus::default().with_cartridge(cartridge));
+            cpu.skip_boot_rom();
+            let state = cpu.state();
+            let registers = [
+                state.a, state.f, state.b, state.c, state.d, state.e, state.h, state.l,
+            ];
+            if registers != expected || (state.sp, state.pc) != (0xFFFE, ENTRY) {
+                return Err(format!("{cgb_flag:#04x} started with {state:x?}"));
+            }
+        }
+        Ok(())
+    }
+    /// `ret nz` takes 2 machine cycles when it stays and 5 when it returns
+    #[test]
+    fn instruction_timings() -> Result<(), String> {
+        let mut cycles = Vec::new();
+        for f in [0xF0, 0x00] {
+            let mut bus = Bus::flat();
+            bus.poke(0xC000, 0xC0);
+            let mut cpu = Cpu::new(bus);
+            cpu.set_state(CpuState {
+                f,
+                sp: 0xD000,
+                pc: 0xC000,
+                ..CpuState::default()
+            });
+            cycles.push(cpu.execute_next().map_err(|e| e.to_string())?);
+        }
+        if cycles != [8, 20] {
+            return Err(format!("took {cycles:?}, expected [8, 20]"));
+        }
+        Ok(())
+    }
+    /// Runs the instruction in `bytes` at 0xC000 on a flat bus
+    fn run_instruction(bytes: &[u8], state: CpuState) -> Result<Cpu, String> {
+        let mut bus = Bus::flat();
+        for (i, byte) in bytes.iter().enumerate() {
+            bus.poke(0xC000 + i as u16, *byte);
+        }
+        let mut cpu = Cpu::new(bus);
+        cpu.set_state(CpuState {
+            pc: 0xC000,
+            ..state
+        });
+        cpu.execute_next().map_err(|e| e.to_string())?;
+        Ok(cpu)
+    }
+    /// Every instruction which does not jump moves the pc past its operands
+    #[test]
+    fn instruction_lengths() -> Result<(), String> {
+        const BRANCHES: [&str; 5] = ["JR", "JP", "CALL", "RET", "RST"];
+        for op in 0..=0xFF {
+            let info = opcodes::info(op);
+            let branches = BRANCHES.iter().any(|b| info.mnemonic.starts_with(b));
+            if branches || op == 0xCB || matches!(info.instruction(), Some(Instruction::Illegal)) {
+                continue;
+            }
+            let state = CpuState {
+                sp: 0xD000,
+                h: 0xC1,
+                ..CpuState::default()
+            };
+            let cpu = run_instruction(&[op, 0x00, 0xD1], state)
+                .map_err(|e| format!("{}: {e}", info.mnemonic))?;
+            let length = cpu.state().pc.wrapping_sub(0xC000);
+            if length != info.length as u16 {
+                return Err(format!(
+                    "{op:#04x} {} moved the pc by {length}, expected {}",
+                    info.mnemonic, info.length
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// `jr e8` jumps relative to the end of the instruction, backwards for negative offsets
+    #[test]
+    fn relative_jumps() -> Result<(), String> {
+        for (distance, target) in [(0x02, 0xC004), (0xFE, 0xC000), (0x80, 0xBF82)] {
+            let cpu = run_instruction(&[0x18, distance], CpuState::default())?.state();
+            if cpu.pc != target {
+                return Err(format!(
+                    "jr {distance:#04x} went to {:#06x}, expected {target:#06x}",
+                    cpu.pc
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// `call z, $d000` skips its operands when Z is clear and returns behind them otherwise
+    #[test]
+    fn conditional_calls() -> Result<(), String> {
+        for (f, pc, sp) in [(0x00, 0xC003, 0xE000), (0x80, 0xD000, 0xDFFE)] {
+            let state = CpuState {
+                f,
+                sp: 0xE000,
+                ..CpuState::default()
+            };
+            let cpu = run_instruction(&[0xCC, 0x00, 0xD0], state)?;
+            let state = cpu.state();
+            let pushed = [cpu.bus().fetch(0xDFFE), cpu.bus().fetch(0xDFFF)];
+            if (state.pc, state.sp) != (pc, sp) || (f != 0 && pushed != [0x03, 0xC0]) {
+                return Err(format!(
+                    "call with F = {f:#04x} gave {state:x?}, pushed {pushed:02x?}"
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// `cp a, b` only sets the flags
+    #[test]
+    fn compare_register() -> Result<(), String> {
+        for (b, f) in [(0x42, 0xC0), (0x43, 0x70), (0x12, 0x40)] {
+            let state = CpuState {
+                a: 0x42,
+                b,
+                ..CpuState::default()
+            };
+            let cpu = run_instruction(&[0xB8], state)?.state();
+            if (cpu.a, cpu.f, cpu.pc) != (0x42, f, 0xC001) {
+                return Err(format!(
+                    "cp a, {b:#04x} gave {cpu:x?}, expected F = {f:#04x}"
+                ));
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn high_page_and_signed_sp() -> Result<(), String> {
+        // ldh ($80), a and ld ($ff00+c), a
+        for (bytes, c) in [(&[0xE0, 0x80][..], 0x00), (&[0xE2][..], 0x80)] {
+            let state = CpuState {
+                a: 0x42,
+                c,
+                ..CpuState::default()
+            };
+            let cpu = run_instruction(bytes, state)?;
+            let stored = cpu.bus().fetch(0xFF80);
+            if stored != 0x42 || cpu.state().pc != 0xC000 + bytes.len() as u16 {
+                return Err(format!("{bytes:02x?} stored {stored:#04x}"));
+            }
+        }
+        // ldh a, ($80) and ld a, ($ff00+c)
+        for (bytes, c) in [(&[0xF0, 0x80][..], 0x00), (&[0xF2][..], 0x80)] {
+            let mut bus = Bus::flat();
+            bus.poke(0xFF80, 0x42);
+            for (i, byte) in bytes.iter().enumerate() {
+                bus.poke(0xC000 + i as u16, *byte);
+            }
+            let mut cpu = Cpu::new(bus);
+            cpu.set_state(CpuState {
+                c,
+                pc: 0xC000,
+                ..CpuState::default()
+            });
+            cpu.execute_next().map_err(|e| e.to_string())?;
+            let state = cpu.state();
+            if state.a != 0x42 || state.pc != 0xC000 + bytes.len() as u16 {
+                return Err(format!("{bytes:02x?} loaded {state:x?}"));
+            }
+        }
+        // ld hl, sp+1 carries out of both nibbles of the low byte
+        let state = CpuState {
+            sp: 0x00FF,
+            f: 0xC0,
+            ..CpuState::default()
+        };
+        let mut cpu = run_instruction(&[0xF8, 0x01], state)?;
+        let hl = cpu.r(V16::HL);
+        let state = cpu.state();
+        if (hl, state.f, state.sp) != (0x0100, 0x30, 0x00FF) {
+            return Err(format!("ld hl, sp+1 gave HL = {hl:#06x} and {state:x?}"));
+        }
+        // add sp, -2 without a carry
+        let state = CpuState {
+            sp: 0x1000,
+            ..CpuState::default()
+        };
+        let cpu = run_instruction(&[0xE8, 0xFE], state)?.state();
+        if (cpu.sp, cpu.f) != (0x0FFE, 0x00) {
+            return Err(format!("add sp, -2 gave {cpu:x?}"));
+        }
+        // ld sp, hl
+        let state = CpuState {
+            h: 0xD0,
+            l: 0x12,
+            ..CpuState::default()
+        };
+        let mut cpu = run_instruction(&[0xF9], state)?;
+        let hl = cpu.r(V16::HL);
+        if cpu.state().sp != hl {
+            return Err(format!("ld sp, hl gave SP = {:#06x}", cpu.state().sp));
+        }
+        Ok(())
+    }
+    #[test]
+    fn rom_halt_bug() -> Result<(), String> {
+        // halt; inc a; halt with a pending interrupt and IME off runs inc a twice
+        let (mut cpu, _) = run_rom(micro_rom(&[0x76, 0x3C, 0x76]), |bus| {
+            bus.write_mem(IE, Interrupt::Timer.bit());
+            bus.write_mem(IF, Interrupt::Timer.bit());
+        });
+        let a = cpu.r(V8::A);
+        if *cpu.mode() == CpuMode::Halt || a != 2 {
+            return Err(format!(
+                "{:?} with A = {a}, expected Run with A = 2",
+                cpu.mode()
+            ));
+        }
+        Ok(())
+    }
+    #[test]
+    fn rom_stop() -> Result<(), String> {
+        let buttons = SharedButtons::default();
+        let (mut cpu, _) = run_rom(micro_rom(&[0x10, 0x00, 0x76]), |bus| {
+            *bus = std::mem::take(bus).with_input(buttons.clone());
+        });
+        cpu.step();
+        if *cpu.mode() != CpuMode::Stop {
+            return Err(format!("{:?} without a button pressed", cpu.mode()));
+        }
+        buttons.set(Buttons(Buttons::A));
+        cpu.step();
+        if *cpu.mode() != CpuMode::Halt {
+            return Err(format!("{:?} after pressing A, expected Halt", cpu.mode()));
+        }
+        Ok(())
+    }
+    /// The gui shows the buttons the game saw, a movie's instead of the held ones
+    #[test]
+    fn published_buttons() -> Result<(), String> {
+        let buttons = SharedButtons::default();
+        buttons.set(Buttons(Buttons::A));
+        let (mut cpu, _) = run_rom(micro_rom(&[0x18, 0xFE]), |bus| {
+            *bus = std::mem::take(bus).with_input(buttons.clone());
+        });
+        for (input_override, expected) in
+            [(None, Buttons::A), (Some(Buttons::START), Buttons::START)]
+        {
+            cpu.bus.set_input_override(input_override.map(Buttons));
+            cpu.step_frame();
+            let shown = cpu.bus.status().buttons().0;
+            if shown != expected {
+                return Err(format!("{shown:#04x} shown, {expected:#04x} held"));
+            }
+        }
+        Ok(())
+    }
+    /// EI is delayed by one instruction, then the pending vblank jumps to its vector
+    #[test]
+    fn interrupt_dispatch() -> Result<(), String> {
+        // ei; nop; halt, the handler halts as well
+        let mut rom = micro_rom(&[0xFB, 0x00, 0x76]);
+        let vector = Interrupt::VBlank.vector();
+        rom[vector as usize] = 0x76;
+        let (mut cpu, ram) = run_rom(rom, |bus| {
+            bus.write_mem(IE, Interrupt::VBlank.bit());
+            bus.request_interrupt(Interrupt::VBlank);
+        });
+        let pc = cpu.r(V16::PC);
+        if pc != vector + 1 {
+            return Err(format!("halted at {pc:#06x} instead of in the handler"));
+        }
+        let sp = cpu.r(V16::SP);
+        if sp != 0xFFFC {
+            return Err(format!("sp is {sp:#06x} after pushing the return address"));
+        }
+        if ram.read().unwrap()[IF] & Interrupt::VBlank.bit() != 0 {
+            return Err("IF was not acknowledged".to_string());
+        }
+        Ok(())
+    }
+    /// Going back to a snapshot restores the registers and the memory
+    #[test]
+    fn practice_snapshot() -> Result<(), String> {
+        let bus = Bus::default();
+        let ram = bus.ram();
+        let mut cpu = Cpu::new(bus);
+        cpu.w(V16::SP, 0xDFF0);
+        cpu.write_mem16_raw(0xC123, 0x42);
+        let snapshot = cpu.snapshot();
+        cpu.w(V16::SP, 0xFFFE);
+        cpu.write_mem16_raw(0xC123, 0x00);
+        cpu.restore(&snapshot).map_err(|e| e.to_string())?;
+        let sp = cpu.r(V16::SP);
+        if sp != 0xDFF0 {
+            return Err(format!("SP is {sp:#06x} after restoring"));
+        }
+        let byte = ram.read().unwrap()[0xC123];
+        if byte != 0x42 {
+            return Err(format!("0xC123 holds {byte:#04x} after restoring"));
+        }
+        Ok(())
+    }
+
+    /// Saving over a slot keeps the state it held as a backup
+    #[test]
+    fn slot_backups() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("gba-test-backups-{}", std::process::id()));
+        let slots = Slots::new(dir.clone());
+        let mut cpu = Cpu::new(Bus::default()).with_slots(slots.clone());
+        let result = (|| {
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            for _ in 0..2 {
+                slots.request(SlotRequest::Save(0));
+                cpu.update_slots();
+            }
+            match (slots.result(), slots.backups(0, 0).len()) {
+                (Some((_, Ok(()))), 1) => Ok(()),
+                (result, backups) => Err(format!("{backups} backups after {result:?}")),
+            }
+        })();
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// Loading a state older than the battery save warns and backs the save up first
+    #[test]
+    fn older_state_than_save() -> Result<(), String> {
+        use crate::cartridge::{Cartridge, CARTRIDGE_TYPE, ROM_BANK_SIZE};
+        use std::{fs, time::SystemTime};
+
+        let dir = std::env::temp_dir().join(format!("gba-test-interlock-{}", std::process::id()));
+        let rom_path = dir.join("save.gb");
+        let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+        // MBC1 with ram and battery, one bank
+        rom[CARTRIDGE_TYPE] = 0x03;
+        rom[CARTRIDGE_TYPE + 2] = 0x02;
+        let slots = Slots::new(dir.clone());
+        let result = (|| {
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            fs::write(&rom_path, &rom).map_err(|e| e.to_string())?;
+            let cartridge = Cartridge::load(&rom_path).map_err(|e| e.to_string())?;
+            let crc = cartridge.crc();
+            let bus = Bus::default().with_cartridge(cartridge);
+            let mut cpu = Cpu::new(bus).with_slots(slots.clone());
+            slots.request(SlotRequest::Save(0));
+            cpu.update_slots();
+            cpu.bus.save();
+            let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+            fs::File::options()
+                .write(true)
+                .open(slots.path(crc, 0))
+                .and_then(|state| state.set_modified(hour_ago))
+                .map_err(|e| e.to_string())?;
+            slots.request(SlotRequest::Load(0));
+            cpu.update_slots();
+            let backups =
+                backup::list(&rom_path.with_extension("sav")).map_err(|e| e.to_string())?;
+            match (slots.result(), slots.older_than_save(), backups.len()) {
+                (Some((_, Ok(()))), true, 1) => Ok(()),
+                (result, older, backups) => Err(format!(
+                    "{result:?}, older than the save: {older}, {backups} backups of the save"
+                )),
+            }
+        })();
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// The state saved on exit is loaded once and then deleted
+    #[test]
+    fn suspend_and_resume() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("gba-test-suspend-{}", std::process::id()));
+        let slots = Slots::new(dir.clone());
+        let result = (|| {
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let (cpu, _) = run_rom(micro_rom(&[0x3E, 0x42, 0x76]), |_| {});
+            let mut cpu = cpu.with_slots(slots.clone());
+            let crc = cpu.bus.status().rom_crc();
+            cpu.suspend();
+            cpu.w(V8::A, 0x00);
+            slots.request(SlotRequest::Resume);
+            cpu.update_slots();
+            match (slots.result(), cpu.r(V8::A), slots.can_resume(crc)) {
+                (Some((_, Ok(()))), 0x42, false) => Ok(()),
+                (result, a, kept) => Err(format!(
+                    "{result:?}, A is {a:#04x}, the state is kept: {kept}"
+                )),
+            }
+        })();
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// Running ahead shows the later frame and leaves the machine where the plain frame does
+    #[test]
+    fn run_ahead() -> Result<(), String> {
+        use crate::{
+            cartridge::Cartridge,
+            frontend::{FrameCallback, Screen},
+            vram::LCDC,
+        };
+        use std::sync::{Arc, Mutex};
+
+        // ld a, $91; ldh (LCDC), a; loop: inc b; jr loop
+        let rom = micro_rom(&[0x3E, 0x91, 0xE0, LCDC as u8, 0x04, 0x18, 0xFD]);
+        let machine = |frames: &Arc<Mutex<usize>>| -> Result<Cpu, String> {
+            let frames = frames.clone();
+            let cartridge = Cartridge::from_bytes(rom.clone()).map_err(|e| e.to_string())?;
+            let bus = Bus::default()
+                .with_cartridge(cartridge)
+                .with_video(FrameCallback(move |_: Screen| *frames.lock().unwrap() += 1));
+            let mut cpu = Cpu::new(bus);
+            cpu.skip_boot_rom();
+            Ok(cpu)
+        };
+        let plain_frames = Arc::new(Mutex::new(0));
+        let mut plain = machine(&plain_frames)?;
+        let ahead_frames = Arc::new(Mutex::new(0));
+        let mut ahead = machine(&ahead_frames)?;
+        for _ in 0..5 {
+            plain.step_frame();
+            ahead.step_frame_ahead(2);
+        }
+        let shown = (*plain_frames.lock().unwrap(), *ahead_frames.lock().unwrap());
+        if ahead.snapshot() != plain.snapshot() || shown.0 != shown.1 || shown.0 == 0 {
+            return Err(format!(
+                "B is {:#04x} instead of {:#04x}, {} frames shown instead of {}",
+                ahead.r(V8::B),
+                plain.r(V8::B),
+                shown.1,
+                shown.0
+            ));
+        }
+        if ahead.bus.status().frame() != plain.bus.status().frame() {
+            return Err("the frames run ahead were counted".to_string());
+        }
+        Ok(())
+    }
+}
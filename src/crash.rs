@@ -0,0 +1,296 @@
+use std::{
+    any::Any,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{cpu::CpuState, state};
+
+/// Instructions kept for the report
+pub const HISTORY_LEN: usize = 32;
+
+/// The addresses of the last executed instructions, a ring buffer
+#[derive(Clone, Debug)]
+pub struct History {
+    pcs: [u16; HISTORY_LEN],
+    next: usize,
+    len: usize,
+}
+impl Default for History {
+    fn default() -> Self {
+        History {
+            pcs: [0; HISTORY_LEN],
+            next: 0,
+            len: 0,
+        }
+    }
+}
+impl History {
+    /// Called by the cpu before every instruction
+    pub fn push(&mut self, pc: u16) {
+        self.pcs[self.next] = pc;
+        self.next = (self.next + 1) % HISTORY_LEN;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+    }
+    /// Oldest first, the last one is the instruction which was executing
+    pub fn pcs(&self) -> impl Iterator<Item = u16> + '_ {
+        let start = (self.next + HISTORY_LEN - self.len) % HISTORY_LEN;
+        (0..self.len).map(move |i| self.pcs[(start + i) % HISTORY_LEN])
+    }
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// One instruction of the history, disassembled from the memory after the crash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Executed {
+    pub pc: u16,
+    pub op: u8,
+    pub text: String,
+}
+
+/// What the cpu was doing when its thread died
+#[derive(Clone, Debug)]
+pub struct PostMortem {
+    pub state: CpuState,
+    pub rom_bank: u16,
+    /// Oldest first, the last one is the failing instruction
+    pub history: Vec<Executed>,
+    /// Of the rom, states only load into the same one
+    pub crc: u32,
+    /// The machine at the crash, see `Cpu::snapshot`
+    pub snapshot: Vec<u8>,
+}
+impl PostMortem {
+    pub fn failing(&self) -> Option<&Executed> {
+        self.history.last()
+    }
+}
+/// The registers and the history as they go into the report
+impl fmt::Display for PostMortem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = &self.state;
+        writeln!(
+            f,
+            "AF={:02X}{:02X} BC={:02X}{:02X} DE={:02X}{:02X} HL={:02X}{:02X}",
+            s.a, s.f, s.b, s.c, s.d, s.e, s.h, s.l
+        )?;
+        writeln!(
+            f,
+            "SP={:04X} PC={:04X} IME={} ROM bank={}",
+            s.sp, s.pc, s.ime as u8, self.rom_bank
+        )?;
+        writeln!(f)?;
+        for executed in &self.history {
+            writeln!(
+                f,
+                "{:04X}: {:02X}  {}",
+                executed.pc, executed.op, executed.text
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Information about a crashed emulation thread
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    pub message: String,
+    /// Where the report was written to, if that succeeded
+    pub path: Option<PathBuf>,
+    /// Missing when the cpu was not running yet
+    pub post_mortem: Option<PostMortem>,
+    /// Where `save_dump` writes the state, next to the report
+    dump_path: PathBuf,
+}
+impl CrashReport {
+    /// Builds a report from a panic payload and writes it into `dir`
+    pub fn from_panic(
+        payload: Box<dyn Any + Send>,
+        post_mortem: Option<PostMortem>,
+        dir: &Path,
+        config: &str,
+    ) -> Self {
+        let message = panic_message(payload.as_ref());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut details = format!("config:\n{config}\n");
+        if let Some(post_mortem) = &post_mortem {
+            details.push_str(&format!("\ncpu:\n{post_mortem}"));
+        }
+        let path = match write(
+            &dir.join(format!("crash-{timestamp}.txt")),
+            &message,
+            &details,
+        ) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                log::error!("could not write crash report: {e}");
+                None
+            }
+        };
+        CrashReport {
+            message,
+            path,
+            post_mortem,
+            dump_path: dir.join(format!("crash-{timestamp}.state")),
+        }
+    }
+    /// Writes the machine at the crash as a state file, which the slots can load
+    pub fn save_dump(&self) -> Result<PathBuf, String> {
+        let post_mortem = self
+            .post_mortem
+            .as_ref()
+            .ok_or("the cpu was not running yet")?;
+        if let Some(dir) = self.dump_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        state::write_file(&self.dump_path, post_mortem.crc, &post_mortem.snapshot)
+            .map_err(|e| e.to_string())?;
+        Ok(self.dump_path.clone())
+    }
+}
+/// The text `panic!` was called with
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown error".to_string()
+    }
+}
+fn write(path: &Path, message: &str, details: &str) -> io::Result<PathBuf> {
+    let report = format!(
+        "gba {} crash report\n\nerror: {message}\n\n{details}",
+        env!("CARGO_PKG_VERSION")
+    );
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, report)?;
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        panic::{self, AssertUnwindSafe},
+    };
+
+    use crate::{
+        bus::Bus,
+        cartridge::Cartridge,
+        cpu::Cpu,
+        serial::{LinkPort, SerialDevice},
+        testing::{micro_rom, ENTRY},
+    };
+
+    use super::*;
+
+    /// The report of a crash names the last instruction and its dump loads as a state
+    #[test]
+    fn crash_post_mortem() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("gba-selftest-crash-{}", std::process::id()));
+        let cartridge =
+            Cartridge::from_bytes(micro_rom(&[0x00, 0x00, 0x3C])).map_err(|e| e.to_string())?;
+        let mut cpu = Cpu::new(Bus::default().with_cartridge(cartridge));
+        cpu.skip_boot_rom();
+        for _ in 0..3 {
+            cpu.execute_next().map_err(|e| format!("{e:?}"))?;
+        }
+        let post_mortem = cpu.post_mortem();
+        let pcs: Vec<u16> = post_mortem
+            .history
+            .iter()
+            .map(|executed| executed.pc)
+            .collect();
+        if pcs != [ENTRY, ENTRY + 1, ENTRY + 2] {
+            return Err(format!("the history is {pcs:04x?}"));
+        }
+        match post_mortem.failing() {
+            Some(failing) if failing.op == 0x3C && failing.text == "INC A" => {}
+            failing => return Err(format!("the failing instruction is {failing:?}")),
+        }
+        let snapshot = post_mortem.snapshot.clone();
+        let crc = post_mortem.crc;
+        let report = CrashReport::from_panic(Box::new("test"), Some(post_mortem), &dir, "");
+        let result = (|| {
+            let written = fs::read_to_string(report.path.as_ref().ok_or("no report was written")?)
+                .map_err(|e| e.to_string())?;
+            if !written.contains("0102: 3C  INC A") {
+                return Err(format!(
+                    "the report misses the last instruction:\n{written}"
+                ));
+            }
+            let path = report.save_dump()?;
+            if state::read_file(&path, crc).map_err(|e| e.to_string())? != snapshot {
+                return Err("the dump is not the machine at the crash".to_string());
+            }
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// A device which breaks in the middle of a transfer
+    struct Broken;
+    impl SerialDevice for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+        fn start(&mut self, _sent: u8) {
+            panic!("the cable broke")
+        }
+    }
+
+    /// A panic inside a write to the bus poisons the ram, the report is made anyway
+    #[test]
+    fn panic_in_a_bus_write() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("gba-test-poison-{}", std::process::id()));
+        // ld a, $81; ldh (SC), a
+        let cartridge = Cartridge::from_bytes(micro_rom(&[0x3E, 0x81, 0xE0, 0x02]))
+            .map_err(|e| e.to_string())?;
+        let bus = Bus::default()
+            .with_cartridge(cartridge)
+            .with_link_port(LinkPort::new(Box::new(Broken)));
+        let ram = bus.ram();
+        let mut cpu = Cpu::new(bus);
+        cpu.skip_boot_rom();
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| {
+            cpu.step();
+            cpu.step();
+        }))
+        .err()
+        .ok_or("starting the transfer did not panic")?;
+        if !ram.is_poisoned() {
+            return Err("the panic did not poison the ram".to_string());
+        }
+        let post_mortem = panic::catch_unwind(AssertUnwindSafe(|| cpu.post_mortem()))
+            .map_err(|_| "the post-mortem panicked on the poisoned ram")?;
+        let pcs: Vec<u16> = post_mortem.history.iter().map(|e| e.pc).collect();
+        if post_mortem.state.a != 0x81 || pcs != [ENTRY, ENTRY + 2] {
+            return Err(format!(
+                "A is {:#04x} after {pcs:04x?}",
+                post_mortem.state.a
+            ));
+        }
+        let report = CrashReport::from_panic(payload, Some(post_mortem), &dir, "");
+        let written = report
+            .path
+            .as_ref()
+            .ok_or("no report was written")
+            .and_then(|path| fs::read_to_string(path).map_err(|_| "the report is unreadable"))
+            .map(|written| written.contains("AF=81") && written.contains("0102: E0  LDH"));
+        let _ = fs::remove_dir_all(&dir);
+        match written? {
+            true => Ok(()),
+            false => Err("the report misses the registers or the history".to_string()),
+        }
+    }
+}
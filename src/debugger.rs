@@ -1,10 +1,567 @@
-pub struct Debugger{
-    pub show_gui: bool,
-    pub init_cpu: bool,
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{call_stack::CallFrame, instruction::DecodeError};
+
+/// Which accesses of a watchpoint pause the cpu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
 }
-impl Default for Debugger {
-    fn default() -> Self {
-        Debugger { show_gui: true
-            , init_cpu: true }
+impl Access {
+    /// `r`, `w` or `rw`
+    pub fn parse(s: &str) -> Option<Access> {
+        match s {
+            "r" => Some(Access::Read),
+            "w" => Some(Access::Write),
+            "rw" | "wr" => Some(Access::ReadWrite),
+            _ => None,
+        }
     }
-}
\ No newline at end of file
+    pub const ALL: [Access; 3] = [Access::Read, Access::Write, Access::ReadWrite];
+    pub fn name(self) -> &'static str {
+        match self {
+            Access::Read => "r",
+            Access::Write => "w",
+            Access::ReadWrite => "rw",
+        }
+    }
+    fn matches(self, write: bool) -> bool {
+        match self {
+            Access::Read => !write,
+            Access::Write => write,
+            Access::ReadWrite => true,
+        }
+    }
+}
+
+/// Pauses on accesses to the addresses from `start` to `end`, both included
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub access: Access,
+}
+impl Watchpoint {
+    pub fn new(start: u16, end: u16, access: Access) -> Self {
+        Watchpoint {
+            start: start.min(end),
+            end: start.max(end),
+            access,
+        }
+    }
+    /// An address like `$C0A3` or a range like `$FF40-$FF4B`
+    pub fn parse_range(s: &str) -> Result<(u16, u16), String> {
+        match s.split_once('-') {
+            Some((start, end)) => Ok((parse_address(start)?, parse_address(end)?)),
+            None => parse_address(s).map(|addr| (addr, addr)),
+        }
+    }
+    fn contains(&self, addr: u16) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+}
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "${:04X} {}", self.start, self.access.name())
+        } else {
+            write!(
+                f,
+                "${:04X}-${:04X} {}",
+                self.start,
+                self.end,
+                self.access.name()
+            )
+        }
+    }
+}
+
+/// Why the cpu paused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Break {
+    Breakpoint(u16),
+    Watchpoint {
+        addr: u16,
+        write: bool,
+        value: u8,
+        /// The instruction which accessed the memory
+        pc: u16,
+    },
+    /// The requested number of instructions ran
+    Step(u16),
+    /// Run to address reached it
+    Reached(u16),
+    /// The requested frame is done, with the pc at its end
+    Frame(u16),
+    /// The requested ppu dots ran, `dot` cycles into line `ly`
+    Dot {
+        pc: u16,
+        ly: u8,
+        dot: u16,
+    },
+    /// Line `ly` starts
+    Scanline {
+        pc: u16,
+        ly: u8,
+    },
+    /// Step out returned to `pc`
+    Returned(u16),
+    /// The instruction at `pc` could not be executed
+    Fault {
+        pc: u16,
+        error: DecodeError,
+    },
+}
+
+/// A change from the gui, which the cpu makes before it continues
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Written through the bus, with the side effects a write of the cpu has
+    Memory(u16, u8),
+    /// BC, DE, HL, AF, PC or SP by their index in the cpu
+    Register(usize, u16),
+    Ime(bool),
+}
+
+/// `$C0A0` and `0xC0A0` are hex, anything else is decimal
+pub fn parse_number(s: &str) -> Result<u32, String> {
+    let parsed = if let Some(hex) = s.strip_prefix('$').or_else(|| s.strip_prefix("0x")) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|_| format!("not a number: {s}"))
+}
+pub fn parse_address(s: &str) -> Result<u16, String> {
+    u16::try_from(parse_number(s)?).map_err(|_| format!("not an address: {s}"))
+}
+
+#[derive(Debug, Default)]
+struct DebuggerInner {
+    breakpoints: Vec<u16>,
+    watchpoints: Vec<Watchpoint>,
+    /// Instructions to run before pausing, 0 if not stepping
+    steps: u32,
+    /// A breakpoint which is removed when it is reached
+    run_to: Option<u16>,
+    /// Pause when the current frame is done
+    frame_step: bool,
+    /// Ppu dots to run before pausing, 0 if not stepping
+    dots: u32,
+    /// Pause when the next line starts
+    line_step: bool,
+    /// Pause when a return leaves SP above this, the frame of the call to step out of
+    step_out: Option<u16>,
+    /// A watchpoint or a step out triggered during the current instruction
+    watch_hit: Option<Break>,
+    /// Of the cpu when it last paused or finished a frame, innermost last
+    call_stack: Vec<CallFrame>,
+    /// The last break, until the gui reports it
+    hit: Option<Break>,
+    /// Changes from the gui, in order
+    edits: Vec<Edit>,
+}
+
+/// Breakpoints, watchpoints and stepping. The console and the gui panels change them,
+/// the cpu and the bus check them. Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    inner: Arc<Mutex<DebuggerInner>>,
+    /// Set while anything is armed, so the cpu skips the lock otherwise
+    active: Arc<AtomicBool>,
+}
+impl Debugger {
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+    fn update(&self, f: impl FnOnce(&mut DebuggerInner)) {
+        let mut inner = self.inner.lock().unwrap();
+        f(&mut inner);
+        let active = !inner.breakpoints.is_empty()
+            || !inner.watchpoints.is_empty()
+            || inner.steps > 0
+            || inner.run_to.is_some()
+            || inner.frame_step
+            || inner.dots > 0
+            || inner.line_step
+            || inner.step_out.is_some();
+        self.active.store(active, Ordering::Relaxed);
+    }
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.inner.lock().unwrap().breakpoints.clone()
+    }
+    pub fn add_breakpoint(&self, addr: u16) {
+        self.update(|inner| {
+            if !inner.breakpoints.contains(&addr) {
+                inner.breakpoints.push(addr);
+            }
+        });
+    }
+    /// Returns whether there was one
+    pub fn remove_breakpoint(&self, addr: u16) -> bool {
+        let mut removed = false;
+        self.update(|inner| {
+            let before = inner.breakpoints.len();
+            inner.breakpoints.retain(|b| *b != addr);
+            removed = inner.breakpoints.len() != before;
+        });
+        removed
+    }
+    pub fn watchpoints(&self) -> Vec<Watchpoint> {
+        self.inner.lock().unwrap().watchpoints.clone()
+    }
+    /// Replaces the watchpoint starting at the same address
+    pub fn add_watchpoint(&self, watchpoint: Watchpoint) {
+        self.update(|inner| {
+            inner.watchpoints.retain(|w| w.start != watchpoint.start);
+            inner.watchpoints.push(watchpoint);
+        });
+    }
+    /// Removes the watchpoint starting at `start`
+    pub fn remove_watchpoint(&self, start: u16) -> bool {
+        let mut removed = false;
+        self.update(|inner| {
+            let before = inner.watchpoints.len();
+            inner.watchpoints.retain(|w| w.start != start);
+            removed = inner.watchpoints.len() != before;
+        });
+        removed
+    }
+    /// Runs `count` instructions, the caller unpauses the cpu
+    pub fn step(&self, count: u32) {
+        self.update(|inner| inner.steps = count);
+    }
+    /// Runs until the pc reaches `addr`, the caller unpauses the cpu
+    pub fn run_to(&self, addr: u16) {
+        self.update(|inner| inner.run_to = Some(addr));
+    }
+    /// Runs until the current frame is done, the caller unpauses the cpu
+    pub fn step_frame(&self) {
+        self.update(|inner| inner.frame_step = true);
+    }
+    /// Runs `count` ppu dots, the caller unpauses the cpu
+    pub fn step_dots(&self, count: u32) {
+        self.update(|inner| inner.dots = count);
+    }
+    /// Runs until the next line starts, the caller unpauses the cpu
+    pub fn step_line(&self) {
+        self.update(|inner| inner.line_step = true);
+    }
+    /// Runs until the innermost call returns, the caller unpauses the cpu.
+    /// Returns false outside of a call.
+    pub fn step_out(&self) -> bool {
+        let mut stepping = false;
+        self.update(|inner| {
+            inner.step_out = inner.call_stack.last().map(|frame| frame.sp);
+            stepping = inner.step_out.is_some();
+        });
+        stepping
+    }
+    pub fn call_stack(&self) -> Vec<CallFrame> {
+        self.inner.lock().unwrap().call_stack.clone()
+    }
+    /// Called by the cpu when it pauses and after every frame
+    pub fn set_call_stack(&self, frames: &[CallFrame]) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.call_stack.clear();
+        inner.call_stack.extend_from_slice(frames);
+    }
+    /// Called by the cpu after every return while active, with SP after it and `pc` returned to
+    pub fn returned(&self, sp: u16, pc: u16) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.step_out.is_some_and(|out| sp > out) {
+            inner.step_out = None;
+            inner.watch_hit = inner.watch_hit.or(Some(Break::Returned(pc)));
+        }
+    }
+    /// Whether the cpu has to tick the ppu dot by dot, checked after every instruction while active
+    pub fn is_stepping_dots(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.dots > 0 || inner.line_step
+    }
+    /// Called by the cpu after every dot while stepping dots or lines,
+    /// returns whether it should pause. `pc` is where the cpu continues.
+    pub fn after_dot(&self, pc: u16, ly: u8, dot: usize) -> bool {
+        let mut hit = None;
+        self.update(|inner| {
+            if inner.dots > 0 {
+                inner.dots -= 1;
+                if inner.dots == 0 {
+                    let dot = dot as u16;
+                    hit = Some(Break::Dot { pc, ly, dot });
+                }
+            }
+            if inner.line_step && dot == 0 {
+                hit = hit.or(Some(Break::Scanline { pc, ly }));
+            }
+            if hit.is_some() {
+                inner.dots = 0;
+                inner.line_step = false;
+                inner.hit = hit;
+            }
+        });
+        hit.is_some()
+    }
+    /// Called by the cpu at the end of every frame while active,
+    /// returns whether it should pause
+    pub fn frame_done(&self, pc: u16) -> bool {
+        let mut done = false;
+        self.update(|inner| {
+            done = std::mem::take(&mut inner.frame_step);
+            if done {
+                inner.hit = Some(Break::Frame(pc));
+            }
+        });
+        done
+    }
+    /// Writes `value` to `addr` before the cpu continues
+    pub fn poke(&self, addr: u16, value: u8) {
+        self.edit(Edit::Memory(addr, value));
+    }
+    pub fn edit(&self, edit: Edit) {
+        self.inner.lock().unwrap().edits.push(edit);
+    }
+    /// Called by the cpu after every frame and while paused
+    pub fn take_edits(&self) -> Vec<Edit> {
+        std::mem::take(&mut self.inner.lock().unwrap().edits)
+    }
+    /// The last break, once
+    pub fn take_hit(&self) -> Option<Break> {
+        self.inner.lock().unwrap().hit.take()
+    }
+    /// Called by the bus for every access while active
+    pub fn memory_access(&self, addr: u16, write: bool, value: u8, pc: u16) {
+        let mut inner = self.inner.lock().unwrap();
+        let hit = inner
+            .watchpoints
+            .iter()
+            .any(|w| w.contains(addr) && w.access.matches(write));
+        if hit && inner.watch_hit.is_none() {
+            inner.watch_hit = Some(Break::Watchpoint {
+                addr,
+                write,
+                value,
+                pc,
+            });
+        }
+    }
+    /// Called by the cpu when it can not execute the instruction at `pc`, also while inactive
+    pub fn fault(&self, pc: u16, error: DecodeError) {
+        self.update(|inner| {
+            inner.steps = 0;
+            inner.run_to = None;
+            inner.frame_step = false;
+            inner.dots = 0;
+            inner.line_step = false;
+            inner.step_out = None;
+            inner.hit = Some(Break::Fault { pc, error });
+        });
+    }
+    /// Called by the cpu after every instruction while active,
+    /// returns whether it should pause before the instruction at `pc`
+    pub fn after_instruction(&self, pc: u16) -> bool {
+        let mut hit = None;
+        self.update(|inner| {
+            hit = inner.watch_hit.take();
+            if inner.steps > 0 {
+                inner.steps -= 1;
+                if inner.steps == 0 {
+                    hit = hit.or(Some(Break::Step(pc)));
+                }
+            }
+            if inner.breakpoints.contains(&pc) {
+                hit = hit.or(Some(Break::Breakpoint(pc)));
+            }
+            if inner.run_to == Some(pc) {
+                hit = hit.or(Some(Break::Reached(pc)));
+            }
+            if hit.is_some() {
+                inner.steps = 0;
+                inner.run_to = None;
+                inner.frame_step = false;
+                inner.dots = 0;
+                inner.line_step = false;
+                inner.step_out = None;
+                inner.hit = hit;
+            }
+        });
+        hit.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bus::Bus, cartridge::Cartridge, cpu::Cpu, testing::micro_rom};
+
+    use super::*;
+
+    #[test]
+    fn debugger_breaks() -> Result<(), String> {
+        let debugger = Debugger::default();
+        if debugger.is_active() {
+            return Err("an empty debugger is active".to_string());
+        }
+        debugger.add_breakpoint(0x0150);
+        if debugger.after_instruction(0x0100) || !debugger.after_instruction(0x0150) {
+            return Err("the breakpoint at 0x0150 did not pause exactly there".to_string());
+        }
+        debugger.add_watchpoint(Watchpoint::new(0xC0A0, 0xC0A0, Access::Write));
+        debugger.memory_access(0xC0A0, false, 0x12, 0x0200);
+        if debugger.after_instruction(0x0201) {
+            return Err("a write watchpoint paused on a read".to_string());
+        }
+        debugger.memory_access(0xC0A0, true, 0x34, 0x0201);
+        debugger.after_instruction(0x0202);
+        let expected = Break::Watchpoint {
+            addr: 0xC0A0,
+            write: true,
+            value: 0x34,
+            pc: 0x0201,
+        };
+        if debugger.take_hit() != Some(expected) {
+            return Err("the write watchpoint did not report the write".to_string());
+        }
+        debugger.step(2);
+        let paused = [0x0300, 0x0301].map(|pc| debugger.after_instruction(pc));
+        if paused != [false, true] || debugger.take_hit() != Some(Break::Step(0x0301)) {
+            return Err(format!("stepping 2 instructions paused {paused:?}"));
+        }
+        debugger.run_to(0x0400);
+        if !debugger.after_instruction(0x0400) || debugger.after_instruction(0x0400) {
+            return Err("run to 0x0400 did not pause there exactly once".to_string());
+        }
+        debugger.step_frame();
+        if debugger.after_instruction(0x0401) || !debugger.frame_done(0x0402) {
+            return Err("stepping a frame did not pause at its end".to_string());
+        }
+        let bus = Bus::default().with_debugger(debugger.clone());
+        let (start, end) = Watchpoint::parse_range("$FF40-$FF4B")?;
+        debugger.add_watchpoint(Watchpoint::new(start, end, Access::Read));
+        bus.fetch(0xFF4C);
+        if debugger.after_instruction(0x0500) {
+            return Err("a read after the watched range paused".to_string());
+        }
+        bus.fetch(0xFF44);
+        debugger.after_instruction(0x0501);
+        match debugger.take_hit() {
+            Some(Break::Watchpoint { addr: 0xFF44, .. }) => Ok(()),
+            hit => Err(format!("reading LY in the watched range gave {hit:?}")),
+        }
+    }
+    #[test]
+    fn dot_stepping() -> Result<(), String> {
+        let debugger = Debugger::default();
+        // halt, the clock keeps running without interrupts
+        let bus = Bus::default().with_cartridge(Cartridge::from_bytes(micro_rom(&[0x76])).unwrap());
+        let mut cpu = Cpu::new(bus).with_debugger(debugger.clone());
+        cpu.skip_boot_rom();
+        debugger.step_line();
+        if cpu.step_frame() {
+            return Err("stepping a line ran the whole frame".to_string());
+        }
+        let (ly, dot) = cpu.bus().ppu_position();
+        match debugger.take_hit() {
+            Some(Break::Scanline {
+                pc: 0x0101,
+                ly: hit,
+            }) if hit == ly && dot == 0 => {}
+            hit => return Err(format!("stepping a line paused at dot {dot} with {hit:?}")),
+        }
+        // a halted cpu ticks 4 cycles at a time, the ppu stops in the middle of them
+        debugger.step_dots(5);
+        cpu.step_frame();
+        if cpu.bus().ppu_position() != (ly, 5) {
+            return Err(format!(
+                "5 dots later the ppu is at {:?}",
+                cpu.bus().ppu_position()
+            ));
+        }
+        debugger.step_line();
+        cpu.step_frame();
+        if cpu.bus().ppu_position() != (ly + 1, 0) {
+            return Err(format!(
+                "the next line started at {:?}",
+                cpu.bus().ppu_position()
+            ));
+        }
+        if debugger.is_active() {
+            return Err("the debugger is still stepping".to_string());
+        }
+        Ok(())
+    }
+    #[test]
+    fn call_stack() -> Result<(), String> {
+        let mut program = vec![0; 0x22];
+        // call $0110, halt
+        program[..4].copy_from_slice(&[0xCD, 0x10, 0x01, 0x76]);
+        // call $0120, nop, ret
+        program[0x10..0x15].copy_from_slice(&[0xCD, 0x20, 0x01, 0x00, 0xC9]);
+        // nop, ret
+        program[0x20..0x22].copy_from_slice(&[0x00, 0xC9]);
+        let debugger = Debugger::default();
+        let bus =
+            Bus::default().with_cartridge(Cartridge::from_bytes(micro_rom(&program)).unwrap());
+        let mut cpu = Cpu::new(bus).with_debugger(debugger.clone());
+        cpu.skip_boot_rom();
+        debugger.step(2);
+        cpu.step_frame();
+        let sites: Vec<_> = debugger
+            .call_stack()
+            .iter()
+            .map(|frame| (frame.site, frame.target, frame.return_address()))
+            .collect();
+        if sites != [(0x0100, 0x0110, 0x0103), (0x0110, 0x0120, 0x0113)] {
+            return Err(format!("two calls deep the call stack is {sites:04X?}"));
+        }
+        for (returned, depth) in [(0x0113, 1), (0x0103, 0)] {
+            if !debugger.step_out() {
+                return Err(format!("could not step out {} calls deep", depth + 1));
+            }
+            cpu.step_frame();
+            match debugger.take_hit() {
+                Some(Break::Returned(pc)) if pc == returned => {}
+                hit => return Err(format!("stepping out gave {hit:?}")),
+            }
+            if debugger.call_stack().len() != depth {
+                return Err(format!(
+                    "after returning to ${returned:04X} the stack is {:?}",
+                    debugger.call_stack()
+                ));
+            }
+        }
+        if debugger.step_out() {
+            return Err("stepped out outside of a call".to_string());
+        }
+        Ok(())
+    }
+    #[test]
+    fn memory_pokes() -> Result<(), String> {
+        let debugger = Debugger::default();
+        let mut bus = Bus::default().with_debugger(debugger.clone());
+        debugger.add_watchpoint(Watchpoint::new(0xC010, 0xC010, Access::Write));
+        debugger.poke(0xE010, 0x5A);
+        for edit in debugger.take_edits() {
+            if let Edit::Memory(addr, value) = edit {
+                bus.poke(addr, value);
+            }
+        }
+        if bus.fetch(0xC010) != 0x5A {
+            return Err("a poke into echo ram did not reach wram".to_string());
+        }
+        if debugger.after_instruction(0x0100) {
+            return Err("a poke triggered a watchpoint".to_string());
+        }
+        if !debugger.take_edits().is_empty() {
+            return Err("the pokes were not taken".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cpu::CLOCK_SPEED;
+
+/// The time of the host. The browser has no `SystemTime::now`, it panics there.
+pub fn wall_clock() -> SystemTime {
+    #[cfg(target_arch = "wasm32")]
+    return UNIX_EPOCH + std::time::Duration::from_millis(js_sys::Date::now() as u64);
+    #[cfg(not(target_arch = "wasm32"))]
+    SystemTime::now()
+}
+
+/// Seedable random numbers for the core. SplitMix64, so a seed gives the same
+/// sequence forever, unlike `StdRng` which may change with a rand update.
+#[derive(Clone, Debug)]
+pub struct EmuRng {
+    seed: u64,
+    state: u64,
+}
+impl EmuRng {
+    const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+    pub fn new(seed: u64) -> Self {
+        EmuRng { seed, state: seed }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Restarts the sequence for `frame`, so the numbers of a frame do not depend
+    /// on the frames before, e.g. after loading a state or rewinding
+    pub fn begin_frame(&mut self, frame: u64) {
+        self.state = self.seed ^ frame.wrapping_mul(Self::GAMMA);
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(Self::GAMMA);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    pub fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let random = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+}
+
+/// The only way for the core to reach randomness and the host clock.
+/// In deterministic mode both are derived from a fixed seed and the emulated cycles,
+/// so movies and netplay replay the same way on every machine.
+#[derive(Debug)]
+pub struct Host {
+    deterministic: bool,
+    rng: EmuRng,
+    /// Unix time at power on in deterministic mode
+    start_time: u64,
+}
+impl Host {
+    /// Seed and start time of every deterministic run
+    const SEED: u64 = 0;
+    const START_TIME: u64 = 0;
+    pub fn deterministic() -> Self {
+        Self::seeded(Self::SEED)
+    }
+    /// Deterministic with another seed, the same seed gives the same run
+    pub fn seeded(seed: u64) -> Self {
+        Host {
+            deterministic: true,
+            rng: EmuRng::new(seed),
+            start_time: Self::START_TIME,
+        }
+    }
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+    pub fn fill_random(&mut self, bytes: &mut [u8]) {
+        self.rng.fill_bytes(bytes);
+    }
+    pub fn random_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+    /// Called by the cpu at the start of every frame
+    pub fn begin_frame(&mut self, frame: u64) {
+        self.rng.begin_frame(frame);
+    }
+    /// Seconds since the unix epoch, for clocks like the cartridge RTC.
+    /// `cycles` are the emulated cycles since power on.
+    pub fn unix_time(&self, cycles: u64) -> u64 {
+        if self.deterministic {
+            self.start_time + cycles / CLOCK_SPEED as u64
+        } else {
+            wall_clock()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        }
+    }
+}
+impl Default for Host {
+    /// Real randomness and the wall clock
+    fn default() -> Self {
+        Host {
+            deterministic: false,
+            rng: EmuRng::new(rand::random()),
+            start_time: 0,
+        }
+    }
+}
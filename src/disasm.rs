@@ -0,0 +1,133 @@
+use crate::{
+    bus::{self, Bus},
+    opcodes::{info, prefixed_info},
+    ram::Ram,
+};
+
+/// Memory the disassembler reads from, without the side effects of a cpu read
+pub trait Memory {
+    fn peek(&self, addr: u16) -> u8;
+}
+impl Memory for Bus {
+    fn peek(&self, addr: u16) -> u8 {
+        self.fetch_op(addr).0
+    }
+}
+impl Memory for Ram {
+    fn peek(&self, addr: u16) -> u8 {
+        bus::read(self, addr)
+    }
+}
+/// A rom image, addresses past the end read 0xFF like open bus
+impl Memory for [u8] {
+    fn peek(&self, addr: u16) -> u8 {
+        self.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+}
+
+/// One disassembled instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub addr: u16,
+    /// In bytes, including the operands
+    pub len: u16,
+    pub text: String,
+}
+
+/// The instruction at `addr` with its operands filled in, like `LD A,[$FF44]`,
+/// and its length in bytes
+pub fn disassemble<M: Memory + ?Sized>(memory: &M, addr: u16) -> (String, u16) {
+    let op = memory.peek(addr);
+    let n8 = memory.peek(addr.wrapping_add(1));
+    let n16 = u16::from_le_bytes([n8, memory.peek(addr.wrapping_add(2))]);
+    let info = if op == 0xCB {
+        prefixed_info(n8)
+    } else {
+        info(op)
+    };
+    let e8 = n8 as i8;
+    let text = if info.mnemonic.starts_with("JR") {
+        // relative to the next instruction
+        let target = addr.wrapping_add(2).wrapping_add(e8 as u16);
+        info.mnemonic.replace("e8", &format!("${target:04X}"))
+    } else {
+        info.mnemonic
+            .replace("SP+e8", &format!("SP{e8:+}"))
+            .replace("e8", &e8.to_string())
+            .replace("n16", &format!("${n16:04X}"))
+            .replace("a16", &format!("${n16:04X}"))
+            .replace("n8", &format!("${n8:02X}"))
+            .replace("a8", &format!("$FF{n8:02X}"))
+    };
+    (text, info.length as u16)
+}
+/// `count` instructions from `addr` on
+pub fn disassemble_range<M: Memory + ?Sized>(memory: &M, addr: u16, count: usize) -> Vec<Line> {
+    let mut lines = Vec::with_capacity(count);
+    let mut addr = addr;
+    for _ in 0..count {
+        let (text, len) = disassemble(memory, addr);
+        lines.push(Line { addr, len, text });
+        addr = addr.wrapping_add(len);
+    }
+    lines
+}
+/// Up to `before` instructions in front of `pc`, the one at `pc` and `after` more.
+/// Instructions have different lengths, so the ones in front are found by decoding
+/// from earlier addresses until one lands on `pc`.
+pub fn around<M: Memory + ?Sized>(memory: &M, pc: u16, before: usize, after: usize) -> Vec<Line> {
+    let mut lines = Vec::new();
+    // the longest instruction has 3 bytes
+    for back in (1..=before as u16 * 3).rev() {
+        let start = pc.wrapping_sub(back);
+        let mut offset = 0;
+        let mut candidate = Vec::new();
+        while offset < back {
+            let addr = start.wrapping_add(offset);
+            let (text, len) = disassemble(memory, addr);
+            candidate.push(Line { addr, len, text });
+            offset += len;
+        }
+        if offset == back {
+            let skip = candidate.len().saturating_sub(before);
+            lines = candidate.split_off(skip);
+            break;
+        }
+    }
+    lines.extend(disassemble_range(memory, pc, after + 1));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn disassembler() -> Result<(), String> {
+        // 0x0150: LD A,[$FF44]  CP A,$90  JR NZ,$0150  CALL $1234  BIT 7,H  LD HL,SP-2
+        let mut rom = vec![0; 0x0150];
+        rom.extend([
+            0xF0, 0x44, 0xFE, 0x90, 0x20, 0xFA, 0xCD, 0x34, 0x12, 0xCB, 0x7C, 0xF8, 0xFE,
+        ]);
+        let expected = [
+            "LDH A,[$FF44]",
+            "CP A,$90",
+            "JR NZ,$0150",
+            "CALL $1234",
+            "BIT 7,H",
+            "LD HL,SP-2",
+        ];
+        let lines = disassemble_range(&rom[..], 0x0150, expected.len());
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        if texts != expected {
+            return Err(format!("disassembled {texts:?}"));
+        }
+        let around = around(&rom[..], 0x0156, 2, 1);
+        let addrs: Vec<u16> = around.iter().map(|line| line.addr).collect();
+        if addrs != [0x0152, 0x0154, 0x0156, 0x0159] {
+            return Err(format!("the lines around 0x0156 start at {addrs:04X?}"));
+        }
+        Ok(())
+    }
+}
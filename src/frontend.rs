@@ -0,0 +1,164 @@
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    mpsc::Sender,
+    Arc,
+};
+
+use crate::{
+    cpu::CpuMode,
+    ppu::{ColorFrameBuffer, FrameBuffer},
+};
+
+// Everything the core needs from a frontend. The egui gui is one implementation,
+// others only have to provide these traits to run the core.
+
+#[derive(Debug, Clone)]
+pub enum DrawSignal {
+    /// A finished frame, sent once per vblank
+    Frame(Box<FrameBuffer>),
+    /// A finished frame of a CGB game
+    ColorFrame(Box<ColorFrameBuffer>),
+    /// The cpu stopped executing at the program counter
+    CpuMode(CpuMode, u16),
+}
+
+/// Receives the picture from the core
+pub trait VideoSink: Send {
+    fn send(&mut self, signal: DrawSignal);
+}
+/// Plays the samples the core produces
+pub trait AudioSink: Send {
+    /// Stereo samples in -1.0..=1.0
+    fn queue(&mut self, samples: &[[f32; 2]]);
+    /// Samples per second the sink plays
+    fn sample_rate(&self) -> u32 {
+        48_000
+    }
+}
+/// Tells the core which buttons are held
+pub trait InputSource: Send {
+    fn buttons(&mut self) -> Buttons;
+}
+
+/// Held buttons as bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Buttons(pub u8);
+impl Buttons {
+    pub const RIGHT: u8 = 0x01;
+    pub const LEFT: u8 = 0x02;
+    pub const UP: u8 = 0x04;
+    pub const DOWN: u8 = 0x08;
+    pub const A: u8 = 0x10;
+    pub const B: u8 = 0x20;
+    pub const SELECT: u8 = 0x40;
+    pub const START: u8 = 0x80;
+    pub fn is_held(self, button: u8) -> bool {
+        self.0 & button != 0
+    }
+}
+
+/// Which frontend runs the emulator
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Frontend {
+    #[default]
+    Egui,
+    #[cfg(feature = "sdl")]
+    Sdl,
+    #[cfg(feature = "tui")]
+    Tui,
+}
+
+/// Buttons written by a frontend thread and read by the core
+#[derive(Clone, Debug, Default)]
+pub struct SharedButtons(Arc<AtomicU8>);
+impl SharedButtons {
+    pub fn get(&self) -> Buttons {
+        Buttons(self.0.load(Ordering::Relaxed))
+    }
+    pub fn set(&self, buttons: Buttons) {
+        self.0.store(buttons.0, Ordering::Relaxed);
+    }
+}
+impl InputSource for SharedButtons {
+    fn buttons(&mut self) -> Buttons {
+        Buttons(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// The channel to the gui thread
+impl VideoSink for Sender<DrawSignal> {
+    fn send(&mut self, signal: DrawSignal) {
+        let _ = Sender::send(self, signal);
+    }
+}
+
+/// A finished frame as `FrameCallback` passes it
+#[derive(Clone, Copy, Debug)]
+pub enum Screen<'a> {
+    Shades(&'a FrameBuffer),
+    Colors(&'a ColorFrameBuffer),
+}
+
+/// Calls the closure with every finished frame, for frontends which only draw
+pub struct FrameCallback<F>(pub F);
+impl<F: FnMut(Screen) + Send> VideoSink for FrameCallback<F> {
+    fn send(&mut self, signal: DrawSignal) {
+        match signal {
+            DrawSignal::Frame(frame) => (self.0)(Screen::Shades(&frame)),
+            DrawSignal::ColorFrame(frame) => (self.0)(Screen::Colors(&frame)),
+            DrawSignal::CpuMode(..) => {}
+        }
+    }
+}
+
+/// Discards all output and never presses a button, for tests and headless runs
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Null;
+impl VideoSink for Null {
+    fn send(&mut self, _signal: DrawSignal) {}
+}
+impl AudioSink for Null {
+    fn queue(&mut self, _samples: &[[f32; 2]]) {}
+}
+impl InputSource for Null {
+    fn buttons(&mut self) -> Buttons {
+        Buttons::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{bus::Bus, cartridge::Cartridge, cpu::Cpu, testing::micro_rom, vram::LCDC};
+
+    use super::*;
+
+    /// Drives the core the way an embedding frontend does, one `step_frame` per frame
+    #[test]
+    fn frame_callback() -> Result<(), String> {
+        let frames = Arc::new(AtomicUsize::new(0));
+        let counter = frames.clone();
+        let cartridge = Cartridge::from_bytes(micro_rom(&[0x76])).map_err(|e| e.to_string())?;
+        let mut bus = Bus::default()
+            .with_cartridge(cartridge)
+            .with_video(FrameCallback(move |_: Screen| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }));
+        bus.write_mem(LCDC, 0x91);
+        let mut cpu = Cpu::new(bus);
+        cpu.skip_boot_rom();
+        for _ in 0..3 {
+            if !cpu.step_frame() {
+                return Err("the frame did not finish".to_string());
+            }
+        }
+        match frames.load(Ordering::Relaxed) {
+            3 => Ok(()),
+            n => Err(format!("3 frames called back {n} times")),
+        }
+    }
+}
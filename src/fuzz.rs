@@ -0,0 +1,141 @@
+use std::panic;
+
+use crate::{
+    bus::{Bus, BOOT_ROM_DISABLE},
+    cartridge::{Cartridge, CARTRIDGE_TYPE, ROM_BANK_SIZE},
+    crash,
+    determinism::EmuRng,
+    mbc::{self, Mbc, RAM_BANK_SIZE},
+};
+
+/// Cartridge types of every controller, with and without ram, battery and clock
+const TYPES: [u8; 12] = [
+    0x00, 0x08, 0x01, 0x02, 0x03, 0x0F, 0x11, 0x13, 0x19, 0x1B, 0x1C, 0x1E,
+];
+/// Header values for no ram up to 16 banks
+const RAM_SIZES: [u8; 5] = [0x00, 0x02, 0x03, 0x04, 0x05];
+/// Longest input `run` generates
+const MAX_INPUT: usize = 512;
+
+/// Reads the input a byte at a time, 0 once it is used up
+struct Input<'a>(&'a [u8]);
+impl Input<'_> {
+    fn u8(&mut self) -> u8 {
+        let Some((first, rest)) = self.0.split_first() else {
+            return 0;
+        };
+        self.0 = rest;
+        *first
+    }
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes([self.u8(), self.u8()])
+    }
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Writes into the rom area of a controller and checks its mapping after every write.
+/// Panics if the mapping names a ram bank which does not exist, maps ram or a clock
+/// register while the ram is disabled, or does not survive a snapshot.
+pub fn mapper(data: &[u8]) {
+    let mut input = Input(data);
+    let cartridge_type = TYPES[input.u8() as usize % TYPES.len()];
+    let ram_banks = input.u8() as usize % 17;
+    let mut mbc = mbc::for_type(cartridge_type, ram_banks);
+    // only the controllers without banking keep the ram enabled
+    let mut ram_enabled = matches!(cartridge_type, 0x00 | 0x08);
+    while !input.is_empty() {
+        let addr = input.u16() & 0x7FFF;
+        let value = input.u8();
+        mbc.write(addr, value);
+        if addr < 0x2000 && !matches!(cartridge_type, 0x00 | 0x08) {
+            ram_enabled = value & 0x0F == 0x0A;
+        }
+        check_mapping(&*mbc, ram_banks, ram_enabled);
+        let mut restored = mbc::for_type(cartridge_type, ram_banks);
+        restored.load_state(&mbc.save_state());
+        assert_eq!(restored.mapping(), mbc.mapping(), "after a snapshot");
+    }
+}
+fn check_mapping(mbc: &dyn Mbc, ram_banks: usize, ram_enabled: bool) {
+    let mapping = mbc.mapping();
+    if let Some(bank) = mapping.ram {
+        assert!(ram_enabled, "ram bank {bank} mapped while disabled");
+        assert!(bank < ram_banks, "ram bank {bank} of {ram_banks}");
+    }
+    if let Some((register, _)) = mapping.rtc {
+        assert!(
+            ram_enabled,
+            "clock register {register} mapped while disabled"
+        );
+        assert!(register < 5, "clock register {register}");
+    }
+}
+
+/// Builds a cartridge from a header in the input and runs the rest as reads and
+/// writes through the bus, the memory has to stay consistent with the controller
+pub fn bus(data: &[u8]) {
+    let mut input = Input(data);
+    let banks = 2 << (input.u8() % 4);
+    let mut rom = vec![0; banks * ROM_BANK_SIZE];
+    rom[CARTRIDGE_TYPE] = TYPES[input.u8() as usize % TYPES.len()];
+    rom[CARTRIDGE_TYPE + 1] = input.u8() % 9;
+    rom[CARTRIDGE_TYPE + 2] = RAM_SIZES[input.u8() as usize % RAM_SIZES.len()];
+    // every bank holds its number at 0x1000, to see which one is mapped
+    for bank in 0..banks {
+        rom[bank * ROM_BANK_SIZE + 0x1000] = bank as u8;
+    }
+    let cartridge = Cartridge::from_bytes(rom).expect("the rom has a whole header");
+    let ram_banks = cartridge.header().ram_size.div_ceil(RAM_BANK_SIZE);
+    let mut bus = Bus::default().with_cartridge(cartridge);
+    bus.write_mem(BOOT_ROM_DISABLE, 1);
+    while !input.is_empty() {
+        let addr = input.u16();
+        match input.u8() % 4 {
+            0 => {
+                bus.fetch(addr);
+            }
+            // mostly the controller registers, they are what malformed roms break
+            1 | 2 => bus.write_mem(addr & 0x7FFF, input.u8()),
+            _ => bus.write_mem(addr, input.u8()),
+        }
+        let mapping = bus.mapping().expect("a cartridge is inserted");
+        if let Some(bank) = mapping.ram {
+            assert!(bank < ram_banks, "ram bank {bank} of {ram_banks}");
+        }
+        let romx = bus.fetch(0x5000) as usize;
+        assert_eq!(romx, mapping.romx % banks, "rom bank at 0x4000");
+    }
+}
+
+/// A target panicked
+#[derive(Debug)]
+pub struct Failure {
+    pub target: &'static str,
+    pub input: Vec<u8>,
+    pub message: String,
+}
+
+/// Runs both targets `iterations` times with random inputs, like `cargo fuzz` would
+pub fn run(iterations: u64, seed: u64) -> Result<(), Failure> {
+    let mut rng = EmuRng::new(seed);
+    panic::set_hook(Box::new(|_| {}));
+    let mut result = Ok(());
+    'outer: for _ in 0..iterations {
+        for (name, target) in [("mapper", mapper as fn(&[u8])), ("bus", bus)] {
+            let mut data = vec![0; rng.next_u64() as usize % MAX_INPUT];
+            rng.fill_bytes(&mut data);
+            if let Err(payload) = panic::catch_unwind(|| target(&data)) {
+                result = Err(Failure {
+                    target: name,
+                    input: data,
+                    message: crash::panic_message(&*payload).to_string(),
+                });
+                break 'outer;
+            }
+        }
+    }
+    let _ = panic::take_hook();
+    result
+}
@@ -0,0 +1,145 @@
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, Visuals},
+    epaint::{Color32, Stroke},
+};
+
+/// Game palettes which stay distinguishable for the common kinds of color blindness.
+/// They only differ in brightness, ordered like the gpu palette indices.
+/// The names are i18n keys.
+pub const PALETTE_PRESETS: [(&str, [[u8; 3]; 4]); 3] = [
+    (
+        "palette-grey",
+        [
+            [0xe0, 0xe0, 0xe0],
+            [0xa0, 0xa0, 0xa0],
+            [0x50, 0x50, 0x50],
+            [0x10, 0x10, 0x10],
+        ],
+    ),
+    (
+        "palette-blue-yellow",
+        [
+            [0xff, 0xf0, 0xa0],
+            [0xd0, 0xa0, 0x30],
+            [0x30, 0x50, 0xa0],
+            [0x08, 0x10, 0x30],
+        ],
+    ),
+    (
+        "palette-high-contrast",
+        [
+            [0xff, 0xff, 0xff],
+            [0xb0, 0xb0, 0xb0],
+            [0x40, 0x40, 0x40],
+            [0x00, 0x00, 0x00],
+        ],
+    ),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorBlindness {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+impl ColorBlindness {
+    const ALL: [ColorBlindness; 3] = [
+        ColorBlindness::Protanopia,
+        ColorBlindness::Deuteranopia,
+        ColorBlindness::Tritanopia,
+    ];
+    /// Approximation of how the color is perceived
+    fn simulate(self, [r, g, b]: [u8; 3]) -> Color32 {
+        let m = match self {
+            ColorBlindness::Protanopia => {
+                [[0.567, 0.433, 0.], [0.558, 0.442, 0.], [0., 0.242, 0.758]]
+            }
+            ColorBlindness::Deuteranopia => [[0.625, 0.375, 0.], [0.7, 0.3, 0.], [0., 0.3, 0.7]],
+            ColorBlindness::Tritanopia => {
+                [[0.95, 0.05, 0.], [0., 0.433, 0.567], [0., 0.475, 0.525]]
+            }
+        };
+        let [r, g, b] = [r as f32, g as f32, b as f32];
+        let channel = |row: [f32; 3]| (row[0] * r + row[1] * g + row[2] * b).min(255.) as u8;
+        Color32::from_rgb(channel(m[0]), channel(m[1]), channel(m[2]))
+    }
+}
+
+pub struct Accessibility {
+    pub ui_scale: f32,
+    pub high_contrast: bool,
+    /// pixels per point of the monitor, read on the first frame
+    native_pixels_per_point: Option<f32>,
+    applied: Option<(f32, bool)>,
+}
+impl Accessibility {
+    /// Updates the egui style if the settings changed
+    pub fn apply(&mut self, ctx: &egui::Context) {
+        if self.applied == Some((self.ui_scale, self.high_contrast)) {
+            return;
+        }
+        let native = *self
+            .native_pixels_per_point
+            .get_or_insert_with(|| ctx.pixels_per_point());
+        ctx.set_pixels_per_point(native * self.ui_scale);
+        ctx.set_visuals(if self.high_contrast {
+            high_contrast_visuals()
+        } else {
+            Visuals::dark()
+        });
+        self.applied = Some((self.ui_scale, self.high_contrast));
+    }
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, palette: &mut [[u8; 3]; 4]) {
+        ui.add(
+            egui::Slider::new(&mut self.ui_scale, 0.5..=3.0)
+                .text(i18n.tr("accessibility-ui-scale")),
+        );
+        ui.checkbox(
+            &mut self.high_contrast,
+            i18n.tr("accessibility-high-contrast"),
+        );
+        ui.separator();
+        ui.label(i18n.tr("accessibility-game-palette"));
+        ui.horizontal(|ui| {
+            for (name, preset) in PALETTE_PRESETS {
+                if ui.button(i18n.tr(name)).clicked() {
+                    *palette = preset;
+                }
+            }
+        });
+        egui::Grid::new("color_blindness").show(ui, |ui| {
+            for kind in ColorBlindness::ALL {
+                ui.label(format!("{kind:?}"));
+                for color in palette.iter() {
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(20., 20.), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2., kind.simulate(*color));
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            ui_scale: 1.,
+            high_contrast: false,
+            native_pixels_per_point: None,
+            applied: None,
+        }
+    }
+}
+fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.selection.bg_fill = Color32::from_rgb(0xff, 0xd0, 0x00);
+    let stroke = Stroke::new(2., Color32::WHITE);
+    visuals.widgets.noninteractive.bg_stroke = stroke;
+    visuals.widgets.inactive.bg_stroke = stroke;
+    visuals.widgets.inactive.fg_stroke = stroke;
+    visuals.widgets.hovered.bg_stroke = Stroke::new(2., Color32::YELLOW);
+    visuals
+}
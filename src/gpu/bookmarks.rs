@@ -0,0 +1,121 @@
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use super::i18n::I18n;
+use eframe::egui;
+use gba::{
+    annotations::{self, Annotation},
+    banked::BankedAddr,
+    ram::Ram,
+};
+
+/// Named address ranges, stored in one file per game
+pub struct Bookmarks {
+    dir: PathBuf,
+    /// File name of the loaded game, `None` before the first frame
+    loaded: Option<String>,
+    entries: Vec<Annotation>,
+    name: String,
+    comment: String,
+}
+impl Bookmarks {
+    pub fn new(dir: PathBuf) -> Self {
+        Bookmarks {
+            dir,
+            loaded: None,
+            entries: Vec::new(),
+            name: String::new(),
+            comment: String::new(),
+        }
+    }
+    /// Switches to the bookmarks of the game in `ram` if it changed
+    fn reload(&mut self, ram: &Ram) {
+        let file = format!(
+            "{}.txt",
+            ram.cartridge_title()
+                .unwrap_or_else(|| "default".to_string())
+        );
+        if self.loaded.as_ref() == Some(&file) {
+            return;
+        }
+        self.entries = annotations::load(&self.dir.join(&file)).unwrap_or_default();
+        self.loaded = Some(file);
+    }
+    fn save(&self) {
+        let Some(file) = &self.loaded else {
+            return;
+        };
+        if let Err(e) = annotations::save(&self.dir.join(file), &self.entries) {
+            log::warn!("could not save bookmarks: {e}");
+        }
+    }
+    /// Lists the bookmarks and offers to bookmark `selected` in the currently mapped `rom_bank`.
+    /// Returns the range of a clicked bookmark.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &I18n,
+        ram: &Ram,
+        rom_bank: u16,
+        selected: Option<RangeInclusive<u16>>,
+    ) -> Option<RangeInclusive<BankedAddr>> {
+        self.reload(ram);
+        let selected = selected.map(|range| {
+            BankedAddr::resolve(*range.start(), rom_bank)
+                ..=BankedAddr::resolve(*range.end(), rom_bank)
+        });
+        if let Some(annotation) = selected
+            .as_ref()
+            .and_then(|range| annotations::find(&self.entries, *range.start()))
+        {
+            ui.label(i18n.tr_args("bookmarks-inside", &[("name", &annotation.name)]));
+        }
+        let mut clicked = None;
+        let mut remove = None;
+        egui::Grid::new("bookmarks").show(ui, |ui| {
+            for (i, annotation) in self.entries.iter().enumerate() {
+                if ui.button(&annotation.name).clicked() {
+                    clicked = Some(annotation.range.clone());
+                }
+                ui.monospace(format!(
+                    "{}-{}",
+                    annotation.range.start(),
+                    annotation.range.end()
+                ));
+                ui.label(&annotation.comment);
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.entries.remove(i);
+            self.save();
+        }
+        ui.horizontal(|ui| {
+            ui.label(i18n.tr("bookmarks-name"));
+            ui.text_edit_singleline(&mut self.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label(i18n.tr("bookmarks-comment"));
+            ui.text_edit_singleline(&mut self.comment);
+        });
+        let add = egui::Button::new(i18n.tr("bookmarks-add"));
+        if ui
+            .add_enabled(selected.is_some() && !self.name.is_empty(), add)
+            .clicked()
+        {
+            if let Some(range) = selected {
+                self.entries.push(Annotation {
+                    range,
+                    name: std::mem::take(&mut self.name).replace('\t', " "),
+                    comment: std::mem::take(&mut self.comment),
+                });
+                self.entries
+                    .sort_by_key(|annotation| *annotation.range.start());
+                self.save();
+            }
+        }
+        clicked
+    }
+}
@@ -0,0 +1,70 @@
+use std::{fs, io, path::PathBuf};
+
+use super::i18n::I18n;
+use eframe::egui;
+use gba::camera::{self, PHOTO_SLOTS};
+
+/// Moves photos between a Game Boy Camera save and png files
+pub struct CameraTools {
+    sav: String,
+    png: String,
+    export_dir: PathBuf,
+    /// 1 based like in the game
+    slot: usize,
+    message: Option<String>,
+}
+impl CameraTools {
+    pub fn new(sav: PathBuf, export_dir: PathBuf) -> Self {
+        CameraTools {
+            sav: sav.display().to_string(),
+            png: String::new(),
+            export_dir,
+            slot: 1,
+            message: None,
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        egui::Grid::new("camera").show(ui, |ui| {
+            ui.label(i18n.tr("camera-save-file"));
+            ui.text_edit_singleline(&mut self.sav);
+            ui.end_row();
+            ui.label(i18n.tr("camera-png-file"));
+            ui.text_edit_singleline(&mut self.png);
+            ui.end_row();
+            ui.label(i18n.tr("camera-slot"));
+            ui.add(egui::DragValue::new(&mut self.slot).clamp_range(1..=PHOTO_SLOTS));
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            if ui.button(i18n.tr("camera-export")).clicked() {
+                let result = fs::read(&self.sav)
+                    .and_then(|sav| camera::export_photos(&sav, &self.export_dir));
+                self.message = Some(match result {
+                    Ok(count) => i18n.tr_args(
+                        "camera-exported",
+                        &[
+                            ("count", &count.to_string()),
+                            ("dir", &self.export_dir.display().to_string()),
+                        ],
+                    ),
+                    Err(e) => i18n.tr_args("memory-error", &[("error", &e.to_string())]),
+                });
+            }
+            if ui.button(i18n.tr("camera-import")).clicked() {
+                self.message = Some(match self.import() {
+                    Ok(()) => i18n.tr_args("camera-imported", &[("slot", &self.slot.to_string())]),
+                    Err(e) => i18n.tr_args("memory-error", &[("error", &e.to_string())]),
+                });
+            }
+        });
+        ui.label(i18n.tr("camera-webcam-unsupported"));
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+    }
+    fn import(&self) -> io::Result<()> {
+        let mut sav = fs::read(&self.sav)?;
+        camera::import_photo(&mut sav, self.slot - 1, self.png.trim().as_ref())?;
+        fs::write(&self.sav, sav)
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    game_window::{GameWindow, GAME_SCREEN_HEIGHT, GAME_SCREEN_WIDTH},
+    i18n::I18n,
+};
+use eframe::egui;
+
+/// Frames per second of the emulated display
+const FPS: usize = 60;
+/// The delay between two frames of a recording as a fraction of a second,
+/// one emulated frame takes about 1/59.7s
+const FRAME_DELAY: (u16, u16) = (10, 597);
+
+/// A recording in progress, one frame in RGBA per emulated frame
+struct Recording {
+    frames: Vec<Vec<u8>>,
+    /// Frames until it stops by itself
+    left: usize,
+}
+
+/// Screenshots and recordings of the screen as the emulator drew it, unscaled
+pub struct Capture {
+    dir: PathBuf,
+    recording: Option<Recording>,
+    /// Length of the next recording
+    seconds: usize,
+    /// The file written last or why it could not be
+    last: Option<Result<PathBuf, String>>,
+}
+impl Capture {
+    pub fn new(dir: PathBuf) -> Self {
+        Capture {
+            dir,
+            recording: None,
+            seconds: 5,
+            last: None,
+        }
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+    /// Writes the current frame to a timestamped png
+    pub fn screenshot(&mut self, screen: &GameWindow) {
+        let path = self.path("screenshot", "png");
+        let result = write_png(&path, &[screen.rgba()]);
+        self.finish(path, result);
+    }
+    /// Starts recording, or stops and writes the recording if one runs
+    pub fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(recording) => self.write_recording(recording),
+            None => {
+                self.recording = Some(Recording {
+                    frames: Vec::new(),
+                    left: self.seconds * FPS,
+                })
+            }
+        }
+    }
+    /// Called with every frame the emulator draws
+    pub fn frame(&mut self, screen: &GameWindow) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+        recording.frames.push(screen.rgba());
+        recording.left = recording.left.saturating_sub(1);
+        if recording.left == 0 {
+            if let Some(recording) = self.recording.take() {
+                self.write_recording(recording);
+            }
+        }
+    }
+    fn write_recording(&mut self, recording: Recording) {
+        if recording.frames.is_empty() {
+            return;
+        }
+        let path = self.path("recording", "apng");
+        let result = write_png(&path, &recording.frames);
+        self.finish(path, result);
+    }
+    fn path(&self, name: &str, extension: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        self.dir.join(format!("{name}-{timestamp}.{extension}"))
+    }
+    fn finish(&mut self, path: PathBuf, result: io::Result<()>) {
+        self.last = Some(match result {
+            Ok(()) => Ok(path),
+            Err(e) => {
+                log::warn!("could not write {}: {e}", path.display());
+                Err(e.to_string())
+            }
+        });
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, screen: &GameWindow) {
+        ui.horizontal(|ui| {
+            if ui.button(i18n.tr("capture-screenshot")).clicked() {
+                self.screenshot(screen);
+            }
+            let record = if self.is_recording() {
+                i18n.tr("capture-stop")
+            } else {
+                i18n.tr("capture-record")
+            };
+            if ui.button(record).clicked() {
+                self.toggle_recording();
+            }
+            ui.add_enabled(
+                !self.is_recording(),
+                egui::Slider::new(&mut self.seconds, 1..=30).suffix(" s"),
+            );
+        });
+        if let Some(recording) = &self.recording {
+            let seconds = format!("{:.1}", recording.left as f32 / FPS as f32);
+            ui.label(i18n.tr_args("capture-recording", &[("seconds", &seconds)]));
+        }
+        match &self.last {
+            Some(Ok(path)) => {
+                ui.label(i18n.tr_args("capture-written", &[("path", &path.display().to_string())]));
+            }
+            Some(Err(e)) => {
+                ui.label(i18n.tr_args("capture-failed", &[("error", e)]));
+            }
+            None => {}
+        }
+    }
+}
+
+/// A png of the frame, or a looping animated png of several
+pub fn write_png(path: &Path, frames: &[Vec<u8>]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, GAME_SCREEN_WIDTH as u32, GAME_SCREEN_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let to_io = |e: png::EncodingError| io::Error::other(e);
+    if frames.len() > 1 {
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(to_io)?;
+        encoder
+            .set_frame_delay(FRAME_DELAY.0, FRAME_DELAY.1)
+            .map_err(to_io)?;
+    }
+    let mut writer = encoder.write_header().map_err(to_io)?;
+    for frame in frames {
+        writer.write_image_data(frame).map_err(to_io)?;
+    }
+    writer.finish().map_err(to_io)
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use super::i18n::I18n;
+use eframe::{egui, epaint::Color32};
+use gba::cheats::{self, Cheat, Cheats};
+
+/// The cheats of the running game, stored in one `.cht` file per game
+pub struct CheatsPanel {
+    cheats: Cheats,
+    dir: PathBuf,
+    /// CRC32 of the game the list belongs to
+    loaded: Option<u32>,
+    list: Vec<Cheat>,
+    description: String,
+    code: String,
+    /// Why the code typed last was not added
+    error: Option<String>,
+}
+impl CheatsPanel {
+    pub fn new(cheats: Cheats, dir: PathBuf) -> Self {
+        CheatsPanel {
+            cheats,
+            dir,
+            loaded: None,
+            list: Vec::new(),
+            description: String::new(),
+            code: String::new(),
+            error: None,
+        }
+    }
+    fn path(&self, crc: u32) -> PathBuf {
+        self.dir.join(format!("{crc:08x}.cht"))
+    }
+    /// Switches to the cheats of the game with `crc` when it starts
+    pub fn update(&mut self, crc: u32) {
+        if self.loaded == Some(crc) {
+            return;
+        }
+        self.list = cheats::load_cht(&self.path(crc)).unwrap_or_default();
+        self.loaded = Some(crc);
+        self.cheats.set(&self.list);
+    }
+    /// Hands the list to the cpu and writes it
+    fn changed(&mut self) {
+        self.cheats.set(&self.list);
+        let Some(crc) = self.loaded else {
+            return;
+        };
+        let path = self.path(crc);
+        let result =
+            std::fs::create_dir_all(&self.dir).and_then(|_| cheats::save_cht(&path, &self.list));
+        if let Err(e) = result {
+            log::warn!("could not save the cheats to {}: {e}", path.display());
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        let mut changed = false;
+        let mut removed = None;
+        egui::Grid::new("cheats").show(ui, |ui| {
+            for (i, cheat) in self.list.iter_mut().enumerate() {
+                changed |= ui
+                    .checkbox(&mut cheat.enabled, &cheat.description)
+                    .changed();
+                ui.monospace(&cheat.code);
+                if let Err(e) = cheats::parse_codes(&cheat.code) {
+                    ui.colored_label(Color32::RED, e);
+                }
+                if ui.small_button(i18n.tr("cheats-delete")).clicked() {
+                    removed = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = removed {
+            self.list.remove(i);
+            changed = true;
+        }
+        ui.separator();
+        egui::Grid::new("new_cheat").show(ui, |ui| {
+            ui.label(i18n.tr("cheats-description"));
+            ui.text_edit_singleline(&mut self.description);
+            ui.end_row();
+            ui.label(i18n.tr("cheats-code"));
+            ui.text_edit_singleline(&mut self.code)
+                .on_hover_text(i18n.tr("cheats-code-hint"));
+            ui.end_row();
+        });
+        if ui.button(i18n.tr("cheats-add")).clicked() {
+            let code = self.code.trim().to_ascii_uppercase();
+            match cheats::parse_codes(&code) {
+                Ok(_) => {
+                    let description = match self.description.trim() {
+                        "" => code.clone(),
+                        description => description.to_string(),
+                    };
+                    self.list.push(Cheat {
+                        description,
+                        code,
+                        enabled: true,
+                    });
+                    self.description.clear();
+                    self.code.clear();
+                    self.error = None;
+                    changed = true;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::RED, e);
+        }
+        if changed {
+            self.changed();
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::borrow::Cow;
+
+use super::game_window::{GameWindow, GAME_SCREEN_HEIGHT, GAME_SCREEN_WIDTH};
+use eframe::egui;
+use gba::{banked::BankedAddr, cpu::CpuMode, ram::Ram, status::Status};
+
+/// Copies the screen and the registers, to paste them into bug reports
+#[derive(Default)]
+pub struct Clipboard {
+    /// Created on the first copy. Some platforms drop the content with the clipboard, so it is kept
+    os: Option<arboard::Clipboard>,
+}
+impl Clipboard {
+    /// Puts the screen in the current palette on the clipboard as an image
+    pub fn copy_screen(&mut self, screen: &GameWindow) {
+        let image = arboard::ImageData {
+            width: GAME_SCREEN_WIDTH,
+            height: GAME_SCREEN_HEIGHT,
+            bytes: Cow::Owned(screen.rgba()),
+        };
+        if self.os.is_none() {
+            self.os = arboard::Clipboard::new()
+                .map_err(|e| log::warn!("no clipboard available: {e}"))
+                .ok();
+        }
+        let Some(os) = &mut self.os else {
+            return;
+        };
+        if let Err(e) = os.set_image(image) {
+            log::warn!("could not copy the screen: {e}");
+        }
+    }
+}
+/// Puts the register dump on the clipboard as text
+pub fn copy_registers(
+    ctx: &egui::Context,
+    status: &Status,
+    cpu_mode: Option<&(CpuMode, u16)>,
+    ram: Option<&Ram>,
+) {
+    ctx.output().copied_text = register_dump(status, cpu_mode, ram);
+}
+/// Registers, flags and the core state as plain text
+pub fn register_dump(
+    status: &Status,
+    cpu_mode: Option<&(CpuMode, u16)>,
+    ram: Option<&Ram>,
+) -> String {
+    let [bc, de, hl, af, _, sp] = status.registers();
+    let f = af.to_be_bytes()[1];
+    let flag = |mask: u8, name: char| if f & mask != 0 { name } else { '-' };
+    let title = ram
+        .and_then(Ram::cartridge_title)
+        .unwrap_or_else(|| "-".to_string());
+    let mode = match cpu_mode {
+        Some((mode, pc)) => format!(
+            "{mode:?} at {}",
+            BankedAddr::resolve(*pc, status.rom_bank())
+        ),
+        None => format!("{:?}", CpuMode::Run),
+    };
+    format!(
+        "title: {title}\n\
+         frame: {}\n\
+         AF: {af:04X}  BC: {bc:04X}  DE: {de:04X}  HL: {hl:04X}\n\
+         SP: {sp:04X}  PC: {}\n\
+         flags: {}{}{}{}\n\
+         mode: {mode}\n\
+         rom bank: {}  LY: {}\n",
+        status.frame(),
+        BankedAddr::resolve(status.pc(), status.rom_bank()),
+        flag(0x80, 'Z'),
+        flag(0x40, 'N'),
+        flag(0x20, 'H'),
+        flag(0x10, 'C'),
+        status.rom_bank(),
+        status.ly(),
+    )
+}
@@ -0,0 +1,306 @@
+use std::sync::{Arc, RwLock};
+
+use super::{clipboard::register_dump, i18n::I18n};
+use eframe::egui::{self, Key, TextStyle};
+use gba::{
+    banked::BankedAddr,
+    debugger::{parse_number, Access, Break, Debugger, Watchpoint},
+    ram::{self, Ram},
+    speed::Speed,
+    status::Status,
+    symbols::Symbols,
+};
+
+/// Lines kept in the scrollback
+const MAX_LINES: usize = 1000;
+const HELP: &str = "\
+addresses can also be labels of the .sym file next to the rom
+b [$addr]          add a breakpoint, list them without an address
+d $addr            delete a breakpoint
+watch $addr[-$end] [r|w|rw]  pause on an access, writes by default
+unwatch $addr      delete the watchpoint starting there
+x[/count] $addr    show memory
+step [count]       run instructions, also s
+until $addr        run to an address, also u
+frame              run to the end of the frame, also f
+line               run to the start of the next line, also l
+dot [count]        run ppu dots
+finish             run until the current call returns, also out
+bt                 show the calls which have not returned
+c                  continue, also continue
+pause              pause
+regs               show the registers
+help               show this";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Break(Option<u16>),
+    Delete(u16),
+    Watch(Watchpoint),
+    Unwatch(u16),
+    Examine { addr: u16, count: u16 },
+    Step(u32),
+    RunTo(u16),
+    Frame,
+    Line,
+    Dot(u32),
+    Finish,
+    Backtrace,
+    Continue,
+    Pause,
+    Registers,
+    Help,
+}
+fn addr(s: Option<&str>, symbols: &Symbols) -> Result<u16, String> {
+    symbols.parse_address(s.ok_or("missing address")?)
+}
+impl Command {
+    fn parse(line: &str, symbols: &Symbols) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        // x/16 passes the count with the command
+        let (name, count) = name.split_once('/').unwrap_or((name, ""));
+        let arg = words.next();
+        Ok(match name {
+            "b" | "break" => Command::Break(arg.map(|a| addr(Some(a), symbols)).transpose()?),
+            "d" | "delete" => Command::Delete(addr(arg, symbols)?),
+            "watch" => {
+                let access = match words.next() {
+                    Some(access) => {
+                        Access::parse(access).ok_or(format!("not r, w or rw: {access}"))?
+                    }
+                    None => Access::Write,
+                };
+                let (start, end) = Watchpoint::parse_range(arg.ok_or("missing address")?)?;
+                Command::Watch(Watchpoint::new(start, end, access))
+            }
+            "unwatch" => Command::Unwatch(addr(arg, symbols)?),
+            "x" => Command::Examine {
+                addr: addr(arg, symbols)?,
+                count: if count.is_empty() {
+                    1
+                } else {
+                    parse_number(count)?.min(0x10000) as u16
+                },
+            },
+            "s" | "step" => Command::Step(arg.map_or(Ok(1), parse_number)?.max(1)),
+            "u" | "until" => Command::RunTo(addr(arg, symbols)?),
+            "f" | "frame" => Command::Frame,
+            "l" | "line" => Command::Line,
+            "dot" => Command::Dot(arg.map_or(Ok(1), parse_number)?.max(1)),
+            "finish" | "out" => Command::Finish,
+            "bt" | "backtrace" => Command::Backtrace,
+            "c" | "continue" => Command::Continue,
+            "pause" => Command::Pause,
+            "regs" | "registers" => Command::Registers,
+            "help" | "?" => Command::Help,
+            _ => return Err(format!("unknown command {name}, try help")),
+        })
+    }
+}
+
+/// Drives the debugger with typed commands, the same breakpoints and watchpoints
+/// the panels show
+pub struct Console {
+    debugger: Debugger,
+    input: String,
+    lines: Vec<String>,
+}
+impl Console {
+    pub fn new(debugger: Debugger) -> Self {
+        Console {
+            debugger,
+            input: String::new(),
+            lines: Vec::new(),
+        }
+    }
+    fn print(&mut self, text: impl Into<String>) {
+        self.lines.extend(text.into().lines().map(str::to_string));
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..excess);
+    }
+    fn execute(
+        &mut self,
+        line: &str,
+        speed: &Speed,
+        status: &Status,
+        ram: Option<&Ram>,
+        symbols: &Symbols,
+    ) {
+        self.print(format!("> {line}"));
+        let command = match Command::parse(line, symbols) {
+            Ok(command) => command,
+            Err(e) => return self.print(e),
+        };
+        let output = match command {
+            Command::Break(Some(addr)) => {
+                self.debugger.add_breakpoint(addr);
+                format!("breakpoint at ${addr:04X}")
+            }
+            Command::Break(None) => {
+                let list: Vec<String> = self
+                    .debugger
+                    .breakpoints()
+                    .iter()
+                    .map(|addr| {
+                        let banked = BankedAddr::resolve(*addr, status.rom_bank());
+                        match symbols.label(banked) {
+                            Some(label) => format!("${addr:04X} {label}"),
+                            None => format!("${addr:04X}"),
+                        }
+                    })
+                    .collect();
+                if list.is_empty() {
+                    "no breakpoints".to_string()
+                } else {
+                    list.join(", ")
+                }
+            }
+            Command::Delete(addr) => match self.debugger.remove_breakpoint(addr) {
+                true => format!("deleted the breakpoint at ${addr:04X}"),
+                false => format!("no breakpoint at ${addr:04X}"),
+            },
+            Command::Watch(watchpoint) => {
+                self.debugger.add_watchpoint(watchpoint);
+                format!("watching {watchpoint}")
+            }
+            Command::Unwatch(addr) => match self.debugger.remove_watchpoint(addr) {
+                true => format!("deleted the watchpoint at ${addr:04X}"),
+                false => format!("no watchpoint at ${addr:04X}"),
+            },
+            Command::Examine { addr, count } => match ram {
+                Some(ram) => examine(ram, addr, count),
+                None => "no memory".to_string(),
+            },
+            Command::Step(count) => {
+                self.debugger.step(count);
+                speed.set_paused(false);
+                return;
+            }
+            Command::RunTo(addr) => {
+                self.debugger.run_to(addr);
+                speed.set_paused(false);
+                format!("running to ${addr:04X}")
+            }
+            Command::Frame => {
+                self.debugger.step_frame();
+                speed.set_paused(false);
+                return;
+            }
+            Command::Line => {
+                self.debugger.step_line();
+                speed.set_paused(false);
+                return;
+            }
+            Command::Dot(count) => {
+                self.debugger.step_dots(count);
+                speed.set_paused(false);
+                return;
+            }
+            Command::Finish => {
+                if !self.debugger.step_out() {
+                    return self.print("not in a call");
+                }
+                speed.set_paused(false);
+                return;
+            }
+            Command::Backtrace => {
+                let frames = self.debugger.call_stack();
+                if frames.is_empty() {
+                    "not in a call".to_string()
+                } else {
+                    let lines: Vec<String> = frames
+                        .iter()
+                        .rev()
+                        .enumerate()
+                        .map(|(i, frame)| format!("#{i} {frame}"))
+                        .collect();
+                    lines.join("\n")
+                }
+            }
+            Command::Continue => {
+                speed.set_paused(false);
+                "continuing".to_string()
+            }
+            Command::Pause => {
+                speed.set_paused(true);
+                "paused".to_string()
+            }
+            Command::Registers => register_dump(status, None, ram),
+            Command::Help => HELP.to_string(),
+        };
+        self.print(output);
+    }
+    /// Prints why the cpu paused, called every frame
+    pub fn update(&mut self) {
+        match self.debugger.take_hit() {
+            Some(Break::Breakpoint(pc)) => self.print(format!("breakpoint at ${pc:04X}")),
+            Some(Break::Watchpoint {
+                addr,
+                write,
+                value,
+                pc,
+            }) => {
+                let access = if write { "write" } else { "read" };
+                self.print(format!(
+                    "{access} of ${value:02X} at ${addr:04X} by ${pc:04X}"
+                ));
+            }
+            Some(Break::Step(pc)) => self.print(format!("stepped to ${pc:04X}")),
+            Some(Break::Reached(pc)) => self.print(format!("reached ${pc:04X}")),
+            Some(Break::Frame(pc)) => self.print(format!("frame done at ${pc:04X}")),
+            Some(Break::Dot { pc, ly, dot }) => {
+                self.print(format!("line {ly} dot {dot} at ${pc:04X}"))
+            }
+            Some(Break::Scanline { pc, ly }) => {
+                self.print(format!("line {ly} starts at ${pc:04X}"))
+            }
+            Some(Break::Returned(pc)) => self.print(format!("returned to ${pc:04X}")),
+            Some(Break::Fault { pc, error }) => self.print(format!("{error} at ${pc:04X}")),
+            None => {}
+        }
+    }
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &I18n,
+        speed: &Speed,
+        status: &Status,
+        ram: Option<&Arc<RwLock<Ram>>>,
+        symbols: &Symbols,
+    ) {
+        egui::ScrollArea::vertical()
+            .max_height(300.)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &self.lines {
+                    ui.label(egui::RichText::new(line).text_style(TextStyle::Monospace));
+                }
+            });
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.input)
+                .hint_text(i18n.tr("console-hint"))
+                .font(TextStyle::Monospace),
+        );
+        if response.lost_focus() && ui.input().key_pressed(Key::Enter) {
+            let line = std::mem::take(&mut self.input);
+            if !line.trim().is_empty() {
+                let ram = ram.map(|ram| ram::lock(ram));
+                self.execute(line.trim(), speed, status, ram.as_deref(), symbols);
+            }
+            response.request_focus();
+        }
+    }
+}
+/// 16 bytes per line like `$FF40: 91 00 ...`
+fn examine(ram: &Ram, addr: u16, count: u16) -> String {
+    let addrs: Vec<u16> = (0..count).map(|i| addr.wrapping_add(i)).collect();
+    addrs
+        .chunks(16)
+        .map(|line| {
+            let bytes: Vec<String> = line.iter().map(|a| format!("{:02X}", ram[*a])).collect();
+            format!("${:04X}: {}", line[0], bytes.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
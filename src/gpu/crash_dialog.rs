@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, RichText},
+    epaint::Color32,
+};
+use gba::crash::CrashReport;
+
+/// Shown once the cpu thread died, with what it was executing and
+/// a button to keep the machine for a bug report
+pub struct CrashDialog {
+    report: CrashReport,
+    /// Where the state dump went, or why it could not be written
+    saved: Option<Result<PathBuf, String>>,
+}
+impl CrashDialog {
+    pub fn new(report: CrashReport) -> Self {
+        CrashDialog {
+            report,
+            saved: None,
+        }
+    }
+    pub fn show(&mut self, ctx: &egui::Context, i18n: &I18n) {
+        egui::Window::new(i18n.tr("window-crashed"))
+            .id(egui::Id::new("crashed"))
+            .show(ctx, |ui| {
+                let report = &self.report;
+                ui.colored_label(Color32::RED, &report.message);
+                match &report.path {
+                    Some(path) => ui.label(i18n.tr_args(
+                        "crash-report-written",
+                        &[("path", &path.display().to_string())],
+                    )),
+                    None => ui.label(i18n.tr("crash-report-failed")),
+                };
+                let Some(post_mortem) = &report.post_mortem else {
+                    return;
+                };
+                if let Some(failing) = post_mortem.failing() {
+                    ui.label(i18n.tr_args(
+                        "crash-failing-op",
+                        &[
+                            ("op", &format!("{:#04x}", failing.op)),
+                            ("pc", &format!("{:#06x}", failing.pc)),
+                            ("text", &failing.text),
+                        ],
+                    ));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .show(ui, |ui| {
+                        ui.label(RichText::new(post_mortem.to_string()).monospace());
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(i18n.tr("crash-save-dump")).clicked() {
+                        self.saved = Some(report.save_dump());
+                    }
+                    match &self.saved {
+                        Some(Ok(path)) => {
+                            ui.label(i18n.tr_args(
+                                "crash-dump-saved",
+                                &[("path", &path.display().to_string())],
+                            ))
+                        }
+                        Some(Err(e)) => ui.colored_label(
+                            Color32::RED,
+                            i18n.tr_args("crash-dump-failed", &[("error", e)]),
+                        ),
+                        None => ui.label(""),
+                    };
+                });
+            });
+    }
+}
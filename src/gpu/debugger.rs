@@ -0,0 +1,306 @@
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, RichText},
+    epaint::Color32,
+};
+use gba::{
+    banked::BankedAddr,
+    debugger::{Access, Debugger, Watchpoint},
+    disasm,
+    layers::{Layer, Layers},
+    ram::{self, Ram},
+    speed::Speed,
+    status::Status,
+    symbols::Symbols,
+    trace::Trace,
+};
+
+/// Instructions shown in front of the pc
+const LINES_BEFORE: usize = 8;
+/// Instructions shown after the pc
+const LINES_AFTER: usize = 24;
+
+/// Pauses, steps and resumes the cpu and edits the breakpoints and watchpoints
+pub struct DebuggerPanel {
+    debugger: Debugger,
+    /// Address typed for a new breakpoint
+    breakpoint: String,
+    /// Address typed for run to
+    run_to: String,
+    /// Address or range typed for a new watchpoint
+    watchpoint: String,
+    access: Access,
+    error: Option<String>,
+    /// The disassembly scrolls to the pc when it changed
+    shown_pc: Option<u16>,
+}
+impl DebuggerPanel {
+    pub fn new(debugger: Debugger) -> Self {
+        DebuggerPanel {
+            debugger,
+            breakpoint: String::new(),
+            run_to: String::new(),
+            watchpoint: String::new(),
+            access: Access::Write,
+            error: None,
+            shown_pc: None,
+        }
+    }
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &I18n,
+        speed: &Speed,
+        status: &Status,
+        ram: Option<&Arc<RwLock<Ram>>>,
+        symbols: &Symbols,
+    ) {
+        let paused = speed.is_paused();
+        if paused {
+            let pc = format!("${:04X}", status.pc());
+            ui.label(i18n.tr_args("debugger-paused-at", &[("pc", &pc)]));
+            let (ly, dot) = (status.ly().to_string(), status.dot().to_string());
+            ui.label(i18n.tr_args("debugger-beam", &[("ly", &ly), ("dot", &dot)]));
+        } else {
+            ui.label(i18n.tr("debugger-running"));
+        }
+        ui.horizontal(|ui| {
+            let toggle = if paused {
+                i18n.tr("debugger-continue")
+            } else {
+                i18n.tr("debugger-pause")
+            };
+            if ui.button(toggle).clicked() {
+                speed.set_paused(!paused);
+            }
+            if ui.button(i18n.tr("debugger-step")).clicked() {
+                self.debugger.step(1);
+                speed.set_paused(false);
+            }
+            if ui.button(i18n.tr("debugger-step-out")).clicked() {
+                if self.debugger.step_out() {
+                    speed.set_paused(false);
+                    self.error = None;
+                } else {
+                    self.error = Some(i18n.tr("debugger-not-in-call").to_string());
+                }
+            }
+            if ui.button(i18n.tr("debugger-step-frame")).clicked() {
+                self.debugger.step_frame();
+                speed.set_paused(false);
+            }
+            if ui.button(i18n.tr("debugger-step-line")).clicked() {
+                self.debugger.step_line();
+                speed.set_paused(false);
+            }
+            if ui.button(i18n.tr("debugger-step-dot")).clicked() {
+                self.debugger.step_dots(1);
+                speed.set_paused(false);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.run_to).desired_width(60.));
+            if ui.button(i18n.tr("debugger-run-to")).clicked() {
+                match symbols.parse_address(self.run_to.trim()) {
+                    Ok(addr) => {
+                        self.debugger.run_to(addr);
+                        speed.set_paused(false);
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+        ui.separator();
+        ui.label(i18n.tr("debugger-breakpoints"));
+        let breakpoints = self.debugger.breakpoints();
+        if breakpoints.is_empty() {
+            ui.label(i18n.tr("debugger-no-breakpoints"));
+        }
+        for addr in breakpoints {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("${addr:04X}"));
+                if let Some(label) = symbols.label(BankedAddr::resolve(addr, status.rom_bank())) {
+                    ui.monospace(label);
+                }
+                if ui.small_button("x").clicked() {
+                    self.debugger.remove_breakpoint(addr);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.breakpoint).desired_width(60.));
+            if ui.button(i18n.tr("debugger-add")).clicked() {
+                match symbols.parse_address(self.breakpoint.trim()) {
+                    Ok(addr) => {
+                        self.debugger.add_breakpoint(addr);
+                        self.breakpoint.clear();
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+        ui.separator();
+        self.watchpoints(ui, i18n);
+        ui.separator();
+        self.call_stack(ui, i18n, symbols, status.rom_bank());
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::RED, e);
+        }
+        if let Some(ram) = ram {
+            ui.separator();
+            ui.label(i18n.tr("debugger-disassembly"));
+            let ram = ram::lock(ram);
+            self.disassembly(ui, &ram, status.pc(), symbols, status.rom_bank());
+        }
+    }
+    fn watchpoints(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        ui.label(i18n.tr("debugger-watchpoints"));
+        let watchpoints = self.debugger.watchpoints();
+        if watchpoints.is_empty() {
+            ui.label(i18n.tr("debugger-no-watchpoints"));
+        }
+        for watchpoint in watchpoints {
+            ui.horizontal(|ui| {
+                ui.monospace(watchpoint.to_string());
+                if ui.small_button("x").clicked() {
+                    self.debugger.remove_watchpoint(watchpoint.start);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.watchpoint)
+                    .hint_text("$FF40-$FF4B")
+                    .desired_width(100.),
+            );
+            for access in Access::ALL {
+                ui.selectable_value(&mut self.access, access, access.name());
+            }
+            if ui.button(i18n.tr("debugger-add")).clicked() {
+                match Watchpoint::parse_range(self.watchpoint.trim()) {
+                    Ok((start, end)) => {
+                        let watchpoint = Watchpoint::new(start, end, self.access);
+                        self.debugger.add_watchpoint(watchpoint);
+                        self.watchpoint.clear();
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+    }
+    /// The calls which have not returned, innermost first
+    fn call_stack(&mut self, ui: &mut egui::Ui, i18n: &I18n, symbols: &Symbols, rom_bank: u16) {
+        ui.label(i18n.tr("debugger-call-stack"));
+        let frames = self.debugger.call_stack();
+        if frames.is_empty() {
+            ui.label(i18n.tr("debugger-not-in-call"));
+        }
+        egui::ScrollArea::vertical()
+            .id_source("call_stack")
+            .max_height(120.)
+            .show(ui, |ui| {
+                for frame in frames.iter().rev() {
+                    let kind = frame.kind.to_string();
+                    let target = BankedAddr::resolve(frame.target, rom_bank);
+                    let (target, site, ret) = (
+                        symbols
+                            .label(target)
+                            .unwrap_or_else(|| format!("${:04X}", frame.target)),
+                        format!("${:04X}", frame.site),
+                        format!("${:04X}", frame.return_address()),
+                    );
+                    let args = [
+                        ("kind", kind.as_str()),
+                        ("target", &target),
+                        ("site", &site),
+                        ("return", &ret),
+                    ];
+                    ui.monospace(i18n.tr_args("debugger-call-frame", &args));
+                }
+            });
+    }
+    /// The code around `pc`, a click on a line toggles its breakpoint.
+    /// Lines with a label start with it, addresses with one are replaced by it.
+    fn disassembly(
+        &mut self,
+        ui: &mut egui::Ui,
+        ram: &Ram,
+        pc: u16,
+        symbols: &Symbols,
+        rom_bank: u16,
+    ) {
+        let breakpoints = self.debugger.breakpoints();
+        let moved = self.shown_pc.replace(pc) != Some(pc);
+        egui::ScrollArea::vertical()
+            .id_source("disassembly")
+            .show(ui, |ui| {
+                for line in disasm::around(ram, pc, LINES_BEFORE, LINES_AFTER) {
+                    if let Some(label) = symbols.exact(BankedAddr::resolve(line.addr, rom_bank)) {
+                        ui.label(RichText::new(format!("{label}:")).monospace().weak());
+                    }
+                    let text = symbols.annotate(&line.text, rom_bank);
+                    let marker = if breakpoints.contains(&line.addr) {
+                        '●'
+                    } else {
+                        ' '
+                    };
+                    let mut text =
+                        RichText::new(format!("{marker} {:04X}  {text}", line.addr)).monospace();
+                    if line.addr == pc {
+                        text = text.strong().background_color(Color32::DARK_BLUE);
+                    }
+                    let label = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+                    if line.addr == pc && moved {
+                        label.scroll_to_me(Some(egui::Align::Center));
+                    }
+                    if label.clicked() && !self.debugger.remove_breakpoint(line.addr) {
+                        self.debugger.add_breakpoint(line.addr);
+                    }
+                }
+            });
+    }
+}
+/// Starts and stops the instruction trace into `file`
+pub fn trace_ui(ui: &mut egui::Ui, i18n: &I18n, trace: &Trace, file: &Path) {
+    let mut active = trace.is_active();
+    let path = file.display().to_string();
+    let checkbox = ui
+        .checkbox(&mut active, i18n.tr("debugger-trace"))
+        .on_hover_text(i18n.tr_args("debugger-trace-file", &[("path", &path)]));
+    if checkbox.changed() {
+        if active {
+            if let Err(e) = trace.start(file) {
+                log::error!("could not trace into {path}: {e}");
+            }
+        } else {
+            trace.stop();
+        }
+    }
+}
+/// A row per layer to hide it or tint it, to see which one a glitch comes from
+pub fn layers_ui(ui: &mut egui::Ui, i18n: &I18n, layers: &Layers) {
+    let mask = layers.get();
+    egui::CollapsingHeader::new(i18n.tr("debugger-layers")).show(ui, |ui| {
+        egui::Grid::new("layers").show(ui, |ui| {
+            for layer in Layer::ALL {
+                let mut visible = mask.is_visible(layer);
+                if ui.checkbox(&mut visible, i18n.tr(layer.name())).changed() {
+                    layers.set_visible(layer, visible);
+                }
+                let mut tinted = mask.is_tinted(layer);
+                if ui.checkbox(&mut tinted, i18n.tr("layer-tint")).changed() {
+                    layers.set_tinted(layer, tinted);
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
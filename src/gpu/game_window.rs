@@ -1,102 +1,268 @@
 use eframe::{
     egui::{self, Frame, TextureOptions},
     emath,
-    epaint::{pos2, vec2, Color32, ColorImage, ImageDelta, Pos2, Rect, Stroke, TextureId},
+    epaint::{pos2, vec2, Color32, ColorImage, ImageDelta, Pos2, Rect, Stroke, TextureId, Vec2},
+};
+use gba::{
+    cgb,
+    ppu::{ColorFrameBuffer, FrameBuffer},
+    sgb::{BorderImage, BORDER_HEIGHT, BORDER_WIDTH, SCREEN_LEFT, SCREEN_TOP},
 };
 
 pub const GAME_SCREEN_WIDTH: usize = 160;
 pub const GAME_SCREEN_SCALE: usize = 3;
 pub const GAME_SCREEN_HEIGHT: usize = 144;
+/// Largest integer prescale, keeps the texture small on huge windows
+const MAX_PRESCALE: usize = 8;
+
+/// How the screen is scaled to the window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scaling {
+    /// Blocky, but pixels differ in size at fractional scales
+    Nearest,
+    /// Smooth and blurry
+    Bilinear,
+    /// Integer prescale with nearest, then bilinear for the rest.
+    /// Pixels stay sharp and only their edges are blended.
+    #[default]
+    SharpBilinear,
+}
+impl Scaling {
+    pub const ALL: [Scaling; 3] = [Scaling::Nearest, Scaling::Bilinear, Scaling::SharpBilinear];
+    /// The i18n key of the name
+    pub fn name(self) -> &'static str {
+        match self {
+            Scaling::Nearest => "scaling-nearest",
+            Scaling::Bilinear => "scaling-bilinear",
+            Scaling::SharpBilinear => "scaling-sharp-bilinear",
+        }
+    }
+    fn options(self) -> TextureOptions {
+        match self {
+            Scaling::Nearest => TextureOptions::NEAREST,
+            Scaling::Bilinear | Scaling::SharpBilinear => TextureOptions::LINEAR,
+        }
+    }
+}
+
 pub struct GameWindow {
     pub color_palette: [[u8; 3]; 4],
-    screen_buffer: [u8; GAME_SCREEN_HEIGHT * GAME_SCREEN_WIDTH],
+    pub scaling: Scaling,
+    /// Only scales by whole physical pixels, so no pixel is wider than another
+    pub integer_scale: bool,
+    screen_buffer: Box<FrameBuffer>,
+    /// The screen of a CGB game, which brings its own colors instead of the palette
+    color_buffer: Option<Box<ColorFrameBuffer>>,
+    /// The border of an SGB game, drawn around the screen when `show_border` is set
+    border: Option<Box<BorderImage>>,
+    pub show_border: bool,
     texture_id: Option<TextureId>,
     update_texture: bool,
+    /// Integer factor the texture is uploaded with, follows the size on screen
+    prescale: usize,
+    /// Palette, scaling, prescale and border of the last upload, to notice changes
+    uploaded: ([[u8; 3]; 4], Scaling, usize, bool),
 }
 impl GameWindow {
     pub fn init_texture(&mut self, ctx: &egui::Context) {
         let tex_manager = ctx.tex_manager();
-        let colors = self
-            .screen_buffer
-            .iter()
-            .map(|c| self.color_palette[*c as usize])
-            .flatten()
-            .collect::<Vec<u8>>();
-        let color_image =
-            ColorImage::from_rgb([GAME_SCREEN_WIDTH, GAME_SCREEN_HEIGHT], &colors[..]);
         let texture_id = tex_manager.write().alloc(
             "GameWindowTexture".into(),
-            color_image.into(),
-            egui::TextureOptions::default(),
+            self.color_image().into(),
+            self.scaling.options(),
         );
         self.texture_id = Some(texture_id);
     }
+    /// Uploads the screen if a pixel or the display settings changed since the last upload
     pub fn update_texture(&mut self, ctx: &egui::Context) {
+        let settings = (
+            self.color_palette,
+            self.scaling,
+            self.prescale,
+            self.framed(),
+        );
+        if !self.update_texture && self.uploaded == settings {
+            return;
+        }
+        self.update_texture = false;
+        self.uploaded = settings;
         let tex_manager = ctx.tex_manager();
-        let colors = self
-            .screen_buffer
-            .iter()
-            .map(|c| self.color_palette[*c as usize])
-            .flatten()
-            .collect::<Vec<u8>>();
-        let color_image =
-            ColorImage::from_rgb([GAME_SCREEN_WIDTH, GAME_SCREEN_HEIGHT], &colors[..]);
         tex_manager.write().set(
             self.texture_id.unwrap(),
-            ImageDelta::full(color_image, TextureOptions::default()),
+            ImageDelta::full(self.color_image(), self.scaling.options()),
         );
     }
-    pub fn draw_pixel(&mut self, x: usize, y: usize, color: usize) {
-        self.screen_buffer[x * GAME_SCREEN_WIDTH + y] = color as u8;
-    }
-    pub fn view(&mut self, ui: &mut egui::Ui) {
-        Frame::canvas(ui.style()).show(ui, |ui| {
-            let tex_size = vec2(
-                (GAME_SCREEN_WIDTH * GAME_SCREEN_SCALE) as f32,
-                (GAME_SCREEN_HEIGHT * GAME_SCREEN_SCALE) as f32,
-            );
-            if let Some(texture_id) = self.texture_id {
-                ui.add(egui::Image::new(texture_id, tex_size));
+    /// The screen in the current palette, every pixel repeated `prescale` times in both directions
+    fn color_image(&self) -> ColorImage {
+        let scale = self.prescale;
+        let (texture_width, texture_height) = self.texture_size();
+        let width = texture_width * scale;
+        let mut colors = Vec::with_capacity(width * texture_height * scale * 3);
+        for y in 0..texture_height {
+            let start = colors.len();
+            for x in 0..texture_width {
+                let rgb = self.texture_pixel(x, y);
+                for _ in 0..scale {
+                    colors.extend_from_slice(&rgb);
+                }
             }
+            for _ in 1..scale {
+                colors.extend_from_within(start..start + width * 3);
+            }
+        }
+        ColorImage::from_rgb([width, texture_height * scale], &colors)
+    }
+    /// Whether the border is drawn around the screen
+    fn framed(&self) -> bool {
+        self.show_border && self.border.is_some()
+    }
+    /// In pixels, with the border if it is drawn
+    fn texture_size(&self) -> (usize, usize) {
+        match self.framed() {
+            true => (BORDER_WIDTH, BORDER_HEIGHT),
+            false => (GAME_SCREEN_WIDTH, GAME_SCREEN_HEIGHT),
+        }
+    }
+    /// The pixel of the screen or of the border around it
+    fn texture_pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let Some(border) = self.border.as_ref().filter(|_| self.show_border) else {
+            return self.pixel(y * GAME_SCREEN_WIDTH + x);
+        };
+        let (screen_x, screen_y) = (x.wrapping_sub(SCREEN_LEFT), y.wrapping_sub(SCREEN_TOP));
+        if screen_x < GAME_SCREEN_WIDTH && screen_y < GAME_SCREEN_HEIGHT {
+            self.pixel(screen_y * GAME_SCREEN_WIDTH + screen_x)
+        } else {
+            cgb::rgb(border[y * BORDER_WIDTH + x])
+        }
+    }
+    /// The part of the drawn `texture` which shows the game screen
+    fn screen_rect(&self, texture: Rect) -> Rect {
+        if !self.framed() {
+            return texture;
+        }
+        let scale = texture.width() / BORDER_WIDTH as f32;
+        Rect::from_min_size(
+            texture.min + vec2(SCREEN_LEFT as f32, SCREEN_TOP as f32) * scale,
+            vec2(GAME_SCREEN_WIDTH as f32, GAME_SCREEN_HEIGHT as f32) * scale,
+        )
+    }
+    /// Pixel `i` in the current palette or in its own color
+    fn pixel(&self, i: usize) -> [u8; 3] {
+        match &self.color_buffer {
+            Some(colors) => cgb::rgb(colors[i]),
+            None => self.color_palette[self.screen_buffer[i] as usize],
+        }
+    }
+    /// The screen in the current palette, four bytes per pixel
+    pub fn rgba(&self) -> Vec<u8> {
+        (0..GAME_SCREEN_WIDTH * GAME_SCREEN_HEIGHT)
+            .flat_map(|i| {
+                let [r, g, b] = self.pixel(i);
+                [r, g, b, 0xFF]
+            })
+            .collect()
+    }
+    /// Replaces the screen, it is uploaded with the next `update_texture`
+    pub fn set_frame(&mut self, frame: Box<FrameBuffer>) {
+        self.screen_buffer = frame;
+        self.color_buffer = None;
+        self.update_texture = true;
+    }
+    pub fn set_color_frame(&mut self, frame: Box<ColorFrameBuffer>) {
+        self.color_buffer = Some(frame);
+        self.update_texture = true;
+    }
+    /// The border of an SGB game, `None` for other games
+    pub fn set_border(&mut self, border: Option<Box<BorderImage>>) {
+        self.border = border;
+        self.update_texture = true;
+    }
+    pub fn has_border(&self) -> bool {
+        self.border.is_some()
+    }
+    /// The size of the screen at `scale`, rounded down to whole pixels with `integer_scale`.
+    /// Picks the prescale for it.
+    fn screen_size(&mut self, ctx: &egui::Context, scale: f32) -> Vec2 {
+        let pixels_per_point = ctx.pixels_per_point();
+        let scale = if self.integer_scale {
+            (scale * pixels_per_point).floor().max(1.) / pixels_per_point
+        } else {
+            scale
+        };
+        self.prescale = match self.scaling {
+            Scaling::SharpBilinear => {
+                let physical = scale * pixels_per_point;
+                (physical.floor() as usize).clamp(1, MAX_PRESCALE)
+            }
+            Scaling::Nearest | Scaling::Bilinear => 1,
+        };
+        let (width, height) = self.texture_size();
+        vec2(width as f32, height as f32) * scale
+    }
+    /// Shows the screen as large as it fits into `ui` with the aspect ratio kept,
+    /// centered. Returns where it was drawn.
+    pub fn view_fitted(&mut self, ui: &mut egui::Ui) -> Option<Rect> {
+        let available = ui.available_rect_before_wrap();
+        let (width, height) = self.texture_size();
+        let scale = (available.width() / width as f32).min(available.height() / height as f32);
+        let size = self.screen_size(ui.ctx(), scale);
+        let rect = Rect::from_center_size(available.center(), size);
+        let texture_id = self.texture_id?;
+        let texture = ui.put(rect, egui::Image::new(texture_id, size)).rect;
+        Some(self.screen_rect(texture))
+    }
+    /// Shows the screen and returns where it was drawn
+    pub fn view(&mut self, ui: &mut egui::Ui) -> Option<Rect> {
+        Frame::canvas(ui.style())
+            .show(ui, |ui| {
+                // fills the width, but never smaller than the native resolution
+                let scale = (ui.available_width() / self.texture_size().0 as f32).max(1.);
+                let tex_size = self.screen_size(ui.ctx(), scale);
+                let screen = self.texture_id.map(|texture_id| {
+                    self.screen_rect(ui.add(egui::Image::new(texture_id, tex_size)).rect)
+                });
 
-            let color = if ui.visuals().dark_mode {
-                Color32::from_additive_luminance(096)
-            } else {
-                Color32::from_black_alpha(040)
-            };
-            ui.ctx().request_repaint();
-            let time = ui.input().time;
+                let color = if ui.visuals().dark_mode {
+                    Color32::from_additive_luminance(096)
+                } else {
+                    Color32::from_black_alpha(040)
+                };
+                let time = ui.input().time;
 
-            let desired_size = ui.available_width() * vec2(1.0, 0.35);
-            let (_id, rect) = ui.allocate_space(desired_size);
+                let desired_size = ui.available_width() * vec2(1.0, 0.35);
+                let (_id, rect) = ui.allocate_space(desired_size);
 
-            let to_screen =
-                emath::RectTransform::from_to(Rect::from_x_y_ranges(0.0..=1.0, -1.0..=1.0), rect);
+                let to_screen = emath::RectTransform::from_to(
+                    Rect::from_x_y_ranges(0.0..=1.0, -1.0..=1.0),
+                    rect,
+                );
 
-            let mut shapes = vec![];
+                let mut shapes = vec![];
 
-            for &mode in &[2, 3, 5] {
-                let mode = mode as f64;
-                let n = 120;
-                let speed = 1.5;
+                for &mode in &[2, 3, 5] {
+                    let mode = mode as f64;
+                    let n = 120;
+                    let speed = 1.5;
 
-                let points: Vec<Pos2> = (0..=n)
-                    .map(|i| {
-                        let t = i as f64 / (n as f64);
-                        let amp = (time * speed * mode).sin() / mode;
-                        let y = amp * (t * std::f64::consts::TAU / 2.0 * mode).sin();
-                        to_screen * pos2(t as f32, y as f32)
-                    })
-                    .collect();
+                    let points: Vec<Pos2> = (0..=n)
+                        .map(|i| {
+                            let t = i as f64 / (n as f64);
+                            let amp = (time * speed * mode).sin() / mode;
+                            let y = amp * (t * std::f64::consts::TAU / 2.0 * mode).sin();
+                            to_screen * pos2(t as f32, y as f32)
+                        })
+                        .collect();
 
-                let thickness = 10.0 / mode as f32;
-                shapes.push(eframe::epaint::Shape::line(
-                    points,
-                    Stroke::new(thickness, color),
-                ));
-            }
-            ui.painter().extend(shapes);
-        });
+                    let thickness = 10.0 / mode as f32;
+                    shapes.push(eframe::epaint::Shape::line(
+                        points,
+                        Stroke::new(thickness, color),
+                    ));
+                }
+                ui.painter().extend(shapes);
+                screen
+            })
+            .inner
     }
 }
 
@@ -109,9 +275,16 @@ impl Default for GameWindow {
                 Color::grey().into(),
                 Color::light_grey().into(),
             ],
-            update_texture: false,
+            scaling: Scaling::default(),
+            integer_scale: false,
+            update_texture: true,
+            prescale: 1,
+            uploaded: ([[0; 3]; 4], Scaling::default(), 1, false),
             texture_id: None,
-            screen_buffer: [0x0; GAME_SCREEN_HEIGHT * GAME_SCREEN_WIDTH],
+            screen_buffer: Box::new([0x0; GAME_SCREEN_HEIGHT * GAME_SCREEN_WIDTH]),
+            color_buffer: None,
+            border: None,
+            show_border: true,
         }
     }
 }
@@ -141,4 +314,3 @@ impl Into<[u8; 3]> for Color {
         [self.0, self.1, self.2]
     }
 }
-
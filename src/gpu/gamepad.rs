@@ -0,0 +1,165 @@
+use super::i18n::I18n;
+use eframe::{egui, epaint::Color32};
+#[cfg(feature = "gamepad")]
+use gba::frontend::Buttons;
+#[cfg(feature = "gamepad")]
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+/// The face buttons where the Game Boy has them, A on the right and B below
+#[cfg(feature = "gamepad")]
+const BUTTONS: [(Button, u8); 8] = [
+    (Button::DPadRight, Buttons::RIGHT),
+    (Button::DPadLeft, Buttons::LEFT),
+    (Button::DPadUp, Buttons::UP),
+    (Button::DPadDown, Buttons::DOWN),
+    (Button::East, Buttons::A),
+    (Button::South, Buttons::B),
+    (Button::Select, Buttons::SELECT),
+    (Button::Start, Buttons::START),
+];
+/// How far the left stick has to be pushed to count as the d-pad
+#[cfg(feature = "gamepad")]
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// Buttons of the connected gamepads, which are added to the keyboard.
+/// Gamepads can be plugged in and out while the game runs.
+#[cfg_attr(not(feature = "gamepad"), derive(Default))]
+pub struct Gamepads {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<Gilrs>,
+    /// Names of the connected gamepads
+    connected: Vec<String>,
+    /// Only this gamepad plays, by name. Any of them if not set.
+    selected: Option<String>,
+    /// The selected gamepad is not connected, only the keyboard plays
+    missing: bool,
+}
+impl Gamepads {
+    pub fn selected(&self) -> Option<&str> {
+        self.selected.as_deref()
+    }
+    pub fn set_selected(&mut self, name: Option<String>) {
+        self.selected = name;
+        self.missing = is_missing(self.selected.as_ref(), &self.connected);
+    }
+    /// The buttons held on the selected gamepad, or on any without one
+    #[cfg(feature = "gamepad")]
+    pub fn update(&mut self) -> u8 {
+        let Some(gilrs) = &mut self.gilrs else {
+            return 0;
+        };
+        // the state of the buttons only updates with the events
+        let mut plugged = false;
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    log::info!("gamepad {} connected", gilrs.gamepad(event.id).name());
+                    plugged = true;
+                }
+                EventType::Disconnected => {
+                    log::info!("gamepad {} disconnected", gilrs.gamepad(event.id).name());
+                    plugged = true;
+                }
+                _ => {}
+            }
+        }
+        if plugged {
+            self.connected = gilrs
+                .gamepads()
+                .map(|(_, gamepad)| gamepad.name().to_string())
+                .collect();
+            let missing = is_missing(self.selected.as_ref(), &self.connected);
+            if let Some(name) = self.selected.as_ref().filter(|_| missing && !self.missing) {
+                log::warn!("gamepad {name} is gone, playing with the keyboard");
+            }
+            self.missing = missing;
+        }
+        let mut held = 0;
+        let selected = gilrs.gamepads().filter(|(_, gamepad)| {
+            self.selected
+                .as_ref()
+                .is_none_or(|name| gamepad.name() == name)
+        });
+        for (_, gamepad) in selected {
+            for (button, bit) in BUTTONS {
+                if gamepad.is_pressed(button) {
+                    held |= bit;
+                }
+            }
+            let x = gamepad.value(Axis::LeftStickX);
+            let y = gamepad.value(Axis::LeftStickY);
+            if x > STICK_THRESHOLD {
+                held |= Buttons::RIGHT;
+            } else if x < -STICK_THRESHOLD {
+                held |= Buttons::LEFT;
+            }
+            if y > STICK_THRESHOLD {
+                held |= Buttons::UP;
+            } else if y < -STICK_THRESHOLD {
+                held |= Buttons::DOWN;
+            }
+        }
+        held
+    }
+    #[cfg(not(feature = "gamepad"))]
+    pub fn update(&mut self) -> u8 {
+        0
+    }
+    /// Picks the gamepad which plays, the ones connected and the one picked before
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        if !cfg!(feature = "gamepad") {
+            ui.label(i18n.tr("gamepad-needs-feature"));
+            return;
+        }
+        let any = i18n.tr("gamepad-any");
+        let mut selected = self.selected.clone();
+        egui::ComboBox::from_label(i18n.tr("gamepad-play-with"))
+            .selected_text(selected.as_deref().unwrap_or(any))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, any);
+                let mut names = self.connected.clone();
+                names.extend(self.selected.clone().filter(|_| self.missing));
+                for name in names {
+                    ui.selectable_value(&mut selected, Some(name.clone()), name);
+                }
+            });
+        if selected != self.selected {
+            self.set_selected(selected);
+        }
+        if let Some(name) = self.selected.as_ref().filter(|_| self.missing) {
+            ui.colored_label(
+                Color32::YELLOW,
+                i18n.tr_args("gamepad-missing", &[("name", name)]),
+            );
+        }
+        ui.separator();
+        if self.connected.is_empty() {
+            ui.label(i18n.tr("gamepad-none"));
+        }
+        for name in &self.connected {
+            ui.label(name);
+        }
+    }
+}
+/// The selected gamepad is not one of the `connected`
+fn is_missing(selected: Option<&String>, connected: &[String]) -> bool {
+    selected.is_some_and(|name| !connected.contains(name))
+}
+#[cfg(feature = "gamepad")]
+impl Default for Gamepads {
+    fn default() -> Self {
+        let gilrs = Gilrs::new()
+            .map_err(|e| log::error!("could not open the gamepads: {e}"))
+            .ok();
+        Gamepads {
+            connected: gilrs
+                .iter()
+                .flat_map(|gilrs| gilrs.gamepads())
+                .map(|(_, gamepad)| gamepad.name().to_string())
+                .collect(),
+            gilrs,
+            selected: None,
+            missing: false,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::{Arc, RwLock};
+
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, RichText, Sense, TextStyle},
+    epaint::Color32,
+};
+use gba::{
+    debugger::{parse_address, parse_number, Debugger},
+    ram::{self, Ram, REGIONS},
+};
+
+const BYTES_PER_ROW: usize = 16;
+const ROWS: usize = 0x10000 / BYTES_PER_ROW;
+
+/// A live hex dump of the address space, bytes can be changed while paused
+pub struct HexView {
+    debugger: Debugger,
+    jump: String,
+    /// Scrolls there in the next frame
+    scroll_to: Option<u16>,
+    poke_addr: String,
+    poke_value: String,
+    error: Option<String>,
+}
+impl HexView {
+    pub fn new(debugger: Debugger) -> Self {
+        HexView {
+            debugger,
+            jump: String::new(),
+            scroll_to: None,
+            poke_addr: String::new(),
+            poke_value: String::new(),
+            error: None,
+        }
+    }
+    /// Writes go through the cpu thread, so they are only possible while `paused`
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, ram: &Arc<RwLock<Ram>>, paused: bool) {
+        ui.horizontal(|ui| {
+            for (name, range) in REGIONS {
+                if ui.button(name).clicked() {
+                    self.scroll_to = Some(range.start as u16);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.jump).desired_width(60.));
+            if ui.button(i18n.tr("memory-view-go")).clicked() {
+                match parse_address(self.jump.trim()) {
+                    Ok(addr) => self.scroll_to = Some(addr),
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let mut area = egui::ScrollArea::vertical()
+            .id_source("hex_view")
+            .max_height(300.);
+        if let Some(addr) = self.scroll_to.take() {
+            let row = addr as usize / BYTES_PER_ROW;
+            area = area
+                .vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+        area.show_rows(ui, row_height, ROWS, |ui, rows| {
+            let ram = ram::lock(ram);
+            for row in rows {
+                let start = (row * BYTES_PER_ROW) as u16;
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{start:04X}"));
+                    for addr in start..start + BYTES_PER_ROW as u16 {
+                        let byte = RichText::new(format!("{:02X}", ram[addr])).monospace();
+                        let label = ui.add(egui::Label::new(byte).sense(Sense::click()));
+                        if label.clicked() {
+                            self.poke_addr = format!("{addr:04X}");
+                            self.poke_value = format!("{:02X}", ram[addr]);
+                        }
+                    }
+                    let text: String = (start..start + BYTES_PER_ROW as u16)
+                        .map(|addr| match ram[addr] {
+                            byte @ 0x20..=0x7E => byte as char,
+                            _ => '.',
+                        })
+                        .collect();
+                    ui.monospace(text);
+                });
+            }
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(i18n.tr("memory-view-address"));
+            ui.add(egui::TextEdit::singleline(&mut self.poke_addr).desired_width(40.));
+            ui.label(i18n.tr("memory-view-value"));
+            ui.add(egui::TextEdit::singleline(&mut self.poke_value).desired_width(24.));
+            let write = ui
+                .add_enabled(paused, egui::Button::new(i18n.tr("memory-view-write")))
+                .on_disabled_hover_text(i18n.tr("memory-view-pause-first"));
+            if write.clicked() {
+                // the fields are hex like the dump
+                let addr = parse_address(&format!("${}", self.poke_addr.trim()));
+                let value = parse_number(&format!("${}", self.poke_value.trim()))
+                    .and_then(|v| u8::try_from(v).map_err(|_| format!("not a byte: {v}")));
+                match (addr, value) {
+                    (Ok(addr), Ok(value)) => {
+                        self.debugger.poke(addr, value);
+                        self.error = None;
+                    }
+                    (Err(e), _) | (_, Err(e)) => self.error = Some(e),
+                }
+            }
+        });
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::RED, e);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{fs, io, path::PathBuf};
+
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, FontId},
+    epaint::{vec2, Color32, Rect},
+};
+use gba::ram::Ram;
+
+/// How a memory value is shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    U8,
+    I8,
+    /// Little endian, like the cpu stores words
+    U16,
+    Hex8,
+    Hex16,
+    /// Two decimal digits per byte, common for scores and timers
+    Bcd,
+}
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::U8,
+        Format::I8,
+        Format::U16,
+        Format::Hex8,
+        Format::Hex16,
+        Format::Bcd,
+    ];
+    /// The name in the config file
+    fn name(self) -> &'static str {
+        match self {
+            Format::U8 => "u8",
+            Format::I8 => "i8",
+            Format::U16 => "u16",
+            Format::Hex8 => "hex8",
+            Format::Hex16 => "hex16",
+            Format::Bcd => "bcd",
+        }
+    }
+    fn parse(s: &str) -> Option<Format> {
+        Format::ALL.into_iter().find(|format| format.name() == s)
+    }
+    fn show(self, ram: &Ram, addr: u16) -> String {
+        let byte = ram[addr];
+        let word = || u16::from_le_bytes([byte, ram[addr.wrapping_add(1)]]);
+        match self {
+            Format::U8 => byte.to_string(),
+            Format::I8 => (byte as i8).to_string(),
+            Format::U16 => word().to_string(),
+            Format::Hex8 => format!("{byte:02X}"),
+            Format::Hex16 => format!("{:04X}", word()),
+            Format::Bcd => format!("{}{}", byte >> 4, byte & 0x0F),
+        }
+    }
+}
+
+/// A labeled memory value on the hud
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HudValue {
+    pub label: String,
+    pub addr: u16,
+    pub format: Format,
+}
+
+/// Memory values drawn over the game screen, configured in one file per game
+pub struct Hud {
+    pub visible: bool,
+    dir: PathBuf,
+    /// File name of the loaded game, `None` before the first frame
+    loaded: Option<String>,
+    values: Vec<HudValue>,
+    label: String,
+    addr: String,
+    format: Format,
+}
+impl Hud {
+    pub fn new(dir: PathBuf) -> Self {
+        Hud {
+            visible: false,
+            dir,
+            loaded: None,
+            values: Vec::new(),
+            label: String::new(),
+            addr: String::new(),
+            format: Format::default(),
+        }
+    }
+    /// Switches to the values of the game in `ram` if it changed
+    fn reload(&mut self, ram: &Ram) {
+        let file = format!(
+            "{}.txt",
+            ram.cartridge_title()
+                .unwrap_or_else(|| "default".to_string())
+        );
+        if self.loaded.as_ref() == Some(&file) {
+            return;
+        }
+        self.values = fs::read_to_string(self.dir.join(&file))
+            .map(|content| parse(&content))
+            .unwrap_or_default();
+        self.loaded = Some(file);
+    }
+    fn save(&self) {
+        let Some(file) = &self.loaded else {
+            return;
+        };
+        let write = || -> io::Result<()> {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(self.dir.join(file), to_string(&self.values))
+        };
+        if let Err(e) = write() {
+            log::warn!("could not save the hud: {e}");
+        }
+    }
+    /// Draws the values into the top left corner of `screen`
+    pub fn paint(&mut self, ui: &egui::Ui, screen: Rect, ram: &Ram) {
+        if !self.visible {
+            return;
+        }
+        self.reload(ram);
+        let painter = ui.painter_at(screen);
+        let font = FontId::monospace(14.);
+        let mut pos = screen.left_top() + vec2(4., 4.);
+        for value in &self.values {
+            let text = format!("{} {}", value.label, value.format.show(ram, value.addr));
+            let galley = painter.layout_no_wrap(text, font.clone(), Color32::WHITE);
+            let rect = Rect::from_min_size(pos, galley.size());
+            painter.rect_filled(rect.expand(2.), 2., Color32::from_black_alpha(160));
+            painter.galley(rect.min, galley);
+            pos.y += rect.height() + 4.;
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, ram: &Ram) {
+        self.reload(ram);
+        ui.checkbox(&mut self.visible, i18n.tr("hud-show"));
+        let mut remove = None;
+        egui::Grid::new("hud_values").show(ui, |ui| {
+            for (i, value) in self.values.iter().enumerate() {
+                ui.label(&value.label);
+                ui.monospace(format!("{:04X}", value.addr));
+                ui.label(value.format.name());
+                ui.monospace(value.format.show(ram, value.addr));
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.values.remove(i);
+            self.save();
+        }
+        ui.separator();
+        egui::Grid::new("hud_add").show(ui, |ui| {
+            ui.label(i18n.tr("hud-label"));
+            ui.text_edit_singleline(&mut self.label);
+            ui.end_row();
+            ui.label(i18n.tr("hud-address"));
+            ui.text_edit_singleline(&mut self.addr);
+            ui.end_row();
+            ui.label(i18n.tr("hud-format"));
+            egui::ComboBox::from_id_source("hud_format")
+                .selected_text(self.format.name())
+                .show_ui(ui, |ui| {
+                    for format in Format::ALL {
+                        ui.selectable_value(&mut self.format, format, format.name());
+                    }
+                });
+            ui.end_row();
+        });
+        let addr = u16::from_str_radix(self.addr.trim().trim_start_matches("0x"), 16).ok();
+        let add = egui::Button::new(i18n.tr("hud-add"));
+        if ui
+            .add_enabled(addr.is_some() && !self.label.is_empty(), add)
+            .clicked()
+        {
+            if let Some(addr) = addr {
+                self.values.push(HudValue {
+                    label: std::mem::take(&mut self.label).replace('\t', " "),
+                    addr,
+                    format: self.format,
+                });
+                self.save();
+            }
+        }
+    }
+}
+/// Parses one value per line: `label<tab>C0A0<tab>u16`, lines starting with `#` are skipped
+pub fn parse(content: &str) -> Vec<HudValue> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let label = fields.next()?.to_string();
+            let addr = u16::from_str_radix(fields.next()?.trim(), 16).ok()?;
+            let format = fields.next().map_or(Some(Format::default()), |format| {
+                Format::parse(format.trim())
+            })?;
+            Some(HudValue {
+                label,
+                addr,
+                format,
+            })
+        })
+        .collect()
+}
+pub fn to_string(values: &[HudValue]) -> String {
+    let mut content = String::from("# label\taddress\tformat\n");
+    for value in values {
+        content.push_str(&format!(
+            "{}\t{:04X}\t{}\n",
+            value.label,
+            value.addr,
+            value.format.name()
+        ));
+    }
+    content
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use eframe::egui;
+
+const BUILTIN: [(&str, &str); 2] = [
+    ("en", include_str!("../../i18n/en.ftl")),
+    ("de", include_str!("../../i18n/de.ftl")),
+];
+
+/// A translation catalog in a small subset of the fluent syntax:
+/// `key = value` lines, `#` comments and `{ $name }` placeables.
+struct Catalog {
+    code: String,
+    entries: HashMap<String, String>,
+}
+impl Catalog {
+    fn parse(code: &str, content: &str) -> Self {
+        let entries = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Catalog {
+            code: code.to_string(),
+            entries,
+        }
+    }
+    fn name(&self) -> &str {
+        self.entries
+            .get("language-name")
+            .map_or(&self.code, String::as_str)
+    }
+}
+
+/// All ui strings are looked up here by key.
+/// Missing keys fall back to english and then to the key itself.
+pub struct I18n {
+    catalogs: Vec<Catalog>,
+    current: usize,
+}
+impl I18n {
+    /// Loads the built in catalogs and every `<code>.ftl` file in `dir`.
+    /// Files in `dir` replace built in catalogs with the same code.
+    pub fn load(dir: &Path) -> Self {
+        let mut i18n = I18n::default();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return i18n;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|ext| ext != "ftl") {
+                continue;
+            }
+            let (Some(code), Ok(content)) = (
+                path.file_stem().and_then(|stem| stem.to_str()),
+                fs::read_to_string(&path),
+            ) else {
+                continue;
+            };
+            let catalog = Catalog::parse(code, &content);
+            match i18n.catalogs.iter_mut().find(|c| c.code == code) {
+                Some(existing) => *existing = catalog,
+                None => i18n.catalogs.push(catalog),
+            }
+        }
+        i18n
+    }
+    pub fn tr<'a>(&'a self, key: &'a str) -> &'a str {
+        self.catalogs[self.current]
+            .entries
+            .get(key)
+            .or_else(|| self.catalogs[0].entries.get(key))
+            .map_or(key, String::as_str)
+    }
+    /// Translates and replaces `{ $name }` with the matching argument
+    pub fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut text = self.tr(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{ ${name} }}"), value);
+        }
+        text
+    }
+    pub fn language_ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label(self.tr("accessibility-language"))
+            .selected_text(self.catalogs[self.current].name())
+            .show_ui(ui, |ui| {
+                for (i, catalog) in self.catalogs.iter().enumerate() {
+                    ui.selectable_value(&mut self.current, i, catalog.name());
+                }
+            });
+    }
+}
+impl Default for I18n {
+    /// Only the built in catalogs, english selected
+    fn default() -> Self {
+        I18n {
+            catalogs: BUILTIN
+                .into_iter()
+                .map(|(code, content)| Catalog::parse(code, content))
+                .collect(),
+            current: 0,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use super::{i18n::I18n, settings::BUTTON_NAMES};
+use eframe::egui::{self, Event, Key};
+use gba::frontend::{Buttons, SharedButtons};
+
+pub const DEFAULT_KEYMAP: [(Key, u8); 8] = [
+    (Key::ArrowRight, Buttons::RIGHT),
+    (Key::ArrowLeft, Buttons::LEFT),
+    (Key::ArrowUp, Buttons::UP),
+    (Key::ArrowDown, Buttons::DOWN),
+    (Key::X, Buttons::A),
+    (Key::Z, Buttons::B),
+    (Key::Backspace, Buttons::SELECT),
+    (Key::Enter, Buttons::START),
+];
+pub const MACRO_KEYS: [Key; 4] = [Key::F5, Key::F6, Key::F7, Key::F8];
+
+/// A recorded button sequence, one entry per gui frame
+struct Macro {
+    key: Key,
+    frames: Vec<Buttons>,
+    looping: bool,
+}
+
+/// Keyboard and gamepad input with macros on top. A macro records the held buttons frame by frame
+/// and plays them back when its key is pressed, pressing the key again stops it.
+pub struct Input {
+    /// The key of every button
+    keymap: [(Key, u8); 8],
+    /// The button waiting for a key press to be rebound
+    rebinding_button: Option<usize>,
+    macros: Vec<Macro>,
+    recording: Option<usize>,
+    /// Macro and frame which is played next
+    playing: Option<(usize, usize)>,
+    /// The macro waiting for a key press to be rebound
+    rebinding: Option<usize>,
+}
+impl Input {
+    /// Stores the buttons held on the keyboard and the `gamepad` this frame,
+    /// with the playing macro added
+    pub fn update(&mut self, ctx: &egui::Context, buttons: &SharedButtons, gamepad: u8) {
+        let input = ctx.input();
+        let mut held = self
+            .keymap
+            .iter()
+            .filter(|(key, _)| input.key_down(*key))
+            .fold(gamepad, |held, (_, button)| held | button);
+        // shift is a modifier in egui, not a key
+        if input.modifiers.shift {
+            held |= Buttons::SELECT;
+        }
+        if let Some(i) = self.recording {
+            self.macros[i].frames.push(Buttons(held));
+        } else if self.rebinding.is_none() && self.rebinding_button.is_none() {
+            for (i, m) in self.macros.iter().enumerate() {
+                if input.key_pressed(m.key) {
+                    self.playing = match self.playing {
+                        Some((playing, _)) if playing == i => None,
+                        _ => Some((i, 0)),
+                    };
+                }
+            }
+        }
+        if let Some((i, frame)) = self.playing {
+            let m = &self.macros[i];
+            let frame = if frame >= m.frames.len() && m.looping {
+                0
+            } else {
+                frame
+            };
+            match m.frames.get(frame) {
+                Some(buttons) => {
+                    held |= buttons.0;
+                    self.playing = Some((i, frame + 1));
+                }
+                None => self.playing = None,
+            }
+        }
+        buttons.set(Buttons(held));
+    }
+    pub fn keymap(&self) -> [(Key, u8); 8] {
+        self.keymap
+    }
+    pub fn set_keymap(&mut self, keymap: [(Key, u8); 8]) {
+        self.keymap = keymap;
+    }
+    /// The keys of the buttons and the macros, the shortcuts should not use them
+    pub fn keys(&self) -> Vec<Key> {
+        let buttons = self.keymap.iter().map(|(key, _)| *key);
+        buttons.chain(self.macros.iter().map(|m| m.key)).collect()
+    }
+    /// Rebinds a button to the next key pressed
+    pub fn buttons_ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        if let Some(i) = self.rebinding_button {
+            if let Some(key) = pressed_key(ui) {
+                if key != Key::Escape {
+                    self.keymap[i].0 = key;
+                }
+                self.rebinding_button = None;
+            }
+        }
+        egui::Grid::new("buttons").show(ui, |ui| {
+            for (i, (key, bit)) in self.keymap.iter().enumerate() {
+                if let Some((_, name)) = BUTTON_NAMES.iter().find(|(b, _)| b == bit) {
+                    ui.label(i18n.tr(&format!("button-{name}")));
+                }
+                let text = if self.rebinding_button == Some(i) {
+                    i18n.tr("shortcuts-press-key").to_string()
+                } else {
+                    format!("{key:?}")
+                };
+                if ui.button(text).clicked() {
+                    self.rebinding_button = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if ui.button(i18n.tr("shortcuts-reset")).clicked() {
+            self.keymap = DEFAULT_KEYMAP;
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        if let Some(i) = self.rebinding {
+            if let Some(key) = pressed_key(ui) {
+                if key != Key::Escape {
+                    self.macros[i].key = key;
+                }
+                self.rebinding = None;
+            }
+        }
+        egui::Grid::new("macros").show(ui, |ui| {
+            for (i, m) in self.macros.iter_mut().enumerate() {
+                let key = if self.rebinding == Some(i) {
+                    i18n.tr("shortcuts-press-key").to_string()
+                } else {
+                    format!("{:?}", m.key)
+                };
+                if ui.button(key).clicked() {
+                    self.rebinding = Some(i);
+                }
+                let record = if self.recording == Some(i) {
+                    i18n.tr("macro-stop")
+                } else {
+                    i18n.tr("macro-record")
+                };
+                if ui.button(record).clicked() {
+                    if self.recording == Some(i) {
+                        self.recording = None;
+                    } else {
+                        m.frames.clear();
+                        self.recording = Some(i);
+                        self.playing = None;
+                    }
+                }
+                ui.label(i18n.tr_args("macro-frames", &[("count", &m.frames.len().to_string())]));
+                ui.checkbox(&mut m.looping, i18n.tr("macro-loop"));
+                if self.playing.is_some_and(|(playing, _)| playing == i) {
+                    ui.label(i18n.tr("macro-playing"));
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            keymap: DEFAULT_KEYMAP,
+            rebinding_button: None,
+            macros: MACRO_KEYS
+                .into_iter()
+                .map(|key| Macro {
+                    key,
+                    frames: Vec::new(),
+                    looping: false,
+                })
+                .collect(),
+            recording: None,
+            playing: None,
+            rebinding: None,
+        }
+    }
+}
+/// The key pressed this frame, for rebinding
+fn pressed_key(ui: &egui::Ui) -> Option<Key> {
+    ui.input().events.iter().find_map(|event| match event {
+        Event::Key {
+            key, pressed: true, ..
+        } => Some(*key),
+        _ => None,
+    })
+}
@@ -0,0 +1,83 @@
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, Align2, Order, Sense},
+    epaint::{vec2, Color32, Rect},
+};
+use gba::{frontend::Buttons, speed::Speed, status::Status};
+
+const SIZE: egui::Vec2 = vec2(124., 50.);
+/// The side of a d-pad arm
+const PAD: f32 = 12.;
+const FACE_RADIUS: f32 = 8.;
+const PILL_SIZE: egui::Vec2 = vec2(14., 6.);
+
+/// The buttons the game reads and whether it fast-forwards, drawn over the bottom left
+/// corner for streams and recordings
+#[derive(Default)]
+pub struct InputDisplay {
+    visible: bool,
+}
+impl InputDisplay {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    pub fn show(&self, ctx: &egui::Context, i18n: &I18n, status: &Status, speed: &Speed) {
+        if !self.visible {
+            return;
+        }
+        egui::Area::new("input_display")
+            .order(Order::Foreground)
+            .anchor(Align2::LEFT_BOTTOM, vec2(10., -30.))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let (rect, _) = ui.allocate_exact_size(SIZE, Sense::hover());
+                    paint_buttons(ui, rect, status.buttons());
+                    let turbo = if speed.is_turbo() {
+                        i18n.tr_args(
+                            "input-display-turbo",
+                            &[("rate", i18n.tr(speed.turbo_rate().name()))],
+                        )
+                    } else {
+                        i18n.tr("input-display-normal").to_string()
+                    };
+                    ui.label(turbo);
+                });
+            });
+    }
+}
+
+/// A d-pad on the left, select and start in the middle and B and A on the right
+fn paint_buttons(ui: &egui::Ui, rect: Rect, buttons: Buttons) {
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let held = visuals.selection.bg_fill;
+    let stroke = visuals.widgets.noninteractive.fg_stroke;
+    let at = |x: f32, y: f32| rect.min + vec2(x, y);
+    let fill = |button: u8| {
+        if buttons.is_held(button) {
+            held
+        } else {
+            Color32::TRANSPARENT
+        }
+    };
+    let pad = at(PAD * 2., SIZE.y / 2.);
+    for (button, offset) in [
+        (Buttons::UP, vec2(0., -PAD)),
+        (Buttons::DOWN, vec2(0., PAD)),
+        (Buttons::LEFT, vec2(-PAD, 0.)),
+        (Buttons::RIGHT, vec2(PAD, 0.)),
+    ] {
+        let arm = Rect::from_center_size(pad + offset, vec2(PAD, PAD));
+        painter.rect(arm, 2., fill(button), stroke);
+    }
+    for (button, center) in [
+        (Buttons::SELECT, at(56., 40.)),
+        (Buttons::START, at(74., 40.)),
+    ] {
+        let pill = Rect::from_center_size(center, PILL_SIZE);
+        painter.rect(pill, PILL_SIZE.y / 2., fill(button), stroke);
+    }
+    for (button, center) in [(Buttons::B, at(94., 30.)), (Buttons::A, at(112., 20.))] {
+        painter.circle(center, FACE_RADIUS, fill(button), stroke);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use super::i18n::I18n;
+use eframe::egui::{self, TextStyle};
+use gba::{
+    bus,
+    debugger::{Debugger, Edit},
+    io_registers::{Group, IoRegister, IO_REGISTERS},
+    ram::Ram,
+};
+
+/// The hardware registers with their bits spelled out, which can be changed while paused
+pub struct IoView {
+    debugger: Debugger,
+    /// The text of the register fields being edited, by address
+    fields: HashMap<u16, String>,
+}
+impl IoView {
+    pub fn new(debugger: Debugger) -> Self {
+        IoView {
+            debugger,
+            fields: HashMap::new(),
+        }
+    }
+    /// Writes go through the cpu thread with the side effects of a cpu write,
+    /// so they are only possible while `paused`
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, ram: &Ram, paused: bool) {
+        egui::ScrollArea::vertical()
+            .id_source("io_view")
+            .max_height(500.)
+            .show(ui, |ui| {
+                for group in Group::ALL {
+                    egui::CollapsingHeader::new(i18n.tr(group_name(group)))
+                        .id_source(group_name(group))
+                        .show(ui, |ui| {
+                            for register in IO_REGISTERS.iter().filter(|r| r.group == group) {
+                                self.register_ui(ui, register, ram, paused);
+                            }
+                        });
+                }
+            });
+    }
+    fn register_ui(&mut self, ui: &mut egui::Ui, register: &IoRegister, ram: &Ram, paused: bool) {
+        let value = bus::read(ram, register.addr);
+        ui.horizontal(|ui| {
+            ui.monospace(format!("{:<4} {:04X}", register.name, register.addr));
+            let text = self
+                .fields
+                .entry(register.addr)
+                .or_insert_with(|| format!("{value:02X}"));
+            let field = ui.add_enabled(
+                paused,
+                egui::TextEdit::singleline(text)
+                    .desired_width(24.)
+                    .font(TextStyle::Monospace),
+            );
+            if field.lost_focus() {
+                if let Ok(new) = u8::from_str_radix(text.trim(), 16) {
+                    self.debugger.edit(Edit::Memory(register.addr, new));
+                }
+            }
+            if !field.has_focus() {
+                *text = format!("{value:02X}");
+            }
+        });
+        ui.indent(register.addr, |ui| {
+            for field in register.fields {
+                let label = format!(
+                    "{}.{} {} = {}",
+                    register.name,
+                    field.bits(),
+                    field.name,
+                    field.get(value)
+                );
+                if field.width == 1 {
+                    let mut set = field.get(value) != 0;
+                    let checkbox = egui::Checkbox::new(&mut set, label);
+                    if ui.add_enabled(paused, checkbox).changed() {
+                        let new = field.set(value, set as u8);
+                        self.debugger.edit(Edit::Memory(register.addr, new));
+                    }
+                } else {
+                    ui.monospace(label);
+                }
+            }
+        });
+    }
+}
+/// The i18n key of the heading
+fn group_name(group: Group) -> &'static str {
+    match group {
+        Group::Joypad => "io-joypad",
+        Group::Serial => "io-serial",
+        Group::Timer => "io-timer",
+        Group::Interrupts => "io-interrupts",
+        Group::Sound => "io-sound",
+        Group::Lcd => "io-lcd",
+        Group::Cgb => "io-cgb",
+        Group::System => "io-system",
+    }
+}
@@ -0,0 +1,132 @@
+use super::i18n::I18n;
+use eframe::{egui, epaint::Color32};
+use gba::{
+    link::{LinkState, LinkStatus, NetworkLink, DEFAULT_PORT},
+    paths::DataDirs,
+    serial::{self, LinkPort, Scripted, DEVICES},
+    trade,
+};
+
+/// Chooses what is plugged into the link port
+pub struct LinkPortPanel {
+    port: LinkPort,
+    /// Path typed for a scripted partner
+    script: String,
+    error: Option<String>,
+    /// Port typed for hosting a link over the network
+    host_port: String,
+    /// Address typed for joining
+    join_address: String,
+    /// The state of the network link which was plugged in last
+    network: Option<LinkState>,
+}
+impl LinkPortPanel {
+    pub fn new(port: LinkPort) -> Self {
+        LinkPortPanel {
+            port,
+            script: String::new(),
+            error: None,
+            host_port: DEFAULT_PORT.to_string(),
+            join_address: String::new(),
+            network: None,
+        }
+    }
+    /// `game` is the title of the inserted cartridge
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, dirs: &DataDirs, game: Option<&str>) {
+        let current = self.port.device_name();
+        let trade = game.map_or(Ok(()), trade::check_game);
+        egui::ComboBox::from_label(i18n.tr("link-port-device"))
+            .selected_text(i18n.tr(&format!("link-port-{current}")))
+            .show_ui(ui, |ui| {
+                for name in DEVICES {
+                    let label = i18n.tr(&format!("link-port-{name}")).to_string();
+                    let label = egui::SelectableLabel::new(current == name, label);
+                    let response = match &trade {
+                        Err(e) if name == "trade" => ui
+                            .add_enabled(false, label)
+                            .on_disabled_hover_text(e.as_str()),
+                        _ => ui.add(label),
+                    };
+                    if response.clicked() && current != name {
+                        if let Some(device) = serial::device(name, dirs) {
+                            self.port.plug(device);
+                        }
+                    }
+                }
+            });
+        if current == "printer" {
+            let dir = dirs.screenshots.join("printer").display().to_string();
+            ui.label(i18n.tr_args("link-port-printer-hint", &[("dir", &dir)]));
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.script)
+                    .hint_text(i18n.tr("link-port-script-hint")),
+            );
+            if ui.button(i18n.tr("link-port-plug")).clicked() {
+                match Scripted::load(self.script.trim().as_ref()) {
+                    Ok(script) => {
+                        self.port.plug(Box::new(script));
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+        });
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::RED, e);
+        }
+        ui.separator();
+        self.network_ui(ui, i18n, &current);
+    }
+    /// Hosting and joining a link cable to another instance
+    fn network_ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, current: &str) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.host_port).desired_width(60.));
+            if ui.button(i18n.tr("link-port-host")).clicked() {
+                match self.host_port.trim().parse() {
+                    Ok(port) => self.plug_network(NetworkLink::host(port)),
+                    Err(_) => {
+                        self.error = Some(
+                            i18n.tr_args("link-port-bad-port", &[("port", self.host_port.trim())]),
+                        )
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.join_address)
+                    .hint_text(i18n.tr("link-port-join-hint")),
+            );
+            if ui.button(i18n.tr("link-port-join")).clicked() {
+                self.plug_network(NetworkLink::join(self.join_address.trim()));
+            }
+        });
+        let Some(state) = self.network.as_ref().filter(|_| current == "network") else {
+            return;
+        };
+        match state.get() {
+            LinkStatus::Waiting(port) => {
+                ui.label(i18n.tr_args("link-status-waiting", &[("port", &port.to_string())]))
+            }
+            LinkStatus::Connecting(addr) => {
+                ui.label(i18n.tr_args("link-status-connecting", &[("addr", &addr)]))
+            }
+            LinkStatus::Connected(addr) => {
+                ui.label(i18n.tr_args("link-status-connected", &[("addr", &addr.to_string())]))
+            }
+            LinkStatus::Local => ui.label(i18n.tr("link-status-local")),
+            LinkStatus::Failed(e) => ui.colored_label(
+                Color32::RED,
+                i18n.tr_args("link-status-failed", &[("error", &e)]),
+            ),
+            LinkStatus::Closed => ui.label(i18n.tr("link-status-closed")),
+        };
+    }
+    fn plug_network(&mut self, link: NetworkLink) {
+        self.network = Some(link.state());
+        self.error = None;
+        self.port.plug(Box::new(link));
+    }
+}
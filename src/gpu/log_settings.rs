@@ -0,0 +1,35 @@
+use super::i18n::I18n;
+use eframe::egui;
+use gba::logging::{self, Subsystem};
+use log::LevelFilter;
+
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+/// Log level per subsystem, changes apply right away
+pub fn ui(ui: &mut egui::Ui, i18n: &I18n) {
+    egui::Grid::new("log_levels").show(ui, |ui| {
+        for subsystem in Subsystem::ALL {
+            ui.label(format!("{subsystem:?}"));
+            let mut level = logging::level(subsystem);
+            egui::ComboBox::from_id_source(subsystem as usize)
+                .selected_text(level.to_string())
+                .show_ui(ui, |ui| {
+                    for filter in LEVELS {
+                        ui.selectable_value(&mut level, filter, filter.to_string());
+                    }
+                });
+            if level != logging::level(subsystem) {
+                logging::set_level(subsystem, level);
+            }
+            ui.end_row();
+        }
+    });
+    ui.label(i18n.tr("logging-file-hint"));
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use super::{bookmarks::Bookmarks, i18n::I18n};
+use eframe::egui;
+use gba::{
+    ram::{self, Ram, REGIONS},
+    vram,
+};
+
+/// Dumps address ranges to a file and loads files back into memory
+pub struct MemoryTools {
+    ram: Option<Arc<RwLock<Ram>>>,
+    /// First address in hex
+    start: String,
+    /// Last address in hex, inclusive
+    end: String,
+    file: String,
+    message: Option<String>,
+    /// Where exported images are written
+    export_dir: PathBuf,
+    export_bg_maps: bool,
+    bookmarks: Bookmarks,
+}
+impl MemoryTools {
+    pub fn new(file: PathBuf, export_dir: PathBuf, bookmarks_dir: PathBuf) -> Self {
+        MemoryTools {
+            ram: None,
+            start: "C000".to_string(),
+            end: "DFFF".to_string(),
+            file: file.display().to_string(),
+            message: None,
+            export_dir,
+            export_bg_maps: false,
+            bookmarks: Bookmarks::new(bookmarks_dir),
+        }
+    }
+    pub fn set_ram(&mut self, ram: Arc<RwLock<Ram>>) {
+        self.ram = Some(ram);
+    }
+    /// Loading is only possible while `paused`, the cpu would overwrite it otherwise
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &I18n,
+        paused: bool,
+        rom_bank: u16,
+        palette: &[[u8; 3]; 4],
+    ) {
+        let Some(ram) = self.ram.clone() else {
+            ui.label(i18n.tr("memory-unavailable"));
+            return;
+        };
+        ui.horizontal(|ui| {
+            for (name, range) in REGIONS {
+                if ui.button(name).clicked() {
+                    self.start = format!("{:04X}", range.start);
+                    self.end = format!("{:04X}", range.end - 1);
+                }
+            }
+        });
+        egui::Grid::new("memory_tools").show(ui, |ui| {
+            ui.label(i18n.tr("memory-start"));
+            ui.text_edit_singleline(&mut self.start);
+            ui.end_row();
+            ui.label(i18n.tr("memory-end"));
+            ui.text_edit_singleline(&mut self.end);
+            ui.end_row();
+            ui.label(i18n.tr("memory-file"));
+            ui.text_edit_singleline(&mut self.file);
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            if ui.button(i18n.tr("memory-dump")).clicked() {
+                self.message = Some(match self.range() {
+                    Some(range) => {
+                        let len = range.len().to_string();
+                        match ram::lock(&ram).dump(range, Path::new(&self.file)) {
+                            Ok(()) => i18n
+                                .tr_args("memory-dumped", &[("bytes", &len), ("file", &self.file)]),
+                            Err(e) => i18n.tr_args("memory-error", &[("error", &e.to_string())]),
+                        }
+                    }
+                    None => i18n.tr("memory-invalid-range").to_string(),
+                });
+            }
+            let load = ui
+                .add_enabled(paused, egui::Button::new(i18n.tr("memory-load")))
+                .on_disabled_hover_text(i18n.tr("memory-pause-first"));
+            if load.clicked() {
+                self.message = Some(match self.range() {
+                    Some(range) => match ram::lock_mut(&ram).load(range, Path::new(&self.file)) {
+                        Ok(len) => i18n.tr_args(
+                            "memory-loaded",
+                            &[("bytes", &len.to_string()), ("file", &self.file)],
+                        ),
+                        Err(e) => i18n.tr_args("memory-error", &[("error", &e.to_string())]),
+                    },
+                    None => i18n.tr("memory-invalid-range").to_string(),
+                });
+            }
+        });
+        ui.separator();
+        ui.checkbox(&mut self.export_bg_maps, i18n.tr("memory-export-bg-maps"));
+        if ui.button(i18n.tr("memory-export-tiles")).clicked() {
+            self.message = Some(match self.export_tiles(&ram::lock(&ram), palette) {
+                Ok(path) => {
+                    i18n.tr_args("memory-exported", &[("file", &path.display().to_string())])
+                }
+                Err(e) => i18n.tr_args("memory-error", &[("error", &e.to_string())]),
+            });
+        }
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        ui.separator();
+        let selected = self
+            .range()
+            .map(|range| range.start as u16..=(range.end - 1) as u16);
+        ui.collapsing(i18n.tr("bookmarks"), |ui| {
+            let clicked = self
+                .bookmarks
+                .ui(ui, i18n, &ram::lock(&ram), rom_bank, selected);
+            if let Some(range) = clicked {
+                if range.start().bank != rom_bank && range.start().bank != 0 {
+                    self.message = Some(i18n.tr_args(
+                        "bookmarks-other-bank",
+                        &[("bank", &format!("{:02X}", range.start().bank))],
+                    ));
+                }
+                self.start = format!("{:04X}", range.start().addr);
+                self.end = format!("{:04X}", range.end().addr);
+            }
+        });
+    }
+    /// Writes `tiles.png` and optionally `bg-map-0.png` and `bg-map-1.png`,
+    /// returns the path of the tile sheet
+    fn export_tiles(&self, ram: &Ram, palette: &[[u8; 3]; 4]) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.export_dir)?;
+        let path = self.export_dir.join("tiles.png");
+        vram::tile_sheet(ram).save_png(&path, palette)?;
+        if self.export_bg_maps {
+            for map in 0..2 {
+                let map_path = self.export_dir.join(format!("bg-map-{map}.png"));
+                vram::bg_map(ram, map).save_png(&map_path, palette)?;
+            }
+        }
+        Ok(path)
+    }
+    fn range(&self) -> Option<Range<usize>> {
+        let parse = |s: &str| u16::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok();
+        let (start, end) = (parse(&self.start)?, parse(&self.end)?);
+        (start <= end).then(|| start as usize..end as usize + 1)
+    }
+}
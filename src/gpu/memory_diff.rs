@@ -0,0 +1,126 @@
+use std::sync::{Arc, RwLock};
+
+use super::i18n::I18n;
+use eframe::egui;
+use gba::{
+    ram::{self, Ram, WRAM},
+    speed::Speed,
+    status::Status,
+};
+
+/// Rows shown at most, a first diff usually changes thousands of bytes
+const MAX_ROWS: usize = 256;
+
+/// Snapshots WRAM, runs some frames and lists what changed.
+/// Narrowing the list down over several runs finds variables like a cheat search.
+pub struct MemoryDiff {
+    frames: u32,
+    snapshot: Option<Vec<u8>>,
+    /// Addresses still in the search, `None` before the first narrowing
+    candidates: Option<Vec<u16>>,
+    /// address, old and new value
+    changes: Vec<(u16, u8, u8)>,
+    /// Set while frames run, the diff is taken once the emulation paused again
+    waiting: bool,
+}
+impl MemoryDiff {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &I18n,
+        ram: &Arc<RwLock<Ram>>,
+        speed: &Speed,
+        status: &Status,
+    ) {
+        if self.waiting && speed.is_paused() {
+            self.waiting = false;
+            self.compare(&ram::lock(ram));
+        }
+        ui.label(i18n.tr_args("diff-frame", &[("frame", &status.frame().to_string())]));
+        ui.horizontal(|ui| {
+            if ui.button(i18n.tr("diff-snapshot")).clicked() {
+                self.snapshot = Some(wram(&ram::lock(ram)));
+                self.changes.clear();
+            }
+            ui.add(egui::DragValue::new(&mut self.frames).clamp_range(1..=600));
+            let run = egui::Button::new(i18n.tr("diff-run"));
+            if ui
+                .add_enabled(self.snapshot.is_some() && !self.waiting, run)
+                .clicked()
+            {
+                speed.run_frames(self.frames);
+                self.waiting = true;
+            }
+            let compare = egui::Button::new(i18n.tr("diff-compare"));
+            if ui.add_enabled(self.snapshot.is_some(), compare).clicked() {
+                self.compare(&ram::lock(ram));
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button(i18n.tr("diff-keep-changed")).clicked() {
+                self.candidates = Some(self.changes.iter().map(|(addr, ..)| *addr).collect());
+            }
+            if ui.button(i18n.tr("diff-reset")).clicked() {
+                self.candidates = None;
+                self.changes.clear();
+            }
+        });
+        if let Some(candidates) = &self.candidates {
+            ui.label(i18n.tr_args(
+                "diff-candidates",
+                &[("count", &candidates.len().to_string())],
+            ));
+        }
+        ui.label(i18n.tr_args(
+            "diff-changed",
+            &[("count", &self.changes.len().to_string())],
+        ));
+        egui::ScrollArea::vertical()
+            .max_height(300.)
+            .show(ui, |ui| {
+                egui::Grid::new("memory_diff").striped(true).show(ui, |ui| {
+                    for (addr, old, new) in self.changes.iter().take(MAX_ROWS) {
+                        ui.monospace(format!("{addr:04X}"));
+                        ui.monospace(format!("{old:02X} -> {new:02X}"));
+                        ui.monospace(format!("{old} -> {new}"));
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+    /// Diffs WRAM against the snapshot, which is replaced by the current content
+    fn compare(&mut self, ram: &Ram) {
+        let Some(snapshot) = &self.snapshot else {
+            return;
+        };
+        let current = wram(ram);
+        self.changes = snapshot
+            .iter()
+            .zip(&current)
+            .enumerate()
+            .map(|(i, (old, new))| ((WRAM.start + i) as u16, *old, *new))
+            .filter(|(addr, old, new)| {
+                old != new
+                    && self
+                        .candidates
+                        .as_ref()
+                        .is_none_or(|candidates| candidates.binary_search(addr).is_ok())
+            })
+            .collect();
+        self.snapshot = Some(current);
+    }
+}
+impl Default for MemoryDiff {
+    fn default() -> Self {
+        MemoryDiff {
+            frames: 60,
+            snapshot: None,
+            candidates: None,
+            changes: Vec::new(),
+            waiting: false,
+        }
+    }
+}
+fn wram(ram: &Ram) -> Vec<u8> {
+    WRAM.map(|addr| ram[addr as u16]).collect()
+}
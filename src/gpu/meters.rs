@@ -0,0 +1,120 @@
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, Sense},
+    epaint::{pos2, vec2, Color32, Rect, Stroke},
+};
+use gba::channels::{Channels, CHANNELS, WAVE_LENGTH};
+
+const METER_SIZE: egui::Vec2 = vec2(6., 14.);
+const MIXER_METER_SIZE: egui::Vec2 = vec2(10., 32.);
+const WAVE_SIZE: egui::Vec2 = vec2(128., 32.);
+/// Share of the level kept each gui frame, so short notes stay visible
+const DECAY: f32 = 0.85;
+/// Short names in the status bar, the channels are numbered like in the registers
+const LABELS: [&str; CHANNELS] = ["1", "2", "3", "4"];
+/// Names in the mixer, i18n keys
+const NAMES: [&str; CHANNELS] = [
+    "mixer-square-1",
+    "mixer-square-2",
+    "mixer-wave",
+    "mixer-noise",
+];
+
+/// A level meter per sound channel
+pub struct ChannelMeters {
+    channels: Channels,
+    /// 0.0..=1.0
+    levels: [f32; CHANNELS],
+}
+impl ChannelMeters {
+    pub fn new(channels: Channels) -> Self {
+        ChannelMeters {
+            channels,
+            levels: [0.; CHANNELS],
+        }
+    }
+    pub fn channels(&self) -> &Channels {
+        &self.channels
+    }
+    /// Takes the peaks the apu recorded since the last frame
+    pub fn update(&mut self) {
+        for (channel, level) in self.levels.iter_mut().enumerate() {
+            let peak = self.channels.take_level(channel) as f32 / u8::MAX as f32;
+            *level = peak.max(*level * DECAY);
+        }
+    }
+    /// Silenced channels are greyed out, a soloed one is highlighted
+    fn color(&self, ui: &egui::Ui, channel: usize) -> Color32 {
+        if !self.channels.is_audible(channel) {
+            ui.visuals().weak_text_color()
+        } else if self.channels.solo() == Some(channel) {
+            Color32::YELLOW
+        } else {
+            Color32::LIGHT_GREEN
+        }
+    }
+    fn meter(&self, ui: &mut egui::Ui, channel: usize, size: egui::Vec2) {
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+        if self.channels.is_audible(channel) {
+            let top = rect.bottom() - rect.height() * self.levels[channel];
+            let bar = Rect::from_min_max(pos2(rect.left(), top), rect.right_bottom());
+            painter.rect_filled(bar, 0., self.color(ui, channel));
+        }
+    }
+    /// Draws the meters next to each other
+    pub fn show(&self, ui: &mut egui::Ui) {
+        for (channel, label) in LABELS.into_iter().enumerate() {
+            ui.colored_label(self.color(ui, channel), label);
+            self.meter(ui, channel, METER_SIZE);
+        }
+    }
+    /// A row per channel to mute, solo and set the volume of it, with its level and waveform
+    pub fn mixer_ui(&self, ui: &mut egui::Ui, i18n: &I18n) {
+        egui::Grid::new("mixer").show(ui, |ui| {
+            for (channel, name) in NAMES.into_iter().enumerate() {
+                ui.colored_label(self.color(ui, channel), i18n.tr(name));
+                let mut muted = self.channels.is_muted(channel);
+                if ui.checkbox(&mut muted, i18n.tr("mixer-mute")).changed() {
+                    self.channels.toggle_mute(channel);
+                }
+                let mut solo = self.channels.solo() == Some(channel);
+                if ui.checkbox(&mut solo, i18n.tr("mixer-solo")).changed() {
+                    self.channels.toggle_solo(channel);
+                }
+                let mut volume = self.channels.channel_volume(channel);
+                if ui
+                    .add(egui::Slider::new(&mut volume, 0..=100).suffix("%"))
+                    .changed()
+                {
+                    self.channels.set_channel_volume(channel, volume);
+                }
+                self.meter(ui, channel, MIXER_METER_SIZE);
+                self.wave(ui, channel);
+                ui.end_row();
+            }
+        });
+    }
+    /// The latest outputs of the channel as a line, the dac range 0..=15 fills the height
+    fn wave(&self, ui: &mut egui::Ui, channel: usize) {
+        let (rect, _) = ui.allocate_exact_size(WAVE_SIZE, Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+        let points = self
+            .channels
+            .wave(channel)
+            .iter()
+            .enumerate()
+            .map(|(i, output)| {
+                let x = rect.left() + rect.width() * i as f32 / (WAVE_LENGTH - 1) as f32;
+                let y = rect.bottom() - rect.height() * *output as f32 / 15.;
+                pos2(x, y)
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            Stroke::new(1., self.color(ui, channel)),
+        ));
+    }
+}
@@ -1,101 +1,1329 @@
-use std::{sync::mpsc::Receiver};
+use std::{
+    path::{Path, PathBuf},
+    sync::{mpsc::Receiver, Arc, Mutex, RwLock},
+    time::Duration,
+};
 
-use self::game_window::{GameWindow, GAME_SCREEN_HEIGHT, GAME_SCREEN_SCALE, GAME_SCREEN_WIDTH};
-use eframe::{egui, epaint::vec2};
+pub use self::second_game::{Launcher, SecondGame};
+use self::{
+    accessibility::Accessibility,
+    camera::CameraTools,
+    capture::Capture,
+    cheats::CheatsPanel,
+    clipboard::Clipboard,
+    console::Console,
+    crash_dialog::CrashDialog,
+    debugger::{layers_ui, trace_ui, DebuggerPanel},
+    game_window::{GameWindow, Scaling, GAME_SCREEN_HEIGHT, GAME_SCREEN_SCALE, GAME_SCREEN_WIDTH},
+    gamepad::Gamepads,
+    hex_view::HexView,
+    hud::Hud,
+    i18n::I18n,
+    input::Input,
+    input_display::InputDisplay,
+    io_view::IoView,
+    link_port::LinkPortPanel,
+    memory::MemoryTools,
+    memory_diff::MemoryDiff,
+    meters::ChannelMeters,
+    movie::MoviePanel,
+    oam_viewer::OamViewer,
+    palettes::Palettes,
+    perf_overlay::PerfOverlay,
+    playtime::Playtime,
+    practice::PracticePanel,
+    registers::RegisterPanel,
+    roms::{RecentRoms, RomChoice},
+    rtc::RtcPanel,
+    serial_output::SerialPanel,
+    settings::{Settings, SettingsFile},
+    shortcuts::{Action, Shortcuts},
+    slots::SlotsPanel,
+    speedrun::SpeedrunTimer,
+    timeline::Timeline,
+    update::UpdateCheck,
+    vram_viewer::VramViewer,
+};
+use eframe::{
+    egui::{self, Align2, RichText},
+    epaint::{vec2, Color32},
+};
+use gba::{
+    camera::POCKET_CAMERA,
+    cartridge::CARTRIDGE_TYPE,
+    channels::Channels,
+    cheats::Cheats,
+    cpu::CpuMode,
+    crash::CrashReport,
+    debugger::Debugger,
+    frontend::{Buttons, DrawSignal, SharedButtons},
+    layers::Layers,
+    movie::Movies,
+    paths::DataDirs,
+    perf::PerfStats,
+    power::{Power, PowerRequest},
+    practice::Practice,
+    ram::{self, Ram},
+    rewind::Rewind,
+    rtc::{Rtc, MBC3_TIMER},
+    serial::LinkPort,
+    sgb::SgbBorder,
+    slots::Slots,
+    speed::{Desync, FocusLoss, Refresh, Speed, Turbo},
+    status::Status,
+    symbols::Symbols,
+    trace::Trace,
+};
+mod accessibility;
+mod bookmarks;
+mod camera;
+mod capture;
+mod cheats;
+mod clipboard;
+mod console;
+mod crash_dialog;
+mod debugger;
 mod game_window;
+mod gamepad;
+mod hex_view;
+mod hud;
+mod i18n;
+mod input;
+mod input_display;
+mod io_view;
+mod link_port;
+mod log_settings;
+mod memory;
+mod memory_diff;
+mod meters;
+mod movie;
+mod oam_viewer;
+mod palettes;
+mod perf_overlay;
+mod playtime;
+mod practice;
+mod registers;
+mod roms;
+mod rtc;
+mod second_game;
+mod serial_output;
+mod settings;
+mod shortcuts;
+mod slots;
+mod speedrun;
+mod status_bar;
+mod timeline;
+mod update;
+mod vram_viewer;
 
 const _BUFFER_SIZE: usize = 0;
 const WINDOW_HEIGHT: f32 = 400.;
 const WINDOW_WIDTH: f32 = 700.;
+const APP_NAME: &str = "Gameboy Emulator";
+/// One frame of the emulated display, which runs at about 59.7Hz
+const FRAME_TIME: Duration = Duration::from_micros(16_743);
+/// Repaint interval while only audio is played in the background
+const BACKGROUND_FRAME_TIME: Duration = Duration::from_millis(250);
 pub struct Gpu {
     signal_receiver: Receiver<DrawSignal>,
     window: Window,
+    dirs: DataDirs,
+    speedrun: SpeedrunTimer,
+    speed: Speed,
+    focus_loss: FocusLoss,
+    /// Speed before the window lost focus, if it was changed because of it
+    unfocused_from: Option<u32>,
+    title: String,
+    crash_receiver: Option<Receiver<CrashReport>>,
+    crash: Option<CrashDialog>,
+    shortcuts: Shortcuts,
+    accessibility: Accessibility,
+    i18n: I18n,
+    /// Caps the ui refresh to the emulated frame rate
+    low_power: bool,
+    /// Fast-forward toggled in the speed window, the key only while held
+    turbo: bool,
+    /// Stops drawing the screen while the window is in the background
+    audio_only_in_background: bool,
+    memory: MemoryTools,
+    memory_diff: MemoryDiff,
+    status: Status,
+    ram: Option<Arc<RwLock<Ram>>>,
+    input: SharedButtons,
+    keyboard: Input,
+    gamepads: Gamepads,
+    clipboard: Clipboard,
+    hud: Hud,
+    perf_overlay: PerfOverlay,
+    input_display: InputDisplay,
+    camera: CameraTools,
+    rtc: Option<RtcPanel>,
+    playtime: Playtime,
+    meters: ChannelMeters,
+    practice: PracticePanel,
+    rewind: Rewind,
+    slots: SlotsPanel,
+    timeline: Timeline,
+    movie: MoviePanel,
+    console: Console,
+    debugger: DebuggerPanel,
+    hex_view: HexView,
+    io_view: IoView,
+    update: UpdateCheck,
+    link_port: LinkPortPanel,
+    serial_output: SerialPanel,
+    registers: RegisterPanel,
+    vram_viewer: VramViewer,
+    oam_viewer: OamViewer,
+    trace: Trace,
+    /// Integer scale the game screen opens with
+    scale: usize,
+    /// Where the last rom was loaded from
+    last_rom_dir: Option<PathBuf>,
+    settings_file: SettingsFile,
+    palettes: Palettes,
+    fullscreen: bool,
+    /// Hides everything but the game screen
+    play_mode: bool,
+    capture: Capture,
+    power: Power,
+    recent_roms: RecentRoms,
+    cheats: CheatsPanel,
+    symbols: Symbols,
+    layers: Layers,
+    sgb_border: SgbBorder,
+    /// Of the border shown, to notice a new one
+    border_version: u32,
+    /// The games next to this one, see `SecondGame`
+    others: Vec<SecondGame>,
+    /// Opens the roms the file menu starts in a window of their own
+    launcher: Option<Launcher>,
 }
 impl Gpu {
-    pub fn new(receiver: Receiver<DrawSignal>) -> Self {
-        Gpu {
+    pub fn new(receiver: Receiver<DrawSignal>, dirs: DataDirs) -> Self {
+        let (settings_file, settings) = SettingsFile::load(dirs.config.join(settings::FILE_NAME));
+        let mut gpu = Gpu {
             signal_receiver: receiver,
             window: Window::default(),
+            speedrun: SpeedrunTimer::default(),
+            speed: Speed::default(),
+            focus_loss: FocusLoss::default(),
+            unfocused_from: None,
+            title: APP_NAME.to_string(),
+            crash_receiver: None,
+            crash: None,
+            shortcuts: Shortcuts::default(),
+            accessibility: Accessibility::default(),
+            i18n: I18n::load(&dirs.config.join("i18n")),
+            low_power: false,
+            turbo: false,
+            audio_only_in_background: false,
+            status: Status::default(),
+            ram: None,
+            input: SharedButtons::default(),
+            keyboard: Input::default(),
+            gamepads: Gamepads::default(),
+            clipboard: Clipboard::default(),
+            hud: Hud::new(dirs.config.join("hud")),
+            perf_overlay: PerfOverlay::new(PerfStats::default()),
+            input_display: InputDisplay::default(),
+            camera: CameraTools::new(
+                dirs.saves.join("GAMEBOYCAMERA.sav"),
+                dirs.screenshots.join("camera"),
+            ),
+            rtc: None,
+            playtime: Playtime::load(dirs.config.join("playtime.txt")),
+            update: UpdateCheck::load(dirs.config.join("update.txt")),
+            meters: ChannelMeters::new(Channels::default()),
+            practice: PracticePanel::new(Practice::default()),
+            rewind: Rewind::default(),
+            slots: SlotsPanel::new(Slots::default()),
+            timeline: Timeline::default(),
+            movie: MoviePanel::new(Movies::default(), dirs.states.join("movies")),
+            console: Console::new(Debugger::default()),
+            debugger: DebuggerPanel::new(Debugger::default()),
+            hex_view: HexView::new(Debugger::default()),
+            io_view: IoView::new(Debugger::default()),
+            registers: RegisterPanel::new(Debugger::default()),
+            vram_viewer: VramViewer::default(),
+            oam_viewer: OamViewer::default(),
+            trace: Trace::default(),
+            scale: GAME_SCREEN_SCALE,
+            last_rom_dir: None,
+            settings_file,
+            palettes: Palettes::default(),
+            fullscreen: false,
+            play_mode: false,
+            capture: Capture::new(dirs.screenshots.clone()),
+            power: Power::default(),
+            recent_roms: RecentRoms::default(),
+            cheats: CheatsPanel::new(Cheats::default(), dirs.config.join("cheats")),
+            symbols: Symbols::default(),
+            layers: Layers::default(),
+            sgb_border: SgbBorder::default(),
+            border_version: 0,
+            others: Vec::new(),
+            launcher: None,
+            link_port: LinkPortPanel::new(LinkPort::default()),
+            serial_output: SerialPanel::new(LinkPort::default().output()),
+            memory_diff: MemoryDiff::default(),
+            memory: MemoryTools::new(
+                dirs.states.join("memory.bin"),
+                dirs.screenshots.clone(),
+                dirs.config.join("bookmarks"),
+            ),
+            dirs,
+        };
+        gpu.apply_settings(settings);
+        gpu
+    }
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale;
+        self
+    }
+    pub fn with_crash_receiver(mut self, receiver: Receiver<CrashReport>) -> Self {
+        self.crash_receiver = Some(receiver);
+        self
+    }
+    pub fn with_ram(mut self, ram: Arc<RwLock<Ram>>) -> Self {
+        self.memory.set_ram(ram.clone());
+        self.ram = Some(ram);
+        self
+    }
+    pub fn with_rtc(mut self, rtc: Arc<Mutex<Rtc>>) -> Self {
+        self.rtc = Some(RtcPanel::new(rtc));
+        self
+    }
+    /// The cpu writes the trace, the debugger panel starts and stops it
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.trace = trace;
+        self
+    }
+    /// The bus exchanges the serial bytes with the device the panel plugs in
+    pub fn with_link_port(mut self, port: LinkPort) -> Self {
+        self.serial_output = SerialPanel::new(port.output());
+        self.link_port = LinkPortPanel::new(port);
+        self
+    }
+    pub fn with_status(mut self, status: Status) -> Self {
+        self.status = status;
+        self
+    }
+    /// The keyboard state is written to `input`, which the core reads
+    pub fn with_input(mut self, input: SharedButtons) -> Self {
+        self.input = input;
+        self
+    }
+    /// Mute and solo go to `channels`, which the apu reads
+    pub fn with_channels(mut self, channels: Channels) -> Self {
+        channels.set_volume(self.meters.channels().volume());
+        self.meters = ChannelMeters::new(channels);
+        self
+    }
+    /// The cpu takes the practice snapshots, the panel only changes the settings
+    pub fn with_practice(mut self, practice: Practice) -> Self {
+        self.practice = PracticePanel::new(practice);
+        self
+    }
+    /// The cpu keeps the history, the gui holds the key and shows how much there is
+    pub fn with_rewind(mut self, rewind: Rewind) -> Self {
+        self.rewind = rewind;
+        self
+    }
+    /// The cpu pokes and patches the enabled codes, the panel edits them
+    pub fn with_cheats(mut self, cheats: Cheats) -> Self {
+        self.cheats = CheatsPanel::new(cheats, self.dirs.config.join("cheats"));
+        self
+    }
+    /// The cpu loads the labels with the rom, the debugger and the console show them
+    pub fn with_symbols(mut self, symbols: Symbols) -> Self {
+        self.symbols = symbols;
+        self
+    }
+    /// The cpu saves and loads the states, the panel requests it
+    pub fn with_slots(mut self, slots: Slots) -> Self {
+        slots.set_suspend_on_exit(self.slots.slots().suspend_on_exit());
+        self.slots = SlotsPanel::new(slots);
+        self
+    }
+    /// The ppu leaves out or tints the layers the debugger asks for
+    pub fn with_layers(mut self, layers: Layers) -> Self {
+        self.layers = layers;
+        self
+    }
+    /// SGB games send their border there
+    pub fn with_sgb_border(mut self, border: SgbBorder) -> Self {
+        self.sgb_border = border;
+        self
+    }
+    /// The cpu measures the frame times while the overlay is shown
+    pub fn with_perf(mut self, perf: PerfStats) -> Self {
+        self.perf_overlay = PerfOverlay::new(perf);
+        self
+    }
+    /// The cpu records and plays the movies, the panel requests it
+    pub fn with_movies(mut self, movies: Movies) -> Self {
+        self.movie = MoviePanel::new(movies, self.dirs.states.join("movies"));
+        self
+    }
+    /// The cpu pauses on the breakpoints and watchpoints of `debugger`
+    pub fn with_debugger(mut self, debugger: Debugger) -> Self {
+        self.console = Console::new(debugger.clone());
+        self.debugger = DebuggerPanel::new(debugger.clone());
+        self.hex_view = HexView::new(debugger.clone());
+        self.io_view = IoView::new(debugger.clone());
+        self.registers = RegisterPanel::new(debugger);
+        self
+    }
+    /// Remembers the directory of the rom for the next start
+    pub fn with_rom_path(mut self, path: &Path) -> Self {
+        self.remember_rom(path);
+        self
+    }
+    fn remember_rom(&mut self, path: &Path) {
+        self.last_rom_dir = path.parent().map(Path::to_path_buf);
+        // relative paths would not be found from another working directory
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.recent_roms.push(path);
+    }
+    /// Swaps the cartridge, the cpu starts over with it
+    fn load_rom(&mut self, path: PathBuf) {
+        self.remember_rom(&path);
+        self.power.request(PowerRequest::Insert(path));
+    }
+    /// Loads the first rom dropped onto the window
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx
+            .input()
+            .raw
+            .dropped_files
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .find(|path| roms::is_rom(path));
+        if let Some(path) = dropped {
+            self.load_rom(path);
         }
     }
+    /// The cpu resets and switches off when the toolbar asks for it
+    pub fn with_power(mut self, power: Power) -> Self {
+        self.power = power;
+        self
+    }
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+    /// Shown next to the game, see `SecondGame`
+    pub fn with_second(mut self, second: SecondGame) -> Self {
+        self.others.push(second);
+        self
+    }
+    /// The file menu can open roms next to this one
+    pub fn with_launcher(mut self, launcher: Launcher) -> Self {
+        self.launcher = Some(launcher);
+        self
+    }
     pub fn init_window(mut self, cc: &eframe::CreationContext) -> Self {
         self.window.init(&cc.egui_ctx);
+        for other in &mut self.others {
+            other.init(&cc.egui_ctx);
+        }
         self
     }
+    /// Runs the rom in another window next to this game
+    fn open_other(&mut self, ctx: &egui::Context, path: PathBuf) {
+        self.remember_rom(&path);
+        let Some(launcher) = &self.launcher else {
+            return;
+        };
+        let mut other = launcher(path);
+        other.init(ctx);
+        self.others.push(other);
+    }
+    /// Shows the other games and stops the ones closed. Only one of them plays with the keyboard.
+    fn others_ui(&mut self, ctx: &egui::Context, size: egui::Vec2) {
+        let mut closed = None;
+        let mut controlled = None;
+        for (i, other) in self.others.iter_mut().enumerate() {
+            let was_controlled = other.is_controlled();
+            if !other.show(ctx, &self.i18n, size) {
+                closed = Some(i);
+            }
+            if other.is_controlled() && !was_controlled {
+                controlled = Some(i);
+            }
+        }
+        if let Some(controlled) = controlled {
+            for (i, other) in self.others.iter_mut().enumerate() {
+                if i != controlled {
+                    other.release();
+                }
+            }
+        }
+        if let Some(closed) = closed {
+            self.others.remove(closed).stop();
+        }
+    }
 
+    fn apply_settings(&mut self, settings: Settings) {
+        self.keyboard.set_keymap(settings.buttons);
+        self.shortcuts.set_bindings(settings.shortcuts);
+        self.window.game_window.color_palette = settings.palette;
+        self.scale = settings.scale;
+        self.window.game_window.integer_scale = settings.integer_scale;
+        self.window.game_window.show_border = settings.sgb_border;
+        self.meters.channels().set_volume(settings.volume);
+        self.last_rom_dir = settings.last_rom_dir;
+        self.recent_roms.paths = settings.recent_roms;
+        self.palettes.user = settings.user_palettes;
+        self.palettes.games = settings.game_palettes;
+        self.slots
+            .slots()
+            .set_suspend_on_exit(settings.suspend_on_exit);
+        self.gamepads.set_selected(settings.gamepad);
+    }
+    fn settings(&self) -> Settings {
+        Settings {
+            buttons: self.keyboard.keymap(),
+            shortcuts: self.shortcuts.bindings().to_vec(),
+            palette: self.window.game_window.color_palette,
+            scale: self.scale,
+            integer_scale: self.window.game_window.integer_scale,
+            sgb_border: self.window.game_window.show_border,
+            volume: self.meters.channels().volume(),
+            last_rom_dir: self.last_rom_dir.clone(),
+            recent_roms: self.recent_roms.paths.clone(),
+            user_palettes: self.palettes.user.clone(),
+            game_palettes: self.palettes.games.clone(),
+            suspend_on_exit: self.slots.slots().suspend_on_exit(),
+            gamepad: self.gamepads.selected().map(str::to_string),
+        }
+    }
+    fn handle_shortcuts(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        for action in self.shortcuts.pressed(ctx) {
+            match action {
+                Action::SpeedrunSplit => self.speedrun.start_or_split(),
+                Action::SpeedrunReset => self.speedrun.reset(),
+                Action::SpeedUp => self.speed.faster(),
+                Action::SpeedDown => self.speed.slower(),
+                Action::CopyScreen => self.clipboard.copy_screen(&self.window.game_window),
+                Action::CopyRegisters => self.copy_registers(ctx),
+                Action::ToggleHud => self.hud.visible = !self.hud.visible,
+                Action::PerfOverlay => self.perf_overlay.toggle(),
+                Action::InputDisplay => self.input_display.toggle(),
+                Action::MuteChannel(channel) => self.meters.channels().toggle_mute(channel),
+                Action::SoloChannel(channel) => self.meters.channels().toggle_solo(channel),
+                Action::PracticeRetry => self.practice.retry(),
+                Action::SaveState => self.slots.save_selected(),
+                Action::LoadState => self.slots.load_selected(),
+                Action::PreviousSlot => self.slots.slots().previous(),
+                Action::NextSlot => self.slots.slots().next(),
+                Action::Fullscreen => {
+                    self.fullscreen = !self.fullscreen;
+                    frame.set_fullscreen(self.fullscreen);
+                }
+                Action::PlayMode => self.play_mode = !self.play_mode,
+                Action::Pause => self.speed.set_paused(!self.speed.is_paused()),
+                Action::Reset => self.power.request(PowerRequest::Reset),
+                Action::Screenshot => self.capture.screenshot(&self.window.game_window),
+                Action::Record => self.capture.toggle_recording(),
+                // held instead of pressed, see below
+                Action::FastForward | Action::Rewind => {}
+            }
+        }
+        self.speed
+            .set_turbo(self.turbo || self.shortcuts.held(ctx, Action::FastForward));
+        self.rewind
+            .set_rewinding(self.shortcuts.held(ctx, Action::Rewind));
+    }
+    /// Only the game screen on a black background, filling the window
+    fn play_mode_ui(&mut self, ctx: &egui::Context) {
+        let background = egui::Frame::none().fill(Color32::BLACK);
+        egui::CentralPanel::default()
+            .frame(background)
+            .show(ctx, |ui| {
+                let screen = self.window.game_window.view_fitted(ui);
+                if let (Some(screen), Some(ram)) = (screen, &self.ram) {
+                    self.hud.paint(ui, screen, &ram::lock(ram));
+                }
+            });
+    }
+    /// Everything which runs every frame, after the windows were drawn
+    fn end_frame(&mut self, ctx: &egui::Context, background: bool) {
+        // not while a color or slider is dragged
+        if !ctx.input().pointer.any_down() {
+            let settings = self.settings();
+            self.settings_file.save_if_changed(&settings);
+        }
+        self.schedule_repaint(ctx, background);
+        if let Some(report) = self
+            .crash_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        {
+            // the report would not be seen without the windows
+            self.play_mode = false;
+            self.crash = Some(CrashDialog::new(report));
+        }
+    }
+    /// Pauses or throttles the emulation while the window is not focused
+    fn handle_focus(&mut self, ctx: &egui::Context) {
+        let focused = ctx.input().raw.has_focus;
+        match (focused, self.unfocused_from) {
+            (false, None) => {
+                self.unfocused_from = Some(self.speed.get());
+                match self.focus_loss {
+                    FocusLoss::KeepRunning => self.unfocused_from = None,
+                    FocusLoss::Pause => self.speed.set_paused(true),
+                    FocusLoss::Throttle => self.speed.set(Speed::MIN),
+                }
+            }
+            (true, Some(percent)) => {
+                self.speed.set_paused(false);
+                self.speed.set(percent);
+                self.unfocused_from = None;
+            }
+            _ => {}
+        }
+    }
+    /// Schedules the next frame, egui only repaints on input otherwise
+    fn schedule_repaint(&self, ctx: &egui::Context, background: bool) {
+        if !self.status.rom_loaded() {
+            // idle, the gui only reacts to input until a game runs
+            return;
+        }
+        if background && self.audio_only_in_background {
+            ctx.request_repaint_after(BACKGROUND_FRAME_TIME);
+        } else if self.low_power {
+            ctx.request_repaint_after(FRAME_TIME);
+        } else {
+            ctx.request_repaint();
+        }
+    }
+    /// The type byte from the header of the inserted cartridge
+    fn cartridge_type(&self) -> Option<u8> {
+        let ram = ram::lock(self.ram.as_ref()?);
+        Some(ram[CARTRIDGE_TYPE as u16])
+    }
+    fn copy_registers(&self, ctx: &egui::Context) {
+        let ram = self.ram.as_ref().map(|ram| ram::lock(ram));
+        clipboard::copy_registers(
+            ctx,
+            &self.status,
+            self.window.cpu_mode.as_ref(),
+            ram.as_deref(),
+        );
+    }
+    /// Explains why the cpu does not execute instructions anymore
+    fn cpu_status(&self) -> Option<String> {
+        let (mode, pc) = self.window.cpu_mode.as_ref()?;
+        let pc = format!("{pc:#06x}");
+        Some(match mode {
+            CpuMode::Halt => self.i18n.tr_args("cpu-halted", &[("pc", &pc)]),
+            CpuMode::Stop => self.i18n.tr_args("cpu-stopped", &[("pc", &pc)]),
+            CpuMode::Locked { op } => self
+                .i18n
+                .tr_args("cpu-locked", &[("pc", &pc), ("op", &format!("{op:#04x}"))]),
+            _ => return None,
+        })
+    }
+    /// The window title with the game and the emulation state,
+    /// e.g. `Gameboy Emulator — TETRIS — paused`
+    /// The title in the header of the inserted cartridge
+    fn game_title(&self) -> Option<String> {
+        self.ram
+            .as_ref()
+            .and_then(|ram| ram::lock(ram).cartridge_title())
+            .filter(|_| self.status.rom_loaded())
+    }
+    /// `<game> — <emulator>` followed by the speed, the game goes first so it stays visible
+    /// in a narrow task bar
+    fn window_title(&self) -> String {
+        let mut title = match self.game_title() {
+            Some(game) => format!("{game} — {APP_NAME}"),
+            None => APP_NAME.to_string(),
+        };
+        if self.power.is_off() {
+            title.push_str(" — off");
+        } else if self.speed.is_paused() {
+            title.push_str(" — paused");
+        } else if self.speed.get() != 100 {
+            title.push_str(&format!(" — {}%", self.speed.get()));
+        }
+        title
+    }
+    /// Pause, reset and the power switch above everything else
+    fn toolbar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let last_dir = self.last_rom_dir.clone();
+                let new_window = self.launcher.is_some();
+                match self
+                    .recent_roms
+                    .menu_ui(ui, &self.i18n, last_dir.as_deref(), new_window)
+                {
+                    Some(RomChoice::Load(path)) => self.load_rom(path),
+                    Some(RomChoice::NewWindow(path)) => self.open_other(ctx, path),
+                    None => {}
+                }
+                let paused = self.speed.is_paused();
+                let pause = if paused {
+                    self.i18n.tr("toolbar-resume")
+                } else {
+                    self.i18n.tr("toolbar-pause")
+                };
+                if ui.button(pause).clicked() {
+                    self.speed.set_paused(!paused);
+                }
+                if ui.button(self.i18n.tr("toolbar-reset")).clicked() {
+                    self.power.request(PowerRequest::Reset);
+                }
+                let (power, request) = if self.power.is_off() {
+                    (self.i18n.tr("toolbar-power-on"), PowerRequest::On)
+                } else {
+                    (self.i18n.tr("toolbar-power-off"), PowerRequest::Off)
+                };
+                if ui.button(power).clicked() {
+                    self.power.request(request);
+                }
+            });
+        });
+    }
     pub fn run(self) {
         let options = eframe::NativeOptions {
-            initial_window_size: Some(egui::vec2(WINDOW_WIDTH, WINDOW_HEIGHT)),
+            // large scales need a larger window for the screen
+            initial_window_size: Some(egui::vec2(
+                WINDOW_WIDTH.max((GAME_SCREEN_WIDTH * self.scale) as f32),
+                WINDOW_HEIGHT.max((GAME_SCREEN_HEIGHT * self.scale) as f32),
+            )),
             ..Default::default()
         };
         eframe::run_native(
-            "Gameboy Emulator",
+            APP_NAME,
             options,
             Box::new(|cc| Box::new(self.init_window(cc))),
         )
     }
-    
 }
 
 struct Window {
     game_window: GameWindow,
+    /// Set when the cpu halted, stopped or locked up
+    cpu_mode: Option<(CpuMode, u16)>,
 }
 impl Window {
     pub fn init(&mut self, ctx: &egui::Context) {
         self.game_window.init_texture(ctx);
     }
-    pub fn view(&mut self, ui: &mut egui::Ui) {
+    pub fn view(&mut self, ui: &mut egui::Ui) -> Option<egui::Rect> {
         self.game_window.view(ui)
     }
-    pub fn process_draw_signal(&mut self, draw_signal: DrawSignal) {
+    /// Returns whether the signal was a new frame
+    pub fn process_draw_signal(&mut self, draw_signal: DrawSignal) -> bool {
         match draw_signal {
-            DrawSignal::DrawPixel(x, y, color) => {
-                self.game_window.draw_pixel(x, y, color);
+            DrawSignal::Frame(frame) => self.game_window.set_frame(frame),
+            DrawSignal::ColorFrame(frame) => self.game_window.set_color_frame(frame),
+            DrawSignal::CpuMode(mode, pc) => {
+                self.cpu_mode = Some((mode, pc));
+                return false;
             }
         }
+        true
     }
 }
 impl Default for Window {
     fn default() -> Self {
         Self {
             game_window: GameWindow::default(),
+            cpu_mode: None,
         }
     }
 }
 
 impl eframe::App for Gpu {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.accessibility.apply(ctx);
+        let gamepad = self.gamepads.update();
+        match self.others.iter().find(|other| other.is_controlled()) {
+            Some(other) => {
+                self.input.set(Buttons::default());
+                self.keyboard.update(ctx, other.input(), gamepad);
+            }
+            _ => self.keyboard.update(ctx, &self.input, gamepad),
+        }
+        self.speedrun.input(self.input.get());
+        for _ in 0..self.status.take_splits() {
+            self.speedrun.start_or_split();
+        }
         let iter = self.signal_receiver.try_iter();
         for signal in iter {
-            self.window.process_draw_signal(signal.clone());
+            if self.window.process_draw_signal(signal) {
+                self.capture.frame(&self.window.game_window);
+            }
+        }
+        self.timeline
+            .update(ctx, &self.rewind, &self.window.game_window);
+        if let Some(slot) = self.slots.take_saved() {
+            let crc = self.status.rom_crc();
+            self.timeline
+                .save_thumbnail(self.slots.slots(), crc, slot, &self.window.game_window);
+        }
+        let border_version = self.sgb_border.version();
+        if border_version != self.border_version {
+            self.border_version = border_version;
+            self.window.game_window.set_border(self.sgb_border.image());
+        }
+        let background = !ctx.input().raw.has_focus;
+        if !(background && self.audio_only_in_background) {
+            self.window.game_window.update_texture(ctx);
+            for other in &mut self.others {
+                other.update(ctx, self.window.game_window.color_palette);
+            }
         }
-        self.window.game_window.update_texture(ctx);
         let size = vec2(
-            GAME_SCREEN_WIDTH as f32 * GAME_SCREEN_SCALE as f32,
-            GAME_SCREEN_HEIGHT as f32 * GAME_SCREEN_SCALE as f32,
+            (GAME_SCREEN_WIDTH * self.scale) as f32,
+            (GAME_SCREEN_HEIGHT * self.scale) as f32,
         );
+        let title = self
+            .ram
+            .as_ref()
+            .and_then(|ram| ram::lock(ram).cartridge_title())
+            .unwrap_or_default();
+        let running = self.status.rom_loaded() && !self.speed.is_paused();
+        self.playtime.update(self.status.rom_crc(), &title, running);
+        self.palettes
+            .update(&title, &mut self.window.game_window.color_palette);
+        self.meters.update();
+        self.handle_shortcuts(ctx, frame);
+        self.handle_dropped_files(ctx);
+        self.perf_overlay.show(ctx, &self.i18n, &self.status);
+        self.input_display
+            .show(ctx, &self.i18n, &self.status, &self.speed);
+        if self.play_mode {
+            self.play_mode_ui(ctx);
+            self.end_frame(ctx, background);
+            return;
+        }
+        self.toolbar(ctx);
+        status_bar::show(
+            ctx,
+            &self.i18n,
+            &self.speed,
+            &self.status,
+            self.ram.as_ref(),
+            self.playtime.total(self.status.rom_crc()),
+            &self.meters,
+        );
+        if self.status.rom_loaded() {
+            egui::SidePanel::right("debugger").show(ctx, |ui| {
+                ui.heading(self.i18n.tr("window-debugger"));
+                let trace_file = self.dirs.logs.join("trace.txt");
+                trace_ui(ui, &self.i18n, &self.trace, &trace_file);
+                layers_ui(ui, &self.i18n, &self.layers);
+                if let Some(ram) = &self.ram {
+                    egui::CollapsingHeader::new(self.i18n.tr("registers-heading"))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            self.registers.ui(
+                                ui,
+                                &self.i18n,
+                                &self.status,
+                                &ram::lock(ram),
+                                self.speed.is_paused(),
+                            );
+                        });
+                }
+                self.debugger.ui(
+                    ui,
+                    &self.i18n,
+                    &self.speed,
+                    &self.status,
+                    self.ram.as_ref(),
+                    &self.symbols,
+                );
+            });
+        }
+        let cpu_status = self.cpu_status();
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("This is the main window");
-            egui::Window::new("Emulator")
+            ui.heading(self.i18n.tr("app-main-heading"));
+            egui::Window::new(self.i18n.tr("window-emulator"))
+                .id(egui::Id::new("emulator"))
                 .default_size(size)
                 .vscroll(false)
                 .show(ctx, |ui| {
-                    self.window.view(ui);
+                    let screen = self.window.view(ui);
+                    if let (Some(screen), Some(ram)) = (screen, &self.ram) {
+                        self.hud.paint(ui, screen, &ram::lock(ram));
+                    }
+                    if let Some(status) = &cpu_status {
+                        ui.colored_label(Color32::YELLOW, status);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button(self.i18n.tr("action-copy-screen")).clicked() {
+                            self.clipboard.copy_screen(&self.window.game_window);
+                        }
+                        if ui.button(self.i18n.tr("action-copy-registers")).clicked() {
+                            self.copy_registers(ctx);
+                        }
+                        let scaling = &mut self.window.game_window.scaling;
+                        egui::ComboBox::from_id_source("scaling")
+                            .selected_text(self.i18n.tr(scaling.name()))
+                            .show_ui(ui, |ui| {
+                                for mode in Scaling::ALL {
+                                    ui.selectable_value(scaling, mode, self.i18n.tr(mode.name()));
+                                }
+                            });
+                        ui.checkbox(
+                            &mut self.window.game_window.integer_scale,
+                            self.i18n.tr("integer-scale"),
+                        );
+                        if self.window.game_window.has_border() {
+                            ui.checkbox(
+                                &mut self.window.game_window.show_border,
+                                self.i18n.tr("sgb-border"),
+                            );
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let key = format!("{:?}", self.shortcuts.key(Action::PlayMode));
+                        if ui
+                            .button(self.i18n.tr("action-play-mode"))
+                            .on_hover_text(self.i18n.tr_args("play-mode-leave", &[("key", &key)]))
+                            .clicked()
+                        {
+                            self.play_mode = true;
+                        }
+                        if ui
+                            .checkbox(&mut self.fullscreen, self.i18n.tr("action-fullscreen"))
+                            .changed()
+                        {
+                            frame.set_fullscreen(self.fullscreen);
+                        }
+                    });
                 });
         });
-        egui::Window::new("Colors")
+        self.others_ui(ctx, size);
+        if let Some(status) = &cpu_status {
+            egui::Area::new("cpu_status")
+                .anchor(Align2::LEFT_TOP, vec2(10., 10.))
+                .show(ctx, |ui| {
+                    ui.label(RichText::new(status).size(18.).color(Color32::YELLOW));
+                });
+        }
+        egui::Window::new(self.i18n.tr("window-capture"))
+            .id(egui::Id::new("capture"))
+            .show(ctx, |ui| {
+                self.capture.ui(ui, &self.i18n, &self.window.game_window)
+            });
+        egui::Window::new(self.i18n.tr("window-colors"))
+            .id(egui::Id::new("colors"))
             .default_size(size)
             .vscroll(false)
             .show(ctx, |ui| {
-                ui.color_edit_button_srgb(&mut self.window.game_window.color_palette[0]);
-                ui.color_edit_button_srgb(&mut self.window.game_window.color_palette[1]);
-                ui.color_edit_button_srgb(&mut self.window.game_window.color_palette[2]);
-                ui.color_edit_button_srgb(&mut self.window.game_window.color_palette[3]);
-                //self.window.view(ui);
+                self.palettes
+                    .ui(ui, &self.i18n, &mut self.window.game_window.color_palette);
+            });
+        egui::Window::new(self.i18n.tr("window-accessibility"))
+            .id(egui::Id::new("accessibility"))
+            .show(ctx, |ui| {
+                self.i18n.language_ui(ui);
+                self.accessibility.settings_ui(
+                    ui,
+                    &self.i18n,
+                    &mut self.window.game_window.color_palette,
+                );
+            });
+        egui::Window::new(self.i18n.tr("window-mixer"))
+            .id(egui::Id::new("mixer"))
+            .show(ctx, |ui| self.meters.mixer_ui(ui, &self.i18n));
+        egui::Window::new(self.i18n.tr("window-shortcuts"))
+            .id(egui::Id::new("shortcuts"))
+            .show(ctx, |ui| {
+                self.shortcuts
+                    .settings_ui(ui, &self.i18n, &self.keyboard.keys());
+            });
+        egui::Window::new(self.i18n.tr("window-settings"))
+            .id(egui::Id::new("settings"))
+            .show(ctx, |ui| {
+                self.keyboard.buttons_ui(ui, &self.i18n);
+                ui.separator();
+                let mut volume = self.meters.channels().volume();
+                let slider = egui::Slider::new(&mut volume, 0..=100)
+                    .suffix("%")
+                    .text(self.i18n.tr("settings-volume"));
+                if ui.add(slider).changed() {
+                    self.meters.channels().set_volume(volume);
+                }
+                ui.add(
+                    egui::Slider::new(&mut self.scale, 1..=8).text(self.i18n.tr("settings-scale")),
+                );
+                ui.label(self.i18n.tr_args(
+                    "settings-file",
+                    &[("path", &self.settings_file.path().display().to_string())],
+                ));
+            });
+        egui::Window::new(self.i18n.tr("window-macros"))
+            .id(egui::Id::new("macros"))
+            .show(ctx, |ui| self.keyboard.ui(ui, &self.i18n));
+        egui::Window::new(self.i18n.tr("window-gamepads"))
+            .id(egui::Id::new("gamepads"))
+            .show(ctx, |ui| self.gamepads.ui(ui, &self.i18n));
+        egui::Window::new(self.i18n.tr("window-speed"))
+            .id(egui::Id::new("speed"))
+            .show(ctx, |ui| {
+                let mut percent = self.speed.get();
+                let slider = egui::Slider::new(&mut percent, Speed::MIN..=Speed::MAX)
+                    .step_by(Speed::STEP as f64)
+                    .suffix("%");
+                if ui.add(slider).changed() {
+                    self.speed.set(percent);
+                }
+                let fps = format!("{:.1}", self.status.fps());
+                ui.label(self.i18n.tr_args("speed-fps", &[("fps", &fps)]));
+                ui.label(self.i18n.tr_args(
+                    "speed-hotkeys",
+                    &[
+                        (
+                            "faster",
+                            &format!("{:?}", self.shortcuts.key(Action::SpeedUp)),
+                        ),
+                        (
+                            "slower",
+                            &format!("{:?}", self.shortcuts.key(Action::SpeedDown)),
+                        ),
+                    ],
+                ));
+                ui.horizontal(|ui| {
+                    ui.label(self.i18n.tr("speed-slow-motion"));
+                    for percent in [25, 50, 100] {
+                        if ui.button(format!("{percent}%")).clicked() {
+                            self.speed.set(percent);
+                        }
+                    }
+                });
+                ui.separator();
+                ui.checkbox(&mut self.turbo, self.i18n.tr("turbo"));
+                let mut rate = self.speed.turbo_rate();
+                ui.horizontal(|ui| {
+                    for option in Turbo::ALL {
+                        if ui
+                            .radio_value(&mut rate, option, self.i18n.tr(option.name()))
+                            .changed()
+                        {
+                            self.speed.set_turbo_rate(rate);
+                        }
+                    }
+                });
+                ui.label(self.i18n.tr_args(
+                    "turbo-hold",
+                    &[(
+                        "key",
+                        &format!("{:?}", self.shortcuts.key(Action::FastForward)),
+                    )],
+                ));
+                ui.separator();
+                let mut rewind = self.rewind.enabled();
+                if ui.checkbox(&mut rewind, self.i18n.tr("rewind")).changed() {
+                    self.rewind.set_enabled(rewind);
+                }
+                if rewind {
+                    let seconds = format!("{:.1}", self.rewind.seconds());
+                    let kib = (self.rewind.memory_usage() / 1024).to_string();
+                    ui.label(
+                        self.i18n
+                            .tr_args("rewind-history", &[("seconds", &seconds), ("kib", &kib)]),
+                    );
+                    ui.label(self.i18n.tr_args(
+                        "rewind-hold",
+                        &[("key", &format!("{:?}", self.shortcuts.key(Action::Rewind)))],
+                    ));
+                }
+                ui.separator();
+                ui.label(self.i18n.tr("focus-loss"));
+                ui.radio_value(
+                    &mut self.focus_loss,
+                    FocusLoss::KeepRunning,
+                    self.i18n.tr("focus-keep-running"),
+                );
+                ui.radio_value(
+                    &mut self.focus_loss,
+                    FocusLoss::Pause,
+                    self.i18n.tr("focus-pause"),
+                );
+                ui.radio_value(
+                    &mut self.focus_loss,
+                    FocusLoss::Throttle,
+                    self.i18n.tr("focus-throttle"),
+                );
+                ui.separator();
+                ui.label(self.i18n.tr("desync"));
+                let mut desync = self.speed.desync();
+                for (policy, key) in [
+                    (Desync::Resync, "desync-resync"),
+                    (Desync::DropFrames, "desync-drop-frames"),
+                    (Desync::StretchAudio, "desync-stretch-audio"),
+                ] {
+                    if ui
+                        .radio_value(&mut desync, policy, self.i18n.tr(key))
+                        .changed()
+                    {
+                        self.speed.set_desync(desync);
+                    }
+                }
+                ui.separator();
+                ui.label(self.i18n.tr("refresh"));
+                let mut refresh = self.speed.refresh();
+                for (rate, key) in [
+                    (Refresh::Native, "refresh-native"),
+                    (Refresh::Host60, "refresh-host-60"),
+                    (Refresh::Audio, "refresh-audio"),
+                ] {
+                    if ui
+                        .radio_value(&mut refresh, rate, self.i18n.tr(key))
+                        .changed()
+                    {
+                        self.speed.set_refresh(refresh);
+                    }
+                }
+                ui.separator();
+                let mut run_ahead = self.speed.run_ahead();
+                if ui
+                    .add(
+                        egui::Slider::new(&mut run_ahead, 0..=Speed::MAX_RUN_AHEAD)
+                            .text(self.i18n.tr("run-ahead")),
+                    )
+                    .on_hover_text(self.i18n.tr("run-ahead-hint"))
+                    .changed()
+                {
+                    self.speed.set_run_ahead(run_ahead);
+                }
+                ui.separator();
+                ui.checkbox(&mut self.low_power, self.i18n.tr("power-low-power"));
+                ui.checkbox(
+                    &mut self.audio_only_in_background,
+                    self.i18n.tr("power-audio-only"),
+                );
+            });
+        self.handle_focus(ctx);
+        let title = self.window_title();
+        if title != self.title {
+            frame.set_window_title(&title);
+            self.title = title;
+        }
+        egui::Window::new(self.i18n.tr("window-speedrun"))
+            .id(egui::Id::new("speedrun"))
+            .show(ctx, |ui| {
+                ui.checkbox(&mut self.speedrun.visible, self.i18n.tr("speedrun-show"));
+                ui.checkbox(
+                    &mut self.speedrun.auto_start,
+                    self.i18n.tr("speedrun-auto-start"),
+                );
+                ui.label(self.i18n.tr_args(
+                    "speedrun-hotkeys",
+                    &[
+                        (
+                            "split",
+                            &format!("{:?}", self.shortcuts.key(Action::SpeedrunSplit)),
+                        ),
+                        (
+                            "reset",
+                            &format!("{:?}", self.shortcuts.key(Action::SpeedrunReset)),
+                        ),
+                    ],
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button(self.i18n.tr("speedrun-finish")).clicked() {
+                        self.speedrun.finish();
+                    }
+                    if ui.button(self.i18n.tr("speedrun-export")).clicked() {
+                        let game = self.game_title().unwrap_or_default();
+                        // the title can be anything the cartridge holds
+                        let mut name: String = game
+                            .chars()
+                            .filter(|c| c.is_ascii_alphanumeric() || " -_".contains(*c))
+                            .collect();
+                        if name.trim().is_empty() {
+                            name = "splits".to_string();
+                        }
+                        let path = self.dirs.splits.join(format!("{name}.lss"));
+                        match self.speedrun.export_livesplit(&path, &game) {
+                            Ok(()) => log::info!("exported the splits to {}", path.display()),
+                            Err(e) => {
+                                log::warn!("could not export splits to {}: {e}", path.display())
+                            }
+                        }
+                    }
+                });
+            });
+        egui::Window::new(self.i18n.tr("window-memory"))
+            .id(egui::Id::new("memory"))
+            .show(ctx, |ui| {
+                self.memory.ui(
+                    ui,
+                    &self.i18n,
+                    self.speed.is_paused(),
+                    self.status.rom_bank(),
+                    &self.window.game_window.color_palette,
+                );
             });
+        if let Some(ram) = &self.ram {
+            egui::Window::new(self.i18n.tr("window-memory-view"))
+                .id(egui::Id::new("memory_view"))
+                .show(ctx, |ui| {
+                    self.hex_view
+                        .ui(ui, &self.i18n, ram, self.speed.is_paused());
+                });
+        }
+        if let Some(ram) = &self.ram {
+            egui::Window::new(self.i18n.tr("window-io"))
+                .id(egui::Id::new("io"))
+                .show(ctx, |ui| {
+                    self.io_view
+                        .ui(ui, &self.i18n, &ram::lock(ram), self.speed.is_paused());
+                });
+        }
+        if let Some(ram) = &self.ram {
+            egui::Window::new(self.i18n.tr("window-vram"))
+                .id(egui::Id::new("vram"))
+                .show(ctx, |ui| {
+                    self.vram_viewer.ui(
+                        ui,
+                        &self.i18n,
+                        &ram::lock(ram),
+                        &self.window.game_window.color_palette,
+                    );
+                });
+        }
+        if let Some(ram) = &self.ram {
+            egui::Window::new(self.i18n.tr("window-oam"))
+                .id(egui::Id::new("oam"))
+                .show(ctx, |ui| {
+                    self.oam_viewer.ui(
+                        ui,
+                        &self.i18n,
+                        &ram::lock(ram),
+                        &self.window.game_window.color_palette,
+                    );
+                });
+        }
+        if let Some(ram) = &self.ram {
+            egui::Window::new(self.i18n.tr("window-memory-diff"))
+                .id(egui::Id::new("memory_diff"))
+                .show(ctx, |ui| {
+                    self.memory_diff
+                        .ui(ui, &self.i18n, ram, &self.speed, &self.status);
+                });
+        }
+        if let Some(ram) = &self.ram {
+            egui::Window::new(self.i18n.tr("window-hud"))
+                .id(egui::Id::new("hud"))
+                .show(ctx, |ui| {
+                    self.hud.ui(ui, &self.i18n, &ram::lock(ram));
+                    ui.label(self.i18n.tr_args(
+                        "hud-hotkey",
+                        &[(
+                            "key",
+                            &format!("{:?}", self.shortcuts.key(Action::ToggleHud)),
+                        )],
+                    ));
+                });
+        }
+        let cartridge_type = self.cartridge_type();
+        if cartridge_type == Some(POCKET_CAMERA) {
+            egui::Window::new(self.i18n.tr("window-camera"))
+                .id(egui::Id::new("camera"))
+                .show(ctx, |ui| self.camera.ui(ui, &self.i18n));
+        }
+        if let Some(rtc) = self
+            .rtc
+            .as_mut()
+            .filter(|_| cartridge_type.is_some_and(|t| MBC3_TIMER.contains(&t)))
+        {
+            egui::Window::new(self.i18n.tr("window-rtc"))
+                .id(egui::Id::new("rtc"))
+                .show(ctx, |ui| rtc.ui(ui, &self.i18n));
+        }
+        egui::Window::new(self.i18n.tr("window-link-port"))
+            .id(egui::Id::new("link_port"))
+            .show(ctx, |ui| {
+                let game = self.game_title();
+                self.link_port
+                    .ui(ui, &self.i18n, &self.dirs, game.as_deref())
+            });
+        egui::Window::new(self.i18n.tr("window-serial"))
+            .id(egui::Id::new("serial_output"))
+            .show(ctx, |ui| self.serial_output.ui(ui, &self.i18n));
+        egui::Window::new(self.i18n.tr("window-playtime"))
+            .id(egui::Id::new("playtime"))
+            .show(ctx, |ui| {
+                let export = self.dirs.config.join("playtime.csv");
+                self.playtime.ui(ui, &self.i18n, &export);
+            });
+        egui::Window::new(self.i18n.tr("window-practice"))
+            .id(egui::Id::new("practice"))
+            .show(ctx, |ui| {
+                let key = format!("{:?}", self.shortcuts.key(Action::PracticeRetry));
+                self.practice.ui(ui, &self.i18n, &key);
+            });
+        if self.status.rom_loaded() {
+            self.slots.resume_ui(ctx, &self.i18n, self.status.rom_crc());
+            egui::Window::new(self.i18n.tr("window-save-states"))
+                .id(egui::Id::new("save_states"))
+                .show(ctx, |ui| {
+                    self.slots.ui(ui, &self.i18n, self.status.rom_crc());
+                });
+            egui::Window::new(self.i18n.tr("window-timeline"))
+                .id(egui::Id::new("timeline"))
+                .show(ctx, |ui| {
+                    self.timeline.ui(
+                        ui,
+                        &self.i18n,
+                        &self.rewind,
+                        self.slots.slots(),
+                        self.status.rom_crc(),
+                    );
+                });
+            egui::Window::new(self.i18n.tr("window-movie"))
+                .id(egui::Id::new("movie"))
+                .show(ctx, |ui| {
+                    self.movie.ui(ui, &self.i18n, self.status.rom_crc());
+                });
+            self.cheats.update(self.status.rom_crc());
+            egui::Window::new(self.i18n.tr("window-cheats"))
+                .id(egui::Id::new("cheats"))
+                .show(ctx, |ui| self.cheats.ui(ui, &self.i18n));
+        }
+        self.console.update();
+        egui::Window::new(self.i18n.tr("window-console"))
+            .id(egui::Id::new("console"))
+            .show(ctx, |ui| {
+                self.console.ui(
+                    ui,
+                    &self.i18n,
+                    &self.speed,
+                    &self.status,
+                    self.ram.as_ref(),
+                    &self.symbols,
+                );
+            });
+        egui::Window::new(self.i18n.tr("window-logging"))
+            .id(egui::Id::new("logging"))
+            .show(ctx, |ui| log_settings::ui(ui, &self.i18n));
+        self.update.update();
+        egui::Window::new(self.i18n.tr("window-updates"))
+            .id(egui::Id::new("updates"))
+            .show(ctx, |ui| self.update.ui(ui, &self.i18n));
+        self.update.show(ctx, &self.i18n);
+        self.speedrun.show(ctx);
+        self.end_frame(ctx, background);
+        if let Some(crash) = &mut self.crash {
+            crash.show(ctx, &self.i18n);
+        }
+    }
+    /// The other games write their saves before the window goes away
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        for other in self.others.drain(..) {
+            other.stop();
+        }
     }
-}
-#[derive(Debug, Clone)]
-pub enum DrawSignal {
-    DrawPixel(usize, usize, usize),
 }
@@ -0,0 +1,91 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::i18n::I18n;
+use eframe::{egui, epaint::Color32};
+use gba::movie::{MovieMode, MovieRequest, Movies, EXTENSION};
+
+/// Recording the buttons of the running game and playing them back
+pub struct MoviePanel {
+    movies: Movies,
+    /// Where the movies are kept
+    dir: PathBuf,
+}
+impl MoviePanel {
+    pub fn new(movies: Movies, dir: PathBuf) -> Self {
+        MoviePanel { movies, dir }
+    }
+    /// The movies of the game, the game is identified by the CRC32 of its rom
+    fn recorded(&self, crc: u32) -> Vec<PathBuf> {
+        let prefix = format!("{crc:08x}-");
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut movies: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+            .filter(|path| file_name(path).starts_with(&prefix))
+            .collect();
+        movies.sort();
+        movies
+    }
+    /// The next free `crc-N.gbm`
+    fn new_path(&self, crc: u32) -> PathBuf {
+        (1..)
+            .map(|n| self.dir.join(format!("{crc:08x}-{n}.{EXTENSION}")))
+            .find(|path| !path.exists())
+            .unwrap()
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, crc: u32) {
+        let mode = self.movies.mode();
+        ui.horizontal(|ui| {
+            match mode {
+                MovieMode::Off => ui.label(i18n.tr("movie-off")),
+                MovieMode::Recording { frames } => {
+                    ui.label(i18n.tr_args("movie-recording", &[("frames", &frames.to_string())]))
+                }
+                MovieMode::Playing { frame, frames } => ui.label(i18n.tr_args(
+                    "movie-playing",
+                    &[
+                        ("frame", &frame.to_string()),
+                        ("frames", &frames.to_string()),
+                    ],
+                )),
+            };
+            if mode == MovieMode::Off {
+                if ui.button(i18n.tr("movie-record")).clicked() {
+                    self.movies
+                        .request(MovieRequest::Record(self.new_path(crc)));
+                }
+            } else if ui.button(i18n.tr("movie-stop")).clicked() {
+                self.movies.request(MovieRequest::Stop);
+            }
+        });
+        ui.separator();
+        let recorded = self.recorded(crc);
+        if recorded.is_empty() {
+            ui.label(i18n.tr("movie-none"));
+        }
+        egui::Grid::new("movies").show(ui, |ui| {
+            for path in recorded {
+                ui.label(file_name(&path));
+                let play = egui::Button::new(i18n.tr("movie-play"));
+                if ui.add_enabled(mode == MovieMode::Off, play).clicked() {
+                    self.movies.request(MovieRequest::Play(path));
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(e) = self.movies.error() {
+            ui.colored_label(Color32::RED, i18n.tr_args("movie-failed", &[("error", &e)]));
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,124 @@
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, RichText, TextureFilter, TextureHandle},
+    epaint::{Color32, ColorImage},
+};
+use gba::{
+    ppu::{Sprite, LY, SPRITES, SPRITES_PER_LINE},
+    ram::Ram,
+    vram::LCDC,
+};
+
+/// Pixels on screen per pixel of a sprite
+const THUMBNAIL_SCALE: f32 = 3.;
+/// Sprites on the line which the ppu draws
+const ON_LINE: Color32 = Color32::YELLOW;
+/// Sprites on the line after the first 10, which the ppu leaves out
+const DROPPED: Color32 = Color32::RED;
+
+/// All 40 OAM entries with a picture of each, the ones on the current line are highlighted
+#[derive(Default)]
+pub struct OamViewer {
+    thumbnails: Vec<TextureHandle>,
+}
+impl OamViewer {
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, ram: &Ram, palette: &[[u8; 3]; 4]) {
+        let (lcdc, ly) = (ram[LCDC], ram[LY]);
+        let sprites: Vec<Sprite> = (0..SPRITES).map(|index| Sprite::read(ram, index)).collect();
+        let on_line = sprites
+            .iter()
+            .filter(|sprite| sprite.covers_line(lcdc, ly))
+            .count();
+        ui.label(i18n.tr_args(
+            "oam-on-line",
+            &[("line", &ly.to_string()), ("count", &on_line.to_string())],
+        ));
+        let mut seen = 0;
+        egui::ScrollArea::vertical()
+            .id_source("oam_viewer")
+            .max_height(400.)
+            .show(ui, |ui| {
+                egui::Grid::new("oam").striped(true).show(ui, |ui| {
+                    for key in ["oam-index", "oam-y", "oam-x", "oam-tile", "oam-flags"] {
+                        ui.strong(i18n.tr(key));
+                    }
+                    ui.label("");
+                    ui.end_row();
+                    for (index, sprite) in sprites.iter().enumerate() {
+                        let mut number = RichText::new(index.to_string()).monospace();
+                        if sprite.covers_line(lcdc, ly) {
+                            seen += 1;
+                            number = number.color(if seen > SPRITES_PER_LINE {
+                                DROPPED
+                            } else {
+                                ON_LINE
+                            });
+                        }
+                        ui.label(number);
+                        ui.monospace(format!("{:02X}", sprite.y));
+                        ui.monospace(format!("{:02X}", sprite.x));
+                        ui.monospace(format!("{:02X}", sprite.tile));
+                        ui.monospace(format!("{:02X}", sprite.flags))
+                            .on_hover_text(flags(i18n, *sprite));
+                        let texture = self.thumbnail(ui, index, *sprite, ram, palette);
+                        let size = texture.size_vec2() * THUMBNAIL_SCALE;
+                        ui.image(texture.id(), size);
+                        ui.end_row();
+                    }
+                });
+            });
+        ui.horizontal(|ui| {
+            ui.colored_label(ON_LINE, i18n.tr("oam-legend-on-line"));
+            ui.colored_label(DROPPED, i18n.tr("oam-legend-dropped"));
+        });
+    }
+    /// The picture of sprite `index` through its palette, the texture is kept between frames
+    fn thumbnail(
+        &mut self,
+        ui: &egui::Ui,
+        index: usize,
+        sprite: Sprite,
+        ram: &Ram,
+        palette: &[[u8; 3]; 4],
+    ) -> &TextureHandle {
+        let indices = sprite.image(ram, None);
+        let obp = ram[sprite.palette()];
+        let rgb: Vec<u8> = indices
+            .pixels
+            .iter()
+            .flat_map(|index| palette[(obp >> (index * 2)) as usize & 0b11])
+            .collect();
+        let image = ColorImage::from_rgb([indices.width, indices.height], &rgb);
+        if let Some(texture) = self.thumbnails.get_mut(index) {
+            texture.set(image, TextureFilter::Nearest);
+        } else {
+            let name = format!("oam_{index}");
+            let texture = ui.ctx().load_texture(name, image, TextureFilter::Nearest);
+            self.thumbnails.push(texture);
+        }
+        &self.thumbnails[index]
+    }
+}
+/// The flags spelled out for the tooltip
+fn flags(i18n: &I18n, sprite: Sprite) -> String {
+    let mut lines = Vec::new();
+    if sprite.behind_background() {
+        lines.push(i18n.tr("oam-behind-background").to_string());
+    }
+    if sprite.flip_x() {
+        lines.push(i18n.tr("oam-flip-x").to_string());
+    }
+    if sprite.flip_y() {
+        lines.push(i18n.tr("oam-flip-y").to_string());
+    }
+    let obp = if sprite.flags & 0x10 != 0 { "1" } else { "0" };
+    lines.push(i18n.tr_args("oam-palette", &[("obp", obp)]));
+    lines.push(i18n.tr_args(
+        "oam-cgb",
+        &[
+            ("bank", &(sprite.flags >> 3 & 1).to_string()),
+            ("palette", &(sprite.flags & 0x07).to_string()),
+        ],
+    ));
+    lines.join("\n")
+}
@@ -0,0 +1,117 @@
+use super::{accessibility::PALETTE_PRESETS, i18n::I18n};
+use eframe::egui;
+
+/// The look of the Game Boy models, lightest color first. The names are i18n keys.
+pub const PRESETS: [(&str, [[u8; 3]; 4]); 3] = [
+    (
+        "palette-dmg-green",
+        [
+            [0x9b, 0xbc, 0x0f],
+            [0x8b, 0xac, 0x0f],
+            [0x30, 0x62, 0x30],
+            [0x0f, 0x38, 0x0f],
+        ],
+    ),
+    (
+        "palette-pocket",
+        [
+            [0xc4, 0xcf, 0xa1],
+            [0x8b, 0x95, 0x6d],
+            [0x4d, 0x53, 0x3c],
+            [0x1f, 0x1f, 0x1f],
+        ],
+    ),
+    (
+        "palette-light",
+        [
+            [0x00, 0xb5, 0x81],
+            [0x00, 0x9a, 0x71],
+            [0x00, 0x69, 0x4a],
+            [0x00, 0x4f, 0x3b],
+        ],
+    ),
+];
+
+/// Palettes saved by the user and the palette chosen for each game
+#[derive(Default)]
+pub struct Palettes {
+    /// By name
+    pub user: Vec<(String, [[u8; 3]; 4])>,
+    /// By cartridge title
+    pub games: Vec<(String, [[u8; 3]; 4])>,
+    /// Title of the running game, `None` before the first frame
+    game: Option<String>,
+    /// The palette when it was last remembered for the game
+    seen: [[u8; 3]; 4],
+    /// Of the palette saved next
+    name: String,
+}
+impl Palettes {
+    /// Switches to the palette of the game with `title` when it starts, afterwards
+    /// remembers every change to the palette for it
+    pub fn update(&mut self, title: &str, palette: &mut [[u8; 3]; 4]) {
+        if self.game.as_deref() != Some(title) {
+            if let Some((_, saved)) = self.games.iter().find(|(game, _)| game == title) {
+                *palette = *saved;
+            }
+            self.game = Some(title.to_string());
+            self.seen = *palette;
+            return;
+        }
+        // without a cartridge title there is nothing to remember it by
+        if *palette == self.seen || title.is_empty() {
+            return;
+        }
+        match self.games.iter_mut().find(|(game, _)| game == title) {
+            Some((_, saved)) => *saved = *palette,
+            None => self.games.push((title.to_string(), *palette)),
+        }
+        self.seen = *palette;
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, palette: &mut [[u8; 3]; 4]) {
+        ui.horizontal(|ui| {
+            for color in palette.iter_mut() {
+                ui.color_edit_button_srgb(color);
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            for (name, preset) in PRESETS.into_iter().chain(PALETTE_PRESETS) {
+                if ui.button(i18n.tr(name)).clicked() {
+                    *palette = preset;
+                }
+            }
+        });
+        ui.separator();
+        let mut removed = None;
+        for (i, (name, user)) in self.user.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button(name).clicked() {
+                    *palette = *user;
+                }
+                if ui.small_button(i18n.tr("palette-delete")).clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            self.user.remove(i);
+        }
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.name);
+            let name = self.name.trim();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new(i18n.tr("palette-save")))
+                .clicked()
+            {
+                match self.user.iter_mut().find(|(user, _)| user == name) {
+                    Some((_, saved)) => *saved = *palette,
+                    None => self.user.push((name.to_string(), *palette)),
+                }
+                self.name.clear();
+            }
+        });
+        if let Some(game) = self.game.as_deref().filter(|game| !game.is_empty()) {
+            ui.label(i18n.tr_args("palette-per-game", &[("title", game)]));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, Align2, Order, Sense},
+    epaint::{pos2, vec2, Color32, Rect, Stroke},
+};
+use gba::{
+    perf::{FrameTime, PerfStats, HISTORY},
+    status::Status,
+};
+
+const GRAPH_SIZE: egui::Vec2 = vec2(HISTORY as f32, 60.);
+/// The height of the graph, two frames at 59.7 Hz
+const GRAPH_TIME: Duration = Duration::from_micros(33_486);
+/// One frame at 59.7 Hz, drawn as a line across the graph
+const FRAME_TIME: Duration = Duration::from_micros(16_743);
+
+/// The fps of the gui and the emulation, how long the cpu thread emulated and slept
+/// per frame and how full the audio buffer is. Drawn over everything else.
+pub struct PerfOverlay {
+    stats: PerfStats,
+    /// When the gui drew its frames in the last second
+    updates: VecDeque<Instant>,
+}
+impl PerfOverlay {
+    pub fn new(stats: PerfStats) -> Self {
+        PerfOverlay {
+            stats,
+            updates: VecDeque::new(),
+        }
+    }
+    pub fn is_visible(&self) -> bool {
+        self.stats.is_enabled()
+    }
+    /// The cpu thread only measures while it is shown
+    pub fn toggle(&mut self) {
+        self.stats.set_enabled(!self.stats.is_enabled());
+        self.updates.clear();
+    }
+    /// Called every gui frame, counts the frames for the host fps
+    fn count_update(&mut self) -> f32 {
+        let now = Instant::now();
+        self.updates.push_back(now);
+        while self
+            .updates
+            .front()
+            .is_some_and(|at| now - *at > Duration::from_secs(1))
+        {
+            self.updates.pop_front();
+        }
+        self.updates.len() as f32
+    }
+    pub fn show(&mut self, ctx: &egui::Context, i18n: &I18n, status: &Status) {
+        if !self.is_visible() {
+            return;
+        }
+        let host_fps = self.count_update();
+        let frames = self.stats.frames();
+        egui::Area::new("perf_overlay")
+            .order(Order::Foreground)
+            .anchor(Align2::RIGHT_TOP, vec2(-10., 30.))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(i18n.tr_args(
+                        "perf-fps",
+                        &[
+                            ("host", &format!("{host_fps:.0}")),
+                            ("emulated", &format!("{:.1}", status.fps())),
+                        ],
+                    ));
+                    graph(ui, &frames);
+                    if !frames.is_empty() {
+                        let emulating = average(&frames, |frame| frame.emulating);
+                        let sleeping = average(&frames, |frame| frame.sleeping);
+                        ui.label(i18n.tr_args(
+                            "perf-frame-time",
+                            &[
+                                ("emulating", &millis(emulating)),
+                                ("sleeping", &millis(sleeping)),
+                            ],
+                        ));
+                    }
+                    let fill = status.audio_fill();
+                    ui.add(
+                        egui::ProgressBar::new(fill as f32 / 100.)
+                            .desired_width(GRAPH_SIZE.x)
+                            .text(i18n.tr_args("perf-audio", &[("percent", &fill.to_string())])),
+                    );
+                });
+            });
+    }
+}
+
+/// A bar per frame, the emulating part at the bottom and the sleeping part on top
+fn graph(ui: &mut egui::Ui, frames: &[FrameTime]) {
+    let (rect, _) = ui.allocate_exact_size(GRAPH_SIZE, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+    let height =
+        |time: Duration| rect.height() * (time.as_secs_f32() / GRAPH_TIME.as_secs_f32()).min(1.);
+    // the newest frame on the right
+    let left = rect.right() - frames.len() as f32;
+    for (i, frame) in frames.iter().enumerate() {
+        let x = left + i as f32;
+        let emulated = rect.bottom() - height(frame.emulating);
+        let total = rect.bottom() - height(frame.total());
+        let slept = Rect::from_min_max(pos2(x, total), pos2(x + 1., emulated));
+        painter.rect_filled(slept, 0., Color32::DARK_GRAY);
+        let emulating = Rect::from_min_max(pos2(x, emulated), pos2(x + 1., rect.bottom()));
+        painter.rect_filled(emulating, 0., Color32::LIGHT_GREEN);
+    }
+    let y = rect.bottom() - height(FRAME_TIME);
+    painter.line_segment(
+        [pos2(rect.left(), y), pos2(rect.right(), y)],
+        Stroke::new(1., Color32::YELLOW),
+    );
+}
+fn average(frames: &[FrameTime], time: impl Fn(&FrameTime) -> Duration) -> Duration {
+    frames.iter().map(time).sum::<Duration>() / frames.len() as u32
+}
+fn millis(time: Duration) -> String {
+    format!("{:.2}", time.as_secs_f64() * 1000.)
+}
@@ -0,0 +1,125 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use super::i18n::I18n;
+use eframe::egui;
+
+/// Time between writes of the totals while playing
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Entry {
+    title: String,
+    time: Duration,
+}
+
+/// Total time each game ran unpaused, by the CRC32 of the rom
+pub struct Playtime {
+    file: PathBuf,
+    games: BTreeMap<u32, Entry>,
+    /// When `update` last counted
+    last: Option<Instant>,
+    unsaved: Duration,
+}
+impl Playtime {
+    /// Reads the totals from `file`, one game per line: `crc<tab>seconds<tab>title`
+    pub fn load(file: PathBuf) -> Self {
+        let games = fs::read_to_string(&file)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let crc = u32::from_str_radix(fields.next()?, 16).ok()?;
+                let seconds = fields.next()?.parse().ok()?;
+                let entry = Entry {
+                    title: fields.next().unwrap_or_default().to_string(),
+                    time: Duration::from_secs(seconds),
+                };
+                Some((crc, entry))
+            })
+            .collect();
+        Playtime {
+            file,
+            games,
+            last: None,
+            unsaved: Duration::ZERO,
+        }
+    }
+    fn save(&mut self) {
+        let mut content = String::new();
+        for (crc, entry) in &self.games {
+            content.push_str(&format!(
+                "{crc:08X}\t{}\t{}\n",
+                entry.time.as_secs(),
+                entry.title
+            ));
+        }
+        if let Err(e) = fs::write(&self.file, content) {
+            log::warn!("could not save the playtime: {e}");
+        }
+        self.unsaved = Duration::ZERO;
+    }
+    /// Counts the time since the last call towards the game if it is `running`, call every frame
+    pub fn update(&mut self, crc: u32, title: &str, running: bool) {
+        let now = Instant::now();
+        let elapsed = self.last.map_or(Duration::ZERO, |last| now - last);
+        self.last = Some(now);
+        if !running {
+            return;
+        }
+        let entry = self.games.entry(crc).or_insert_with(|| Entry {
+            title: title.replace(['\t', '\n'], " "),
+            time: Duration::ZERO,
+        });
+        entry.time += elapsed;
+        self.unsaved += elapsed;
+        if self.unsaved >= SAVE_INTERVAL {
+            self.save();
+        }
+    }
+    pub fn total(&self, crc: u32) -> Duration {
+        self.games
+            .get(&crc)
+            .map_or(Duration::ZERO, |entry| entry.time)
+    }
+    /// Writes `title,crc,seconds` rows with a header line
+    pub fn export_csv(&self, path: &Path) -> io::Result<()> {
+        let mut csv = String::from("title,crc32,seconds\n");
+        for (crc, entry) in &self.games {
+            csv.push_str(&format!(
+                "\"{}\",{crc:08X},{}\n",
+                entry.title.replace('"', "\"\""),
+                entry.time.as_secs()
+            ));
+        }
+        fs::write(path, csv)
+    }
+    /// Lists every game with its total and offers the csv export
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, export: &Path) {
+        egui::Grid::new("playtime").striped(true).show(ui, |ui| {
+            for entry in self.games.values() {
+                ui.label(&entry.title);
+                ui.label(format_duration(entry.time));
+                ui.end_row();
+            }
+        });
+        if ui.button(i18n.tr("playtime-export")).clicked() {
+            match self.export_csv(export) {
+                Ok(()) => log::info!("exported the playtime to {}", export.display()),
+                Err(e) => log::warn!("could not export the playtime: {e}"),
+            }
+        }
+    }
+}
+/// Like `12h 05m`, or `4m` below an hour
+pub fn format_duration(time: Duration) -> String {
+    let minutes = time.as_secs() / 60;
+    if minutes < 60 {
+        format!("{minutes}m")
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
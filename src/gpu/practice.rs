@@ -0,0 +1,64 @@
+use super::i18n::I18n;
+use eframe::{egui, epaint::Color32};
+use gba::practice::{Practice, Watch};
+
+/// Settings of the practice mode, the snapshots are taken by the cpu
+pub struct PracticePanel {
+    practice: Practice,
+    /// One watch per line, like `C0A4` or `C0A4=05`
+    watches: String,
+}
+impl PracticePanel {
+    pub fn new(practice: Practice) -> Self {
+        PracticePanel {
+            practice,
+            watches: String::new(),
+        }
+    }
+    pub fn retry(&self) {
+        self.practice.retry();
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, retry_key: &str) {
+        let mut enabled = self.practice.enabled();
+        if ui
+            .checkbox(&mut enabled, i18n.tr("practice-enabled"))
+            .changed()
+        {
+            self.practice.set_enabled(enabled);
+        }
+        ui.horizontal(|ui| {
+            ui.label(i18n.tr("practice-interval"));
+            let mut interval = self.practice.interval();
+            let drag = egui::DragValue::new(&mut interval)
+                .clamp_range(0..=600)
+                .suffix(" s");
+            if ui.add(drag).changed() {
+                self.practice.set_interval(interval);
+            }
+        });
+        ui.label(i18n.tr("practice-watches"));
+        if ui.text_edit_multiline(&mut self.watches).changed() {
+            let watches = self.watches.lines().filter_map(Watch::parse).collect();
+            self.practice.set_watches(watches);
+        }
+        for line in self.watches.lines() {
+            if !line.trim().is_empty() && Watch::parse(line).is_none() {
+                ui.colored_label(
+                    Color32::RED,
+                    i18n.tr_args("practice-invalid-watch", &[("watch", line)]),
+                );
+            }
+        }
+        ui.label(i18n.tr_args(
+            "practice-taken",
+            &[("count", &self.practice.taken().to_string())],
+        ));
+        let retry = egui::Button::new(i18n.tr_args("practice-retry", &[("key", retry_key)]));
+        if ui
+            .add_enabled(self.practice.has_snapshot(), retry)
+            .clicked()
+        {
+            self.practice.retry();
+        }
+    }
+}
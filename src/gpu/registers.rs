@@ -0,0 +1,99 @@
+use super::i18n::I18n;
+use eframe::egui::{self, TextStyle};
+use gba::{
+    debugger::{Debugger, Edit},
+    interrupt::{Interrupt, IE, IF},
+    ppu::STAT,
+    ram::Ram,
+    status::Status,
+};
+
+/// The names of the registers in the order the cpu stores them
+const REGISTERS: [&str; 6] = ["BC", "DE", "HL", "AF", "PC", "SP"];
+/// The index of AF, its low byte holds the flags
+const AF: usize = 3;
+/// Z, N, H and C with their bit in F like the cpu tests them
+const FLAGS: [(&str, u8); 4] = [("Z", 0x80), ("N", 0x40), ("H", 0x20), ("C", 0x10)];
+const PPU_MODES: [&str; 4] = ["HBlank", "VBlank", "OAM scan", "Drawing"];
+
+/// The cpu registers, flags and interrupts, which can be changed while paused
+pub struct RegisterPanel {
+    debugger: Debugger,
+    /// The text of each register field, refreshed while it is not edited
+    fields: [String; 6],
+}
+impl RegisterPanel {
+    pub fn new(debugger: Debugger) -> Self {
+        RegisterPanel {
+            debugger,
+            fields: Default::default(),
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, status: &Status, ram: &Ram, paused: bool) {
+        let registers = status.registers();
+        egui::Grid::new("registers").show(ui, |ui| {
+            for (i, name) in REGISTERS.into_iter().enumerate() {
+                ui.monospace(name);
+                let field = ui.add_enabled(
+                    paused,
+                    egui::TextEdit::singleline(&mut self.fields[i])
+                        .desired_width(40.)
+                        .font(TextStyle::Monospace),
+                );
+                if field.lost_focus() {
+                    if let Ok(value) = u16::from_str_radix(self.fields[i].trim(), 16) {
+                        self.debugger.edit(Edit::Register(i, value));
+                    }
+                }
+                if !field.has_focus() {
+                    self.fields[i] = format!("{:04X}", registers[i]);
+                }
+                if i % 2 == 1 {
+                    ui.end_row();
+                }
+            }
+        });
+        let [a, f] = registers[AF].to_be_bytes();
+        ui.horizontal(|ui| {
+            ui.label(i18n.tr("registers-flags"));
+            for (name, mask) in FLAGS {
+                let mut set = f & mask != 0;
+                if ui
+                    .add_enabled(paused, egui::Checkbox::new(&mut set, name))
+                    .changed()
+                {
+                    let af = u16::from_be_bytes([a, f ^ mask]);
+                    self.debugger.edit(Edit::Register(AF, af));
+                }
+            }
+        });
+        let mut ime = status.ime();
+        let checkbox = egui::Checkbox::new(&mut ime, i18n.tr("registers-ime"));
+        if ui.add_enabled(paused, checkbox).changed() {
+            self.debugger.edit(Edit::Ime(ime));
+        }
+        egui::Grid::new("interrupts").show(ui, |ui| {
+            ui.label("");
+            ui.monospace("IE");
+            ui.monospace("IF");
+            ui.end_row();
+            for interrupt in Interrupt::ALL {
+                ui.label(format!("{interrupt:?}"));
+                for register in [IE, IF] {
+                    let mut set = ram[register] & interrupt.bit() != 0;
+                    let checkbox = egui::Checkbox::new(&mut set, "");
+                    if ui.add_enabled(paused, checkbox).changed() {
+                        let value = ram[register] ^ interrupt.bit();
+                        self.debugger.poke(register, value);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        let mode = PPU_MODES[(ram[STAT] & 0b11) as usize];
+        ui.label(i18n.tr_args(
+            "registers-ppu",
+            &[("mode", mode), ("ly", &status.ly().to_string())],
+        ));
+    }
+}
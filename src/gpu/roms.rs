@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::i18n::I18n;
+use eframe::egui;
+
+/// Roms kept in the recent list
+const MAX_RECENT: usize = 10;
+const EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+/// Whether the file looks like a rom by its extension
+pub fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// What the file menu asks for
+pub enum RomChoice {
+    /// Instead of the game running
+    Load(PathBuf),
+    /// Next to the game running, in a window of its own
+    NewWindow(PathBuf),
+}
+
+/// The roms loaded last, newest first
+#[derive(Default)]
+pub struct RecentRoms {
+    pub paths: Vec<PathBuf>,
+}
+impl RecentRoms {
+    pub fn push(&mut self, path: PathBuf) {
+        self.paths.retain(|recent| *recent != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT);
+    }
+    /// The file menu with the recent roms and the roms next to the last one,
+    /// and with `new_window` the recent roms to open next to the game.
+    /// Returns the rom clicked.
+    pub fn menu_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        i18n: &I18n,
+        last_dir: Option<&Path>,
+        new_window: bool,
+    ) -> Option<RomChoice> {
+        let mut clicked = None;
+        ui.menu_button(i18n.tr("menu-file"), |ui| {
+            ui.label(i18n.tr("menu-recent"));
+            if self.paths.is_empty() {
+                ui.weak(i18n.tr("menu-recent-none"));
+            }
+            for path in &self.paths {
+                if rom_button(ui, path) {
+                    clicked = Some(RomChoice::Load(path.clone()));
+                }
+            }
+            if !self.paths.is_empty() && ui.button(i18n.tr("menu-recent-clear")).clicked() {
+                self.paths.clear();
+                ui.close_menu();
+            }
+            if let Some(dir) = last_dir {
+                ui.separator();
+                ui.menu_button(i18n.tr("menu-folder"), |ui| {
+                    let roms = roms_in(dir);
+                    if roms.is_empty() {
+                        ui.weak(i18n.tr("menu-folder-empty"));
+                    }
+                    for path in roms {
+                        if rom_button(ui, &path) {
+                            clicked = Some(RomChoice::Load(path));
+                        }
+                    }
+                });
+            }
+            if new_window && !self.paths.is_empty() {
+                ui.separator();
+                ui.menu_button(i18n.tr("menu-new-window"), |ui| {
+                    for path in &self.paths {
+                        if rom_button(ui, path) {
+                            clicked = Some(RomChoice::NewWindow(path.clone()));
+                        }
+                    }
+                });
+            }
+            ui.separator();
+            ui.weak(i18n.tr("menu-drop-hint"));
+        });
+        clicked
+    }
+}
+/// A button with the file name, the whole path on hover. Closes the menu when clicked.
+fn rom_button(ui: &mut egui::Ui, path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let clicked = ui
+        .button(name)
+        .on_hover_text(path.display().to_string())
+        .clicked();
+    if clicked {
+        ui.close_menu();
+    }
+    clicked
+}
+/// The roms in `dir` sorted by name
+fn roms_in(dir: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| is_rom(path))
+        .collect();
+    roms.sort();
+    roms
+}
@@ -0,0 +1,108 @@
+use std::{
+    f32::consts::TAU,
+    sync::{Arc, Mutex},
+};
+
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, Sense},
+    epaint::{vec2, Color32, Pos2, Stroke},
+};
+use gba::rtc::{Rtc, RtcTime};
+
+const CLOCK_SIZE: f32 = 120.;
+
+/// Shows and edits the cartridge clock, for testing daily events
+pub struct RtcPanel {
+    rtc: Arc<Mutex<Rtc>>,
+    /// Days added by the advance button
+    step: u64,
+}
+impl RtcPanel {
+    pub fn new(rtc: Arc<Mutex<Rtc>>) -> Self {
+        RtcPanel { rtc, step: 1 }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        let mut rtc = self.rtc.lock().unwrap();
+        let mut time = rtc.time();
+        clock_face(ui, time);
+        let before = time;
+        egui::Grid::new("rtc").show(ui, |ui| {
+            ui.label(i18n.tr("rtc-day"));
+            ui.add(egui::DragValue::new(&mut time.days).clamp_range(0..=511));
+            ui.end_row();
+            ui.label(i18n.tr("rtc-time"));
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut time.hours).clamp_range(0..=23));
+                ui.label(":");
+                ui.add(egui::DragValue::new(&mut time.minutes).clamp_range(0..=59));
+                ui.label(":");
+                ui.add(egui::DragValue::new(&mut time.seconds).clamp_range(0..=59));
+            });
+            ui.end_row();
+        });
+        if time != before {
+            rtc.set_time(time);
+        }
+        let mut halted = rtc.halted();
+        if ui.checkbox(&mut halted, i18n.tr("rtc-halted")).changed() {
+            rtc.set_halted(halted);
+        }
+        ui.horizontal(|ui| {
+            if rtc.carry() {
+                ui.colored_label(Color32::YELLOW, i18n.tr("rtc-carry"));
+                if ui.button(i18n.tr("rtc-clear-carry")).clicked() {
+                    rtc.clear_carry();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            for days in [1, 7] {
+                let label = i18n.tr_args("rtc-advance", &[("days", &days.to_string())]);
+                if ui.button(label).clicked() {
+                    rtc.advance_days(days);
+                }
+            }
+            ui.separator();
+            ui.add(egui::DragValue::new(&mut self.step).clamp_range(1..=511));
+            let label = i18n.tr_args("rtc-advance", &[("days", &self.step.to_string())]);
+            if ui.button(label).clicked() {
+                rtc.advance_days(self.step);
+            }
+        });
+    }
+}
+/// An analog clock with hour, minute and second hands
+fn clock_face(ui: &mut egui::Ui, time: RtcTime) {
+    let (rect, _) = ui.allocate_exact_size(vec2(CLOCK_SIZE, CLOCK_SIZE), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let center = rect.center();
+    let radius = CLOCK_SIZE / 2. - 2.;
+    let color = ui.visuals().text_color();
+    painter.circle_stroke(center, radius, Stroke::new(2., color));
+    // 0 is at the top, the angle grows clockwise
+    let point = |turns: f32, length: f32| -> Pos2 {
+        let angle = turns * TAU;
+        center + vec2(angle.sin(), -angle.cos()) * length
+    };
+    for hour in 0..12 {
+        let turns = hour as f32 / 12.;
+        painter.line_segment(
+            [point(turns, radius * 0.85), point(turns, radius)],
+            Stroke::new(1.5, color),
+        );
+    }
+    let seconds = time.seconds as f32;
+    let minutes = time.minutes as f32 + seconds / 60.;
+    let hours = (time.hours % 12) as f32 + minutes / 60.;
+    for (turns, length, width, color) in [
+        (hours / 12., 0.5, 4., color),
+        (minutes / 60., 0.75, 2.5, color),
+        (seconds / 60., 0.85, 1., Color32::RED),
+    ] {
+        painter.line_segment(
+            [center, point(turns, radius * length)],
+            Stroke::new(width, color),
+        );
+    }
+}
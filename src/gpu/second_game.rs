@@ -0,0 +1,141 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Receiver,
+        Arc, RwLock,
+    },
+};
+
+use super::{game_window::GameWindow, i18n::I18n};
+use crate::app::CpuThread;
+use eframe::{
+    egui,
+    epaint::{Color32, Vec2},
+};
+use gba::{
+    crash::CrashReport,
+    frontend::{Buttons, DrawSignal, SharedButtons},
+    ram::Ram,
+    speed::Speed,
+    status::Status,
+};
+
+/// Tells the windows of the games apart
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Starts another game with the options of the first, for the file menu
+pub type Launcher = Box<dyn Fn(PathBuf) -> SecondGame>;
+
+/// Another game in the same window, running on its own thread with its own input.
+/// The one of `--link-rom` is linked to the first by cable, for trying link games
+/// without a second process, the ones the file menu opens run on their own.
+pub struct SecondGame {
+    id: usize,
+    receiver: Receiver<DrawSignal>,
+    input: SharedButtons,
+    speed: Speed,
+    status: Status,
+    ram: Arc<RwLock<Ram>>,
+    crash_receiver: Receiver<CrashReport>,
+    game_window: GameWindow,
+    /// The keyboard and the gamepads play this game instead of the first
+    controlled: bool,
+    crash: Option<CrashReport>,
+    cpu: CpuThread,
+}
+impl SecondGame {
+    pub fn new(
+        receiver: Receiver<DrawSignal>,
+        input: SharedButtons,
+        speed: Speed,
+        status: Status,
+        ram: Arc<RwLock<Ram>>,
+        crash_receiver: Receiver<CrashReport>,
+        cpu: CpuThread,
+    ) -> Self {
+        SecondGame {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            receiver,
+            input,
+            speed,
+            status,
+            ram,
+            crash_receiver,
+            game_window: GameWindow::default(),
+            controlled: false,
+            crash: None,
+            cpu,
+        }
+    }
+    pub fn init(&mut self, ctx: &egui::Context) {
+        self.game_window.init_texture(ctx);
+    }
+    /// The buttons of this game, the keyboard writes them while it is controlled
+    pub fn input(&self) -> &SharedButtons {
+        &self.input
+    }
+    pub fn is_controlled(&self) -> bool {
+        self.controlled
+    }
+    /// The keyboard plays another game
+    pub fn release(&mut self) {
+        self.controlled = false;
+        self.input.set(Buttons::default());
+    }
+    /// Waits until the cpu wrote the save and ended
+    pub fn stop(self) {
+        self.cpu.stop();
+    }
+    /// Takes the frames the cpu sent since the last call, shown in the colors of the first game
+    pub fn update(&mut self, ctx: &egui::Context, palette: [[u8; 3]; 4]) {
+        for signal in self.receiver.try_iter() {
+            match signal {
+                DrawSignal::Frame(frame) => self.game_window.set_frame(frame),
+                DrawSignal::ColorFrame(frame) => self.game_window.set_color_frame(frame),
+                DrawSignal::CpuMode(..) => {}
+            }
+        }
+        if let Ok(report) = self.crash_receiver.try_recv() {
+            self.crash = Some(report);
+        }
+        self.game_window.color_palette = palette;
+        self.game_window.update_texture(ctx);
+    }
+    /// Opens as large as the first game at `size`. Returns false when the window was closed.
+    pub fn show(&mut self, ctx: &egui::Context, i18n: &I18n, size: Vec2) -> bool {
+        let title = self
+            .ram
+            .read()
+            .unwrap()
+            .cartridge_title()
+            .filter(|_| self.status.rom_loaded())
+            .unwrap_or_else(|| i18n.tr("window-second-game").to_string());
+        let mut open = true;
+        egui::Window::new(title)
+            .id(egui::Id::new(("second_game", self.id)))
+            .open(&mut open)
+            .default_size(size)
+            .vscroll(false)
+            .show(ctx, |ui| {
+                if let Some(report) = &self.crash {
+                    ui.colored_label(Color32::RED, &report.message);
+                    return;
+                }
+                self.game_window.view(ui);
+                ui.horizontal(|ui| {
+                    if ui
+                        .checkbox(&mut self.controlled, i18n.tr("second-game-controls"))
+                        .changed()
+                        && !self.controlled
+                    {
+                        self.release();
+                    }
+                    if ui.button(i18n.tr("action-pause")).clicked() {
+                        self.speed.set_paused(!self.speed.is_paused());
+                    }
+                });
+            });
+        open
+    }
+}
@@ -0,0 +1,38 @@
+use super::i18n::I18n;
+use eframe::egui;
+use gba::serial::SerialOutput;
+
+/// The text the game prints through the link port
+pub struct SerialPanel {
+    output: SerialOutput,
+}
+impl SerialPanel {
+    pub fn new(output: SerialOutput) -> Self {
+        SerialPanel { output }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        ui.horizontal(|ui| {
+            let mut enabled = self.output.is_enabled();
+            if ui
+                .checkbox(&mut enabled, i18n.tr("serial-enabled"))
+                .changed()
+            {
+                self.output.set_enabled(enabled);
+            }
+            if ui.button(i18n.tr("serial-clear")).clicked() {
+                self.output.clear();
+            }
+        });
+        let text = self.output.text();
+        if text.is_empty() {
+            ui.label(i18n.tr("serial-empty"));
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .max_height(300.)
+            .show(ui, |ui| {
+                ui.monospace(text);
+            });
+    }
+}
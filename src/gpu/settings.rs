@@ -0,0 +1,409 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    game_window::{GameWindow, GAME_SCREEN_SCALE},
+    input::DEFAULT_KEYMAP,
+    shortcuts::{Action, Shortcuts},
+};
+use eframe::egui::Key;
+use gba::frontend::Buttons;
+
+/// In the config directory
+pub const FILE_NAME: &str = "settings.toml";
+/// Every key egui knows, to read them back by name
+const KEYS: [Key; 71] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Escape,
+    Key::Tab,
+    Key::Backspace,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+];
+/// The names of the buttons in the file and their i18n keys
+pub const BUTTON_NAMES: [(u8, &str); 8] = [
+    (Buttons::RIGHT, "right"),
+    (Buttons::LEFT, "left"),
+    (Buttons::UP, "up"),
+    (Buttons::DOWN, "down"),
+    (Buttons::A, "a"),
+    (Buttons::B, "b"),
+    (Buttons::SELECT, "select"),
+    (Buttons::START, "start"),
+];
+
+pub fn key_name(key: Key) -> String {
+    format!("{key:?}")
+}
+fn parse_key(name: &str) -> Option<Key> {
+    KEYS.into_iter().find(|key| key_name(*key) == name)
+}
+/// The name of a shortcut in the file, its i18n key without the prefix
+fn action_name(action: Action) -> &'static str {
+    action.name().trim_start_matches("action-")
+}
+
+/// What the gui remembers between runs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// The key of every button
+    pub buttons: [(Key, u8); 8],
+    pub shortcuts: Vec<(Action, Key)>,
+    pub palette: [[u8; 3]; 4],
+    /// Integer scale of the game screen
+    pub scale: usize,
+    /// Only whole pixels when the screen is scaled to the window
+    pub integer_scale: bool,
+    /// Draws the border SGB games send around the screen
+    pub sgb_border: bool,
+    /// Of the sound output in percent
+    pub volume: u8,
+    /// Where the last rom was loaded from
+    pub last_rom_dir: Option<PathBuf>,
+    /// Newest first
+    pub recent_roms: Vec<PathBuf>,
+    /// Saved by the user, by name
+    pub user_palettes: Vec<(String, [[u8; 3]; 4])>,
+    /// Chosen for a game, by cartridge title
+    pub game_palettes: Vec<(String, [[u8; 3]; 4])>,
+    /// Saves a state when the emulator closes and offers it on the next start of the game
+    pub suspend_on_exit: bool,
+    /// The name of the only gamepad which plays, any of them if not set
+    pub gamepad: Option<String>,
+}
+impl Settings {
+    /// Reads the subset of TOML `to_toml` writes, anything missing or unknown keeps
+    /// its default
+    pub fn parse(s: &str) -> Self {
+        let mut settings = Settings::default();
+        let mut section = "";
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim();
+                continue;
+            }
+            let Some((key, value)) = split_key(line) else {
+                log::warn!("ignoring the setting {line}");
+                continue;
+            };
+            if !settings.set(section, &key, value) {
+                log::warn!("ignoring the setting {key} = {value}");
+            }
+        }
+        settings
+    }
+    /// Returns false if the key or the value is not known
+    fn set(&mut self, section: &str, key: &str, value: &str) -> bool {
+        match (section, key) {
+            ("", "scale") => match value.parse() {
+                Ok(scale) if scale > 0 => self.scale = scale,
+                _ => return false,
+            },
+            ("", "integer_scale") => match value.parse() {
+                Ok(integer_scale) => self.integer_scale = integer_scale,
+                _ => return false,
+            },
+            ("", "sgb_border") => match value.parse() {
+                Ok(sgb_border) => self.sgb_border = sgb_border,
+                _ => return false,
+            },
+            ("", "suspend_on_exit") => match value.parse() {
+                Ok(suspend) => self.suspend_on_exit = suspend,
+                _ => return false,
+            },
+            ("", "volume") => match value.parse() {
+                Ok(volume) if volume <= 100 => self.volume = volume,
+                _ => return false,
+            },
+            ("", "gamepad") => match unquote(value) {
+                Some(name) => self.gamepad = Some(name),
+                None => return false,
+            },
+            ("", "last_rom_dir") => match unquote(value) {
+                Some(dir) => self.last_rom_dir = Some(PathBuf::from(dir)),
+                None => return false,
+            },
+            ("", "recent_roms") => match parse_strings(value) {
+                Some(paths) => self.recent_roms = paths.into_iter().map(PathBuf::from).collect(),
+                None => return false,
+            },
+            ("", "palette") => match parse_palette(value) {
+                Some(palette) => self.palette = palette,
+                None => return false,
+            },
+            ("palettes", name) => match parse_palette(value) {
+                Some(palette) => self.user_palettes.push((name.to_string(), palette)),
+                None => return false,
+            },
+            ("game-palettes", title) => match parse_palette(value) {
+                Some(palette) => self.game_palettes.push((title.to_string(), palette)),
+                None => return false,
+            },
+            ("buttons", name) => {
+                let bit = BUTTON_NAMES.iter().find(|(_, n)| *n == name);
+                match (bit, unquote(value).as_deref().and_then(parse_key)) {
+                    (Some((bit, _)), Some(key)) => {
+                        if let Some(binding) = self.buttons.iter_mut().find(|(_, b)| b == bit) {
+                            binding.0 = key;
+                        }
+                    }
+                    _ => return false,
+                }
+            }
+            ("shortcuts", name) => {
+                let binding = self
+                    .shortcuts
+                    .iter_mut()
+                    .find(|(action, _)| action_name(*action) == name);
+                match (binding, unquote(value).as_deref().and_then(parse_key)) {
+                    (Some(binding), Some(key)) => binding.1 = key,
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+    pub fn to_toml(&self) -> String {
+        let mut toml = format!(
+            "scale = {}\ninteger_scale = {}\nsgb_border = {}\nvolume = {}\nsuspend_on_exit = {}\n",
+            self.scale, self.integer_scale, self.sgb_border, self.volume, self.suspend_on_exit
+        );
+        if let Some(name) = &self.gamepad {
+            toml.push_str(&format!("gamepad = {}\n", quote(name)));
+        }
+        if let Some(dir) = &self.last_rom_dir {
+            toml.push_str(&format!(
+                "last_rom_dir = {}\n",
+                quote(&dir.to_string_lossy())
+            ));
+        }
+        let recent: Vec<String> = self
+            .recent_roms
+            .iter()
+            .map(|path| quote(&path.to_string_lossy()))
+            .collect();
+        toml.push_str(&format!("recent_roms = [{}]\n", recent.join(", ")));
+        toml.push_str(&format!("palette = {}\n", palette_toml(&self.palette)));
+        toml.push_str("\n[buttons]\n");
+        for (key, bit) in &self.buttons {
+            if let Some((_, name)) = BUTTON_NAMES.iter().find(|(b, _)| b == bit) {
+                toml.push_str(&format!("{name} = {}\n", quote(&key_name(*key))));
+            }
+        }
+        toml.push_str("\n[shortcuts]\n");
+        for (action, key) in &self.shortcuts {
+            toml.push_str(&format!(
+                "{} = {}\n",
+                action_name(*action),
+                quote(&key_name(*key))
+            ));
+        }
+        for (section, palettes) in [
+            ("palettes", &self.user_palettes),
+            ("game-palettes", &self.game_palettes),
+        ] {
+            toml.push_str(&format!("\n[{section}]\n"));
+            for (name, palette) in palettes {
+                toml.push_str(&format!("{} = {}\n", quote(name), palette_toml(palette)));
+            }
+        }
+        toml
+    }
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            buttons: DEFAULT_KEYMAP,
+            shortcuts: Shortcuts::default().bindings().to_vec(),
+            palette: GameWindow::default().color_palette,
+            scale: GAME_SCREEN_SCALE,
+            integer_scale: false,
+            sgb_border: true,
+            volume: 100,
+            last_rom_dir: None,
+            recent_roms: Vec::new(),
+            user_palettes: Vec::new(),
+            game_palettes: Vec::new(),
+            suspend_on_exit: false,
+            gamepad: None,
+        }
+    }
+}
+/// A TOML basic string
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+fn unquote(s: &str) -> Option<String> {
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some(out)
+}
+/// Splits `key = value`, the key may be a quoted string
+fn split_key(line: &str) -> Option<(String, &str)> {
+    if !line.starts_with('"') {
+        let (key, value) = line.split_once('=')?;
+        return Some((key.trim().to_string(), value.trim()));
+    }
+    let (key, rest) = split_string(line)?;
+    let value = rest.trim_start().strip_prefix('=')?;
+    Some((key, value.trim()))
+}
+/// Splits off the quoted string `s` starts with
+fn split_string(s: &str) -> Option<(String, &str)> {
+    // the closing quote is the first one not escaped
+    let mut escaped = false;
+    let end = s.char_indices().skip(1).find_map(|(i, c)| {
+        let end = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        end.then_some(i)
+    })?;
+    Some((unquote(&s[..=end])?, &s[end + 1..]))
+}
+/// An array of strings, which may contain commas
+fn parse_strings(value: &str) -> Option<Vec<String>> {
+    let mut rest = value.strip_prefix('[')?.trim_start();
+    let mut strings = Vec::new();
+    while !rest.starts_with(']') {
+        let (string, after) = split_string(rest)?;
+        strings.push(string);
+        let after = after.trim_start();
+        rest = after.strip_prefix(',').unwrap_or(after).trim_start();
+    }
+    Some(strings)
+}
+/// An array of four colors
+fn parse_palette(value: &str) -> Option<[[u8; 3]; 4]> {
+    let colors: Vec<[u8; 3]> = value
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split(',')
+        .map(|c| parse_color(c.trim()))
+        .collect::<Option<_>>()?;
+    colors.try_into().ok()
+}
+fn palette_toml(palette: &[[u8; 3]; 4]) -> String {
+    let colors: Vec<String> = palette
+        .iter()
+        .map(|[r, g, b]| format!("\"#{r:02X}{g:02X}{b:02X}\""))
+        .collect();
+    format!("[{}]", colors.join(", "))
+}
+/// `"#RRGGBB"`
+fn parse_color(s: &str) -> Option<[u8; 3]> {
+    let hex = unquote(s)?;
+    let hex = hex.strip_prefix('#')?;
+    let value = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 6)?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+/// The settings file, written again whenever the settings change
+pub struct SettingsFile {
+    path: PathBuf,
+    saved: Settings,
+}
+impl SettingsFile {
+    /// Reads the file, the defaults are used without one
+    pub fn load(path: PathBuf) -> (Self, Settings) {
+        let settings = match fs::read_to_string(&path) {
+            Ok(s) => Settings::parse(&s),
+            Err(_) => Settings::default(),
+        };
+        let file = SettingsFile {
+            path,
+            saved: settings.clone(),
+        };
+        (file, settings)
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn save_if_changed(&mut self, settings: &Settings) {
+        if *settings == self.saved {
+            return;
+        }
+        if let Err(e) = fs::write(&self.path, settings.to_toml()) {
+            log::warn!(
+                "could not save the settings to {}: {e}",
+                self.path.display()
+            );
+        }
+        self.saved = settings.clone();
+    }
+}
@@ -0,0 +1,265 @@
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, Event, Key},
+    epaint::Color32,
+};
+
+/// Everything which can be triggered by a hotkey
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    SpeedrunSplit,
+    SpeedrunReset,
+    SpeedUp,
+    SpeedDown,
+    CopyScreen,
+    CopyRegisters,
+    ToggleHud,
+    /// Shows the fps and the frame times
+    PerfOverlay,
+    /// Shows the held buttons and fast-forward
+    InputDisplay,
+    /// Square 1, square 2, wave or noise
+    MuteChannel(usize),
+    SoloChannel(usize),
+    PracticeRetry,
+    SaveState,
+    LoadState,
+    PreviousSlot,
+    NextSlot,
+    /// Runs at the turbo rate while held
+    FastForward,
+    /// Goes back in time while held
+    Rewind,
+    Fullscreen,
+    /// Only the game screen, without the windows around it
+    PlayMode,
+    Screenshot,
+    /// Starts or stops a recording of the screen
+    Record,
+    Pause,
+    Reset,
+}
+impl Action {
+    pub const ALL: [Action; 30] = [
+        Action::SpeedrunSplit,
+        Action::SpeedrunReset,
+        Action::SpeedUp,
+        Action::SpeedDown,
+        Action::CopyScreen,
+        Action::CopyRegisters,
+        Action::ToggleHud,
+        Action::PerfOverlay,
+        Action::InputDisplay,
+        Action::MuteChannel(0),
+        Action::MuteChannel(1),
+        Action::MuteChannel(2),
+        Action::MuteChannel(3),
+        Action::SoloChannel(0),
+        Action::SoloChannel(1),
+        Action::SoloChannel(2),
+        Action::SoloChannel(3),
+        Action::PracticeRetry,
+        Action::SaveState,
+        Action::LoadState,
+        Action::PreviousSlot,
+        Action::NextSlot,
+        Action::FastForward,
+        Action::Rewind,
+        Action::Fullscreen,
+        Action::PlayMode,
+        Action::Screenshot,
+        Action::Record,
+        Action::Pause,
+        Action::Reset,
+    ];
+    /// The i18n key of the action name
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::SpeedrunSplit => "action-speedrun-split",
+            Action::SpeedrunReset => "action-speedrun-reset",
+            Action::SpeedUp => "action-speed-up",
+            Action::SpeedDown => "action-speed-down",
+            Action::CopyScreen => "action-copy-screen",
+            Action::CopyRegisters => "action-copy-registers",
+            Action::ToggleHud => "action-toggle-hud",
+            Action::PerfOverlay => "action-perf-overlay",
+            Action::InputDisplay => "action-input-display",
+            Action::MuteChannel(channel) => [
+                "action-mute-square-1",
+                "action-mute-square-2",
+                "action-mute-wave",
+                "action-mute-noise",
+            ][channel],
+            Action::SoloChannel(channel) => [
+                "action-solo-square-1",
+                "action-solo-square-2",
+                "action-solo-wave",
+                "action-solo-noise",
+            ][channel],
+            Action::PracticeRetry => "action-practice-retry",
+            Action::SaveState => "action-save-state",
+            Action::LoadState => "action-load-state",
+            Action::PreviousSlot => "action-previous-slot",
+            Action::NextSlot => "action-next-slot",
+            Action::FastForward => "action-fast-forward",
+            Action::Rewind => "action-rewind",
+            Action::Fullscreen => "action-fullscreen",
+            Action::PlayMode => "action-play-mode",
+            Action::Screenshot => "action-screenshot",
+            Action::Record => "action-record",
+            Action::Pause => "action-pause",
+            Action::Reset => "action-reset",
+        }
+    }
+    fn default_key(self) -> Key {
+        match self {
+            Action::SpeedrunSplit => Key::F1,
+            Action::SpeedrunReset => Key::F2,
+            Action::SpeedUp => Key::PageUp,
+            Action::SpeedDown => Key::PageDown,
+            Action::CopyScreen => Key::F9,
+            Action::CopyRegisters => Key::F10,
+            Action::ToggleHud => Key::End,
+            Action::PerfOverlay => Key::F3,
+            Action::InputDisplay => Key::I,
+            Action::MuteChannel(channel) => [Key::Num1, Key::Num2, Key::Num3, Key::Num4][channel],
+            Action::SoloChannel(channel) => [Key::Num5, Key::Num6, Key::Num7, Key::Num8][channel],
+            Action::PracticeRetry => Key::F4,
+            // F5 to F8 play the macros
+            Action::SaveState => Key::S,
+            Action::PreviousSlot => Key::Num9,
+            Action::NextSlot => Key::Num0,
+            Action::LoadState => Key::L,
+            Action::FastForward => Key::Tab,
+            Action::Rewind => Key::R,
+            Action::Fullscreen => Key::F11,
+            Action::PlayMode => Key::F12,
+            Action::Screenshot => Key::Insert,
+            Action::Record => Key::Home,
+            Action::Pause => Key::Space,
+            Action::Reset => Key::Delete,
+        }
+    }
+}
+
+pub struct Shortcuts {
+    bindings: Vec<(Action, Key)>,
+    /// The action waiting for a key press to be rebound
+    rebinding: Option<Action>,
+}
+impl Shortcuts {
+    pub fn key(&self, action: Action) -> Key {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(action.default_key(), |(_, key)| *key)
+    }
+    /// The actions whose key was pressed this frame
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        // digits and letters are typed into text fields instead
+        if self.rebinding.is_some() || ctx.wants_keyboard_input() {
+            return Vec::new();
+        }
+        let input = ctx.input();
+        self.bindings
+            .iter()
+            .filter(|(_, key)| input.key_pressed(*key))
+            .map(|(action, _)| *action)
+            .collect()
+    }
+    /// Whether the key of the action is held down right now
+    pub fn held(&self, ctx: &egui::Context, action: Action) -> bool {
+        if self.rebinding.is_some() || ctx.wants_keyboard_input() {
+            return false;
+        }
+        ctx.input().key_down(self.key(action))
+    }
+    /// Actions which share a key with another action or with `taken`, the keys of the
+    /// buttons and the macros
+    pub fn conflicts(&self, taken: &[Key]) -> Vec<Action> {
+        self.bindings
+            .iter()
+            .filter(|(action, key)| {
+                taken.contains(key)
+                    || self
+                        .bindings
+                        .iter()
+                        .any(|(other, other_key)| other != action && other_key == key)
+            })
+            .map(|(action, _)| *action)
+            .collect()
+    }
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, taken: &[Key]) {
+        if let Some(action) = self.rebinding {
+            let pressed = ui.input().events.iter().find_map(|event| match event {
+                Event::Key {
+                    key, pressed: true, ..
+                } => Some(*key),
+                _ => None,
+            });
+            if let Some(key) = pressed {
+                if key != Key::Escape {
+                    self.set(action, key);
+                }
+                self.rebinding = None;
+            }
+        }
+        let conflicts = self.conflicts(taken);
+        egui::Grid::new("shortcuts").show(ui, |ui| {
+            for action in Action::ALL {
+                ui.label(i18n.tr(action.name()));
+                let text = if self.rebinding == Some(action) {
+                    i18n.tr("shortcuts-press-key").to_string()
+                } else {
+                    format!("{:?}", self.key(action))
+                };
+                if ui.button(text).clicked() {
+                    self.rebinding = Some(action);
+                }
+                if conflicts.contains(&action) {
+                    ui.colored_label(Color32::RED, i18n.tr("shortcuts-conflict"));
+                }
+                ui.end_row();
+            }
+        });
+        if ui.button(i18n.tr("shortcuts-reset")).clicked() {
+            *self = Shortcuts::default();
+        }
+    }
+    pub fn bindings(&self) -> &[(Action, Key)] {
+        &self.bindings
+    }
+    pub fn set_bindings(&mut self, bindings: Vec<(Action, Key)>) {
+        self.bindings = bindings;
+    }
+    fn set(&mut self, action: Action, key: Key) {
+        if let Some(binding) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            binding.1 = key;
+        }
+    }
+}
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Shortcuts {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_key()))
+                .collect(),
+            rebinding: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gpu::input::{DEFAULT_KEYMAP, MACRO_KEYS};
+
+    use super::*;
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        let buttons = DEFAULT_KEYMAP.map(|(key, _)| key);
+        let taken: Vec<Key> = buttons.into_iter().chain(MACRO_KEYS).collect();
+        assert_eq!(Shortcuts::default().conflicts(&taken), []);
+    }
+}
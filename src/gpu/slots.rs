@@ -0,0 +1,151 @@
+use std::time::SystemTime;
+
+use super::{i18n::I18n, playtime::format_duration};
+use eframe::{egui, epaint::Color32};
+use gba::slots::{SlotRequest, Slots, SLOTS};
+
+/// The numbered save state slots of the running game
+pub struct SlotsPanel {
+    slots: Slots,
+    /// The slot whose backups are listed
+    browsing: Option<usize>,
+    /// Why the last restore failed
+    restore_error: Option<String>,
+    /// The game whose state saved on exit was continued or thrown away
+    resume_answered: Option<u32>,
+    /// The slot just saved to, the timeline keeps a picture of the screen with it
+    saved: Option<usize>,
+}
+impl SlotsPanel {
+    pub fn new(slots: Slots) -> Self {
+        SlotsPanel {
+            slots,
+            browsing: None,
+            restore_error: None,
+            resume_answered: None,
+            saved: None,
+        }
+    }
+    pub fn slots(&self) -> &Slots {
+        &self.slots
+    }
+    pub fn save_selected(&mut self) {
+        self.save(self.slots.selected());
+    }
+    fn save(&mut self, slot: usize) {
+        self.slots.request(SlotRequest::Save(slot));
+        self.saved = Some(slot);
+    }
+    pub fn take_saved(&mut self) -> Option<usize> {
+        self.saved.take()
+    }
+    pub fn load_selected(&self) {
+        self.slots.request(SlotRequest::Load(self.slots.selected()));
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, crc: u32) {
+        let mut selected = self.slots.selected();
+        egui::Grid::new("slots").show(ui, |ui| {
+            for slot in 0..SLOTS {
+                let number = (slot + 1).to_string();
+                let name = i18n.tr_args("slots-slot", &[("number", &number)]);
+                if ui.radio_value(&mut selected, slot, name).changed() {
+                    self.slots.select(slot);
+                }
+                let modified = self.slots.modified(crc, slot);
+                match modified.and_then(|time| SystemTime::now().duration_since(time).ok()) {
+                    Some(age) => ui
+                        .label(i18n.tr_args("slots-saved-ago", &[("time", &format_duration(age))])),
+                    None => ui.label(i18n.tr("slots-empty")),
+                };
+                if ui.button(i18n.tr("slots-save")).clicked() {
+                    self.save(slot);
+                }
+                let load = egui::Button::new(i18n.tr("slots-load"));
+                if ui.add_enabled(modified.is_some(), load).clicked() {
+                    self.slots.request(SlotRequest::Load(slot));
+                }
+                let browsing = self.browsing == Some(slot);
+                if ui
+                    .selectable_label(browsing, i18n.tr("slots-backups"))
+                    .clicked()
+                {
+                    self.browsing = (!browsing).then_some(slot);
+                    self.restore_error = None;
+                }
+                ui.end_row();
+            }
+        });
+        match self.slots.result() {
+            Some((_, Err(e))) => {
+                ui.colored_label(Color32::RED, i18n.tr_args("slots-failed", &[("error", &e)]));
+            }
+            Some((SlotRequest::Load(_), Ok(()))) if self.slots.older_than_save() => {
+                ui.colored_label(Color32::YELLOW, i18n.tr("slots-older-than-save"));
+            }
+            _ => {}
+        }
+        let mut suspend = self.slots.suspend_on_exit();
+        if ui
+            .checkbox(&mut suspend, i18n.tr("slots-suspend-on-exit"))
+            .changed()
+        {
+            self.slots.set_suspend_on_exit(suspend);
+        }
+        if let Some(slot) = self.browsing {
+            ui.separator();
+            self.backups_ui(ui, i18n, crc, slot);
+        }
+    }
+    /// Offers to continue from the state saved when the game was last closed
+    pub fn resume_ui(&mut self, ctx: &egui::Context, i18n: &I18n, crc: u32) {
+        if self.resume_answered == Some(crc) || !self.slots.can_resume(crc) {
+            return;
+        }
+        egui::Window::new(i18n.tr("window-resume"))
+            .id(egui::Id::new("resume"))
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(i18n.tr("resume-question"));
+                ui.horizontal(|ui| {
+                    if ui.button(i18n.tr("resume-continue")).clicked() {
+                        self.slots.request(SlotRequest::Resume);
+                        self.resume_answered = Some(crc);
+                    }
+                    if ui.button(i18n.tr("resume-start-over")).clicked() {
+                        if let Err(e) = self.slots.discard_suspend(crc) {
+                            log::warn!("could not delete the suspended state: {e}");
+                        }
+                        self.resume_answered = Some(crc);
+                    }
+                });
+            });
+    }
+    /// The earlier states of `slot`, any of them can go back into the slot
+    fn backups_ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, crc: u32, slot: usize) {
+        let number = (slot + 1).to_string();
+        ui.label(i18n.tr_args("slots-backups-of", &[("number", &number)]));
+        let backups = self.slots.backups(crc, slot);
+        if backups.is_empty() {
+            ui.label(i18n.tr("slots-no-backups"));
+        }
+        egui::Grid::new("slot backups").show(ui, |ui| {
+            for backup in &backups {
+                let age = SystemTime::now()
+                    .duration_since(backup.time())
+                    .unwrap_or_default();
+                ui.label(i18n.tr_args("slots-saved-ago", &[("time", &format_duration(age))]));
+                if ui.button(i18n.tr("slots-restore")).clicked() {
+                    self.restore_error = self
+                        .slots
+                        .restore(crc, slot, backup)
+                        .err()
+                        .map(|e| e.to_string());
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(e) = &self.restore_error {
+            ui.colored_label(Color32::RED, i18n.tr_args("slots-failed", &[("error", e)]));
+        }
+    }
+}
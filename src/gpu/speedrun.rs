@@ -0,0 +1,169 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use eframe::{
+    egui::{self, Align2, RichText},
+    epaint::{vec2, Color32},
+};
+use gba::frontend::Buttons;
+
+/// Overlay timer for speedruns.
+/// Splits are stored as the total time since the start of the run.
+#[derive(Default)]
+pub struct SpeedrunTimer {
+    pub visible: bool,
+    /// Starts the run with the first button pressed after a reset
+    pub auto_start: bool,
+    start: Option<Instant>,
+    splits: Vec<Duration>,
+    finished: Option<Duration>,
+}
+impl SpeedrunTimer {
+    /// Starts the run or, if it is already running, records a split
+    pub fn start_or_split(&mut self) {
+        match self.start {
+            None => self.start = Some(Instant::now()),
+            Some(start) if self.finished.is_none() => self.splits.push(start.elapsed()),
+            Some(_) => {}
+        }
+    }
+    /// Called every frame with the held buttons
+    pub fn input(&mut self, held: Buttons) {
+        if self.auto_start && self.start.is_none() && held != Buttons::default() {
+            self.start = Some(Instant::now());
+        }
+    }
+    /// Stops the timer, the last split is the final time
+    pub fn finish(&mut self) {
+        if self.finished.is_some() {
+            return;
+        }
+        if let Some(start) = self.start {
+            let end = start.elapsed();
+            self.splits.push(end);
+            self.finished = Some(end);
+        }
+    }
+    pub fn reset(&mut self) {
+        self.start = None;
+        self.splits.clear();
+        self.finished = None;
+    }
+    pub fn elapsed(&self) -> Duration {
+        match (self.start, self.finished) {
+            (_, Some(end)) => end,
+            (Some(start), None) => start.elapsed(),
+            (None, None) => Duration::ZERO,
+        }
+    }
+    pub fn show(&self, ctx: &egui::Context) {
+        if !self.visible {
+            return;
+        }
+        egui::Area::new("speedrun_timer")
+            .anchor(Align2::RIGHT_TOP, vec2(-10., 10.))
+            .show(ctx, |ui| {
+                let mut last = Duration::ZERO;
+                for (i, split) in self.splits.iter().enumerate() {
+                    ui.label(format!(
+                        "{}  {}  (+{})",
+                        i + 1,
+                        format_time(*split),
+                        format_time(*split - last)
+                    ));
+                    last = *split;
+                }
+                let color = if self.finished.is_some() {
+                    Color32::GOLD
+                } else {
+                    Color32::WHITE
+                };
+                ui.label(
+                    RichText::new(format_time(self.elapsed()))
+                        .size(24.)
+                        .monospace()
+                        .color(color),
+                );
+            });
+    }
+    /// Writes the splits as a LiveSplit `.lss` file
+    pub fn export_livesplit(&self, path: &Path, game: &str) -> io::Result<()> {
+        let mut lss = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        lss.push_str("<Run version=\"1.7.0\">\n");
+        let _ = writeln!(lss, "  <GameName>{}</GameName>", escape_xml(game));
+        lss.push_str("  <CategoryName></CategoryName>\n");
+        lss.push_str("  <Offset>00:00:00</Offset>\n  <AttemptCount>1</AttemptCount>\n");
+        lss.push_str("  <Segments>\n");
+        let mut last = Duration::ZERO;
+        for (i, split) in self.splits.iter().enumerate() {
+            let _ = write!(
+                lss,
+                "    <Segment>\n      <Name>Split {}</Name>\n      <SplitTimes>\n        \
+                 <SplitTime name=\"Personal Best\">\n          <RealTime>{}</RealTime>\n        \
+                 </SplitTime>\n      </SplitTimes>\n      <BestSegmentTime>\n        \
+                 <RealTime>{}</RealTime>\n      </BestSegmentTime>\n      <SegmentHistory />\n    \
+                 </Segment>\n",
+                i + 1,
+                format_livesplit_time(*split),
+                format_livesplit_time(*split - last),
+            );
+            last = *split;
+        }
+        lss.push_str("  </Segments>\n  <AutoSplitterSettings />\n</Run>\n");
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, lss)
+    }
+}
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_millis() / 10
+    )
+}
+/// LiveSplit uses `hh:mm:ss.fffffff`
+fn format_livesplit_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_nanos() / 100
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_start_and_finish_once() {
+        let mut timer = SpeedrunTimer {
+            auto_start: true,
+            ..SpeedrunTimer::default()
+        };
+        timer.input(Buttons::default());
+        assert!(timer.start.is_none());
+        timer.input(Buttons(Buttons::START));
+        assert!(timer.start.is_some());
+        timer.finish();
+        timer.finish();
+        assert_eq!(timer.splits.len(), 1);
+        assert_eq!(Some(timer.splits[0]), timer.finished);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use super::{i18n::I18n, meters::ChannelMeters, playtime};
+use eframe::{egui, epaint::Color32};
+use gba::{
+    ram::{self, Ram},
+    speed::Speed,
+    status::Status,
+};
+
+const CARTRIDGE_TYPE: u16 = 0x0147;
+
+/// Bottom bar with the emulation state, fps, the inserted cartridge, its total `playtime`
+/// and the sound channel meters
+pub fn show(
+    ctx: &egui::Context,
+    i18n: &I18n,
+    speed: &Speed,
+    status: &Status,
+    ram: Option<&Arc<RwLock<Ram>>>,
+    playtime: Duration,
+    meters: &ChannelMeters,
+) {
+    egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            let state = if speed.is_paused() {
+                i18n.tr("status-paused").to_string()
+            } else if speed.get() > 100 {
+                i18n.tr_args(
+                    "status-fast-forward",
+                    &[("percent", &speed.get().to_string())],
+                )
+            } else if speed.get() < 100 {
+                i18n.tr_args(
+                    "status-slow-motion",
+                    &[("percent", &speed.get().to_string())],
+                )
+            } else {
+                i18n.tr("status-running").to_string()
+            };
+            ui.label(state);
+            ui.separator();
+            ui.label(format!("{:.1} fps", status.fps()));
+            if let Some(ram) = ram {
+                let ram = ram::lock(ram);
+                ui.separator();
+                ui.label(
+                    ram.cartridge_title()
+                        .unwrap_or_else(|| i18n.tr("status-no-title").to_string()),
+                );
+                ui.separator();
+                ui.label(mapper(ram[CARTRIDGE_TYPE]));
+            }
+            if status.rom_loaded() {
+                ui.separator();
+                ui.label(i18n.tr_args(
+                    "status-playtime",
+                    &[("time", &playtime::format_duration(playtime))],
+                ));
+            }
+            ui.separator();
+            let color = if status.sram_dirty() {
+                Color32::LIGHT_RED
+            } else {
+                ui.visuals().weak_text_color()
+            };
+            ui.colored_label(color, "SRAM");
+            ui.separator();
+            meters.show(ui);
+        });
+    });
+}
+/// Memory bank controller named by the cartridge type byte
+fn mapper(cartridge_type: u8) -> &'static str {
+    match cartridge_type {
+        0x00 => "ROM",
+        0x01..=0x03 => "MBC1",
+        0x05..=0x06 => "MBC2",
+        0x0B..=0x0D => "MMM01",
+        0x0F..=0x13 => "MBC3",
+        0x19..=0x1E => "MBC5",
+        0x20 => "MBC6",
+        0x22 => "MBC7",
+        0xFC => "Pocket Camera",
+        0xFD => "TAMA5",
+        0xFE => "HuC3",
+        0xFF => "HuC1",
+        _ => "?",
+    }
+}
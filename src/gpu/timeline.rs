@@ -0,0 +1,203 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+    time::SystemTime,
+};
+
+use super::{
+    capture,
+    game_window::{GameWindow, GAME_SCREEN_HEIGHT, GAME_SCREEN_WIDTH},
+    i18n::I18n,
+    playtime::format_duration,
+};
+use eframe::{
+    egui::{self, TextureFilter, TextureHandle},
+    epaint::{vec2, ColorImage},
+};
+use gba::{
+    rewind::Rewind,
+    slots::{SlotRequest, Slots, SLOTS},
+};
+
+/// Rewind snapshots between two thumbnails, a second at the rewind interval
+const THUMBNAIL_EVERY: u64 = 30;
+/// Half the game screen
+const THUMBNAIL_SCALE: f32 = 0.5;
+
+/// A point of the rewind history with the screen at the time
+struct RewindPoint {
+    number: u64,
+    time: SystemTime,
+    texture: TextureHandle,
+}
+
+/// The thumbnail of a save state slot, loaded again when the slot was saved again
+struct SlotThumbnail {
+    crc: u32,
+    modified: SystemTime,
+    texture: Option<TextureHandle>,
+}
+
+/// A point to go back to, the rewind history and the save states on one line
+enum Point {
+    Rewind(u64),
+    Slot(usize),
+}
+
+/// The rewind history and the save state slots by time, with a picture of each.
+/// Clicking a point goes back to it.
+#[derive(Default)]
+pub struct Timeline {
+    /// Oldest first
+    points: VecDeque<RewindPoint>,
+    slots: [Option<SlotThumbnail>; SLOTS],
+}
+impl Timeline {
+    /// Takes a thumbnail every `THUMBNAIL_EVERY` snapshots and drops the ones of snapshots
+    /// which are gone, rewound or too old
+    pub fn update(&mut self, ctx: &egui::Context, rewind: &Rewind, screen: &GameWindow) {
+        let kept = rewind.oldest()..=rewind.newest();
+        self.points.retain(|point| kept.contains(&point.number));
+        let newest = *kept.end();
+        let due = self
+            .points
+            .back()
+            .is_none_or(|last| newest >= last.number + THUMBNAIL_EVERY);
+        if due && !kept.is_empty() {
+            let name = format!("timeline_{newest}");
+            let texture = ctx.load_texture(name, screen_image(screen), TextureFilter::Linear);
+            self.points.push_back(RewindPoint {
+                number: newest,
+                time: SystemTime::now(),
+                texture,
+            });
+        }
+    }
+    /// Keeps the screen next to the state of `slot`, the timeline shows it
+    pub fn save_thumbnail(&self, slots: &Slots, crc: u32, slot: usize, screen: &GameWindow) {
+        let path = slots.thumbnail_path(crc, slot);
+        if let Err(e) = capture::write_png(&path, &[screen.rgba()]) {
+            log::warn!("could not write {}: {e}", path.display());
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, rewind: &Rewind, slots: &Slots, crc: u32) {
+        let mut points: Vec<(SystemTime, Point)> = self
+            .points
+            .iter()
+            .map(|point| (point.time, Point::Rewind(point.number)))
+            .collect();
+        for slot in 0..SLOTS {
+            if let Some(modified) = slots.modified(crc, slot) {
+                self.load_slot(ui.ctx(), slots, crc, slot, modified);
+                points.push((modified, Point::Slot(slot)));
+            }
+        }
+        if points.is_empty() {
+            ui.label(i18n.tr("timeline-empty"));
+            return;
+        }
+        points.sort_by_key(|(time, _)| *time);
+        let size = vec2(
+            GAME_SCREEN_WIDTH as f32 * THUMBNAIL_SCALE,
+            GAME_SCREEN_HEIGHT as f32 * THUMBNAIL_SCALE,
+        );
+        egui::ScrollArea::horizontal()
+            .stick_to_right(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (time, point) in points {
+                        let ago = SystemTime::now().duration_since(time).unwrap_or_default();
+                        let ago = i18n.tr_args("timeline-ago", &[("time", &format_duration(ago))]);
+                        let (texture, label) = match point {
+                            Point::Rewind(number) => (
+                                self.points
+                                    .iter()
+                                    .find(|point| point.number == number)
+                                    .map(|point| &point.texture),
+                                ago,
+                            ),
+                            Point::Slot(slot) => {
+                                let number = (slot + 1).to_string();
+                                let name = i18n.tr_args("slots-slot", &[("number", &number)]);
+                                (
+                                    self.slots[slot]
+                                        .as_ref()
+                                        .and_then(|thumbnail| thumbnail.texture.as_ref()),
+                                    format!("{name}, {ago}"),
+                                )
+                            }
+                        };
+                        ui.vertical(|ui| {
+                            let clicked = match texture {
+                                Some(texture) => {
+                                    ui.add(egui::ImageButton::new(texture.id(), size)).clicked()
+                                }
+                                None => ui.add_sized(size, egui::Button::new("?")).clicked(),
+                            };
+                            ui.label(label);
+                            if clicked {
+                                match point {
+                                    Point::Rewind(number) => rewind.seek(number),
+                                    Point::Slot(slot) => slots.request(SlotRequest::Load(slot)),
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+    }
+    /// Reads the thumbnail of `slot` unless the one of this save is loaded
+    fn load_slot(
+        &mut self,
+        ctx: &egui::Context,
+        slots: &Slots,
+        crc: u32,
+        slot: usize,
+        modified: SystemTime,
+    ) {
+        if let Some(thumbnail) = &self.slots[slot] {
+            if thumbnail.crc == crc && thumbnail.modified == modified {
+                return;
+            }
+        }
+        let path = slots.thumbnail_path(crc, slot);
+        let texture = match read_thumbnail(&path) {
+            Ok(image) => {
+                Some(ctx.load_texture(format!("slot_{slot}"), image, TextureFilter::Linear))
+            }
+            // states saved before the thumbnails or from the command line have none
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("could not read {}: {e}", path.display());
+                None
+            }
+        };
+        self.slots[slot] = Some(SlotThumbnail {
+            crc,
+            modified,
+            texture,
+        });
+    }
+}
+fn screen_image(screen: &GameWindow) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied([GAME_SCREEN_WIDTH, GAME_SCREEN_HEIGHT], &screen.rgba())
+}
+/// A screen `save_thumbnail` wrote
+fn read_thumbnail(path: &Path) -> io::Result<ColorImage> {
+    let invalid = |e: png::DecodingError| io::Error::new(io::ErrorKind::InvalidData, e);
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let info = reader.info();
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an 8 bit RGBA png",
+        ));
+    }
+    let size = [info.width as usize, info.height as usize];
+    let mut rgba = vec![0; size[0] * size[1] * 4];
+    reader.next_frame(&mut rgba).map_err(invalid)?;
+    Ok(ColorImage::from_rgba_unmultiplied(size, &rgba))
+}
@@ -0,0 +1,195 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::i18n::I18n;
+use eframe::egui;
+
+/// The only request made, it sends nothing but the user agent
+#[cfg(feature = "update-check")]
+const LATEST_RELEASE: &str = "https://api.github.com/repos/EmiOnGit/gba/releases/latest";
+const RELEASES_PAGE: &str = "https://github.com/EmiOnGit/gba/releases";
+/// Automatic checks happen at most this often
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A release on GitHub
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    /// The changelog, as markdown
+    pub notes: String,
+    pub url: String,
+}
+
+/// Compares dotted version numbers like `v0.2.1`, missing parts count as 0
+pub fn is_newer(version: &str, current: &str) -> bool {
+    let parts = |v: &str| -> Vec<u32> {
+        v.trim_start_matches('v')
+            .split(['.', '-'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    let (version, current) = (parts(version), parts(current));
+    for i in 0..version.len().max(current.len()) {
+        let (a, b) = (
+            version.get(i).copied().unwrap_or(0),
+            current.get(i).copied().unwrap_or(0),
+        );
+        if a != b {
+            return a > b;
+        }
+    }
+    false
+}
+
+#[cfg(feature = "update-check")]
+fn fetch_latest() -> Result<Release, String> {
+    let body = ureq::get(LATEST_RELEASE)
+        .set("User-Agent", concat!("gba/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(10))
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    let json: serde_json::Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    let field = |name: &str| json[name].as_str().unwrap_or_default().to_string();
+    let version = field("tag_name");
+    if version.is_empty() {
+        return Err("the release has no version".to_string());
+    }
+    Ok(Release {
+        version,
+        notes: field("body"),
+        url: field("html_url"),
+    })
+}
+#[cfg(not(feature = "update-check"))]
+fn fetch_latest() -> Result<Release, String> {
+    Err("checking for updates needs the update-check feature".to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Asks GitHub for the latest release once a day if the user opted in,
+/// and shows its changelog when it is newer than this build
+pub struct UpdateCheck {
+    file: PathBuf,
+    enabled: bool,
+    /// Unix time of the last check
+    last_check: u64,
+    pending: Option<Receiver<Result<Release, String>>>,
+    /// A newer release, shown until the dialog is closed
+    release: Option<Release>,
+    /// Result of the last check without a newer release
+    outcome: Option<Result<(), String>>,
+}
+impl UpdateCheck {
+    /// Reads the settings from `file`: `enabled` and the time of the last check, one per line
+    pub fn load(file: PathBuf) -> Self {
+        let content = fs::read_to_string(&file).unwrap_or_default();
+        let mut lines = content.lines();
+        let enabled = lines.next() == Some("enabled");
+        let last_check = lines.next().and_then(|l| l.parse().ok()).unwrap_or(0);
+        UpdateCheck {
+            file,
+            enabled,
+            last_check,
+            pending: None,
+            release: None,
+            outcome: None,
+        }
+    }
+    fn save(&self) {
+        let enabled = if self.enabled { "enabled" } else { "disabled" };
+        if let Err(e) = fs::write(&self.file, format!("{enabled}\n{}\n", self.last_check)) {
+            log::warn!("could not save the update settings: {e}");
+        }
+    }
+    fn start(&mut self) {
+        self.last_check = now();
+        self.save();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(fetch_latest());
+        });
+        self.pending = Some(receiver);
+    }
+    /// Starts the automatic check when it is due and collects the result, call every frame
+    pub fn update(&mut self) {
+        let due = now().saturating_sub(self.last_check) >= CHECK_INTERVAL.as_secs();
+        if self.enabled && due && self.pending.is_none() {
+            self.start();
+        }
+        let Some(result) = self.pending.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return;
+        };
+        self.pending = None;
+        match result {
+            Ok(release) if is_newer(&release.version, env!("CARGO_PKG_VERSION")) => {
+                self.outcome = None;
+                self.release = Some(release);
+            }
+            Ok(_) => self.outcome = Some(Ok(())),
+            Err(e) => {
+                log::warn!("could not check for updates: {e}");
+                self.outcome = Some(Err(e));
+            }
+        }
+    }
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n) {
+        ui.label(i18n.tr_args("update-current", &[("version", env!("CARGO_PKG_VERSION"))]));
+        if ui
+            .checkbox(&mut self.enabled, i18n.tr("update-enabled"))
+            .on_hover_text(i18n.tr("update-privacy"))
+            .changed()
+        {
+            self.save();
+        }
+        let button = ui.add_enabled(
+            self.pending.is_none(),
+            egui::Button::new(i18n.tr("update-check-now")),
+        );
+        if button.clicked() {
+            self.start();
+        }
+        match &self.outcome {
+            Some(Ok(())) => {
+                ui.label(i18n.tr("update-up-to-date"));
+            }
+            Some(Err(e)) => {
+                ui.label(i18n.tr_args("update-failed", &[("error", e)]));
+            }
+            None => {}
+        }
+        ui.hyperlink_to(i18n.tr("update-all-releases"), RELEASES_PAGE);
+    }
+    /// The dialog for a newer release
+    pub fn show(&mut self, ctx: &egui::Context, i18n: &I18n) {
+        let Some(release) = &self.release else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new(i18n.tr("window-update"))
+            .id(egui::Id::new("update"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.heading(i18n.tr_args("update-available", &[("version", &release.version)]));
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .show(ui, |ui| ui.label(&release.notes));
+                ui.hyperlink_to(i18n.tr("update-download"), &release.url);
+            });
+        if !open {
+            self.release = None;
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::i18n::I18n;
+use eframe::{
+    egui::{self, TextureFilter, TextureHandle},
+    epaint::{vec2, ColorImage},
+};
+use gba::{
+    ram::Ram,
+    vram::{self, IndexedImage, BG_MAPS, LCDC, TILES_PER_ROW, TILE_DATA},
+};
+
+/// Pixels on screen per pixel of a tile sheet
+const TILE_SCALE: f32 = 2.;
+
+/// Which tiles the sheet shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TileView {
+    /// All of them in memory order
+    All,
+    /// Numbered from 0x8000
+    Unsigned,
+    /// Numbered from 0x9000, the numbers 128 and up from 0x8800
+    Signed,
+}
+impl TileView {
+    const ALL: [TileView; 3] = [TileView::All, TileView::Unsigned, TileView::Signed];
+    fn name(self) -> &'static str {
+        match self {
+            TileView::All => "vram-all",
+            TileView::Unsigned => "vram-8000",
+            TileView::Signed => "vram-8800",
+        }
+    }
+    /// The number and address of the tile at `index` in the sheet
+    fn tile(self, index: usize) -> (usize, u16) {
+        match self {
+            TileView::All => (index, TILE_DATA + index as u16 * 16),
+            TileView::Unsigned => (index, vram::tile_addr(0x10, index as u8)),
+            TileView::Signed => (index, vram::tile_addr(0, index as u8)),
+        }
+    }
+}
+
+/// Live view of the tile data and the two background maps
+pub struct VramViewer {
+    view: TileView,
+    tiles: Option<TextureHandle>,
+    maps: [Option<TextureHandle>; 2],
+}
+impl VramViewer {
+    pub fn ui(&mut self, ui: &mut egui::Ui, i18n: &I18n, ram: &Ram, palette: &[[u8; 3]; 4]) {
+        ui.horizontal(|ui| {
+            for view in TileView::ALL {
+                ui.radio_value(&mut self.view, view, i18n.tr(view.name()));
+            }
+        });
+        let sheet = match self.view {
+            TileView::All => vram::tile_sheet(ram),
+            TileView::Unsigned => vram::numbered_tiles(ram, false),
+            TileView::Signed => vram::numbered_tiles(ram, true),
+        };
+        let texture = upload(ui, &mut self.tiles, "vram_tiles", &sheet, palette);
+        let size = vec2(sheet.width as f32, sheet.height as f32) * TILE_SCALE;
+        let response = ui.image(texture.id(), size);
+        if let Some(pos) = response.hover_pos() {
+            let local = (pos - response.rect.min) / TILE_SCALE / 8.;
+            let index = local.y as usize * TILES_PER_ROW + local.x as usize;
+            let (number, addr) = self.view.tile(index);
+            response.on_hover_text_at_pointer(i18n.tr_args(
+                "vram-tile-tooltip",
+                &[
+                    ("number", &format!("{number:02X}")),
+                    ("addr", &format!("{addr:04X}")),
+                ],
+            ));
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            for (map, texture) in self.maps.iter_mut().enumerate() {
+                ui.vertical(|ui| {
+                    ui.label(
+                        i18n.tr_args("vram-map", &[("addr", &format!("{:04X}", BG_MAPS[map]))]),
+                    );
+                    let image = vram::bg_map(ram, map);
+                    let name = format!("vram_map_{map}");
+                    let texture = upload(ui, texture, &name, &image, palette);
+                    let response = ui.image(texture.id(), vec2(256., 256.));
+                    if let Some(pos) = response.hover_pos() {
+                        let local = (pos - response.rect.min) / 8.;
+                        let (x, y) = ((local.x as u16).min(31), (local.y as u16).min(31));
+                        let entry = BG_MAPS[map] + y * 32 + x;
+                        let number = ram[entry];
+                        let addr = vram::tile_addr(ram[LCDC], number);
+                        response.on_hover_text_at_pointer(i18n.tr_args(
+                            "vram-map-tooltip",
+                            &[
+                                ("x", &x.to_string()),
+                                ("y", &y.to_string()),
+                                ("entry", &format!("{entry:04X}")),
+                                ("number", &format!("{number:02X}")),
+                                ("addr", &format!("{addr:04X}")),
+                            ],
+                        ));
+                    }
+                });
+            }
+        });
+    }
+}
+impl Default for VramViewer {
+    fn default() -> Self {
+        VramViewer {
+            view: TileView::All,
+            tiles: None,
+            maps: [None, None],
+        }
+    }
+}
+/// Replaces the contents of the texture with the image, creating it the first time
+fn upload<'a>(
+    ui: &egui::Ui,
+    texture: &'a mut Option<TextureHandle>,
+    name: &str,
+    image: &IndexedImage,
+    palette: &[[u8; 3]; 4],
+) -> &'a TextureHandle {
+    let rgb: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|index| palette[*index as usize])
+        .collect();
+    let image = ColorImage::from_rgb([image.width, image.height], &rgb);
+    match texture {
+        Some(texture) => {
+            texture.set(image, TextureFilter::Nearest);
+            texture
+        }
+        None => texture.insert(ui.ctx().load_texture(name, image, TextureFilter::Nearest)),
+    }
+}
@@ -0,0 +1,127 @@
+use std::io::Write;
+
+use crate::{
+    bus::Bus,
+    cartridge::Cartridge,
+    cpu::{Cpu, CpuMode, CYCLES_PER_FRAME},
+    serial::{Capture, LinkPort},
+};
+
+/// Two minutes of emulated time, cpu_instrs needs about one
+pub const DEFAULT_CYCLE_LIMIT: u64 = 120 * 4_194_304;
+/// Mooneye test roms send the fibonacci numbers on success
+const MOONEYE_PASSED: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAILED: [u8; 6] = [0x42; 6];
+
+/// How a test rom run ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    /// The cycle limit was reached without a result
+    TimedOut,
+    /// The cpu executed an illegal opcode
+    Locked,
+    /// The cpu met an opcode it can not execute
+    Fault,
+}
+impl Outcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Passed => 0,
+            Outcome::Failed => 1,
+            Outcome::TimedOut => 2,
+            Outcome::Locked => 3,
+            Outcome::Fault => 4,
+        }
+    }
+}
+
+/// Looks for the result in the serial output, like Blargg's `Passed` and `Failed`
+pub fn outcome(output: &[u8]) -> Option<Outcome> {
+    let text = String::from_utf8_lossy(output);
+    if text.contains("Passed") || output.ends_with(&MOONEYE_PASSED) {
+        Some(Outcome::Passed)
+    } else if text.contains("Failed") || output.ends_with(&MOONEYE_FAILED) {
+        Some(Outcome::Failed)
+    } else {
+        None
+    }
+}
+
+/// Runs `cartridge` without a frontend until its serial output has a result or
+/// `cycle_limit` is reached. The boot rom is skipped, the serial output goes to `out`
+/// as it arrives.
+pub fn run(cartridge: Cartridge, cycle_limit: u64, out: &mut impl Write) -> Outcome {
+    let capture = Capture::default();
+    let bus = Bus::default()
+        .with_cartridge(cartridge)
+        .with_link_port(LinkPort::new(Box::new(capture.clone())));
+    let mut cpu = Cpu::new(bus);
+    cpu.skip_boot_rom();
+    let mut output = Vec::new();
+    let mut cycles = 0;
+    while cycles < cycle_limit {
+        cpu.step_frame();
+        cycles += CYCLES_PER_FRAME as u64;
+        let sent = capture.take();
+        if !sent.is_empty() {
+            let _ = out.write_all(&sent).and_then(|_| out.flush());
+            output.extend(sent);
+            if let Some(outcome) = outcome(&output) {
+                return outcome;
+            }
+        }
+        if let Some(error) = cpu.fault() {
+            log::error!("stopped by {error}");
+            return Outcome::Fault;
+        }
+        if let CpuMode::Locked { op } = cpu.mode() {
+            log::error!("locked up by the illegal opcode {op:#04x}");
+            return Outcome::Locked;
+        }
+    }
+    Outcome::TimedOut
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::Bus,
+        cartridge::Cartridge,
+        cpu::CYCLES_PER_FRAME,
+        serial::{Capture, LinkPort, SB, SC, TRANSFER_CYCLES},
+        testing::micro_rom,
+    };
+
+    use super::*;
+
+    #[test]
+    fn headless_results() -> Result<(), String> {
+        let capture = Capture::default();
+        let mut bus = Bus::default().with_link_port(LinkPort::new(Box::new(capture.clone())));
+        for byte in *b"Passed" {
+            bus.write_mem(SB, byte);
+            bus.write_mem(SC, 0x81);
+            bus.tick(TRANSFER_CYCLES);
+        }
+        let output = capture.take();
+        if outcome(&output) != Some(Outcome::Passed) {
+            return Err(format!("{output:?} did not pass"));
+        }
+        let fibonacci = outcome(&[3, 5, 8, 13, 21, 34]);
+        let failed = outcome(b"Failed #3");
+        if fibonacci != Some(Outcome::Passed) || failed != Some(Outcome::Failed) {
+            return Err(format!(
+                "the fibonacci numbers gave {fibonacci:?} and Failed #3 {failed:?}"
+            ));
+        }
+        let cartridge = Cartridge::from_bytes(micro_rom(&[0x76])).map_err(|e| e.to_string())?;
+        let mut out = Vec::new();
+        let outcome = run(cartridge, 10 * CYCLES_PER_FRAME as u64, &mut out);
+        if outcome != Outcome::TimedOut || !out.is_empty() {
+            return Err(format!("a silent rom ended {outcome:?}"));
+        }
+        Ok(())
+    }
+}
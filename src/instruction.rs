@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::{
     bus::OpCode,
     cpu::{V16, V8},
@@ -10,11 +12,28 @@ pub enum AddressMove {
 impl AddressMove {
     pub fn apply(self, pc: u16) -> u16 {
         match self {
-            AddressMove::Add(v) => pc + v,
+            AddressMove::Add(v) => pc.wrapping_add(v),
             AddressMove::To(v) => v,
         }
     }
 }
+/// An opcode the cpu can not execute. Instead of taking down the cpu thread,
+/// it pauses in the debugger before the instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Decoded to an instruction which does not cover the opcode, a bug in the decoding
+    Operands(u8),
+    /// Not implemented yet
+    Unimplemented(u8),
+}
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Operands(op) => write!(f, "opcode {op:#04x} was decoded wrongly"),
+            DecodeError::Unimplemented(op) => write!(f, "opcode {op:#04x} is not implemented"),
+        }
+    }
+}
 #[derive(Clone, Debug)]
 pub enum Instruction {
     Nop,
@@ -93,12 +112,13 @@ pub enum Instruction {
     LoadHlinSp,
     EnableInterrupts,
     TwoByteInstruction,
+    /// Opcodes without an instruction, they lock up the cpu
+    Illegal,
 }
 
 impl From<OpCode> for Instruction {
     fn from(op: OpCode) -> Self {
         let op = op.0;
-        println!("op {op:x}");
         let n0 = (op & 0xF0) >> 4; // first nibble of op
         let n1 = op & 0x0F; // second nibble of op
         match (n0, n1) {
@@ -214,7 +234,7 @@ impl From<OpCode> for Instruction {
             | (0xF, 0xC)
             | (0xF, 0xD)
             | (0xD, 0xD)
-            | (0xE, 0xD) => Self::Nop,
+            | (0xE, 0xD) => Self::Illegal,
             (0x10.., _) => panic!("cannot execute {op:x}"),
             (_, 0x10..) => panic!("cannot execute {op:x}"),
         }
@@ -225,3 +245,35 @@ pub struct Load16 {
     pub load_into: V16,
     pub from: (V8, V8),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::OpCode;
+
+    use super::*;
+
+    /// Every opcode decodes and exactly the 11 unused ones are illegal
+    #[test]
+    fn decode_table() -> Result<(), String> {
+        let illegal = (0..=0xFF)
+            .filter(|op| matches!(Instruction::from(OpCode(*op)), Instruction::Illegal))
+            .count();
+        if illegal != 11 {
+            return Err(format!("{illegal} illegal opcodes instead of 11"));
+        }
+        for (op, expected) in [
+            (0x00, "Nop"),
+            (0x10, "Stop"),
+            (0x76, "Halt"),
+            (0xCB, "TwoByteInstruction"),
+        ] {
+            let decoded = format!("{:?}", Instruction::from(OpCode(op)));
+            if decoded != expected {
+                return Err(format!(
+                    "{op:#04x} decodes to {decoded} instead of {expected}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::ram::Ram;
+
+/// Interrupt enable, a bit per interrupt like in IF
+pub const IE: u16 = 0xFFFF;
+/// Interrupt flag, a set bit requests the interrupt
+pub const IF: u16 = 0xFF0F;
+
+/// The interrupt sources, in the order of their priority
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    Stat,
+    Timer,
+    Serial,
+    Joypad,
+}
+impl Interrupt {
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::Stat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+    /// The bit in IE and IF
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+    /// Where the cpu jumps to when handling the interrupt
+    pub fn vector(self) -> u16 {
+        0x40 + 8 * self as u16
+    }
+}
+/// Sets the bit of the interrupt in IF
+pub fn request(ram: &mut Ram, interrupt: Interrupt) {
+    ram[IF] |= interrupt.bit();
+}
+/// The requested and enabled interrupt with the highest priority
+pub fn pending(ram: &Ram) -> Option<Interrupt> {
+    let pending = ram[IE] & ram[IF];
+    Interrupt::ALL
+        .into_iter()
+        .find(|interrupt| pending & interrupt.bit() != 0)
+}
+/// Clears the bit of the interrupt in IF, when the cpu starts handling it
+pub fn acknowledge(ram: &mut Ram, interrupt: Interrupt) {
+    ram[IF] &= !interrupt.bit();
+}
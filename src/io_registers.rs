@@ -0,0 +1,351 @@
+use crate::{
+    audio::{
+        NR10, NR11, NR12, NR13, NR14, NR21, NR22, NR23, NR24, NR30, NR31, NR32, NR33, NR34, NR41,
+        NR42, NR43, NR44, NR50, NR51, NR52,
+    },
+    bus::{BOOT_ROM_DISABLE, DMA},
+    cgb::{BCPS, KEY1, OCPS, SVBK, VBK},
+    interrupt::{IE, IF},
+    joypad::P1,
+    ppu::{LY, LYC, OBP0, OBP1, SCX, SCY, STAT, WX, WY},
+    serial::{SB, SC},
+    timer::{DIV, TAC, TIMA, TMA},
+    vram::{BGP, LCDC},
+};
+
+/// Bits of a register which belong together
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub name: &'static str,
+    /// The lowest bit
+    pub shift: u8,
+    pub width: u8,
+}
+impl Field {
+    pub fn get(self, value: u8) -> u8 {
+        (value >> self.shift) & self.mask()
+    }
+    /// `value` with the field replaced by `field`
+    pub fn set(self, value: u8, field: u8) -> u8 {
+        value & !(self.mask() << self.shift) | (field & self.mask()) << self.shift
+    }
+    fn mask(self) -> u8 {
+        (0xFFu16 >> (8 - self.width)) as u8
+    }
+    /// `7` for a single bit, `0-1` for more
+    pub fn bits(self) -> String {
+        if self.width == 1 {
+            self.shift.to_string()
+        } else {
+            format!("{}-{}", self.shift, self.shift + self.width - 1)
+        }
+    }
+}
+const fn bit(shift: u8, name: &'static str) -> Field {
+    Field {
+        name,
+        shift,
+        width: 1,
+    }
+}
+const fn bits(shift: u8, width: u8, name: &'static str) -> Field {
+    Field { name, shift, width }
+}
+
+/// The part of the hardware a register belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Group {
+    Joypad,
+    Serial,
+    Timer,
+    Interrupts,
+    Sound,
+    Lcd,
+    Cgb,
+    System,
+}
+impl Group {
+    pub const ALL: [Group; 8] = [
+        Group::Joypad,
+        Group::Serial,
+        Group::Timer,
+        Group::Interrupts,
+        Group::Sound,
+        Group::Lcd,
+        Group::Cgb,
+        Group::System,
+    ];
+}
+
+/// A hardware register with the meaning of its bits
+#[derive(Clone, Copy, Debug)]
+pub struct IoRegister {
+    pub name: &'static str,
+    pub addr: u16,
+    pub group: Group,
+    pub fields: &'static [Field],
+}
+const fn register(
+    name: &'static str,
+    addr: u16,
+    group: Group,
+    fields: &'static [Field],
+) -> IoRegister {
+    IoRegister {
+        name,
+        addr,
+        group,
+        fields,
+    }
+}
+
+const INTERRUPTS: &[Field] = &[
+    bit(4, "joypad"),
+    bit(3, "serial"),
+    bit(2, "timer"),
+    bit(1, "stat"),
+    bit(0, "vblank"),
+];
+const DUTY_LENGTH: &[Field] = &[bits(6, 2, "duty"), bits(0, 6, "length")];
+const ENVELOPE: &[Field] = &[
+    bits(4, 4, "initial volume"),
+    bit(3, "envelope up"),
+    bits(0, 3, "envelope pace"),
+];
+const PERIOD_LOW: &[Field] = &[bits(0, 8, "period low")];
+const CONTROL: &[Field] = &[
+    bit(7, "trigger"),
+    bit(6, "length enable"),
+    bits(0, 3, "period high"),
+];
+const PALETTE: &[Field] = &[
+    bits(6, 2, "color 3"),
+    bits(4, 2, "color 2"),
+    bits(2, 2, "color 1"),
+    bits(0, 2, "color 0"),
+];
+const PALETTE_INDEX: &[Field] = &[bit(7, "auto increment"), bits(0, 6, "index")];
+const BYTE: &[Field] = &[bits(0, 8, "value")];
+
+/// The registers from P1 to IE in address order
+pub const IO_REGISTERS: &[IoRegister] = &[
+    register(
+        "P1",
+        P1,
+        Group::Joypad,
+        &[
+            bit(5, "select buttons (0 = selected)"),
+            bit(4, "select d-pad (0 = selected)"),
+            bits(0, 4, "inputs (0 = pressed)"),
+        ],
+    ),
+    register("SB", SB, Group::Serial, &[bits(0, 8, "data")]),
+    register(
+        "SC",
+        SC,
+        Group::Serial,
+        &[
+            bit(7, "transfer"),
+            bit(1, "fast clock (CGB)"),
+            bit(0, "internal clock"),
+        ],
+    ),
+    register("DIV", DIV, Group::Timer, BYTE),
+    register("TIMA", TIMA, Group::Timer, BYTE),
+    register("TMA", TMA, Group::Timer, &[bits(0, 8, "modulo")]),
+    register(
+        "TAC",
+        TAC,
+        Group::Timer,
+        &[
+            bit(2, "enable"),
+            bits(
+                0,
+                2,
+                "clock (0 = 4096 Hz, 1 = 262144, 2 = 65536, 3 = 16384)",
+            ),
+        ],
+    ),
+    register("IF", IF, Group::Interrupts, INTERRUPTS),
+    register(
+        "NR10",
+        NR10,
+        Group::Sound,
+        &[
+            bits(4, 3, "sweep pace"),
+            bit(3, "sweep down"),
+            bits(0, 3, "sweep step"),
+        ],
+    ),
+    register("NR11", NR11, Group::Sound, DUTY_LENGTH),
+    register("NR12", NR12, Group::Sound, ENVELOPE),
+    register("NR13", NR13, Group::Sound, PERIOD_LOW),
+    register("NR14", NR14, Group::Sound, CONTROL),
+    register("NR21", NR21, Group::Sound, DUTY_LENGTH),
+    register("NR22", NR22, Group::Sound, ENVELOPE),
+    register("NR23", NR23, Group::Sound, PERIOD_LOW),
+    register("NR24", NR24, Group::Sound, CONTROL),
+    register("NR30", NR30, Group::Sound, &[bit(7, "dac enable")]),
+    register("NR31", NR31, Group::Sound, &[bits(0, 8, "length")]),
+    register(
+        "NR32",
+        NR32,
+        Group::Sound,
+        &[bits(
+            5,
+            2,
+            "output level (0 = mute, 1 = 100%, 2 = 50%, 3 = 25%)",
+        )],
+    ),
+    register("NR33", NR33, Group::Sound, PERIOD_LOW),
+    register("NR34", NR34, Group::Sound, CONTROL),
+    register("NR41", NR41, Group::Sound, &[bits(0, 6, "length")]),
+    register("NR42", NR42, Group::Sound, ENVELOPE),
+    register(
+        "NR43",
+        NR43,
+        Group::Sound,
+        &[
+            bits(4, 4, "clock shift"),
+            bit(3, "7 bit lfsr"),
+            bits(0, 3, "clock divider"),
+        ],
+    ),
+    register(
+        "NR44",
+        NR44,
+        Group::Sound,
+        &[bit(7, "trigger"), bit(6, "length enable")],
+    ),
+    register(
+        "NR50",
+        NR50,
+        Group::Sound,
+        &[
+            bit(7, "vin left"),
+            bits(4, 3, "left volume"),
+            bit(3, "vin right"),
+            bits(0, 3, "right volume"),
+        ],
+    ),
+    register(
+        "NR51",
+        NR51,
+        Group::Sound,
+        &[
+            bit(7, "noise left"),
+            bit(6, "wave left"),
+            bit(5, "square 2 left"),
+            bit(4, "square 1 left"),
+            bit(3, "noise right"),
+            bit(2, "wave right"),
+            bit(1, "square 2 right"),
+            bit(0, "square 1 right"),
+        ],
+    ),
+    register(
+        "NR52",
+        NR52,
+        Group::Sound,
+        &[
+            bit(7, "sound on"),
+            bit(3, "noise on"),
+            bit(2, "wave on"),
+            bit(1, "square 2 on"),
+            bit(0, "square 1 on"),
+        ],
+    ),
+    register(
+        "LCDC",
+        LCDC,
+        Group::Lcd,
+        &[
+            bit(7, "lcd enable"),
+            bit(6, "window map (1 = 9C00)"),
+            bit(5, "window enable"),
+            bit(4, "tile data (1 = 8000)"),
+            bit(3, "background map (1 = 9C00)"),
+            bit(2, "sprite size (1 = 8x16)"),
+            bit(1, "sprites enable"),
+            bit(0, "background enable"),
+        ],
+    ),
+    register(
+        "STAT",
+        STAT,
+        Group::Lcd,
+        &[
+            bit(6, "lyc interrupt"),
+            bit(5, "oam scan interrupt"),
+            bit(4, "vblank interrupt"),
+            bit(3, "hblank interrupt"),
+            bit(2, "ly = lyc"),
+            bits(
+                0,
+                2,
+                "mode (0 = hblank, 1 = vblank, 2 = oam scan, 3 = drawing)",
+            ),
+        ],
+    ),
+    register("SCY", SCY, Group::Lcd, BYTE),
+    register("SCX", SCX, Group::Lcd, BYTE),
+    register("LY", LY, Group::Lcd, BYTE),
+    register("LYC", LYC, Group::Lcd, BYTE),
+    register("DMA", DMA, Group::Lcd, &[bits(0, 8, "source high byte")]),
+    register("BGP", BGP, Group::Lcd, PALETTE),
+    register("OBP0", OBP0, Group::Lcd, PALETTE),
+    register("OBP1", OBP1, Group::Lcd, PALETTE),
+    register("WY", WY, Group::Lcd, BYTE),
+    register("WX", WX, Group::Lcd, BYTE),
+    register(
+        "KEY1",
+        KEY1,
+        Group::Cgb,
+        &[bit(7, "double speed"), bit(0, "switch armed")],
+    ),
+    register("VBK", VBK, Group::Cgb, &[bit(0, "vram bank")]),
+    register(
+        "BOOT",
+        BOOT_ROM_DISABLE,
+        Group::System,
+        &[bit(0, "boot rom unmapped")],
+    ),
+    register("BCPS", BCPS, Group::Cgb, PALETTE_INDEX),
+    register("OCPS", OCPS, Group::Cgb, PALETTE_INDEX),
+    register("SVBK", SVBK, Group::Cgb, &[bits(0, 3, "wram bank")]),
+    register("IE", IE, Group::Interrupts, INTERRUPTS),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The register table is in address order and its fields cover distinct bits
+    #[test]
+    fn io_register_fields() -> Result<(), String> {
+        for pair in IO_REGISTERS.windows(2) {
+            if pair[0].addr >= pair[1].addr {
+                return Err(format!("{} is not before {}", pair[0].name, pair[1].name));
+            }
+        }
+        for register in IO_REGISTERS {
+            let mut used = 0u8;
+            for field in register.fields {
+                let bits = field.set(0, 0xFF);
+                if used & bits != 0 || field.get(bits) != bits >> field.shift {
+                    return Err(format!("{}.{} overlaps", register.name, field.bits()));
+                }
+                used |= bits;
+            }
+        }
+        let lcdc = IO_REGISTERS
+            .iter()
+            .find(|r| r.name == "LCDC")
+            .ok_or("no LCDC")?;
+        let enable = lcdc.fields[0];
+        if enable.get(0x91) != 1 || enable.set(0x91, 0) != 0x11 {
+            return Err("LCDC.7 decodes wrong".to_string());
+        }
+        Ok(())
+    }
+}
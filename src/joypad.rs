@@ -0,0 +1,102 @@
+use crate::{
+    frontend::Buttons,
+    interrupt::{self, Interrupt},
+    ram::Ram,
+};
+
+/// P1/JOYP, bits 4 and 5 select the direction or action buttons, bits 0-3 read them
+pub const P1: u16 = 0xFF00;
+/// Cleared to select the directions
+const SELECT_DIRECTIONS: u8 = 0x10;
+/// Cleared to select A, B, Select and Start
+const SELECT_ACTIONS: u8 = 0x20;
+/// Bits 6 and 7 are unused and read as 1
+const UNUSED: u8 = 0xC0;
+
+/// The button matrix behind P1
+#[derive(Clone, Debug, Default)]
+pub struct Joypad {
+    held: Buttons,
+    /// The joypad the SGB selected, `None` without more than one
+    player: Option<u8>,
+}
+impl Joypad {
+    /// Stores the buttons held now and requests the joypad interrupt
+    /// when a selected line goes low
+    pub fn update(&mut self, ram: &mut Ram, held: Buttons) {
+        self.held = held;
+        self.refresh(ram);
+    }
+    /// Called after a write to P1, only the select bits are writable
+    pub fn write(&mut self, ram: &mut Ram, before: u8) {
+        let select = ram[P1] & (SELECT_DIRECTIONS | SELECT_ACTIONS);
+        ram[P1] = before & !(SELECT_DIRECTIONS | SELECT_ACTIONS) | select;
+        self.refresh(ram);
+    }
+    /// Set by the SGB after every write to P1
+    pub fn set_player(&mut self, player: Option<u8>) {
+        self.player = player;
+    }
+    fn refresh(&self, ram: &mut Ram) {
+        let before = ram[P1];
+        let select = before & (SELECT_DIRECTIONS | SELECT_ACTIONS);
+        // with both rows deselected the SGB shows the id of the joypad, 0xF for the first
+        if let (Some(player), 0x30) = (self.player, select) {
+            ram[P1] = UNUSED | select | (0x0F - player);
+            return;
+        }
+        // the other joypads are not connected
+        let connected = self.player.unwrap_or(0) == 0;
+        let mut pressed = 0;
+        if connected && select & SELECT_DIRECTIONS == 0 {
+            pressed |= self.held.0 & 0x0F;
+        }
+        if connected && select & SELECT_ACTIONS == 0 {
+            pressed |= self.held.0 >> 4;
+        }
+        // the lines are pulled low by a pressed button
+        let lines = !pressed & 0x0F;
+        ram[P1] = UNUSED | select | lines;
+        if before & !lines & 0x0F != 0 {
+            interrupt::request(ram, Interrupt::Joypad);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::Bus,
+        frontend::{Buttons, SharedButtons},
+        interrupt::{Interrupt, IF},
+    };
+
+    use super::*;
+
+    /// Reads both halves of the matrix and checks that a press requests the interrupt
+    #[test]
+    fn joypad_matrix() -> Result<(), String> {
+        let buttons = SharedButtons::default();
+        let mut bus = Bus::default().with_input(buttons.clone());
+        buttons.set(Buttons(Buttons::LEFT | Buttons::START));
+        bus.write_mem(P1, 0x20);
+        bus.tick(1);
+        let directions = bus.fetch(P1);
+        if directions != 0xED {
+            return Err(format!("P1 reads {directions:#04x} with left held"));
+        }
+        if bus.fetch(IF) & Interrupt::Joypad.bit() == 0 {
+            return Err("pressing left did not request the joypad interrupt".to_string());
+        }
+        bus.write_mem(P1, 0x10);
+        let actions = bus.fetch(P1);
+        if actions != 0xD7 {
+            return Err(format!("P1 reads {actions:#04x} with start held"));
+        }
+        bus.write_mem(P1, 0x30);
+        if bus.fetch(P1) != 0xFF {
+            return Err("P1 reads buttons with nothing selected".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+/// What the ppu draws a pixel from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Background,
+    Window,
+    Sprites,
+}
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Background, Layer::Window, Layer::Sprites];
+    /// The i18n key of the layer name
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Background => "layer-background",
+            Layer::Window => "layer-window",
+            Layer::Sprites => "layer-sprites",
+        }
+    }
+    fn hidden_bit(self) -> u8 {
+        1 << self as u8
+    }
+    fn tinted_bit(self) -> u8 {
+        0x10 << self as u8
+    }
+    /// 15 bit RGB the pixels of the layer are mixed with
+    fn tint(self) -> u16 {
+        match self {
+            Layer::Background => 0x001F,
+            Layer::Window => 0x03E0,
+            Layer::Sprites => 0x7C00,
+        }
+    }
+}
+
+/// The layers hidden or tinted in the debugger at one point, hidden in bits 0-2
+/// and tinted in bits 4-6
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayerMask(u8);
+impl LayerMask {
+    pub fn is_visible(self, layer: Layer) -> bool {
+        self.0 & layer.hidden_bit() == 0
+    }
+    pub fn is_tinted(self, layer: Layer) -> bool {
+        self.0 & layer.tinted_bit() != 0
+    }
+    /// The frame is sent in color then, also on the DMG
+    pub fn any_tinted(self) -> bool {
+        self.0 & 0x70 != 0
+    }
+    /// `color` of a pixel from `layer`, half mixed with the tint of the layer if it is tinted
+    pub fn color(self, color: u16, layer: Layer) -> u16 {
+        if !self.is_tinted(layer) {
+            return color;
+        }
+        let tint = layer.tint();
+        [0, 5, 10].into_iter().fold(0, |mixed, shift| {
+            let channel = ((color >> shift & 0x1F) + (tint >> shift & 0x1F)) / 2;
+            mixed | channel << shift
+        })
+    }
+}
+
+/// Hides or tints the layers to see which one a glitch comes from.
+/// Shared between the gui and the ppu.
+#[derive(Clone, Debug, Default)]
+pub struct Layers(Arc<AtomicU8>);
+impl Layers {
+    /// Read once per line by the ppu
+    pub fn get(&self) -> LayerMask {
+        LayerMask(self.0.load(Ordering::Relaxed))
+    }
+    pub fn set_visible(&self, layer: Layer, visible: bool) {
+        self.set_bit(layer.hidden_bit(), !visible);
+    }
+    pub fn set_tinted(&self, layer: Layer, tinted: bool) {
+        self.set_bit(layer.tinted_bit(), tinted);
+    }
+    fn set_bit(&self, bit: u8, set: bool) {
+        if set {
+            self.0.fetch_or(bit, Ordering::Relaxed);
+        } else {
+            self.0.fetch_and(!bit, Ordering::Relaxed);
+        }
+    }
+}
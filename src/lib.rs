@@ -0,0 +1,72 @@
+// The emulation core, without a window. Frontends build a `Bus` with their
+// `VideoSink`, `AudioSink` and `InputSource`, hand it to a `Cpu` and call
+// `Cpu::step_frame` whenever they want the next frame.
+
+pub mod annotations;
+pub mod audio;
+pub mod backup;
+pub mod banked;
+pub mod bench;
+pub mod boot_rom;
+pub mod bus;
+pub mod call_stack;
+pub mod camera;
+pub mod cartridge;
+pub mod cgb;
+pub mod channels;
+pub mod cheats;
+pub mod cpu;
+pub mod crash;
+pub mod debugger;
+pub mod determinism;
+pub mod disasm;
+pub mod frontend;
+pub mod fuzz;
+pub mod headless;
+pub mod instruction;
+pub mod interrupt;
+pub mod io_registers;
+pub mod joypad;
+pub mod layers;
+pub mod link;
+pub mod logging;
+pub mod mbc;
+pub mod movie;
+pub mod opcodes;
+pub mod pacing;
+pub mod perf;
+pub mod patch;
+pub mod paths;
+pub mod power;
+pub mod ppu;
+pub mod practice;
+pub mod printer;
+pub mod protocol;
+pub mod ram;
+pub mod rewind;
+pub mod rtc;
+pub mod script;
+pub mod selftest;
+pub mod serial;
+pub mod sgb;
+pub mod single_step;
+pub mod slots;
+pub mod speed;
+pub mod sram;
+pub mod state;
+pub mod status;
+pub mod symbols;
+#[cfg(test)]
+mod testing;
+pub mod timer;
+pub mod trace;
+pub mod trade;
+pub mod vram;
+pub mod watch;
+
+pub use audio::Audio as Apu;
+pub use bus::Bus;
+pub use cartridge::Cartridge;
+pub use cpu::Cpu;
+pub use frontend::{AudioSink, Buttons, DrawSignal, FrameCallback, InputSource, Screen, VideoSink};
+pub use ppu::Ppu;
@@ -0,0 +1,459 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::Sender,
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::serial::SerialDevice;
+
+/// Bumped whenever a message changes, both sides have to speak the same version
+pub const LINK_VERSION: u16 = 1;
+/// The port hosting listens on and joining connects to without one given
+pub const DEFAULT_PORT: u16 = 5738;
+/// Starts the connection, followed by `LINK_VERSION`
+const HELLO: &[u8; 4] = b"GBLK";
+/// The game with the internal clock waits this long for the answer, afterwards the
+/// transfer receives 0xFF like without a cable. The other side sleeps between frames,
+/// so this has to be more than a frame.
+const TIMEOUT: Duration = Duration::from_millis(50);
+/// How often hosting checks whether it was given up while waiting
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// What goes over the cable. The side with the internal clock sends a `Transfer`,
+/// the other side answers with a `Reply` carrying its own byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkMessage {
+    Transfer { seq: u16, byte: u8 },
+    Reply { seq: u16, byte: u8 },
+}
+impl LinkMessage {
+    pub fn encode(self) -> [u8; 4] {
+        let (tag, seq, byte) = match self {
+            LinkMessage::Transfer { seq, byte } => (0, seq, byte),
+            LinkMessage::Reply { seq, byte } => (1, seq, byte),
+        };
+        let [lo, hi] = seq.to_le_bytes();
+        [tag, lo, hi, byte]
+    }
+    pub fn decode(bytes: [u8; 4]) -> Option<Self> {
+        let seq = u16::from_le_bytes([bytes[1], bytes[2]]);
+        let byte = bytes[3];
+        match bytes[0] {
+            0 => Some(LinkMessage::Transfer { seq, byte }),
+            1 => Some(LinkMessage::Reply { seq, byte }),
+            _ => None,
+        }
+    }
+}
+
+/// How far the connection got, shown in the link port panel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkStatus {
+    /// Hosting, waiting for the other side to join on this port
+    Waiting(u16),
+    Connecting(String),
+    Connected(SocketAddr),
+    /// To another instance in the same process
+    Local,
+    Failed(String),
+    /// The other side left
+    Closed,
+}
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkStatus::Waiting(port) => write!(f, "waiting on port {port}"),
+            LinkStatus::Connecting(addr) => write!(f, "connecting to {addr}"),
+            LinkStatus::Connected(addr) => write!(f, "connected to {addr}"),
+            LinkStatus::Local => write!(f, "connected to the other game"),
+            LinkStatus::Failed(e) => write!(f, "failed: {e}"),
+            LinkStatus::Closed => write!(f, "the other side left"),
+        }
+    }
+}
+
+/// The status of a `NetworkLink`, shared with the gui
+#[derive(Clone, Debug)]
+pub struct LinkState(Arc<Mutex<LinkStatus>>);
+impl LinkState {
+    pub fn get(&self) -> LinkStatus {
+        self.0.lock().unwrap().clone()
+    }
+    fn set(&self, status: LinkStatus) {
+        log::info!("link cable {status}");
+        *self.0.lock().unwrap() = status;
+    }
+}
+
+/// Where the messages to the other side go
+enum Outgoing {
+    Tcp(TcpStream),
+    /// Straight to the other instance in the same process
+    Local(Sender<(Instant, LinkMessage)>),
+}
+
+/// An open connection, the messages are read on their own thread
+struct Connection {
+    outgoing: Outgoing,
+    /// Received messages with the time they arrived
+    incoming: Receiver<(Instant, LinkMessage)>,
+}
+impl Connection {
+    fn send(&mut self, message: LinkMessage) -> io::Result<()> {
+        match &mut self.outgoing {
+            Outgoing::Tcp(stream) => stream.write_all(&message.encode()),
+            Outgoing::Local(sender) => sender
+                .send((Instant::now(), message))
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the other game stopped")),
+        }
+    }
+}
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // the reader thread holds a clone of the stream, which would keep it open
+        if let Outgoing::Tcp(stream) = &self.outgoing {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// A link cable to another instance over TCP. Until the other side connected
+/// it behaves like no cable.
+pub struct NetworkLink {
+    state: LinkState,
+    /// Delivers the connection once it is established
+    pending: Option<Receiver<Connection>>,
+    connection: Option<Connection>,
+    /// Numbers the transfers, so late replies to a timed out one are dropped
+    seq: u16,
+    /// The transfer waiting for its reply, with when it gives up
+    waiting: Option<(u16, Instant)>,
+    /// Hosting stops waiting once this is dropped with the link
+    _alive: Arc<()>,
+}
+impl NetworkLink {
+    /// Waits for another instance to join on `port`
+    pub fn host(port: u16) -> Self {
+        Self::connect(LinkStatus::Waiting(port), move |alive| {
+            let listener = TcpListener::bind(("0.0.0.0", port))?;
+            listener.set_nonblocking(true)?;
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        stream.set_nonblocking(false)?;
+                        return Ok(stream);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        if alive.upgrade().is_none() {
+                            return Err(io::Error::new(io::ErrorKind::Interrupted, "unplugged"));
+                        }
+                        thread::sleep(ACCEPT_POLL);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        })
+    }
+    /// Connects to an instance which hosts, `addr` is `host:port` or only the host
+    pub fn join(addr: &str) -> Self {
+        let addr = if addr.contains(':') {
+            addr.to_string()
+        } else {
+            format!("{addr}:{DEFAULT_PORT}")
+        };
+        Self::connect(LinkStatus::Connecting(addr.clone()), move |_| {
+            TcpStream::connect(&addr)
+        })
+    }
+    fn connect(
+        status: LinkStatus,
+        open: impl FnOnce(Weak<()>) -> io::Result<TcpStream> + Send + 'static,
+    ) -> Self {
+        let state = LinkState(Arc::new(Mutex::new(status)));
+        let (sender, pending) = mpsc::channel();
+        let thread_state = state.clone();
+        let alive = Arc::new(());
+        let weak = Arc::downgrade(&alive);
+        thread::spawn(move || {
+            match open(weak).and_then(|stream| handshake(stream, thread_state.clone())) {
+                Ok(connection) => {
+                    let _ = sender.send(connection);
+                }
+                Err(e) => thread_state.set(LinkStatus::Failed(e.to_string())),
+            }
+        });
+        NetworkLink {
+            state,
+            pending: Some(pending),
+            connection: None,
+            seq: 0,
+            waiting: None,
+            _alive: alive,
+        }
+    }
+    /// Both ends of a cable between two instances in the same process
+    pub fn pair() -> (Self, Self) {
+        let (to_second, from_first) = mpsc::channel();
+        let (to_first, from_second) = mpsc::channel();
+        let end = |outgoing, incoming| NetworkLink {
+            state: LinkState(Arc::new(Mutex::new(LinkStatus::Local))),
+            pending: None,
+            connection: Some(Connection {
+                outgoing: Outgoing::Local(outgoing),
+                incoming,
+            }),
+            seq: 0,
+            waiting: None,
+            _alive: Arc::new(()),
+        };
+        (end(to_second, from_second), end(to_first, from_first))
+    }
+    pub fn state(&self) -> LinkState {
+        self.state.clone()
+    }
+    /// The connection if it is open by now
+    fn connection(&mut self) -> Option<&mut Connection> {
+        if let Some(connection) = self.pending.as_ref().and_then(|p| p.try_recv().ok()) {
+            self.connection = Some(connection);
+            self.pending = None;
+        }
+        self.connection.as_mut()
+    }
+    fn send(&mut self, message: LinkMessage) {
+        let Some(connection) = self.connection() else {
+            return;
+        };
+        if let Err(e) = connection.send(message) {
+            self.state.set(LinkStatus::Failed(e.to_string()));
+            self.connection = None;
+        }
+    }
+}
+impl SerialDevice for NetworkLink {
+    fn name(&self) -> &str {
+        "network"
+    }
+    fn start(&mut self, sent: u8) {
+        self.waiting = None;
+        if self.connection().is_none() {
+            return;
+        }
+        self.seq = self.seq.wrapping_add(1);
+        let seq = self.seq;
+        self.send(LinkMessage::Transfer { seq, byte: sent });
+        self.waiting = Some((seq, Instant::now() + TIMEOUT));
+    }
+    fn finish(&mut self, sent: u8) -> Option<u8> {
+        let Some((seq, deadline)) = self.waiting else {
+            return Some(0xFF);
+        };
+        loop {
+            let Some(connection) = self.connection.as_ref() else {
+                self.waiting = None;
+                return Some(0xFF);
+            };
+            match connection.incoming.try_recv() {
+                Ok((_, LinkMessage::Reply { seq: reply, byte })) if reply == seq => {
+                    self.waiting = None;
+                    return Some(byte);
+                }
+                // a reply to a transfer which timed out
+                Ok((_, LinkMessage::Reply { .. })) => {}
+                // both sides use the internal clock, each gets the byte of the other
+                Ok((_, LinkMessage::Transfer { seq, .. })) => {
+                    self.send(LinkMessage::Reply { seq, byte: sent })
+                }
+                Err(TryRecvError::Empty) if Instant::now() < deadline => return None,
+                Err(TryRecvError::Empty) => {
+                    log::debug!("transfer {seq} was not answered in time");
+                    self.waiting = None;
+                    return Some(0xFF);
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.connection = None;
+                    self.waiting = None;
+                    return Some(0xFF);
+                }
+            }
+        }
+    }
+    fn poll(&mut self, sent: u8) -> Option<u8> {
+        loop {
+            let connection = self.connection()?;
+            match connection.incoming.try_recv() {
+                // the other side gave up on it already
+                Ok((arrived, LinkMessage::Transfer { .. })) if arrived.elapsed() > TIMEOUT => {}
+                Ok((_, LinkMessage::Transfer { seq, byte })) => {
+                    self.send(LinkMessage::Reply { seq, byte: sent });
+                    return Some(byte);
+                }
+                Ok((_, LinkMessage::Reply { .. })) => {}
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.connection = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+/// Exchanges the version and starts reading the messages
+fn handshake(mut stream: TcpStream, state: LinkState) -> io::Result<Connection> {
+    stream.set_nodelay(true)?;
+    stream.write_all(HELLO)?;
+    stream.write_all(&LINK_VERSION.to_le_bytes())?;
+    let mut hello = [0; 6];
+    stream.read_exact(&mut hello)?;
+    if &hello[..4] != HELLO {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the other side is not a link cable",
+        ));
+    }
+    let version = u16::from_le_bytes([hello[4], hello[5]]);
+    if version != LINK_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the other side speaks link version {version}, this is {LINK_VERSION}"),
+        ));
+    }
+    state.set(LinkStatus::Connected(stream.peer_addr()?));
+    let mut reader = stream.try_clone()?;
+    let (sender, incoming) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = [0; 4];
+        while reader.read_exact(&mut bytes).is_ok() {
+            match LinkMessage::decode(bytes) {
+                Some(message) => {
+                    if sender.send((Instant::now(), message)).is_err() {
+                        return;
+                    }
+                }
+                None => {
+                    log::error!("unknown link message {bytes:02x?}");
+                    break;
+                }
+            }
+        }
+        state.set(LinkStatus::Closed);
+    });
+    Ok(Connection {
+        outgoing: Outgoing::Tcp(stream),
+        incoming,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        bus::Bus,
+        serial::{LinkPort, SB, SC},
+    };
+
+    use super::*;
+
+    /// Two buses linked over localhost, one clocks the transfer and the other waits for it
+    #[test]
+    fn network_link() -> Result<(), String> {
+        let message = LinkMessage::Reply {
+            seq: 0x1234,
+            byte: 0x56,
+        };
+        if LinkMessage::decode(message.encode()) != Some(message) {
+            return Err("a link message did not survive encoding".to_string());
+        }
+        // a free port from the system
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map_err(|e| e.to_string())?
+            .port();
+        let host = NetworkLink::host(port);
+        let host_state = host.state();
+        let mut clocked = Bus::default().with_link_port(LinkPort::new(Box::new(host)));
+        let deadline = Instant::now() + Duration::from_secs(2);
+        // the listener has to be up before joining
+        while host_state.get() == LinkStatus::Waiting(port) && Instant::now() < deadline {
+            let joined = NetworkLink::join(&format!("127.0.0.1:{port}"));
+            let join_state = joined.state();
+            while matches!(join_state.get(), LinkStatus::Connecting(_)) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            if let LinkStatus::Connected(_) = join_state.get() {
+                let waiting = thread::spawn(move || {
+                    let mut bus = Bus::default().with_link_port(LinkPort::new(Box::new(joined)));
+                    bus.write_mem(SB, 0x22);
+                    bus.write_mem(SC, 0x80);
+                    while bus.fetch(SC) & 0x80 != 0 && Instant::now() < deadline {
+                        bus.tick(4);
+                    }
+                    bus.fetch(SB)
+                });
+                while !matches!(host_state.get(), LinkStatus::Connected(_)) {
+                    if Instant::now() > deadline {
+                        return Err(format!("hosting is still {}", host_state.get()));
+                    }
+                    thread::sleep(Duration::from_millis(5));
+                }
+                clocked.write_mem(SB, 0x11);
+                clocked.write_mem(SC, 0x81);
+                while clocked.fetch(SC) & 0x80 != 0 && Instant::now() < deadline {
+                    clocked.tick(4);
+                }
+                let received = clocked.fetch(SB);
+                let other = waiting.join().map_err(|_| "the waiting side panicked")?;
+                if received != 0x22 || other != 0x11 {
+                    return Err(format!(
+                        "the sides received {received:#04x} and {other:#04x} instead of 0x22 and 0x11"
+                    ));
+                }
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        Err(format!(
+            "could not connect, hosting is {}",
+            host_state.get()
+        ))
+    }
+    /// Two games in the same process, linked without a socket
+    #[test]
+    fn local_link() -> Result<(), String> {
+        let (first, second) = NetworkLink::pair();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let waiting = thread::spawn(move || {
+            let mut bus = Bus::default().with_link_port(LinkPort::new(Box::new(second)));
+            bus.write_mem(SB, 0x22);
+            bus.write_mem(SC, 0x80);
+            while bus.fetch(SC) & 0x80 != 0 && Instant::now() < deadline {
+                bus.tick(4);
+            }
+            bus.fetch(SB)
+        });
+        let mut clocked = Bus::default().with_link_port(LinkPort::new(Box::new(first)));
+        clocked.write_mem(SB, 0x11);
+        clocked.write_mem(SC, 0x81);
+        while clocked.fetch(SC) & 0x80 != 0 && Instant::now() < deadline {
+            clocked.tick(4);
+        }
+        let received = clocked.fetch(SB);
+        let other = waiting.join().map_err(|_| "the waiting side panicked")?;
+        if received != 0x22 || other != 0x11 {
+            return Err(format!(
+                "the games received {received:#04x} and {other:#04x} instead of 0x22 and 0x11"
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const LOG_FILE: &str = "gba.log";
+/// The log file is rotated once it grows past this size
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Number of rotated files kept next to the current one
+const KEEP_FILES: usize = 3;
+
+/// Parts of the emulator with their own log level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Cpu,
+    Ppu,
+    Apu,
+    Mbc,
+    Serial,
+    /// Gui and everything else, including the libraries
+    Other,
+}
+impl Subsystem {
+    pub const ALL: [Subsystem; 6] = [
+        Subsystem::Cpu,
+        Subsystem::Ppu,
+        Subsystem::Apu,
+        Subsystem::Mbc,
+        Subsystem::Serial,
+        Subsystem::Other,
+    ];
+    /// Maps a log target like `gba::cpu` or an explicit `target: "ppu"` to its subsystem
+    fn of_target(target: &str) -> Subsystem {
+        let module = target
+            .trim_start_matches("gba::")
+            .split("::")
+            .next()
+            .unwrap_or_default();
+        match module {
+            "cpu" | "instruction" => Subsystem::Cpu,
+            "ppu" | "vram" => Subsystem::Ppu,
+            "apu" | "audio" => Subsystem::Apu,
+            "mbc" | "cartridge" => Subsystem::Mbc,
+            "serial" => Subsystem::Serial,
+            _ => Subsystem::Other,
+        }
+    }
+}
+
+static LOGGER: Logger = Logger {
+    levels: [
+        AtomicUsize::new(LevelFilter::Warn as usize),
+        AtomicUsize::new(LevelFilter::Warn as usize),
+        AtomicUsize::new(LevelFilter::Warn as usize),
+        AtomicUsize::new(LevelFilter::Warn as usize),
+        AtomicUsize::new(LevelFilter::Warn as usize),
+        AtomicUsize::new(LevelFilter::Warn as usize),
+    ],
+    file: Mutex::new(None),
+};
+
+struct Logger {
+    /// LevelFilter per subsystem, in the order of Subsystem::ALL
+    levels: [AtomicUsize; 6],
+    file: Mutex<Option<LogFile>>,
+}
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile { path, file, size })
+    }
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size + line.len() as u64 > MAX_FILE_SIZE {
+            rotate(&self.path)?;
+            *self = LogFile::open(self.path.clone())?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+/// Moves `gba.log` to `gba.log.1`, `gba.log.1` to `gba.log.2` and so on
+fn rotate(path: &Path) -> io::Result<()> {
+    let numbered = |i: usize| path.with_extension(format!("log.{i}"));
+    for i in (1..KEEP_FILES).rev() {
+        if numbered(i).exists() {
+            fs::rename(numbered(i), numbered(i + 1))?;
+        }
+    }
+    if path.exists() {
+        fs::rename(path, numbered(1))?;
+    }
+    Ok(())
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= level(Subsystem::of_target(metadata.target()))
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "[{:<5} {}] {}\n",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if record.level() <= Level::Warn {
+            eprint!("{line}");
+        }
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.write_line(&line);
+        }
+    }
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// Installs the logger, writing to `gba.log` in `dir`.
+/// The file of the previous run is rotated away.
+pub fn init(dir: &Path) {
+    let path = dir.join(LOG_FILE);
+    match rotate(&path).and_then(|_| LogFile::open(path)) {
+        Ok(file) => *LOGGER.file.lock().unwrap() = Some(file),
+        Err(e) => eprintln!("could not open log file: {e}"),
+    }
+    if log::set_logger(&LOGGER).is_ok() {
+        update_max_level();
+    }
+}
+pub fn level(subsystem: Subsystem) -> LevelFilter {
+    let index = Subsystem::ALL.iter().position(|s| *s == subsystem).unwrap();
+    match LOGGER.levels[index].load(Ordering::Relaxed) {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+pub fn set_level(subsystem: Subsystem, filter: LevelFilter) {
+    let index = Subsystem::ALL.iter().position(|s| *s == subsystem).unwrap();
+    LOGGER.levels[index].store(filter as usize, Ordering::Relaxed);
+    update_max_level();
+}
+/// Lets the log macros skip formatting for levels no subsystem wants
+fn update_max_level() {
+    let max = Subsystem::ALL.into_iter().map(level).max();
+    log::set_max_level(max.unwrap_or(LevelFilter::Off));
+}
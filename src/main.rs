@@ -1,15 +1,462 @@
-use gba::Gba;
+// The browser build only has the game screen, the command line is for the native one
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
 
-mod audio;
-mod bus;
-mod cpu;
-mod debugger;
-mod gba;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use affinity::ThreadTuning;
+#[cfg(not(target_arch = "wasm32"))]
+use app::Gba;
+use gba::{
+    bench,
+    cartridge::{Cartridge, Header},
+    frontend::Frontend,
+    fuzz, headless,
+    link::{self, NetworkLink},
+    opcodes,
+    paths::DataDirs,
+    ram::RamPattern,
+    selftest,
+    serial::{self, LinkPort, Scripted, SerialDevice},
+    single_step,
+    slots::SLOTS,
+    speed::Refresh,
+    trade::{self, TradePartner},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod affinity;
+#[cfg(not(target_arch = "wasm32"))]
+mod app;
+mod audio_out;
+#[cfg(target_arch = "wasm32")]
+#[path = "gpu/game_window.rs"]
+mod game_window;
+#[cfg(not(target_arch = "wasm32"))]
 mod gpu;
-mod instruction;
-mod ram;
+#[cfg(feature = "sdl")]
+mod sdl;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+const USAGE: &str = "\
+usage: gba [rom.gb] [options]
+
+  --rom <path>            the game to run, also the first argument without a flag
+  --patch <path>          apply this ips or bps patch, not the one next to the rom
+  --scale <n>             integer scale of the game screen
+  --sync native|60|audio  pace the frames at 59.73 Hz, 60 Hz or by the audio output
+  --bootrom <path>        the DMG boot rom, config/dmg_boot.bin by default
+  --no-bootrom            start the game right away, also without a boot rom
+  --savestate <slot>      load a save state slot after the first frame
+  --headless              run a test rom without a window, see --cycle-limit
+  --cycle-limit <cycles>  cycles a headless run takes at most
+  --bench <rom.gb>        run a rom as fast as possible and print the speed
+  --frames <n>            frames a benchmark runs, 3600 by default
+  --no-video              benchmark without sending the frames through a channel
+  --trace <file>          log every instruction in the Gameboy Doctor format
+  --script <file.rhai>    run a script on frames and memory accesses, needs the
+                          scripting feature
+  --frontend egui|sdl|tui sdl and tui need the features of the same name
+  --serial <device>       disconnected, loopback, printer, trade or a script file,
+                          host[:port] or join:<address> link two instances over tcp
+  --serial-stdout         print what the game sends through the link port
+  --link-rom <path>       run a second game in the same window, linked by cable
+  --ram-pattern <pattern> zero, ones, random or a seed for the power on ram
+  --deterministic         run without host randomness and wall clock
+  --seed <number>         deterministic from another seed
+  --watch                 reload the rom when the file changes
+  --portable              keep the data next to the executable
+  --<kind>-dir <path>     saves, states, screenshots, splits, config or logs
+                          somewhere else
+  --pin-core <index>      pin the emulation thread to a core
+  --high-priority         raise the priority of the emulation thread
+  --export-opcodes <path> write the opcode table as csv or json
+  --fuzz [iterations]     fuzz the mapper and the bus
+  --single-step <path>    run the SM83 json tests of a file or directory, needs the
+                          single-step feature
+  --selftest              run a quick check of the core
+  --help                  show this";
 
+#[cfg(target_arch = "wasm32")]
 fn main() {
-    let gba = Gba::default();
+    web::start();
+}
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let has = |flag: &str| args.iter().any(|arg| arg == flag);
+    if has("--help") || has("-h") {
+        println!("{USAGE}");
+        return;
+    }
+    if has("--selftest") {
+        process::exit(if selftest::run() { 0 } else { 1 });
+    }
+    if let Some(iterations) = fuzz_iterations(&args) {
+        let seed = seed(&args).unwrap_or(0);
+        match fuzz::run(iterations, seed) {
+            Ok(()) => println!("{iterations} iterations without a failure"),
+            Err(failure) => {
+                println!("{} failed: {}", failure.target, failure.message);
+                println!("input: {:02x?}", failure.input);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if has("--headless") {
+        process::exit(run_headless(&args));
+    }
+    if let Some(path) = value_of(&args, "--bench") {
+        process::exit(run_bench(&args, path.as_ref()));
+    }
+    if let Some(path) = value_of(&args, "--single-step") {
+        process::exit(run_single_step(path.as_ref()));
+    }
+    // a `.csv` path gets csv and anything else json
+    if let Some(path) = value_of(&args, "--export-opcodes") {
+        if let Err(e) = opcodes::export(path.as_ref()) {
+            eprintln!("could not export the opcodes to {path}: {e}");
+            process::exit(1);
+        }
+        return;
+    }
+    let dirs = data_dirs(&args);
+    let link_port = LinkPort::new(serial_device(&args, &dirs));
+    if has("--serial-stdout") {
+        link_port.output().set_stdout(true);
+    }
+    let mut gba = Gba::default()
+        .with_dirs(dirs)
+        .with_ram_pattern(ram_pattern(&args))
+        .with_link_port(link_port)
+        .with_deterministic(has("--deterministic"))
+        .with_watch(has("--watch"))
+        .with_skip_boot_rom(has("--no-bootrom"))
+        .with_frontend(frontend(&args))
+        .with_refresh(refresh(&args))
+        .with_thread_tuning(thread_tuning(&args));
+    if let Some(seed) = seed(&args) {
+        gba = gba.with_seed(seed);
+    }
+    if let Some(path) = rom_path(&args) {
+        gba = gba.with_rom(path);
+    }
+    if let Some(path) = value_of(&args, "--patch") {
+        gba = gba.with_patch(path.into());
+    }
+    if let Some(path) = value_of(&args, "--trace") {
+        gba = gba.with_trace(path.into());
+    }
+    if let Some(path) = value_of(&args, "--script") {
+        gba = gba.with_script(path.into());
+    }
+    if let Some(path) = value_of(&args, "--bootrom") {
+        gba = gba.with_boot_rom(path.into());
+    }
+    if let Some(scale) = scale(&args) {
+        gba = gba.with_scale(scale);
+    }
+    if let Some(slot) = state_slot(&args) {
+        gba = gba.with_state_slot(slot);
+    }
+    if let Some(path) = value_of(&args, "--link-rom") {
+        let second = Gba::new(Some(path.into()))
+            .with_dirs(data_dirs(&args))
+            .with_ram_pattern(ram_pattern(&args))
+            .with_skip_boot_rom(has("--no-bootrom"))
+            .with_refresh(refresh(&args));
+        gba = gba.with_second(second);
+    }
     pollster::block_on(gba.run());
-}
\ No newline at end of file
+}
+/// The argument after `flag`
+fn value_of<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let pos = args.iter().position(|arg| arg == flag)?;
+    args.get(pos + 1).map(String::as_str)
+}
+/// Builds the data directories from `--portable` and the `--<kind>-dir <path>` overrides
+fn data_dirs(args: &[String]) -> DataDirs {
+    let mut dirs = if args.iter().any(|arg| arg == "--portable") {
+        DataDirs::portable()
+    } else {
+        DataDirs::default()
+    };
+    for pair in args.windows(2) {
+        let path = PathBuf::from(&pair[1]);
+        dirs = match pair[0].as_str() {
+            "--saves-dir" => dirs.with_saves(path),
+            "--states-dir" => dirs.with_states(path),
+            "--screenshots-dir" => dirs.with_screenshots(path),
+            "--splits-dir" => dirs.with_splits(path),
+            "--config-dir" => dirs.with_config(path),
+            "--logs-dir" => dirs.with_logs(path),
+            _ => dirs,
+        };
+    }
+    dirs
+}
+/// Parses `--rom <path>`, or the first argument if it is not a flag
+fn rom_path(args: &[String]) -> Option<PathBuf> {
+    value_of(args, "--rom")
+        .or_else(|| args.first().map(String::as_str))
+        .filter(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+}
+/// Parses `--scale <n>`, from 1 to 8
+fn scale(args: &[String]) -> Option<usize> {
+    let scale = value_of(args, "--scale")?;
+    match scale.parse() {
+        Ok(scale @ 1..=8) => Some(scale),
+        _ => {
+            eprintln!("scale {scale} is not from 1 to 8, using the default");
+            None
+        }
+    }
+}
+/// Parses `--sync native|60|audio`
+fn refresh(args: &[String]) -> Refresh {
+    match value_of(args, "--sync") {
+        None => Refresh::default(),
+        Some("native") => Refresh::Native,
+        Some("60") => Refresh::Host60,
+        Some("audio") => Refresh::Audio,
+        Some(sync) => {
+            eprintln!("unknown sync {sync}, using native");
+            Refresh::Native
+        }
+    }
+}
+/// Parses `--savestate <slot>`
+fn state_slot(args: &[String]) -> Option<usize> {
+    let slot = value_of(args, "--savestate")?;
+    match slot.parse() {
+        Ok(slot) if slot < SLOTS => Some(slot),
+        _ => {
+            eprintln!(
+                "there is no save state slot {slot}, slots go from 0 to {}",
+                SLOTS - 1
+            );
+            None
+        }
+    }
+}
+/// Runs the test vectors and prints a line per file, returns the exit code
+fn run_single_step(path: &Path) -> i32 {
+    let results = match single_step::run(path) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("could not run the tests in {}: {e}", path.display());
+            return 2;
+        }
+    };
+    for (file, result) in &results {
+        match &result.first_failure {
+            None => println!("{file}: {} passed", result.total),
+            Some((name, e)) => println!(
+                "{file}: {}/{} passed, {name}: {e}",
+                result.passed, result.total
+            ),
+        }
+    }
+    let failed = results.iter().filter(|(_, r)| !r.passed_all()).count();
+    println!(
+        "{} of {} files passed",
+        results.len() - failed,
+        results.len()
+    );
+    if failed == 0 {
+        0
+    } else {
+        1
+    }
+}
+/// Runs the `--rom` as a test rom for `--headless`, up to `--cycle-limit <cycles>`,
+/// and returns the exit code
+fn run_headless(args: &[String]) -> i32 {
+    let Some(path) = rom_path(args) else {
+        eprintln!("--headless needs a --rom");
+        return 2;
+    };
+    let patch = value_of(args, "--patch").map(Path::new);
+    let cartridge = match Cartridge::load_patched(&path, patch) {
+        Ok(cartridge) => cartridge,
+        Err(e) => {
+            eprintln!("could not load rom {}: {e}", path.display());
+            return 2;
+        }
+    };
+    let cycle_limit = value_of(args, "--cycle-limit")
+        .and_then(|cycles| cycles.parse().ok())
+        .unwrap_or(headless::DEFAULT_CYCLE_LIMIT);
+    let outcome = headless::run(cartridge, cycle_limit, &mut io::stdout());
+    println!();
+    eprintln!("{outcome:?}");
+    outcome.exit_code()
+}
+/// Runs the `--bench` rom for `--frames <n>` as fast as possible and prints the report,
+/// returns the exit code
+fn run_bench(args: &[String], path: &Path) -> i32 {
+    let patch = value_of(args, "--patch").map(Path::new);
+    let cartridge = match Cartridge::load_patched(path, patch) {
+        Ok(cartridge) => cartridge,
+        Err(e) => {
+            eprintln!("could not load rom {}: {e}", path.display());
+            return 2;
+        }
+    };
+    let frames = value_of(args, "--frames")
+        .and_then(|frames| frames.parse().ok())
+        .unwrap_or(bench::DEFAULT_FRAMES);
+    let video = !args.iter().any(|arg| arg == "--no-video");
+    print!("{}", bench::run(cartridge, frames, video));
+    0
+}
+/// Parses `--fuzz [iterations]`, `--seed` picks other inputs
+fn fuzz_iterations(args: &[String]) -> Option<u64> {
+    if !args.iter().any(|arg| arg == "--fuzz") {
+        return None;
+    }
+    match value_of(args, "--fuzz").map(str::parse) {
+        Some(Ok(iterations)) => Some(iterations),
+        _ => Some(10_000),
+    }
+}
+/// Parses `--seed <number>`, which implies `--deterministic`
+fn seed(args: &[String]) -> Option<u64> {
+    let seed = value_of(args, "--seed")?;
+    match seed.parse() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            eprintln!("seed {seed} is not a number, using the default");
+            None
+        }
+    }
+}
+/// Parses `--ram-pattern zero|ones|random|<seed>`
+fn ram_pattern(args: &[String]) -> RamPattern {
+    match value_of(args, "--ram-pattern") {
+        None => RamPattern::default(),
+        Some("zero") => RamPattern::Zero,
+        Some("ones") => RamPattern::Ones,
+        Some("random") => RamPattern::Random,
+        Some(seed) => match seed.parse() {
+            Ok(seed) => RamPattern::Seeded(seed),
+            Err(_) => {
+                eprintln!("unknown ram pattern {seed}, using zero");
+                RamPattern::Zero
+            }
+        },
+    }
+}
+fn serial_device(args: &[String], dirs: &DataDirs) -> Box<dyn SerialDevice> {
+    let Some(arg) = value_of(args, "--serial") else {
+        return Box::new(serial::Disconnected);
+    };
+    if (arg == "trade" || arg.starts_with("trade:")) && !trade_supported(args) {
+        return Box::new(serial::Disconnected);
+    }
+    if let Some(device) = serial::device(arg, dirs) {
+        return device;
+    }
+    if arg == "host" {
+        return Box::new(NetworkLink::host(link::DEFAULT_PORT));
+    }
+    if let Some(port) = arg.strip_prefix("host:") {
+        return match port.parse() {
+            Ok(port) => Box::new(NetworkLink::host(port)),
+            Err(_) => {
+                eprintln!("{port} is not a port, using disconnected");
+                Box::new(serial::Disconnected)
+            }
+        };
+    }
+    if let Some(addr) = arg.strip_prefix("join:") {
+        return Box::new(NetworkLink::join(addr));
+    }
+    if let Some(path) = arg.strip_prefix("trade:") {
+        return match TradePartner::from_file(path.as_ref()) {
+            Ok(partner) => Box::new(partner),
+            Err(e) => {
+                eprintln!("could not read pokemon {path}: {e}, using disconnected");
+                Box::new(serial::Disconnected)
+            }
+        };
+    }
+    match Scripted::load(arg.as_ref()) {
+        Ok(script) => Box::new(script),
+        Err(e) => {
+            eprintln!("could not read serial script {arg}: {e}, using disconnected");
+            Box::new(serial::Disconnected)
+        }
+    }
+}
+/// Whether the fake trade partner knows the protocol of the `--rom`
+fn trade_supported(args: &[String]) -> bool {
+    let title = rom_path(args)
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|rom| Header::parse(&rom).ok())
+        .map(|header| header.title);
+    match title.map(|title| trade::check_game(&title)) {
+        Some(Err(e)) => {
+            eprintln!("{e}, using disconnected");
+            false
+        }
+        _ => true,
+    }
+}
+/// Parses `--pin-core <index>` and `--high-priority` for the emulation thread
+#[cfg(not(target_arch = "wasm32"))]
+fn thread_tuning(args: &[String]) -> ThreadTuning {
+    let core = value_of(args, "--pin-core").and_then(|core| match core.parse() {
+        Ok(core) => Some(core),
+        Err(_) => {
+            eprintln!("--pin-core needs a core index, not pinning");
+            None
+        }
+    });
+    ThreadTuning {
+        core,
+        high_priority: args.iter().any(|arg| arg == "--high-priority"),
+    }
+}
+/// Parses `--frontend egui|sdl|tui`, sdl and tui need the features of the same name
+fn frontend(args: &[String]) -> Frontend {
+    match value_of(args, "--frontend") {
+        Some("egui") | None => Frontend::Egui,
+        #[cfg(feature = "sdl")]
+        Some("sdl") => Frontend::Sdl,
+        #[cfg(feature = "tui")]
+        Some("tui") => Frontend::Tui,
+        Some(other) => {
+            eprintln!("unknown or disabled frontend {other}, using egui");
+            Frontend::Egui
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rom_from_the_arguments() {
+        let args = |line: &str| -> Vec<String> { line.split(' ').map(String::from).collect() };
+        let rom = |line: &str| rom_path(&args(line));
+        assert_eq!(rom("game.gb --scale 3"), Some(PathBuf::from("game.gb")));
+        assert_eq!(
+            rom("--scale 3 --rom game.gb"),
+            Some(PathBuf::from("game.gb"))
+        );
+        assert_eq!(rom("--scale 3"), None);
+        assert_eq!(value_of(&args("--sync audio"), "--sync"), Some("audio"));
+        assert_eq!(value_of(&args("--sync"), "--sync"), None);
+    }
+}
@@ -0,0 +1,410 @@
+use std::sync::{Arc, Mutex};
+
+use crate::rtc::{Rtc, MBC3_TIMER};
+
+/// Size of one external ram bank at 0xA000
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// Which banks are visible in the address space
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    /// The rom bank at 0x0000
+    pub rom0: usize,
+    /// The rom bank at 0x4000
+    pub romx: usize,
+    /// The ram bank at 0xA000, `None` while the ram is disabled
+    pub ram: Option<usize>,
+    /// The clock register at 0xA000 instead of ram, with its latched value
+    pub rtc: Option<(usize, u8)>,
+}
+
+/// A memory bank controller, it sees the writes into the rom area
+pub trait Mbc: Send {
+    fn write(&mut self, addr: u16, value: u8);
+    fn mapping(&self) -> Mapping;
+    /// A write to the clock register which is mapped
+    fn write_rtc(&mut self, _value: u8) {}
+    /// Replaces the clock of controllers which have one, so the gui sees the same clock
+    fn set_rtc(&mut self, _rtc: Arc<Mutex<Rtc>>) {}
+    /// The clock, it is saved after the ram
+    fn rtc(&self) -> Option<&Arc<Mutex<Rtc>>> {
+        None
+    }
+    /// The registers, for snapshots
+    fn save_state(&self) -> Vec<u8>;
+    fn load_state(&mut self, state: &[u8]);
+}
+
+/// Picks the controller named by the cartridge type byte
+pub fn for_type(cartridge_type: u8, ram_banks: usize) -> Box<dyn Mbc> {
+    match cartridge_type {
+        0x00 | 0x08 | 0x09 => Box::new(NoMbc { ram_banks }),
+        0x01..=0x03 => Box::new(Mbc1::new(ram_banks)),
+        0x0F..=0x13 => Box::new(Mbc3::new(ram_banks, MBC3_TIMER.contains(&cartridge_type))),
+        0x19..=0x1E => Box::new(Mbc5::new(ram_banks, cartridge_type >= 0x1C)),
+        _ => {
+            log::warn!("cartridge type {cartridge_type:#04x} is not supported, using MBC1");
+            Box::new(Mbc1::new(ram_banks))
+        }
+    }
+}
+
+/// 32 KiB rom without banking, optionally with 8 KiB ram
+pub struct NoMbc {
+    ram_banks: usize,
+}
+impl Mbc for NoMbc {
+    fn write(&mut self, _addr: u16, _value: u8) {}
+    fn mapping(&self) -> Mapping {
+        Mapping {
+            rom0: 0,
+            romx: 1,
+            ram: (self.ram_banks > 0).then_some(0),
+            rtc: None,
+        }
+    }
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+    fn load_state(&mut self, _state: &[u8]) {}
+}
+
+/// Up to 2 MiB rom and 32 KiB ram
+pub struct Mbc1 {
+    ram_banks: usize,
+    ram_enabled: bool,
+    /// 5 bits, 0 selects bank 1
+    low: u8,
+    /// 2 bits, the upper rom bank bits or the ram bank
+    high: u8,
+    /// Mode 1 also applies `high` to the bank at 0x0000 and the ram
+    advanced: bool,
+}
+impl Mbc1 {
+    pub fn new(ram_banks: usize) -> Self {
+        Mbc1 {
+            ram_banks,
+            ram_enabled: false,
+            low: 1,
+            high: 0,
+            advanced: false,
+        }
+    }
+}
+impl Mbc for Mbc1 {
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.low = (value & 0x1F).max(1),
+            0x4000..=0x5FFF => self.high = value & 0b11,
+            _ => self.advanced = value & 1 != 0,
+        }
+    }
+    fn mapping(&self) -> Mapping {
+        let high = self.high as usize;
+        let ram = if self.advanced && self.ram_banks > 1 {
+            high % self.ram_banks
+        } else {
+            0
+        };
+        Mapping {
+            rom0: if self.advanced { high << 5 } else { 0 },
+            romx: high << 5 | self.low as usize,
+            ram: (self.ram_enabled && self.ram_banks > 0).then_some(ram),
+            rtc: None,
+        }
+    }
+    fn save_state(&self) -> Vec<u8> {
+        vec![
+            self.ram_enabled as u8,
+            self.low,
+            self.high,
+            self.advanced as u8,
+        ]
+    }
+    fn load_state(&mut self, state: &[u8]) {
+        if let [ram_enabled, low, high, advanced] = *state {
+            self.ram_enabled = ram_enabled != 0;
+            self.low = low;
+            self.high = high;
+            self.advanced = advanced != 0;
+        }
+    }
+}
+
+/// Up to 2 MiB rom, 32 KiB ram and optionally a real time clock.
+/// The MBC30 of the Japanese Pokémon Crystal has 4 MiB rom and 64 KiB ram.
+pub struct Mbc3 {
+    ram_banks: usize,
+    /// Enables the ram and the clock
+    ram_enabled: bool,
+    /// 7 bits, 8 on the MBC30, 0 selects bank 1
+    rom_bank: u8,
+    /// 0-3 select a ram bank, 0-7 on the MBC30, 8-12 a clock register
+    select: u8,
+    /// Writing 0 and then 1 latches the clock
+    latch_armed: bool,
+    latched: [u8; 5],
+    rtc: Option<Arc<Mutex<Rtc>>>,
+    mbc30: bool,
+}
+impl Mbc3 {
+    /// The header has no type for the MBC30, more than 32 KiB ram give it away
+    pub fn new(ram_banks: usize, timer: bool) -> Self {
+        Mbc3 {
+            mbc30: ram_banks > 4,
+            ram_banks,
+            ram_enabled: false,
+            rom_bank: 1,
+            select: 0,
+            latch_armed: false,
+            latched: [0; 5],
+            rtc: timer.then(|| Arc::new(Mutex::new(Rtc::default()))),
+        }
+    }
+}
+impl Mbc for Mbc3 {
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                let mask = if self.mbc30 { 0xFF } else { 0x7F };
+                self.rom_bank = (value & mask).max(1);
+            }
+            0x4000..=0x5FFF => self.select = value & 0x0F,
+            _ => {
+                if self.latch_armed && value == 1 {
+                    if let Some(rtc) = &self.rtc {
+                        self.latched = rtc.lock().unwrap().registers();
+                    }
+                }
+                self.latch_armed = value == 0;
+            }
+        }
+    }
+    fn mapping(&self) -> Mapping {
+        let select = self.select as usize;
+        let ram_selects = if self.mbc30 { 8 } else { 4 };
+        let ram = (self.ram_enabled && select < ram_selects && self.ram_banks > 0)
+            .then(|| select % self.ram_banks);
+        let rtc = (self.ram_enabled && self.rtc.is_some() && (8..=12).contains(&select))
+            .then(|| (select - 8, self.latched[select - 8]));
+        Mapping {
+            rom0: 0,
+            romx: self.rom_bank as usize,
+            ram,
+            rtc,
+        }
+    }
+    fn write_rtc(&mut self, value: u8) {
+        let (Some(rtc), Some((register, _))) = (&self.rtc, self.mapping().rtc) else {
+            return;
+        };
+        rtc.lock().unwrap().set_register(register, value);
+        self.latched[register] = value;
+    }
+    fn set_rtc(&mut self, rtc: Arc<Mutex<Rtc>>) {
+        match &self.rtc {
+            Some(own) if !Arc::ptr_eq(own, &rtc) => {
+                // keeps the time loaded from the save
+                *rtc.lock().unwrap() = own.lock().unwrap().clone();
+                self.rtc = Some(rtc);
+            }
+            _ => {}
+        }
+    }
+    fn rtc(&self) -> Option<&Arc<Mutex<Rtc>>> {
+        self.rtc.as_ref()
+    }
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = vec![
+            self.ram_enabled as u8,
+            self.rom_bank,
+            self.select,
+            self.latch_armed as u8,
+        ];
+        state.extend(self.latched);
+        state
+    }
+    fn load_state(&mut self, state: &[u8]) {
+        if let [ram_enabled, rom_bank, select, latch_armed, ref latched @ ..] = *state {
+            self.ram_enabled = ram_enabled != 0;
+            self.rom_bank = rom_bank;
+            self.select = select;
+            self.latch_armed = latch_armed != 0;
+            if let Ok(latched) = latched.try_into() {
+                self.latched = latched;
+            }
+        }
+    }
+}
+
+/// Up to 8 MiB rom and 128 KiB ram
+pub struct Mbc5 {
+    ram_banks: usize,
+    ram_enabled: bool,
+    /// 9 bits, bank 0 can be mapped at 0x4000 as well
+    rom_bank: u16,
+    /// 4 bits
+    ram_bank: u8,
+    /// Bit 3 of the ram bank drives the motor instead
+    rumble: bool,
+}
+impl Mbc5 {
+    pub fn new(ram_banks: usize, rumble: bool) -> Self {
+        Mbc5 {
+            ram_banks,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble,
+        }
+    }
+}
+impl Mbc for Mbc5 {
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = self.rom_bank & 0x100 | value as u16,
+            0x3000..=0x3FFF => self.rom_bank = self.rom_bank & 0xFF | (value as u16 & 1) << 8,
+            0x4000..=0x5FFF => {
+                let mask = if self.rumble { 0x07 } else { 0x0F };
+                self.ram_bank = value & mask;
+            }
+            _ => {}
+        }
+    }
+    fn mapping(&self) -> Mapping {
+        Mapping {
+            rom0: 0,
+            romx: self.rom_bank as usize,
+            ram: (self.ram_enabled && self.ram_banks > 0)
+                .then(|| self.ram_bank as usize % self.ram_banks),
+            rtc: None,
+        }
+    }
+    fn save_state(&self) -> Vec<u8> {
+        let [low, high] = self.rom_bank.to_le_bytes();
+        vec![self.ram_enabled as u8, low, high, self.ram_bank]
+    }
+    fn load_state(&mut self, state: &[u8]) {
+        if let [ram_enabled, low, high, ram_bank] = *state {
+            self.ram_enabled = ram_enabled != 0;
+            self.rom_bank = u16::from_le_bytes([low, high]);
+            self.ram_bank = ram_bank;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtc::RtcTime;
+
+    use super::*;
+
+    /// Bank 0 can not be selected at 0x4000 on the MBC1 and the MBC3, it maps bank 1
+    #[test]
+    fn bank_zero_remapping() -> Result<(), String> {
+        let mut mbcs: [Box<dyn Mbc>; 2] = [Box::new(Mbc1::new(0)), Box::new(Mbc3::new(0, false))];
+        for mbc in &mut mbcs {
+            for (value, romx) in [(0x00, 1), (0x05, 5), (0x00, 1)] {
+                mbc.write(0x2000, value);
+                if mbc.mapping().romx != romx {
+                    return Err(format!("bank {value} mapped {:?}", mbc.mapping()));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// The upper bits go to the rom bank at 0x4000, in mode 1 also to 0x0000 and the ram
+    #[test]
+    fn mbc1_modes() -> Result<(), String> {
+        let mut mbc = Mbc1::new(4);
+        mbc.write(0x0000, 0x0A);
+        mbc.write(0x2000, 0x02);
+        mbc.write(0x4000, 0x01);
+        for (mode, expected) in [
+            (0, (0x00, 0x22, Some(0))),
+            (1, (0x20, 0x22, Some(1))),
+            (0, (0x00, 0x22, Some(0))),
+        ] {
+            mbc.write(0x6000, mode);
+            let mapping = mbc.mapping();
+            if (mapping.rom0, mapping.romx, mapping.ram) != expected {
+                return Err(format!("mode {mode} mapped {mapping:?}"));
+            }
+        }
+        // 0x20 is bank 0 in the low bits, which maps 0x21
+        mbc.write(0x2000, 0x20);
+        match mbc.mapping().romx {
+            0x21 => Ok(()),
+            romx => Err(format!("0x20 mapped bank {romx:#04x}")),
+        }
+    }
+    /// The MBC5 maps bank 0 at 0x4000 and has a ninth bank bit
+    #[test]
+    fn mbc5_banks() -> Result<(), String> {
+        let mut mbc = Mbc5::new(0, false);
+        for (addr, value, romx) in [
+            (0x2000, 0x00, 0x000),
+            (0x2000, 0x05, 0x005),
+            (0x3000, 0x01, 0x105),
+            (0x2000, 0xFF, 0x1FF),
+            (0x3000, 0x00, 0x0FF),
+        ] {
+            mbc.write(addr, value);
+            if mbc.mapping().romx != romx {
+                return Err(format!(
+                    "{value:#04x} at {addr:#06x} mapped {:?}",
+                    mbc.mapping()
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// The MBC30 has an eighth rom bank bit and eight ram banks
+    #[test]
+    fn mbc30_banks() -> Result<(), String> {
+        for (ram_banks, rom, ram) in [(4, 0x7F, None), (8, 0xFF, Some(7))] {
+            let mut mbc = Mbc3::new(ram_banks, true);
+            mbc.write(0x0000, 0x0A);
+            mbc.write(0x2000, 0xFF);
+            mbc.write(0x4000, 0x07);
+            let mapping = mbc.mapping();
+            if (mapping.romx, mapping.ram) != (rom, ram) {
+                return Err(format!("{ram_banks} ram banks mapped {mapping:?}"));
+            }
+        }
+        Ok(())
+    }
+    /// The clock registers only change after writing 0 and then 1, DH halts the clock
+    #[test]
+    fn mbc3_rtc_latch() -> Result<(), String> {
+        let mut mbc = Mbc3::new(4, true);
+        let rtc = mbc.rtc().cloned().ok_or("no clock")?;
+        mbc.write(0x0000, 0x0A);
+        mbc.write(0x4000, 0x0C);
+        mbc.write_rtc(0x40);
+        if !rtc.lock().unwrap().halted() {
+            return Err("writing the halt bit did not stop the clock".to_string());
+        }
+        mbc.write(0x4000, 0x0A);
+        mbc.write_rtc(5);
+        rtc.lock().unwrap().set_time(RtcTime {
+            hours: 7,
+            ..RtcTime::default()
+        });
+        for (writes, hours) in [(&[0x01][..], 5), (&[0x00, 0x01][..], 7)] {
+            for value in writes {
+                mbc.write(0x6000, *value);
+            }
+            if mbc.mapping().rtc != Some((2, hours)) {
+                return Err(format!(
+                    "latching with {writes:?} mapped {:?}",
+                    mbc.mapping()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
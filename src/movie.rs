@@ -0,0 +1,216 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::state::{self, Reader, Writer};
+
+/// Start of every movie file
+const MAGIC: [u8; 4] = *b"GBMV";
+/// Bumped whenever the layout changes
+const VERSION: u16 = 1;
+pub const EXTENSION: &str = "gbm";
+
+/// The buttons of every frame from a start state on. Played back from the same
+/// state with the same seed, the game runs exactly the same way.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Movie {
+    /// CRC32 of the rom it was recorded with
+    pub crc: u32,
+    /// Of the deterministic host, see `Host::seeded`
+    pub seed: u64,
+    /// The frame counter at the start, the random numbers of a frame depend on it
+    pub start_frame: u32,
+    /// A snapshot of the cpu
+    pub start: Vec<u8>,
+    /// `Buttons` held through each frame
+    pub inputs: Vec<u8>,
+}
+impl Movie {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.bytes(&MAGIC);
+        w.u16(VERSION);
+        // the start is a snapshot, which only loads into the same version
+        w.u16(state::VERSION);
+        w.u32(self.crc);
+        w.bytes(&self.seed.to_le_bytes());
+        w.u32(self.start_frame);
+        w.block(&self.start);
+        w.block(&self.inputs);
+        w.finish()
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Movie, String> {
+        let truncated = |_| "the movie ends early".to_string();
+        let mut r = Reader::new(bytes);
+        if r.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err("not a movie".to_string());
+        }
+        let version = r.u16().map_err(truncated)?;
+        let state_version = r.u16().map_err(truncated)?;
+        if version != VERSION || state_version != state::VERSION {
+            return Err(format!(
+                "recorded by version {version}.{state_version}, this is version {VERSION}.{}",
+                state::VERSION
+            ));
+        }
+        let crc = r.u32().map_err(truncated)?;
+        let seed = r.bytes(8).map_err(truncated)?;
+        Ok(Movie {
+            crc,
+            seed: u64::from_le_bytes(seed.try_into().unwrap()),
+            start_frame: r.u32().map_err(truncated)?,
+            start: r.block().map_err(truncated)?.to_vec(),
+            inputs: r.block().map_err(truncated)?.to_vec(),
+        })
+    }
+    pub fn load(path: &Path) -> Result<Movie, String> {
+        Movie::from_bytes(&fs::read(path).map_err(|e| e.to_string())?)
+    }
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MovieRequest {
+    /// From the current state on, written to the path when it stops
+    Record(PathBuf),
+    Play(PathBuf),
+    Stop,
+}
+
+/// What the cpu does with the movie
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MovieMode {
+    #[default]
+    Off,
+    Recording {
+        frames: usize,
+    },
+    Playing {
+        frame: usize,
+        frames: usize,
+    },
+}
+
+#[derive(Debug, Default)]
+struct MoviesInner {
+    request: Option<MovieRequest>,
+    mode: MovieMode,
+    /// Why the last request failed
+    error: Option<String>,
+}
+
+/// Recording and playback of movies, the gui requests them and the cpu
+/// starts them after the current frame. Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Movies(Arc<Mutex<MoviesInner>>);
+impl Movies {
+    pub fn request(&self, request: MovieRequest) {
+        let mut inner = self.0.lock().unwrap();
+        inner.request = Some(request);
+        inner.error = None;
+    }
+    /// Called by the cpu after every frame
+    pub fn take_request(&self) -> Option<MovieRequest> {
+        self.0.lock().unwrap().request.take()
+    }
+    pub fn mode(&self) -> MovieMode {
+        self.0.lock().unwrap().mode
+    }
+    pub fn set_mode(&self, mode: MovieMode) {
+        self.0.lock().unwrap().mode = mode;
+    }
+    pub fn error(&self) -> Option<String> {
+        self.0.lock().unwrap().error.clone()
+    }
+    pub fn report_error(&self, error: String) {
+        self.0.lock().unwrap().error = Some(error);
+    }
+}
+
+/// The movie the cpu records or plays
+#[derive(Clone, Debug)]
+pub enum ActiveMovie {
+    Recording { movie: Movie, path: PathBuf },
+    Playing { movie: Movie, frame: usize },
+}
+impl ActiveMovie {
+    pub fn mode(&self) -> MovieMode {
+        match self {
+            ActiveMovie::Recording { movie, .. } => MovieMode::Recording {
+                frames: movie.inputs.len(),
+            },
+            ActiveMovie::Playing { movie, frame } => MovieMode::Playing {
+                frame: *frame,
+                frames: movie.inputs.len(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bus::Bus,
+        cpu::{Cpu, V16},
+        frontend::Buttons,
+        joypad::P1,
+    };
+
+    use super::*;
+
+    /// A recording holds the buttons of every frame, playing it back holds them again
+    #[test]
+    fn movie_replay() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("gba-selftest-{}.gbm", std::process::id()));
+        let movies = Movies::default();
+        let mut cpu = Cpu::new(Bus::default()).with_movies(movies.clone());
+        let result = (|| {
+            movies.request(MovieRequest::Record(path.clone()));
+            for _ in 0..3 {
+                cpu.update_movie();
+            }
+            if movies.mode() != (MovieMode::Recording { frames: 3 }) {
+                return Err(format!("recording 3 frames is {:?}", movies.mode()));
+            }
+            movies.request(MovieRequest::Stop);
+            cpu.update_movie();
+            let mut movie = Movie::load(&path)?;
+            if movie.inputs != [0; 3] {
+                return Err(format!("recorded {:?} without input", movie.inputs));
+            }
+            if Movie::from_bytes(&movie.to_bytes())? != movie {
+                return Err("the movie changed through its bytes".to_string());
+            }
+            movie.inputs = vec![Buttons::A, 0];
+            movie.save(&path)?;
+            cpu.w(V16::SP, 0x1234);
+            movies.request(MovieRequest::Play(path.clone()));
+            cpu.update_movie();
+            if cpu.r(V16::SP) == 0x1234 {
+                return Err("playing did not go back to the start".to_string());
+            }
+            // the action buttons are selected
+            cpu.bus_mut().write_mem(P1, 0x10);
+            if !cpu.bus_mut().poll_buttons() {
+                return Err("the recorded A is not held".to_string());
+            }
+            cpu.update_movie();
+            cpu.update_movie();
+            if movies.mode() != MovieMode::Off || cpu.bus_mut().poll_buttons() {
+                return Err("the movie did not stop at its end".to_string());
+            }
+            Ok(())
+        })();
+        let _ = fs::remove_file(&path);
+        result
+    }
+}
@@ -0,0 +1,344 @@
+use std::{fs, io, path::Path};
+
+use crate::{bus::OpCode, instruction::Instruction};
+
+/// What an instruction does to one flag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagEffect {
+    Unchanged,
+    Reset,
+    Set,
+    /// Depends on the result
+    Changed,
+}
+impl FlagEffect {
+    /// The usual notation, `-`, `0`, `1` or the name of the flag
+    pub fn symbol(self, flag: char) -> char {
+        match self {
+            FlagEffect::Unchanged => '-',
+            FlagEffect::Reset => '0',
+            FlagEffect::Set => '1',
+            FlagEffect::Changed => flag,
+        }
+    }
+}
+/// Parses the usual notation like `Z0H-`
+fn flags(s: &str) -> [FlagEffect; 4] {
+    let mut effects = [FlagEffect::Unchanged; 4];
+    for (effect, c) in effects.iter_mut().zip(s.chars()) {
+        *effect = match c {
+            '-' => FlagEffect::Unchanged,
+            '0' => FlagEffect::Reset,
+            '1' => FlagEffect::Set,
+            _ => FlagEffect::Changed,
+        };
+    }
+    effects
+}
+
+/// One row of the opcode matrix
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpInfo {
+    pub opcode: u8,
+    /// Follows the 0xCB prefix
+    pub prefixed: bool,
+    pub mnemonic: String,
+    /// In bytes, including the prefix
+    pub length: u8,
+    /// In machine cycles, for branches when the branch is not taken
+    pub cycles: u8,
+    /// In machine cycles when a conditional branch is taken
+    pub branch_cycles: Option<u8>,
+    /// Z, N, H and C
+    pub flags: [FlagEffect; 4],
+}
+impl OpInfo {
+    fn new(opcode: u8, mnemonic: impl Into<String>, length: u8, cycles: u8, f: &str) -> Self {
+        OpInfo {
+            opcode,
+            prefixed: false,
+            mnemonic: mnemonic.into(),
+            length,
+            cycles,
+            branch_cycles: None,
+            flags: flags(f),
+        }
+    }
+    fn branch(mut self, taken: u8) -> Self {
+        self.branch_cycles = Some(taken);
+        self
+    }
+    /// Like `Z0H-`
+    pub fn flag_string(&self) -> String {
+        self.flags
+            .iter()
+            .zip(['Z', 'N', 'H', 'C'])
+            .map(|(effect, flag)| effect.symbol(flag))
+            .collect()
+    }
+    /// The variant the cpu decodes an unprefixed opcode into
+    pub fn instruction(&self) -> Option<Instruction> {
+        (!self.prefixed).then(|| Instruction::from(OpCode(self.opcode)))
+    }
+}
+
+const R8: [&str; 8] = ["B", "C", "D", "E", "H", "L", "[HL]", "A"];
+const R16: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const R16_STACK: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const R16_MEM: [&str; 4] = ["[BC]", "[DE]", "[HL+]", "[HL-]"];
+const CONDITIONS: [&str; 4] = ["NZ", "Z", "NC", "C"];
+const ALU: [(&str, &str); 8] = [
+    ("ADD A,", "Z0HC"),
+    ("ADC A,", "Z0HC"),
+    ("SUB A,", "Z1HC"),
+    ("SBC A,", "Z1HC"),
+    ("AND A,", "Z010"),
+    ("XOR A,", "Z000"),
+    ("OR A,", "Z000"),
+    ("CP A,", "Z1HC"),
+];
+const ILLEGAL: [u8; 11] = [
+    0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+];
+
+/// The unprefixed instruction of `op`
+pub fn info(op: u8) -> OpInfo {
+    let x = (op >> 3) & 7;
+    let z = op & 7;
+    let p = ((op >> 4) & 3) as usize;
+    // [HL] costs an extra read, or a read and a write
+    let hl = z == 6;
+    let r8 = R8[z as usize];
+    let dst = R8[x as usize];
+    if ILLEGAL.contains(&op) {
+        return OpInfo::new(op, format!("ILLEGAL_{op:02X}"), 1, 1, "----");
+    }
+    match op {
+        0x00 => OpInfo::new(op, "NOP", 1, 1, "----"),
+        0x08 => OpInfo::new(op, "LD [a16],SP", 3, 5, "----"),
+        0x10 => OpInfo::new(op, "STOP n8", 2, 1, "----"),
+        0x18 => OpInfo::new(op, "JR e8", 2, 3, "----"),
+        0x20 | 0x28 | 0x30 | 0x38 => OpInfo::new(
+            op,
+            format!("JR {},e8", CONDITIONS[x as usize - 4]),
+            2,
+            2,
+            "----",
+        )
+        .branch(3),
+        0x01 | 0x11 | 0x21 | 0x31 => OpInfo::new(op, format!("LD {},n16", R16[p]), 3, 3, "----"),
+        0x09 | 0x19 | 0x29 | 0x39 => OpInfo::new(op, format!("ADD HL,{}", R16[p]), 1, 2, "-0HC"),
+        0x02 | 0x12 | 0x22 | 0x32 => OpInfo::new(op, format!("LD {},A", R16_MEM[p]), 1, 2, "----"),
+        0x0A | 0x1A | 0x2A | 0x3A => OpInfo::new(op, format!("LD A,{}", R16_MEM[p]), 1, 2, "----"),
+        0x03 | 0x13 | 0x23 | 0x33 => OpInfo::new(op, format!("INC {}", R16[p]), 1, 2, "----"),
+        0x0B | 0x1B | 0x2B | 0x3B => OpInfo::new(op, format!("DEC {}", R16[p]), 1, 2, "----"),
+        0x00..=0x3F if z == 4 => {
+            let cycles = if x == 6 { 3 } else { 1 };
+            OpInfo::new(op, format!("INC {dst}"), 1, cycles, "Z0H-")
+        }
+        0x00..=0x3F if z == 5 => {
+            let cycles = if x == 6 { 3 } else { 1 };
+            OpInfo::new(op, format!("DEC {dst}"), 1, cycles, "Z1H-")
+        }
+        0x00..=0x3F if z == 6 => {
+            let cycles = if x == 6 { 3 } else { 2 };
+            OpInfo::new(op, format!("LD {dst},n8"), 2, cycles, "----")
+        }
+        0x07 => OpInfo::new(op, "RLCA", 1, 1, "000C"),
+        0x0F => OpInfo::new(op, "RRCA", 1, 1, "000C"),
+        0x17 => OpInfo::new(op, "RLA", 1, 1, "000C"),
+        0x1F => OpInfo::new(op, "RRA", 1, 1, "000C"),
+        0x27 => OpInfo::new(op, "DAA", 1, 1, "Z-0C"),
+        0x2F => OpInfo::new(op, "CPL", 1, 1, "-11-"),
+        0x37 => OpInfo::new(op, "SCF", 1, 1, "-001"),
+        0x3F => OpInfo::new(op, "CCF", 1, 1, "-00C"),
+        0x76 => OpInfo::new(op, "HALT", 1, 1, "----"),
+        0x40..=0x7F => {
+            let cycles = if hl || x == 6 { 2 } else { 1 };
+            OpInfo::new(op, format!("LD {dst},{r8}"), 1, cycles, "----")
+        }
+        0x80..=0xBF => {
+            let (name, f) = ALU[x as usize];
+            OpInfo::new(op, format!("{name}{r8}"), 1, if hl { 2 } else { 1 }, f)
+        }
+        0xC0 | 0xC8 | 0xD0 | 0xD8 => {
+            OpInfo::new(op, format!("RET {}", CONDITIONS[x as usize]), 1, 2, "----").branch(5)
+        }
+        0xC2 | 0xCA | 0xD2 | 0xDA => OpInfo::new(
+            op,
+            format!("JP {},a16", CONDITIONS[x as usize]),
+            3,
+            3,
+            "----",
+        )
+        .branch(4),
+        0xC4 | 0xCC | 0xD4 | 0xDC => OpInfo::new(
+            op,
+            format!("CALL {},a16", CONDITIONS[x as usize]),
+            3,
+            3,
+            "----",
+        )
+        .branch(6),
+        0xF1 => OpInfo::new(op, "POP AF", 1, 3, "ZNHC"),
+        0xC1 | 0xD1 | 0xE1 => OpInfo::new(op, format!("POP {}", R16_STACK[p]), 1, 3, "----"),
+        0xC5 | 0xD5 | 0xE5 | 0xF5 => {
+            OpInfo::new(op, format!("PUSH {}", R16_STACK[p]), 1, 4, "----")
+        }
+        0xC3 => OpInfo::new(op, "JP a16", 3, 4, "----"),
+        0xC9 => OpInfo::new(op, "RET", 1, 4, "----"),
+        0xD9 => OpInfo::new(op, "RETI", 1, 4, "----"),
+        0xCB => OpInfo::new(op, "PREFIX", 1, 1, "----"),
+        0xCD => OpInfo::new(op, "CALL a16", 3, 6, "----"),
+        0xE0 => OpInfo::new(op, "LDH [a8],A", 2, 3, "----"),
+        0xF0 => OpInfo::new(op, "LDH A,[a8]", 2, 3, "----"),
+        0xE2 => OpInfo::new(op, "LDH [C],A", 1, 2, "----"),
+        0xF2 => OpInfo::new(op, "LDH A,[C]", 1, 2, "----"),
+        0xE8 => OpInfo::new(op, "ADD SP,e8", 2, 4, "00HC"),
+        0xE9 => OpInfo::new(op, "JP HL", 1, 1, "----"),
+        0xEA => OpInfo::new(op, "LD [a16],A", 3, 4, "----"),
+        0xFA => OpInfo::new(op, "LD A,[a16]", 3, 4, "----"),
+        0xF3 => OpInfo::new(op, "DI", 1, 1, "----"),
+        0xFB => OpInfo::new(op, "EI", 1, 1, "----"),
+        0xF8 => OpInfo::new(op, "LD HL,SP+e8", 2, 3, "00HC"),
+        0xF9 => OpInfo::new(op, "LD SP,HL", 1, 2, "----"),
+        0xC0..=0xFF if z == 6 => {
+            let (name, f) = ALU[x as usize];
+            OpInfo::new(op, format!("{name}n8"), 2, 2, f)
+        }
+        // RST, z is 7 for every opcode left
+        _ => OpInfo::new(op, format!("RST ${:02X}", op & 0x38), 1, 4, "----"),
+    }
+}
+/// The instruction of `op` after the 0xCB prefix
+pub fn prefixed_info(op: u8) -> OpInfo {
+    const SHIFTS: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SWAP", "SRL"];
+    let x = (op >> 3) & 7;
+    let r8 = R8[(op & 7) as usize];
+    let hl = op & 7 == 6;
+    let (mnemonic, cycles, f) = match op >> 6 {
+        0 => {
+            let f = if x == 6 { "Z000" } else { "Z00C" };
+            (format!("{} {r8}", SHIFTS[x as usize]), 4, f)
+        }
+        // only reads [HL]
+        1 => (format!("BIT {x},{r8}"), 3, "Z01-"),
+        2 => (format!("RES {x},{r8}"), 4, "----"),
+        _ => (format!("SET {x},{r8}"), 4, "----"),
+    };
+    let mut info = OpInfo::new(op, mnemonic, 2, if hl { cycles } else { 2 }, f);
+    info.prefixed = true;
+    info
+}
+/// Machine cycles of every unprefixed opcode, for branches when not taken and when taken
+pub fn timings() -> [(u8, u8); 256] {
+    std::array::from_fn(|op| {
+        let info = info(op as u8);
+        (info.cycles, info.branch_cycles.unwrap_or(info.cycles))
+    })
+}
+/// Bytes of every unprefixed opcode, with its operands
+pub fn lengths() -> [u8; 256] {
+    std::array::from_fn(|op| info(op as u8).length)
+}
+/// All 512 instructions, unprefixed first
+pub fn table() -> impl Iterator<Item = OpInfo> {
+    (0..=0xFF).map(info).chain((0..=0xFF).map(prefixed_info))
+}
+
+pub fn to_csv() -> String {
+    let mut csv =
+        String::from("opcode,prefixed,mnemonic,length,cycles,branch_cycles,flags,decoded\n");
+    for op in table() {
+        csv.push_str(&format!(
+            "0x{:02X},{},\"{}\",{},{},{},{},{}\n",
+            op.opcode,
+            op.prefixed,
+            op.mnemonic,
+            op.length,
+            op.cycles,
+            op.branch_cycles.map(|c| c.to_string()).unwrap_or_default(),
+            op.flag_string(),
+            op.instruction()
+                .map(|i| format!("{i:?}"))
+                .unwrap_or_default(),
+        ));
+    }
+    csv
+}
+pub fn to_json() -> String {
+    let rows: Vec<String> = table()
+        .map(|op| {
+            let branch = op.branch_cycles.map_or("null".to_string(), |c| c.to_string());
+            let decoded = op
+                .instruction()
+                .map_or("null".to_string(), |i| format!("\"{i:?}\""));
+            format!(
+                "  {{\"opcode\": \"0x{:02X}\", \"prefixed\": {}, \"mnemonic\": \"{}\", \"length\": {}, \"cycles\": {}, \"branch_cycles\": {branch}, \"flags\": \"{}\", \"decoded\": {decoded}}}",
+                op.opcode,
+                op.prefixed,
+                op.mnemonic,
+                op.length,
+                op.cycles,
+                op.flag_string(),
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+/// Writes the matrix as csv if the path ends in `.csv`, as json otherwise
+pub fn export(path: &Path) -> io::Result<()> {
+    let is_csv = path.extension().is_some_and(|ext| ext == "csv");
+    fs::write(path, if is_csv { to_csv() } else { to_json() })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instruction::Instruction;
+
+    use super::*;
+
+    /// The metadata agrees with the decode table and with published values
+    #[test]
+    fn opcode_matrix() -> Result<(), String> {
+        for op in table().filter(|op| !op.prefixed) {
+            let illegal = matches!(op.instruction(), Some(Instruction::Illegal));
+            if illegal != op.mnemonic.starts_with("ILLEGAL") {
+                return Err(format!(
+                    "{:#04x} is {} but decodes to {:?}",
+                    op.opcode,
+                    op.mnemonic,
+                    op.instruction()
+                ));
+            }
+        }
+        for (op, prefixed, mnemonic, length, cycles, flags) in [
+            (0x01, false, "LD BC,n16", 3, 3, "----"),
+            (0x20, false, "JR NZ,e8", 2, 2, "----"),
+            (0x34, false, "INC [HL]", 1, 3, "Z0H-"),
+            (0xC4, false, "CALL NZ,a16", 3, 3, "----"),
+            (0xE8, false, "ADD SP,e8", 2, 4, "00HC"),
+            (0xF1, false, "POP AF", 1, 3, "ZNHC"),
+            (0xFF, false, "RST $38", 1, 4, "----"),
+            (0x36, true, "SWAP [HL]", 2, 4, "Z000"),
+            (0x7E, true, "BIT 7,[HL]", 2, 3, "Z01-"),
+            (0xC7, true, "SET 0,A", 2, 2, "----"),
+        ] {
+            let info = if prefixed {
+                prefixed_info(op)
+            } else {
+                info(op)
+            };
+            let row = (
+                info.mnemonic.as_str(),
+                info.length,
+                info.cycles,
+                info.flag_string(),
+            );
+            if row != (mnemonic, length, cycles, flags.to_string()) {
+                return Err(format!("{op:#04x} (prefixed {prefixed}) is {row:?}"));
+            }
+        }
+        Ok(())
+    }
+}
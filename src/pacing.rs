@@ -0,0 +1,192 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bus::Bus,
+    cpu::{CLOCK_SPEED, CYCLES_PER_FRAME},
+    speed::{Desync, Refresh, Speed, Turbo},
+};
+
+/// Real time of one emulated frame at 100%
+const FRAME_TIME: Duration =
+    Duration::from_nanos(1_000_000_000 * CYCLES_PER_FRAME as u64 / CLOCK_SPEED as u64);
+/// One frame of a 60 Hz display
+const HOST_FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// The audio rate follows the buffer fill by at most this much,
+/// so the output device and the emulation do not drift apart
+const MAX_RATE_CORRECTION: f32 = 0.005;
+/// Being late by less is made up without a recovery
+const TOLERANCE: Duration = Duration::from_millis(50);
+/// Dropping frames resyncs anyway when it is further behind
+const MAX_CATCH_UP: Duration = Duration::from_secs(1);
+/// The audio plays at half speed at the slowest
+const MAX_STRETCH: f32 = 2.;
+/// The end of a wait is spun instead of slept, sleeping overshoots by up to a millisecond
+/// on most systems and more on some
+const SPIN: Duration = Duration::from_millis(2);
+/// With `Refresh::Audio` the next frame runs once the audio buffer has drained to this percent
+const AUDIO_TARGET: u8 = 50;
+/// Polling interval while waiting for the audio output
+const AUDIO_POLL: Duration = Duration::from_micros(500);
+/// Fast-forward plays the audio at up to this many times the speed,
+/// the output drops what is faster
+const MAX_TURBO_AUDIO: f32 = 8.;
+
+/// Keeps the emulation at real time by sleeping after every frame
+/// and recovers with the selected `Desync` policy when it falls behind
+pub struct Pacing {
+    /// When the current frame should be done
+    deadline: Instant,
+    /// Start of the frame which is running
+    frame_start: Instant,
+    /// Counts the frames of the current second for the fps
+    fps_since: Instant,
+    frames: u32,
+    /// When the last frame was drawn during fast-forward
+    drawn: Instant,
+    /// Whether the last frame was fast-forwarded
+    turbo: bool,
+}
+impl Pacing {
+    /// Starts counting from now, e.g. after a pause which should not be made up
+    pub fn resync(&mut self) {
+        let now = Instant::now();
+        self.deadline = now;
+        self.frame_start = now;
+    }
+    /// How long the running frame took so far
+    pub fn elapsed(&self) -> Duration {
+        self.frame_start.elapsed()
+    }
+    /// Called after every emulated frame, waits until it is due
+    pub fn frame_done(&mut self, speed: &Speed, bus: &mut Bus) {
+        if speed.is_turbo() {
+            self.turbo_frame_done(speed.turbo_rate(), bus);
+            self.count_frame(bus);
+            return;
+        }
+        if self.turbo {
+            // continues at the normal speed from here, what fast-forward skipped stays skipped
+            self.turbo = false;
+            self.deadline = Instant::now();
+            bus.set_skip_frames(false);
+        }
+        let refresh = speed.refresh();
+        let frame = frame_time(refresh) * 100 / speed.get();
+        self.deadline += frame;
+        let fill = bus.status().audio_fill();
+        if refresh == Refresh::Audio && speed.get() == 100 && fill > 0 {
+            // the sound card clock paces the frames, the samples are taken as they are
+            wait_for_audio(bus, self.deadline + frame);
+            bus.set_skip_frames(false);
+            bus.set_audio_stretch(1.);
+            self.deadline = Instant::now();
+            self.count_frame(bus);
+            return;
+        }
+        let now = Instant::now();
+        let took = now - self.frame_start;
+        if now < self.deadline {
+            wait_until(self.deadline);
+            bus.set_skip_frames(false);
+            bus.set_audio_stretch(audio_rate(refresh, fill));
+        } else if now - self.deadline > TOLERANCE {
+            let behind = now - self.deadline;
+            match speed.desync() {
+                Desync::Resync => self.deadline = now,
+                Desync::DropFrames if behind < MAX_CATCH_UP => bus.set_skip_frames(true),
+                Desync::DropFrames => {
+                    log::debug!("{behind:?} behind, too far to catch up");
+                    bus.set_skip_frames(false);
+                    self.deadline = now;
+                }
+                Desync::StretchAudio => {
+                    let stretch = took.as_secs_f32() / frame.as_secs_f32();
+                    bus.set_audio_stretch(stretch.clamp(1., MAX_STRETCH));
+                    self.deadline = now;
+                }
+            }
+        }
+        self.count_frame(bus);
+    }
+    /// Runs at a multiple of the speed or uncapped. Only as many frames as the display shows
+    /// are drawn and the audio plays faster, time lost on the way is never made up.
+    fn turbo_frame_done(&mut self, rate: Turbo, bus: &mut Bus) {
+        self.turbo = true;
+        if let Some(factor) = rate.factor() {
+            self.deadline += FRAME_TIME / factor;
+            if Instant::now() < self.deadline {
+                wait_until(self.deadline);
+            }
+        }
+        let now = Instant::now();
+        if now > self.deadline {
+            self.deadline = now;
+        }
+        let took = now - self.frame_start;
+        let stretch = took.as_secs_f32() / FRAME_TIME.as_secs_f32();
+        bus.set_audio_stretch(stretch.clamp(1. / MAX_TURBO_AUDIO, 1.));
+        let draw = now - self.drawn >= HOST_FRAME_TIME;
+        if draw {
+            self.drawn = now;
+        }
+        bus.set_skip_frames(!draw);
+    }
+    fn count_frame(&mut self, bus: &Bus) {
+        self.frame_start = Instant::now();
+        self.frames += 1;
+        let second = self.fps_since.elapsed();
+        if second >= Duration::from_secs(1) {
+            bus.status()
+                .set_fps(self.frames as f32 / second.as_secs_f32());
+            self.frames = 0;
+            self.fps_since = Instant::now();
+        }
+    }
+}
+/// Sleeps most of the time and spins the rest, so the frame ends on time
+fn wait_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now + SPIN {
+        thread::sleep(deadline - now - SPIN);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+/// Waits until the output has played enough of the buffer, but not past `limit`
+/// in case the device stopped taking samples
+fn wait_for_audio(bus: &Bus, limit: Instant) {
+    while bus.status().audio_fill() > AUDIO_TARGET && Instant::now() < limit {
+        thread::sleep(AUDIO_POLL);
+    }
+}
+fn frame_time(refresh: Refresh) -> Duration {
+    match refresh {
+        Refresh::Native | Refresh::Audio => FRAME_TIME,
+        Refresh::Host60 => HOST_FRAME_TIME,
+    }
+}
+/// Stretch of the audio while on time. At 60 Hz the emulation runs faster than the hardware,
+/// fewer samples are taken so the audio keeps up, which raises the pitch a little.
+fn audio_rate(refresh: Refresh, fill: u8) -> f32 {
+    let rate = FRAME_TIME.as_secs_f32() / frame_time(refresh).as_secs_f32();
+    // a fuller buffer takes fewer samples, an emptier one more
+    let correction = (50. - fill as f32) / 50. * MAX_RATE_CORRECTION;
+    (1. + correction) / rate
+}
+impl Default for Pacing {
+    fn default() -> Self {
+        let now = Instant::now();
+        Pacing {
+            deadline: now,
+            frame_start: now,
+            fps_since: now,
+            frames: 0,
+            drawn: now,
+            turbo: false,
+        }
+    }
+}
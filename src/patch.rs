@@ -0,0 +1,333 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum PatchError {
+    Io(io::Error),
+    UnknownFormat,
+    UnexpectedEof,
+    /// A bps checksum did not match, `what` names the checked data
+    Checksum {
+        what: &'static str,
+    },
+    /// A bps number or offset does not fit, or reaches past the data
+    OutOfRange,
+}
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Io(e) => write!(f, "could not read patch: {e}"),
+            PatchError::UnknownFormat => write!(f, "patch is neither ips nor bps"),
+            PatchError::UnexpectedEof => write!(f, "patch ended unexpectedly"),
+            PatchError::Checksum { what } => write!(f, "{what} checksum does not match"),
+            PatchError::OutOfRange => write!(f, "patch points outside of the rom"),
+        }
+    }
+}
+impl From<io::Error> for PatchError {
+    fn from(e: io::Error) -> Self {
+        PatchError::Io(e)
+    }
+}
+
+/// The largest rom a header can name, the target size of a bps patch is only trusted up to it
+const MAX_PREALLOCATION: usize = 0x8000 << 8;
+
+/// Looks for a `.ips` or `.bps` file with the same name as the rom
+pub fn find_patch(rom: &Path) -> Option<PathBuf> {
+    ["ips", "bps"]
+        .into_iter()
+        .map(|extension| rom.with_extension(extension))
+        .find(|path| path.exists())
+}
+/// Applies the patch file to the rom, the format is detected from the file header
+pub fn apply_file(rom: &[u8], patch: &Path) -> Result<Vec<u8>, PatchError> {
+    let patch = fs::read(patch)?;
+    if patch.starts_with(b"PATCH") {
+        apply_ips(rom, &patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, &patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = Reader::new(
+        patch
+            .strip_prefix(b"PATCH")
+            .ok_or(PatchError::UnknownFormat)?,
+    );
+    let mut out = rom.to_vec();
+    loop {
+        let offset = reader.bytes(3)?;
+        if offset == b"EOF" {
+            break;
+        }
+        let offset = u24_be(offset);
+        let size = reader.u16_be()? as usize;
+        let (size, data) = if size == 0 {
+            // run length encoded record
+            let size = reader.u16_be()? as usize;
+            (size, vec![reader.byte()?; size])
+        } else {
+            (size, reader.bytes(size)?.to_vec())
+        };
+        if out.len() < offset + size {
+            out.resize(offset + size, 0);
+        }
+        out[offset..offset + size].copy_from_slice(&data);
+    }
+    // optional truncation extension
+    if let Ok(size) = reader.bytes(3) {
+        out.truncate(u24_be(size));
+    }
+    Ok(out)
+}
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.len() < 16 {
+        return Err(PatchError::UnexpectedEof);
+    }
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    let checksum = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    if crc32(&patch[..patch.len() - 4]) != checksum(8) {
+        return Err(PatchError::Checksum { what: "patch" });
+    }
+    if crc32(rom) != checksum(0) {
+        return Err(PatchError::Checksum { what: "source rom" });
+    }
+    let mut reader = Reader::new(
+        body.strip_prefix(b"BPS1")
+            .ok_or(PatchError::UnknownFormat)?,
+    );
+    let _source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+
+    let mut out = Vec::with_capacity(target_size.min(MAX_PREALLOCATION));
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+    while !reader.is_empty() {
+        let data = reader.varint()?;
+        let length = (data >> 2) + 1;
+        // no command writes past the target
+        let end = checked_end(out.len(), length)?;
+        if end > target_size {
+            return Err(PatchError::OutOfRange);
+        }
+        match data & 3 {
+            // source read
+            0 => {
+                let bytes = rom.get(out.len()..end).ok_or(PatchError::UnexpectedEof)?;
+                out.extend_from_slice(bytes);
+            }
+            // target read
+            1 => out.extend_from_slice(reader.bytes(length)?),
+            // source copy
+            2 => {
+                source_offset = relative_offset(source_offset, reader.varint()?)?;
+                let source_end = checked_end(source_offset, length)?;
+                let bytes = rom
+                    .get(source_offset..source_end)
+                    .ok_or(PatchError::UnexpectedEof)?;
+                out.extend_from_slice(bytes);
+                source_offset = source_end;
+            }
+            // target copy, byte by byte because source and destination may overlap
+            _ => {
+                target_offset = relative_offset(target_offset, reader.varint()?)?;
+                for _ in 0..length {
+                    let byte = *out.get(target_offset).ok_or(PatchError::UnexpectedEof)?;
+                    out.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if crc32(&out) != checksum(4) {
+        return Err(PatchError::Checksum {
+            what: "patched rom",
+        });
+    }
+    Ok(out)
+}
+fn u24_be(bytes: &[u8]) -> usize {
+    u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]) as usize
+}
+/// The lowest bit of `data` is the sign, the rest the distance
+fn relative_offset(offset: usize, data: usize) -> Result<usize, PatchError> {
+    if data & 1 == 1 {
+        offset.checked_sub(data >> 1)
+    } else {
+        offset.checked_add(data >> 1)
+    }
+    .ok_or(PatchError::OutOfRange)
+}
+fn checked_end(start: usize, length: usize) -> Result<usize, PatchError> {
+    start.checked_add(length).ok_or(PatchError::OutOfRange)
+}
+/// CRC-32 as used by zip, png and bps
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader(data)
+    }
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], PatchError> {
+        if self.0.len() < n {
+            return Err(PatchError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(bytes)
+    }
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+    /// bps variable length number
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut data: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let x = self.byte()? as usize;
+            data = (x & 0x7f)
+                .checked_mul(shift)
+                .and_then(|bits| data.checked_add(bits))
+                .ok_or(PatchError::OutOfRange)?;
+            if x & 0x80 != 0 {
+                return Ok(data);
+            }
+            shift = shift.checked_mul(1 << 7).ok_or(PatchError::OutOfRange)?;
+            data = data.checked_add(shift).ok_or(PatchError::OutOfRange)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// bps variable length numbers
+    fn varints(numbers: &[usize]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &number in numbers {
+            let mut n = number;
+            loop {
+                let x = (n & 0x7f) as u8;
+                n >>= 7;
+                if n == 0 {
+                    bytes.push(0x80 | x);
+                    break;
+                }
+                bytes.push(x);
+                n -= 1;
+            }
+        }
+        bytes
+    }
+    /// A bps patch for `rom` with the checksums filled in, for an empty target
+    fn bps(rom: &[u8], body: &[u8]) -> Vec<u8> {
+        bps_to(rom, &[], body)
+    }
+    /// A bps patch from `rom` to `target` with the checksums filled in
+    fn bps_to(rom: &[u8], target: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(body);
+        patch.extend(crc32(rom).to_le_bytes());
+        patch.extend(crc32(target).to_le_bytes());
+        patch.extend(crc32(&patch).to_le_bytes());
+        patch
+    }
+    /// A plain record, a run length encoded one past the end and the truncation extension
+    #[test]
+    fn ips() -> Result<(), String> {
+        let rom = [0x00; 8];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);
+        patch.extend([0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0xCC]);
+        patch.extend(b"EOF");
+        patch.extend([0x00, 0x00, 0x09]);
+        let out = apply_ips(&rom, &patch).map_err(|e| e.to_string())?;
+        let expected = [0x00, 0x00, 0xAA, 0xBB, 0x00, 0x00, 0xCC, 0xCC, 0xCC];
+        if out != expected {
+            return Err(format!("patched to {out:02x?}, expected {expected:02x?}"));
+        }
+        Ok(())
+    }
+    /// Every command once, the target copy repeats what the target read wrote
+    #[test]
+    fn bps_commands() -> Result<(), String> {
+        let rom = b"ABCDEFGH";
+        let target = b"ABxyzEFGxyzE";
+        let body = [
+            // source and target size, no metadata
+            varints(&[8, 12, 0]),
+            // source read of 2
+            varints(&[1 << 2]),
+            // target read of 3
+            varints(&[(2 << 2) | 1]),
+            b"xyz".to_vec(),
+            // source copy of 3 from 4 bytes ahead
+            varints(&[(2 << 2) | 2, 4 << 1]),
+            // target copy of 4 from 2 bytes ahead
+            varints(&[(3 << 2) | 3, 2 << 1]),
+        ]
+        .concat();
+        let out = apply_bps(rom, &bps_to(rom, target, &body)).map_err(|e| e.to_string())?;
+        if out != target {
+            return Err(format!(
+                "patched to {:?}, expected {:?}",
+                String::from_utf8_lossy(&out),
+                String::from_utf8_lossy(target)
+            ));
+        }
+        Ok(())
+    }
+    #[test]
+    fn malformed_bps() {
+        let rom = [0x00; 16];
+        let mut reader = Reader::new(&[0x00; 16]);
+        assert!(matches!(reader.varint(), Err(PatchError::OutOfRange)));
+        // the target size is not allocated up front
+        let huge = bps(&rom, &varints(&[16, usize::MAX >> 8, 0]));
+        assert!(apply_bps(&rom, &huge).is_ok_and(|out| out.is_empty()));
+        // a target read past the target size
+        let long = bps(
+            &rom,
+            &[varints(&[16, 1, 0, (1 << 2) | 1]), vec![0x00; 2]].concat(),
+        );
+        assert!(matches!(
+            apply_bps(&rom, &long),
+            Err(PatchError::OutOfRange)
+        ));
+        // a source copy from before the rom
+        let before = bps(&rom, &varints(&[16, 16, 0, 2, 3]));
+        assert!(matches!(
+            apply_bps(&rom, &before),
+            Err(PatchError::OutOfRange)
+        ));
+    }
+}
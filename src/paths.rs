@@ -0,0 +1,92 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+const APP_DIR: &str = "gba";
+
+/// Locations for everything the emulator writes to disk.
+#[derive(Clone)]
+pub struct DataDirs {
+    pub saves: PathBuf,
+    pub states: PathBuf,
+    pub screenshots: PathBuf,
+    /// LiveSplit files of the speedrun timer
+    pub splits: PathBuf,
+    pub config: PathBuf,
+    pub logs: PathBuf,
+}
+impl DataDirs {
+    /// Keeps all data in a `data` folder next to the executable,
+    /// so the emulator can be run from a usb stick or a synced folder.
+    pub fn portable() -> Self {
+        let root = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        Self::in_root(root.join("data"))
+    }
+    pub fn in_root(root: PathBuf) -> Self {
+        Self {
+            saves: root.join("saves"),
+            states: root.join("states"),
+            screenshots: root.join("screenshots"),
+            splits: root.join("splits"),
+            config: root.join("config"),
+            logs: root.join("logs"),
+        }
+    }
+    pub fn with_saves(mut self, path: PathBuf) -> Self {
+        self.saves = path;
+        self
+    }
+    pub fn with_states(mut self, path: PathBuf) -> Self {
+        self.states = path;
+        self
+    }
+    pub fn with_screenshots(mut self, path: PathBuf) -> Self {
+        self.screenshots = path;
+        self
+    }
+    pub fn with_splits(mut self, path: PathBuf) -> Self {
+        self.splits = path;
+        self
+    }
+    pub fn with_config(mut self, path: PathBuf) -> Self {
+        self.config = path;
+        self
+    }
+    pub fn with_logs(mut self, path: PathBuf) -> Self {
+        self.logs = path;
+        self
+    }
+    /// Creates all directories which do not exist yet
+    pub fn create_all(&self) -> io::Result<()> {
+        for dir in [
+            &self.saves,
+            &self.states,
+            &self.screenshots,
+            &self.splits,
+            &self.config,
+            &self.logs,
+        ] {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+impl Default for DataDirs {
+    /// Uses the platform specific data directory
+    fn default() -> Self {
+        let base = if cfg!(target_os = "windows") {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+        } else {
+            env::var_os("XDG_DATA_HOME").map(PathBuf::from).or_else(|| {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+        };
+        Self::in_root(base.unwrap_or_default().join(APP_DIR))
+    }
+}
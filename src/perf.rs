@@ -0,0 +1,91 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Frames the performance overlay keeps for its graph
+pub const HISTORY: usize = 240;
+
+/// Where the cpu thread spent the time of one frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameTime {
+    /// Running the cpu, the ppu and everything else of the frame
+    pub emulating: Duration,
+    /// Waiting until the frame was due
+    pub sleeping: Duration,
+}
+impl FrameTime {
+    pub fn total(&self) -> Duration {
+        self.emulating + self.sleeping
+    }
+}
+
+#[derive(Debug, Default)]
+struct PerfInner {
+    enabled: AtomicBool,
+    /// Oldest first
+    frames: Mutex<VecDeque<FrameTime>>,
+}
+
+/// The frame times of the cpu thread for the performance overlay, only measured while
+/// it is shown. Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct PerfStats(Arc<PerfInner>);
+impl PerfStats {
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled.load(Ordering::Relaxed)
+    }
+    /// Turning it off forgets the frames measured so far
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.0.frames.lock().unwrap().clear();
+        }
+    }
+    /// Called by the cpu after every frame, the oldest frame is dropped past `HISTORY`
+    pub fn push(&self, frame: FrameTime) {
+        let mut frames = self.0.frames.lock().unwrap();
+        if frames.len() == HISTORY {
+            frames.pop_front();
+        }
+        frames.push_back(frame);
+    }
+    /// The latest frames, oldest first
+    pub fn frames(&self) -> Vec<FrameTime> {
+        self.0.frames.lock().unwrap().iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// The overlay gets the latest frames, none once it is hidden
+    #[test]
+    fn frame_times() -> Result<(), String> {
+        let stats = PerfStats::default();
+        stats.set_enabled(true);
+        for ms in 0..HISTORY as u64 + 10 {
+            stats.push(FrameTime {
+                emulating: Duration::from_millis(ms),
+                sleeping: Duration::ZERO,
+            });
+        }
+        let frames = stats.frames();
+        let oldest = frames.first().map(|frame| frame.emulating);
+        if frames.len() != HISTORY || oldest != Some(Duration::from_millis(10)) {
+            return Err(format!("kept {} frames from {oldest:?} on", frames.len()));
+        }
+        stats.set_enabled(false);
+        if !stats.frames().is_empty() {
+            return Err("hiding the overlay kept the frames".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PowerRequest {
+    /// Starts over from the boot rom with the same cartridge
+    Reset,
+    Off,
+    /// Switches back on, which starts over like a reset
+    On,
+    /// Swaps the cartridge for the rom in the file and starts over
+    Insert(PathBuf),
+}
+
+#[derive(Debug, Default)]
+struct PowerInner {
+    request: Mutex<Option<PowerRequest>>,
+    off: AtomicBool,
+}
+
+/// The power switch, the reset and swapping the cartridge, the gui requests them and the cpu does it
+/// before the next frame. Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Power(Arc<PowerInner>);
+impl Power {
+    pub fn request(&self, request: PowerRequest) {
+        *self.0.request.lock().unwrap() = Some(request);
+    }
+    /// Called by the cpu before every frame
+    pub fn take_request(&self) -> Option<PowerRequest> {
+        self.0.request.lock().unwrap().take()
+    }
+    /// The cpu does not run while the machine is off
+    pub fn is_off(&self) -> bool {
+        self.0.off.load(Ordering::Relaxed)
+    }
+    pub fn set_off(&self, off: bool) {
+        self.0.off.store(off, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,669 @@
+use crate::{
+    cgb::Cgb,
+    frontend::{DrawSignal, VideoSink},
+    interrupt::{self, Interrupt},
+    layers::{Layer, LayerMask, Layers},
+    ram::{Ram, OAM, VRAM},
+    state::{Reader, StateError, Writer},
+    vram::{self, IndexedImage, BGP, BG_MAPS, LCDC},
+};
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+/// Shades 0..=3, line by line from the top
+pub type FrameBuffer = [u8; SCREEN_WIDTH * SCREEN_HEIGHT];
+/// 15 bit RGB like the CGB palettes, line by line from the top
+pub type ColorFrameBuffer = [u16; SCREEN_WIDTH * SCREEN_HEIGHT];
+pub const SCY: u16 = 0xFF42;
+pub const SCX: u16 = 0xFF43;
+/// Mode in bits 0-1, LY == LYC in bit 2, bits 3-6 enable the sources of the stat interrupt
+pub const STAT: u16 = 0xFF41;
+/// The line the ppu is drawing, including the 10 lines of vblank
+pub const LY: u16 = 0xFF44;
+/// Compared with LY, sets STAT bit 2 when equal
+pub const LYC: u16 = 0xFF45;
+pub const OBP0: u16 = 0xFF48;
+pub const OBP1: u16 = 0xFF49;
+/// Top edge of the window
+pub const WY: u16 = 0xFF4A;
+/// Left edge of the window plus 7
+pub const WX: u16 = 0xFF4B;
+/// Cycles per line, drawn or not
+const LINE_CYCLES: usize = 456;
+const LINES: u8 = 154;
+/// Cycles into a line when the oam search ends and drawing starts
+const OAM_SEARCHED: usize = 80;
+/// Cycles into a line when the pixels are done, the end of the drawing mode
+const DRAWN: usize = 252;
+/// Entries in OAM
+pub const SPRITES: usize = 40;
+/// More sprites on a line are not drawn
+pub const SPRITES_PER_LINE: usize = 10;
+/// The DMG shades as 15 bit RGB, for the frame sent in color while a layer is tinted
+const GRAYS: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+/// Where every layer is hidden
+const BLANK: u16 = 0x7FFF;
+
+/// What the ppu does, shown in STAT bits 0-1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamSearch = 2,
+    Drawing = 3,
+}
+impl Mode {
+    fn at(ly: u8, dot: usize) -> Mode {
+        match dot {
+            _ if ly as usize >= SCREEN_HEIGHT => Mode::VBlank,
+            0..=OAM_SEARCHED => Mode::OamSearch,
+            _ if dot <= DRAWN => Mode::Drawing,
+            _ => Mode::HBlank,
+        }
+    }
+    /// The STAT bit enabling the interrupt when this mode starts
+    fn interrupt_bit(self) -> Option<u8> {
+        match self {
+            Mode::HBlank => Some(0x08),
+            Mode::VBlank => Some(0x10),
+            Mode::OamSearch => Some(0x20),
+            Mode::Drawing => None,
+        }
+    }
+}
+
+/// Draws the background, the window and the sprites line by line while the cpu runs.
+/// The finished frame is sent to the frontend at the start of vblank.
+#[derive(Clone, Debug)]
+pub struct Ppu {
+    /// The frame being drawn
+    frame: Box<FrameBuffer>,
+    /// The frame being drawn in CGB mode
+    color_frame: Box<ColorFrameBuffer>,
+    /// Cycles into the current line
+    dot: usize,
+    /// The or of all enabled stat sources. The interrupt is only requested when it rises,
+    /// so sources which follow each other directly request it once
+    stat_line: bool,
+    /// The next line of the window to draw. It only advances on lines which show the window,
+    /// so a window hidden for some lines continues where it stopped
+    window_line: u8,
+    layers: Layers,
+}
+impl Ppu {
+    /// Hidden and tinted from the debugger
+    pub fn with_layers(mut self, layers: Layers) -> Self {
+        self.layers = layers;
+        self
+    }
+    /// The position in the frame, the frame itself is drawn again
+    pub fn save_state(&self, w: &mut Writer) {
+        w.u32(self.dot as u32);
+        w.bool(self.stat_line);
+        w.u8(self.window_line);
+    }
+    pub fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.dot = r.u32()? as usize;
+        self.stat_line = r.bool()?;
+        self.window_line = r.u8()?;
+        Ok(())
+    }
+    /// `cgb` has the second VRAM bank and the palettes in CGB mode
+    pub fn tick(
+        &mut self,
+        ram: &mut Ram,
+        cycles: usize,
+        video: &mut dyn VideoSink,
+        cgb: Option<&Cgb>,
+    ) {
+        // LCDC bit 7 turns the lcd off, it restarts at the top
+        if ram[LCDC] & 0x80 == 0 {
+            self.dot = 0;
+            self.window_line = 0;
+            self.stat_line = false;
+            ram[LY] = 0;
+            ram[STAT] &= !0b111;
+            return;
+        }
+        for _ in 0..cycles {
+            self.dot += 1;
+            let ly = ram[LY];
+            self.update_stat(ram, ly);
+            if self.dot == DRAWN && (ly as usize) < SCREEN_HEIGHT {
+                self.scanline(ram, cgb, ly);
+            }
+            if self.dot == LINE_CYCLES {
+                self.dot = 0;
+                let ly = (ly + 1) % LINES;
+                ram[LY] = ly;
+                if ly as usize == SCREEN_HEIGHT {
+                    interrupt::request(ram, Interrupt::VBlank);
+                    self.window_line = 0;
+                    self.send_frame(video, cgb.is_some());
+                }
+            }
+        }
+    }
+    /// Sends the frame, also one which is only drawn up to the current line
+    pub fn send_frame(&self, video: &mut dyn VideoSink, cgb: bool) {
+        video.send(match cgb || self.layers.get().any_tinted() {
+            true => DrawSignal::ColorFrame(self.color_frame.clone()),
+            false => DrawSignal::Frame(self.frame.clone()),
+        });
+    }
+    /// Cycles into the current line
+    pub fn dot(&self) -> usize {
+        self.dot
+    }
+    /// Shows the mode and the LY compare in STAT and requests the stat interrupt on a rising edge
+    fn update_stat(&mut self, ram: &mut Ram, ly: u8) {
+        let mode = Mode::at(ly, self.dot);
+        let coincidence = ly == ram[LYC];
+        let stat = ram[STAT] & 0x78 | (coincidence as u8) << 2 | mode as u8;
+        ram[STAT] = stat;
+        let line = mode.interrupt_bit().is_some_and(|bit| stat & bit != 0)
+            || (coincidence && stat & 0x40 != 0);
+        if line && !self.stat_line {
+            interrupt::request(ram, Interrupt::Stat);
+        }
+        self.stat_line = line;
+    }
+    /// Called after a write to STAT, only the interrupt enable bits are writable
+    pub fn write_stat(&mut self, ram: &mut Ram, value: u8, before: u8) {
+        ram[STAT] = value & 0x78 | before & 0x07;
+    }
+    /// Draws line `ly` with the background scrolled, the window and the sprites on top.
+    /// The layers hidden in the debugger are left out, the tinted ones are mixed with their color.
+    fn scanline(&mut self, ram: &Ram, cgb: Option<&Cgb>, ly: u8) {
+        let layers = self.layers.get();
+        let vram = Vram::new(ram, cgb);
+        let mut background = background(ram, &vram, ly);
+        if self.window(ram, &vram, ly, &mut background) {
+            self.window_line += 1;
+        }
+        for pixel in &mut background {
+            if pixel.layer.is_some_and(|layer| !layers.is_visible(layer)) {
+                *pixel = BgPixel::hidden();
+            }
+        }
+        let sprites = if ram[LCDC] & 0x02 != 0 && layers.is_visible(Layer::Sprites) {
+            sprites_on_line(ram, ly, cgb.is_some())
+        } else {
+            Vec::new()
+        };
+        let start = ly as usize * SCREEN_WIDTH;
+        let line = start..start + SCREEN_WIDTH;
+        match cgb {
+            Some(cgb) => {
+                let (colors, sources) = colors(ram, &vram, cgb, ly, &background, &sprites);
+                for (x, pixel) in self.color_frame[line].iter_mut().enumerate() {
+                    *pixel = tinted(layers, colors[x], sources[x]);
+                }
+            }
+            None => {
+                let (shades, sources) = shades(ram, &vram, ly, &background, &sprites);
+                self.frame[line.clone()].copy_from_slice(&shades);
+                if layers.any_tinted() {
+                    for (x, pixel) in self.color_frame[line].iter_mut().enumerate() {
+                        *pixel = tinted(layers, GRAYS[shades[x] as usize], sources[x]);
+                    }
+                }
+            }
+        }
+    }
+    /// Draws the window over the background, returns whether it is on this line.
+    /// LCDC bit 5 enables it, bit 6 selects its map and on the DMG bit 0 hides it as well.
+    fn window(
+        &self,
+        ram: &Ram,
+        vram: &Vram,
+        ly: u8,
+        background: &mut [BgPixel; SCREEN_WIDTH],
+    ) -> bool {
+        let lcdc = ram[LCDC];
+        let left = ram[WX] as usize;
+        let enabled = if vram.cgb {
+            lcdc & 0x20 != 0
+        } else {
+            lcdc & 0x21 == 0x21
+        };
+        if !enabled || ly < ram[WY] || left >= SCREEN_WIDTH + 7 {
+            return false;
+        }
+        let map = BG_MAPS[(lcdc >> 6) as usize & 1];
+        let y = self.window_line;
+        // WX below 7 shifts the window out on the left
+        for (x, pixel) in background
+            .iter_mut()
+            .enumerate()
+            .skip(left.saturating_sub(7))
+        {
+            let x = (x + 7 - left) as u8;
+            let entry = map + (y as u16 / 8) * 32 + x as u16 / 8;
+            *pixel = BgPixel {
+                layer: Some(Layer::Window),
+                ..vram.bg_pixel(lcdc, entry, x % 8, y % 8)
+            };
+        }
+        true
+    }
+}
+impl Default for Ppu {
+    fn default() -> Self {
+        Ppu {
+            frame: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            color_frame: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            dot: 0,
+            stat_line: false,
+            window_line: 0,
+            layers: Layers::default(),
+        }
+    }
+}
+/// The VRAM banks the ppu reads, the DMG only has the first
+struct Vram<'a> {
+    banks: [&'a [u8]; 2],
+    cgb: bool,
+}
+impl<'a> Vram<'a> {
+    fn new(ram: &'a Ram, cgb: Option<&'a Cgb>) -> Self {
+        match cgb {
+            Some(cgb) => Vram {
+                banks: [cgb.vram(ram, 0), cgb.vram(ram, 1)],
+                cgb: true,
+            },
+            None => Vram {
+                banks: [&ram.bytes()[VRAM]; 2],
+                cgb: false,
+            },
+        }
+    }
+    fn get(&self, bank: u8, addr: u16) -> u8 {
+        self.banks[bank as usize & 1][addr as usize - VRAM.start]
+    }
+    /// Color index of pixel `x` in the tile row at `row`
+    fn tile_pixel(&self, bank: u8, row: u16, x: u8) -> u8 {
+        vram::pixel_index(self.get(bank, row), self.get(bank, row + 1), x)
+    }
+    /// Pixel `x`, `y` of the tile the map entry at `entry` shows. On the CGB the second
+    /// bank holds the attributes of the entry: the palette in bits 0-2, the tile bank
+    /// in bit 3, the flips in bits 5 and 6 and the priority over sprites in bit 7.
+    fn bg_pixel(&self, lcdc: u8, entry: u16, x: u8, y: u8) -> BgPixel {
+        let attributes = if self.cgb { self.get(1, entry) } else { 0 };
+        let x = if attributes & 0x20 != 0 { 7 - x } else { x };
+        let y = if attributes & 0x40 != 0 { 7 - y } else { y };
+        let row = vram::tile_addr(lcdc, self.get(0, entry)) + y as u16 * 2;
+        BgPixel {
+            index: self.tile_pixel(attributes >> 3 & 1, row, x),
+            attributes,
+            layer: Some(Layer::Background),
+        }
+    }
+}
+/// A background or window pixel before the palette
+#[derive(Clone, Copy, Debug, Default)]
+struct BgPixel {
+    index: u8,
+    /// Of its tile, always 0 on the DMG
+    attributes: u8,
+    /// `None` if the layer is hidden, the sprites are drawn over it like over color 0
+    layer: Option<Layer>,
+}
+impl BgPixel {
+    fn hidden() -> Self {
+        BgPixel {
+            layer: None,
+            ..Default::default()
+        }
+    }
+}
+/// Which layer each pixel of a line is from, `None` where the layers are hidden
+type Sources = [Option<Layer>; SCREEN_WIDTH];
+/// The color of a pixel with the layers of `layers` tinted, blank where they are hidden
+fn tinted(layers: LayerMask, color: u16, source: Option<Layer>) -> u16 {
+    match source {
+        Some(layer) => layers.color(color, layer),
+        None => BLANK,
+    }
+}
+/// The first sprite with a visible pixel at `x` and its color index
+fn sprite_pixel(
+    sprites: &[Sprite],
+    vram: &Vram,
+    lcdc: u8,
+    ly: u8,
+    x: usize,
+) -> Option<(Sprite, u8)> {
+    sprites
+        .iter()
+        .find_map(|sprite| Some((*sprite, sprite.pixel(vram, lcdc, ly, x)?)))
+}
+/// The shades after the palettes, with the sprites over the background
+fn shades(
+    ram: &Ram,
+    vram: &Vram,
+    ly: u8,
+    background: &[BgPixel; SCREEN_WIDTH],
+    sprites: &[Sprite],
+) -> ([u8; SCREEN_WIDTH], Sources) {
+    let lcdc = ram[LCDC];
+    let mut line = [0; SCREEN_WIDTH];
+    let mut sources = [None; SCREEN_WIDTH];
+    for (x, shade) in line.iter_mut().enumerate() {
+        let bg = background[x];
+        if bg.layer.is_some() {
+            *shade = (ram[BGP] >> (bg.index * 2)) & 0b11;
+            sources[x] = bg.layer;
+        }
+        // the first sprite with a visible pixel wins, even if the background covers it
+        let Some((sprite, index)) = sprite_pixel(sprites, vram, lcdc, ly, x) else {
+            continue;
+        };
+        if !sprite.behind_background() || bg.index == 0 {
+            *shade = (ram[sprite.palette()] >> (index * 2)) & 0b11;
+            sources[x] = Some(Layer::Sprites);
+        }
+    }
+    (line, sources)
+}
+/// The colors after the CGB palettes. With LCDC bit 0 cleared the sprites are always on top,
+/// otherwise the background covers them if the sprite or the tile asks for it.
+fn colors(
+    ram: &Ram,
+    vram: &Vram,
+    cgb: &Cgb,
+    ly: u8,
+    background: &[BgPixel; SCREEN_WIDTH],
+    sprites: &[Sprite],
+) -> ([u16; SCREEN_WIDTH], Sources) {
+    let lcdc = ram[LCDC];
+    let mut line = [0; SCREEN_WIDTH];
+    let mut sources = [None; SCREEN_WIDTH];
+    for (x, color) in line.iter_mut().enumerate() {
+        let bg = background[x];
+        *color = cgb.bg_color(bg.attributes & 0x07, bg.index);
+        sources[x] = bg.layer;
+        let Some((sprite, index)) = sprite_pixel(sprites, vram, lcdc, ly, x) else {
+            continue;
+        };
+        let covered = lcdc & 0x01 != 0
+            && bg.index != 0
+            && (sprite.behind_background() || bg.attributes & 0x80 != 0);
+        if !covered {
+            *color = cgb.obj_color(sprite.flags & 0x07, index);
+            sources[x] = Some(Layer::Sprites);
+        }
+    }
+    (line, sources)
+}
+/// The background before the palettes, all 0 with LCDC bit 0 cleared on the DMG
+fn background(ram: &Ram, vram: &Vram, ly: u8) -> [BgPixel; SCREEN_WIDTH] {
+    let blank = BgPixel {
+        layer: Some(Layer::Background),
+        ..Default::default()
+    };
+    let mut line = [blank; SCREEN_WIDTH];
+    let lcdc = ram[LCDC];
+    if lcdc & 0x01 == 0 && !vram.cgb {
+        return line;
+    }
+    let map = BG_MAPS[(lcdc >> 3) as usize & 1];
+    let y = ly.wrapping_add(ram[SCY]);
+    for (x, pixel) in line.iter_mut().enumerate() {
+        let x = (x as u8).wrapping_add(ram[SCX]);
+        let entry = map + (y as u16 / 8) * 32 + x as u16 / 8;
+        *pixel = vram.bg_pixel(lcdc, entry, x % 8, y % 8);
+    }
+    line
+}
+
+/// An entry in OAM
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+    /// Screen position plus 16
+    pub y: u8,
+    /// Screen position plus 8
+    pub x: u8,
+    pub tile: u8,
+    /// Priority, flips and palette, on the CGB also the tile bank and the color palette
+    pub flags: u8,
+}
+impl Sprite {
+    pub fn read(ram: &Ram, index: usize) -> Self {
+        let addr = OAM.start as u16 + index as u16 * 4;
+        Sprite {
+            y: ram[addr],
+            x: ram[addr + 1],
+            tile: ram[addr + 2],
+            flags: ram[addr + 3],
+        }
+    }
+    pub fn behind_background(self) -> bool {
+        self.flags & 0x80 != 0
+    }
+    pub fn flip_y(self) -> bool {
+        self.flags & 0x40 != 0
+    }
+    pub fn flip_x(self) -> bool {
+        self.flags & 0x20 != 0
+    }
+    /// OBP0 or OBP1
+    pub fn palette(self) -> u16 {
+        if self.flags & 0x10 != 0 {
+            OBP1
+        } else {
+            OBP0
+        }
+    }
+    /// The color index at screen column `x`, `None` if transparent or outside the sprite.
+    /// On the CGB flag bit 3 selects the VRAM bank of the tile.
+    fn pixel(self, vram: &Vram, lcdc: u8, ly: u8, x: usize) -> Option<u8> {
+        let col = (x + 8)
+            .checked_sub(self.x as usize)
+            .filter(|col| *col < 8)? as u8;
+        let row = (ly as u16 + 16 - self.y as u16) as u8;
+        Some(self.tile_pixel(vram, sprite_height(lcdc), row, col)).filter(|index| *index != 0)
+    }
+    /// The color index at `row`, `col` of the sprite before flipping
+    fn tile_pixel(self, vram: &Vram, height: u8, row: u8, col: u8) -> u8 {
+        let col = if self.flip_x() { 7 - col } else { col };
+        let row = if self.flip_y() { height - 1 - row } else { row };
+        // tall sprites ignore bit 0 of the tile number
+        let tile = if height == 16 {
+            self.tile & 0xFE
+        } else {
+            self.tile
+        };
+        let addr = vram::TILE_DATA + tile as u16 * 16 + row as u16 * 2;
+        let bank = if vram.cgb { self.flags >> 3 & 1 } else { 0 };
+        vram.tile_pixel(bank, addr, col)
+    }
+    /// Whether the sprite covers line `ly` with the sprite height of `lcdc`
+    pub fn covers_line(self, lcdc: u8, ly: u8) -> bool {
+        let line = ly as u16 + 16;
+        (self.y as u16..self.y as u16 + sprite_height(lcdc) as u16).contains(&line)
+    }
+    /// The sprite as it is drawn, flipped and with the color indices before the palette,
+    /// for the sprite viewer
+    pub fn image(self, ram: &Ram, cgb: Option<&Cgb>) -> IndexedImage {
+        let vram = Vram::new(ram, cgb);
+        let height = sprite_height(ram[LCDC]);
+        let mut image = IndexedImage::new(8, height as usize);
+        for row in 0..height {
+            for col in 0..8 {
+                image.pixels[row as usize * 8 + col as usize] =
+                    self.tile_pixel(&vram, height, row, col);
+            }
+        }
+        image
+    }
+}
+/// 8x8 or with LCDC bit 2 8x16
+pub fn sprite_height(lcdc: u8) -> u8 {
+    if lcdc & 0x04 != 0 {
+        16
+    } else {
+        8
+    }
+}
+/// The first 10 sprites in OAM covering line `ly`, by priority: on the DMG lower x first,
+/// then OAM order, on the CGB only OAM order
+fn sprites_on_line(ram: &Ram, ly: u8, cgb: bool) -> Vec<Sprite> {
+    let lcdc = ram[LCDC];
+    let mut sprites: Vec<Sprite> = (0..SPRITES)
+        .map(|index| Sprite::read(ram, index))
+        .filter(|sprite| sprite.covers_line(lcdc, ly))
+        .take(SPRITES_PER_LINE)
+        .collect();
+    if !cgb {
+        sprites.sort_by_key(|sprite| sprite.x);
+    }
+    sprites
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use crate::{
+        frontend::{DrawSignal, Null},
+        interrupt::{Interrupt, IF},
+        layers::{Layer, Layers},
+        ram::{Ram, OAM},
+        vram::{BGP, BG_MAPS, LCDC},
+    };
+
+    use super::*;
+
+    /// Draws a frame with tile 1 at the left edge, scrolled by 4 pixels, and sends it at vblank
+    #[test]
+    fn background_scanline() -> Result<(), String> {
+        let mut ram = Ram::default();
+        // lcd and background on, tiles from 0x8000, map at 0x9800
+        ram[LCDC] = 0x91;
+        ram[BGP] = 0b11_10_01_00;
+        ram[SCX] = 4;
+        ram[0x8010] = 0xFF;
+        ram[BG_MAPS[0]] = 1;
+        let line = first_frame(&mut ram)?;
+        let expected: Vec<u8> = (0..8).map(|x| u8::from(x < 4)).collect();
+        if line[..8] != expected {
+            return Err(format!("line starts with {:?}", &line[..8]));
+        }
+        if ram[LY] as usize != SCREEN_HEIGHT {
+            return Err(format!("stopped at line {}", ram[LY]));
+        }
+        if ram[IF] & Interrupt::VBlank.bit() == 0 {
+            return Err("vblank was not requested".to_string());
+        }
+        Ok(())
+    }
+    /// A sprite at the top left corner whose only pixel moves to its right edge with x flip
+    #[test]
+    fn flipped_sprite() -> Result<(), String> {
+        let mut ram = Ram::default();
+        // lcd, background and sprites on, background tiles from 0x8000
+        ram[LCDC] = 0x93;
+        ram[OBP0] = 0b11_10_01_00;
+        let oam = OAM.start as u16;
+        // y and x are offset by 16 and 8, tile 2 with x flip
+        (ram[oam], ram[oam + 1], ram[oam + 2], ram[oam + 3]) = (16, 8, 2, 0x20);
+        ram[0x8020] = 0x80;
+        let line = first_frame(&mut ram)?;
+        if line[..8] != [0, 0, 0, 0, 0, 0, 0, 1] {
+            return Err(format!("line starts with {:?}", &line[..8]));
+        }
+        Ok(())
+    }
+    /// The window covers the right half from its map at 0x9C00, the background stays blank
+    #[test]
+    fn window_layer() -> Result<(), String> {
+        let mut ram = Ram::default();
+        // lcd, window and background on, window map at 0x9C00, tiles from 0x8000
+        ram[LCDC] = 0xF1;
+        ram[BGP] = 0b11_10_01_00;
+        (ram[WY], ram[WX]) = (0, 7 + 80);
+        ram[0x8010] = 0xFF;
+        for x in 0..32 {
+            ram[BG_MAPS[1] + x] = 1;
+        }
+        let line = first_frame(&mut ram)?;
+        if line[79] != 0 || line[80] != 1 {
+            return Err(format!(
+                "the window starts at {:?}",
+                line.iter().position(|shade| *shade == 1)
+            ));
+        }
+        Ok(())
+    }
+    /// A hidden window shows the background under it, a tinted background comes in color
+    #[test]
+    fn layer_toggles() -> Result<(), String> {
+        let mut ram = Ram::default();
+        ram[LCDC] = 0xF1;
+        ram[BGP] = 0b11_10_01_00;
+        (ram[WY], ram[WX]) = (0, 7 + 80);
+        ram[0x8010] = 0xFF;
+        for x in 0..32 {
+            ram[BG_MAPS[1] + x] = 1;
+        }
+        let layers = Layers::default();
+        layers.set_visible(Layer::Window, false);
+        let mut ppu = Ppu::default().with_layers(layers.clone());
+        let (mut sender, receiver) = mpsc::channel();
+        ppu.tick(&mut ram, 456 * SCREEN_HEIGHT, &mut sender, None);
+        match receiver.try_recv() {
+            Ok(DrawSignal::Frame(line)) if line[80] == 0 => {}
+            Ok(DrawSignal::Frame(line)) => {
+                return Err(format!("the hidden window drew shade {}", line[80]))
+            }
+            _ => return Err("no frame was sent at vblank".to_string()),
+        }
+        layers.set_visible(Layer::Window, true);
+        layers.set_tinted(Layer::Background, true);
+        ppu.tick(&mut ram, 456 * 154, &mut sender, None);
+        let expected = layers.get().color(0x7FFF, Layer::Background);
+        match receiver.try_recv() {
+            Ok(DrawSignal::ColorFrame(line)) if line[0] == expected && line[80] == 0x56B5 => Ok(()),
+            Ok(DrawSignal::ColorFrame(line)) => Err(format!(
+                "the tinted line starts with {:#06x} and the window with {:#06x}",
+                line[0], line[80]
+            )),
+            _ => Err("no color frame was sent while tinting".to_string()),
+        }
+    }
+    /// Drawing starts after the oam search, and LY reaching LYC requests the stat interrupt
+    #[test]
+    fn stat_lyc() -> Result<(), String> {
+        let mut ram = Ram::default();
+        ram[LCDC] = 0x91;
+        ram[LYC] = 2;
+        // only the LY == LYC source is enabled
+        ram[STAT] = 0x40;
+        let mut ppu = Ppu::default();
+        ppu.tick(&mut ram, 100, &mut Null, None);
+        if ram[STAT] & 0b11 != 3 {
+            return Err(format!("mode {} while drawing", ram[STAT] & 0b11));
+        }
+        if ram[IF] & Interrupt::Stat.bit() != 0 {
+            return Err("stat interrupt before LY reached LYC".to_string());
+        }
+        ppu.tick(&mut ram, 2 * 456, &mut Null, None);
+        if ram[LY] != 2 || ram[STAT] & 0x04 == 0 {
+            return Err(format!("LY {} with STAT {:#04x}", ram[LY], ram[STAT]));
+        }
+        if ram[IF] & Interrupt::Stat.bit() == 0 {
+            return Err("LY == LYC did not request the stat interrupt".to_string());
+        }
+        Ok(())
+    }
+    /// Runs the ppu until it sends the first frame
+    fn first_frame(ram: &mut Ram) -> Result<Box<FrameBuffer>, String> {
+        let (mut sender, receiver) = mpsc::channel();
+        Ppu::default().tick(ram, 456 * SCREEN_HEIGHT, &mut sender, None);
+        match receiver.try_recv() {
+            Ok(DrawSignal::Frame(frame)) => Ok(frame),
+            _ => Err("no frame was sent at vblank".to_string()),
+        }
+    }
+}
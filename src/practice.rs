@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    cpu::{CLOCK_SPEED, CYCLES_PER_FRAME},
+    ram::Ram,
+};
+
+/// Frames per second of the emulated display, rounded
+const FPS: u32 = (CLOCK_SPEED / CYCLES_PER_FRAME) as u32;
+
+/// A memory condition which takes a snapshot, e.g. the room number changing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watch {
+    /// `C0A4`: the byte changed since the last frame
+    Changed(u16),
+    /// `C0A4=05`: the byte became the value
+    Equals(u16, u8),
+}
+impl Watch {
+    /// Parses `ADDR` or `ADDR=VALUE`, both in hex
+    pub fn parse(s: &str) -> Option<Watch> {
+        fn hex(s: &str) -> &str {
+            s.trim().trim_start_matches("0x")
+        }
+        match s.split_once('=') {
+            Some((addr, value)) => Some(Watch::Equals(
+                u16::from_str_radix(hex(addr), 16).ok()?,
+                u8::from_str_radix(hex(value), 16).ok()?,
+            )),
+            None => Some(Watch::Changed(u16::from_str_radix(hex(s), 16).ok()?)),
+        }
+    }
+    fn addr(self) -> u16 {
+        match self {
+            Watch::Changed(addr) | Watch::Equals(addr, _) => addr,
+        }
+    }
+    fn triggered(self, previous: u8, now: u8) -> bool {
+        match self {
+            Watch::Changed(_) => previous != now,
+            Watch::Equals(_, value) => previous != value && now == value,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PracticeInner {
+    enabled: bool,
+    /// Seconds between snapshots, 0 only snapshots on the watches
+    interval: u32,
+    watches: Vec<Watch>,
+    /// Values of the watched bytes at the end of the last frame
+    previous: Vec<u8>,
+    frames: u32,
+    retry: bool,
+    latest: Option<Vec<u8>>,
+    taken: u32,
+}
+
+/// Speedrun practice: snapshots are taken automatically and a single key goes back
+/// to the latest one. Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Practice(Arc<Mutex<PracticeInner>>);
+impl Practice {
+    pub fn enabled(&self) -> bool {
+        self.0.lock().unwrap().enabled
+    }
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut inner = self.0.lock().unwrap();
+        inner.enabled = enabled;
+        inner.latest = None;
+        inner.taken = 0;
+    }
+    pub fn interval(&self) -> u32 {
+        self.0.lock().unwrap().interval
+    }
+    pub fn set_interval(&self, seconds: u32) {
+        self.0.lock().unwrap().interval = seconds;
+    }
+    pub fn set_watches(&self, watches: Vec<Watch>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.previous.clear();
+        inner.watches = watches;
+    }
+    /// Snapshots taken since practice was enabled
+    pub fn taken(&self) -> u32 {
+        self.0.lock().unwrap().taken
+    }
+    pub fn has_snapshot(&self) -> bool {
+        self.0.lock().unwrap().latest.is_some()
+    }
+    /// The cpu loads the latest snapshot after the current frame
+    pub fn retry(&self) {
+        self.0.lock().unwrap().retry = true;
+    }
+    /// Called by the cpu after every frame, returns the snapshot to load on a retry
+    pub fn take_retry(&self) -> Option<Vec<u8>> {
+        let mut inner = self.0.lock().unwrap();
+        if !std::mem::take(&mut inner.retry) {
+            return None;
+        }
+        inner.previous.clear();
+        inner.frames = 0;
+        inner.latest.clone()
+    }
+    /// Called by the cpu after every frame, returns whether a snapshot is due
+    pub fn frame_done(&self, ram: &Ram) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        if !inner.enabled {
+            return false;
+        }
+        inner.frames += 1;
+        let timed = inner.interval > 0 && inner.frames >= inner.interval * FPS;
+        let now: Vec<u8> = inner
+            .watches
+            .iter()
+            .map(|watch| ram[watch.addr()])
+            .collect();
+        let watched = inner.previous.len() == now.len()
+            && inner
+                .watches
+                .iter()
+                .zip(inner.previous.iter().zip(&now))
+                .any(|(watch, (previous, now))| watch.triggered(*previous, *now));
+        inner.previous = now;
+        // the first frame after enabling always has a snapshot to go back to
+        timed || watched || inner.latest.is_none()
+    }
+    pub fn store(&self, snapshot: Vec<u8>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.latest = Some(snapshot);
+        inner.frames = 0;
+        inner.taken += 1;
+    }
+}
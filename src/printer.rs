@@ -0,0 +1,207 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::{camera::GRAYSCALE, serial::SerialDevice, vram::IndexedImage};
+
+const MAGIC: [u8; 2] = [0x88, 0x33];
+const INIT: u8 = 0x01;
+const PRINT: u8 = 0x02;
+const DATA: u8 = 0x04;
+const STATUS: u8 = 0x0F;
+/// Sent back during the first byte after the checksum
+const DEVICE_ID: u8 = 0x81;
+/// Bits of the status byte
+const CHECKSUM_ERROR: u8 = 0x01;
+const PRINTING: u8 = 0x02;
+const UNPROCESSED: u8 = 0x08;
+/// The print buffer holds 9 packets of 2 rows of 20 tiles
+const BUFFER_SIZE: usize = 9 * 640;
+const WIDTH_TILES: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Magic(usize),
+    Command,
+    Compression,
+    Length(usize),
+    Data,
+    Checksum(usize),
+    /// The printer answers with its id, then with the status
+    Reply(usize),
+}
+
+/// The Game Boy Printer. Collects the image data games send in packets and
+/// writes every printed page as a png.
+pub struct Printer {
+    dir: PathBuf,
+    field: Field,
+    command: u8,
+    compressed: bool,
+    length: usize,
+    data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+    /// Decompressed image data since the last print
+    buffer: Vec<u8>,
+    status: u8,
+    pages: usize,
+}
+impl Printer {
+    /// Pages are written into `dir`
+    pub fn new(dir: PathBuf) -> Self {
+        Printer {
+            dir,
+            field: Field::Magic(0),
+            command: 0,
+            compressed: false,
+            length: 0,
+            data: Vec::new(),
+            checksum: 0,
+            received_checksum: 0,
+            buffer: Vec::new(),
+            status: 0,
+            pages: 0,
+        }
+    }
+    fn packet_done(&mut self) {
+        if self.checksum != self.received_checksum {
+            self.status |= CHECKSUM_ERROR;
+            return;
+        }
+        self.status &= !CHECKSUM_ERROR;
+        match self.command {
+            INIT => {
+                self.buffer.clear();
+                self.status = 0;
+            }
+            DATA => {
+                let data = std::mem::take(&mut self.data);
+                if self.compressed {
+                    decompress(&data, &mut self.buffer);
+                } else {
+                    self.buffer.extend(data);
+                }
+                self.buffer.truncate(BUFFER_SIZE);
+                self.status |= UNPROCESSED;
+            }
+            PRINT => {
+                // the third byte of the arguments is the palette
+                let palette = self.data.get(2).copied().unwrap_or(0xE4);
+                if let Err(e) = self.print(palette) {
+                    log::warn!("could not save the printed page: {e}");
+                }
+                self.buffer.clear();
+                // reported once, the page prints instantly
+                self.status = PRINTING;
+            }
+            STATUS => {}
+            command => log::debug!("unknown printer command {command:#04x}"),
+        }
+    }
+    fn print(&mut self, palette: u8) -> io::Result<()> {
+        let rows = self.buffer.len() / (WIDTH_TILES * 16);
+        if rows == 0 {
+            return Ok(());
+        }
+        let mut image = IndexedImage::new(WIDTH_TILES * 8, rows * 8);
+        for (tile, bytes) in self.buffer.chunks_exact(16).enumerate() {
+            let (x, y) = (tile % WIDTH_TILES * 8, tile / WIDTH_TILES * 8);
+            for row in 0..8 {
+                let (low, high) = (bytes[row * 2], bytes[row * 2 + 1]);
+                for col in 0..8 {
+                    let bit = 7 - col;
+                    let index = (((high >> bit) & 1) << 1) | ((low >> bit) & 1);
+                    image.pixels[(y + row) * image.width + x + col] =
+                        (palette >> (index * 2)) & 0b11;
+                }
+            }
+        }
+        fs::create_dir_all(&self.dir)?;
+        self.pages += 1;
+        let path = self.dir.join(format!("page-{}.png", self.pages));
+        image.save_png(&path, &GRAYSCALE)?;
+        log::info!("printed {}", path.display());
+        Ok(())
+    }
+}
+/// Runs of `(n & 0x7F) + 2` copies of the next byte if the high bit is set,
+/// else `n + 1` bytes as they are
+fn decompress(data: &[u8], out: &mut Vec<u8>) {
+    let mut bytes = data.iter().copied();
+    while let Some(n) = bytes.next() {
+        if n & 0x80 != 0 {
+            let byte = bytes.next().unwrap_or(0);
+            out.extend(std::iter::repeat_n(byte, (n & 0x7F) as usize + 2));
+        } else {
+            out.extend(bytes.by_ref().take(n as usize + 1));
+        }
+    }
+}
+impl SerialDevice for Printer {
+    fn name(&self) -> &str {
+        "printer"
+    }
+    fn exchange(&mut self, sent: u8) -> u8 {
+        let mut reply = 0x00;
+        self.field = match self.field {
+            Field::Magic(0) if sent == MAGIC[0] => Field::Magic(1),
+            Field::Magic(1) if sent == MAGIC[1] => Field::Command,
+            Field::Magic(_) if sent == MAGIC[0] => Field::Magic(1),
+            Field::Magic(_) => Field::Magic(0),
+            Field::Command => {
+                self.command = sent;
+                self.checksum = sent as u16;
+                Field::Compression
+            }
+            Field::Compression => {
+                self.compressed = sent & 1 != 0;
+                self.checksum = self.checksum.wrapping_add(sent as u16);
+                Field::Length(0)
+            }
+            Field::Length(0) => {
+                self.length = sent as usize;
+                self.checksum = self.checksum.wrapping_add(sent as u16);
+                Field::Length(1)
+            }
+            Field::Length(_) => {
+                self.length |= (sent as usize) << 8;
+                self.checksum = self.checksum.wrapping_add(sent as u16);
+                self.data.clear();
+                if self.length == 0 {
+                    Field::Checksum(0)
+                } else {
+                    Field::Data
+                }
+            }
+            Field::Data => {
+                self.data.push(sent);
+                self.checksum = self.checksum.wrapping_add(sent as u16);
+                if self.data.len() == self.length {
+                    Field::Checksum(0)
+                } else {
+                    Field::Data
+                }
+            }
+            Field::Checksum(0) => {
+                self.received_checksum = sent as u16;
+                Field::Checksum(1)
+            }
+            Field::Checksum(_) => {
+                self.received_checksum |= (sent as u16) << 8;
+                Field::Reply(0)
+            }
+            Field::Reply(0) => {
+                reply = DEVICE_ID;
+                Field::Reply(1)
+            }
+            Field::Reply(_) => {
+                self.packet_done();
+                reply = self.status;
+                if self.command == STATUS {
+                    self.status &= !PRINTING;
+                }
+                Field::Magic(0)
+            }
+        };
+        reply
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    cpu::CpuMode,
+    frontend::{Buttons, DrawSignal},
+    ppu::{ColorFrameBuffer, FrameBuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
+    state::{Reader, StateError, Writer},
+};
+
+/// Bumped whenever a message changes or is removed. New messages get a new tag,
+/// old frontends reject them as unknown instead of misreading them.
+pub const PROTOCOL_VERSION: u16 = 1;
+/// Messages above this size are rejected before reading them
+const MAX_MESSAGE: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// The other side speaks this version
+    Version(u16),
+    /// The first message was not `Hello`
+    NoHello,
+    UnknownMessage(u8),
+    Truncated,
+    TooLarge(usize),
+    Io(io::Error),
+}
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Version(version) => write!(
+                f,
+                "the other side speaks protocol {version}, this is {PROTOCOL_VERSION}"
+            ),
+            ProtocolError::NoHello => write!(f, "the other side did not say hello"),
+            ProtocolError::UnknownMessage(tag) => write!(f, "unknown message {tag}"),
+            ProtocolError::Truncated => write!(f, "the message ends early"),
+            ProtocolError::TooLarge(len) => write!(f, "a message of {len} bytes is too large"),
+            ProtocolError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+impl From<io::Error> for ProtocolError {
+    fn from(e: io::Error) -> Self {
+        ProtocolError::Io(e)
+    }
+}
+impl From<StateError> for ProtocolError {
+    fn from(e: StateError) -> Self {
+        match e {
+            StateError::Io(e) => ProtocolError::Io(e),
+            _ => ProtocolError::Truncated,
+        }
+    }
+}
+
+/// What a frontend asks the core to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Pause,
+    Resume,
+    Reset,
+    /// Runs this many instructions, then pauses
+    Step(u32),
+    SaveState(u8),
+    LoadState(u8),
+}
+
+/// Everything exchanged between the core and an out of process frontend.
+/// Both sides start with `Hello`, all numbers are little endian.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Hello {
+        version: u16,
+    },
+    /// Shades 0 to 3, one byte per pixel
+    Frame(Box<FrameBuffer>),
+    /// The cpu stopped executing at the program counter
+    CpuMode(CpuMode, u16),
+    Audio {
+        sample_rate: u32,
+        samples: Vec<[f32; 2]>,
+    },
+    Input(Buttons),
+    Control(Control),
+    /// 15 bit RGB, two bytes per pixel
+    ColorFrame(Box<ColorFrameBuffer>),
+}
+impl Message {
+    pub fn hello() -> Self {
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+        }
+    }
+    /// A tag byte followed by the fields
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        match self {
+            Message::Hello { version } => {
+                w.u8(0);
+                w.u16(*version);
+            }
+            Message::Frame(frame) => {
+                w.u8(1);
+                w.bytes(&frame[..]);
+            }
+            Message::CpuMode(mode, pc) => {
+                w.u8(2);
+                match mode {
+                    CpuMode::Run => w.u8(0),
+                    CpuMode::Halt => w.u8(1),
+                    CpuMode::Stop => w.u8(2),
+                    CpuMode::Locked { op } => {
+                        w.u8(3);
+                        w.u8(*op);
+                    }
+                    CpuMode::Shutdown => w.u8(4),
+                    CpuMode::_DebugGpu => w.u8(5),
+                }
+                w.u16(*pc);
+            }
+            Message::Audio {
+                sample_rate,
+                samples,
+            } => {
+                w.u8(3);
+                w.u32(*sample_rate);
+                w.u32(samples.len() as u32);
+                for [left, right] in samples {
+                    w.f32(*left);
+                    w.f32(*right);
+                }
+            }
+            Message::Input(buttons) => {
+                w.u8(4);
+                w.u8(buttons.0);
+            }
+            Message::Control(control) => {
+                w.u8(5);
+                match control {
+                    Control::Pause => w.u8(0),
+                    Control::Resume => w.u8(1),
+                    Control::Reset => w.u8(2),
+                    Control::Step(count) => {
+                        w.u8(3);
+                        w.u32(*count);
+                    }
+                    Control::SaveState(slot) => {
+                        w.u8(4);
+                        w.u8(*slot);
+                    }
+                    Control::LoadState(slot) => {
+                        w.u8(5);
+                        w.u8(*slot);
+                    }
+                }
+            }
+            Message::ColorFrame(frame) => {
+                w.u8(6);
+                for color in frame.iter() {
+                    w.u16(*color);
+                }
+            }
+        }
+        w.finish()
+    }
+    pub fn decode(bytes: &[u8]) -> Result<Message, ProtocolError> {
+        let mut r = Reader::new(bytes);
+        let tag = r.u8()?;
+        Ok(match tag {
+            0 => Message::Hello { version: r.u16()? },
+            1 => {
+                let mut frame: Box<FrameBuffer> = Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]);
+                frame.copy_from_slice(r.bytes(SCREEN_WIDTH * SCREEN_HEIGHT)?);
+                Message::Frame(frame)
+            }
+            2 => {
+                let mode = match r.u8()? {
+                    0 => CpuMode::Run,
+                    1 => CpuMode::Halt,
+                    2 => CpuMode::Stop,
+                    3 => CpuMode::Locked { op: r.u8()? },
+                    4 => CpuMode::Shutdown,
+                    5 => CpuMode::_DebugGpu,
+                    _ => return Err(ProtocolError::UnknownMessage(tag)),
+                };
+                Message::CpuMode(mode, r.u16()?)
+            }
+            3 => {
+                let sample_rate = r.u32()?;
+                let len = r.u32()? as usize;
+                if len * 8 > MAX_MESSAGE {
+                    return Err(ProtocolError::TooLarge(len * 8));
+                }
+                let mut samples = Vec::with_capacity(len);
+                for _ in 0..len {
+                    samples.push([r.f32()?, r.f32()?]);
+                }
+                Message::Audio {
+                    sample_rate,
+                    samples,
+                }
+            }
+            4 => Message::Input(Buttons(r.u8()?)),
+            5 => Message::Control(match r.u8()? {
+                0 => Control::Pause,
+                1 => Control::Resume,
+                2 => Control::Reset,
+                3 => Control::Step(r.u32()?),
+                4 => Control::SaveState(r.u8()?),
+                5 => Control::LoadState(r.u8()?),
+                _ => return Err(ProtocolError::UnknownMessage(tag)),
+            }),
+            6 => {
+                let mut frame: Box<ColorFrameBuffer> = Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]);
+                for color in frame.iter_mut() {
+                    *color = r.u16()?;
+                }
+                Message::ColorFrame(frame)
+            }
+            _ => return Err(ProtocolError::UnknownMessage(tag)),
+        })
+    }
+    /// Writes the message with its length in front, for stream transports
+    pub fn write_to(&self, out: &mut impl Write) -> Result<(), ProtocolError> {
+        let bytes = self.encode();
+        out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        out.write_all(&bytes)?;
+        Ok(())
+    }
+    pub fn read_from(input: &mut impl Read) -> Result<Message, ProtocolError> {
+        let mut len = [0; 4];
+        input.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_MESSAGE {
+            return Err(ProtocolError::TooLarge(len));
+        }
+        let mut bytes = vec![0; len];
+        input.read_exact(&mut bytes)?;
+        Message::decode(&bytes)
+    }
+}
+impl From<DrawSignal> for Message {
+    fn from(signal: DrawSignal) -> Self {
+        match signal {
+            DrawSignal::Frame(frame) => Message::Frame(frame),
+            DrawSignal::ColorFrame(frame) => Message::ColorFrame(frame),
+            DrawSignal::CpuMode(mode, pc) => Message::CpuMode(mode, pc),
+        }
+    }
+}
+/// Checks the `Hello` the other side sent first
+pub fn check_hello(message: &Message) -> Result<(), ProtocolError> {
+    match message {
+        Message::Hello {
+            version: PROTOCOL_VERSION,
+        } => Ok(()),
+        Message::Hello { version } => Err(ProtocolError::Version(*version)),
+        _ => Err(ProtocolError::NoHello),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::CpuMode,
+        frontend::Buttons,
+        ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    };
+
+    use super::*;
+
+    /// Every message survives the stream framing, other versions are refused
+    #[test]
+    fn protocol_messages() -> Result<(), String> {
+        let messages = [
+            Message::hello(),
+            Message::Frame(Box::new([2; SCREEN_WIDTH * SCREEN_HEIGHT])),
+            Message::CpuMode(CpuMode::Locked { op: 0xD3 }, 0x0150),
+            Message::Audio {
+                sample_rate: 48_000,
+                samples: vec![[0.5, -0.25]; 3],
+            },
+            Message::Input(Buttons(Buttons::A | Buttons::START)),
+            Message::Control(Control::Step(10)),
+            Message::Control(Control::LoadState(3)),
+        ];
+        let mut stream = Vec::new();
+        for message in &messages {
+            message.write_to(&mut stream).map_err(|e| e.to_string())?;
+        }
+        let mut input = &stream[..];
+        for message in &messages {
+            let read = Message::read_from(&mut input).map_err(|e| e.to_string())?;
+            if read != *message {
+                return Err(format!("{message:?} came back as {read:?}"));
+            }
+        }
+        let future = Message::Hello {
+            version: PROTOCOL_VERSION + 1,
+        };
+        if check_hello(&future).is_ok() || Message::decode(&[0xFF]).is_ok() {
+            return Err("another version or an unknown message was accepted".to_string());
+        }
+        Ok(())
+    }
+}
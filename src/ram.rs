@@ -1,24 +1,107 @@
-use std::ops::{Index, IndexMut};
-const BIOS: [u8; 256] = [
-    0x31, 0xFE, 0xFF, 0xAF, 0x21, 0xFF, 0x9F, 0x32, 0xCB, 0x7C, 0x20, 0xFB, 0x21, 0x26, 0xFF, 0x0E,
-    0x11, 0x3E, 0x80, 0x32, 0xE2, 0x0C, 0x3E, 0xF3, 0xE2, 0x32, 0x3E, 0x77, 0x77, 0x3E, 0xFC, 0xE0,
-    0x47, 0x11, 0x04, 0x01, 0x21, 0x10, 0x80, 0x1A, 0xCD, 0x95, 0x00, 0xCD, 0x96, 0x00, 0x13, 0x7B,
-    0xFE, 0x34, 0x20, 0xF3, 0x11, 0xD8, 0x00, 0x06, 0x08, 0x1A, 0x13, 0x22, 0x23, 0x05, 0x20, 0xF9,
-    0x3E, 0x19, 0xEA, 0x10, 0x99, 0x21, 0x2F, 0x99, 0x0E, 0x0C, 0x3D, 0x28, 0x08, 0x32, 0x0D, 0x20,
-    0xF9, 0x2E, 0x0F, 0x18, 0xF3, 0x67, 0x3E, 0x64, 0x57, 0xE0, 0x42, 0x3E, 0x91, 0xE0, 0x40, 0x04,
-    0x1E, 0x02, 0x0E, 0x0C, 0xF0, 0x44, 0xFE, 0x90, 0x20, 0xFA, 0x0D, 0x20, 0xF7, 0x1D, 0x20, 0xF2,
-    0x0E, 0x13, 0x24, 0x7C, 0x1E, 0x83, 0xFE, 0x62, 0x28, 0x06, 0x1E, 0xC1, 0xFE, 0x64, 0x20, 0x06,
-    0x7B, 0xE2, 0x0C, 0x3E, 0x87, 0xF2, 0xF0, 0x42, 0x90, 0xE0, 0x42, 0x15, 0x20, 0xD2, 0x05, 0x20,
-    0x4F, 0x16, 0x20, 0x18, 0xCB, 0x4F, 0x06, 0x04, 0xC5, 0xCB, 0x11, 0x17, 0xC1, 0xCB, 0x11, 0x17,
-    0x05, 0x20, 0xF5, 0x22, 0x23, 0x22, 0x23, 0xC9, 0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B,
-    0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E,
-    0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC,
-    0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E, 0x3c, 0x42, 0xB9, 0xA5, 0xB9, 0xA5, 0x42, 0x4C,
-    0x21, 0x04, 0x01, 0x11, 0xA8, 0x00, 0x1A, 0x13, 0xBE, 0x20, 0xFE, 0x23, 0x7D, 0xFE, 0x34, 0x20,
-    0xF5, 0x06, 0x19, 0x78, 0x86, 0x23, 0x05, 0x20, 0xFB, 0x86, 0x20, 0xFE, 0x3E, 0x01, 0xE0, 0x50,
-];
+use std::{
+    fs, io,
+    ops::{Index, IndexMut, Range},
+    path::Path,
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::determinism::{EmuRng, Host};
 const RAM_SIZE: usize = 65536;
+pub const VRAM: Range<usize> = 0x8000..0xA000;
+/// Battery backed cartridge ram
+pub const SRAM: Range<usize> = 0xA000..0xC000;
+pub const WRAM: Range<usize> = 0xC000..0xE000;
+/// Mirrors the start of WRAM
+pub const ECHO: Range<usize> = 0xE000..0xFE00;
+pub const OAM: Range<usize> = 0xFE00..0xFEA0;
+/// Nothing is connected here
+pub const UNUSABLE: Range<usize> = 0xFEA0..0xFF00;
+pub const IO: Range<usize> = 0xFF00..0xFF80;
+pub const HRAM: Range<usize> = 0xFF80..0xFFFF;
+const CARTRIDGE_TITLE: Range<u16> = 0x0134..0x0144;
+/// Named memory regions for the debugger
+pub const REGIONS: [(&str, Range<usize>); 6] = [
+    ("All", 0..RAM_SIZE),
+    ("VRAM", VRAM),
+    ("WRAM", WRAM),
+    ("OAM", OAM),
+    ("IO", IO),
+    ("HRAM", HRAM),
+];
+
+/// Locks the ram for reading, also after the emulation panicked while writing it,
+/// so the gui and the crash report still see what is left
+pub fn lock(ram: &RwLock<Ram>) -> RwLockReadGuard<'_, Ram> {
+    ram.read().unwrap_or_else(PoisonError::into_inner)
+}
+/// Locks the ram for writing, see `lock`
+pub fn lock_mut(ram: &RwLock<Ram>) -> RwLockWriteGuard<'_, Ram> {
+    ram.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Content of VRAM, WRAM and HRAM after power-on.
+/// Some games seed their RNG from uninitialized memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamPattern {
+    #[default]
+    Zero,
+    Ones,
+    /// Random content like on real hardware, different on every boot
+    Random,
+    /// Random content which is the same for the same seed
+    Seeded(u64),
+}
+impl RamPattern {
+    fn fill(self, mem: &mut [u8], host: &mut Host) {
+        match self {
+            RamPattern::Zero => mem.fill(0),
+            RamPattern::Ones => mem.fill(0xFF),
+            RamPattern::Random => host.fill_random(mem),
+            RamPattern::Seeded(seed) => EmuRng::new(seed).fill_bytes(mem),
+        }
+    }
+}
 pub struct Ram([u8; RAM_SIZE]);
+impl Ram {
+    pub fn with_pattern(pattern: RamPattern, host: &mut Host) -> Ram {
+        let mut ram = Ram::default();
+        for region in [VRAM, WRAM, HRAM] {
+            pattern.fill(&mut ram.0[region], host);
+        }
+        ram
+    }
+    /// The title from the cartridge header, if it is printable
+    pub fn cartridge_title(&self) -> Option<String> {
+        let title: String = CARTRIDGE_TITLE
+            .map(|addr| self[addr])
+            // the CGB flag ends a shorter title
+            .take_while(|byte| *byte != 0 && *byte < 0x80)
+            .map(char::from)
+            .collect();
+        let printable =
+            !title.is_empty() && title.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+        printable.then_some(title)
+    }
+    /// Copies rom content into the address space, e.g. when a bank is switched
+    pub fn map(&mut self, start: usize, data: &[u8]) {
+        self.0[start..start + data.len()].copy_from_slice(data);
+    }
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+    /// Writes the bytes in `range` to a file
+    pub fn dump(&self, range: Range<usize>, path: &Path) -> io::Result<()> {
+        fs::write(path, &self.0[range])
+    }
+    /// Copies a file into memory starting at the beginning of `range`.
+    /// Bytes which do not fit into the range are ignored, returns the number of bytes written.
+    pub fn load(&mut self, range: Range<usize>, path: &Path) -> io::Result<usize> {
+        let data = fs::read(path)?;
+        let len = data.len().min(range.len());
+        self.0[range.start..range.start + len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+}
 impl Index<u16> for Ram {
     type Output = u8;
 
@@ -33,11 +116,6 @@ impl IndexMut<u16> for Ram {
 }
 impl Default for Ram {
     fn default() -> Ram {
-        let mut ram = [0; RAM_SIZE];
-        for i in 0..BIOS.len() {
-            ram[i] = BIOS[i];
-        }
-
-        Ram(ram)
+        Ram([0; RAM_SIZE])
     }
 }
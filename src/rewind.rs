@@ -0,0 +1,262 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use crate::cpu::{CLOCK_SPEED, CYCLES_PER_FRAME};
+
+/// Frames between two snapshots, rewinding shows one frame per snapshot and so runs
+/// backwards at this many times the speed
+pub const INTERVAL: u32 = 2;
+/// Ten seconds of history at one snapshot every `INTERVAL` frames
+pub const DEFAULT_CAPACITY: usize = 300;
+
+/// Snapshot history for rewinding. Only the newest snapshot is kept whole, every older one
+/// is stored as the XOR against its successor, compressed with LZ4. Between two snapshots
+/// few bytes change, so the XOR is mostly zeros and compresses to a few hundred bytes.
+pub struct RewindBuffer {
+    /// The newest snapshot, the base the deltas are applied to
+    latest: Option<Vec<u8>>,
+    /// Compressed XOR of each snapshot with the next newer one, oldest first
+    deltas: VecDeque<Vec<u8>>,
+    capacity: usize,
+}
+impl RewindBuffer {
+    /// Keeps at most `capacity` snapshots, the oldest are dropped first
+    pub fn new(capacity: usize) -> Self {
+        RewindBuffer {
+            latest: None,
+            deltas: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        if let Some(latest) = self.latest.take() {
+            if latest.len() == snapshot.len() {
+                self.deltas
+                    .push_back(lz4_flex::compress_prepend_size(&xor(&latest, &snapshot)));
+            } else {
+                // snapshots of another size can't be diffed, e.g. after switching games
+                self.deltas.clear();
+            }
+        }
+        while self.deltas.len() >= self.capacity {
+            self.deltas.pop_front();
+        }
+        self.latest = Some(snapshot);
+    }
+    /// Removes and returns the newest snapshot, the one before becomes the newest
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let latest = self.latest.take()?;
+        if let Some(delta) = self.deltas.pop_back() {
+            match lz4_flex::decompress_size_prepended(&delta) {
+                Ok(delta) => self.latest = Some(xor(&latest, &delta)),
+                Err(e) => {
+                    log::error!("rewind history is corrupt, dropping it: {e}");
+                    self.deltas.clear();
+                }
+            }
+        }
+        Some(latest)
+    }
+    /// Number of stored snapshots
+    pub fn len(&self) -> usize {
+        self.latest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+    }
+    /// Bytes used by the history, to show next to the rewind settings
+    pub fn memory_usage(&self) -> usize {
+        let latest = self.latest.as_ref().map_or(0, Vec::len);
+        latest + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+}
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        RewindBuffer::new(DEFAULT_CAPACITY)
+    }
+}
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+#[derive(Default)]
+struct RewindInner {
+    enabled: bool,
+    /// Held by the gui, the cpu goes back a snapshot per frame instead of running
+    rewinding: bool,
+    frames: u32,
+    history: RewindBuffer,
+    /// Snapshots pushed minus the ones popped, the number of the newest one
+    newest: u64,
+    /// The snapshot the gui picked on the timeline
+    seek: Option<u64>,
+}
+
+impl RewindInner {
+    fn pop(&mut self) -> Option<Vec<u8>> {
+        let snapshot = self.history.pop()?;
+        self.newest -= 1;
+        Some(snapshot)
+    }
+}
+
+/// Rewinding the last seconds of gameplay: the cpu takes a snapshot every `INTERVAL` frames
+/// and while the rewind key is held it loads them newest first.
+/// Shared between the gui and the cpu thread.
+#[derive(Clone, Default)]
+pub struct Rewind(Arc<Mutex<RewindInner>>);
+impl Rewind {
+    pub fn enabled(&self) -> bool {
+        self.0.lock().unwrap().enabled
+    }
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut inner = self.0.lock().unwrap();
+        inner.enabled = enabled;
+        if !enabled {
+            inner.history.clear();
+        }
+    }
+    pub fn is_rewinding(&self) -> bool {
+        let inner = self.0.lock().unwrap();
+        inner.enabled && inner.rewinding
+    }
+    pub fn set_rewinding(&self, rewinding: bool) {
+        self.0.lock().unwrap().rewinding = rewinding;
+    }
+    /// Called by the cpu after every frame, returns whether a snapshot is due
+    pub fn frame_done(&self) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        if !inner.enabled || inner.rewinding {
+            return false;
+        }
+        inner.frames += 1;
+        if inner.frames < INTERVAL {
+            return false;
+        }
+        inner.frames = 0;
+        true
+    }
+    pub fn push(&self, snapshot: Vec<u8>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.history.push(snapshot);
+        inner.newest += 1;
+    }
+    /// The newest snapshot, `None` once the history is used up
+    pub fn pop(&self) -> Option<Vec<u8>> {
+        self.0.lock().unwrap().pop()
+    }
+    /// The number of the newest snapshot, they are counted up from the start and
+    /// the numbers of popped ones are given out again
+    pub fn newest(&self) -> u64 {
+        self.0.lock().unwrap().newest
+    }
+    /// The number of the oldest snapshot still kept, past `newest` if there is none
+    pub fn oldest(&self) -> u64 {
+        let inner = self.0.lock().unwrap();
+        inner.newest + 1 - inner.history.len() as u64
+    }
+    /// The cpu goes back to snapshot `number` after the current frame
+    pub fn seek(&self, number: u64) {
+        self.0.lock().unwrap().seek = Some(number);
+    }
+    /// Called by the cpu after every frame, drops the snapshots newer than the one
+    /// the gui seeks and returns that one
+    pub fn take_seek(&self) -> Option<Vec<u8>> {
+        let mut inner = self.0.lock().unwrap();
+        let number = inner.seek.take()?;
+        // dropped from the history meanwhile
+        if number + inner.history.len() as u64 <= inner.newest {
+            return None;
+        }
+        let mut snapshot = None;
+        while inner.newest >= number {
+            snapshot = Some(inner.pop()?);
+        }
+        snapshot
+    }
+    /// Drops the history, e.g. after another game was loaded
+    pub fn clear(&self) {
+        let mut inner = self.0.lock().unwrap();
+        inner.history.clear();
+        inner.frames = 0;
+        inner.seek = None;
+    }
+    /// Seconds which can be rewound
+    pub fn seconds(&self) -> f32 {
+        let snapshots = self.0.lock().unwrap().history.len() as f32;
+        snapshots * INTERVAL as f32 * CYCLES_PER_FRAME as f32 / CLOCK_SPEED as f32
+    }
+    pub fn memory_usage(&self) -> usize {
+        self.0.lock().unwrap().history.memory_usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::Bus,
+        cpu::{Cpu, V16},
+    };
+
+    use super::*;
+
+    /// Snapshots are taken every `INTERVAL` frames and come back newest first
+    #[test]
+    fn rewind_history() -> Result<(), String> {
+        let mut cpu = Cpu::new(Bus::default());
+        let rewind = Rewind::default();
+        rewind.set_enabled(true);
+        for sp in 1..=3 * INTERVAL as u16 {
+            cpu.w(V16::SP, sp);
+            if rewind.frame_done() {
+                rewind.push(cpu.snapshot());
+            }
+        }
+        rewind.set_rewinding(true);
+        if rewind.frame_done() {
+            return Err("a snapshot was due while rewinding".to_string());
+        }
+        for back in 0..3 {
+            let snapshot = rewind.pop().ok_or("the history ended early")?;
+            cpu.restore(&snapshot).map_err(|e| e.to_string())?;
+            let expected = (3 - back) * INTERVAL as u16;
+            let sp = cpu.r(V16::SP);
+            if sp != expected {
+                return Err(format!("SP is {sp:#06x} instead of {expected:#06x}"));
+            }
+        }
+        if rewind.pop().is_some() {
+            return Err("the history holds more snapshots than were taken".to_string());
+        }
+        Ok(())
+    }
+
+    /// Seeking drops the newer snapshots and returns the one picked on the timeline
+    #[test]
+    fn seek() -> Result<(), String> {
+        let rewind = Rewind::default();
+        rewind.set_enabled(true);
+        for snapshot in 1..=5u8 {
+            rewind.push(vec![snapshot; 4]);
+        }
+        rewind.seek(3);
+        let snapshot = rewind.take_seek().ok_or("nothing to seek to")?;
+        let kept = (rewind.oldest(), rewind.newest());
+        if snapshot != [3; 4] || kept != (1, 2) {
+            return Err(format!("went back to {snapshot:?}, keeping {kept:?}"));
+        }
+        for number in [0, 7] {
+            rewind.seek(number);
+            if rewind.take_seek().is_some() || rewind.newest() != 2 {
+                return Err(format!("went to snapshot {number}, which is not kept"));
+            }
+        }
+        Ok(())
+    }
+}
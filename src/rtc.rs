@@ -0,0 +1,187 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::determinism::wall_clock;
+
+/// Cartridge types with an MBC3 and a real time clock
+pub const MBC3_TIMER: [u8; 2] = [0x0F, 0x10];
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+/// The day counter has 9 bits, the carry flag is set when it overflows
+const DAYS: u64 = 512;
+/// The clock appended to a save like BGB and VBA do: the registers and the latched
+/// registers as 32 bit values, then the unix time of the save as 64 bit value
+pub const FOOTER_SIZE: usize = 48;
+
+/// A reading of the clock
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RtcTime {
+    /// 0..512
+    pub days: u16,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+impl RtcTime {
+    fn from_seconds(total: u64) -> Self {
+        RtcTime {
+            days: (total / DAY % DAYS) as u16,
+            hours: (total % DAY / HOUR) as u8,
+            minutes: (total % HOUR / MINUTE) as u8,
+            seconds: (total % MINUTE) as u8,
+        }
+    }
+    fn total_seconds(self) -> u64 {
+        self.days as u64 * DAY
+            + self.hours as u64 * HOUR
+            + self.minutes as u64 * MINUTE
+            + self.seconds as u64
+    }
+}
+
+/// The clock of an MBC3 cartridge. It keeps running with the host clock
+/// while the emulator is closed, like the battery powered original.
+#[derive(Clone, Debug)]
+pub struct Rtc {
+    /// Seconds on the clock at `since`
+    base: u64,
+    since: SystemTime,
+    halted: bool,
+    /// Set when the day counter overflowed, stays set until cleared
+    carry: bool,
+}
+impl Rtc {
+    /// Seconds since day 0, including overflowed days
+    fn elapsed(&self) -> u64 {
+        if self.halted {
+            return self.base;
+        }
+        let running = wall_clock()
+            .duration_since(self.since)
+            .unwrap_or(Duration::ZERO);
+        self.base + running.as_secs()
+    }
+    /// Restarts counting from `seconds` now
+    fn rebase(&mut self, seconds: u64) {
+        // keeps the overflow in the flag, so the counter itself wraps
+        self.carry |= seconds >= DAYS * DAY;
+        self.base = seconds % (DAYS * DAY);
+        self.since = wall_clock();
+    }
+    pub fn time(&self) -> RtcTime {
+        RtcTime::from_seconds(self.elapsed())
+    }
+    pub fn set_time(&mut self, time: RtcTime) {
+        self.rebase(time.total_seconds());
+    }
+    /// Moves the clock forward, e.g. to trigger daily events
+    pub fn advance_days(&mut self, days: u64) {
+        self.rebase(self.elapsed() + days * DAY);
+    }
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+    pub fn set_halted(&mut self, halted: bool) {
+        let elapsed = self.elapsed();
+        self.halted = halted;
+        self.rebase(elapsed);
+    }
+    pub fn carry(&self) -> bool {
+        self.carry || self.elapsed() >= DAYS * DAY
+    }
+    pub fn clear_carry(&mut self) {
+        self.rebase(self.elapsed());
+        self.carry = false;
+    }
+    /// S, M, H, DL and DH like the MBC3 latches them.
+    /// DH holds bit 8 of the day counter, the halt flag in bit 6 and the carry in bit 7.
+    pub fn registers(&self) -> [u8; 5] {
+        let time = self.time();
+        let high = (time.days >> 8) as u8 | (self.halted as u8) << 6 | (self.carry() as u8) << 7;
+        [
+            time.seconds,
+            time.minutes,
+            time.hours,
+            time.days as u8,
+            high,
+        ]
+    }
+    /// Sets one register like the game writes it while it is mapped, in the order of `registers`
+    pub fn set_register(&mut self, index: usize, value: u8) {
+        let mut registers = self.registers();
+        registers[index] = value;
+        self.set_registers(registers);
+    }
+    fn set_registers(&mut self, [seconds, minutes, hours, low, high]: [u8; 5]) {
+        let time = RtcTime {
+            days: (high as u16 & 1) << 8 | low as u16,
+            hours,
+            minutes,
+            seconds,
+        };
+        self.halted = high & 0x40 != 0;
+        self.rebase(time.total_seconds());
+        self.carry = high & 0x80 != 0;
+    }
+    pub fn to_footer(&self) -> [u8; FOOTER_SIZE] {
+        let mut footer = [0; FOOTER_SIZE];
+        for (i, register) in self.registers().into_iter().enumerate() {
+            // the latched copy is not kept, it is the same as the time
+            footer[i * 4] = register;
+            footer[20 + i * 4] = register;
+        }
+        let now = wall_clock().duration_since(UNIX_EPOCH).unwrap_or_default();
+        footer[40..].copy_from_slice(&now.as_secs().to_le_bytes());
+        footer
+    }
+    /// Reads the clock from a save footer and adds the time which passed since it was written.
+    /// Older emulators store the time in 32 bits, which makes the footer 44 bytes.
+    pub fn from_footer(footer: &[u8]) -> Option<Rtc> {
+        let saved = match footer.len() {
+            FOOTER_SIZE => u64::from_le_bytes(footer[40..48].try_into().ok()?),
+            44 => u32::from_le_bytes(footer[40..44].try_into().ok()?) as u64,
+            _ => return None,
+        };
+        let mut rtc = Rtc::default();
+        rtc.set_registers([0, 1, 2, 3, 4].map(|i| footer[i * 4]));
+        if !rtc.halted {
+            let now = wall_clock().duration_since(UNIX_EPOCH).unwrap_or_default();
+            rtc.rebase(rtc.elapsed() + now.as_secs().saturating_sub(saved));
+        }
+        Some(rtc)
+    }
+}
+impl Default for Rtc {
+    fn default() -> Self {
+        Rtc {
+            base: 0,
+            since: wall_clock(),
+            halted: false,
+            carry: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn rtc_footer() -> Result<(), String> {
+        let mut rtc = Rtc::default();
+        let time = RtcTime {
+            days: 300,
+            hours: 13,
+            minutes: 37,
+            seconds: 5,
+        };
+        rtc.set_time(time);
+        rtc.set_halted(true);
+        let loaded = Rtc::from_footer(&rtc.to_footer()).ok_or("the footer was not read")?;
+        if loaded.time() != time || !loaded.halted() {
+            return Err(format!("{time:?} was read back as {:?}", loaded.time()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+#[cfg(feature = "scripting")]
+use std::{cell::RefCell, rc::Rc, sync::RwLock};
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::cpu::Cpu;
+#[cfg(feature = "scripting")]
+use crate::{bus, cpu::CpuState, frontend::Buttons, ram::Ram, status::Status};
+#[cfg(feature = "scripting")]
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+/// An access of the cpu to a watched address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryEvent {
+    pub addr: u16,
+    pub value: u8,
+    pub write: bool,
+}
+
+#[derive(Debug, Default)]
+struct HooksInner {
+    reads: Vec<u16>,
+    writes: Vec<u16>,
+    events: Vec<MemoryEvent>,
+}
+
+/// The addresses a script watches. The bus records the accesses to them,
+/// the cpu hands them to the script after the instruction.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryHooks {
+    inner: Arc<Mutex<HooksInner>>,
+    /// Set while an address is watched, so the bus skips the lock otherwise
+    active: Arc<AtomicBool>,
+}
+impl MemoryHooks {
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+    pub fn watch(&self, addr: u16, write: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let list = if write {
+            &mut inner.writes
+        } else {
+            &mut inner.reads
+        };
+        if !list.contains(&addr) {
+            list.push(addr);
+        }
+        self.active.store(true, Ordering::Relaxed);
+    }
+    /// Called by the bus for every access while active
+    pub fn access(&self, addr: u16, value: u8, write: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let list = if write { &inner.writes } else { &inner.reads };
+        if list.contains(&addr) {
+            inner.events.push(MemoryEvent { addr, value, write });
+        }
+    }
+    pub fn take_events(&self) -> Vec<MemoryEvent> {
+        std::mem::take(&mut self.inner.lock().unwrap().events)
+    }
+}
+
+/// What the functions of the script see and change while it runs
+#[cfg(feature = "scripting")]
+struct Context {
+    ram: Arc<RwLock<Ram>>,
+    state: CpuState,
+    frame: u32,
+    /// Carries the splits to the speedrun timer
+    status: Status,
+    hooks: MemoryHooks,
+    /// Made by the cpu after the callback, through the bus like the pokes of the debugger
+    writes: Vec<(u16, u8)>,
+    /// `state` is given back to the cpu after the callback
+    registers_changed: bool,
+    /// Replaces the buttons of the player, `None` gives them back
+    buttons: Option<Buttons>,
+}
+
+/// A Rhai script with callbacks on frames and on memory accesses, which reads
+/// and writes the memory and the registers and holds buttons for bots, tests
+/// and input replays. `split()` splits the speedrun timer, an auto-splitter
+/// on a memory condition. The script defines the callbacks it wants:
+///
+/// ```text
+/// watch_write(0xC0A0);
+/// fn on_frame(frame) { if frame == 60 { press("start+a"); } else { release(); } }
+/// fn on_write(addr, value) { if value == 0x05 { split(); } }
+/// ```
+///
+/// It runs on the cpu thread, in between instructions. Its writes are made when
+/// the callback returns. Builds without the `scripting` feature can not load scripts.
+pub struct Script {
+    #[cfg(feature = "scripting")]
+    engine: Engine,
+    #[cfg(feature = "scripting")]
+    ast: AST,
+    #[cfg(feature = "scripting")]
+    scope: Scope<'static>,
+    #[cfg(feature = "scripting")]
+    context: Rc<RefCell<Context>>,
+    hooks: MemoryHooks,
+    /// The callbacks the script defines
+    on_frame: bool,
+    on_read: bool,
+    on_write: bool,
+    /// Stopped after an error, it would repeat with every call
+    failed: bool,
+}
+impl Script {
+    /// Compiles the script and runs its top level, which sets up the watches
+    #[cfg(feature = "scripting")]
+    pub fn load(path: &Path, cpu: &Cpu) -> Result<Script, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let hooks = MemoryHooks::default();
+        let context = Rc::new(RefCell::new(Context {
+            ram: cpu.bus().ram(),
+            state: cpu.state(),
+            frame: cpu.bus().status().frame(),
+            status: cpu.bus().status().clone(),
+            hooks: hooks.clone(),
+            writes: Vec::new(),
+            registers_changed: false,
+            buttons: None,
+        }));
+        let mut engine = Engine::new();
+        register(&mut engine, &context);
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+        let (on_frame, on_read, on_write) =
+            (defines("on_frame"), defines("on_read"), defines("on_write"));
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| e.to_string())?;
+        log::info!("running the script {}", path.display());
+        Ok(Script {
+            engine,
+            ast,
+            scope,
+            context,
+            hooks,
+            on_frame,
+            on_read,
+            on_write,
+            failed: false,
+        })
+    }
+    #[cfg(not(feature = "scripting"))]
+    pub fn load(path: &Path, _cpu: &Cpu) -> Result<Script, String> {
+        Err(format!(
+            "{} needs a build with the scripting feature",
+            path.display()
+        ))
+    }
+    /// The bus records the accesses of the watches here
+    pub fn hooks(&self) -> &MemoryHooks {
+        &self.hooks
+    }
+    /// Called by the cpu when a frame is done
+    pub fn frame_done(&mut self, cpu: &mut Cpu) {
+        if self.on_frame && !self.failed {
+            let frame = cpu.bus().status().frame();
+            self.call(cpu, "on_frame", &[frame as i64]);
+        }
+    }
+    /// Called by the cpu after every instruction while the hooks are active
+    pub fn memory_accesses(&mut self, cpu: &mut Cpu) {
+        for event in self.hooks.take_events() {
+            let wanted = if event.write {
+                self.on_write
+            } else {
+                self.on_read
+            };
+            if wanted && !self.failed {
+                let name = if event.write { "on_write" } else { "on_read" };
+                self.call(cpu, name, &[event.addr as i64, event.value as i64]);
+            }
+        }
+    }
+    /// Runs the callback with the state of `cpu`, then makes its changes
+    #[cfg(feature = "scripting")]
+    fn call(&mut self, cpu: &mut Cpu, name: &str, args: &[i64]) {
+        {
+            let mut context = self.context.borrow_mut();
+            context.state = cpu.state();
+            context.frame = cpu.bus().status().frame();
+        }
+        let args: Vec<Dynamic> = args.iter().map(|arg| Dynamic::from(*arg)).collect();
+        let result = self
+            .engine
+            // the top level ran once when the script was loaded
+            .call_fn_with_options::<Dynamic>(
+                CallFnOptions::new().eval_ast(false),
+                &mut self.scope,
+                &self.ast,
+                name,
+                args,
+            );
+        if let Err(e) = result {
+            log::error!("the script stopped in {name}: {e}");
+            self.failed = true;
+        }
+        let mut context = self.context.borrow_mut();
+        if std::mem::take(&mut context.registers_changed) {
+            cpu.set_state(context.state);
+        }
+        for (addr, value) in context.writes.drain(..) {
+            cpu.bus_mut().poke(addr, value);
+        }
+        cpu.bus_mut().set_input_override(context.buttons);
+    }
+    #[cfg(not(feature = "scripting"))]
+    fn call(&mut self, _cpu: &mut Cpu, _name: &str, _args: &[i64]) {}
+}
+
+/// The names `press` takes
+#[cfg(feature = "scripting")]
+const BUTTONS: [(&str, u8); 8] = [
+    ("right", Buttons::RIGHT),
+    ("left", Buttons::LEFT),
+    ("up", Buttons::UP),
+    ("down", Buttons::DOWN),
+    ("a", Buttons::A),
+    ("b", Buttons::B),
+    ("select", Buttons::SELECT),
+    ("start", Buttons::START),
+];
+
+/// The functions scripts call
+#[cfg(feature = "scripting")]
+fn register(engine: &mut Engine, context: &Rc<RefCell<Context>>) {
+    engine.on_print(|text| log::info!("script: {text}"));
+    let c = context.clone();
+    engine.register_fn("read8", move |addr: i64| -> i64 {
+        let context = c.borrow();
+        let ram = context.ram.read().unwrap();
+        bus::read(&ram, addr as u16) as i64
+    });
+    let c = context.clone();
+    engine.register_fn("read16", move |addr: i64| -> i64 {
+        let context = c.borrow();
+        let ram = context.ram.read().unwrap();
+        let lower = bus::read(&ram, addr as u16);
+        let upper = bus::read(&ram, (addr as u16).wrapping_add(1));
+        u16::from_le_bytes([lower, upper]) as i64
+    });
+    let c = context.clone();
+    engine.register_fn("write8", move |addr: i64, value: i64| {
+        c.borrow_mut().writes.push((addr as u16, value as u8));
+    });
+    let c = context.clone();
+    engine.register_fn(
+        "reg",
+        move |name: &str| -> Result<i64, Box<EvalAltResult>> {
+            read_register(&c.borrow().state, name)
+                .map(i64::from)
+                .ok_or_else(|| format!("no register {name}").into())
+        },
+    );
+    let c = context.clone();
+    engine.register_fn(
+        "set_reg",
+        move |name: &str, value: i64| -> Result<(), Box<EvalAltResult>> {
+            let mut context = c.borrow_mut();
+            if !write_register(&mut context.state, name, value as u16) {
+                return Err(format!("no register {name}").into());
+            }
+            context.registers_changed = true;
+            Ok(())
+        },
+    );
+    let c = context.clone();
+    engine.register_fn("frame", move || -> i64 { c.borrow().frame as i64 });
+    let c = context.clone();
+    engine.register_fn(
+        "press",
+        move |names: &str| -> Result<(), Box<EvalAltResult>> {
+            let buttons = parse_buttons(names).ok_or_else(|| format!("no buttons {names}"))?;
+            c.borrow_mut().buttons = Some(buttons);
+            Ok(())
+        },
+    );
+    let c = context.clone();
+    engine.register_fn("release", move || c.borrow_mut().buttons = None);
+    let c = context.clone();
+    engine.register_fn("split", move || c.borrow().status.request_split());
+    let c = context.clone();
+    engine.register_fn("watch_read", move |addr: i64| {
+        c.borrow().hooks.watch(addr as u16, false)
+    });
+    let c = context.clone();
+    engine.register_fn("watch_write", move |addr: i64| {
+        c.borrow().hooks.watch(addr as u16, true)
+    });
+}
+
+/// Names like `a+right`, in any case
+#[cfg(feature = "scripting")]
+fn parse_buttons(names: &str) -> Option<Buttons> {
+    names
+        .split('+')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .try_fold(0, |held, name| {
+            let (_, button) = BUTTONS.iter().find(|(button, _)| *button == name)?;
+            Some(held | button)
+        })
+        .map(Buttons)
+}
+/// `a` to `l`, `af`, `bc`, `de`, `hl`, `sp` and `pc`
+#[cfg(feature = "scripting")]
+fn read_register(state: &CpuState, name: &str) -> Option<u16> {
+    let pair = |upper: u8, lower: u8| u16::from_be_bytes([upper, lower]);
+    Some(match name {
+        "a" => state.a as u16,
+        "f" => state.f as u16,
+        "b" => state.b as u16,
+        "c" => state.c as u16,
+        "d" => state.d as u16,
+        "e" => state.e as u16,
+        "h" => state.h as u16,
+        "l" => state.l as u16,
+        "af" => pair(state.a, state.f),
+        "bc" => pair(state.b, state.c),
+        "de" => pair(state.d, state.e),
+        "hl" => pair(state.h, state.l),
+        "sp" => state.sp,
+        "pc" => state.pc,
+        _ => return None,
+    })
+}
+/// Returns false if there is no register `name`
+#[cfg(feature = "scripting")]
+fn write_register(state: &mut CpuState, name: &str, value: u16) -> bool {
+    let byte = value as u8;
+    match name {
+        "a" => state.a = byte,
+        // the lower bits of F are always 0
+        "f" => state.f = byte & 0xF0,
+        "b" => state.b = byte,
+        "c" => state.c = byte,
+        "d" => state.d = byte,
+        "e" => state.e = byte,
+        "h" => state.h = byte,
+        "l" => state.l = byte,
+        "af" => [state.a, state.f] = [(value >> 8) as u8, byte & 0xF0],
+        "bc" => [state.b, state.c] = value.to_be_bytes(),
+        "de" => [state.d, state.e] = value.to_be_bytes(),
+        "hl" => [state.h, state.l] = value.to_be_bytes(),
+        "sp" => state.sp = value,
+        "pc" => state.pc = value,
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bus::Bus, frontend::Buttons, joypad::P1};
+
+    use super::*;
+
+    #[test]
+    fn script_hooks() -> Result<(), String> {
+        let hooks = MemoryHooks::default();
+        let mut bus = Bus::default();
+        bus.set_memory_hooks(hooks.clone());
+        bus.write_mem(0xC000, 1);
+        if hooks.is_active() || !hooks.take_events().is_empty() {
+            return Err("recorded an access without a watch".to_string());
+        }
+        hooks.watch(0xC000, true);
+        bus.write_mem(0xC000, 2);
+        bus.write_mem(0xC001, 3);
+        bus.fetch(0xC000);
+        let events = hooks.take_events();
+        let expected = [MemoryEvent {
+            addr: 0xC000,
+            value: 2,
+            write: true,
+        }];
+        if events != expected {
+            return Err(format!("watching writes to $C000 recorded {events:?}"));
+        }
+        // the action buttons are selected
+        bus.write_mem(P1, 0x10);
+        if bus.poll_buttons() {
+            return Err("a button is held without input".to_string());
+        }
+        bus.set_input_override(Some(Buttons(Buttons::A)));
+        if !bus.poll_buttons() {
+            return Err("the buttons of the script are not held".to_string());
+        }
+        bus.set_input_override(None);
+        if bus.poll_buttons() {
+            return Err("the script did not give the buttons back".to_string());
+        }
+        Ok(())
+    }
+}
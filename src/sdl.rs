@@ -0,0 +1,109 @@
+use std::sync::mpsc::Receiver;
+
+use sdl2::{event::Event, keyboard::Scancode, pixels::PixelFormatEnum};
+
+use gba::{
+    cgb,
+    frontend::{Buttons, DrawSignal, SharedButtons},
+    speed::Speed,
+};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 144;
+const SCALE: u32 = 3;
+const PALETTE: [[u8; 3]; 4] = [
+    [0xe0, 0xe0, 0xe0],
+    [0xa0, 0xa0, 0xa0],
+    [0x50, 0x50, 0x50],
+    [0x10, 0x10, 0x10],
+];
+const KEYMAP: [(Scancode, u8); 8] = [
+    (Scancode::Right, Buttons::RIGHT),
+    (Scancode::Left, Buttons::LEFT),
+    (Scancode::Up, Buttons::UP),
+    (Scancode::Down, Buttons::DOWN),
+    (Scancode::X, Buttons::A),
+    (Scancode::Z, Buttons::B),
+    (Scancode::Backspace, Buttons::SELECT),
+    (Scancode::Return, Buttons::START),
+];
+
+/// Shows the screen in a plain SDL window until it is closed.
+/// Escape quits and P pauses, the buttons are mapped like in the egui gui.
+pub fn run(
+    receiver: Receiver<DrawSignal>,
+    input: SharedButtons,
+    speed: Speed,
+) -> Result<(), String> {
+    let sdl = sdl2::init()?;
+    let window = sdl
+        .video()?
+        .window(
+            "Gameboy Emulator",
+            WIDTH as u32 * SCALE,
+            HEIGHT as u32 * SCALE,
+        )
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut canvas = window
+        .into_canvas()
+        .present_vsync()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let creator = canvas.texture_creator();
+    let mut texture = creator
+        .create_texture_streaming(PixelFormatEnum::RGB24, WIDTH as u32, HEIGHT as u32)
+        .map_err(|e| e.to_string())?;
+    let mut screen = [PALETTE[0]; WIDTH * HEIGHT];
+    let mut events = sdl.event_pump()?;
+    loop {
+        for event in events.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    scancode: Some(Scancode::Escape),
+                    ..
+                } => return Ok(()),
+                Event::KeyDown {
+                    scancode: Some(Scancode::P),
+                    repeat: false,
+                    ..
+                } => speed.set_paused(!speed.is_paused()),
+                _ => {}
+            }
+        }
+        let keyboard = events.keyboard_state();
+        let held = KEYMAP
+            .iter()
+            .filter(|(scancode, _)| keyboard.is_scancode_pressed(*scancode))
+            .fold(0, |held, (_, button)| held | button);
+        input.set(Buttons(held));
+
+        for signal in receiver.try_iter() {
+            match signal {
+                DrawSignal::Frame(frame) => {
+                    for (rgb, shade) in screen.iter_mut().zip(frame.iter()) {
+                        *rgb = PALETTE[*shade as usize % 4];
+                    }
+                }
+                DrawSignal::ColorFrame(frame) => {
+                    for (rgb, color) in screen.iter_mut().zip(frame.iter()) {
+                        *rgb = cgb::rgb(*color);
+                    }
+                }
+                DrawSignal::CpuMode(mode, pc) => log::info!("cpu {mode:?} at {pc:#06x}"),
+            }
+        }
+        texture.with_lock(None, |buffer, pitch| {
+            for (i, rgb) in screen.iter().enumerate() {
+                let offset = i / WIDTH * pitch + i % WIDTH * 3;
+                buffer[offset..offset + 3].copy_from_slice(rgb);
+            }
+        })?;
+        canvas.clear();
+        canvas.copy(&texture, None, None)?;
+        canvas.present();
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    bus::{Bus, OpCode},
+    cartridge::{Cartridge, ROM_BANK_SIZE},
+    cpu::{Cpu, CpuMode, V16, V8},
+    crash,
+    frontend::{FrameCallback, Screen},
+    instruction::Instruction,
+    opcodes,
+    vram::LCDC,
+};
+
+/// Where execution starts after the boot rom
+const ENTRY: u16 = 0x0100;
+
+type Check = fn() -> Result<(), String>;
+
+/// A quick run of the core on this machine, the unit tests cover the details
+const CHECKS: [(&str, Check); 6] = [
+    ("register round-trips", registers),
+    ("opcode table invariants", opcode_table),
+    ("a rom runs until it halts", rom_halt),
+    ("micro roms", micro_roms),
+    ("frames reach the frontend", frame_callback),
+    ("save state round-trip", save_state),
+];
+/// Small programs which halt with `expected` at `addr`
+const MICRO_ROMS: [(&str, &[u8], u16, u8); 4] = [
+    // ld hl, $c000; ld a, $42; ld (hl), a; halt
+    (
+        "store",
+        &[0x21, 0x00, 0xC0, 0x3E, 0x42, 0x77, 0x76],
+        0xC000,
+        0x42,
+    ),
+    // ld a, $38; ld b, $45; add a, b; daa; ld ($c000), a; halt
+    (
+        "add and daa",
+        &[0x3E, 0x38, 0x06, 0x45, 0x80, 0x27, 0xEA, 0x00, 0xC0, 0x76],
+        0xC000,
+        0x83,
+    ),
+    // ld b, 5; xor a; add a, b; dec b; jr nz, -4; ld ($c000), a; halt
+    (
+        "loop",
+        &[
+            0x06, 0x05, 0xAF, 0x80, 0x05, 0x20, 0xFC, 0xEA, 0x00, 0xC0, 0x76,
+        ],
+        0xC000,
+        15,
+    ),
+    // call $0108; ld ($c000), a; halt; ld a, $99; ret
+    (
+        "call and return",
+        &[
+            0xCD, 0x08, 0x01, 0xEA, 0x00, 0xC0, 0x76, 0x00, 0x3E, 0x99, 0xC9,
+        ],
+        0xC000,
+        0x99,
+    ),
+];
+
+/// Runs the built in checks for `--selftest` and prints a report, returns whether all passed
+pub fn run() -> bool {
+    // a failing check panics, the report already says so
+    panic::set_hook(Box::new(|_| {}));
+    println!("gba self-test");
+    let mut failed = 0;
+    for (name, check) in CHECKS {
+        let result = panic::catch_unwind(check).unwrap_or_else(|payload| {
+            Err(format!("panicked: {}", crash::panic_message(&*payload)))
+        });
+        match result {
+            Ok(()) => println!("  ok    {name}"),
+            Err(e) => {
+                failed += 1;
+                println!("  FAIL  {name}: {e}");
+            }
+        }
+    }
+    println!("{} passed, {failed} failed", CHECKS.len() - failed);
+    let _ = panic::take_hook();
+    failed == 0
+}
+
+fn registers() -> Result<(), String> {
+    let mut cpu = Cpu::new(Bus::default());
+    for register in [V16::AF, V16::BC, V16::DE, V16::HL, V16::PC, V16::SP] {
+        cpu.w(register, 0x1234);
+        let read = cpu.r(register);
+        if read != 0x1234 {
+            return Err(format!("{register:?} wrote 0x1234, read {read:#06x}"));
+        }
+    }
+    // the first register of a pair is its high byte
+    for (pair, high, low) in [
+        (V16::AF, V8::A, V8::F),
+        (V16::BC, V8::B, V8::C),
+        (V16::DE, V8::D, V8::E),
+        (V16::HL, V8::H, V8::L),
+    ] {
+        cpu.w(pair, 0);
+        cpu.w(high, 0xAB);
+        cpu.w(low, 0xC0);
+        let read = cpu.r(pair);
+        if read != 0xABC0 {
+            return Err(format!("{high:?} and {low:?} make {pair:?} {read:#06x}"));
+        }
+    }
+    Ok(())
+}
+fn opcode_table() -> Result<(), String> {
+    let mut illegal = 0;
+    for op in 0..=0xFF {
+        let info = opcodes::info(op);
+        if !(1..=3).contains(&info.length) || info.cycles == 0 {
+            return Err(format!(
+                "{} is {} bytes in {} cycles",
+                info.mnemonic, info.length, info.cycles
+            ));
+        }
+        if info.branch_cycles.is_some_and(|taken| taken <= info.cycles) {
+            return Err(format!(
+                "{} takes no longer when it branches",
+                info.mnemonic
+            ));
+        }
+        match Instruction::from(OpCode(op)) {
+            Instruction::Illegal if info.mnemonic.starts_with("ILLEGAL") => illegal += 1,
+            Instruction::Illegal => return Err(format!("{} decodes as illegal", info.mnemonic)),
+            _ if info.mnemonic.starts_with("ILLEGAL") => {
+                return Err(format!("{op:#04x} is illegal but decodes"))
+            }
+            _ => {}
+        }
+        let prefixed = opcodes::prefixed_info(op);
+        if prefixed.length != 2 || prefixed.cycles == 0 {
+            return Err(format!(
+                "{} is {} bytes",
+                prefixed.mnemonic, prefixed.length
+            ));
+        }
+    }
+    match illegal {
+        11 => Ok(()),
+        n => Err(format!("{n} illegal opcodes instead of 11")),
+    }
+}
+fn rom_halt() -> Result<(), String> {
+    let mut cpu = Cpu::new(Bus::default().with_cartridge(cartridge(&[0x00, 0x00, 0x76])?));
+    cpu.skip_boot_rom();
+    cpu.step_frame();
+    let pc = cpu.r(V16::PC);
+    if *cpu.mode() != CpuMode::Halt || pc != ENTRY + 3 {
+        return Err(format!("{:?} at {pc:#06x}", cpu.mode()));
+    }
+    Ok(())
+}
+fn micro_roms() -> Result<(), String> {
+    for (name, program, addr, expected) in MICRO_ROMS {
+        let bus = Bus::default().with_cartridge(cartridge(program)?);
+        let ram = bus.ram();
+        let mut cpu = Cpu::new(bus);
+        cpu.skip_boot_rom();
+        cpu.step_frame();
+        if *cpu.mode() != CpuMode::Halt {
+            return Err(format!("{name} did not halt, {:?}", cpu.mode()));
+        }
+        let value = ram.read().unwrap()[addr];
+        if value != expected {
+            return Err(format!(
+                "{name} left {value:#04x} at {addr:#06x}, expected {expected:#04x}"
+            ));
+        }
+    }
+    Ok(())
+}
+fn frame_callback() -> Result<(), String> {
+    let frames = Arc::new(AtomicUsize::new(0));
+    let counter = frames.clone();
+    let mut bus = Bus::default()
+        .with_cartridge(cartridge(&[0x76])?)
+        .with_video(FrameCallback(move |_: Screen| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+    bus.write_mem(LCDC, 0x91);
+    let mut cpu = Cpu::new(bus);
+    cpu.skip_boot_rom();
+    for _ in 0..3 {
+        cpu.step_frame();
+    }
+    match frames.load(Ordering::Relaxed) {
+        3 => Ok(()),
+        n => Err(format!("3 frames called back {n} times")),
+    }
+}
+/// Restoring a state after running on gives back the same registers and memory
+fn save_state() -> Result<(), String> {
+    let (_, program, _, _) = MICRO_ROMS[2];
+    let mut cpu = Cpu::new(Bus::default().with_cartridge(cartridge(program)?));
+    cpu.skip_boot_rom();
+    for _ in 0..4 {
+        cpu.step();
+    }
+    let state = cpu.state();
+    let snapshot = cpu.snapshot();
+    cpu.step_frame();
+    cpu.restore(&snapshot).map_err(|e| e.to_string())?;
+    if cpu.state() != state {
+        return Err(format!("restored {:x?}, saved {state:x?}", cpu.state()));
+    }
+    if cpu.snapshot() != snapshot {
+        return Err("the machine differs from the state it restored".to_string());
+    }
+    Ok(())
+}
+/// A two bank cartridge with `program` at the entry point
+fn cartridge(program: &[u8]) -> Result<Cartridge, String> {
+    let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+    rom[ENTRY as usize..ENTRY as usize + program.len()].copy_from_slice(program);
+    Cartridge::from_bytes(rom).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_pass() -> Result<(), String> {
+        for (name, check) in CHECKS {
+            check().map_err(|e| format!("{name}: {e}"))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    interrupt::{self, Interrupt},
+    paths::DataDirs,
+    printer::Printer,
+    ram::Ram,
+    trade::TradePartner,
+};
+
+pub const SB: u16 = 0xFF01;
+pub const SC: u16 = 0xFF02;
+/// Characters the serial output keeps, the oldest go first
+pub const OUTPUT_LEN: usize = 64 * 1024;
+/// Cycles to shift a byte out with the internal clock, 8 bits at 8192 Hz
+pub const TRANSFER_CYCLES: usize = 8 * 512;
+
+/// Something plugged into the link port. Every transfer shifts a byte out
+/// and receives one from the device in exchange.
+pub trait SerialDevice: Send {
+    /// The name `--serial` and the link port selector use
+    fn name(&self) -> &str;
+    /// The byte received in exchange for `sent`, like no cable by default
+    fn exchange(&mut self, _sent: u8) -> u8 {
+        0xFF
+    }
+    /// Called when the game starts shifting `sent` out
+    fn start(&mut self, _sent: u8) {}
+    /// Called once the bits of `sent` are shifted, `None` while the answer is on its way
+    fn finish(&mut self, sent: u8) -> Option<u8> {
+        Some(self.exchange(sent))
+    }
+    /// Whether the device provides the clock, then transfers with the external clock
+    /// complete too
+    fn drives_clock(&self) -> bool {
+        false
+    }
+    /// Called while the game waits for a transfer with the external clock. A device which
+    /// clocked one from its side gets `sent` and returns the byte it sent.
+    fn poll(&mut self, _sent: u8) -> Option<u8> {
+        None
+    }
+}
+
+/// No cable, every transfer receives 0xFF
+pub struct Disconnected;
+impl SerialDevice for Disconnected {
+    fn name(&self) -> &str {
+        "disconnected"
+    }
+}
+
+/// The cable is plugged back into the same port, every byte comes back
+pub struct Loopback;
+impl SerialDevice for Loopback {
+    fn name(&self) -> &str {
+        "loopback"
+    }
+    fn exchange(&mut self, sent: u8) -> u8 {
+        sent
+    }
+}
+
+/// A partner which answers with the bytes in order, repeating from the start
+pub struct Scripted {
+    responses: Vec<u8>,
+    next: usize,
+}
+impl Scripted {
+    /// Reads the responses from a binary file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Scripted {
+            responses: fs::read(path)?,
+            next: 0,
+        })
+    }
+}
+impl SerialDevice for Scripted {
+    fn name(&self) -> &str {
+        "script"
+    }
+    fn exchange(&mut self, _sent: u8) -> u8 {
+        let Some(byte) = self.responses.get(self.next).copied() else {
+            return 0xFF;
+        };
+        self.next = (self.next + 1) % self.responses.len();
+        byte
+    }
+    fn drives_clock(&self) -> bool {
+        true
+    }
+}
+
+/// Keeps every byte the game sends, like a terminal on the other end. Test roms
+/// print their results this way.
+#[derive(Clone, Default)]
+pub struct Capture(Arc<Mutex<Vec<u8>>>);
+impl Capture {
+    /// The bytes sent since the last call
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+impl SerialDevice for Capture {
+    fn name(&self) -> &str {
+        "capture"
+    }
+    fn exchange(&mut self, sent: u8) -> u8 {
+        self.0.lock().unwrap().push(sent);
+        0xFF
+    }
+}
+
+#[derive(Debug, Default)]
+struct SerialOutputInner {
+    enabled: AtomicBool,
+    /// Also printed to stdout
+    stdout: AtomicBool,
+    text: Mutex<String>,
+}
+
+/// What the game prints through the link port, the printf of homebrew and test roms:
+/// the byte in SB whenever a transfer with the internal clock starts. Taken apart from
+/// the device plugged in, so it works with any of them.
+#[derive(Clone, Debug, Default)]
+pub struct SerialOutput(Arc<SerialOutputInner>);
+impl SerialOutput {
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled.load(Ordering::Relaxed)
+    }
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.enabled.store(enabled, Ordering::Relaxed);
+    }
+    /// Prints the bytes to stdout as well, enables the output
+    pub fn set_stdout(&self, stdout: bool) {
+        self.0.stdout.store(stdout, Ordering::Relaxed);
+        if stdout {
+            self.set_enabled(true);
+        }
+    }
+    /// Called for every transfer the game starts
+    pub fn push(&self, byte: u8) {
+        if !self.is_enabled() {
+            return;
+        }
+        if self.0.stdout.load(Ordering::Relaxed) {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(&[byte]);
+            let _ = stdout.flush();
+        }
+        let mut text = self.0.text.lock().unwrap();
+        match byte {
+            b'\r' => {}
+            b'\n' | b'\t' | 0x20..=0x7E => text.push(byte as char),
+            _ => text.push_str(&format!("\\x{byte:02X}")),
+        }
+        if text.len() > OUTPUT_LEN {
+            let cut = text.len() - OUTPUT_LEN;
+            // the escapes and the text are ascii, any cut is on a char boundary
+            text.drain(..cut);
+        }
+    }
+    pub fn text(&self) -> String {
+        self.0.text.lock().unwrap().clone()
+    }
+    pub fn clear(&self) {
+        self.0.text.lock().unwrap().clear();
+    }
+}
+
+/// The devices which need no file, in the order the selector shows them
+pub const DEVICES: [&str; 4] = ["disconnected", "loopback", "printer", "trade"];
+
+/// The device called `name` in `DEVICES`, the printer writes its pages to the screenshots
+pub fn device(name: &str, dirs: &DataDirs) -> Option<Box<dyn SerialDevice>> {
+    Some(match name {
+        "disconnected" => Box::new(Disconnected),
+        "loopback" => Box::new(Loopback),
+        "printer" => Box::new(Printer::new(dirs.screenshots.join("printer"))),
+        "trade" => Box::new(TradePartner::default()),
+        _ => return None,
+    })
+}
+
+/// A byte on its way through the cable
+#[derive(Clone, Copy, Debug)]
+struct Transfer {
+    sent: u8,
+    /// Cycles until the last bit is shifted
+    left: usize,
+}
+
+/// What is connected to the link port. Shared, so the gui can plug in another device
+/// while the game runs.
+#[derive(Clone)]
+pub struct LinkPort {
+    device: Arc<Mutex<Box<dyn SerialDevice>>>,
+    /// Only the bus clocks it
+    transfer: Option<Transfer>,
+    output: SerialOutput,
+}
+impl LinkPort {
+    pub fn new(device: Box<dyn SerialDevice>) -> Self {
+        LinkPort {
+            device: Arc::new(Mutex::new(device)),
+            transfer: None,
+            output: SerialOutput::default(),
+        }
+    }
+    /// Replaces the connected device
+    pub fn plug(&self, device: Box<dyn SerialDevice>) {
+        log::info!("{} plugged into the link port", device.name());
+        *self.device.lock().unwrap() = device;
+    }
+    pub fn device_name(&self) -> String {
+        self.device.lock().unwrap().name().to_string()
+    }
+    /// What the game prints, whatever is plugged in
+    pub fn output(&self) -> SerialOutput {
+        self.output.clone()
+    }
+    /// Called after a write to SC. Starts a transfer with the internal clock, with the
+    /// external clock only when the device drives the clock. `poll` finishes it.
+    pub fn write_control(&mut self, ram: &mut Ram) {
+        if ram[SC] & 0x81 == 0x81 {
+            self.output.push(ram[SB]);
+        }
+        self.start(ram);
+    }
+    fn start(&mut self, ram: &Ram) {
+        let control = ram[SC];
+        let mut device = self.device.lock().unwrap();
+        let clocked = control & 0x01 != 0 || device.drives_clock();
+        // stopping the clock gives the transfer up
+        self.transfer = None;
+        if control & 0x80 == 0 || !clocked {
+            return;
+        }
+        device.start(ram[SB]);
+        self.transfer = Some(Transfer {
+            sent: ram[SB],
+            left: TRANSFER_CYCLES,
+        });
+    }
+    /// Called by the bus as the clock runs. Completes a transfer once its bits are shifted
+    /// and the device answered, with the external clock once the device on the other end
+    /// clocked it.
+    pub fn poll(&mut self, ram: &mut Ram, cycles: usize) {
+        if self.transfer.is_none() && ram[SC] & 0x81 == 0x81 {
+            // a state saved in the middle of a transfer
+            self.start(ram);
+        }
+        let (sent, received) = match &mut self.transfer {
+            Some(transfer) => {
+                transfer.left = transfer.left.saturating_sub(cycles);
+                if transfer.left > 0 {
+                    return;
+                }
+                // an answer over the network can take longer than the bits
+                let Some(received) = self.device.lock().unwrap().finish(transfer.sent) else {
+                    return;
+                };
+                let sent = transfer.sent;
+                self.transfer = None;
+                (sent, received)
+            }
+            None if ram[SC] & 0x81 == 0x80 => {
+                let Some(received) = self.device.lock().unwrap().poll(ram[SB]) else {
+                    return;
+                };
+                (ram[SB], received)
+            }
+            None => return,
+        };
+        log::debug!("sent {sent:#04x}, received {received:#04x}");
+        ram[SB] = received;
+        ram[SC] &= !0x80;
+        interrupt::request(ram, Interrupt::Serial);
+    }
+}
+impl Default for LinkPort {
+    fn default() -> Self {
+        LinkPort::new(Box::new(Disconnected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{bus::Bus, printer::Printer};
+
+    use super::*;
+
+    /// A printer packet with its checksum and the two bytes for the reply
+    fn printer_packet(command: u8, data: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x88, 0x33, command, 0x00];
+        packet.extend((data.len() as u16).to_le_bytes());
+        packet.extend(data);
+        let checksum = packet[2..]
+            .iter()
+            .fold(0u16, |sum, b| sum.wrapping_add(*b as u16));
+        packet.extend(checksum.to_le_bytes());
+        packet.extend([0x00, 0x00]);
+        packet
+    }
+    /// Shifts `byte` out with the internal clock, returns the byte received
+    fn transfer(bus: &mut Bus, byte: u8) -> u8 {
+        bus.write_mem(SB, byte);
+        bus.write_mem(SC, 0x81);
+        bus.tick(TRANSFER_CYCLES - 4);
+        if bus.fetch(SC) & 0x80 == 0 {
+            return 0x00;
+        }
+        bus.tick(4);
+        bus.fetch(SB)
+    }
+    #[test]
+    fn link_port() -> Result<(), String> {
+        let port = LinkPort::new(Box::new(Loopback));
+        let mut bus = Bus::default().with_link_port(port.clone());
+        let received = transfer(&mut bus, 0x42);
+        if received != 0x42 || bus.fetch(SC) & 0x80 != 0 {
+            return Err(format!("the loopback cable returned {received:#04x}"));
+        }
+        let dir = std::env::temp_dir().join(format!("gba-selftest-printer-{}", std::process::id()));
+        port.plug(Box::new(Printer::new(dir.clone())));
+        let mut exchange = |packet: Vec<u8>| -> Vec<u8> {
+            packet
+                .into_iter()
+                .map(|byte| transfer(&mut bus, byte))
+                .collect()
+        };
+        let reply = exchange(printer_packet(0x01, &[]));
+        if reply[reply.len() - 2..] != [0x81, 0x00] {
+            return Err(format!("the printer answered {reply:02x?} to init"));
+        }
+        exchange(printer_packet(0x04, &[0xFF; 640]));
+        exchange(printer_packet(0x02, &[0x01, 0x00, 0xE4, 0x40]));
+        let printed = dir.join("page-1.png").exists();
+        let _ = fs::remove_dir_all(&dir);
+        if !printed {
+            return Err("the printer did not save the page".to_string());
+        }
+        Ok(())
+    }
+    /// The bytes sent with the internal clock are printed, whatever is plugged in
+    #[test]
+    fn serial_output() -> Result<(), String> {
+        let port = LinkPort::new(Box::new(Loopback));
+        let output = port.output();
+        let mut bus = Bus::default().with_link_port(port);
+        let mut print = |bytes: &[u8], control: u8| {
+            for &byte in bytes {
+                bus.write_mem(SB, byte);
+                bus.write_mem(SC, control);
+            }
+        };
+        print(b"off", 0x81);
+        output.set_enabled(true);
+        print(b"Passed\r\n\x01", 0x81);
+        // waiting for the other side to clock
+        print(b"?", 0x80);
+        let text = output.text();
+        if text != "Passed\n\\x01" {
+            return Err(format!("printed {text:?}"));
+        }
+        output.clear();
+        if !output.text().is_empty() {
+            return Err("clearing kept the text".to_string());
+        }
+        Ok(())
+    }
+}
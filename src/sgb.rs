@@ -0,0 +1,606 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    frontend::{DrawSignal, VideoSink},
+    ppu::{ColorFrameBuffer, SCREEN_HEIGHT, SCREEN_WIDTH},
+    ram::Ram,
+    state::{Reader, StateError, Writer},
+    vram::{tile_addr, BG_MAPS, LCDC},
+};
+
+pub const BORDER_WIDTH: usize = 256;
+pub const BORDER_HEIGHT: usize = 224;
+/// Where the game screen sits in the border
+pub const SCREEN_LEFT: usize = 48;
+pub const SCREEN_TOP: usize = 40;
+/// The border in 15 bit RGB, line by line from the top
+pub type BorderImage = [u16; BORDER_WIDTH * BORDER_HEIGHT];
+
+/// Bytes of a packet, a command takes up to 7 of them
+const PACKET_LEN: usize = 16;
+/// The palettes are assigned to cells of 8x8 pixels
+const CELLS_X: usize = SCREEN_WIDTH / 8;
+const CELLS_Y: usize = SCREEN_HEIGHT / 8;
+/// Palettes of 4 colors PAL_TRN sends, PAL_SET picks from them
+const SYSTEM_PALETTES: usize = 512;
+/// Attribute files ATTR_TRN sends, with 2 bits per cell
+const ATTR_FILES: usize = 45;
+const ATTR_FILE_LEN: usize = CELLS_X * CELLS_Y / 4;
+/// Bytes a VRAM transfer copies from the screen
+const TRANSFER_LEN: usize = 4096;
+/// 256 tiles with 4 bits per pixel, CHR_TRN sends them in two halves
+const BORDER_TILES_LEN: usize = 256 * 32;
+/// 32x32 map entries of 2 bytes and the palettes 4-7 after them
+const BORDER_MAP_LEN: usize = 0x800 + 4 * 16 * 2;
+/// The DMG shades, until the game sends palettes
+const GRAYS: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MASK_EN: u8 = 0x17;
+
+/// What MASK_EN shows instead of the game while it prepares the screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mask {
+    #[default]
+    Off,
+    /// Keeps the last frame
+    Freeze,
+    Black,
+    /// Filled with color 0
+    Color0,
+}
+impl Mask {
+    fn from_bits(bits: u8) -> Mask {
+        match bits & 0x03 {
+            1 => Mask::Freeze,
+            2 => Mask::Black,
+            3 => Mask::Color0,
+            _ => Mask::Off,
+        }
+    }
+}
+
+/// The functions of the Super Game Boy, which games for it use through packets written
+/// bit by bit into P1: 4 palettes assigned to the cells of the screen, a border
+/// around it and the ids of up to 4 joypads.
+#[derive(Clone, Debug)]
+pub struct Sgb {
+    /// The bit of the packet being received, `None` between packets
+    bit: Option<usize>,
+    packet: [u8; PACKET_LEN],
+    /// The packets of the command received so far
+    command: Vec<u8>,
+    /// P1 bits 4 and 5 of the last write
+    select: u8,
+    palettes: [[u16; 4]; 4],
+    /// The palette of every cell
+    attributes: [u8; CELLS_X * CELLS_Y],
+    system_palettes: Vec<[u16; 4]>,
+    attr_files: Vec<[u8; ATTR_FILE_LEN]>,
+    mask: Mask,
+    /// 1, 2 or 4 joypads
+    players: u8,
+    /// The joypad P1 reads
+    player: u8,
+    border_tiles: Vec<u8>,
+    border_map: Vec<u8>,
+    /// Set once PCT_TRN sent the map, the border is drawn from then on
+    has_border: bool,
+    border: SgbBorder,
+}
+impl Sgb {
+    /// The border is published to `border`
+    pub fn new(border: SgbBorder) -> Self {
+        border.set(None);
+        Sgb {
+            bit: None,
+            packet: [0; PACKET_LEN],
+            command: Vec::new(),
+            select: 0x30,
+            palettes: [GRAYS; 4],
+            attributes: [0; CELLS_X * CELLS_Y],
+            system_palettes: vec![GRAYS; SYSTEM_PALETTES],
+            attr_files: vec![[0; ATTR_FILE_LEN]; ATTR_FILES],
+            mask: Mask::Off,
+            players: 1,
+            player: 0,
+            border_tiles: vec![0; BORDER_TILES_LEN],
+            border_map: vec![0; BORDER_MAP_LEN],
+            has_border: false,
+            border,
+        }
+    }
+    /// The joypad P1 reads its id from while both rows are deselected,
+    /// `None` unless MLT_REQ asked for more than one
+    pub fn player(&self) -> Option<u8> {
+        (self.players > 1).then_some(self.player)
+    }
+    pub fn mask(&self) -> Mask {
+        self.mask
+    }
+    /// Called after a write to P1. Both rows low starts a packet, then P14 low sends
+    /// a 0 and P15 low a 1, each followed by both high. The packet ends with a 0.
+    pub fn write(&mut self, ram: &Ram, value: u8) {
+        let select = value & 0x30;
+        let before = std::mem::replace(&mut self.select, select);
+        // P15 going high selects the next joypad
+        if before & 0x20 == 0 && select & 0x20 != 0 && self.players > 1 {
+            self.player = (self.player + 1) % self.players;
+        }
+        if select == 0x00 {
+            self.bit = Some(0);
+            self.packet = [0; PACKET_LEN];
+            return;
+        }
+        let (Some(bit), 0x30) = (self.bit, before) else {
+            return;
+        };
+        let one = match select {
+            0x10 => 1,
+            0x20 => 0,
+            _ => return,
+        };
+        if bit < PACKET_LEN * 8 {
+            self.packet[bit / 8] |= one << (bit % 8);
+            self.bit = Some(bit + 1);
+            return;
+        }
+        self.bit = None;
+        if one == 0 {
+            let packet = self.packet;
+            self.receive(ram, &packet);
+        }
+    }
+    /// Collects the packets of a command, the first one has their number in its low bits
+    fn receive(&mut self, ram: &Ram, packet: &[u8; PACKET_LEN]) {
+        if self.command.is_empty() && packet[0] & 0x07 == 0 {
+            return;
+        }
+        self.command.extend_from_slice(packet);
+        if self.command.len() >= (self.command[0] & 0x07) as usize * PACKET_LEN {
+            let command = std::mem::take(&mut self.command);
+            self.execute(ram, &command);
+        }
+    }
+    fn execute(&mut self, ram: &Ram, data: &[u8]) {
+        let backdrop = self.palettes[0][0];
+        let mut border_changed = false;
+        match data[0] >> 3 {
+            PAL01 => self.set_palettes(0, 1, data),
+            PAL23 => self.set_palettes(2, 3, data),
+            PAL03 => self.set_palettes(0, 3, data),
+            PAL12 => self.set_palettes(1, 2, data),
+            ATTR_BLK => self.attr_blk(data),
+            ATTR_LIN => self.attr_lin(data),
+            ATTR_DIV => self.attr_div(data),
+            ATTR_CHR => self.attr_chr(data),
+            PAL_SET => self.pal_set(data),
+            PAL_TRN => {
+                let colors = transfer(ram);
+                for (palette, bytes) in self.system_palettes.iter_mut().zip(colors.chunks(8)) {
+                    *palette = [0, 1, 2, 3].map(|i| color(bytes, i * 2));
+                }
+            }
+            MLT_REQ => {
+                self.players = match data[1] & 0x03 {
+                    1 => 2,
+                    3 => 4,
+                    _ => 1,
+                };
+                self.player = 0;
+            }
+            CHR_TRN => {
+                let half = (data[1] & 0x01) as usize * TRANSFER_LEN;
+                self.border_tiles[half..half + TRANSFER_LEN].copy_from_slice(&transfer(ram));
+                border_changed = true;
+            }
+            PCT_TRN => {
+                self.border_map
+                    .copy_from_slice(&transfer(ram)[..BORDER_MAP_LEN]);
+                self.has_border = true;
+                border_changed = true;
+            }
+            ATTR_TRN => {
+                let bytes = transfer(ram);
+                for (file, bytes) in self.attr_files.iter_mut().zip(bytes.chunks(ATTR_FILE_LEN)) {
+                    file.copy_from_slice(bytes);
+                }
+            }
+            ATTR_SET => self.attr_set(data[1]),
+            MASK_EN => self.mask = Mask::from_bits(data[1]),
+            code => log::debug!("the SGB command {code:#04x} is not supported"),
+        }
+        // the transparent pixels of the border show color 0
+        if border_changed || self.palettes[0][0] != backdrop {
+            self.publish_border();
+        }
+    }
+    /// PAL01, PAL23, PAL03 and PAL12: color 0 for all palettes and colors 1-3 of two
+    fn set_palettes(&mut self, first: usize, second: usize, data: &[u8]) {
+        let backdrop = color(data, 1);
+        for palette in &mut self.palettes {
+            palette[0] = backdrop;
+        }
+        for i in 1..4 {
+            self.palettes[first][i] = color(data, 1 + i * 2);
+            self.palettes[second][i] = color(data, 7 + i * 2);
+        }
+    }
+    /// Palettes inside, on and outside of rectangles given in cells
+    fn attr_blk(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for set in data[2..].chunks_exact(6).take(count) {
+            let &[control, palettes, left, top, right, bottom] = set else {
+                continue;
+            };
+            let inside = palettes & 0x03;
+            let outside = palettes >> 4 & 0x03;
+            // with only the inside or the outside set, the border goes along with it
+            let border = match control & 0x07 {
+                0x01 => Some(inside),
+                0x04 => Some(outside),
+                _ if control & 0x02 != 0 => Some(palettes >> 2 & 0x03),
+                _ => None,
+            };
+            for (i, attribute) in self.attributes.iter_mut().enumerate() {
+                let (x, y) = ((i % CELLS_X) as u8, (i / CELLS_X) as u8);
+                let within = (left..=right).contains(&x) && (top..=bottom).contains(&y);
+                let on_edge = within && (x == left || x == right || y == top || y == bottom);
+                let palette = if on_edge {
+                    border
+                } else if within {
+                    (control & 0x01 != 0).then_some(inside)
+                } else {
+                    (control & 0x04 != 0).then_some(outside)
+                };
+                if let Some(palette) = palette {
+                    *attribute = palette;
+                }
+            }
+        }
+    }
+    /// Palettes of whole lines and columns of cells
+    fn attr_lin(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for &line in data[2..].iter().take(count) {
+            let number = (line & 0x1F) as usize;
+            let palette = line >> 5 & 0x03;
+            for (i, attribute) in self.attributes.iter_mut().enumerate() {
+                let (x, y) = (i % CELLS_X, i / CELLS_X);
+                let on_line = if line & 0x80 != 0 {
+                    y == number
+                } else {
+                    x == number
+                };
+                if on_line {
+                    *attribute = palette;
+                }
+            }
+        }
+    }
+    /// Divides the screen at a line or column of cells into two palettes and one on it
+    fn attr_div(&mut self, data: &[u8]) {
+        let control = data[1];
+        let at = data[2] as usize;
+        for (i, attribute) in self.attributes.iter_mut().enumerate() {
+            let (x, y) = (i % CELLS_X, i / CELLS_X);
+            let position = if control & 0x40 != 0 { y } else { x };
+            *attribute = match position.cmp(&at) {
+                std::cmp::Ordering::Less => control >> 2 & 0x03,
+                std::cmp::Ordering::Equal => control >> 4 & 0x03,
+                std::cmp::Ordering::Greater => control & 0x03,
+            };
+        }
+    }
+    /// The palettes of single cells from a start cell on, 4 cells per byte
+    fn attr_chr(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (data[1] as usize, data[2] as usize);
+        let count = u16::from_le_bytes([data[3], data[4]]) as usize;
+        let vertical = data[5] & 0x01 != 0;
+        let palettes = data[6..]
+            .iter()
+            .flat_map(|byte| [6, 4, 2, 0].map(|shift| byte >> shift & 0x03));
+        for palette in palettes.take(count.min(CELLS_X * CELLS_Y)) {
+            if x >= CELLS_X || y >= CELLS_Y {
+                break;
+            }
+            self.attributes[y * CELLS_X + x] = palette;
+            if vertical {
+                y += 1;
+                if y == CELLS_Y {
+                    (x, y) = (x + 1, 0);
+                }
+            } else {
+                x += 1;
+                if x == CELLS_X {
+                    (x, y) = (0, y + 1);
+                }
+            }
+        }
+    }
+    /// Picks the 4 palettes from the system palettes, maybe with an attribute file
+    fn pal_set(&mut self, data: &[u8]) {
+        for i in 0..4 {
+            let number = u16::from_le_bytes([data[1 + i * 2], data[2 + i * 2]]) as usize;
+            self.palettes[i] = self.system_palettes[number % SYSTEM_PALETTES];
+        }
+        let backdrop = self.palettes[0][0];
+        for palette in &mut self.palettes {
+            palette[0] = backdrop;
+        }
+        if data[9] & 0x80 != 0 {
+            self.attr_set(data[9] & 0x7F);
+        }
+    }
+    /// Applies an attribute file, bit 6 ends the mask
+    fn attr_set(&mut self, value: u8) {
+        let Some(file) = self.attr_files.get((value & 0x3F) as usize) else {
+            return;
+        };
+        for (i, attribute) in self.attributes.iter_mut().enumerate() {
+            *attribute = file[i / 4] >> (6 - i % 4 * 2) & 0x03;
+        }
+        if value & 0x40 != 0 {
+            self.mask = Mask::Off;
+        }
+    }
+    /// The frame of shades in the colors of the cells, `None` while the screen is frozen
+    pub fn colorize(&self, frame: &[u8]) -> Option<Box<ColorFrameBuffer>> {
+        let mut colors = Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]);
+        match self.mask {
+            Mask::Freeze => return None,
+            Mask::Black => {}
+            Mask::Color0 => colors.fill(self.palettes[0][0]),
+            Mask::Off => {
+                for (i, pixel) in colors.iter_mut().enumerate() {
+                    let (x, y) = (i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+                    let palette = self.attributes[y / 8 * CELLS_X + x / 8] as usize;
+                    *pixel = self.palettes[palette][frame[i] as usize & 0x03];
+                }
+            }
+        }
+        Some(colors)
+    }
+    /// Draws the border from the tiles and the map and sends it to the gui
+    fn publish_border(&self) {
+        if !self.has_border {
+            return;
+        }
+        let mut image = Box::new([0; BORDER_WIDTH * BORDER_HEIGHT]);
+        for (i, pixel) in image.iter_mut().enumerate() {
+            let (x, y) = (i % BORDER_WIDTH, i / BORDER_WIDTH);
+            let entry = (y / 8 * 32 + x / 8) * 2;
+            let entry = u16::from_le_bytes([self.border_map[entry], self.border_map[entry + 1]]);
+            let column = if entry & 0x4000 != 0 {
+                x % 8
+            } else {
+                7 - x % 8
+            };
+            let row = if entry & 0x8000 != 0 {
+                7 - y % 8
+            } else {
+                y % 8
+            };
+            // the bit planes 0 and 1 of the rows, then 2 and 3
+            let tile = &self.border_tiles[(entry & 0xFF) as usize * 32..];
+            let index = (0..4).fold(0, |index, plane| {
+                let byte = tile[plane / 2 * 16 + row * 2 + plane % 2];
+                index | (byte >> column & 1) << plane
+            }) as usize;
+            *pixel = if index == 0 {
+                self.palettes[0][0]
+            } else {
+                // the map names the palettes 4-7
+                let palette = (entry >> 10 & 0x03) as usize;
+                color(&self.border_map, 0x800 + palette * 32 + index * 2)
+            };
+        }
+        self.border.set(Some(image));
+    }
+    /// The palettes, the cells and what the transfers sent, the packet in progress is lost
+    pub fn save_state(&self, w: &mut Writer) {
+        for color in self.palettes.iter().flatten() {
+            w.u16(*color);
+        }
+        w.bytes(&self.attributes);
+        for color in self.system_palettes.iter().flatten() {
+            w.u16(*color);
+        }
+        for file in &self.attr_files {
+            w.bytes(file);
+        }
+        w.u8(self.mask as u8);
+        w.u8(self.players);
+        w.u8(self.player);
+        w.bytes(&self.border_tiles);
+        w.bytes(&self.border_map);
+        w.bool(self.has_border);
+    }
+    pub fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        for color in self.palettes.iter_mut().flatten() {
+            *color = r.u16()?;
+        }
+        self.attributes.copy_from_slice(r.bytes(CELLS_X * CELLS_Y)?);
+        for color in self.system_palettes.iter_mut().flatten() {
+            *color = r.u16()?;
+        }
+        for file in &mut self.attr_files {
+            file.copy_from_slice(r.bytes(ATTR_FILE_LEN)?);
+        }
+        self.mask = Mask::from_bits(r.u8()?);
+        self.players = r.u8()?;
+        self.player = r.u8()?;
+        self.border_tiles
+            .copy_from_slice(r.bytes(BORDER_TILES_LEN)?);
+        self.border_map.copy_from_slice(r.bytes(BORDER_MAP_LEN)?);
+        self.has_border = r.bool()?;
+        self.bit = None;
+        self.command.clear();
+        self.border.set(None);
+        self.publish_border();
+        Ok(())
+    }
+}
+/// The little endian color at `at`
+fn color(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]) & 0x7FFF
+}
+/// The 4 KiB the SGB copies from the screen: the tiles of the background map,
+/// 20 per row from the top left
+fn transfer(ram: &Ram) -> Vec<u8> {
+    let lcdc = ram[LCDC];
+    let map = BG_MAPS[(lcdc >> 3) as usize & 1];
+    let mut bytes = Vec::with_capacity(TRANSFER_LEN);
+    for i in 0..TRANSFER_LEN / 16 {
+        let entry = map + (i / CELLS_X * 32 + i % CELLS_X) as u16;
+        let tile = tile_addr(lcdc, ram[entry]);
+        bytes.extend((0..16).map(|offset| ram[tile + offset]));
+    }
+    bytes
+}
+
+/// Passes the frames through the SGB palettes, the other signals as they are
+pub struct SgbVideo<'a> {
+    pub sgb: &'a Sgb,
+    pub video: &'a mut dyn VideoSink,
+}
+impl VideoSink for SgbVideo<'_> {
+    fn send(&mut self, signal: DrawSignal) {
+        match signal {
+            DrawSignal::Frame(frame) => {
+                if let Some(colors) = self.sgb.colorize(&frame[..]) {
+                    self.video.send(DrawSignal::ColorFrame(colors));
+                }
+            }
+            signal => self.video.send(signal),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BorderInner {
+    image: Option<Box<BorderImage>>,
+    /// Counts the changes, so the gui uploads it only when it changed
+    version: u32,
+}
+
+/// The border the game sent, shared between the gui and the cpu thread
+#[derive(Clone, Debug, Default)]
+pub struct SgbBorder(Arc<Mutex<BorderInner>>);
+impl SgbBorder {
+    pub fn set(&self, image: Option<Box<BorderImage>>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.image = image;
+        inner.version = inner.version.wrapping_add(1);
+    }
+    pub fn version(&self) -> u32 {
+        self.0.lock().unwrap().version
+    }
+    pub fn image(&self) -> Option<Box<BorderImage>> {
+        self.0.lock().unwrap().image.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        bus::{Bus, BOOT_ROM_DISABLE},
+        cartridge::{Cartridge, OLD_LICENSEE, SGB_FLAG},
+        frontend::{FrameCallback, Screen},
+        joypad::P1,
+        testing::micro_rom,
+        vram::{BGP, LCDC},
+    };
+
+    use super::*;
+
+    /// Writes a packet into P1 like SGB games send them, bit 0 first and a 0 at the end
+    fn sgb_packet(bus: &mut Bus, packet: [u8; 16]) {
+        bus.write_mem(P1, 0x00);
+        bus.write_mem(P1, 0x30);
+        for i in 0..16 * 8 {
+            let one = packet[i / 8] >> (i % 8) & 1 != 0;
+            bus.write_mem(P1, if one { 0x10 } else { 0x20 });
+            bus.write_mem(P1, 0x30);
+        }
+        bus.write_mem(P1, 0x20);
+        bus.write_mem(P1, 0x30);
+    }
+    /// An SGB game colors the screen with PAL01, gets a border and reads the joypad ids
+    #[test]
+    fn sgb_packets() -> Result<(), String> {
+        let mut rom = micro_rom(&[0x76]);
+        (rom[SGB_FLAG], rom[OLD_LICENSEE]) = (0x03, 0x33);
+        let pixel = Arc::new(AtomicUsize::new(0));
+        let first = pixel.clone();
+        let border = SgbBorder::default();
+        let mut bus = Bus::default()
+            .with_sgb_border(border.clone())
+            .with_cartridge(Cartridge::from_bytes(rom).map_err(|e| e.to_string())?)
+            .with_video(FrameCallback(move |screen: Screen| {
+                if let Screen::Colors(frame) = screen {
+                    first.store(frame[0] as usize, Ordering::Relaxed);
+                }
+            }));
+        if !bus.is_sgb() {
+            return Err("the header does not mark an SGB game".to_string());
+        }
+        bus.write_mem(BOOT_ROM_DISABLE, 1);
+        // PAL01 in one packet, with pure red as color 0
+        sgb_packet(
+            &mut bus,
+            [1, 0x1F, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        bus.write_mem(LCDC, 0x91);
+        bus.write_mem(BGP, 0xE4);
+        bus.tick(456 * 154);
+        let red = pixel.load(Ordering::Relaxed) as u16;
+        if red != 0x001F {
+            return Err(format!("the screen shows {red:04X} instead of red"));
+        }
+        // PCT_TRN of an empty screen, a border of color 0
+        sgb_packet(
+            &mut bus,
+            [0x14 << 3 | 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        match border.image() {
+            Some(image) if image[0] == 0x001F => {}
+            Some(image) => return Err(format!("the border starts with {:04X}", image[0])),
+            None => return Err("PCT_TRN sent no border".to_string()),
+        }
+        // MLT_REQ for 2 joypads, P15 going high selects the next
+        sgb_packet(
+            &mut bus,
+            [0x11 << 3 | 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            bus.write_mem(P1, 0x30);
+            ids.push(bus.fetch(P1) & 0x0F);
+            bus.write_mem(P1, 0x10);
+        }
+        if ids != [0xF, 0xE, 0xF] {
+            return Err(format!("the joypad ids read {ids:X?}"));
+        }
+        Ok(())
+    }
+}
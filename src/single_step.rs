@@ -0,0 +1,294 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    bus::{Bus, BusAccess},
+    cpu::{Cpu, CpuState},
+};
+#[cfg(any(test, feature = "single-step"))]
+use serde_json::Value;
+
+/// IE, the single step tests set it along with the registers
+const IE: u16 = 0xFFFF;
+
+/// Numbers, and booleans as 0 and 1
+#[cfg(any(test, feature = "single-step"))]
+fn number(json: &Value) -> Option<i64> {
+    json.as_i64().or_else(|| json.as_bool().map(i64::from))
+}
+
+/// The registers, IE and the memory before or after the instruction
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TestState {
+    pub cpu: CpuState,
+    pub ie: u8,
+    pub ram: Vec<(u16, u8)>,
+}
+impl TestState {
+    #[cfg(any(test, feature = "single-step"))]
+    fn parse(json: &Value) -> Result<Self, String> {
+        let field = |key: &str| json.get(key).and_then(number).ok_or(format!("no {key}"));
+        let byte = |key: &str| field(key).map(|n| n as u8);
+        let cpu = CpuState {
+            a: byte("a")?,
+            f: byte("f")?,
+            b: byte("b")?,
+            c: byte("c")?,
+            d: byte("d")?,
+            e: byte("e")?,
+            h: byte("h")?,
+            l: byte("l")?,
+            sp: field("sp")? as u16,
+            pc: field("pc")? as u16,
+            ime: field("ime").unwrap_or(0) != 0,
+        };
+        let ram = json
+            .get("ram")
+            .and_then(Value::as_array)
+            .ok_or("no ram")?
+            .iter()
+            .map(|entry| match entry.as_array().map(Vec::as_slice) {
+                Some([addr, value]) => Some((number(addr)? as u16, number(value)? as u8)),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or("bad ram entry")?;
+        Ok(TestState {
+            cpu,
+            ie: field("ie").unwrap_or(0) as u8,
+            ram,
+        })
+    }
+}
+
+/// One instruction with the state before and after it. The pc of the initial state
+/// points at the opcode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    pub name: String,
+    pub initial: TestState,
+    pub expected: TestState,
+    /// The bus access of every machine cycle, `None` for internal ones
+    pub cycles: Vec<Option<BusAccess>>,
+}
+impl TestCase {
+    #[cfg(any(test, feature = "single-step"))]
+    fn parse(json: &Value) -> Result<Self, String> {
+        let name = json
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("no name")?
+            .to_string();
+        let state = |key: &str| {
+            TestState::parse(json.get(key).ok_or(format!("{name}: no {key}"))?)
+                .map_err(|e| format!("{name}: {e}"))
+        };
+        let cycles = json
+            .get("cycles")
+            .and_then(Value::as_array)
+            .ok_or(format!("{name}: no cycles"))?
+            .iter()
+            .map(|cycle| match cycle.as_array().map(Vec::as_slice) {
+                // `r-m` reads, `-wm` writes, `---` is internal
+                Some([addr, value, Value::String(pins)]) if pins.contains(['r', 'w']) => {
+                    Some(BusAccess {
+                        addr: number(addr)? as u16,
+                        value: number(value)? as u8,
+                        write: pins.contains('w'),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(TestCase {
+            initial: state("initial")?,
+            expected: state("final")?,
+            cycles,
+            name,
+        })
+    }
+    /// Runs the instruction on a flat bus, returns the first difference to the expected state
+    pub fn run(&self) -> Result<(), String> {
+        let mut bus = Bus::flat();
+        for &(addr, value) in &self.initial.ram {
+            bus.poke(addr, value);
+        }
+        bus.poke(IE, self.initial.ie);
+        let mut cpu = Cpu::new(bus);
+        cpu.set_state(self.initial.cpu);
+        let cycles = cpu.execute_next().map_err(|e| e.to_string())?;
+        let state = cpu.state();
+        let expected = self.expected.cpu;
+        let registers = [
+            ("A", state.a as u16, expected.a as u16),
+            ("F", state.f as u16, expected.f as u16),
+            ("B", state.b as u16, expected.b as u16),
+            ("C", state.c as u16, expected.c as u16),
+            ("D", state.d as u16, expected.d as u16),
+            ("E", state.e as u16, expected.e as u16),
+            ("H", state.h as u16, expected.h as u16),
+            ("L", state.l as u16, expected.l as u16),
+            ("SP", state.sp, expected.sp),
+            ("PC", state.pc, expected.pc),
+            ("IME", state.ime as u16, expected.ime as u16),
+        ];
+        for (name, value, expected) in registers {
+            if value != expected {
+                return Err(format!("{name} is {value:#04x}, expected {expected:#04x}"));
+            }
+        }
+        let bus = cpu.bus();
+        let accesses = bus.take_accesses();
+        let ram = bus.ram();
+        let ram = ram.read().unwrap();
+        for &(addr, expected) in &self.expected.ram {
+            if ram[addr] != expected {
+                return Err(format!(
+                    "${addr:04X} holds {:#04x}, expected {expected:#04x}",
+                    ram[addr]
+                ));
+            }
+        }
+        let expected_accesses: Vec<BusAccess> = self.cycles.iter().flatten().copied().collect();
+        if accesses != expected_accesses {
+            return Err(format!(
+                "accessed {accesses:x?}, expected {expected_accesses:x?}"
+            ));
+        }
+        let expected_cycles = self.cycles.len() * 4;
+        if cycles != expected_cycles {
+            return Err(format!("took {cycles} cycles, expected {expected_cycles}"));
+        }
+        Ok(())
+    }
+}
+
+/// Parses a file of test vectors, an array of cases
+#[cfg(any(test, feature = "single-step"))]
+pub fn parse(s: &str) -> Result<Vec<TestCase>, String> {
+    let json: Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+    json.as_array()
+        .ok_or("not an array of tests")?
+        .iter()
+        .map(TestCase::parse)
+        .collect()
+}
+#[cfg(not(any(test, feature = "single-step")))]
+pub fn parse(_s: &str) -> Result<Vec<TestCase>, String> {
+    Err("the json tests need a build with the single-step feature".to_string())
+}
+
+/// How the cases of one file went
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileResult {
+    pub passed: usize,
+    pub total: usize,
+    /// The name and the difference of the first failing case
+    pub first_failure: Option<(String, String)>,
+}
+impl FileResult {
+    pub fn passed_all(&self) -> bool {
+        self.passed == self.total
+    }
+}
+pub fn run_file(path: &Path) -> io::Result<FileResult> {
+    let cases = parse(&fs::read_to_string(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut result = FileResult {
+        total: cases.len(),
+        ..FileResult::default()
+    };
+    for case in &cases {
+        match case.run() {
+            Ok(()) => result.passed += 1,
+            Err(e) => {
+                result.first_failure.get_or_insert((case.name.clone(), e));
+            }
+        }
+    }
+    Ok(result)
+}
+/// Runs a file of test vectors, or every `.json` file in a directory in the order
+/// of their names. Returns the results by file name.
+pub fn run(path: &Path) -> io::Result<Vec<(String, FileResult)>> {
+    let mut files = if path.is_dir() {
+        fs::read_dir(path)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files.sort();
+    files
+        .iter()
+        .map(|file| {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            Ok((name.into_owned(), run_file(file)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ld a, b` in the format of the SM83 json tests, once as it is and once expecting
+    /// the wrong value in A
+    const LD_A_B_VECTORS: &str = r#"[
+        {"name": "78 0000",
+         "initial": {"pc": 49152, "sp": 65534, "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 176,
+                     "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 120]]},
+         "final": {"pc": 49153, "sp": 65534, "a": 2, "b": 2, "c": 3, "d": 4, "e": 5, "f": 176,
+                   "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 120]]},
+         "cycles": [[49152, 120, "r-m"]]},
+        {"name": "78 0001",
+         "initial": {"pc": 49152, "sp": 65534, "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 176,
+                     "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 120]]},
+         "final": {"pc": 49153, "sp": 65534, "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 176,
+                   "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 120]]},
+         "cycles": [[49152, 120, "r-m"]]}
+    ]"#;
+    #[test]
+    fn single_step_vectors() -> Result<(), String> {
+        let cases = parse(LD_A_B_VECTORS)?;
+        if cases.len() != 2 || cases[0].cycles.len() != 1 || cases[0].initial.cpu.pc != 0xC000 {
+            return Err(format!("parsed {cases:?}"));
+        }
+        cases[0]
+            .run()
+            .map_err(|e| format!("{}: {e}", cases[0].name))?;
+        match cases[1].run() {
+            Err(e) if e.starts_with("A is 0x02") => Ok(()),
+            result => Err(format!("a wrong A gave {result:?}")),
+        }
+    }
+    /// Vendored files the cpu is known to fail, with the reason. Passing one of them
+    /// fails the test as well, so the list is kept up to date.
+    const EXPECTED_FAILURES: [(&str, &str); 1] = [(
+        "cb 37.json",
+        "the instructions behind the 0xCB prefix are not implemented",
+    )];
+    /// A few cases per file in the format of the SM83 json tests, checked by hand. The whole
+    /// set runs with `--single-step`.
+    #[test]
+    fn vendored_vectors() -> Result<(), String> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83");
+        let results = run(&dir).map_err(|e| e.to_string())?;
+        if results.is_empty() {
+            return Err(format!("no vectors in {}", dir.display()));
+        }
+        for (file, result) in results {
+            let expected = EXPECTED_FAILURES.iter().find(|(name, _)| *name == file);
+            match (result.first_failure, expected) {
+                (Some((name, e)), None) => return Err(format!("{file}: {name}: {e}")),
+                (None, Some((_, reason))) => {
+                    return Err(format!(
+                        "{file} passes, but is expected to fail as {reason}"
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::backup::{self, Backup};
+
+/// Numbered save state slots per game
+pub const SLOTS: usize = 10;
+/// Earlier states kept of every slot, saving over a slot backs up its state
+pub const SLOT_BACKUPS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotRequest {
+    Save(usize),
+    Load(usize),
+    /// Loads the state saved when the game was closed and deletes it
+    Resume,
+}
+
+#[derive(Debug, Default)]
+struct SlotsInner {
+    dir: PathBuf,
+    selected: usize,
+    request: Option<SlotRequest>,
+    /// What happened to the last request, an error message if it failed
+    result: Option<(SlotRequest, Result<(), String>)>,
+    /// The last loaded state is older than the battery save, whose ram it replaced
+    older_than_save: bool,
+    /// The cpu saves a state when it stops, which is offered on the next start of the game
+    suspend_on_exit: bool,
+}
+
+/// The save state slots, the gui requests saving and loading and the cpu does it
+/// after the current frame. Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Slots(Arc<Mutex<SlotsInner>>);
+impl Slots {
+    /// The states are kept in `dir`
+    pub fn new(dir: PathBuf) -> Self {
+        Slots(Arc::new(Mutex::new(SlotsInner {
+            dir,
+            ..Default::default()
+        })))
+    }
+    /// The file of a slot, the game is identified by the CRC32 of its rom
+    pub fn path(&self, crc: u32, slot: usize) -> PathBuf {
+        let dir = &self.0.lock().unwrap().dir;
+        dir.join(format!("{crc:08x}.{slot}.state"))
+    }
+    /// The screen at the time the slot was saved, next to the state
+    pub fn thumbnail_path(&self, crc: u32, slot: usize) -> PathBuf {
+        self.path(crc, slot).with_extension("png")
+    }
+    /// The state saved when the game was closed, see `SlotRequest::Resume`
+    pub fn suspend_path(&self, crc: u32) -> PathBuf {
+        let dir = &self.0.lock().unwrap().dir;
+        dir.join(format!("{crc:08x}.suspend.state"))
+    }
+    /// Whether the game was closed with a state to continue from
+    pub fn can_resume(&self, crc: u32) -> bool {
+        self.suspend_path(crc).exists()
+    }
+    /// Starting over, the state saved on exit is deleted
+    pub fn discard_suspend(&self, crc: u32) -> io::Result<()> {
+        match fs::remove_file(self.suspend_path(crc)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+    pub fn set_suspend_on_exit(&self, suspend: bool) {
+        self.0.lock().unwrap().suspend_on_exit = suspend;
+    }
+    pub fn suspend_on_exit(&self) -> bool {
+        self.0.lock().unwrap().suspend_on_exit
+    }
+    /// When the slot was saved, `None` if it is empty
+    pub fn modified(&self, crc: u32, slot: usize) -> Option<SystemTime> {
+        self.path(crc, slot).metadata().ok()?.modified().ok()
+    }
+    /// The earlier states of a slot, newest first
+    pub fn backups(&self, crc: u32, slot: usize) -> Vec<Backup> {
+        backup::list(&self.path(crc, slot)).unwrap_or_default()
+    }
+    /// Puts an earlier state back into its slot, the current one becomes a backup
+    pub fn restore(&self, crc: u32, slot: usize, backup: &Backup) -> io::Result<()> {
+        backup::restore(&self.path(crc, slot), backup, SLOT_BACKUPS)
+    }
+    pub fn selected(&self) -> usize {
+        self.0.lock().unwrap().selected
+    }
+    pub fn select(&self, slot: usize) {
+        self.0.lock().unwrap().selected = slot % SLOTS;
+    }
+    pub fn next(&self) {
+        self.select(self.selected() + 1);
+    }
+    pub fn previous(&self) {
+        self.select(self.selected() + SLOTS - 1);
+    }
+    pub fn request(&self, request: SlotRequest) {
+        self.0.lock().unwrap().request = Some(request);
+    }
+    /// Called by the cpu after every frame
+    pub fn take_request(&self) -> Option<SlotRequest> {
+        self.0.lock().unwrap().request.take()
+    }
+    pub fn report(&self, request: SlotRequest, result: Result<(), String>) {
+        self.0.lock().unwrap().result = Some((request, result));
+    }
+    pub fn result(&self) -> Option<(SlotRequest, Result<(), String>)> {
+        self.0.lock().unwrap().result.clone()
+    }
+    pub fn set_older_than_save(&self, older: bool) {
+        self.0.lock().unwrap().older_than_save = older;
+    }
+    pub fn older_than_save(&self) -> bool {
+        self.0.lock().unwrap().older_than_save
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Restoring puts the earlier state back and keeps the overwritten one
+    #[test]
+    fn restore_backup() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("gba-test-slots-{}", std::process::id()));
+        let slots = Slots::new(dir.clone());
+        let path = slots.path(0x1234, 3);
+        let result = (|| {
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            fs::write(&path, "good").map_err(|e| e.to_string())?;
+            backup::backup(&path, SLOT_BACKUPS).map_err(|e| e.to_string())?;
+            fs::write(&path, "bad").map_err(|e| e.to_string())?;
+            let backups = slots.backups(0x1234, 3);
+            let [good] = &backups[..] else {
+                return Err(format!("{} backups of one save", backups.len()));
+            };
+            // the backups are told apart by their millisecond
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            slots.restore(0x1234, 3, good).map_err(|e| e.to_string())?;
+            let restored = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            let kept = slots.backups(0x1234, 3);
+            match kept.first().map(|newest| fs::read_to_string(&newest.path)) {
+                Some(Ok(newest)) if restored == "good" && newest == "bad" => Ok(()),
+                newest => Err(format!(
+                    "restored {restored:?}, the newest backup is {newest:?}"
+                )),
+            }
+        })();
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+}
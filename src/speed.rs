@@ -0,0 +1,204 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+    Arc,
+};
+
+/// Emulation speed in percent, the pause state and stopping,
+/// shared between the gui and the cpu thread
+#[derive(Clone, Debug)]
+pub struct Speed(Arc<SpeedInner>);
+#[derive(Debug)]
+struct SpeedInner {
+    percent: AtomicU32,
+    paused: AtomicBool,
+    /// Frames to run before pausing again, 0 if not stepping
+    frames_left: AtomicU32,
+    /// `Desync` as u8
+    desync: AtomicU8,
+    /// `Refresh` as u8
+    refresh: AtomicU8,
+    /// Fast-forward instead of `percent` while set
+    turbo: AtomicBool,
+    /// `Turbo` as u8
+    turbo_rate: AtomicU8,
+    /// Frames emulated ahead of the one shown, see `Cpu::step_frame_ahead`
+    run_ahead: AtomicU8,
+    stopped: AtomicBool,
+}
+impl Speed {
+    pub const MIN: u32 = 25;
+    pub const MAX: u32 = 400;
+    pub const STEP: u32 = 25;
+    pub const MAX_RUN_AHEAD: u8 = 4;
+    pub fn get(&self) -> u32 {
+        self.0.percent.load(Ordering::Relaxed)
+    }
+    pub fn set(&self, percent: u32) {
+        self.0
+            .percent
+            .store(percent.clamp(Self::MIN, Self::MAX), Ordering::Relaxed);
+    }
+    pub fn faster(&self) {
+        self.set(self.get() + Self::STEP);
+    }
+    pub fn slower(&self) {
+        self.set(self.get().saturating_sub(Self::STEP));
+    }
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+    pub fn set_paused(&self, paused: bool) {
+        self.0.paused.store(paused, Ordering::Relaxed);
+    }
+    /// Runs the given number of frames and pauses again
+    pub fn run_frames(&self, frames: u32) {
+        self.0.frames_left.store(frames, Ordering::Relaxed);
+        self.set_paused(frames == 0);
+    }
+    pub fn desync(&self) -> Desync {
+        match self.0.desync.load(Ordering::Relaxed) {
+            1 => Desync::DropFrames,
+            2 => Desync::StretchAudio,
+            _ => Desync::Resync,
+        }
+    }
+    pub fn set_desync(&self, desync: Desync) {
+        self.0.desync.store(desync as u8, Ordering::Relaxed);
+    }
+    pub fn refresh(&self) -> Refresh {
+        match self.0.refresh.load(Ordering::Relaxed) {
+            1 => Refresh::Host60,
+            2 => Refresh::Audio,
+            _ => Refresh::Native,
+        }
+    }
+    pub fn set_refresh(&self, refresh: Refresh) {
+        self.0.refresh.store(refresh as u8, Ordering::Relaxed);
+    }
+    pub fn is_turbo(&self) -> bool {
+        self.0.turbo.load(Ordering::Relaxed)
+    }
+    /// Fast-forwards at the `turbo_rate` while set, held with a key or toggled in the gui
+    pub fn set_turbo(&self, turbo: bool) {
+        self.0.turbo.store(turbo, Ordering::Relaxed);
+    }
+    pub fn turbo_rate(&self) -> Turbo {
+        match self.0.turbo_rate.load(Ordering::Relaxed) {
+            0 => Turbo::X2,
+            1 => Turbo::X4,
+            2 => Turbo::X8,
+            _ => Turbo::Uncapped,
+        }
+    }
+    pub fn set_turbo_rate(&self, rate: Turbo) {
+        self.0.turbo_rate.store(rate as u8, Ordering::Relaxed);
+    }
+    pub fn run_ahead(&self) -> u8 {
+        self.0.run_ahead.load(Ordering::Relaxed)
+    }
+    /// Shows the frame `frames` later than the emulated one, 0 turns run-ahead off
+    pub fn set_run_ahead(&self, frames: u8) {
+        self.0
+            .run_ahead
+            .store(frames.min(Self::MAX_RUN_AHEAD), Ordering::Relaxed);
+    }
+    /// Ends the cpu thread after the current frame, it writes the save first
+    pub fn stop(&self) {
+        self.0.stopped.store(true, Ordering::Relaxed);
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.0.stopped.load(Ordering::Relaxed)
+    }
+    /// Called by the cpu after every emulated frame
+    pub fn frame_done(&self) {
+        let left = self
+            .0
+            .frames_left
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        if left == Ok(1) {
+            self.set_paused(true);
+        }
+    }
+}
+impl Default for Speed {
+    fn default() -> Self {
+        Speed(Arc::new(SpeedInner {
+            percent: AtomicU32::new(100),
+            paused: AtomicBool::new(false),
+            frames_left: AtomicU32::new(0),
+            desync: AtomicU8::new(Desync::default() as u8),
+            refresh: AtomicU8::new(Refresh::default() as u8),
+            turbo: AtomicBool::new(false),
+            turbo_rate: AtomicU8::new(Turbo::default() as u8),
+            run_ahead: AtomicU8::new(0),
+            stopped: AtomicBool::new(false),
+        }))
+    }
+}
+
+/// What happens to the emulation while the window is not focused
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FocusLoss {
+    #[default]
+    KeepRunning,
+    Pause,
+    /// Run at the minimum speed
+    Throttle,
+}
+
+/// How the emulation recovers after it fell behind real time
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Desync {
+    /// Continues from now on, the lost time is not made up
+    #[default]
+    Resync,
+    /// Runs without drawing until it caught up
+    DropFrames,
+    /// Plays the audio slower while behind, so it does not crackle
+    StretchAudio,
+}
+
+/// The rate at which emulated frames are shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Refresh {
+    /// 59.7275 Hz like the hardware, judders a little with vsync on a 60 Hz display
+    #[default]
+    Native,
+    /// Exactly 60 Hz to match the display, the audio plays slightly higher
+    Host60,
+    /// Waits for the audio output to take the samples, without crackle but with the clock of
+    /// the sound card. Falls back to `Native` without audio or at another speed
+    Audio,
+}
+
+/// How fast fast-forward runs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Turbo {
+    X2,
+    X4,
+    X8,
+    /// As fast as the host can
+    #[default]
+    Uncapped,
+}
+impl Turbo {
+    pub const ALL: [Turbo; 4] = [Turbo::X2, Turbo::X4, Turbo::X8, Turbo::Uncapped];
+    /// Multiple of the normal speed, `None` when uncapped
+    pub fn factor(self) -> Option<u32> {
+        match self {
+            Turbo::X2 => Some(2),
+            Turbo::X4 => Some(4),
+            Turbo::X8 => Some(8),
+            Turbo::Uncapped => None,
+        }
+    }
+    /// The i18n key of the name
+    pub fn name(self) -> &'static str {
+        match self {
+            Turbo::X2 => "turbo-x2",
+            Turbo::X4 => "turbo-x4",
+            Turbo::X8 => "turbo-x8",
+            Turbo::Uncapped => "turbo-uncapped",
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::rtc::FOOTER_SIZE;
+
+/// Older emulators store the save time of the clock in 32 bits
+const SHORT_FOOTER_SIZE: usize = FOOTER_SIZE - 4;
+const FOOTERS: [usize; 3] = [0, FOOTER_SIZE, SHORT_FOOTER_SIZE];
+
+/// How a save file is laid out, other emulators pad the ram or append a clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveLayout {
+    /// The ram, followed by a clock footer of `footer` bytes
+    Ram { footer: usize },
+    /// The ram padded to `padded` bytes, e.g. 32 KiB for every cartridge, then the footer
+    Padded { padded: usize, footer: usize },
+    /// Less than the ram, the rest keeps its power-on content
+    Short,
+    /// Nothing known, the start is used as the ram
+    Unknown,
+}
+impl SaveLayout {
+    /// Recognizes the layout from the size of the file and of the cartridge ram
+    pub fn detect(len: usize, ram_len: usize) -> SaveLayout {
+        if let Some(footer) = FOOTERS.into_iter().find(|footer| len == ram_len + footer) {
+            return SaveLayout::Ram { footer };
+        }
+        if len < ram_len {
+            return SaveLayout::Short;
+        }
+        FOOTERS
+            .into_iter()
+            .map(|footer| (len - footer, footer))
+            .find(|(padded, _)| *padded > ram_len && padded.is_power_of_two())
+            .map_or(SaveLayout::Unknown, |(padded, footer)| SaveLayout::Padded {
+                padded,
+                footer,
+            })
+    }
+    /// The ram and the clock footer of `save`, either may be empty
+    pub fn split(self, save: &[u8], ram_len: usize) -> (&[u8], &[u8]) {
+        let ram = &save[..save.len().min(ram_len)];
+        let footer = match self {
+            SaveLayout::Ram { footer } | SaveLayout::Padded { footer, .. } => {
+                &save[save.len() - footer..]
+            }
+            SaveLayout::Short | SaveLayout::Unknown => &[],
+        };
+        (ram, footer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saves of other emulators are recognized by their size
+    #[test]
+    fn save_layouts() -> Result<(), String> {
+        let kib = 1024;
+        for (len, ram_len, expected) in [
+            (8 * kib, 8 * kib, SaveLayout::Ram { footer: 0 }),
+            (32 * kib + 48, 32 * kib, SaveLayout::Ram { footer: 48 }),
+            (8 * kib + 44, 8 * kib, SaveLayout::Ram { footer: 44 }),
+            // a clock without ram
+            (48, 0, SaveLayout::Ram { footer: 48 }),
+            (
+                32 * kib,
+                8 * kib,
+                SaveLayout::Padded {
+                    padded: 32 * kib,
+                    footer: 0,
+                },
+            ),
+            (
+                32 * kib + 48,
+                8 * kib,
+                SaveLayout::Padded {
+                    padded: 32 * kib,
+                    footer: 48,
+                },
+            ),
+            (2 * kib, 8 * kib, SaveLayout::Short),
+            (8 * kib + 3, 8 * kib, SaveLayout::Unknown),
+        ] {
+            let layout = SaveLayout::detect(len, ram_len);
+            if layout != expected {
+                return Err(format!(
+                    "{len} bytes for {ram_len} are {layout:?}, expected {expected:?}"
+                ));
+            }
+        }
+        let save: Vec<u8> = (0..32 * kib + 48).map(|i| i as u8).collect();
+        let layout = SaveLayout::detect(save.len(), 8 * kib);
+        let (ram, footer) = layout.split(&save, 8 * kib);
+        if ram.len() != 8 * kib || footer != &save[32 * kib..] {
+            return Err(format!(
+                "split into {} and {} bytes",
+                ram.len(),
+                footer.len()
+            ));
+        }
+        Ok(())
+    }
+}
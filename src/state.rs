@@ -0,0 +1,200 @@
+use std::{fmt, fs, io, path::Path};
+
+/// Start of every save state file
+const MAGIC: [u8; 4] = *b"GBSS";
+/// Bumped whenever the layout changes, states of other versions are rejected
+pub const VERSION: u16 = 4;
+
+#[derive(Debug)]
+pub enum StateError {
+    NotAState,
+    Version(u16),
+    /// The state was saved with the rom of this CRC32
+    OtherRom(u32),
+    Truncated,
+    Io(io::Error),
+}
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "not a save state"),
+            StateError::Version(version) => {
+                write!(f, "saved by version {version}, this is version {VERSION}")
+            }
+            StateError::OtherRom(crc) => write!(f, "saved with another rom ({crc:08x})"),
+            StateError::Truncated => write!(f, "the state ends early"),
+            StateError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+impl std::error::Error for StateError {}
+impl From<io::Error> for StateError {
+    fn from(e: io::Error) -> Self {
+        StateError::Io(e)
+    }
+}
+
+/// Collects the machine state, all numbers are little endian
+#[derive(Default)]
+pub struct Writer(Vec<u8>);
+impl Writer {
+    pub fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+    pub fn u16(&mut self, value: u16) {
+        self.0.extend(value.to_le_bytes());
+    }
+    pub fn u32(&mut self, value: u32) {
+        self.0.extend(value.to_le_bytes());
+    }
+    pub fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+    /// Bytes whose length is not fixed, with the length in front
+    pub fn block(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.bytes(bytes);
+    }
+    pub fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// Reads what `Writer` wrote in the same order
+pub struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    pub fn new(state: &'a [u8]) -> Self {
+        Reader(state)
+    }
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.0.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.u8()? != 0)
+    }
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    pub fn f32(&mut self) -> Result<f32, StateError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+    pub fn block(&mut self) -> Result<&'a [u8], StateError> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+}
+
+/// Writes a state of the machine running the rom with `crc`
+pub fn write_file(path: &Path, crc: u32, state: &[u8]) -> Result<(), StateError> {
+    let mut file = Writer::default();
+    file.bytes(&MAGIC);
+    file.u16(VERSION);
+    file.u32(crc);
+    file.bytes(state);
+    fs::write(path, file.finish())?;
+    Ok(())
+}
+/// Reads a state and checks that it belongs to this version and the rom with `crc`
+pub fn read_file(path: &Path, crc: u32) -> Result<Vec<u8>, StateError> {
+    let file = fs::read(path)?;
+    let mut reader = Reader::new(&file);
+    if reader.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(StateError::NotAState);
+    }
+    let version = reader.u16()?;
+    if version != VERSION {
+        return Err(StateError::Version(version));
+    }
+    let saved_crc = reader.u32()?;
+    if saved_crc != crc {
+        return Err(StateError::OtherRom(saved_crc));
+    }
+    Ok(reader.0.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bus::Bus,
+        cpu::{Cpu, CpuMode, V16},
+        interrupt::{Interrupt, IE, IF},
+    };
+
+    use super::*;
+
+    /// A state file restores the machine, broken or foreign states leave it alone
+    #[test]
+    fn save_state() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("gba-selftest-{}.state", std::process::id()));
+        let bus = Bus::default();
+        let ram = bus.ram();
+        let mut cpu = Cpu::new(bus);
+        cpu.w(V16::SP, 0xDFF0);
+        cpu.write_mem16_raw(0xC123, 0x42);
+        // a halt with a pending interrupt and IME off sets up the halt bug
+        cpu.write_mem16_raw(IE, Interrupt::Timer.bit());
+        cpu.write_mem16_raw(IF, Interrupt::Timer.bit());
+        cpu.write_mem16_raw(0xC000, 0x76);
+        cpu.w(V16::PC, 0xC000);
+        let locked = CpuMode::Locked { op: 0xD3 };
+        let result = (|| {
+            cpu.execute_next().map_err(|e| e.to_string())?;
+            cpu.set_mode(locked.clone());
+            write_file(&path, 0x1234, &cpu.snapshot()).map_err(|e| e.to_string())?;
+            cpu.w(V16::SP, 0xFFFE);
+            cpu.write_mem16_raw(0xC123, 0x00);
+            // a nop uses up the halt bug
+            cpu.write_mem16_raw(0xC000, 0x00);
+            cpu.set_mode(CpuMode::Run);
+            cpu.execute_next().map_err(|e| e.to_string())?;
+            if read_file(&path, 0x4321).is_ok() {
+                return Err("the state of another rom was accepted".to_string());
+            }
+            let snapshot = read_file(&path, 0x1234).map_err(|e| e.to_string())?;
+            if cpu.restore(&snapshot[..snapshot.len() / 2]).is_ok() {
+                return Err("a truncated state was accepted".to_string());
+            }
+            if cpu.r(V16::SP) != 0xFFFE {
+                return Err("a truncated state changed the registers".to_string());
+            }
+            cpu.restore(&snapshot).map_err(|e| e.to_string())?;
+            let (sp, byte) = (cpu.r(V16::SP), ram.read().unwrap()[0xC123]);
+            if sp != 0xDFF0 || byte != 0x42 {
+                return Err(format!(
+                    "SP is {sp:#06x} and 0xC123 {byte:#04x} after loading"
+                ));
+            }
+            if *cpu.mode() != locked || !cpu.halt_bug() {
+                return Err(format!(
+                    "{:?} with the halt bug {} after loading",
+                    cpu.mode(),
+                    cpu.halt_bug()
+                ));
+            }
+            Ok(())
+        })();
+        let _ = fs::remove_file(&path);
+        result
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU8, Ordering},
+    Arc,
+};
+
+use crate::frontend::Buttons;
+
+/// Core state which the cpu thread publishes and the gui reads every frame,
+/// without locks so the core never waits for the gui
+#[derive(Clone, Debug)]
+pub struct Status(Arc<StatusInner>);
+#[derive(Debug)]
+struct StatusInner {
+    /// f32 bits
+    fps: AtomicU32,
+    pc: AtomicU16,
+    rom_bank: AtomicU16,
+    ly: AtomicU8,
+    /// Cycles into the line, published when the debugger pauses
+    dot: AtomicU16,
+    /// Audio buffer fill level in percent
+    audio_fill: AtomicU8,
+    sram_dirty: AtomicBool,
+    /// Set once a cartridge is inserted, without one there is nothing to show
+    rom_loaded: AtomicBool,
+    /// CRC32 of the inserted rom, identifies the game across renames
+    rom_crc: AtomicU32,
+    /// Emulated frames since power on
+    frame: AtomicU32,
+    /// BC, DE, HL, AF, PC and SP at the end of the last frame
+    registers: [AtomicU16; 6],
+    /// Interrupt master enable, with the registers
+    ime: AtomicBool,
+    /// Splits a script asked for, the speedrun timer takes them
+    splits: AtomicU32,
+    /// The buttons the game saw at the end of the last frame, a playing movie's included
+    buttons: AtomicU8,
+}
+impl Status {
+    pub fn fps(&self) -> f32 {
+        f32::from_bits(self.0.fps.load(Ordering::Relaxed))
+    }
+    pub fn set_fps(&self, fps: f32) {
+        self.0.fps.store(fps.to_bits(), Ordering::Relaxed);
+    }
+    pub fn pc(&self) -> u16 {
+        self.0.pc.load(Ordering::Relaxed)
+    }
+    pub fn set_pc(&self, pc: u16) {
+        self.0.pc.store(pc, Ordering::Relaxed);
+    }
+    pub fn rom_bank(&self) -> u16 {
+        self.0.rom_bank.load(Ordering::Relaxed)
+    }
+    pub fn set_rom_bank(&self, bank: u16) {
+        self.0.rom_bank.store(bank, Ordering::Relaxed);
+    }
+    pub fn ly(&self) -> u8 {
+        self.0.ly.load(Ordering::Relaxed)
+    }
+    pub fn set_ly(&self, ly: u8) {
+        self.0.ly.store(ly, Ordering::Relaxed);
+    }
+    pub fn dot(&self) -> u16 {
+        self.0.dot.load(Ordering::Relaxed)
+    }
+    pub fn set_dot(&self, dot: u16) {
+        self.0.dot.store(dot, Ordering::Relaxed);
+    }
+    pub fn audio_fill(&self) -> u8 {
+        self.0.audio_fill.load(Ordering::Relaxed)
+    }
+    pub fn set_audio_fill(&self, percent: u8) {
+        self.0.audio_fill.store(percent.min(100), Ordering::Relaxed);
+    }
+    pub fn frame(&self) -> u32 {
+        self.0.frame.load(Ordering::Relaxed)
+    }
+    pub fn next_frame(&self) {
+        self.0.frame.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Goes back to the frame a movie starts at
+    pub fn set_frame(&self, frame: u32) {
+        self.0.frame.store(frame, Ordering::Relaxed);
+    }
+    /// BC, DE, HL, AF, PC and SP like the cpu stores them
+    pub fn registers(&self) -> [u16; 6] {
+        let registers = &self.0.registers;
+        [0, 1, 2, 3, 4, 5].map(|i| registers[i].load(Ordering::Relaxed))
+    }
+    pub fn set_registers(&self, values: [u16; 6]) {
+        for (register, value) in self.0.registers.iter().zip(values) {
+            register.store(value, Ordering::Relaxed);
+        }
+    }
+    pub fn ime(&self) -> bool {
+        self.0.ime.load(Ordering::Relaxed)
+    }
+    pub fn set_ime(&self, ime: bool) {
+        self.0.ime.store(ime, Ordering::Relaxed);
+    }
+    pub fn rom_loaded(&self) -> bool {
+        self.0.rom_loaded.load(Ordering::Relaxed)
+    }
+    pub fn set_rom_loaded(&self, loaded: bool) {
+        self.0.rom_loaded.store(loaded, Ordering::Relaxed);
+    }
+    pub fn rom_crc(&self) -> u32 {
+        self.0.rom_crc.load(Ordering::Relaxed)
+    }
+    pub fn set_rom_crc(&self, crc: u32) {
+        self.0.rom_crc.store(crc, Ordering::Relaxed);
+    }
+    pub fn sram_dirty(&self) -> bool {
+        self.0.sram_dirty.load(Ordering::Relaxed)
+    }
+    pub fn mark_sram_dirty(&self) {
+        self.0.sram_dirty.store(true, Ordering::Relaxed);
+    }
+    /// Returns whether the save ram changed and resets the flag, for whoever writes the save file
+    pub fn take_sram_dirty(&self) -> bool {
+        self.0.sram_dirty.swap(false, Ordering::Relaxed)
+    }
+    pub fn buttons(&self) -> Buttons {
+        Buttons(self.0.buttons.load(Ordering::Relaxed))
+    }
+    pub fn set_buttons(&self, buttons: Buttons) {
+        self.0.buttons.store(buttons.0, Ordering::Relaxed);
+    }
+    pub fn request_split(&self) {
+        self.0.splits.fetch_add(1, Ordering::Relaxed);
+    }
+    /// The splits requested since the last call
+    pub fn take_splits(&self) -> u32 {
+        self.0.splits.swap(0, Ordering::Relaxed)
+    }
+}
+impl Default for Status {
+    fn default() -> Self {
+        Status(Arc::new(StatusInner {
+            fps: AtomicU32::new(0f32.to_bits()),
+            pc: AtomicU16::new(0),
+            rom_bank: AtomicU16::new(1),
+            ly: AtomicU8::new(0),
+            dot: AtomicU16::new(0),
+            audio_fill: AtomicU8::new(0),
+            sram_dirty: AtomicBool::new(false),
+            rom_loaded: AtomicBool::new(false),
+            rom_crc: AtomicU32::new(0),
+            frame: AtomicU32::new(0),
+            registers: Default::default(),
+            ime: AtomicBool::new(false),
+            splits: AtomicU32::new(0),
+            buttons: AtomicU8::new(0),
+        }))
+    }
+}
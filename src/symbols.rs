@@ -0,0 +1,216 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use crate::{banked::BankedAddr, debugger::parse_address};
+
+/// The labels of a `.sym` file, like `rgblink -n` and wla-dx write them:
+/// `01:4000 Name` per line, `;` starts a comment. Of the sections of wla-dx
+/// only `[labels]` has addresses.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    by_addr: BTreeMap<BankedAddr, String>,
+    by_name: HashMap<String, BankedAddr>,
+}
+impl SymbolTable {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(SymbolTable::parse(&fs::read_to_string(path)?))
+    }
+    /// The lines which are no label are skipped
+    pub fn parse(content: &str) -> Self {
+        let mut table = SymbolTable::default();
+        let mut labels = true;
+        for line in content.lines() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.starts_with('[') {
+                labels = line.eq_ignore_ascii_case("[labels]");
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(addr), Some(name)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            if !labels || !addr.contains(':') {
+                continue;
+            }
+            if let Some(addr) = BankedAddr::parse(addr) {
+                table.insert(addr, name);
+            }
+        }
+        table
+    }
+    /// The first label of an address is the one shown for it
+    fn insert(&mut self, addr: BankedAddr, name: &str) {
+        self.by_addr.entry(addr).or_insert_with(|| name.to_string());
+        self.by_name.insert(name.to_string(), addr);
+    }
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+    /// The label at `addr`
+    pub fn exact(&self, addr: BankedAddr) -> Option<&str> {
+        self.by_addr.get(&addr).map(String::as_str)
+    }
+    /// `Name` or `Name+$12` of the closest label at or in front of `addr`,
+    /// in the same bank and the same memory region
+    pub fn label(&self, addr: BankedAddr) -> Option<String> {
+        let (at, name) = self.by_addr.range(..=addr).next_back()?;
+        if at.bank != addr.bank || region(at.addr) != region(addr.addr) {
+            return None;
+        }
+        Some(match addr.addr - at.addr {
+            0 => name.clone(),
+            offset => format!("{name}+${offset:X}"),
+        })
+    }
+    pub fn lookup(&self, name: &str) -> Option<BankedAddr> {
+        self.by_name.get(name).copied()
+    }
+    /// Replaces the addresses like `$4000` in a disassembled instruction
+    /// which have a label, the ones in the switchable bank with `rom_bank`
+    pub fn annotate(&self, text: &str, rom_bank: u16) -> String {
+        let mut annotated = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            annotated.push_str(&rest[..start]);
+            let hex = rest[start + 1..]
+                .get(..4)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+            let label = hex
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                .and_then(|addr| self.exact(BankedAddr::resolve(addr, rom_bank)));
+            match label {
+                Some(label) => {
+                    annotated.push_str(label);
+                    rest = &rest[start + 5..];
+                }
+                None => {
+                    annotated.push('$');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        annotated.push_str(rest);
+        annotated
+    }
+}
+
+/// Where labels of one region do not continue into the next
+fn region(addr: u16) -> u16 {
+    match addr {
+        0x0000..=0x3FFF => 0,
+        0x4000..=0x7FFF => 1,
+        0x8000..=0x9FFF => 2,
+        0xA000..=0xBFFF => 3,
+        0xC000..=0xDFFF => 4,
+        0xE000..=0xFDFF => 5,
+        0xFE00..=0xFEFF => 6,
+        0xFF00..=0xFF7F => 7,
+        _ => 8,
+    }
+}
+
+/// The `.sym` file of `rom`, next to it with the same name
+pub fn path_for(rom: &Path) -> PathBuf {
+    rom.with_extension("sym")
+}
+
+/// The labels of the running game for the debugger, the disassembly and the trace.
+/// Shared between the gui and the cpu thread.
+#[derive(Clone, Debug, Default)]
+pub struct Symbols(Arc<RwLock<SymbolTable>>);
+impl Symbols {
+    /// Loads the `.sym` file next to `rom`, without one the labels are cleared
+    pub fn load_for(&self, rom: &Path) {
+        let path = path_for(rom);
+        let table = match SymbolTable::load(&path) {
+            Ok(table) => {
+                log::info!("{} labels from {}", table.len(), path.display());
+                table
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => SymbolTable::default(),
+            Err(e) => {
+                log::warn!("could not read the labels of {}: {e}", path.display());
+                SymbolTable::default()
+            }
+        };
+        self.set(table);
+    }
+    pub fn set(&self, table: SymbolTable) {
+        *self.0.write().unwrap() = table;
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.read().unwrap().is_empty()
+    }
+    pub fn exact(&self, addr: BankedAddr) -> Option<String> {
+        self.0.read().unwrap().exact(addr).map(str::to_string)
+    }
+    pub fn label(&self, addr: BankedAddr) -> Option<String> {
+        self.0.read().unwrap().label(addr)
+    }
+    pub fn lookup(&self, name: &str) -> Option<BankedAddr> {
+        self.0.read().unwrap().lookup(name)
+    }
+    pub fn annotate(&self, text: &str, rom_bank: u16) -> String {
+        self.0.read().unwrap().annotate(text, rom_bank)
+    }
+    /// An address like `parse_address` takes it, or the name of a label
+    pub fn parse_address(&self, s: &str) -> Result<u16, String> {
+        parse_address(s).or_else(|_| {
+            self.lookup(s)
+                .map(|addr| addr.addr)
+                .ok_or(format!("not an address or label: {s}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banked::BankedAddr;
+
+    use super::*;
+
+    #[test]
+    fn symbol_labels() -> Result<(), String> {
+        let table = SymbolTable::parse(
+            "; File generated by rgblink\n\
+             00:0150 Main\n\
+             00:0153 Main.loop\n\
+             01:4000 BankedThing ; a comment\n\
+             00:c000 wBuffer\n\
+             [definitions]\n\
+             00000010 SOMETHING\n",
+        );
+        let labels = [
+            (BankedAddr::new(0, 0x0155), Some("Main.loop+$2")),
+            (BankedAddr::new(1, 0x4010), Some("BankedThing+$10")),
+            (BankedAddr::new(2, 0x4010), None),
+            (BankedAddr::new(0, 0xC000), Some("wBuffer")),
+            // io registers are no part of the buffer in wram
+            (BankedAddr::new(0, 0xFF40), None),
+        ];
+        for (addr, expected) in labels {
+            let label = table.label(addr);
+            if label.as_deref() != expected {
+                return Err(format!("{addr} is labeled {label:?}"));
+            }
+        }
+        if table.lookup("Main") != Some(BankedAddr::new(0, 0x0150))
+            || table.lookup("SOMETHING").is_some()
+        {
+            return Err("looking up labels by name".to_string());
+        }
+        let annotated = table.annotate("CALL NZ,$4000", 1);
+        if annotated != "CALL NZ,BankedThing" || table.annotate("LD A,[$C001]", 1) != "LD A,[$C001]"
+        {
+            return Err(format!("annotated the call as {annotated}"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+// Helpers shared by the unit tests, a cartridge with a few instructions and a
+// way to run it until the cpu stops.
+
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    bus::{Bus, BOOT_ROM_DISABLE},
+    cartridge::{Cartridge, ROM_BANK_SIZE},
+    cpu::{Cpu, CpuMode, V16},
+    ram::Ram,
+};
+
+/// Where execution starts after the boot rom
+pub const ENTRY: u16 = 0x0100;
+/// Stops a micro rom which never halts
+const MAX_STEPS: usize = 1000;
+
+/// A two bank rom with `program` at the entry point
+pub fn micro_rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 2 * ROM_BANK_SIZE];
+    rom[ENTRY as usize..ENTRY as usize + program.len()].copy_from_slice(program);
+    rom
+}
+/// Runs the rom from the entry point until the cpu stops, `setup` prepares the bus before
+pub fn run_rom(rom: Vec<u8>, setup: impl FnOnce(&mut Bus)) -> (Cpu, Arc<RwLock<Ram>>) {
+    let mut bus = Bus::default().with_cartridge(Cartridge::from_bytes(rom).unwrap());
+    // starts where the boot rom ends, with the cartridge mapped completely
+    bus.write_mem(BOOT_ROM_DISABLE, 1);
+    setup(&mut bus);
+    let ram = bus.ram();
+    let mut cpu = Cpu::new(bus);
+    cpu.w(V16::PC, ENTRY);
+    // where the boot rom leaves the stack
+    cpu.w(V16::SP, 0xFFFE);
+    for _ in 0..MAX_STEPS {
+        if *cpu.mode() != CpuMode::Run {
+            break;
+        }
+        cpu.step();
+    }
+    (cpu, ram)
+}
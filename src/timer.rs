@@ -0,0 +1,103 @@
+use crate::{
+    interrupt::{self, Interrupt},
+    ram::Ram,
+    state::{Reader, StateError, Writer},
+};
+
+/// Divider, the upper byte of the internal counter. Any write resets it to 0
+pub const DIV: u16 = 0xFF04;
+/// Timer counter, requests the timer interrupt when it overflows
+pub const TIMA: u16 = 0xFF05;
+/// Timer modulo, loaded into TIMA on overflow
+pub const TMA: u16 = 0xFF06;
+/// Timer control: bit 2 enables TIMA, bits 0-1 select its frequency
+pub const TAC: u16 = 0xFF07;
+
+/// DIV, TIMA, TMA and TAC, ticked with the cycles the cpu ran
+#[derive(Clone, Debug, Default)]
+pub struct Timer {
+    /// Increments every cycle, DIV shows its upper byte
+    counter: u16,
+}
+impl Timer {
+    pub fn save_state(&self, w: &mut Writer) {
+        w.u16(self.counter);
+    }
+    pub fn load_state(&mut self, r: &mut Reader) -> Result<(), StateError> {
+        self.counter = r.u16()?;
+        Ok(())
+    }
+    pub fn tick(&mut self, ram: &mut Ram, cycles: usize) {
+        for _ in 0..cycles {
+            let before = self.input(ram);
+            self.counter = self.counter.wrapping_add(1);
+            ram[DIV] = (self.counter >> 8) as u8;
+            if before && !self.input(ram) {
+                increment(ram);
+            }
+        }
+    }
+    /// Called after a write to DIV, which clears the whole counter
+    pub fn reset_divider(&mut self, ram: &mut Ram) {
+        // TIMA counts the falling edge of its counter bit, so resetting can tick it once
+        if self.input(ram) {
+            increment(ram);
+        }
+        self.counter = 0;
+        ram[DIV] = 0;
+    }
+    /// The counter bit selected by TAC, TIMA increments when it falls
+    fn input(&self, ram: &Ram) -> bool {
+        let control = ram[TAC];
+        // 4096, 262144, 65536 and 16384 Hz
+        let bit = [9, 3, 5, 7][control as usize & 0b11];
+        control & 0b100 != 0 && self.counter & (1 << bit) != 0
+    }
+}
+/// Overflowing TIMA reloads it from TMA and requests the interrupt
+fn increment(ram: &mut Ram) {
+    let (tima, overflow) = ram[TIMA].overflowing_add(1);
+    if overflow {
+        ram[TIMA] = ram[TMA];
+        interrupt::request(ram, Interrupt::Timer);
+    } else {
+        ram[TIMA] = tima;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bus::Bus,
+        interrupt::{Interrupt, IF},
+    };
+
+    use super::*;
+
+    /// At 262144 Hz TIMA counts every 16 cycles and reloads from TMA on overflow
+    #[test]
+    fn timer_overflow() -> Result<(), String> {
+        let mut bus = Bus::default();
+        bus.write_mem(TIMA, 0xFF);
+        bus.write_mem(TMA, 0x80);
+        bus.write_mem(TAC, 0b101);
+        bus.tick(16);
+        let tima = bus.fetch(TIMA);
+        if tima != 0x80 {
+            return Err(format!("TIMA is {tima:#04x} instead of the TMA value 0x80"));
+        }
+        if bus.fetch(IF) & Interrupt::Timer.bit() == 0 {
+            return Err("the timer interrupt was not requested".to_string());
+        }
+        bus.tick(256 - 16);
+        let div = bus.fetch(DIV);
+        if div != 1 {
+            return Err(format!("DIV is {div} after 256 cycles"));
+        }
+        bus.write_mem(DIV, 0x12);
+        if bus.fetch(DIV) != 0 {
+            return Err("writing DIV did not reset it".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// The cpu state before an instruction
+pub struct TraceState {
+    /// A, F, B, C, D, E, H and L
+    pub registers: [u8; 8],
+    pub sp: u16,
+    pub pc: u16,
+    /// The instruction and the bytes after it
+    pub pcmem: [u8; 4],
+    /// Of the pc, from the `.sym` file of the game
+    pub label: Option<String>,
+}
+impl TraceState {
+    /// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`,
+    /// the format of Gameboy Doctor. A label follows as `; Name+$3`, which Gameboy Doctor
+    /// does not understand, so traces to compare are taken without a `.sym` file.
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let [a, f, b, c, d, e, h, l] = self.registers;
+        let [m0, m1, m2, m3] = self.pcmem;
+        write!(
+            out,
+            "A:{a:02X} F:{f:02X} B:{b:02X} C:{c:02X} D:{d:02X} E:{e:02X} H:{h:02X} L:{l:02X} \
+             SP:{:04X} PC:{:04X} PCMEM:{m0:02X},{m1:02X},{m2:02X},{m3:02X}",
+            self.sp, self.pc
+        )?;
+        match &self.label {
+            Some(label) => writeln!(out, " ; {label}"),
+            None => writeln!(out),
+        }
+    }
+}
+
+/// Writes a line per instruction into a file, to diff runs against another emulator.
+/// Shared, so the gui can start and stop it while the game runs.
+#[derive(Clone, Default)]
+pub struct Trace {
+    writer: Arc<Mutex<Option<BufWriter<File>>>>,
+    /// Set while a file is open, so the cpu skips the lock otherwise
+    active: Arc<AtomicBool>,
+}
+impl Trace {
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+    /// Starts writing into `path`, replacing the file
+    pub fn start(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = self.writer.lock().unwrap();
+        if let Some(mut previous) = writer.replace(BufWriter::with_capacity(1 << 16, file)) {
+            let _ = previous.flush();
+        }
+        self.active.store(true, Ordering::Relaxed);
+        log::info!("tracing into {}", path.display());
+        Ok(())
+    }
+    /// Flushes and closes the file
+    pub fn stop(&self) {
+        self.active.store(false, Ordering::Relaxed);
+        if let Some(mut writer) = self.writer.lock().unwrap().take() {
+            if let Err(e) = writer.flush() {
+                log::warn!("could not finish the trace: {e}");
+            }
+        }
+    }
+    /// Called by the cpu before every instruction while active
+    pub fn log(&self, state: &TraceState) {
+        let mut writer = self.writer.lock().unwrap();
+        let Some(out) = writer.as_mut() else {
+            return;
+        };
+        if let Err(e) = state.write_to(out) {
+            log::warn!("stopped tracing: {e}");
+            *writer = None;
+            self.active.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bus::{Bus, BOOT_ROM_DISABLE},
+        cartridge::Cartridge,
+        cpu::{Cpu, V16},
+        testing::{micro_rom, ENTRY},
+    };
+
+    use super::*;
+
+    #[test]
+    fn instruction_trace() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("gba-selftest-{}.trace", std::process::id()));
+        let trace = Trace::default();
+        trace.start(&path).map_err(|e| e.to_string())?;
+        let cartridge =
+            Cartridge::from_bytes(micro_rom(&[0x00, 0x76])).map_err(|e| e.to_string())?;
+        let mut bus = Bus::default().with_cartridge(cartridge);
+        bus.write_mem(BOOT_ROM_DISABLE, 1);
+        let mut cpu = Cpu::new(bus).with_trace(trace.clone());
+        cpu.w(V16::PC, ENTRY);
+        cpu.w(V16::SP, 0xFFFE);
+        cpu.step();
+        cpu.step();
+        trace.stop();
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string());
+        let _ = fs::remove_file(&path);
+        let content = content?;
+        let lines: Vec<&str> = content.lines().collect();
+        let expected = [
+            "A:00 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0100 PCMEM:00,76,00,00",
+            "A:00 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0101 PCMEM:76,00,00,00",
+        ];
+        if lines != expected {
+            return Err(format!("traced {lines:?}"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::{fs, io, path::Path};
+
+use crate::serial::SerialDevice;
+
+/// Size of a party pokemon in memory
+const MON_SIZE: usize = 44;
+/// Size of a name, padded with the terminator
+const NAME_SIZE: usize = 11;
+const PARTY_SIZE: usize = 6;
+/// Trainer name, party count, species list, pokemon, trainer names and nicknames
+const BLOCK_SIZE: usize = NAME_SIZE + 1 + PARTY_SIZE + 1 + PARTY_SIZE * (MON_SIZE + 2 * NAME_SIZE);
+/// Bytes after the preamble of the patch list exchange
+const PATCH_LIST_SIZE: usize = 197;
+
+const MASTER: u8 = 0x01;
+const SLAVE: u8 = 0x02;
+const CONNECTED: u8 = 0x60;
+const TRADE_CENTER: u8 = 0xD4;
+const BREAK_LINK: u8 = 0xD6;
+const PREAMBLE: u8 = 0xFD;
+/// Marks a byte which was 0xFE before sending, the game uses 0xFE itself
+const NO_DATA: u8 = 0xFE;
+/// Selecting the first party slot, the other slots follow up to 0x65
+const SELECT_FIRST: u8 = 0x60;
+const CANCEL: u8 = 0x6F;
+const DECLINE: u8 = 0x61;
+const ACCEPT: u8 = 0x62;
+const TERMINATOR: u8 = 0x50;
+
+/// Title prefixes of the second generation games. Their trade block has another layout,
+/// supporting them is a follow-up, until then the partner refuses them.
+const GEN2_TITLES: [&str; 3] = ["POKEMON_GLD", "POKEMON_SLV", "PM_CRYSTAL"];
+
+/// Level 5 Mew with Pound, what the partner offers without a file
+const MEW: [u8; MON_SIZE] = [
+    0x15, 0x00, 25, 5, 0x00, 0x18, 0x18, 45, 0x01, 0x00, 0x00, 0x00, 0x12, 0x34, 0x00, 0x00, 135,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x00, 35, 0, 0, 0, 5, 0x00, 25, 0x00, 15, 0x00, 15, 0x00,
+    15, 0x00, 15,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    /// Waiting in the cable club for the other game
+    Connecting,
+    /// Choosing between trade center and colosseum
+    Menu,
+    Ready,
+    /// Random numbers for the battle rng, echoed back
+    Random,
+    WaitForData,
+    Data,
+    PatchList,
+    /// The player picks a pokemon, we always offer the first
+    Select,
+    Confirm,
+    Done,
+}
+
+/// Whether the fake partner speaks the link protocol of the game with the header `title`
+pub fn check_game(title: &str) -> Result<(), String> {
+    if GEN2_TITLES.iter().any(|gen2| title.starts_with(gen2)) {
+        return Err(format!(
+            "{title} is a second generation game, the trade partner only knows the first"
+        ));
+    }
+    Ok(())
+}
+
+/// A fake trade partner for the first generation games, which offers one pokemon.
+/// Answers each byte like the other game would, following the link protocol
+/// through the cable club menu into the trade center.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TradePartner {
+    stage: Stage,
+    block: Vec<u8>,
+    patch_list: Vec<u8>,
+    counter: usize,
+}
+impl TradePartner {
+    /// Offers the pokemon in the file, a party pokemon followed by its nickname
+    /// as the game stores them
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() != MON_SIZE + NAME_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {} bytes", MON_SIZE + NAME_SIZE),
+            ));
+        }
+        let (mon, nickname) = bytes.split_at(MON_SIZE);
+        Ok(TradePartner::new(mon, nickname))
+    }
+    fn new(mon: &[u8], nickname: &[u8]) -> Self {
+        let trainer = encode("GBA");
+        let mut block = Vec::with_capacity(BLOCK_SIZE);
+        block.extend_from_slice(&trainer);
+        block.push(1);
+        block.extend_from_slice(&[mon[0], 0xFF, 0, 0, 0, 0, 0]);
+        let mut mons = vec![0; PARTY_SIZE * MON_SIZE];
+        mons[..MON_SIZE].copy_from_slice(mon);
+        // 0xFE can't be sent, the patch list tells the game where to put it back
+        let mut patches = [Vec::new(), Vec::new()];
+        for (i, byte) in mons.iter_mut().enumerate() {
+            if *byte == NO_DATA {
+                *byte = 0xFF;
+                patches[i / 0xFC].push((i % 0xFC + 1) as u8);
+            }
+        }
+        block.extend_from_slice(&mons);
+        block.extend_from_slice(&trainer);
+        block.extend_from_slice(&[TERMINATOR; NAME_SIZE * (PARTY_SIZE - 1)]);
+        block.extend_from_slice(nickname);
+        block.extend_from_slice(&[TERMINATOR; NAME_SIZE * (PARTY_SIZE - 1)]);
+        let mut patch_list = Vec::with_capacity(PATCH_LIST_SIZE);
+        for part in patches {
+            patch_list.extend(part);
+            patch_list.push(0xFF);
+        }
+        patch_list.resize(PATCH_LIST_SIZE, 0);
+        TradePartner {
+            stage: Stage::Connecting,
+            block,
+            patch_list,
+            counter: 0,
+        }
+    }
+}
+impl SerialDevice for TradePartner {
+    fn name(&self) -> &str {
+        "trade"
+    }
+    /// The byte the other game sends in exchange for `sent`
+    fn exchange(&mut self, sent: u8) -> u8 {
+        let (next, received) = match self.stage {
+            Stage::Connecting => match sent {
+                MASTER => (Stage::Connecting, SLAVE),
+                CONNECTED => (Stage::Menu, CONNECTED),
+                _ => (Stage::Connecting, sent),
+            },
+            Stage::Menu => match sent {
+                TRADE_CENTER => (Stage::Ready, sent),
+                BREAK_LINK => (Stage::Connecting, sent),
+                _ => (Stage::Menu, sent),
+            },
+            Stage::Ready if sent == PREAMBLE => {
+                self.counter = 0;
+                (Stage::Random, PREAMBLE)
+            }
+            Stage::Ready => (Stage::Ready, sent),
+            Stage::Random if sent == PREAMBLE && self.counter == 0 => (Stage::Random, PREAMBLE),
+            Stage::Random if sent == PREAMBLE => (Stage::WaitForData, PREAMBLE),
+            Stage::Random => {
+                self.counter += 1;
+                (Stage::Random, sent)
+            }
+            Stage::WaitForData if sent == PREAMBLE => (Stage::WaitForData, PREAMBLE),
+            Stage::WaitForData | Stage::Data => {
+                if self.stage == Stage::WaitForData {
+                    self.counter = 0;
+                }
+                let byte = self.block[self.counter];
+                self.counter += 1;
+                if self.counter == self.block.len() {
+                    self.counter = 0;
+                    (Stage::PatchList, byte)
+                } else {
+                    (Stage::Data, byte)
+                }
+            }
+            Stage::PatchList if sent == PREAMBLE => {
+                self.counter = 0;
+                (Stage::PatchList, PREAMBLE)
+            }
+            Stage::PatchList => {
+                let byte = self.patch_list[self.counter];
+                self.counter += 1;
+                if self.counter == PATCH_LIST_SIZE {
+                    (Stage::Select, byte)
+                } else {
+                    (Stage::PatchList, byte)
+                }
+            }
+            Stage::Select => match sent {
+                CANCEL => (Stage::Done, CANCEL),
+                SELECT_FIRST..=0x65 => (Stage::Confirm, SELECT_FIRST),
+                _ => (Stage::Select, 0x00),
+            },
+            Stage::Confirm => match sent {
+                DECLINE => (Stage::Select, DECLINE),
+                ACCEPT => {
+                    log::info!("traded with the fake partner");
+                    (Stage::Done, sent)
+                }
+                _ => (Stage::Confirm, sent),
+            },
+            Stage::Done if sent == 0x00 => (Stage::Ready, 0x00),
+            Stage::Done => (Stage::Done, sent),
+        };
+        if next != self.stage {
+            log::debug!("trade {:?} -> {next:?}", self.stage);
+        }
+        self.stage = next;
+        received
+    }
+    /// The partner clocks the transfers when the game waits as the slave
+    fn drives_clock(&self) -> bool {
+        true
+    }
+}
+impl Default for TradePartner {
+    fn default() -> Self {
+        TradePartner::new(&MEW, &encode("MEW"))
+    }
+}
+/// Converts upper case ascii into the character set of the games
+fn encode(name: &str) -> [u8; NAME_SIZE] {
+    let mut encoded = [TERMINATOR; NAME_SIZE];
+    for (byte, c) in encoded.iter_mut().zip(name.bytes().take(NAME_SIZE - 1)) {
+        *byte = match c {
+            b'A'..=b'Z' => 0x80 + c - b'A',
+            b'0'..=b'9' => 0xF6 + c - b'0',
+            _ => 0x7F,
+        };
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_generation_is_refused() {
+        assert!(check_game("POKEMON RED").is_ok());
+        assert!(check_game("POKEMON_GLDAAUE").is_err());
+        assert!(check_game("PM_CRYSTAL").is_err());
+    }
+}
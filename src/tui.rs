@@ -0,0 +1,130 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    sync::mpsc::Receiver,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, terminal,
+};
+
+use gba::{
+    cgb,
+    frontend::{Buttons, DrawSignal, SharedButtons},
+    speed::Speed,
+};
+
+const WIDTH: usize = 160;
+const HEIGHT: usize = 144;
+const FRAME_TIME: Duration = Duration::from_millis(33);
+/// Terminals only report key presses, so a button counts as held for a while after each press
+const HOLD_TIME: Duration = Duration::from_millis(150);
+const PALETTE: [[u8; 3]; 4] = [
+    [0xe0, 0xe0, 0xe0],
+    [0xa0, 0xa0, 0xa0],
+    [0x50, 0x50, 0x50],
+    [0x10, 0x10, 0x10],
+];
+const KEYMAP: [(KeyCode, u8); 8] = [
+    (KeyCode::Right, Buttons::RIGHT),
+    (KeyCode::Left, Buttons::LEFT),
+    (KeyCode::Up, Buttons::UP),
+    (KeyCode::Down, Buttons::DOWN),
+    (KeyCode::Char('x'), Buttons::A),
+    (KeyCode::Char('z'), Buttons::B),
+    (KeyCode::Backspace, Buttons::SELECT),
+    (KeyCode::Enter, Buttons::START),
+];
+
+/// Renders the screen into the terminal with two pixels per character, using `▀`
+/// with the upper pixel as foreground and the lower one as background color.
+/// Needs a true color terminal with at least 160x72 cells. Escape or q quits, p pauses.
+pub fn run(receiver: Receiver<DrawSignal>, input: SharedButtons, speed: Speed) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = run_loop(&mut stdout, receiver, input, speed);
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+fn run_loop(
+    stdout: &mut io::Stdout,
+    receiver: Receiver<DrawSignal>,
+    input: SharedButtons,
+    speed: Speed,
+) -> io::Result<()> {
+    let mut screen = [PALETTE[0]; WIDTH * HEIGHT];
+    let mut pressed_at: [Option<Instant>; 8] = [None; 8];
+    loop {
+        let frame_start = Instant::now();
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                KeyCode::Char('p') => speed.set_paused(!speed.is_paused()),
+                code => {
+                    if let Some(i) = KEYMAP.iter().position(|(key, _)| *key == code) {
+                        pressed_at[i] = Some(frame_start);
+                    }
+                }
+            }
+        }
+        let held = KEYMAP
+            .iter()
+            .zip(pressed_at)
+            .filter(|(_, at)| at.map_or(false, |at| at.elapsed() < HOLD_TIME))
+            .fold(0, |held, ((_, button), _)| held | button);
+        input.set(Buttons(held));
+
+        for signal in receiver.try_iter() {
+            match signal {
+                DrawSignal::Frame(frame) => {
+                    for (rgb, shade) in screen.iter_mut().zip(frame.iter()) {
+                        *rgb = PALETTE[*shade as usize % 4];
+                    }
+                }
+                DrawSignal::ColorFrame(frame) => {
+                    for (rgb, color) in screen.iter_mut().zip(frame.iter()) {
+                        *rgb = cgb::rgb(*color);
+                    }
+                }
+                DrawSignal::CpuMode(mode, pc) => log::info!("cpu {mode:?} at {pc:#06x}"),
+            }
+        }
+        stdout.write_all(render(&screen).as_bytes())?;
+        stdout.flush()?;
+        if let Some(rest) = FRAME_TIME.checked_sub(frame_start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+}
+/// Escape sequences which draw the whole screen from the top left corner
+fn render(screen: &[[u8; 3]; WIDTH * HEIGHT]) -> String {
+    let mut out = String::from("\x1b[H");
+    for y in (0..HEIGHT).step_by(2) {
+        // colors are only written when they change
+        let mut last = None;
+        for x in 0..WIDTH {
+            let colors = (screen[y * WIDTH + x], screen[(y + 1) * WIDTH + x]);
+            if last != Some(colors) {
+                let [r, g, b] = colors.0;
+                let [br, bg, bb] = colors.1;
+                let _ = write!(out, "\x1b[38;2;{r};{g};{b}m\x1b[48;2;{br};{bg};{bb}m");
+                last = Some(colors);
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\r\n");
+    }
+    out
+}
@@ -0,0 +1,105 @@
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use crate::ram::Ram;
+
+pub const TILE_DATA: u16 = 0x8000;
+pub const TILE_COUNT: usize = 384;
+pub const TILES_PER_ROW: usize = 16;
+pub const BG_MAPS: [u16; 2] = [0x9800, 0x9C00];
+pub const LCDC: u16 = 0xFF40;
+pub const BGP: u16 = 0xFF47;
+
+/// An image of color indices 0..=3
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+impl IndexedImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        IndexedImage {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+    /// Draws the 8x8 tile starting at `addr` with its top left corner at `x`, `y`
+    fn draw_tile(&mut self, ram: &Ram, addr: u16, x: usize, y: usize, palette: u8) {
+        for row in 0..8 {
+            let low = ram[addr + row as u16 * 2];
+            let high = ram[addr + row as u16 * 2 + 1];
+            for col in 0..8 {
+                let index = pixel_index(low, high, col as u8);
+                let color = (palette >> (index * 2)) & 0b11;
+                self.pixels[(y + row) * self.width + x + col] = color;
+            }
+        }
+    }
+    pub fn save_png(&self, path: &Path, palette: &[[u8; 3]; 4]) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let rgb: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|index| palette[*index as usize])
+            .collect();
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&rgb))
+            .map_err(io::Error::other)
+    }
+}
+
+/// Color index of pixel `x` in a tile row made of the bytes `low` and `high`,
+/// the first byte holds bit 0 of every pixel and the second bit 1
+pub fn pixel_index(low: u8, high: u8, x: u8) -> u8 {
+    let bit = 7 - x;
+    (((high >> bit) & 1) << 1) | ((low >> bit) & 1)
+}
+/// All tiles in VRAM, 16 per row, with the raw color indices
+pub fn tile_sheet(ram: &Ram) -> IndexedImage {
+    let rows = TILE_COUNT / TILES_PER_ROW;
+    let mut image = IndexedImage::new(TILES_PER_ROW * 8, rows * 8);
+    for tile in 0..TILE_COUNT {
+        let addr = TILE_DATA + tile as u16 * 16;
+        let (x, y) = (tile % TILES_PER_ROW * 8, tile / TILES_PER_ROW * 8);
+        image.draw_tile(ram, addr, x, y, 0b11_10_01_00);
+    }
+    image
+}
+/// The 256 tiles a tile number can select, in the order of the numbers. `signed` numbers
+/// from 0x8800 like LCDC bit 4 clear, unsigned ones from 0x8000 otherwise.
+pub fn numbered_tiles(ram: &Ram, signed: bool) -> IndexedImage {
+    let lcdc = if signed { 0 } else { 0x10 };
+    let mut image = IndexedImage::new(TILES_PER_ROW * 8, 256 / TILES_PER_ROW * 8);
+    for number in 0..=255 {
+        let addr = tile_addr(lcdc, number);
+        let (x, y) = (
+            number as usize % TILES_PER_ROW * 8,
+            number as usize / TILES_PER_ROW * 8,
+        );
+        image.draw_tile(ram, addr, x, y, 0b11_10_01_00);
+    }
+    image
+}
+/// Where background tile `number` starts.
+/// LCDC bit 4 selects unsigned tile numbers from 0x8000 or signed ones from 0x9000
+pub fn tile_addr(lcdc: u8, number: u8) -> u16 {
+    if lcdc & 0x10 != 0 {
+        TILE_DATA + number as u16 * 16
+    } else {
+        (0x9000 + number as i8 as i32 * 16) as u16
+    }
+}
+/// One of the two 256x256 background maps, colored through BGP like on screen
+pub fn bg_map(ram: &Ram, map: usize) -> IndexedImage {
+    let mut image = IndexedImage::new(256, 256);
+    for i in 0..32 * 32 {
+        let number = ram[BG_MAPS[map] + i as u16];
+        let addr = tile_addr(ram[LCDC], number);
+        image.draw_tile(ram, addr, i % 32 * 8, i / 32 * 8, ram[BGP]);
+    }
+    image
+}
@@ -0,0 +1,54 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::cartridge::Cartridge;
+
+/// Notices when the rom file is rebuilt, to reload it without restarting the emulator
+pub struct RomWatch {
+    path: PathBuf,
+    /// Applied instead of the one next to the rom
+    patch: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+impl RomWatch {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified(&path);
+        RomWatch {
+            path,
+            patch: None,
+            modified,
+        }
+    }
+    pub fn with_patch(mut self, patch: Option<PathBuf>) -> Self {
+        self.patch = patch;
+        self
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// The reloaded cartridge if the file changed since the last call
+    pub fn poll(&mut self) -> Option<Cartridge> {
+        let modified = modified(&self.path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        // a half written file fails to load, the write finishing changes the time again
+        self.modified = Some(modified);
+        match Cartridge::load_patched(&self.path, self.patch.as_deref()) {
+            Ok(cartridge) => {
+                log::info!("{} changed, reloading", self.path.display());
+                Some(cartridge)
+            }
+            Err(e) => {
+                log::warn!("could not reload {}: {e}", self.path.display());
+                None
+            }
+        }
+    }
+}
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,199 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{
+    audio_out::{self, Speaker},
+    game_window::GameWindow,
+};
+use eframe::{
+    egui::{self, Key},
+    epaint::Color32,
+};
+use gba::{
+    bus::Bus,
+    cartridge::Cartridge,
+    cpu::Cpu,
+    frontend::{Buttons, DrawSignal, SharedButtons},
+    status::Status,
+};
+
+/// The canvas of index.html the emulator draws into
+const CANVAS_ID: &str = "gba_canvas";
+/// One frame of the emulated display in seconds, which runs at about 59.7Hz
+const FRAME_TIME: f64 = 0.016_743;
+/// Frames caught up at most, the browser stops drawing tabs in the background
+const MAX_BEHIND: f64 = 4. * FRAME_TIME;
+/// Like the default keymap of the native gui
+const KEYMAP: [(Key, u8); 8] = [
+    (Key::ArrowRight, Buttons::RIGHT),
+    (Key::ArrowLeft, Buttons::LEFT),
+    (Key::ArrowUp, Buttons::UP),
+    (Key::ArrowDown, Buttons::DOWN),
+    (Key::X, Buttons::A),
+    (Key::Z, Buttons::B),
+    (Key::Backspace, Buttons::SELECT),
+    (Key::Enter, Buttons::START),
+];
+
+/// Starts the emulator in the canvas of the page
+pub fn start() {
+    wasm_bindgen_futures::spawn_local(async {
+        let result = eframe::start_web(
+            CANVAS_ID,
+            eframe::WebOptions::default(),
+            Box::new(|cc| Box::new(WebApp::new(cc))),
+        )
+        .await;
+        if let Err(e) = result {
+            log::error!("could not start in the canvas: {e:?}");
+        }
+    });
+}
+
+/// The emulator in the browser. There are no threads, the cpu runs the frames
+/// which are due whenever the browser draws the page.
+struct WebApp {
+    cpu: Option<Cpu>,
+    game_window: GameWindow,
+    signal_sender: Sender<DrawSignal>,
+    signal_receiver: Receiver<DrawSignal>,
+    /// Roms from the file dialog, which answers later
+    rom_sender: Sender<Vec<u8>>,
+    rom_receiver: Receiver<Vec<u8>>,
+    input: SharedButtons,
+    status: Status,
+    /// The browser only plays audio started by a click, so it is opened with the first one
+    audio: Option<(cpal::Stream, Speaker)>,
+    /// Emulated time the cpu is behind the page, in seconds
+    behind: f64,
+    /// Of the last page frame
+    last_time: Option<f64>,
+    /// Why the last rom did not load
+    error: Option<String>,
+}
+impl WebApp {
+    fn new(cc: &eframe::CreationContext) -> Self {
+        let (signal_sender, signal_receiver) = mpsc::channel();
+        let (rom_sender, rom_receiver) = mpsc::channel();
+        let mut game_window = GameWindow::default();
+        game_window.init_texture(&cc.egui_ctx);
+        WebApp {
+            cpu: None,
+            game_window,
+            signal_sender,
+            signal_receiver,
+            rom_sender,
+            rom_receiver,
+            input: SharedButtons::default(),
+            status: Status::default(),
+            audio: None,
+            behind: 0.,
+            last_time: None,
+            error: None,
+        }
+    }
+    /// Opens the file dialog of the browser, the rom arrives through `rom_receiver`
+    fn pick_rom(&mut self) {
+        if self.audio.is_none() {
+            match audio_out::open(self.status.clone()) {
+                Ok(audio) => self.audio = Some(audio),
+                Err(e) => log::warn!("playing without sound: {e}"),
+            }
+        }
+        let sender = self.rom_sender.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let file = rfd::AsyncFileDialog::new()
+                .add_filter("Game Boy", &["gb", "gbc"])
+                .pick_file()
+                .await;
+            if let Some(file) = file {
+                let _ = sender.send(file.read().await);
+            }
+        });
+    }
+    /// Powers on with the rom, without a boot rom
+    fn load(&mut self, rom: Vec<u8>) {
+        let cartridge = match Cartridge::from_bytes(rom) {
+            Ok(cartridge) => cartridge,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
+        let mut bus = Bus::default()
+            .with_status(self.status.clone())
+            .with_video(self.signal_sender.clone())
+            .with_input(self.input.clone())
+            .with_cartridge(cartridge);
+        if let Some((_, speaker)) = &self.audio {
+            bus = bus.with_audio_out(speaker.clone());
+        }
+        let mut cpu = Cpu::new(bus);
+        cpu.skip_boot_rom();
+        self.cpu = Some(cpu);
+        self.behind = 0.;
+        self.error = None;
+    }
+    /// Runs the frames which are due since the last page frame
+    fn step(&mut self, time: f64) {
+        let elapsed = self.last_time.map_or(0., |last| time - last);
+        self.last_time = Some(time);
+        let Some(cpu) = &mut self.cpu else {
+            return;
+        };
+        self.behind = (self.behind + elapsed).min(MAX_BEHIND);
+        while self.behind >= FRAME_TIME {
+            cpu.step_frame();
+            self.behind -= FRAME_TIME;
+        }
+    }
+}
+impl eframe::App for WebApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let (time, held, dropped) = {
+            let input = ctx.input();
+            let held = KEYMAP
+                .iter()
+                .filter(|(key, _)| input.key_down(*key))
+                .fold(0, |held, (_, button)| held | button);
+            // in the browser the content of a dropped file comes along instead of a path
+            let dropped: Vec<_> = input
+                .raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.bytes.clone())
+                .collect();
+            (input.time, held, dropped)
+        };
+        for rom in dropped {
+            self.load(rom.to_vec());
+        }
+        while let Ok(rom) = self.rom_receiver.try_recv() {
+            self.load(rom);
+        }
+        self.input.set(Buttons(held));
+        self.step(time);
+        for signal in self.signal_receiver.try_iter() {
+            match signal {
+                DrawSignal::Frame(frame) => self.game_window.set_frame(frame),
+                DrawSignal::ColorFrame(frame) => self.game_window.set_color_frame(frame),
+                DrawSignal::CpuMode(mode, pc) => log::info!("cpu {mode:?} at {pc:#06x}"),
+            }
+        }
+        self.game_window.update_texture(ctx);
+        egui::TopBottomPanel::top("web_toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Open rom").clicked() {
+                    self.pick_rom();
+                }
+                ui.weak("or drop a .gb or .gbc file onto the page");
+                if let Some(e) = &self.error {
+                    ui.colored_label(Color32::RED, e);
+                }
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.game_window.view_fitted(ui);
+        });
+        ctx.request_repaint();
+    }
+}